@@ -0,0 +1,2414 @@
+// src-tauri/src/commands/backtest.rs
+// Backtest commands and the equity curve types shared with the frontend
+
+use chrono::NaiveDate;
+use futures_util::future::join_all;
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tauri::Emitter;
+
+use super::display::Presented;
+use super::state::{BackgroundJobKind, JobOutput, JobRegistry, ProviderRegistry};
+use crate::market_data::dates;
+use crate::market_data::types::Candle;
+use crate::engine::analytics::{autocorrelation_lag1, runs_test, RunsTestResult};
+use crate::engine::basket::{self, BasketAllocation, BasketBacktest, BasketRankMetric, BasketRun};
+use crate::engine::calendar::MarketCalendar;
+use crate::engine::equity_history::SampledEquity;
+use crate::engine::metrics::{
+    annualized_cagr, beta_and_correlation, calc_drawdown_series, dual_drawdown, sharpe_ratio, DrawdownBasis, TRADING_DAYS_PER_YEAR,
+};
+use crate::engine::reproducibility::{self, BacktestManifest, BarSeriesRecord, BarSource, ReproductionReport};
+use crate::engine::round_trips::{round_trips, trade_statistics, RoundTrip, TradeStatistics};
+use crate::engine::pairs::{
+    align_closes, leg_quantities, pair_signals, spread_zscores, PairAction, PairConfig, PairDirection, PairSizing, SpreadDefinition,
+};
+use crate::engine::signal_optimizer::{self, OptimizationSpec, SignalOptimization};
+use crate::engine::simulation::SimRng;
+use crate::engine::spread;
+use crate::engine::strategies::{BacktestStrategy, SignalOrderConfig, WorkingEntry};
+use crate::engine::symbols::normalize_symbol;
+use crate::engine::types::{BrokerConfig, CommissionSchedule, InstrumentType, OrderSide, Trade};
+use crate::provider::diagnostics;
+use crate::provider::entitlements::Entitlement;
+use crate::provider::polygon as poly;
+use crate::provider::yahoo as yfin;
+use crate::storage::api_budget::{self, RequestEstimate};
+use crate::storage::backtest_history::{BacktestHistory, BacktestRun};
+use crate::storage::backtests::BacktestStore;
+use crate::storage::downloads::DownloadManager;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EquityPoint {
+    #[serde(alias = "t", deserialize_with = "dates::lenient")]
+    pub date: NaiveDate,
+    pub equity: f64,   // portfolio equity
+    pub drawdown: f64, // <= 0
+    #[serde(default)]
+    pub rolling_vol_20d: Option<f64>,    // annualized; None until 20 points of history
+    #[serde(default)]
+    pub rolling_sharpe_20d: Option<f64>, // annualized, zero risk-free rate
+    #[serde(default)]
+    pub realized_equity: Option<f64>,   // open positions at cost; None where it is not tracked
+    #[serde(default)]
+    pub realized_drawdown: Option<f64>, // <= 0, on realized_equity
+    #[serde(default)]
+    pub missing_bar: bool, // A trading day without a bar; equity carried from the previous point
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub benchmark_equity: Option<f64>, // The shadow benchmark account's on the same session; never set on backtests
+}
+
+impl EquityPoint {
+    pub fn equity_on(&self, basis: DrawdownBasis) -> f64 {
+        match basis {
+            DrawdownBasis::Total => self.equity,
+            DrawdownBasis::Realized => self.realized_equity.unwrap_or(self.equity),
+        }
+    }
+}
+
+/// Account equity per recorded session with drawdown on both bases
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EquityHistory {
+    pub points: Vec<EquityPoint>,
+    pub max_dd: f64,          // <= 0, total equity
+    pub max_dd_realized: f64, // <= 0, realized-only equity
+    #[serde(default)]
+    pub sampled: Option<SampledEquity>, // Intraday samples across retention tiers, when asked for
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BacktestParams {
+    pub ticker: String,
+    #[serde(rename = "start_on", alias = "start_date", deserialize_with = "dates::lenient")]
+    pub start_date: NaiveDate,
+    #[serde(rename = "end_on", alias = "end_date", deserialize_with = "dates::lenient")]
+    pub end_date: NaiveDate,
+    pub strategy: String,     // e.g. "BuyHold" / "PMCC" / "TsMomentum" / "MeanReversion"
+    pub initial_capital: f64, // e.g. 100000
+    pub seed: Option<u32>,
+    #[serde(default)]
+    pub warm_job_id: Option<String>, // Completed download job that must cover the range
+    #[serde(default)]
+    pub transaction_costs: TransactionCostModel,
+    #[serde(default)]
+    pub cost_model: Option<BacktestCostModel>, // Replaces transaction_costs when set
+    #[serde(default)]
+    pub pair: Option<PairConfig>, // Two-leg run over the pair's symbols; ticker is not used
+    #[serde(default)]
+    pub signal_orders: SignalOrderConfig, // How signal strategies' entries are priced and how long they work
+}
+
+impl BacktestParams {
+    /// Costs this run pays: `cost_model` when given, else `transaction_costs`
+    pub fn effective_cost_model(&self) -> BacktestCostModel {
+        self.cost_model.clone().unwrap_or_else(|| BacktestCostModel::from(&self.transaction_costs))
+    }
+}
+
+/// Per-trade execution costs. The default charges nothing, which keeps runs saved
+/// before costs existed reproducible.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TransactionCostModel {
+    pub commission_per_share: f64,
+    pub spread_bps: f64,               // Quoted bid/ask spread; each trade crosses half of it
+    pub market_impact_coefficient: f64,
+    pub adv: f64,                      // Average daily volume in shares; <= 0 uses the candles' mean volume
+}
+
+impl TransactionCostModel {
+    /// Cost of trading `quantity` shares at `price`: commission, half spread and
+    /// Almgren-Chriss square-root impact of `price * k * sqrt(q / (0.1 * adv))` per share
+    pub fn trade_cost(&self, price: f64, quantity: f64, adv: f64) -> f64 {
+        if price <= 0.0 || quantity <= 0.0 {
+            return 0.0;
+        }
+        let commission = self.commission_per_share * quantity;
+        let spread = price * quantity * self.spread_bps / 10_000.0 / 2.0;
+        let impact = if adv > 0.0 {
+            price * self.market_impact_coefficient * (quantity / (adv * 0.1)).sqrt() * quantity
+        } else {
+            0.0
+        };
+        commission + spread + impact
+    }
+
+}
+
+/// Execution costs for a backtest, kept apart from the paper broker's BrokerConfig so
+/// cost scenarios never touch live settings. The default charges nothing.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct BacktestCostModel {
+    pub label: Option<String>, // Name shown in comparisons; described from the settings when None
+    pub commission_schedule: CommissionSchedule,
+    pub commission_per_share: f64,   // PerShare schedule only, as are the three below
+    pub commission_per_trade: f64,
+    pub min_commission: f64,
+    pub max_commission: Option<f64>, // None is uncapped
+    pub fill_model: FillModel,
+    pub slippage_bps: f64,           // FillModel::Slippage
+    pub spread_bps: f64,             // FillModel::SpreadAndImpact, as are the two below
+    pub market_impact_coefficient: f64,
+    pub adv: f64,                    // <= 0 uses the candles' mean volume
+    pub estimated_spread_bps: Option<f64>, // FillModel::EstimatedSpread; read from the bars when None
+    pub buy: SideCostOverride,
+    pub sell: SideCostOverride,
+}
+
+/// Rates that differ for one side of the trade
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SideCostOverride {
+    pub commission_per_share: Option<f64>,
+    pub slippage_bps: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FillModel {
+    #[default]
+    Slippage,        // The close moved slippage_bps against the trade
+    SpreadAndImpact, // Half the spread plus square-root market impact, as TransactionCostModel
+    EstimatedSpread, // Half the spread estimated from the bars' highs and lows; slippage_bps without enough bars
+}
+
+impl From<&TransactionCostModel> for BacktestCostModel {
+    fn from(costs: &TransactionCostModel) -> Self {
+        Self {
+            commission_per_share: costs.commission_per_share,
+            fill_model: FillModel::SpreadAndImpact,
+            spread_bps: costs.spread_bps,
+            market_impact_coefficient: costs.market_impact_coefficient,
+            adv: costs.adv,
+            ..Default::default()
+        }
+    }
+}
+
+impl BacktestCostModel {
+    fn side(&self, side: &OrderSide) -> &SideCostOverride {
+        match side {
+            OrderSide::Buy => &self.buy,
+            OrderSide::Sell => &self.sell,
+        }
+    }
+
+    /// Commission on `quantity` shares at `price`. `trailing_shares` traded over the
+    /// previous 30 days set the Tiered rate.
+    pub fn commission(&self, side: &OrderSide, price: f64, quantity: f64, trailing_shares: i64) -> f64 {
+        if price <= 0.0 || quantity <= 0.0 {
+            return 0.0;
+        }
+        match self.commission_schedule {
+            CommissionSchedule::PerShare => {
+                let per_share = self.side(side).commission_per_share.unwrap_or(self.commission_per_share);
+                (quantity * per_share + self.commission_per_trade)
+                    .max(self.min_commission)
+                    .min(self.max_commission.unwrap_or(f64::INFINITY))
+            }
+            schedule => schedule.stock_cost(&BrokerConfig::default(), quantity.round() as i64, price, trailing_shares),
+        }
+    }
+
+    /// Dollars lost to the fill price on `quantity` shares at `price`
+    pub fn slippage(&self, side: &OrderSide, price: f64, quantity: f64, adv: f64) -> f64 {
+        if price <= 0.0 || quantity <= 0.0 {
+            return 0.0;
+        }
+        match self.fill_model {
+            FillModel::Slippage => price * quantity * self.side(side).slippage_bps.unwrap_or(self.slippage_bps) / 10_000.0,
+            FillModel::EstimatedSpread => match self.estimated_spread_bps {
+                Some(spread_bps) => price * quantity * spread_bps / 20_000.0,
+                None => price * quantity * self.side(side).slippage_bps.unwrap_or(self.slippage_bps) / 10_000.0,
+            },
+            FillModel::SpreadAndImpact => TransactionCostModel {
+                commission_per_share: 0.0,
+                spread_bps: self.spread_bps,
+                market_impact_coefficient: self.market_impact_coefficient,
+                adv,
+            }
+            .trade_cost(price, quantity, adv),
+        }
+    }
+
+    pub fn trade_cost(&self, side: &OrderSide, price: f64, quantity: f64, adv: f64, trailing_shares: i64) -> f64 {
+        self.commission(side, price, quantity, trailing_shares) + self.slippage(side, price, quantity, adv)
+    }
+
+    /// The model with the EstimatedSpread fill model's spread read from `symbol`'s
+    /// `candles`, unless it is already set
+    pub fn with_estimated_spread(&self, symbol: &str, candles: &[Candle]) -> Self {
+        let mut costs = self.clone();
+        if costs.fill_model == FillModel::EstimatedSpread && costs.estimated_spread_bps.is_none() {
+            costs.estimated_spread_bps = spread::from_bars(symbol, candles, 0).map(|e| e.spread_bps);
+        }
+        costs
+    }
+
+    /// Every rate multiplied by `factor`. Flat, Tiered and Zero commissions are fixed
+    /// schedules and stay as they are.
+    pub fn scaled(&self, factor: f64) -> Self {
+        let side = |s: &SideCostOverride| SideCostOverride {
+            commission_per_share: s.commission_per_share.map(|c| c * factor),
+            slippage_bps: s.slippage_bps.map(|b| b * factor),
+        };
+        Self {
+            label: self.label.as_ref().map(|label| format!("{} x{}", label, factor)),
+            commission_per_share: self.commission_per_share * factor,
+            commission_per_trade: self.commission_per_trade * factor,
+            min_commission: self.min_commission * factor,
+            max_commission: self.max_commission.map(|c| c * factor),
+            slippage_bps: self.slippage_bps * factor,
+            spread_bps: self.spread_bps * factor,
+            estimated_spread_bps: self.estimated_spread_bps.map(|s| s * factor),
+            market_impact_coefficient: self.market_impact_coefficient * factor,
+            buy: side(&self.buy),
+            sell: side(&self.sell),
+            ..self.clone()
+        }
+    }
+
+    /// `label`, or a short description of the settings such as
+    /// "PerShare $0.0050/sh, 5.0 bps slippage"
+    pub fn describe(&self) -> String {
+        if let Some(label) = &self.label {
+            return label.clone();
+        }
+        let commission = match self.commission_schedule {
+            CommissionSchedule::PerShare if self.commission_per_share == 0.0 && self.commission_per_trade == 0.0 => {
+                "no commission".to_string()
+            }
+            CommissionSchedule::PerShare => {
+                format!("PerShare ${:.4}/sh + ${:.2}/trade", self.commission_per_share, self.commission_per_trade)
+            }
+            schedule => schedule.name().to_string(),
+        };
+        let fills = match self.fill_model {
+            FillModel::Slippage => format!("{:.1} bps slippage", self.slippage_bps),
+            FillModel::SpreadAndImpact => {
+                format!("{:.1} bps spread, impact {}", self.spread_bps, self.market_impact_coefficient)
+            }
+            FillModel::EstimatedSpread => match self.estimated_spread_bps {
+                Some(spread_bps) => format!("{:.1} bps estimated spread", spread_bps),
+                None => format!("estimated spread, else {:.1} bps slippage", self.slippage_bps),
+            },
+        };
+        let overridden = [("buy", &self.buy), ("sell", &self.sell)]
+            .iter()
+            .filter(|(_, o)| **o != SideCostOverride::default())
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>();
+        if overridden.is_empty() {
+            format!("{}, {}", commission, fills)
+        } else {
+            format!("{}, {} ({} overrides)", commission, fills, overridden.join("/"))
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BacktestSummary {
+    pub strategy: String,
+    pub symbol: String,
+    #[serde(rename = "start_on", alias = "start", deserialize_with = "dates::lenient")]
+    pub start: NaiveDate,
+    #[serde(rename = "end_on", alias = "end", deserialize_with = "dates::lenient")]
+    pub end: NaiveDate,
+    pub capital: f64,
+    pub cagr: f64,
+    pub trades: u32,
+    pub win_rate: f64, // 0..1
+    pub max_dd: f64,   // <= 0
+    #[serde(default)]
+    pub max_dd_realized: f64, // <= 0, with open positions at cost: losses actually locked in
+    pub equity_curve: Vec<EquityPoint>, // One point per calendar trading day from the first bar to the last
+    #[serde(default)]
+    pub missing_trading_days: u32, // Trading days in the range, up to today, with no bar
+    #[serde(default)]
+    pub total_transaction_costs: f64,
+    #[serde(default)]
+    pub gross_pnl: f64,
+    #[serde(default)]
+    pub net_pnl: f64, // gross_pnl - total_transaction_costs
+    #[serde(default)]
+    pub sensitivity_analysis: Option<Vec<(f64, BacktestSummary)>>, // (cost multiplier, summary)
+    #[serde(default)]
+    pub randomness_test: Option<RunsTestResult>, // Runs test on the equity curve's daily returns
+    #[serde(default)]
+    pub autocorrelation_lag1: f64,
+    #[serde(default)]
+    pub benchmark: Option<BenchmarkComparison>, // Buy & hold of the same ticker; None in sensitivity runs
+    #[serde(default)]
+    pub trade_log: Vec<BacktestTrade>, // Round trips of signal-driven strategies; left out of run_backtest's reply
+    #[serde(default)]
+    pub trade_log_count: usize,
+    #[serde(default)]
+    pub fills: Vec<Trade>, // Executions behind trade_log
+    #[serde(default)]
+    pub id: Option<String>, // Saved result, reloaded in full by get_backtest
+    #[serde(default)]
+    pub cost_model: Option<BacktestCostModel>, // Costs the run paid; None for frictionless runs
+    #[serde(default)]
+    pub pair: Option<PairBacktestDetail>, // Spread and per-leg results of pair runs
+    #[serde(default)]
+    pub signal_fills: Option<SignalFillStats>, // Signal strategies only
+}
+
+/// How a signal strategy's entry signals turned into fills. Limit and stop entries can
+/// go unfilled, which changes what the strategy's results mean.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SignalFillStats {
+    pub entry_signals: u32,
+    pub filled: u32,
+    pub expired: u32,  // Never reached within expire_after_bars
+    pub canceled: u32, // Still working at an exit signal, a newer entry or the last bar
+    pub conversion_rate: f64, // filled / entry_signals; 0 without signals
+}
+
+/// A backtest round trip, matched and measured like the paper broker's journal
+pub type BacktestTrade = RoundTrip;
+
+impl BacktestSummary {
+    /// The summary without its trade log and fills, keeping their count
+    pub fn without_trades(mut self) -> Self {
+        self.trade_log_count = self.trade_log.len();
+        self.trade_log = Vec::new();
+        self.fills = Vec::new();
+        self
+    }
+}
+
+/// Spread-level results of a pair backtest with each leg's share
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PairBacktestDetail {
+    pub label: String, // "FIRST/SECOND"
+    pub spread: SpreadDefinition,
+    pub sizing: PairSizing,
+    pub spread_pnl: f64, // Both legs of every closed round trip, net of costs
+    pub legs: Vec<PairLegSummary>, // First leg, then second
+    pub round_trips: Vec<PairRoundTrip>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PairLegSummary {
+    pub symbol: String,
+    pub trades: u32,
+    pub pnl: f64, // Closed round trips, net of the leg's costs
+    pub transaction_costs: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PairRoundTrip {
+    pub direction: PairDirection,
+    #[serde(rename = "entry_on", alias = "entry_date", deserialize_with = "dates::lenient")]
+    pub entry_date: NaiveDate,
+    #[serde(rename = "exit_on", alias = "exit_date", deserialize_with = "dates::lenient")]
+    pub exit_date: NaiveDate,
+    pub entry_z: f64,
+    pub exit_z: Option<f64>,
+    pub exit_reason: String,
+    pub first_quantity: i64, // Negative for the short leg
+    pub second_quantity: i64,
+    pub first_pnl: f64,
+    pub second_pnl: f64,
+    pub spread_pnl: f64,
+}
+
+/// Buy & hold of the backtest's ticker over the same bars, and how the strategy's
+/// returns relate to it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BenchmarkComparison {
+    pub cagr: f64,
+    pub max_dd: f64,
+    pub sharpe: f64,
+    pub alpha: f64,       // Strategy CAGR - beta * benchmark CAGR
+    pub beta: f64,        // cov(strategy, benchmark) / var(benchmark) of daily returns
+    pub correlation: f64,
+    pub equity_curve: Vec<EquityPoint>,
+}
+
+impl BenchmarkComparison {
+    /// Compare a strategy's equity series and CAGR against a benchmark curve spanning
+    /// `days` calendar days
+    pub fn new(strategy_equities: &[f64], strategy_cagr: f64, equity_curve: Vec<EquityPoint>, days: usize) -> Self {
+        let equities: Vec<f64> = equity_curve.iter().map(|p| p.equity).collect();
+        let cagr = match (equities.first(), equities.last()) {
+            (Some(first), Some(last)) => annualized_cagr(*first, *last, days),
+            _ => 0.0,
+        };
+        let (_, max_dd) = calc_drawdown_series(&equities);
+        let (beta, correlation) = beta_and_correlation(strategy_equities, &equities);
+        Self {
+            cagr,
+            max_dd,
+            sharpe: sharpe_ratio(&equities),
+            alpha: strategy_cagr - beta * cagr,
+            beta,
+            correlation,
+            equity_curve,
+        }
+    }
+}
+
+/// Two recorded runs side by side; differences are run A minus run B
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BacktestComparison {
+    pub run_a: BacktestSummary,
+    pub run_b: BacktestSummary,
+    pub cagr_diff: f64,
+    pub max_dd_diff: f64, // Positive when A's worst drawdown was shallower
+    pub sharpe_diff: f64,
+    pub better_run: String, // Run id with the higher Sharpe, CAGR breaking ties
+    pub summary: String,
+}
+
+impl BacktestComparison {
+    pub fn new(a: BacktestRun, b: BacktestRun) -> Self {
+        let sharpe = |s: &BacktestSummary| sharpe_ratio(&s.equity_curve.iter().map(|p| p.equity).collect::<Vec<_>>());
+        let sharpe_diff = sharpe(&a.summary) - sharpe(&b.summary);
+        let cagr_diff = a.summary.cagr - b.summary.cagr;
+        let max_dd_diff = a.summary.max_dd - b.summary.max_dd;
+        let a_better = sharpe_diff > 1e-12 || (sharpe_diff.abs() <= 1e-12 && cagr_diff >= 0.0);
+        let (better, worse) = if a_better { (&a, &b) } else { (&b, &a) };
+        let sign = if a_better { 1.0 } else { -1.0 };
+        let summary = format!(
+            "{} ({}) beat {} ({}): Sharpe {:+.2}, CAGR {:+.2} pts, max drawdown {:+.2} pts",
+            better.run_id,
+            better.summary.strategy,
+            worse.run_id,
+            worse.summary.strategy,
+            sharpe_diff * sign,
+            cagr_diff * sign * 100.0,
+            max_dd_diff * sign * 100.0,
+        );
+        Self {
+            better_run: better.run_id.clone(),
+            run_a: a.summary,
+            run_b: b.summary,
+            cagr_diff,
+            max_dd_diff,
+            sharpe_diff,
+            summary,
+        }
+    }
+}
+
+const ROLLING_WINDOW: usize = 20;
+const COST_SENSITIVITY_MULTIPLIERS: [f64; 4] = [0.5, 1.0, 2.0, 5.0];
+const COMMISSION_TIER_WINDOW_SECONDS: i64 = 30 * 86400; // Share volume that sets the Tiered rate
+const BASKET_CONCURRENCY: usize = 4; // Symbols loading and running at once
+
+// Rolling annualized volatility and Sharpe over the last 20 points (19 daily log returns)
+pub fn fill_rolling_stats(curve: &mut [EquityPoint]) {
+    fill_rolling_stats_on(curve, DrawdownBasis::Total);
+}
+
+/// `fill_rolling_stats` over the equity on `basis`
+pub fn fill_rolling_stats_on(curve: &mut [EquityPoint], basis: DrawdownBasis) {
+    let returns: Vec<f64> = curve
+        .windows(2)
+        .map(|w| (w[0].equity_on(basis), w[1].equity_on(basis)))
+        .map(|(a, b)| if a > 0.0 && b > 0.0 { (b / a).ln() } else { 0.0 })
+        .collect();
+
+    for (i, point) in curve.iter_mut().enumerate() {
+        if i + 1 < ROLLING_WINDOW {
+            point.rolling_vol_20d = None;
+            point.rolling_sharpe_20d = None;
+            continue;
+        }
+
+        let window = &returns[i + 1 - ROLLING_WINDOW..i];
+        let n = window.len() as f64;
+        let mean = window.iter().sum::<f64>() / n;
+        let std = (window.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+
+        point.rolling_vol_20d = Some(std * TRADING_DAYS_PER_YEAR.sqrt());
+        point.rolling_sharpe_20d = Some(if std > 1e-12 { mean / std * TRADING_DAYS_PER_YEAR.sqrt() } else { 0.0 });
+    }
+}
+
+/// Drawdown of each point on both bases; returns the deepest total and realized
+/// drawdowns. A curve without realized equity on every point gets no realized series.
+pub fn fill_drawdowns(curve: &mut [EquityPoint]) -> (f64, f64) {
+    let tracked = curve.iter().all(|p| p.realized_equity.is_some());
+    let total: Vec<f64> = curve.iter().map(|p| p.equity).collect();
+    let realized: Vec<f64> = curve.iter().map(|p| p.equity_on(DrawdownBasis::Realized)).collect();
+    let drawdowns = dual_drawdown(&total, &realized);
+    for ((point, total), realized) in curve.iter_mut().zip(&drawdowns.total).zip(&drawdowns.realized) {
+        point.drawdown = *total;
+        point.realized_drawdown = tracked.then_some(*realized);
+    }
+    (drawdowns.max_total, if tracked { drawdowns.max_realized } else { 0.0 })
+}
+
+/// The curve over every calendar trading day from its first point to its last. A
+/// trading day without a point repeats the previous point's equity and is flagged as
+/// a missing bar; points on days the calendar does not trade are kept.
+pub fn align_to_calendar(curve: Vec<EquityPoint>, calendar: &MarketCalendar) -> Vec<EquityPoint> {
+    let (Some(first), Some(last)) = (curve.first().map(|p| p.date), curve.last().map(|p| p.date)) else {
+        return curve;
+    };
+    let mut expected = calendar.get_trading_days(first, last).into_iter().peekable();
+    let mut aligned: Vec<EquityPoint> = Vec::with_capacity(curve.len());
+    for point in curve {
+        let date = point.date;
+        while let Some(day) = expected.next_if(|day| *day <= date) {
+            let Some(previous) = aligned.last().filter(|_| day < date) else {
+                continue;
+            };
+            aligned.push(EquityPoint {
+                date: day,
+                missing_bar: true,
+                rolling_vol_20d: None,
+                rolling_sharpe_20d: None,
+                ..previous.clone()
+            });
+        }
+        aligned.push(point);
+    }
+    aligned
+}
+
+/// Trading days in the params' range, up to today, that no point of `curve` has a bar
+/// for
+fn missing_trading_days(params: &BacktestParams, curve: &[EquityPoint], calendar: &MarketCalendar) -> u32 {
+    let bars: std::collections::HashSet<NaiveDate> = curve.iter().filter(|p| !p.missing_bar).map(|p| p.date).collect();
+    let (start, end) = (params.start_date, params.end_date.min(chrono::Utc::now().date_naive()));
+    if start > end {
+        return 0;
+    }
+    calendar.get_trading_days(start, end).iter().filter(|day| !bars.contains(day)).count() as u32
+}
+
+/// Calendar days from the curve's first point to its last, the span its CAGR is
+/// annualized over
+fn curve_span_days(curve: &[EquityPoint]) -> usize {
+    match (curve.first(), curve.last()) {
+        (Some(first), Some(last)) => (last.date - first.date).num_days().max(0) as usize,
+        _ => curve.len(),
+    }
+}
+
+#[tauri::command]
+pub async fn get_sample_backtest_result() -> Presented<BacktestSummary> {
+    // TODO: return your existing sample, or synthesize a small curve
+    // minimal safe stub:
+    let mut sample = BacktestSummary {
+        strategy: "PMCC".into(),
+        symbol: "SPY".into(),
+        start: NaiveDate::from_ymd_opt(2023, 1, 1).unwrap_or_default(),
+        end: NaiveDate::from_ymd_opt(2023, 12, 31).unwrap_or_default(),
+        capital: 100_000.0,
+        cagr: 0.12,
+        trades: 40,
+        win_rate: 0.55,
+        max_dd: -0.15,
+        max_dd_realized: 0.0,
+        missing_trading_days: 0,
+        equity_curve: {
+            let mut curve = generate_deterministic_equity_curve(252, 100_000.0, 42);
+            fill_rolling_stats(&mut curve);
+            curve
+        },
+        total_transaction_costs: 0.0,
+        gross_pnl: 0.0,
+        net_pnl: 0.0,
+        sensitivity_analysis: None,
+        randomness_test: None,
+        autocorrelation_lag1: 0.0,
+        benchmark: None,
+        trade_log: Vec::new(),
+        trade_log_count: 0,
+        fills: Vec::new(),
+        id: None,
+        cost_model: None,
+        pair: None,
+        signal_fills: None,
+    };
+    let strategy_equities: Vec<f64> = sample.equity_curve.iter().map(|p| p.equity).collect();
+    let mut benchmark_curve = generate_deterministic_equity_curve(252, 100_000.0, 7);
+    fill_rolling_stats(&mut benchmark_curve);
+    sample.benchmark = Some(BenchmarkComparison::new(&strategy_equities, sample.cagr, benchmark_curve, 364));
+    Presented(sample)
+}
+
+#[tauri::command]
+pub async fn suggest_and_analyze(_params: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+      "ok": true,
+      "notes": ["stub"],
+      "recommendation": { "strategy": "PMCC", "confidence": 0.6 }
+    })
+}
+
+#[tauri::command]
+pub async fn fetch_news_sentiment(symbol: String) -> serde_json::Value {
+    serde_json::json!({ "symbol": symbol, "stories": [], "sentiment": 0.0 })
+}
+
+#[tauri::command]
+pub async fn adaptive_run(_mode: String) -> serde_json::Value {
+    serde_json::json!({
+        "status": "stub",
+        "message": "Adaptive run not implemented yet"
+    })
+}
+
+//
+// ---------- run_backtest (uses Polygon, falls back to Yahoo) ----------
+//
+
+/// Run and save a backtest. The reply leaves out the trade log to keep it small;
+/// `get_backtest` with the reply's id returns the full result. The saved result's
+/// manifest records the exact bars used, pinned in the backtest store or, with
+/// `embed_bars`, written into the manifest itself.
+#[tauri::command]
+pub async fn run_backtest(
+    providers: tauri::State<'_, ProviderRegistry>,
+    downloads: tauri::State<'_, DownloadManager>,
+    params: BacktestParams,
+    embed_bars: Option<bool>,
+) -> Result<Presented<BacktestSummary>, String> {
+    let t0 = Instant::now();
+
+    let series = backtest_series(&providers, &downloads, &params).await?;
+    let mut out = run_on_series(&params, &series)?;
+
+    let store = BacktestStore::new(providers.backtests_dir());
+    if let Err(e) = save_with_manifest(&store, &mut out, &params, &series, embed_bars.unwrap_or(false)) {
+        eprintln!("Failed to save backtest result: {}", e);
+        out.id = None;
+    }
+    let out = out.without_trades();
+    if let Err(e) = record_backtest_run(&providers, out.clone()) {
+        eprintln!("Failed to record backtest run: {}", e);
+    }
+
+    let _elapsed_ms = t0.elapsed().as_millis();
+    Ok(Presented(out))
+}
+
+/// What a run would fetch before it is started, with anything that will keep it from
+/// getting the bars it asks for
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BacktestPreflight {
+    pub estimates: Vec<RequestEstimate>, // Polygon requests for series not already cached
+    pub warnings: Vec<String>,
+}
+
+/// Check a run's bar requests against the Polygon key's plan and the quote checks
+/// without fetching anything
+#[tauri::command]
+pub async fn preflight_backtest(providers: tauri::State<'_, ProviderRegistry>, params: BacktestParams) -> Result<BacktestPreflight, String> {
+    let mut preflight = BacktestPreflight { estimates: Vec::new(), warnings: Vec::new() };
+    if params.warm_job_id.is_some() {
+        return Ok(preflight); // Served from the download job's cache
+    }
+    let symbols = match &params.pair {
+        Some(pair) => {
+            let pair = pair.normalized()?;
+            vec![pair.first, pair.second]
+        }
+        None => vec![normalize_symbol(&params.ticker)?],
+    };
+    let app = providers.app()?;
+    let calendar = MarketCalendar::new();
+    let uncached: Vec<String> = symbols
+        .into_iter()
+        .filter(|symbol| !poly::is_history_cached(app, symbol, params.start_date, params.end_date, Some("1day")))
+        .collect();
+    for symbol in &uncached {
+        preflight.estimates.push(poly::estimate_request(symbol, params.start_date, params.end_date, Some("1day"), false, &calendar));
+    }
+    if uncached.is_empty() {
+        return Ok(preflight);
+    }
+    if let Some(profile) = poly::current_entitlements(app).await {
+        if let Err(e) = profile.check(Entitlement::Stocks) {
+            preflight.warnings.push(format!("{}; bars will come from Yahoo", e));
+        }
+        preflight.warnings.extend(profile.history_warning(params.start_date, chrono::Utc::now().date_naive()));
+    }
+    if providers.is_degraded(api_budget::POLYGON).await {
+        preflight.warnings.push("Quote checks have Polygon marked degraded; bars will come from Yahoo first".to_string());
+    }
+    Ok(preflight)
+}
+
+/// Bars a run needs: the ticker's, or each leg's for a pair
+async fn backtest_series(
+    providers: &ProviderRegistry,
+    downloads: &DownloadManager,
+    params: &BacktestParams,
+) -> Result<Vec<(String, Vec<Candle>)>, String> {
+    match &params.pair {
+        Some(pair) => {
+            let pair = pair.normalized()?;
+            let mut series = Vec::new();
+            for ticker in [&pair.first, &pair.second] {
+                let leg_params = BacktestParams { ticker: ticker.clone(), pair: None, ..params.clone() };
+                series.push((ticker.clone(), load_candles(providers, downloads, &leg_params).await?));
+            }
+            Ok(series)
+        }
+        None => Ok(vec![(params.ticker.clone(), load_candles(providers, downloads, params).await?)]),
+    }
+}
+
+fn run_on_series(params: &BacktestParams, series: &[(String, Vec<Candle>)]) -> Result<BacktestSummary, String> {
+    match (&params.pair, series) {
+        (Some(pair), [(_, first), (_, second)]) => Ok(summarize_pair_backtest(params, &pair.normalized()?, first, second)),
+        (None, [(_, candles)]) => Ok(summarize_backtest(params, candles)),
+        _ => Err(format!("Expected {} bar series, got {}", if params.pair.is_some() { 2 } else { 1 }, series.len())),
+    }
+}
+
+/// Save a result and its reproducibility manifest, pinning or embedding its bars
+fn save_with_manifest(
+    store: &BacktestStore,
+    summary: &mut BacktestSummary,
+    params: &BacktestParams,
+    series: &[(String, Vec<Candle>)],
+    embed_bars: bool,
+) -> Result<String, String> {
+    let id = store.save(summary)?;
+    let mut records = Vec::with_capacity(series.len());
+    for (symbol, bars) in series {
+        if !embed_bars {
+            store.pins().pin(symbol, bars, &id)?;
+        }
+        records.push(BarSeriesRecord::new(symbol, bars, embed_bars));
+    }
+    store.save_manifest(&BacktestManifest::new(summary, params, records))?;
+    Ok(id)
+}
+
+/// Re-run saved backtest `id` from its manifest and report whether the result matches
+/// the original bit for bit, with the metrics that moved when it does not. Bars come
+/// from the manifest or its pins; only series whose stored copy is lost are fetched
+/// again, and those are flagged when the provider has since restated them.
+#[tauri::command]
+pub async fn reproduce_backtest(
+    providers: tauri::State<'_, ProviderRegistry>,
+    downloads: tauri::State<'_, DownloadManager>,
+    id: String,
+) -> Result<Presented<ReproductionReport>, String> {
+    let store = BacktestStore::new(providers.backtests_dir());
+    let original = store.load(&id)?;
+    let manifest = store
+        .load_manifest(&id)?
+        .ok_or_else(|| format!("Backtest {} was saved without a manifest and cannot be reproduced", id))?;
+
+    let mut warnings = Vec::new();
+    if manifest.engine_version != env!("CARGO_PKG_VERSION") || manifest.feature_hash != reproducibility::engine_feature_hash() {
+        warnings.push(format!("The run was made by engine {}; this is {}", manifest.engine_version, env!("CARGO_PKG_VERSION")));
+    }
+    let (mut series, mut restated_series) = (Vec::with_capacity(manifest.series.len()), Vec::new());
+    for record in &manifest.series {
+        let stored = match &record.source {
+            BarSource::Embedded { bars } => Ok(bars.clone()),
+            BarSource::Pinned => store.pins().load(&record.content_hash),
+        };
+        let bars = match stored {
+            Ok(bars) => bars,
+            Err(e) => {
+                warnings.push(format!("{}; fetching {} again", e, record.symbol));
+                let params = BacktestParams { ticker: record.symbol.clone(), pair: None, warm_job_id: None, ..manifest.params.clone() };
+                let bars = load_candles(&providers, &downloads, &params).await?;
+                if reproducibility::bars_hash(&bars) != record.content_hash {
+                    restated_series.push(record.symbol.clone());
+                }
+                bars
+            }
+        };
+        series.push((record.symbol.clone(), bars));
+    }
+
+    let params = manifest.params.clone();
+    let reproduced = tokio::task::spawn_blocking(move || run_on_series(&params, &series))
+        .await
+        .map_err(|e| format!("Backtest run failed: {}", e))??;
+    let reproduced_hash = reproducibility::result_hash(&reproduced);
+    let original_hash = if manifest.schema_version < 2 {
+        warnings.push("The manifest predates native dates; the original result was hashed again from its saved copy".to_string());
+        reproducibility::result_hash(&original)
+    } else {
+        manifest.result_hash
+    };
+    Ok(Presented(ReproductionReport {
+        matches: reproduced_hash == original_hash,
+        original_hash,
+        reproduced_hash,
+        diffs: reproducibility::diff_metrics(&original, &reproduced),
+        restated_series,
+        warnings,
+        reproduced: reproduced.without_trades(),
+        id,
+    }))
+}
+
+/// Add a run to the history; saved results of runs it evicts are deleted with them
+fn record_backtest_run(providers: &ProviderRegistry, summary: BacktestSummary) -> Result<(), String> {
+    let history = BacktestHistory::open(providers.app()?)?;
+    let (_, evicted) = history.record(summary, chrono::Utc::now().timestamp())?;
+    let store = BacktestStore::new(providers.backtests_dir());
+    for id in evicted.iter().filter_map(|run| run.summary.id.as_deref()) {
+        store.delete(id)?;
+    }
+    Ok(())
+}
+
+/// Recent backtest runs, oldest first
+#[tauri::command]
+pub async fn load_backtest_history(app: tauri::AppHandle) -> Result<Presented<Vec<BacktestRun>>, String> {
+    BacktestHistory::open(&app)?.load().map(Presented)
+}
+
+#[tauri::command]
+pub async fn compare_backtest_runs(app: tauri::AppHandle, run_id_a: String, run_id_b: String) -> Result<Presented<BacktestComparison>, String> {
+    let history = BacktestHistory::open(&app)?;
+    Ok(Presented(BacktestComparison::new(history.get(&run_id_a)?, history.get(&run_id_b)?)))
+}
+
+/// Remove a run from the history along with its saved result
+#[tauri::command]
+pub async fn delete_backtest_run(
+    app: tauri::AppHandle,
+    providers: tauri::State<'_, ProviderRegistry>,
+    run_id: String,
+) -> Result<(), String> {
+    let run = BacktestHistory::open(&app)?.delete(&run_id)?;
+    match run.summary.id {
+        Some(id) => BacktestStore::new(providers.backtests_dir()).delete(&id),
+        None => Ok(()),
+    }
+}
+
+/// A saved backtest with its trade log and fills
+#[tauri::command]
+pub async fn get_backtest(providers: tauri::State<'_, ProviderRegistry>, id: String) -> Result<Presented<BacktestSummary>, String> {
+    BacktestStore::new(providers.backtests_dir()).load(&id).map(Presented)
+}
+
+/// Round-trip statistics of a saved backtest, comparable with `get_trade_statistics`
+#[tauri::command]
+pub async fn get_backtest_statistics(providers: tauri::State<'_, ProviderRegistry>, id: String) -> Result<TradeStatistics, String> {
+    let summary = BacktestStore::new(providers.backtests_dir()).load(&id)?;
+    Ok(trade_statistics(&summary.trade_log))
+}
+
+/// Run the same strategy and bars under the params' own costs and each of
+/// `cost_variants`, concurrently, and report each variant's metrics against the
+/// params' run. `spread_multiples` add variants that fill at half of that multiple of
+/// the spread estimated from the bars.
+#[tauri::command]
+pub async fn run_cost_sensitivity(
+    providers: tauri::State<'_, ProviderRegistry>,
+    downloads: tauri::State<'_, DownloadManager>,
+    params: BacktestParams,
+    cost_variants: Vec<BacktestCostModel>,
+    spread_multiples: Option<Vec<f64>>,
+) -> Result<CostSensitivityReport, String> {
+    if let Some(pair) = &params.pair {
+        return Err(format!("Cost comparisons run on single-symbol backtests, not the {} pair", pair.label()));
+    }
+    let candles = load_candles(&providers, &downloads, &params).await?;
+    compare_cost_models(params, Arc::new(candles), cost_variants, &spread_multiples.unwrap_or_default()).await
+}
+
+/// Payload of the "basket_backtest_progress" event, sent as each symbol finishes
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BasketProgress {
+    pub symbol: String,
+    pub completed: usize,
+    pub total: usize,
+    pub error: Option<String>,
+}
+
+/// Backtest the params' strategy on each of `symbols` on its own, a few at a time
+/// through the same bar cache as single runs, then combine the runs with the starting
+/// capital split by `allocation_mode`. A symbol without bars is reported in the
+/// result's errors rather than failing the basket. Each symbol's run and the basket
+/// are saved; members' run ids load the full runs with `get_backtest`.
+#[tauri::command]
+pub async fn run_basket_backtest(
+    providers: tauri::State<'_, ProviderRegistry>,
+    downloads: tauri::State<'_, DownloadManager>,
+    params: BacktestParams,
+    symbols: Vec<String>,
+    allocation_mode: Option<BasketAllocation>,
+    rank_by: Option<BasketRankMetric>,
+) -> Result<Presented<BasketBacktest>, String> {
+    if let Some(pair) = &params.pair {
+        return Err(format!("Baskets run single-symbol backtests, not the {} pair", pair.label()));
+    }
+    let mut symbols = symbols.iter().map(|s| normalize_symbol(s)).collect::<Result<Vec<_>, _>>()?;
+    symbols.sort();
+    symbols.dedup();
+    if symbols.is_empty() {
+        return Err("A basket needs at least one symbol".to_string());
+    }
+
+    let app = providers.app()?;
+    let store = BacktestStore::new(providers.backtests_dir());
+    let (total, completed) = (symbols.len(), AtomicUsize::new(0));
+    let runs = stream::iter(symbols)
+        .map(|symbol| {
+            let (providers, downloads, store, completed) = (&providers, &downloads, &store, &completed);
+            let params = BacktestParams { ticker: symbol.clone(), ..params.clone() };
+            async move {
+                let run = run_basket_member(providers, downloads, store, params).await;
+                let _ = app.emit("basket_backtest_progress", &BasketProgress {
+                    symbol: symbol.clone(),
+                    completed: completed.fetch_add(1, Ordering::Relaxed) + 1,
+                    total,
+                    error: run.as_ref().err().cloned(),
+                });
+                (symbol, run)
+            }
+        })
+        .buffer_unordered(BASKET_CONCURRENCY)
+        .collect::<Vec<_>>()
+        .await;
+
+    let allocation = allocation_mode.unwrap_or_default();
+    let mut basket = basket::aggregate_basket(runs, params.initial_capital, allocation, rank_by.unwrap_or_default(), chrono::Utc::now().timestamp());
+    if basket.members.is_empty() {
+        return Err(format!(
+            "No symbol in the basket could be backtested: {}",
+            basket.errors.iter().map(|e| format!("{} ({})", e.symbol, e.error)).collect::<Vec<_>>().join(", ")
+        ));
+    }
+    store.save_basket(&mut basket)?;
+    Ok(Presented(basket))
+}
+
+/// One symbol of a basket, saved with its trade log
+async fn run_basket_member(
+    providers: &ProviderRegistry,
+    downloads: &DownloadManager,
+    store: &BacktestStore,
+    params: BacktestParams,
+) -> Result<BasketRun, String> {
+    let candles = load_candles(providers, downloads, &params).await?;
+    if candles.len() < 2 {
+        return Err(format!("Not enough bars for {} in {} - {}", params.ticker, params.start_date, params.end_date));
+    }
+    let volatility = basket::annualized_volatility(&candles.iter().map(|c| c.close).collect::<Vec<_>>());
+    let series = vec![(params.ticker.clone(), candles)];
+    let (mut summary, params, series) = tokio::task::spawn_blocking(move || {
+        let summary = summarize_backtest(&params, &series[0].1);
+        (summary, params, series)
+    })
+    .await
+    .map_err(|e| format!("Backtest run failed: {}", e))?;
+    if let Err(e) = save_with_manifest(store, &mut summary, &params, &series, false) {
+        eprintln!("Failed to save basket member {}: {}", summary.symbol, e);
+        summary.id = None;
+    }
+    Ok(BasketRun { summary: summary.without_trades(), volatility })
+}
+
+/// Saved basket results, newest first
+#[tauri::command]
+pub async fn list_basket_backtests(providers: tauri::State<'_, ProviderRegistry>) -> Result<Presented<Vec<BasketBacktest>>, String> {
+    BacktestStore::new(providers.backtests_dir()).list_baskets().map(Presented)
+}
+
+#[tauri::command]
+pub async fn get_basket_backtest(providers: tauri::State<'_, ProviderRegistry>, id: String) -> Result<Presented<BasketBacktest>, String> {
+    BacktestStore::new(providers.backtests_dir()).load_basket(&id).map(Presented)
+}
+
+/// Remove a basket result along with its members' saved runs
+#[tauri::command]
+pub async fn delete_basket_backtest(providers: tauri::State<'_, ProviderRegistry>, id: String) -> Result<(), String> {
+    BacktestStore::new(providers.backtests_dir()).delete_basket(&id).map(|_| ())
+}
+
+/// Search the enabled signals' declared parameter ranges for the config that scores best
+/// on `spec.objective`, replaying each symbol's daily bars through the live loop's signal
+/// code. Bars load through the usual provider path and budget checks before the job
+/// starts; the search runs as a background job with progress and cancellation, and the
+/// finished job's result_id loads the stored result with `get_signal_optimization`.
+#[tauri::command]
+pub async fn optimize_signal_config(
+    providers: tauri::State<'_, ProviderRegistry>,
+    downloads: tauri::State<'_, DownloadManager>,
+    jobs: tauri::State<'_, JobRegistry>,
+    mut spec: OptimizationSpec,
+) -> Result<String, String> {
+    spec.symbols = spec.symbols.iter().map(|s| normalize_symbol(s)).collect::<Result<Vec<_>, _>>()?;
+    spec.symbols.sort();
+    spec.symbols.dedup();
+    let total = spec.validate()?;
+
+    let mut bars = Vec::with_capacity(spec.symbols.len());
+    for symbol in &spec.symbols {
+        let params = BacktestParams {
+            ticker: symbol.clone(),
+            start_date: spec.start_date,
+            end_date: spec.end_date,
+            strategy: "SignalOptimization".to_string(),
+            initial_capital: 0.0,
+            seed: None,
+            warm_job_id: None,
+            transaction_costs: TransactionCostModel::default(),
+            cost_model: None,
+            pair: None,
+            signal_orders: SignalOrderConfig::default(),
+        };
+        bars.push((symbol.clone(), load_candles(&providers, &downloads, &params).await?));
+    }
+
+    let store = BacktestStore::new(providers.backtests_dir());
+    Ok(jobs.spawn(BackgroundJobKind::Optimization, None, move |job| {
+        job.set_total(total as u64);
+        let mut optimization = signal_optimizer::optimize(&spec, &bars, &mut |evaluated, _| {
+            job.progress(evaluated as u64, 0);
+            !job.is_cancelled()
+        })?;
+        let id = store.save_optimization(&mut optimization)?;
+        Ok(JobOutput { rows: optimization.candidates.len() as u64, result_id: Some(id), ..JobOutput::default() })
+    }))
+}
+
+/// Saved signal optimizations, newest first
+#[tauri::command]
+pub async fn list_signal_optimizations(providers: tauri::State<'_, ProviderRegistry>) -> Result<Presented<Vec<SignalOptimization>>, String> {
+    BacktestStore::new(providers.backtests_dir()).list_optimizations().map(Presented)
+}
+
+#[tauri::command]
+pub async fn get_signal_optimization(providers: tauri::State<'_, ProviderRegistry>, id: String) -> Result<Presented<SignalOptimization>, String> {
+    BacktestStore::new(providers.backtests_dir()).load_optimization(&id).map(Presented)
+}
+
+/// Daily bars for a backtest: the warm download job's cache when one is named, else
+/// Polygon with Yahoo as the fallback (Yahoo first while Polygon is marked degraded)
+async fn load_candles(providers: &ProviderRegistry, downloads: &DownloadManager, params: &BacktestParams) -> Result<Vec<Candle>, String> {
+    let candles: Vec<Candle> = if let Some(job_id) = &params.warm_job_id {
+        // Pre-warmed runs read only from the download job's cache and never fall back
+        downloads
+            .load_warm_bars(job_id, &params.ticker, params.start_date, params.end_date)
+            .await?
+    } else {
+        let polygon = || async {
+            let bars = poly::fetch_history(
+                providers.app()?,
+                params.ticker.clone(),
+                params.start_date,
+                params.end_date,
+                Some("1day".into()),
+            )
+            .await?;
+            if bars.is_empty() { Err(format!("No Polygon bars for {}", params.ticker)) } else { Ok(bars) }
+        };
+        let yahoo = || async {
+            let capture_dir = providers.app().ok().and_then(diagnostics::capture_dir);
+            yfin::yahoo_history(params.ticker.clone(), params.start_date, params.end_date, capture_dir.as_deref()).await
+        };
+
+        // Polygon first with Yahoo as the fallback, the other way round while quote
+        // checks have Polygon marked degraded
+        if providers.is_degraded(api_budget::POLYGON).await {
+            match yahoo().await {
+                Ok(v) if !v.is_empty() => v,
+                _ => polygon().await.map_err(|e| format!("Both providers failed: {e}"))?,
+            }
+        } else {
+            match polygon().await {
+                Ok(v) => v,
+                Err(_) => yahoo().await.map_err(|e| format!("Both providers failed: {e}"))?,
+            }
+        }
+    };
+    Ok(candles)
+}
+
+/// One run of a cost comparison; deltas are against the baseline run
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CostVariantResult {
+    pub label: String,
+    pub cost_model: BacktestCostModel,
+    pub cagr: f64,
+    pub max_dd: f64,
+    pub total_fees: f64, // Commissions and slippage
+    pub cagr_delta: f64,
+    pub max_dd_delta: f64,
+    pub total_fees_delta: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CostSensitivityReport {
+    pub strategy: String,
+    pub symbol: String,
+    pub baseline: CostVariantResult, // The params' own costs
+    pub variants: Vec<CostVariantResult>, // In the order requested, spread multiples last
+}
+
+pub async fn compare_cost_models(
+    params: BacktestParams,
+    candles: Arc<Vec<Candle>>,
+    cost_variants: Vec<BacktestCostModel>,
+    spread_multiples: &[f64],
+) -> Result<CostSensitivityReport, String> {
+    if cost_variants.is_empty() && spread_multiples.is_empty() {
+        return Err("At least one cost variant is required".to_string());
+    }
+    if spread_multiples.iter().any(|m| !m.is_finite() || *m < 0.0) {
+        return Err("Spread multiples cannot be negative".to_string());
+    }
+    if !pays_transaction_costs(&params) {
+        return Err(format!("{} is the frictionless benchmark and pays no costs", params.strategy));
+    }
+    if candles.len() < 2 {
+        return Err(format!("Not enough bars for {} to compare costs", params.ticker));
+    }
+
+    let baseline_costs = params.effective_cost_model().with_estimated_spread(&params.ticker, &candles);
+    let spread_variants: Vec<BacktestCostModel> = if spread_multiples.is_empty() {
+        Vec::new()
+    } else {
+        let estimate = spread::from_bars(&params.ticker, &candles, 0)
+            .ok_or_else(|| format!("Not enough bars for {} to estimate its spread", params.ticker))?;
+        spread_multiples
+            .iter()
+            .map(|m| BacktestCostModel {
+                label: Some(format!("Estimated spread x{}", m)),
+                fill_model: FillModel::EstimatedSpread,
+                estimated_spread_bps: Some(estimate.spread_bps * m),
+                ..baseline_costs.clone()
+            })
+            .collect()
+    };
+    let cost_variants: Vec<BacktestCostModel> = cost_variants.iter().map(|costs| costs.with_estimated_spread(&params.ticker, &candles)).collect();
+
+    let params = Arc::new(params);
+    let runs = std::iter::once(baseline_costs).chain(cost_variants).chain(spread_variants).map(|costs| {
+        let (params, candles) = (params.clone(), candles.clone());
+        tokio::task::spawn_blocking(move || {
+            let summary = summarize_with_costs(&params, &candles, &costs);
+            (costs, summary)
+        })
+    });
+    let mut runs = join_all(runs)
+        .await
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Cost variant run failed: {}", e))?
+        .into_iter();
+
+    let (baseline_costs, baseline) = runs.next().expect("baseline run");
+    let result = |costs: BacktestCostModel, summary: &BacktestSummary| CostVariantResult {
+        label: costs.describe(),
+        cost_model: costs,
+        cagr: summary.cagr,
+        max_dd: summary.max_dd,
+        total_fees: summary.total_transaction_costs,
+        cagr_delta: summary.cagr - baseline.cagr,
+        max_dd_delta: summary.max_dd - baseline.max_dd,
+        total_fees_delta: summary.total_transaction_costs - baseline.total_transaction_costs,
+    };
+    Ok(CostSensitivityReport {
+        strategy: params.strategy.clone(),
+        symbol: params.ticker.clone(),
+        baseline: result(baseline_costs, &baseline),
+        variants: runs.map(|(costs, summary)| result(costs, &summary)).collect(),
+    })
+}
+
+/// Backtest summary over daily candles. Fewer than two candles yields an empty
+/// curve, which the frontend replaces with synthetic data.
+///
+/// TsMomentum and MeanReversion trade on their signals; any other strategy name
+/// holds the ticker from the first close to the last.
+///
+/// Strategies other than BuyHold pay the params' cost model (`cost_model`, or else
+/// `transaction_costs`) on the entry and exit trades and report the same run at 0.5x,
+/// 1x, 2x and 5x those costs, without trade logs. Every run with bars is compared against a frictionless
+/// buy & hold of the ticker.
+pub fn summarize_backtest(params: &BacktestParams, candles: &[Candle]) -> BacktestSummary {
+    let costs = params.effective_cost_model().with_estimated_spread(&params.ticker, candles);
+    let mut summary = summarize_with_costs(params, candles, &costs);
+    if candles.len() >= 2 {
+        let equities: Vec<f64> = summary.equity_curve.iter().map(|p| p.equity).collect();
+        let days = curve_span_days(&summary.equity_curve);
+        summary.benchmark = Some(BenchmarkComparison::new(&equities, summary.cagr, buy_and_hold_curve(params, candles), days));
+    }
+    if pays_transaction_costs(params) && candles.len() >= 2 {
+        summary.sensitivity_analysis = Some(
+            COST_SENSITIVITY_MULTIPLIERS
+                .iter()
+                .map(|&m| (m, summarize_with_costs(params, candles, &costs.scaled(m)).without_trades()))
+                .collect(),
+        );
+    }
+    summary
+}
+
+// BuyHold is the frictionless benchmark the other strategies are compared against
+fn pays_transaction_costs(params: &BacktestParams) -> bool {
+    !params.strategy.eq_ignore_ascii_case("BuyHold")
+}
+
+fn summarize_with_costs(params: &BacktestParams, candles: &[Candle], costs: &BacktestCostModel) -> BacktestSummary {
+    let cost_model = pays_transaction_costs(params).then(|| costs.clone());
+    if candles.len() < 2 {
+        return BacktestSummary {
+            strategy: params.strategy.clone(),
+            symbol: params.ticker.clone(),
+            start: params.start_date,
+            end: params.end_date,
+            capital: params.initial_capital,
+            cagr: 0.0,
+            trades: 0,
+            win_rate: 0.0,
+            max_dd: 0.0,
+            max_dd_realized: 0.0,
+            equity_curve: vec![], // Empty curve - frontend will detect and use synthetic data
+            missing_trading_days: 0,
+            total_transaction_costs: 0.0,
+            gross_pnl: 0.0,
+            net_pnl: 0.0,
+            sensitivity_analysis: None,
+            randomness_test: None,
+            autocorrelation_lag1: 0.0,
+            benchmark: None,
+            trade_log: Vec::new(),
+            trade_log_count: 0,
+            fills: Vec::new(),
+            id: None,
+            cost_model,
+            pair: None,
+            signal_fills: None,
+        };
+    }
+
+    if let Some(strategy) = BacktestStrategy::parse(&params.strategy) {
+        return summarize_signals(params, strategy, candles, costs);
+    }
+
+    let start_close = candles[0].close.max(1e-9);
+    let last_close = candles[candles.len() - 1].close;
+
+    // Entry at the first close and exit at the last, sized to the full starting capital
+    let (entry_cost, exit_cost) = if pays_transaction_costs(params) {
+        let shares = params.initial_capital / start_close;
+        let adv = average_daily_volume(costs, candles);
+        (
+            costs.trade_cost(&OrderSide::Buy, start_close, shares, adv, 0),
+            costs.trade_cost(&OrderSide::Sell, last_close, shares, adv, 0),
+        )
+    } else {
+        (0.0, 0.0)
+    };
+
+    // Simple buy & hold example backtest; replace with your strategy later.
+    let mut equity_curve = Vec::with_capacity(candles.len());
+
+    for (i, candle) in candles.iter().enumerate() {
+        // scale equity proportional to close/first_close, net of costs paid so far
+        let mut equity = params.initial_capital * (candle.close / start_close) - entry_cost;
+        // The position is held at cost until it is sold at the last close
+        let mut realized_equity = params.initial_capital - entry_cost;
+        if i == candles.len() - 1 {
+            equity -= exit_cost;
+            realized_equity = equity;
+        }
+        // drawdown computed later
+        equity_curve.push(EquityPoint {
+            date: candle.date(),
+            equity,
+            drawdown: 0.0,
+            rolling_vol_20d: None,
+            rolling_sharpe_20d: None,
+            realized_equity: Some(realized_equity),
+            realized_drawdown: None,
+            missing_bar: false,
+            benchmark_equity: None,
+        });
+    }
+
+    let calendar = MarketCalendar::new();
+    let mut equity_curve = align_to_calendar(equity_curve, &calendar);
+    let missing_trading_days = missing_trading_days(params, &equity_curve, &calendar);
+    let equities: Vec<f64> = equity_curve.iter().map(|p| p.equity).collect();
+    let (max_dd, max_dd_realized) = fill_drawdowns(&mut equity_curve);
+    fill_rolling_stats(&mut equity_curve);
+
+    // Daily positive return as a proxy for "win"
+    let mut wins = 0u32;
+    let mut trades = 0u32;
+    for pair in candles.windows(2) {
+        let r = (pair[1].close / pair[0].close) - 1.0;
+        trades += 1;
+        if r > 0.0 {
+            wins += 1;
+        }
+    }
+    let win_rate = (wins as f64) / (trades as f64);
+
+    let total_transaction_costs = entry_cost + exit_cost;
+    let gross_pnl = params.initial_capital * (last_close / start_close) - params.initial_capital;
+    let cagr = annualized_cagr(params.initial_capital, equity_curve.last().unwrap().equity, curve_span_days(&equity_curve));
+
+    BacktestSummary {
+        strategy: params.strategy.clone(),
+        symbol: params.ticker.clone(),
+        start: params.start_date,
+        end: params.end_date,
+        capital: params.initial_capital,
+        cagr,
+        trades,
+        win_rate,
+        max_dd,
+        max_dd_realized,
+        equity_curve,
+        missing_trading_days,
+        total_transaction_costs,
+        gross_pnl,
+        net_pnl: gross_pnl - total_transaction_costs,
+        sensitivity_analysis: None,
+        randomness_test: Some(runs_test(&equities)),
+        autocorrelation_lag1: autocorrelation_lag1(&equities),
+        benchmark: None,
+        trade_log: Vec::new(),
+        trade_log_count: 0,
+        fills: Vec::new(),
+        id: None,
+        cost_model,
+        pair: None,
+        signal_fills: None,
+    }
+}
+
+/// Run a long/flat strategy's signals with broker-style accounting: whole shares
+/// bought with all available cash, commission charged on its schedule and slippage
+/// taken on the fill price. A position still open at the last close is sold there.
+/// Limit and stop entries work from the bar after their signal for
+/// `expire_after_bars` bars; limit fills pay commission but no slippage.
+fn summarize_signals(
+    params: &BacktestParams,
+    strategy: BacktestStrategy,
+    candles: &[Candle],
+    costs: &BacktestCostModel,
+) -> BacktestSummary {
+    let closes: Vec<(NaiveDate, f64)> = candles.iter().map(|c| (c.date(), c.close)).collect();
+    let mut signals = strategy.signals(&closes).into_iter().peekable();
+    let adv = average_daily_volume(costs, candles);
+
+    let mut cash = params.initial_capital;
+    let mut shares = 0i64;
+    let mut entry_value = 0.0; // Cash paid for the open position, costs included
+    let mut fills: Vec<Trade> = Vec::new();
+    let mut total_costs = 0.0;
+    let (mut round_trips, mut wins) = (0u32, 0u32);
+    let mut equities = Vec::with_capacity(candles.len());
+    let mut realized_equities = Vec::with_capacity(candles.len()); // Open position at what was paid for it
+    let config = &params.signal_orders;
+    let mut working: Option<(WorkingEntry, usize, &'static str)> = None; // Order, signal bar, reason
+    let mut intended_prices: HashMap<String, f64> = HashMap::new(); // Entry fill id -> price its signal asked for
+    let mut signal_fills = SignalFillStats::default();
+
+    for (i, candle) in candles.iter().enumerate() {
+        let last_bar = i == candles.len() - 1;
+        // (side, reason, fill price, intended price, filled at a limit)
+        let mut orders: Vec<(OrderSide, &'static str, f64, f64, bool)> = Vec::new();
+        if let Some((order, placed_at, reason)) = working.take() {
+            match order.fill_price(candle, config.touch_rule) {
+                Some(price) => orders.push((OrderSide::Buy, reason, price, order.price(), matches!(order, WorkingEntry::Limit(_)))),
+                None if i - placed_at >= config.expire_after_bars.max(1) => signal_fills.expired += 1,
+                None => working = Some((order, placed_at, reason)),
+            }
+        }
+        while let Some(signal) = signals.next_if(|s| s.index == i) {
+            if signal.side == OrderSide::Buy {
+                signal_fills.entry_signals += 1;
+            }
+            if working.take().is_some() {
+                signal_fills.canceled += 1;
+            }
+            match (&signal.side, config.entry.working_entry(candle)) {
+                (OrderSide::Buy, Some(order)) => working = Some((order, i, signal.reason)),
+                _ => orders.push((signal.side, signal.reason, candle.close, candle.close, false)),
+            }
+        }
+        if last_bar {
+            if working.take().is_some() {
+                signal_fills.canceled += 1;
+            }
+            if !orders.iter().any(|(side, ..)| *side == OrderSide::Sell) {
+                orders.push((OrderSide::Sell, "end_of_backtest", candle.close, candle.close, false));
+            }
+        }
+
+        for (side, reason, price, intended_price, at_limit) in orders {
+            let trailing_shares = trailing_share_volume(&fills, candle.timestamp);
+            let quantity = match side {
+                OrderSide::Buy if shares == 0 && price > 0.0 => affordable_shares(cash, price, costs, adv, trailing_shares),
+                OrderSide::Sell => shares,
+                _ => 0,
+            };
+            if quantity == 0 {
+                continue;
+            }
+            let fill = if at_limit {
+                BacktestFill::at_limit(costs, &side, price, quantity, trailing_shares)
+            } else {
+                BacktestFill::new(costs, &side, price, quantity, adv, trailing_shares)
+            };
+            total_costs += fill.commission + fill.slippage;
+            let net_amount = fill.net_amount;
+            cash += net_amount;
+            match side {
+                OrderSide::Buy => {
+                    shares = quantity;
+                    entry_value = -net_amount;
+                }
+                OrderSide::Sell => {
+                    shares = 0;
+                    round_trips += 1;
+                    if net_amount > entry_value {
+                        wins += 1;
+                    }
+                }
+            }
+            let trade = fill.trade(fills.len(), &params.ticker, side, quantity, candle.timestamp, reason);
+            if trade.side == OrderSide::Buy {
+                signal_fills.filled += 1;
+                intended_prices.insert(trade.id.clone(), intended_price);
+            }
+            fills.push(trade);
+        }
+        equities.push(cash + shares as f64 * candle.close);
+        realized_equities.push(if shares > 0 { cash + entry_value } else { cash });
+    }
+
+    let equity_curve: Vec<EquityPoint> = candles
+        .iter()
+        .zip(equities.iter().zip(&realized_equities))
+        .map(|(candle, (equity, realized_equity))| EquityPoint {
+            date: candle.date(),
+            equity: *equity,
+            drawdown: 0.0,
+            rolling_vol_20d: None,
+            rolling_sharpe_20d: None,
+            realized_equity: Some(*realized_equity),
+            realized_drawdown: None,
+            missing_bar: false,
+            benchmark_equity: None,
+        })
+        .collect();
+    let calendar = MarketCalendar::new();
+    let mut equity_curve = align_to_calendar(equity_curve, &calendar);
+    let missing_trading_days = missing_trading_days(params, &equity_curve, &calendar);
+    let equities: Vec<f64> = equity_curve.iter().map(|p| p.equity).collect();
+    let (max_dd, max_dd_realized) = fill_drawdowns(&mut equity_curve);
+    fill_rolling_stats(&mut equity_curve);
+
+    let final_equity = *equities.last().unwrap_or(&params.initial_capital);
+    let net_pnl = final_equity - params.initial_capital;
+    let mut trade_log = backtest_trades(&fills, &[(params.ticker.as_str(), candles)]);
+    for trade in &mut trade_log {
+        trade.intended_entry_price = intended_prices.get(&trade.entry_trade_id).copied();
+    }
+    if signal_fills.entry_signals > 0 {
+        signal_fills.conversion_rate = signal_fills.filled as f64 / signal_fills.entry_signals as f64;
+    }
+    BacktestSummary {
+        strategy: params.strategy.clone(),
+        symbol: params.ticker.clone(),
+        start: params.start_date,
+        end: params.end_date,
+        capital: params.initial_capital,
+        cagr: annualized_cagr(params.initial_capital, final_equity, curve_span_days(&equity_curve)),
+        trades: round_trips,
+        win_rate: if round_trips > 0 { wins as f64 / round_trips as f64 } else { 0.0 },
+        max_dd,
+        max_dd_realized,
+        equity_curve,
+        missing_trading_days,
+        total_transaction_costs: total_costs,
+        gross_pnl: net_pnl + total_costs,
+        net_pnl,
+        sensitivity_analysis: None,
+        randomness_test: Some(runs_test(&equities)),
+        autocorrelation_lag1: autocorrelation_lag1(&equities),
+        benchmark: None,
+        trade_log_count: trade_log.len(),
+        trade_log,
+        fills,
+        id: None,
+        cost_model: Some(costs.clone()),
+        pair: None,
+        signal_fills: Some(signal_fills),
+    }
+}
+
+/// One backtest fill at a bar's close: slippage moves the price against the order and
+/// commission is charged on the schedule
+struct BacktestFill {
+    price: f64,
+    commission: f64,
+    slippage: f64,
+    net_amount: f64, // Cash received (sells) or paid (buys, negative)
+}
+
+impl BacktestFill {
+    fn new(costs: &BacktestCostModel, side: &OrderSide, close: f64, quantity: i64, adv: f64, trailing_shares: i64) -> Self {
+        let commission = costs.commission(side, close, quantity as f64, trailing_shares);
+        let slippage = costs.slippage(side, close, quantity as f64, adv);
+        let price = match side {
+            OrderSide::Buy => close + slippage / quantity as f64,
+            OrderSide::Sell => close - slippage / quantity as f64,
+        };
+        let net_amount = match side {
+            OrderSide::Buy => -(price * quantity as f64 + commission),
+            OrderSide::Sell => price * quantity as f64 - commission,
+        };
+        Self { price, commission, slippage, net_amount }
+    }
+
+    /// A limit order's fill at its limit: commission only, no slippage
+    fn at_limit(costs: &BacktestCostModel, side: &OrderSide, price: f64, quantity: i64, trailing_shares: i64) -> Self {
+        let commission = costs.commission(side, price, quantity as f64, trailing_shares);
+        let net_amount = match side {
+            OrderSide::Buy => -(price * quantity as f64 + commission),
+            OrderSide::Sell => price * quantity as f64 - commission,
+        };
+        Self { price, commission, slippage: 0.0, net_amount }
+    }
+
+    /// Journal entry for the fill, numbered after `prior_trades`
+    fn trade(&self, prior_trades: usize, symbol: &str, side: OrderSide, quantity: i64, timestamp: i64, reason: &str) -> Trade {
+        Trade {
+            id: format!("bt-{}", prior_trades + 1),
+            symbol: symbol.to_string(),
+            side,
+            quantity,
+            price: self.price,
+            timestamp,
+            order_id: format!("bt-order-{}", prior_trades + 1),
+            commission: self.commission,
+            net_amount: self.net_amount,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            leg_number: None,
+            assignment_id: None,
+            tag: Some(reason.to_string()),
+            evaluation_id: None,
+        }
+    }
+}
+
+/// Round trips of `fills`, with excursions tracked through each symbol's bars
+fn backtest_trades(fills: &[Trade], bars: &[(&str, &[Candle])]) -> Vec<BacktestTrade> {
+    let mut trades = round_trips(fills, &[]);
+    for trade in &mut trades {
+        if let Some((_, candles)) = bars.iter().find(|(symbol, _)| *symbol == trade.symbol) {
+            trade.track_excursions(candles);
+        }
+    }
+    trades
+}
+
+/// Shares traded in the commission tier window before `timestamp`
+fn trailing_share_volume(fills: &[Trade], timestamp: i64) -> i64 {
+    fills
+        .iter()
+        .filter(|t| timestamp - t.timestamp < COMMISSION_TIER_WINDOW_SECONDS)
+        .map(|t| t.quantity)
+        .sum()
+}
+
+/// Mean daily volume of `candles`, unless the cost model fixes ADV
+fn average_daily_volume(costs: &BacktestCostModel, candles: &[Candle]) -> f64 {
+    if costs.adv > 0.0 || candles.is_empty() {
+        costs.adv
+    } else {
+        candles.iter().map(|c| c.volume as f64).sum::<f64>() / candles.len() as f64
+    }
+}
+
+/// Pair backtest over both legs' daily candles aligned by date. Entries put half the
+/// equity in the first leg and the hedge ratio times that in the second; both legs fill
+/// at the same close and are closed together, at the latest on the last bar. Reported
+/// at 0.5x, 1x, 2x and 5x the params' costs like single-symbol strategies.
+pub fn summarize_pair_backtest(params: &BacktestParams, pair: &PairConfig, first: &[Candle], second: &[Candle]) -> BacktestSummary {
+    let costs = params.effective_cost_model();
+    // Each leg crosses its own estimated spread
+    let legs = [costs.with_estimated_spread(&pair.first, first), costs.with_estimated_spread(&pair.second, second)];
+    let mut summary = summarize_pair_with_costs(params, pair, first, second, &costs, &legs);
+    if summary.equity_curve.len() >= 2 {
+        summary.sensitivity_analysis = Some(
+            COST_SENSITIVITY_MULTIPLIERS
+                .iter()
+                .map(|&m| {
+                    let scaled = summarize_pair_with_costs(params, pair, first, second, &costs.scaled(m), &legs.each_ref().map(|c| c.scaled(m)));
+                    (m, scaled.without_trades())
+                })
+                .collect(),
+        );
+    }
+    summary
+}
+
+/// An open pair position in a backtest
+struct OpenPair {
+    direction: PairDirection,
+    entry: usize,
+    quantities: [i64; 2],
+    entry_flows: [f64; 2],
+}
+
+fn summarize_pair_with_costs(
+    params: &BacktestParams,
+    pair: &PairConfig,
+    first: &[Candle],
+    second: &[Candle],
+    costs: &BacktestCostModel,
+    leg_costs: &[BacktestCostModel; 2],
+) -> BacktestSummary {
+    let label = pair.label();
+    let daily_closes = |candles: &[Candle]| candles.iter().map(|c| (c.date(), c.close)).collect::<Vec<_>>();
+    let closes = align_closes(&daily_closes(first), &daily_closes(second));
+    if closes.len() < 2 {
+        let mut empty = summarize_with_costs(params, &[], costs);
+        empty.symbol = label;
+        return empty;
+    }
+    let timestamps: HashMap<NaiveDate, i64> = first.iter().map(|c| (c.date(), c.timestamp)).collect();
+    let symbols = [pair.first.as_str(), pair.second.as_str()];
+    let advs = [average_daily_volume(costs, first), average_daily_volume(costs, second)];
+    let zscores = spread_zscores(pair, &closes);
+    let mut signals = pair_signals(pair, &closes).into_iter().peekable();
+
+    let mut cash = params.initial_capital;
+    let mut held = [0i64; 2];
+    let mut open: Option<OpenPair> = None;
+    let mut fills: Vec<Trade> = Vec::new();
+    let mut legs: Vec<PairLegSummary> = symbols
+        .iter()
+        .map(|symbol| PairLegSummary { symbol: symbol.to_string(), trades: 0, pnl: 0.0, transaction_costs: 0.0 })
+        .collect();
+    let mut round_trips: Vec<PairRoundTrip> = Vec::new();
+    let mut equities = Vec::with_capacity(closes.len());
+    let mut realized_equities = Vec::with_capacity(closes.len());
+
+    for (i, bar) in closes.iter().enumerate() {
+        let prices = [bar.first, bar.second];
+        let timestamp = timestamps[&bar.date];
+        let mut actions: Vec<(PairAction, &str)> = Vec::new();
+        while let Some(signal) = signals.next_if(|s| s.index == i) {
+            actions.push((signal.action, signal.reason));
+        }
+        if i == closes.len() - 1 && open.is_some() && !actions.iter().any(|(a, _)| *a == PairAction::Close) {
+            actions.push((PairAction::Close, "end_of_backtest"));
+        }
+
+        for (action, reason) in actions {
+            // Signed share change per leg
+            let orders = match (action, &open) {
+                (PairAction::Open(direction), None) => {
+                    let (a, b) = leg_quantities(cash / 2.0, pair.hedge_ratio(&closes, i), prices[0], prices[1]);
+                    if a == 0 || b == 0 {
+                        continue;
+                    }
+                    match direction {
+                        PairDirection::LongSpread => [a, -b],
+                        PairDirection::ShortSpread => [-a, b],
+                    }
+                }
+                (PairAction::Close, Some(_)) => [-held[0], -held[1]],
+                _ => continue,
+            };
+
+            let mut flows = [0.0; 2];
+            for leg in 0..2 {
+                let (side, quantity) = if orders[leg] > 0 { (OrderSide::Buy, orders[leg]) } else { (OrderSide::Sell, -orders[leg]) };
+                let fill = BacktestFill::new(&leg_costs[leg], &side, prices[leg], quantity, advs[leg], trailing_share_volume(&fills, timestamp));
+                cash += fill.net_amount;
+                held[leg] += orders[leg];
+                flows[leg] = fill.net_amount;
+                legs[leg].trades += 1;
+                legs[leg].transaction_costs += fill.commission + fill.slippage;
+                fills.push(fill.trade(fills.len(), symbols[leg], side, quantity, timestamp, reason));
+            }
+
+            match (action, open.take()) {
+                (PairAction::Open(direction), _) => {
+                    open = Some(OpenPair { direction, entry: i, quantities: orders, entry_flows: flows });
+                }
+                (PairAction::Close, Some(entry)) => {
+                    let pnl = [entry.entry_flows[0] + flows[0], entry.entry_flows[1] + flows[1]];
+                    legs[0].pnl += pnl[0];
+                    legs[1].pnl += pnl[1];
+                    round_trips.push(PairRoundTrip {
+                        direction: entry.direction,
+                        entry_date: closes[entry.entry].date,
+                        exit_date: bar.date,
+                        entry_z: zscores[entry.entry].unwrap_or(0.0),
+                        exit_z: zscores[i],
+                        exit_reason: reason.to_string(),
+                        first_quantity: entry.quantities[0],
+                        second_quantity: entry.quantities[1],
+                        first_pnl: pnl[0],
+                        second_pnl: pnl[1],
+                        spread_pnl: pnl[0] + pnl[1],
+                    });
+                }
+                (PairAction::Close, None) => {}
+            }
+        }
+        equities.push(cash + held[0] as f64 * prices[0] + held[1] as f64 * prices[1]);
+        // Undo the open legs' entry cash flows to hold them at cost
+        realized_equities.push(cash - open.as_ref().map_or(0.0, |o| o.entry_flows[0] + o.entry_flows[1]));
+    }
+
+    let equity_curve: Vec<EquityPoint> = closes
+        .iter()
+        .zip(equities.iter().zip(&realized_equities))
+        .map(|(bar, (equity, realized_equity))| EquityPoint {
+            date: bar.date,
+            equity: *equity,
+            drawdown: 0.0,
+            rolling_vol_20d: None,
+            rolling_sharpe_20d: None,
+            realized_equity: Some(*realized_equity),
+            realized_drawdown: None,
+            missing_bar: false,
+            benchmark_equity: None,
+        })
+        .collect();
+    // A date either leg lacks counts as missing
+    let calendar = MarketCalendar::new();
+    let mut equity_curve = align_to_calendar(equity_curve, &calendar);
+    let missing_trading_days = missing_trading_days(params, &equity_curve, &calendar);
+    let equities: Vec<f64> = equity_curve.iter().map(|p| p.equity).collect();
+    let (max_dd, max_dd_realized) = fill_drawdowns(&mut equity_curve);
+    fill_rolling_stats(&mut equity_curve);
+
+    let final_equity = *equities.last().unwrap_or(&params.initial_capital);
+    let net_pnl = final_equity - params.initial_capital;
+    let total_costs: f64 = legs.iter().map(|l| l.transaction_costs).sum();
+    let wins = round_trips.iter().filter(|r| r.spread_pnl > 0.0).count();
+    let trade_log = backtest_trades(&fills, &[(symbols[0], first), (symbols[1], second)]);
+    BacktestSummary {
+        strategy: params.strategy.clone(),
+        symbol: label.clone(),
+        start: params.start_date,
+        end: params.end_date,
+        capital: params.initial_capital,
+        cagr: annualized_cagr(params.initial_capital, final_equity, curve_span_days(&equity_curve)),
+        trades: round_trips.len() as u32,
+        win_rate: if round_trips.is_empty() { 0.0 } else { wins as f64 / round_trips.len() as f64 },
+        max_dd,
+        max_dd_realized,
+        equity_curve,
+        missing_trading_days,
+        total_transaction_costs: total_costs,
+        gross_pnl: net_pnl + total_costs,
+        net_pnl,
+        sensitivity_analysis: None,
+        randomness_test: Some(runs_test(&equities)),
+        autocorrelation_lag1: autocorrelation_lag1(&equities),
+        benchmark: None,
+        trade_log_count: trade_log.len(),
+        trade_log,
+        fills,
+        id: None,
+        cost_model: Some(costs.clone()),
+        pair: Some(PairBacktestDetail {
+            label,
+            spread: pair.spread,
+            sizing: pair.sizing,
+            spread_pnl: round_trips.iter().map(|r| r.spread_pnl).sum(),
+            legs,
+            round_trips,
+        }),
+        signal_fills: None,
+    }
+}
+
+/// Whole shares whose price and transaction costs fit in `cash`
+fn affordable_shares(cash: f64, price: f64, costs: &BacktestCostModel, adv: f64, trailing_shares: i64) -> i64 {
+    let mut quantity = (cash / price).floor() as i64;
+    while quantity > 0
+        && quantity as f64 * price + costs.trade_cost(&OrderSide::Buy, price, quantity as f64, adv, trailing_shares) > cash
+    {
+        quantity -= 1;
+    }
+    quantity.max(0)
+}
+
+/// Buy & hold of the full starting capital at the first close, without costs, aligned
+/// to the trading calendar like the strategy's curve
+fn buy_and_hold_curve(params: &BacktestParams, candles: &[Candle]) -> Vec<EquityPoint> {
+    let start_close = candles[0].close.max(1e-9);
+    let curve: Vec<EquityPoint> = candles
+        .iter()
+        .map(|candle| EquityPoint {
+            date: candle.date(),
+            equity: params.initial_capital * (candle.close / start_close),
+            drawdown: 0.0,
+            rolling_vol_20d: None,
+            rolling_sharpe_20d: None,
+            realized_equity: None,
+            realized_drawdown: None,
+            missing_bar: false,
+            benchmark_equity: None,
+        })
+        .collect();
+    let mut curve = align_to_calendar(curve, &MarketCalendar::new());
+    fill_drawdowns(&mut curve);
+    fill_rolling_stats(&mut curve);
+    curve
+}
+
+// Helper function to generate synthetic equity curve
+pub fn generate_deterministic_equity_curve(days: usize, start_equity: f64, seed: u64) -> Vec<EquityPoint> {
+    // Simple LCG for deterministic random numbers
+    let mut rng = SimRng::new(seed);
+
+    let mut equity = start_equity;
+    let mut max_equity = start_equity;
+    let mut curve = Vec::with_capacity(days);
+
+    for i in 0..days {
+        // Generate deterministic return
+        let rand_val = rng.next_u15() as f64 / 32767.0; // 0 to 1
+        let daily_return = 0.0006 + (rand_val - 0.5) * 0.02; // ~0.06% avg with volatility
+
+        equity *= 1.0 + daily_return;
+        max_equity = max_equity.max(equity);
+        let drawdown = (equity - max_equity) / max_equity;
+
+        curve.push(EquityPoint {
+            date: NaiveDate::from_ymd_opt(2023, (i % 12) as u32 + 1, (i % 28) as u32 + 1).unwrap_or_default(),
+            equity,
+            drawdown,
+            rolling_vol_20d: None,
+            rolling_sharpe_20d: None,
+            realized_equity: None,
+            realized_drawdown: None,
+            missing_bar: false,
+            benchmark_equity: None,
+        });
+    }
+
+    curve
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_stats_start_after_twenty_points() {
+        let mut curve = generate_deterministic_equity_curve(60, 100_000.0, 7);
+        fill_rolling_stats(&mut curve);
+
+        assert!(curve[..19].iter().all(|p| p.rolling_vol_20d.is_none() && p.rolling_sharpe_20d.is_none()));
+        assert!(curve[19..].iter().all(|p| p.rolling_vol_20d.is_some() && p.rolling_sharpe_20d.is_some()));
+        // ~1% daily noise annualizes to roughly 10-20%
+        let vol = curve[40].rolling_vol_20d.unwrap();
+        assert!(vol > 0.05 && vol < 0.3, "vol {}", vol);
+    }
+
+    #[test]
+    fn test_rolling_stats_constant_growth_and_short_curves() {
+        let mut curve: Vec<EquityPoint> = (0..25)
+            .map(|i| EquityPoint {
+                date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + chrono::Duration::days(i as i64),
+                equity: 100.0 * 1.001_f64.powi(i),
+                drawdown: 0.0,
+                rolling_vol_20d: None,
+                rolling_sharpe_20d: None,
+                realized_equity: None,
+                realized_drawdown: None,
+                missing_bar: false,
+                benchmark_equity: None,
+            })
+            .collect();
+        fill_rolling_stats(&mut curve);
+        assert!(curve[24].rolling_vol_20d.unwrap() < 1e-9);
+        assert_eq!(curve[24].rolling_sharpe_20d, Some(0.0));
+
+        let mut short = curve[..10].to_vec();
+        fill_rolling_stats(&mut short);
+        assert!(short.iter().all(|p| p.rolling_vol_20d.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_sample_backtest_has_rolling_stats() {
+        let sample = get_sample_backtest_result().await.0;
+        assert_eq!(sample.equity_curve.len(), 252);
+        let benchmark = sample.benchmark.as_ref().unwrap();
+        assert_eq!(benchmark.equity_curve.len(), 252);
+        assert!((benchmark.alpha - (sample.cagr - benchmark.beta * benchmark.cagr)).abs() < 1e-12);
+        assert!(sample.equity_curve[18].rolling_sharpe_20d.is_none());
+        assert!(sample.equity_curve[19].rolling_sharpe_20d.is_some());
+    }
+
+    fn trending_candles(days: i64) -> Vec<Candle> {
+        (0..days)
+            .map(|i| {
+                let close = 100.0 * 1.001_f64.powi(i as i32);
+                Candle::new(1704153600 + i * 86400, close, close, close, close, 2_000_000)
+            })
+            .collect()
+    }
+
+    fn params(strategy: &str, transaction_costs: TransactionCostModel) -> BacktestParams {
+        BacktestParams {
+            ticker: "SPY".into(),
+            start_date: NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+            strategy: strategy.into(),
+            initial_capital: 1_000_000.0,
+            seed: None,
+            warm_job_id: None,
+            transaction_costs,
+            cost_model: None,
+            pair: None,
+            signal_orders: SignalOrderConfig::default(),
+        }
+    }
+
+    #[test]
+    fn test_transaction_costs_reduce_cagr() {
+        let costs = TransactionCostModel { commission_per_share: 0.005, spread_bps: 2.0, market_impact_coefficient: 0.1, adv: 0.0 };
+        let candles = trending_candles(250);
+
+        let free = summarize_backtest(&params("PMCC", TransactionCostModel::default()), &candles);
+        let costly = summarize_backtest(&params("PMCC", costs.clone()), &candles);
+        assert_eq!(free.total_transaction_costs, 0.0);
+        assert!(costly.total_transaction_costs > 0.0);
+        assert!(costly.cagr < free.cagr);
+        assert_eq!(costly.gross_pnl, free.gross_pnl);
+        assert_eq!(free.randomness_test.as_ref().map(|r| (r.n_runs, r.is_random)), Some((1, false)));
+        assert!((costly.net_pnl - (costly.gross_pnl - costly.total_transaction_costs)).abs() < 1e-6);
+        assert!((costly.equity_curve.last().unwrap().equity - (1_000_000.0 + costly.net_pnl)).abs() < 1e-6);
+
+        // Entry of 10,000 shares against 2M ADV: impact is 0.1 * sqrt(10_000 / 200_000) of price per share
+        let entry = costs.trade_cost(100.0, 10_000.0, 2_000_000.0);
+        let expected = 50.0 + 100.0 + 100.0 * 0.1 * 0.05_f64.sqrt() * 10_000.0;
+        assert!((entry - expected).abs() < 1e-6);
+
+        let sensitivity = costly.sensitivity_analysis.as_ref().unwrap();
+        assert_eq!(sensitivity.iter().map(|(m, _)| *m).collect::<Vec<_>>(), vec![0.5, 1.0, 2.0, 5.0]);
+        assert!(sensitivity.windows(2).all(|w| w[1].1.cagr < w[0].1.cagr));
+        assert_eq!(sensitivity[1].1.cagr, costly.cagr);
+
+        // Buy & hold stays the frictionless benchmark
+        let buy_hold = summarize_backtest(&params("BuyHold", costs), &candles);
+        assert_eq!((buy_hold.total_transaction_costs, buy_hold.cagr), (0.0, free.cagr));
+        assert!(buy_hold.sensitivity_analysis.is_none());
+    }
+
+    #[test]
+    fn test_buy_and_hold_benchmark_alpha_and_beta() {
+        // Buy & hold against itself: beta 1, no alpha
+        let candles: Vec<Candle> = [100.0, 102.0, 99.0, 103.0, 101.0, 106.0]
+            .iter()
+            .enumerate()
+            .map(|(i, close)| Candle::new(1704153600 + i as i64 * 86400, *close, *close, *close, *close, 1_000_000))
+            .collect();
+        let buy_hold = summarize_backtest(&params("BuyHold", TransactionCostModel::default()), &candles);
+        let benchmark = buy_hold.benchmark.as_ref().unwrap();
+        assert!((benchmark.beta - 1.0).abs() < 1e-12 && (benchmark.correlation - 1.0).abs() < 1e-12);
+        assert!(benchmark.alpha.abs() < 1e-12);
+        assert_eq!((benchmark.cagr, benchmark.max_dd), (buy_hold.cagr, buy_hold.max_dd));
+        assert_eq!(benchmark.equity_curve.last().unwrap().equity, 1_060_000.0);
+
+        // A cost-paying run lags the benchmark by its costs
+        let costs = TransactionCostModel { commission_per_share: 0.01, spread_bps: 5.0, ..Default::default() };
+        let costly = summarize_backtest(&params("PMCC", costs), &candles);
+        let benchmark = costly.benchmark.as_ref().unwrap();
+        assert!((benchmark.alpha - (costly.cagr - benchmark.beta * benchmark.cagr)).abs() < 1e-12);
+        assert!(benchmark.alpha < 0.0);
+        assert!(benchmark.correlation > 0.99);
+        assert!(costly.sensitivity_analysis.unwrap().iter().all(|(_, s)| s.benchmark.is_none()));
+
+        assert!(summarize_backtest(&params("BuyHold", TransactionCostModel::default()), &candles[..1]).benchmark.is_none());
+    }
+
+    #[test]
+    fn test_mean_reversion_records_trades_with_costs() {
+        // Quiet range, a drop below the lower band, then a rally through the upper band
+        let mut closes: Vec<f64> = (0..25).map(|i| if i % 2 == 0 { 100.5 } else { 99.5 }).collect();
+        closes.extend([90.0, 95.0, 100.0, 112.0, 111.0, 80.0, 79.0]);
+        let candles: Vec<Candle> = closes
+            .iter()
+            .enumerate()
+            .map(|(i, close)| Candle::new(1704153600 + i as i64 * 86400, *close, *close, *close, *close, 1_000_000))
+            .collect();
+        let costs = TransactionCostModel { commission_per_share: 0.01, spread_bps: 10.0, ..Default::default() };
+
+        let summary = summarize_backtest(&params("MeanReversion", costs), &candles);
+        let trades = &summary.fills;
+        assert_eq!(
+            trades.iter().map(|t| (t.side.clone(), t.timestamp, t.tag.as_deref().unwrap())).collect::<Vec<_>>(),
+            vec![
+                (OrderSide::Buy, candles[25].timestamp, "below_lower_band"),
+                (OrderSide::Sell, candles[28].timestamp, "above_upper_band"),
+                (OrderSide::Buy, candles[30].timestamp, "below_lower_band"),
+                (OrderSide::Sell, candles[31].timestamp, "end_of_backtest"),
+            ]
+        );
+        // Whole shares, filled through half the spread
+        assert_eq!(trades[0].quantity, 11_104);
+        assert!((trades[0].price - 90.0 * 1.0005).abs() < 1e-9);
+        assert!((trades[1].price - 112.0 * 0.9995).abs() < 1e-9);
+        assert!((trades[0].commission - 111.04).abs() < 1e-9);
+        assert_eq!((summary.trades, summary.win_rate), (2, 0.5));
+
+        let cash_flow: f64 = trades.iter().map(|t| t.net_amount).sum();
+        assert!((summary.net_pnl - cash_flow).abs() < 1e-6);
+        assert!((summary.gross_pnl - summary.net_pnl - summary.total_transaction_costs).abs() < 1e-6);
+        assert!((summary.equity_curve.last().unwrap().equity - (1_000_000.0 + summary.net_pnl)).abs() < 1e-6);
+        // Flat before the first entry
+        assert!(summary.equity_curve[..25].iter().all(|p| p.equity == 1_000_000.0));
+        assert_eq!(summary.sensitivity_analysis.as_ref().unwrap().len(), 4);
+
+        // Round trips carry the signals and the excursions of the closes held through
+        let log = &summary.trade_log;
+        assert_eq!(summary.trade_log_count, 2);
+        assert_eq!(
+            log.iter().map(|t| (t.entry_reason.as_deref().unwrap(), t.exit_reason.as_deref().unwrap(), t.holding_days)).collect::<Vec<_>>(),
+            vec![("below_lower_band", "above_upper_band", 3), ("below_lower_band", "end_of_backtest", 1)]
+        );
+        assert_eq!((log[0].quantity, log[0].entry_price, log[0].exit_price), (11_104, trades[0].price, trades[1].price));
+        assert!((log[0].pnl - (trades[0].net_amount + trades[1].net_amount)).abs() < 1e-6);
+        assert!((log[0].return_pct - log[0].pnl / (trades[0].price * 11_104.0)).abs() < 1e-12);
+        assert_eq!(log[0].mae, Some(0.0)); // Never below the entry
+        assert!((log[0].mfe.unwrap() - (112.0 / trades[0].price - 1.0)).abs() < 1e-12);
+        assert!((log[1].mae.unwrap() - (79.0 / trades[2].price - 1.0)).abs() < 1e-12 && log[1].mfe == Some(0.0));
+        assert!(log[1].pnl < 0.0);
+
+        let lean = summary.clone().without_trades();
+        assert!(lean.trade_log.is_empty() && lean.fills.is_empty());
+        assert_eq!((lean.trade_log_count, lean.trades), (2, 2));
+        assert!(summary.sensitivity_analysis.unwrap().iter().all(|(_, s)| s.trade_log.is_empty() && s.trade_log_count == 2));
+    }
+    #[test]
+    fn test_cost_model_overrides_transaction_costs() {
+        let candles = trending_candles(250);
+        let legacy = TransactionCostModel { commission_per_share: 0.005, spread_bps: 2.0, market_impact_coefficient: 0.1, adv: 0.0 };
+        let as_model = BacktestParams { cost_model: Some(BacktestCostModel::from(&legacy)), ..params("PMCC", TransactionCostModel::default()) };
+        let from_legacy = summarize_backtest(&params("PMCC", legacy.clone()), &candles);
+        assert_eq!(summarize_backtest(&as_model, &candles).cagr, from_legacy.cagr);
+        assert_eq!(from_legacy.cost_model, Some(BacktestCostModel::from(&legacy)));
+
+        // A free cost model wins over the legacy costs
+        let free = BacktestParams { cost_model: Some(BacktestCostModel::default()), ..params("PMCC", legacy) };
+        assert_eq!(summarize_backtest(&free, &candles).total_transaction_costs, 0.0);
+
+        // Per-side rates: 20 bps slippage on sells only, $1 minimum commission
+        let costs = BacktestCostModel {
+            commission_per_share: 0.001,
+            min_commission: 1.0,
+            sell: SideCostOverride { slippage_bps: Some(20.0), ..Default::default() },
+            ..Default::default()
+        };
+        assert_eq!(costs.commission(&OrderSide::Buy, 50.0, 100.0, 0), 1.0);
+        assert_eq!(costs.slippage(&OrderSide::Buy, 50.0, 100.0, 0.0), 0.0);
+        assert!((costs.slippage(&OrderSide::Sell, 50.0, 100.0, 0.0) - 10.0).abs() < 1e-9);
+        assert_eq!(costs.describe(), "PerShare $0.0010/sh + $0.00/trade, 0.0 bps slippage (sell overrides)");
+        let flat = BacktestCostModel { commission_schedule: CommissionSchedule::Flat, ..costs };
+        assert_eq!(flat.commission(&OrderSide::Buy, 50.0, 100.0, 0), crate::engine::types::FLAT_COMMISSION_PER_TRADE);
+        assert!(summarize_backtest(&params("BuyHold", TransactionCostModel::default()), &candles).cost_model.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cost_sensitivity_reports_deltas_per_variant() {
+        let candles: Vec<Candle> = {
+            let mut closes: Vec<f64> = (0..25).map(|i| if i % 2 == 0 { 100.5 } else { 99.5 }).collect();
+            closes.extend([90.0, 95.0, 100.0, 112.0, 111.0]);
+            closes
+                .iter()
+                .enumerate()
+                .map(|(i, close)| Candle::new(1704153600 + i as i64 * 86400, *close, *close, *close, *close, 1_000_000))
+                .collect()
+        };
+        let baseline = TransactionCostModel { commission_per_share: 0.005, ..Default::default() };
+        let variants = vec![
+            BacktestCostModel { label: Some("zero".into()), ..Default::default() },
+            BacktestCostModel { slippage_bps: 20.0, ..BacktestCostModel::from(&baseline) },
+            BacktestCostModel { fill_model: FillModel::Slippage, slippage_bps: 20.0, ..BacktestCostModel::from(&baseline) },
+        ];
+
+        let report = compare_cost_models(params("MeanReversion", baseline), Arc::new(candles), variants, &[]).await.unwrap();
+        assert_eq!((report.baseline.cagr_delta, report.baseline.total_fees_delta), (0.0, 0.0));
+        assert_eq!(report.variants.iter().map(|v| v.label.as_str()).collect::<Vec<_>>()[0], "zero");
+        assert_eq!(report.variants[0].total_fees, 0.0);
+        assert!(report.variants[0].cagr_delta > 0.0);
+        assert!((report.variants[0].total_fees_delta + report.baseline.total_fees).abs() < 1e-9);
+        // Slippage bps only apply under the Slippage fill model
+        assert_eq!(report.variants[1].cagr, report.baseline.cagr);
+        assert!(report.variants[2].cagr_delta < 0.0 && report.variants[2].total_fees_delta > 0.0);
+        assert!(report.variants[2].label.ends_with("20.0 bps slippage"));
+
+        let buy_hold = compare_cost_models(params("BuyHold", TransactionCostModel::default()), Arc::new(trending_candles(10)), vec![BacktestCostModel::default()], &[]);
+        assert!(buy_hold.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_estimated_spread_raises_thin_stock_costs() {
+        // Mean reversion round trips in a thin stock whose days range 3% around the close
+        let mut closes: Vec<f64> = (0..25).map(|i| if i % 2 == 0 { 100.5 } else { 99.5 }).collect();
+        closes.extend([90.0, 95.0, 100.0, 112.0, 111.0]);
+        let candles: Vec<Candle> = closes
+            .iter()
+            .enumerate()
+            .map(|(i, close)| Candle::new(1704153600 + i as i64 * 86400, *close, close * 1.015, close * 0.985, *close, 20_000))
+            .collect();
+        let flat = BacktestCostModel { slippage_bps: 5.0, ..Default::default() };
+        let estimated = BacktestCostModel { fill_model: FillModel::EstimatedSpread, ..flat.clone() };
+        let with_costs = |costs: &BacktestCostModel| BacktestParams { cost_model: Some(costs.clone()), ..params("MeanReversion", TransactionCostModel::default()) };
+
+        let baseline = summarize_backtest(&with_costs(&flat), &candles);
+        let thin = summarize_backtest(&with_costs(&estimated), &candles);
+        let spread_bps = thin.cost_model.as_ref().and_then(|c| c.estimated_spread_bps).unwrap();
+        assert!(spread_bps > 10.0, "{}", spread_bps);
+        assert_eq!(thin.trades, baseline.trades);
+        assert!(thin.total_transaction_costs > baseline.total_transaction_costs && thin.cagr < baseline.cagr);
+        // The spread scales with the other costs in the sensitivity runs
+        let sensitivity = thin.sensitivity_analysis.as_ref().unwrap();
+        assert_eq!(sensitivity[2].1.cost_model.as_ref().unwrap().estimated_spread_bps, Some(spread_bps * 2.0));
+        // Too few bars to estimate from falls back to the flat slippage
+        assert_eq!(summarize_backtest(&with_costs(&estimated), &candles[..5]).total_transaction_costs, summarize_backtest(&with_costs(&flat), &candles[..5]).total_transaction_costs);
+
+        let report = compare_cost_models(with_costs(&flat), Arc::new(candles), Vec::new(), &[1.0, 3.0]).await.unwrap();
+        assert_eq!(report.variants.iter().map(|v| v.label.as_str()).collect::<Vec<_>>(), vec!["Estimated spread x1", "Estimated spread x3"]);
+        assert!((report.variants[0].total_fees - thin.total_transaction_costs).abs() < 1e-9);
+        assert!(report.variants[1].total_fees > report.variants[0].total_fees && report.variants[0].total_fees_delta > 0.0);
+    }
+
+    #[test]
+    fn test_limit_entries_fill_within_the_bar_or_expire() {
+        use crate::engine::strategies::{EntryOrder, TouchRule};
+        // The mean reversion entry signal comes on bar 25's close of 90 (low 89); bar 26
+        // is scripted per case, then the rally to 112 exits on bar 28
+        let run = |next_open: f64, next_low: f64, signal_orders: SignalOrderConfig| {
+            let mut bars: Vec<(f64, f64, f64, f64)> = (0..25).map(|i| if i % 2 == 0 { 100.5 } else { 99.5 }).map(|c| (c, c, c, c)).collect();
+            bars.push((99.0, 99.0, 89.0, 90.0));
+            bars.push((next_open, 95.0, next_low, 95.0));
+            bars.extend([100.0, 112.0, 111.0].map(|c| (c, c, c, c)));
+            let candles: Vec<Candle> = bars
+                .iter()
+                .enumerate()
+                .map(|(i, (open, high, low, close))| Candle::new(1704153600 + i as i64 * 86400, *open, *high, *low, *close, 1_000_000))
+                .collect();
+            let params = BacktestParams { signal_orders, ..params("MeanReversion", TransactionCostModel::default()) };
+            summarize_backtest(&params, &candles)
+        };
+        let orders = |entry: EntryOrder, touch_rule: TouchRule| SignalOrderConfig { entry, touch_rule, expire_after_bars: 1 };
+
+        // Gapping below a limit at the signal bar's low fills at the open, not the limit
+        let gapped = run(85.0, 84.0, orders(EntryOrder::LimitAtLow, TouchRule::Conservative));
+        assert_eq!(gapped.trade_log.len(), 1);
+        let trade = &gapped.trade_log[0];
+        assert_eq!((trade.entry_price, trade.intended_entry_price), (85.0, Some(89.0)));
+        assert_eq!(trade.entry_timestamp, 1704153600 + 26 * 86400);
+        let stats = gapped.signal_fills.unwrap();
+        assert_eq!((stats.entry_signals, stats.filled, stats.conversion_rate), (1, 1, 1.0));
+
+        // A limit 5% below the close ($85.50) the next bar never reaches expires unfilled
+        let missed = run(88.0, 86.0, orders(EntryOrder::Limit { offset_pct: 0.05 }, TouchRule::Conservative));
+        assert!(missed.trade_log.is_empty() && missed.fills.is_empty());
+        assert_eq!(missed.signal_fills.unwrap(), SignalFillStats { entry_signals: 1, filled: 0, expired: 1, canceled: 0, conversion_rate: 0.0 });
+        assert_eq!(missed.equity_curve.last().unwrap().equity, 1_000_000.0);
+
+        // A bar whose low only touches the limit fills under the optimistic rule alone
+        let touched = |rule| run(88.0, 85.5, orders(EntryOrder::Limit { offset_pct: 0.05 }, rule));
+        assert_eq!(touched(TouchRule::Conservative).signal_fills.unwrap().expired, 1);
+        let optimistic = touched(TouchRule::Optimistic);
+        assert!((optimistic.trade_log[0].entry_price - 85.5).abs() < 1e-9);
+
+        // At-close entries keep filling at the signal bar's close
+        let at_close = run(85.0, 84.0, SignalOrderConfig::default());
+        assert_eq!((at_close.trade_log[0].entry_price, at_close.trade_log[0].intended_entry_price), (90.0, Some(90.0)));
+    }
+
+    #[test]
+    fn test_comparison_prefers_higher_sharpe() {
+        let series = |closes: &[f64]| -> Vec<Candle> {
+            closes
+                .iter()
+                .enumerate()
+                .map(|(i, close)| Candle::new(1704153600 + i as i64 * 86400, *close, *close, *close, *close, 1_000_000))
+                .collect()
+        };
+        let steady = series(&[100.0, 101.0, 102.5, 103.0, 104.5, 105.0]);
+        let choppy = series(&[100.0, 108.0, 96.0, 110.0, 99.0, 107.0]);
+        let run = |run_id: &str, candles: &[Candle]| BacktestRun {
+            run_id: run_id.to_string(),
+            run_timestamp: 1_700_000_000,
+            summary: summarize_backtest(&params("BuyHold", TransactionCostModel::default()), candles),
+        };
+        let (a, b) = (run("a", &choppy), run("b", &steady));
+
+        let comparison = BacktestComparison::new(a.clone(), b.clone());
+        let sharpe = |r: &BacktestRun| sharpe_ratio(&r.summary.equity_curve.iter().map(|p| p.equity).collect::<Vec<_>>());
+        assert!((comparison.sharpe_diff - (sharpe(&a) - sharpe(&b))).abs() < 1e-12);
+        assert!(comparison.sharpe_diff < 0.0);
+        assert!((comparison.cagr_diff - (a.summary.cagr - b.summary.cagr)).abs() < 1e-12 && comparison.cagr_diff > 0.0);
+        assert!((comparison.max_dd_diff - (a.summary.max_dd - b.summary.max_dd)).abs() < 1e-12 && comparison.max_dd_diff < 0.0);
+        // The smoother run wins despite the lower CAGR
+        assert_eq!(comparison.better_run, "b");
+        assert!(comparison.summary.starts_with("b (BuyHold) beat a (BuyHold): Sharpe +"));
+        assert!(comparison.summary.contains("CAGR -"));
+
+        let flipped = BacktestComparison::new(b, a);
+        assert_eq!(flipped.better_run, "b");
+        assert!((flipped.sharpe_diff + comparison.sharpe_diff).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_pair_backtest_round_trips_on_cointegrated_series() {
+        // XOP trends up; XLE is twice XOP apart from +/-0.1% noise, a 5% dip on day 30
+        // and a 5% spike on day 60 that each revert the next day
+        let days = 80;
+        let deviation = |i: usize| match i {
+            30 => -0.05,
+            60 => 0.05,
+            _ if i.is_multiple_of(2) => 0.001,
+            _ => -0.001,
+        };
+        let candle = |i: usize, close: f64| Candle::new(1704153600 + i as i64 * 86400, close, close, close, close, 1_000_000);
+        let xop: Vec<Candle> = (0..days).map(|i| candle(i, 50.0 * (1.0 + 0.002 * i as f64))).collect();
+        let xle: Vec<Candle> = (0..days).map(|i| candle(i, 2.0 * xop[i].close * (1.0 + deviation(i)))).collect();
+        let pair = PairConfig {
+            first: "XLE".into(),
+            second: "XOP".into(),
+            spread: SpreadDefinition::Ratio,
+            lookback: 10,
+            entry_z: 2.0,
+            exit_z: 0.0,
+            sizing: PairSizing::DollarNeutral,
+            leg_notional: 10_000.0,
+        };
+        let costs = TransactionCostModel { commission_per_share: 0.005, ..Default::default() };
+        let params = BacktestParams { pair: Some(pair.clone()), ..params("Pairs", costs) };
+
+        let summary = summarize_pair_backtest(&params, &pair, &xle, &xop);
+        let detail = summary.pair.as_ref().unwrap();
+        let trips: Vec<(PairDirection, NaiveDate, NaiveDate, &str)> = detail
+            .round_trips
+            .iter()
+            .map(|r| (r.direction, r.entry_date, r.exit_date, r.exit_reason.as_str()))
+            .collect();
+        let date = |i: usize| xle[i].date();
+        assert_eq!(
+            trips,
+            vec![
+                (PairDirection::LongSpread, date(30), date(31), "spread_reverted"),
+                (PairDirection::ShortSpread, date(60), date(61), "spread_reverted"),
+            ]
+        );
+        assert_eq!((summary.symbol.as_str(), summary.trades, summary.win_rate), ("XLE/XOP", 2, 1.0));
+
+        // Dollar neutral: half the equity in each leg, long XLE and short XOP first
+        let first_trip = &detail.round_trips[0];
+        assert!(first_trip.first_quantity > 0 && first_trip.second_quantity < 0);
+        let notionals = (first_trip.first_quantity as f64 * xle[30].close, -first_trip.second_quantity as f64 * xop[30].close);
+        assert!((notionals.0 - 500_000.0).abs() < xle[30].close && (notionals.1 - notionals.0).abs() < xop[30].close);
+        // The reverting leg earns the spread; the hedge gives a little back
+        assert!(first_trip.first_pnl > 0.0 && first_trip.second_pnl < 0.0 && first_trip.spread_pnl > 0.0);
+        assert!(detail.round_trips[1].first_quantity < 0 && detail.round_trips[1].spread_pnl > 0.0);
+
+        // Legs add up to the spread, and with everything closed to the run's P&L
+        let fills = &summary.fills;
+        assert_eq!(fills.len(), 8);
+        assert_eq!(fills.iter().filter(|t| t.symbol == "XLE").count(), 4);
+        assert!((detail.legs[0].pnl + detail.legs[1].pnl - detail.spread_pnl).abs() < 1e-6);
+        assert!((detail.spread_pnl - summary.net_pnl).abs() < 1e-6);
+        let fees: f64 = fills.iter().map(|t| t.commission).sum();
+        assert!((summary.total_transaction_costs - fees).abs() < 1e-9 && fees > 0.0);
+        // Each leg's round trips add up to its P&L
+        assert_eq!(summary.trade_log.len(), 4);
+        assert_eq!(summary.trade_log.iter().filter(|t| t.symbol == "XOP" && t.quantity < 0).count(), 1);
+        let xle_pnl: f64 = summary.trade_log.iter().filter(|t| t.symbol == "XLE").map(|t| t.pnl).sum();
+        assert!((xle_pnl - detail.legs[0].pnl).abs() < 1e-6);
+        assert!((summary.equity_curve.last().unwrap().equity - (1_000_000.0 + summary.net_pnl)).abs() < 1e-6);
+        assert!(summary.equity_curve[..30].iter().all(|p| p.equity == 1_000_000.0));
+        assert_eq!(summary.sensitivity_analysis.as_ref().unwrap().len(), 4);
+
+        // A trading day missing from one leg is carried forward for both
+        let gapped = summarize_pair_backtest(&params, &pair, &xle, &[&xop[..45], &xop[46..]].concat());
+        assert_eq!(gapped.equity_curve.len(), days);
+        let carried: Vec<&EquityPoint> = gapped.equity_curve.iter().filter(|p| p.missing_bar).collect();
+        assert_eq!(carried.iter().map(|p| p.date).collect::<Vec<_>>(), vec![date(45)]);
+        assert_eq!(carried[0].equity, gapped.equity_curve[44].equity);
+    }
+
+    fn daily_candles(days: &[NaiveDate], close: impl Fn(usize) -> f64) -> Vec<Candle> {
+        days.iter()
+            .enumerate()
+            .map(|(i, day)| {
+                let timestamp = day.and_hms_opt(21, 0, 0).unwrap().and_utc().timestamp();
+                Candle::new(timestamp, close(i), close(i), close(i), close(i), 1_000_000)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cagr_over_a_year_of_trading_days() {
+        let calendar = MarketCalendar::new();
+        let days = calendar.get_trading_days(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(), NaiveDate::from_ymd_opt(2024, 12, 31).unwrap());
+        assert_eq!(days.len(), 252);
+        // 10% over the year in equal daily steps
+        let step = 1.1_f64.powf(1.0 / (days.len() - 1) as f64);
+        let candles = daily_candles(&days, |i| 100.0 * step.powi(i as i32));
+
+        let summary = summarize_backtest(&params("BuyHold", TransactionCostModel::default()), &candles);
+        assert!((summary.equity_curve.last().unwrap().equity - 1_100_000.0).abs() < 1e-6);
+        // Jan 2 to Dec 31 is 364 days, so the CAGR is the total return over 364/365.25 years
+        let expected = 1.1_f64.powf(365.25 / 364.0) - 1.0;
+        assert!((summary.cagr - expected).abs() < 1e-12, "{}", summary.cagr);
+        assert!((summary.cagr - 0.10).abs() < 1e-3);
+        assert_eq!(summary.missing_trading_days, 0);
+        assert!(summary.equity_curve.iter().all(|p| !p.missing_bar));
+        assert_eq!(summary.benchmark.as_ref().unwrap().cagr, summary.cagr);
+    }
+
+    #[test]
+    fn test_missing_bars_are_carried_forward_and_flagged() {
+        let calendar = MarketCalendar::new();
+        let days = calendar.get_trading_days(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(), NaiveDate::from_ymd_opt(2024, 3, 28).unwrap());
+        let full = daily_candles(&days, |i| 100.0 + i as f64);
+        // Two weeks without data: Feb 5 through Feb 16
+        let gap: Vec<NaiveDate> = days
+            .iter()
+            .copied()
+            .filter(|d| (NaiveDate::from_ymd_opt(2024, 2, 5).unwrap()..=NaiveDate::from_ymd_opt(2024, 2, 16).unwrap()).contains(d))
+            .collect();
+        assert_eq!(gap.len(), 10);
+        let gapped: Vec<Candle> = full.iter().filter(|c| !gap.contains(&c.date())).cloned().collect();
+        let params = BacktestParams { end_date: NaiveDate::from_ymd_opt(2024, 3, 28).unwrap(), ..params("BuyHold", TransactionCostModel::default()) };
+
+        let summary = summarize_backtest(&params, &gapped);
+        assert_eq!(summary.equity_curve.len(), days.len());
+        assert_eq!(summary.missing_trading_days, 10);
+        let carried: Vec<&EquityPoint> = summary.equity_curve.iter().filter(|p| p.missing_bar).collect();
+        assert_eq!(carried.iter().map(|p| p.date).collect::<Vec<_>>(), gap);
+        // Each carried point holds Friday Feb 2's equity and flat drawdown from it
+        let before_gap = summary.equity_curve.iter().find(|p| p.date == NaiveDate::from_ymd_opt(2024, 2, 2).unwrap()).unwrap();
+        assert!(carried.iter().all(|p| p.equity == before_gap.equity && p.drawdown == before_gap.drawdown));
+        let benchmark = summary.benchmark.as_ref().unwrap();
+        assert_eq!(benchmark.equity_curve.len(), days.len());
+        assert_eq!(benchmark.equity_curve.iter().filter(|p| p.missing_bar).count(), 10);
+
+        // The run over the complete bars ends at the same equity over the same span
+        let complete = summarize_backtest(&params, &full);
+        assert_eq!((complete.missing_trading_days, complete.cagr), (0, summary.cagr));
+        assert!(summary.max_dd == 0.0 && complete.max_dd == 0.0);
+    }
+}
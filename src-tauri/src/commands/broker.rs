@@ -0,0 +1,1217 @@
+// src-tauri/src/commands/broker.rs
+// Paper broker commands: orders, positions, risk and persistence
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tauri::{Emitter, Listener, Manager};
+
+use super::display::Presented;
+use super::state::{BackgroundJob, BackgroundJobKind, BrokerHandle, JobContext, JobOutput, JobRegistry};
+use crate::engine::allocations::{Allocation, AllocationTransfer, AllocationsReport};
+use crate::engine::benchmark::BenchmarkAccount;
+use crate::engine::derisk::{DeriskPlan, DeriskPriority};
+use crate::engine::drawdown::{DrawdownAlertConfig, DrawdownStatus};
+use crate::engine::equity_history::EquityRetention;
+use crate::engine::freshness::{FreshnessConfig, PortfolioFreshness};
+use crate::engine::badges::{BadgeConfig, BadgeSummary};
+use crate::engine::hedge::HedgePlan;
+use crate::engine::margin::{AccountType, MarginRates, MarginSummary};
+use crate::engine::mtm::OptionProbabilities;
+use crate::engine::position_history::{position_history, session_date, BasisMode, PositionHistoryPoint};
+use crate::engine::risk::{ExposureBreakdown, RiskMetrics};
+use crate::engine::rejections::RejectionReport;
+use crate::engine::risk_history::RiskSnapshot;
+use crate::engine::simulation::SimulationConfig;
+use crate::engine::spread::{self, SpreadEstimate, SPREAD_LOOKBACK_BARS};
+use super::backtest::{fill_drawdowns, fill_rolling_stats_on, EquityHistory, EquityPoint};
+use crate::engine::metrics::DrawdownBasis;
+use crate::engine::statements::{build_statement, month_bounds, pnl_report as build_pnl_report, PnlReportRow, Statement};
+use crate::engine::round_trips::{round_trips, trade_statistics, TradeStatistics};
+use crate::engine::tax_lots::{TaxLot, TaxOptimizationGoal};
+use crate::engine::portfolio_view::{PortfolioQuery, PortfolioResponse};
+use crate::engine::rates::{RateInputs, RatePoint};
+use crate::engine::bulk::{BulkAction, PositionFilter};
+use crate::engine::numeric::NumericFault;
+use crate::engine::tick_size::TickSizeRules;
+use crate::engine::undo::{UndoEntry, UndoOutcome};
+use crate::engine::what_if::{self, ExitRule, WhatIfReport};
+use crate::engine::symbols::normalize_symbol;
+use crate::engine::types::{
+    AdjustmentKind, CashAdjustment, CommissionModelComparison, CommissionSuggestion, CorporateActionAdjustment, DailySummary,
+    EnhancedPortfolio, ExerciseAction, ExerciseInstruction, InstrumentType, MarketData, OptionAssignment, OptionDetails,
+    OptionExpirationReport, OptionType, Order, OrderPreview, OrderQuery, OrderRequest, OrderType, OrderShortfall, OrderStateViolation, Portfolio, Position, PositionDetail, PositionExit, StockSplit,
+    SymbolPnl, Trade, TradeExecution, VenueStats,
+};
+use crate::market_data::dates;
+use crate::market_data::types::Candle;
+use crate::provider::diagnostics;
+use crate::provider::polygon as poly;
+use crate::provider::yahoo as yfin;
+use crate::providers::polygon::RealTimeTick;
+use crate::storage::cache::JournalStats;
+use crate::storage::equity_history::{EquityHistoryStore, EQUITY_HISTORY_DIR};
+use crate::storage::export::{ChunkedExporter, ExportFormat, ExportSpec, EXPORT_CHUNK_ROWS};
+use crate::storage::journal_store::{self, JournalStore, TradeQuery};
+use crate::storage::statements::{StatementEntry, StatementStore};
+
+/// Feed stream trade prints (`tick` events) to the managed broker's limit order queue model
+pub fn attach_tick_stream(app: &tauri::AppHandle) {
+    let handle = app.clone();
+    app.listen_any("tick", move |event| {
+        let Ok(tick) = serde_json::from_str::<RealTimeTick>(event.payload()) else {
+            return;
+        };
+        if let Ok(mut broker) = handle.state::<BrokerHandle>().lock_for("tick_stream") {
+            broker.on_trade_print(&tick.symbol, tick.price, tick.size);
+        }
+    });
+}
+
+const ORDER_EXPIRY_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Expire good-till-date orders on a timer, so orders for symbols without market data
+/// still expire on time
+pub fn start_order_expiry_timer(app: &tauri::AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(ORDER_EXPIRY_INTERVAL);
+        if let Ok(mut broker) = app.state::<BrokerHandle>().lock_for("order_expiry_timer") {
+            broker.expire_orders();
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn paper_order(
+    broker: tauri::State<'_, BrokerHandle>,
+    req: OrderRequest,
+) -> Result<Presented<TradeExecution>, String> {
+    let mut broker = broker.lock_for("paper_order")?;
+    broker.place_order(req).map(Presented)
+}
+
+/// What `req` would cost and leave the portfolio at, with the warnings placing it would
+/// produce; nothing is ordered or reserved
+#[tauri::command]
+pub async fn preview_order(
+    broker: tauri::State<'_, BrokerHandle>,
+    req: OrderRequest,
+) -> Result<Presented<OrderPreview>, String> {
+    let broker = broker.lock_for("preview_order")?;
+    broker.preview_order(req).map(Presented)
+}
+
+/// Book fills whose simulated latency has elapsed
+#[tauri::command]
+pub async fn process_matured_fills(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<Presented<Vec<TradeExecution>>, String> {
+    let mut broker = broker.lock_for("process_matured_fills")?;
+    Ok(Presented(broker.process_matured_fills()))
+}
+
+/// Orders matching `query`; `expiring_within_hours` lists open orders about to expire
+#[tauri::command]
+pub async fn query_orders(
+    broker: tauri::State<'_, BrokerHandle>,
+    query: OrderQuery,
+) -> Result<Presented<Vec<Order>>, String> {
+    let broker = broker.lock_for("query_orders")?;
+    Ok(Presented(broker.query_orders(&query)))
+}
+
+/// Orders restored at startup whose status, quantities and fills do not add up. Fills
+/// and status changes that would leave them inconsistent are refused.
+#[tauri::command]
+pub async fn get_order_state_diagnostics(broker: tauri::State<'_, BrokerHandle>) -> Result<Vec<OrderStateViolation>, String> {
+    Ok(broker.lock_for("get_order_state_diagnostics")?.order_state_violations.clone())
+}
+
+/// NaN and infinite values repaired in restored state, or refused by fills, quotes and
+/// saves since startup
+#[tauri::command]
+pub async fn get_numeric_faults(broker: tauri::State<'_, BrokerHandle>) -> Result<Vec<NumericFault>, String> {
+    Ok(broker.lock_for("get_numeric_faults")?.numeric_faults.clone())
+}
+
+#[tauri::command]
+pub async fn portfolio(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<Presented<Portfolio>, String> {
+    let broker = broker.lock_for("portfolio")?;
+    Ok(Presented(broker.get_portfolio()))
+}
+
+#[tauri::command]
+pub async fn trades(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<Presented<Vec<Trade>>, String> {
+    let broker = broker.lock_for("trades")?;
+    Ok(Presented(broker.get_trades()))
+}
+
+#[tauri::command]
+pub async fn get_symbol_pnl(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<Vec<SymbolPnl>, String> {
+    let broker = broker.lock_for("get_symbol_pnl")?;
+    Ok(broker.get_symbol_pnl())
+}
+
+/// Round-trip statistics of the journal, matched first in first out
+#[tauri::command]
+pub async fn get_trade_statistics(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<TradeStatistics, String> {
+    let broker = broker.lock_for("get_trade_statistics")?;
+    Ok(trade_statistics(&round_trips(&broker.trades, &broker.get_applied_splits())))
+}
+
+/// Replay the journal's stock entries from `from` through `to` with `exit_rule` in place
+/// of the exits actually taken, and compare the two. `tag` keeps only entries whose
+/// trade carries it (a strategy, signal or rule).
+#[tauri::command]
+pub async fn what_if_exits(
+    app: tauri::AppHandle,
+    broker: tauri::State<'_, BrokerHandle>,
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+    exit_rule: ExitRule,
+    tag: Option<String>,
+) -> Result<Presented<WhatIfReport>, String> {
+    exit_rule.validate()?;
+    let (journal, trades, splits, today) = {
+        let broker = broker.lock_for("what_if_exits")?;
+        (broker.journal_store()?, broker.trades.clone(), broker.get_applied_splits(), broker.get_current_session().date)
+    };
+    let symbols: BTreeSet<&str> = trades
+        .iter()
+        .filter(|t| t.instrument_type == InstrumentType::Stock && (from..=to).contains(&session_date(t.timestamp)))
+        .map(|t| t.symbol.as_str())
+        .collect();
+
+    // Enough sessions before the first entry for the rule's indicators
+    let warmup = chrono::Duration::days(exit_rule.history_bars() as i64 * 7 / 5 + BAR_COVERAGE_SLACK_DAYS);
+    let mut bars = HashMap::new();
+    for symbol in symbols {
+        match daily_bars(&app, journal.as_ref(), symbol, from - warmup, today).await {
+            Ok(series) => {
+                bars.insert(symbol.to_string(), series);
+            }
+            Err(e) => eprintln!("No daily bars for {} in the what-if replay: {}", symbol, e),
+        }
+    }
+    what_if::what_if_exits(&trades, &splits, &bars, from, to, tag.as_deref(), &exit_rule).map(Presented)
+}
+
+#[tauri::command]
+pub async fn get_implementation_shortfall_report(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<Vec<OrderShortfall>, String> {
+    let broker = broker.lock_for("get_implementation_shortfall_report")?;
+    Ok(broker.get_implementation_shortfall_report())
+}
+
+#[tauri::command]
+pub async fn cancel_order(
+    broker: tauri::State<'_, BrokerHandle>,
+    order_id: String,
+) -> Result<(), String> {
+    let mut broker = broker.lock_for("cancel_order")?;
+    broker.cancel_order(&order_id)
+}
+
+#[tauri::command]
+pub async fn close_position(
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+) -> Result<Presented<TradeExecution>, String> {
+    let mut broker = broker.lock_for("close_position")?;
+    broker.close_position(&symbol).map(Presented)
+}
+
+/// Cancel every working order matching `filter` (all of them without one) in one pass.
+/// Orders that filled before the cancel reached them are listed as filled.
+#[tauri::command]
+pub async fn cancel_all_orders(
+    broker: tauri::State<'_, BrokerHandle>,
+    filter: Option<OrderQuery>,
+) -> Result<Presented<BulkAction>, String> {
+    let mut filter = filter.unwrap_or_default();
+    filter.symbol = filter.symbol.as_deref().map(normalize_symbol).transpose()?;
+    let mut broker = broker.lock_for("cancel_all_orders")?;
+    Ok(Presented(broker.cancel_all_orders(&filter)))
+}
+
+/// Close every position matching `filter` (all of them without one) at market, buying
+/// back spread shorts before selling their longs
+#[tauri::command]
+pub async fn close_all_positions(
+    broker: tauri::State<'_, BrokerHandle>,
+    filter: Option<PositionFilter>,
+) -> Result<Presented<BulkAction>, String> {
+    let mut filter = filter.unwrap_or_default();
+    filter.symbol = filter.symbol.as_deref().map(normalize_symbol).transpose()?;
+    let mut broker = broker.lock_for("close_all_positions")?;
+    Ok(Presented(broker.close_all_positions(&filter)))
+}
+
+/// Cancel every working order and close every position
+#[tauri::command]
+pub async fn go_flat(
+    broker: tauri::State<'_, BrokerHandle>,
+    confirm: bool,
+) -> Result<Presented<BulkAction>, String> {
+    let mut broker = broker.lock_for("go_flat")?;
+    broker.go_flat(confirm).map(Presented)
+}
+
+/// Bulk actions logged in sessions `from` through `to`, each with what it did to every
+/// order and position
+#[tauri::command]
+pub async fn get_bulk_actions(
+    broker: tauri::State<'_, BrokerHandle>,
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+) -> Result<Presented<Vec<BulkAction>>, String> {
+    let broker = broker.lock_for("get_bulk_actions")?;
+    broker.get_bulk_actions(from, to).map(Presented)
+}
+
+#[tauri::command]
+pub async fn configure_simulation(
+    broker: tauri::State<'_, BrokerHandle>,
+    config: SimulationConfig,
+    symbols: Vec<String>,
+) -> Result<(), String> {
+    let mut broker = broker.lock_for("configure_simulation")?;
+    broker.configure_simulation(config, symbols)
+}
+
+#[tauri::command]
+pub async fn tick_simulation(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<HashMap<String, f64>, String> {
+    let mut broker = broker.lock_for("tick_simulation")?;
+    broker.tick_simulation()
+}
+
+#[tauri::command]
+pub async fn get_position_detail(
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+) -> Result<Presented<PositionDetail>, String> {
+    let broker = broker.lock_for("get_position_detail")?;
+    broker.get_position_detail(&symbol).map(Presented)
+}
+
+#[tauri::command]
+pub async fn get_open_lots(
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+) -> Result<Vec<TaxLot>, String> {
+    let broker = broker.lock_for("get_open_lots")?;
+    broker.get_open_lots(&symbol)
+}
+
+/// Sell `lot_selections` (lot id, shares) of a long stock position
+#[tauri::command]
+pub async fn close_specific_lots(
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+    lot_selections: Vec<(String, i64)>,
+    order_type: OrderType,
+) -> Result<TradeExecution, String> {
+    let mut broker = broker.lock_for("close_specific_lots")?;
+    broker.close_specific_lots(&symbol, lot_selections, order_type)
+}
+
+#[tauri::command]
+pub async fn optimize_lots_for_tax(
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+    target_quantity: i64,
+    goal: TaxOptimizationGoal,
+) -> Result<Vec<(String, i64)>, String> {
+    let broker = broker.lock_for("optimize_lots_for_tax")?;
+    broker.optimize_lots_for_tax(&symbol, target_quantity, goal)
+}
+
+/// Probability of finishing in the money and expected payoff of buying an option on
+/// `symbol`; `option_type` is "call" or "put", `expiry` YYYY-MM-DD (or MM/DD/YYYY)
+#[tauri::command]
+pub async fn calculate_option_probability(
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+    strike: f64,
+    expiry: String,
+    option_type: String,
+) -> Result<OptionProbabilities, String> {
+    let option_type = match option_type.trim().to_lowercase().as_str() {
+        "call" => OptionType::Call,
+        "put" => OptionType::Put,
+        other => return Err(format!("Option type must be call or put, not '{}'", other)),
+    };
+    let symbol = normalize_symbol(&symbol)?;
+    let expiry = dates::parse(&expiry)?;
+    let broker = broker.lock_for("calculate_option_probability")?;
+    broker.calculate_option_probability(&symbol, strike, expiry, option_type)
+}
+
+/// Reverse the newest manual fill, cash adjustment or exit level change
+#[tauri::command]
+pub async fn undo_last_action(broker: tauri::State<'_, BrokerHandle>) -> Result<Presented<UndoOutcome>, String> {
+    let mut broker = broker.lock_for("undo_last_action")?;
+    broker.undo_last_action().map(Presented)
+}
+
+/// Manual actions undo_last_action would reverse, newest first
+#[tauri::command]
+pub async fn get_undo_stack(broker: tauri::State<'_, BrokerHandle>) -> Result<Vec<UndoEntry>, String> {
+    Ok(broker.lock_for("get_undo_stack")?.get_undo_stack())
+}
+
+#[tauri::command]
+pub async fn set_undo_depth(broker: tauri::State<'_, BrokerHandle>, depth: usize) -> Result<(), String> {
+    broker.lock_for("set_undo_depth")?.set_undo_depth(depth)
+}
+
+#[tauri::command]
+pub async fn configure_position_exits(
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+    exit: PositionExit,
+) -> Result<Position, String> {
+    let mut broker = broker.lock_for("configure_position_exits")?;
+    broker.configure_position_exits(&symbol, exit)
+}
+
+/// Settle expired option positions now instead of waiting for the strategy loop's end-of-day run
+#[tauri::command]
+pub async fn process_option_expirations(
+    app: tauri::AppHandle,
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<Presented<OptionExpirationReport>, String> {
+    let mut broker = broker.lock_for("process_option_expirations")?;
+    let report = broker.process_option_expirations();
+    let _ = app.emit("option_expiration_processed", Presented(&report));
+    Ok(Presented(report))
+}
+
+#[tauri::command]
+pub async fn get_upcoming_expirations(
+    broker: tauri::State<'_, BrokerHandle>,
+    days_ahead: i32,
+) -> Result<Presented<Vec<OptionDetails>>, String> {
+    let broker = broker.lock_for("get_upcoming_expirations")?;
+    Ok(Presented(broker.check_for_upcoming_expirations(days_ahead)))
+}
+
+/// Exercise long contracts before expiry
+#[tauri::command]
+pub async fn exercise_option(
+    app: tauri::AppHandle,
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+    quantity: i64,
+) -> Result<Presented<OptionAssignment>, String> {
+    let mut broker = broker.lock_for("exercise_option")?;
+    let assignment = broker.exercise_option(&symbol, quantity)?;
+    let _ = app.emit("option_exercised", Presented(&assignment));
+    Ok(Presented(assignment))
+}
+
+#[tauri::command]
+pub async fn set_exercise_instruction(
+    app: tauri::AppHandle,
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+    instruction: ExerciseAction,
+    quantity: Option<i64>,
+) -> Result<ExerciseInstruction, String> {
+    let mut broker = broker.lock_for("set_exercise_instruction")?;
+    let instruction = broker.set_exercise_instruction(&symbol, instruction, quantity)?;
+    let _ = app.emit("exercise_instruction_set", &instruction);
+    Ok(instruction)
+}
+
+#[tauri::command]
+pub async fn clear_exercise_instruction(
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+) -> Result<(), String> {
+    let mut broker = broker.lock_for("clear_exercise_instruction")?;
+    broker.clear_exercise_instruction(&symbol)
+}
+
+#[tauri::command]
+pub async fn get_exercise_instructions(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<Vec<ExerciseInstruction>, String> {
+    let broker = broker.lock_for("get_exercise_instructions")?;
+    Ok(broker.get_exercise_instructions())
+}
+
+#[tauri::command]
+pub async fn schedule_stock_split(
+    broker: tauri::State<'_, BrokerHandle>,
+    split: StockSplit,
+) -> Result<StockSplit, String> {
+    let mut broker = broker.lock_for("schedule_stock_split")?;
+    broker.schedule_stock_split(split)
+}
+
+#[tauri::command]
+pub async fn get_pending_splits(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<Vec<StockSplit>, String> {
+    let broker = broker.lock_for("get_pending_splits")?;
+    Ok(broker.get_pending_splits())
+}
+
+#[tauri::command]
+pub async fn get_corporate_actions(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<Vec<CorporateActionAdjustment>, String> {
+    let broker = broker.lock_for("get_corporate_actions")?;
+    Ok(broker.get_corporate_actions())
+}
+
+/// Daily quantity, cost basis and mark of one symbol rebuilt from the journal, as
+/// traded or restated by later splits
+#[tauri::command]
+pub async fn get_position_history(
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+    basis_mode: Option<BasisMode>,
+) -> Result<Vec<PositionHistoryPoint>, String> {
+    let (trades, splits, summaries) = {
+        let broker = broker.lock_for("get_position_history")?;
+        (broker.trades.clone(), broker.get_applied_splits(), broker.daily_summaries.clone())
+    };
+    let symbol = normalize_symbol(&symbol)?;
+    position_history(&symbol, &trades, &splits, &summaries, from, to, basis_mode.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn get_venue_statistics(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<HashMap<String, VenueStats>, String> {
+    let broker = broker.lock_for("get_venue_statistics")?;
+    Ok(broker.get_venue_statistics())
+}
+
+#[tauri::command]
+pub async fn update_market_data(
+    broker: tauri::State<'_, BrokerHandle>,
+    data: MarketData,
+) -> Result<(), String> {
+    let mut broker = broker.lock_for("update_market_data")?;
+    broker.update_market_data(data);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn enhanced_portfolio(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<EnhancedPortfolio, String> {
+    let snapshot = broker.lock_for("enhanced_portfolio")?.valuation_snapshot();
+    Ok(snapshot.enhanced_portfolio())
+}
+
+/// The enhanced portfolio cut down to `query`: some fields, some symbols, one page of
+/// positions. Pass the last state_version as if_none_match to poll cheaply.
+#[tauri::command]
+pub async fn get_portfolio_view(
+    broker: tauri::State<'_, BrokerHandle>,
+    query: Option<PortfolioQuery>,
+) -> Result<Presented<PortfolioResponse>, String> {
+    let query = query.unwrap_or_default();
+    let snapshot = {
+        let broker = broker.lock_for("get_portfolio_view")?;
+        if let Some(response) = query.not_modified(broker.portfolio_version) {
+            return Ok(Presented(response));
+        }
+        broker.valuation_snapshot()
+    };
+    Ok(Presented(snapshot.portfolio_view(&query)?))
+}
+
+#[tauri::command]
+pub async fn risk_status(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<RiskMetrics, String> {
+    let broker = broker.lock_for("risk_status")?;
+    Ok(broker.get_risk_status())
+}
+
+/// Set when "drawdown_alert" fires: past a drawdown from the all-time high and/or on a
+/// new all-time high. Returns the current high-water marks.
+#[tauri::command]
+pub async fn set_drawdown_alerts(
+    broker: tauri::State<'_, BrokerHandle>,
+    alerts: DrawdownAlertConfig,
+) -> Result<DrawdownStatus, String> {
+    let mut broker = broker.lock_for("set_drawdown_alerts")?;
+    broker.set_drawdown_alerts(alerts)
+}
+
+/// Live and delayed mark thresholds, and whether stale quotes refuse new orders. Returns
+/// the portfolio's freshness under the new settings.
+#[tauri::command]
+pub async fn set_freshness_config(
+    broker: tauri::State<'_, BrokerHandle>,
+    config: FreshnessConfig,
+) -> Result<PortfolioFreshness, String> {
+    let mut broker = broker.lock_for("set_freshness_config")?;
+    broker.set_freshness_config(config)
+}
+
+/// Thresholds of the position risk badges. Returns the portfolio's badge counts under the
+/// new settings.
+#[tauri::command]
+pub async fn set_badge_config(
+    broker: tauri::State<'_, BrokerHandle>,
+    config: BadgeConfig,
+) -> Result<BadgeSummary, String> {
+    let mut broker = broker.lock_for("set_badge_config")?;
+    broker.set_badge_config(config)
+}
+
+/// The risk-free curve and dividend yields option Greeks and theoretical prices use
+#[tauri::command]
+pub async fn get_rate_inputs(broker: tauri::State<'_, BrokerHandle>) -> Result<Presented<RateInputs>, String> {
+    Ok(Presented(broker.lock_for("get_rate_inputs")?.rate_inputs()))
+}
+
+/// Replace the risk-free curve with `points` (tenor in years, rate as an annual fraction),
+/// interpolated linearly between tenors
+#[tauri::command]
+pub async fn set_rate_curve(
+    broker: tauri::State<'_, BrokerHandle>,
+    points: Vec<RatePoint>,
+) -> Result<Presented<RateInputs>, String> {
+    let mut broker = broker.lock_for("set_rate_curve")?;
+    broker.set_rate_curve(points, None).map(Presented)
+}
+
+/// Pin `symbol`'s dividend yield, or go back to its trailing yield with None
+#[tauri::command]
+pub async fn set_dividend_yield(
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+    dividend_yield: Option<f64>,
+) -> Result<Presented<RateInputs>, String> {
+    let mut broker = broker.lock_for("set_dividend_yield")?;
+    broker.set_dividend_yield(&symbol, dividend_yield).map(Presented)
+}
+
+/// Estimated bid/ask spread of `symbol`: realized from its recent streamed quotes when
+/// there are enough, else read from its daily bars. The estimate is kept to price the
+/// symbol's orders while its quote has no bid or ask.
+#[tauri::command]
+pub async fn get_spread_estimate(
+    app: tauri::AppHandle,
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+) -> Result<SpreadEstimate, String> {
+    let symbol = normalize_symbol(&symbol)?;
+    let (journal, today, now, realized) = {
+        let broker = broker.lock_for("get_spread_estimate")?;
+        (broker.journal_store()?, broker.get_current_session().date, broker.now(), broker.realized_spread_estimate(&symbol))
+    };
+    let estimate = match realized {
+        Some(estimate) => estimate,
+        None => {
+            let lookback = chrono::Duration::days(SPREAD_LOOKBACK_BARS as i64 * 7 / 5 + BAR_COVERAGE_SLACK_DAYS);
+            let bars = daily_bars(&app, journal.as_ref(), &symbol, today - lookback, today).await?;
+            spread::from_bars(&symbol, &bars, now).ok_or_else(|| format!("Not enough daily bars for {} to estimate its spread", symbol))?
+        }
+    };
+    broker.lock_for("get_spread_estimate")?.record_spread_estimate(estimate.clone());
+    Ok(estimate)
+}
+
+/// Daily risk snapshots for `from` through `to`, each flagging the limits breached that
+/// day and whether the circuit breaker fired
+#[tauri::command]
+pub async fn get_risk_history(
+    broker: tauri::State<'_, BrokerHandle>,
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+) -> Result<Vec<RiskSnapshot>, String> {
+    let broker = broker.lock_for("get_risk_history")?;
+    broker.get_risk_history(from, to)
+}
+
+/// Orders rejected in sessions `from` through `to`, counted by reason, symbol, time of
+/// day (15 minute buckets unless `bucket_minutes` is given) and source, with the newest
+/// `recent_limit` (default 50) records in full
+#[tauri::command]
+pub async fn get_rejection_report(
+    broker: tauri::State<'_, BrokerHandle>,
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+    bucket_minutes: Option<u32>,
+    recent_limit: Option<usize>,
+) -> Result<RejectionReport, String> {
+    let broker = broker.lock_for("get_rejection_report")?;
+    broker.get_rejection_report(from, to, bucket_minutes.unwrap_or(15), recent_limit.unwrap_or(50))
+}
+
+#[tauri::command]
+pub async fn risk_violations(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<Vec<String>, String> {
+    let broker = broker.lock_for("risk_violations")?;
+    Ok(broker.get_risk_violations())
+}
+
+#[tauri::command]
+pub async fn get_exposure_breakdown(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<ExposureBreakdown, String> {
+    let broker = broker.lock_for("get_exposure_breakdown")?;
+    Ok(broker.get_exposure_breakdown())
+}
+
+#[tauri::command]
+pub async fn get_derisk_plan(
+    broker: tauri::State<'_, BrokerHandle>,
+    target_exposure_pct: f64,
+    priority: Option<DeriskPriority>,
+) -> Result<Presented<DeriskPlan>, String> {
+    let mut broker = broker.lock_for("get_derisk_plan")?;
+    broker.get_derisk_plan(target_exposure_pct, priority.unwrap_or_default()).map(Presented)
+}
+
+#[tauri::command]
+pub async fn execute_derisk_plan(
+    broker: tauri::State<'_, BrokerHandle>,
+    plan_id: String,
+    confirm: bool,
+) -> Result<Presented<Vec<TradeExecution>>, String> {
+    let mut broker = broker.lock_for("execute_derisk_plan")?;
+    broker.execute_derisk_plan(&plan_id, confirm).map(Presented)
+}
+
+#[tauri::command]
+pub async fn get_hedge_suggestion(
+    broker: tauri::State<'_, BrokerHandle>,
+    target_delta: f64,
+    band: f64,
+    put_delta: Option<f64>,
+) -> Result<Presented<HedgePlan>, String> {
+    let mut broker = broker.lock_for("get_hedge_suggestion")?;
+    broker.get_hedge_suggestion(target_delta, band, put_delta).map(Presented)
+}
+
+#[tauri::command]
+pub async fn execute_hedge(
+    broker: tauri::State<'_, BrokerHandle>,
+    plan_id: String,
+    use_options: Option<bool>,
+) -> Result<Presented<Vec<TradeExecution>>, String> {
+    let mut broker = broker.lock_for("execute_hedge")?;
+    broker.execute_hedge(&plan_id, use_options.unwrap_or(false)).map(Presented)
+}
+
+#[tauri::command]
+pub async fn update_risk_metrics(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<(), String> {
+    let mut broker = broker.lock_for("update_risk_metrics")?;
+    broker.update_risk_metrics();
+    Ok(())
+}
+
+//
+// ---------- Broker Persistence ----------
+//
+
+#[tauri::command]
+pub async fn save_broker_state(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<(), String> {
+    let mut broker = broker.lock_for("save_broker_state")?;
+    broker.save_state()
+}
+
+#[tauri::command]
+pub async fn get_journal_stats(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<JournalStats, String> {
+    let journal = broker.lock_for("get_journal_stats")?.journal_store()?;
+    journal.journal_stats()
+}
+
+#[tauri::command]
+pub async fn backup_journal(
+    broker: tauri::State<'_, BrokerHandle>,
+    backup_suffix: String,
+) -> Result<String, String> {
+    let journal = broker.lock_for("backup_journal")?.journal_store()?;
+    let backup_path = journal.backup(&backup_suffix)?;
+    Ok(backup_path.to_string_lossy().to_string())
+}
+
+/// Journal trades matching `query`, oldest first. The SQLite backend filters in SQL;
+/// the file backend reads the whole journal.
+#[tauri::command]
+pub async fn query_trades(
+    broker: tauri::State<'_, BrokerHandle>,
+    mut query: TradeQuery,
+) -> Result<Presented<Vec<Trade>>, String> {
+    query.symbol = query.symbol.as_deref().map(normalize_symbol).transpose()?;
+    let journal = broker.lock_for("query_trades")?.journal_store()?;
+    journal.query_trades(&query).map(Presented)
+}
+
+/// Realized P&L per symbol over sessions `from` through `to`
+#[tauri::command]
+pub async fn pnl_report(
+    broker: tauri::State<'_, BrokerHandle>,
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+    symbol: Option<String>,
+) -> Result<Vec<PnlReportRow>, String> {
+    let (journal, splits) = {
+        let broker = broker.lock_for("pnl_report")?;
+        (broker.journal_store()?, broker.get_applied_splits())
+    };
+    // Earlier trades set the basis, so only the end and the symbol are filtered
+    let symbol = symbol.as_deref().map(normalize_symbol).transpose()?;
+    let trades = journal.query_trades(&TradeQuery { symbol, to: Some(to), ..Default::default() })?;
+    build_pnl_report(&trades, &splits, from, to)
+}
+
+// Calendar days before `from` that hold the 20 sessions the rolling window needs
+const ROLLING_WARMUP_DAYS: i64 = 45;
+
+/// One point per recorded session, total and realized-only equity
+pub fn session_equity_curve(summaries: &[DailySummary]) -> Vec<EquityPoint> {
+    summaries
+        .iter()
+        .map(|s| EquityPoint {
+            date: s.date,
+            equity: s.ending_equity,
+            drawdown: 0.0,
+            rolling_vol_20d: None,
+            rolling_sharpe_20d: None,
+            realized_equity: Some(s.equity_on(DrawdownBasis::Realized)),
+            realized_drawdown: None,
+            missing_bar: false,
+            benchmark_equity: None,
+        })
+        .collect()
+}
+
+/// Account equity per recorded session from `from` through `to`, with drawdown over
+/// the range and 20-session rolling volatility and Sharpe. `drawdown` and the rolling
+/// stats are on `drawdown_basis` (total equity by default); `realized_drawdown` is
+/// always on realized-only equity. With a benchmark account set, sessions since its
+/// start also carry the benchmark's equity.
+#[tauri::command]
+pub async fn get_rolling_metrics(
+    broker: tauri::State<'_, BrokerHandle>,
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+    drawdown_basis: Option<DrawdownBasis>,
+) -> Result<Vec<EquityPoint>, String> {
+    if from > to {
+        return Err(format!("Range start {} is after its end {}", from, to));
+    }
+    let basis = drawdown_basis.unwrap_or_default();
+    let (journal, benchmark) = {
+        let broker = broker.lock_for("get_rolling_metrics")?;
+        (broker.journal_store()?, broker.benchmark.clone())
+    };
+    let summaries = journal.daily_summaries(Some(from - chrono::Duration::days(ROLLING_WARMUP_DAYS)), Some(to))?;
+
+    let mut curve = session_equity_curve(&summaries);
+    if let Some(benchmark) = &benchmark {
+        for (point, summary) in curve.iter_mut().zip(&summaries) {
+            point.benchmark_equity = benchmark.equity_on(summary.date);
+        }
+    }
+    fill_rolling_stats_on(&mut curve, basis);
+    let warmup = summaries.iter().take_while(|s| s.date < from).count();
+    curve.drain(..warmup);
+
+    fill_drawdowns(&mut curve);
+    if basis == DrawdownBasis::Realized {
+        for point in curve.iter_mut() {
+            point.drawdown = point.realized_drawdown.unwrap_or(point.drawdown);
+        }
+    }
+    Ok(curve)
+}
+
+// Calendar days a cached series may start after or end before the range it is read
+// for and still cover it, for weekends and holidays
+const BAR_COVERAGE_SLACK_DAYS: i64 = 4;
+
+/// Daily bars of `symbol` for sessions `from` up to `to`: the bar cache's when they cover
+/// the range, else fetched from Polygon (which caches them) or Yahoo
+async fn daily_bars(app: &tauri::AppHandle, journal: &dyn JournalStore, symbol: &str, from: chrono::NaiveDate, to: chrono::NaiveDate) -> Result<Vec<Candle>, String> {
+    let (Some(start), Some(end)) = (journal_store::session_start(from), journal_store::session_start(to)) else {
+        return Err(format!("Invalid range {} to {}", from, to));
+    };
+    let cached = journal.query_bars(symbol, "1d", start, end - 1)?;
+    let within_slack = |earlier: chrono::NaiveDate, later: chrono::NaiveDate| (later - earlier).num_days() <= BAR_COVERAGE_SLACK_DAYS;
+    if let (Some(first), Some(last)) = (cached.first(), cached.last()) {
+        if within_slack(from, session_date(first.timestamp)) && within_slack(session_date(last.timestamp), to) {
+            return Ok(cached);
+        }
+    }
+
+    match poly::fetch_history(app, symbol.to_string(), from, to, Some("1day".into())).await {
+        Ok(bars) if !bars.is_empty() => Ok(bars),
+        _ => {
+            let capture_dir = diagnostics::capture_dir(app);
+            yfin::yahoo_history(symbol.to_string(), from, to, capture_dir.as_deref())
+                .await
+                .map_err(|e| format!("Both providers failed: {e}"))
+        }
+    }
+}
+
+/// Track a shadow buy-and-hold account in `symbol` from `start_date`, sized to the paper
+/// account's equity going into that session and moved with every later deposit and
+/// withdrawal. A past start date is reconstructed from daily bars and the transfers
+/// since; recorded sessions get its day P&L. Replaces any benchmark already set.
+#[tauri::command]
+pub async fn set_benchmark_account(
+    app: tauri::AppHandle,
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+    start_date: chrono::NaiveDate,
+) -> Result<BenchmarkAccount, String> {
+    let symbol = normalize_symbol(&symbol)?;
+    let (journal, starting_equity, transfers, today) = {
+        let broker = broker.lock_for("set_benchmark_account")?;
+        (broker.journal_store()?, broker.equity_going_into(start_date), broker.transfers(), broker.get_current_session().date)
+    };
+    if start_date > today {
+        return Err(format!("The benchmark cannot start after today, {}", today));
+    }
+
+    // The current session is marked when it rolls
+    let account = if start_date == today {
+        BenchmarkAccount::new(&symbol, start_date, starting_equity)
+    } else {
+        let bars = daily_bars(&app, journal.as_ref(), &symbol, start_date, today).await?;
+        BenchmarkAccount::reconstruct(&symbol, start_date, starting_equity, &bars, &transfers, today)?
+    };
+    broker.lock_for("set_benchmark_account")?.set_benchmark_account(account)
+}
+
+#[tauri::command]
+pub async fn clear_benchmark_account(broker: tauri::State<'_, BrokerHandle>) -> Result<(), String> {
+    broker.lock_for("clear_benchmark_account")?.clear_benchmark_account()
+}
+
+/// Open a strategy sub-account with `capital` taken from the unallocated part of the
+/// account. Orders naming it, or whose client_order_id starts with `client_order_prefix`,
+/// trade from it.
+#[tauri::command]
+pub async fn create_allocation(
+    broker: tauri::State<'_, BrokerHandle>,
+    name: String,
+    capital: f64,
+    client_order_prefix: Option<String>,
+) -> Result<Allocation, String> {
+    broker.lock_for("create_allocation")?.create_allocation(&name, capital, client_order_prefix)
+}
+
+#[tauri::command]
+pub async fn remove_allocation(broker: tauri::State<'_, BrokerHandle>, name: String) -> Result<Allocation, String> {
+    broker.lock_for("remove_allocation")?.remove_allocation(&name)
+}
+
+/// Move `amount` of cash from one sub-account to another. Leaving `from` or `to` out
+/// moves it from or to the unallocated part of the account.
+#[tauri::command]
+pub async fn rebalance_allocations(
+    broker: tauri::State<'_, BrokerHandle>,
+    from: Option<String>,
+    to: Option<String>,
+    amount: f64,
+    note: Option<String>,
+) -> Result<AllocationTransfer, String> {
+    broker.lock_for("rebalance_allocations")?.rebalance_allocations(from.as_deref(), to.as_deref(), amount, note)
+}
+
+/// Each sub-account's positions, P&L, room left and equity at each close, with the
+/// transfers between them
+#[tauri::command]
+pub async fn get_allocation_report(broker: tauri::State<'_, BrokerHandle>) -> Result<AllocationsReport, String> {
+    Ok(broker.lock_for("get_allocation_report")?.get_allocation_report())
+}
+
+/// Account equity per recorded session, optionally limited to `from` through `to`,
+/// with drawdown series and maxima on total and realized-only equity. With `sampled`,
+/// also the intraday equity samples, stitched from every retention tier with the
+/// resolution of each stretch.
+#[tauri::command]
+pub async fn get_equity_history(
+    broker: tauri::State<'_, BrokerHandle>,
+    from: Option<chrono::NaiveDate>,
+    to: Option<chrono::NaiveDate>,
+    sampled: Option<bool>,
+) -> Result<Presented<EquityHistory>, String> {
+    let (journal, storage) = {
+        let broker = broker.lock_for("get_equity_history")?;
+        (broker.journal_store()?, broker.journal_storage()?)
+    };
+    let mut points = session_equity_curve(&journal.daily_summaries(from, to)?);
+    let (max_dd, max_dd_realized) = fill_drawdowns(&mut points);
+    let sampled = if sampled.unwrap_or(false) {
+        let tiers = EquityHistoryStore::new(storage.cache_dir().join(EQUITY_HISTORY_DIR)).load()?;
+        Some(tiers.stitch(from, to))
+    } else {
+        None
+    };
+    Ok(Presented(EquityHistory { points, max_dd, max_dd_realized, sampled }))
+}
+
+/// How often equity is sampled into the equity history and how long samples stay at
+/// full and 15-minute resolution before being compacted
+#[tauri::command]
+pub async fn set_equity_retention(
+    broker: tauri::State<'_, BrokerHandle>,
+    retention: EquityRetention,
+) -> Result<EquityRetention, String> {
+    let mut broker = broker.lock_for("set_equity_retention")?;
+    broker.set_equity_retention(retention)
+}
+
+#[tauri::command]
+pub async fn get_commission_suggestions(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<Vec<CommissionSuggestion>, String> {
+    let broker = broker.lock_for("get_commission_suggestions")?;
+    Ok(broker.get_commission_suggestions())
+}
+
+#[tauri::command]
+pub async fn find_optimal_commission_model(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<CommissionModelComparison, String> {
+    let broker = broker.lock_for("find_optimal_commission_model")?;
+    Ok(broker.compare_commission_models())
+}
+
+#[tauri::command]
+pub async fn set_auto_save(
+    broker: tauri::State<'_, BrokerHandle>,
+    enabled: bool,
+) -> Result<(), String> {
+    let mut broker = broker.lock_for("set_auto_save")?;
+    broker.set_auto_save(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_tick_size_rules(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<TickSizeRules, String> {
+    let broker = broker.lock_for("get_tick_size_rules")?;
+    Ok(broker.config.tick_sizes.clone())
+}
+
+/// Price increments order prices must sit on and fills are snapped to
+#[tauri::command]
+pub async fn set_tick_size_rules(
+    broker: tauri::State<'_, BrokerHandle>,
+    rules: TickSizeRules,
+) -> Result<(), String> {
+    let mut broker = broker.lock_for("set_tick_size_rules")?;
+    broker.set_tick_size_rules(rules)
+}
+
+/// Buying power, margin used and each position's requirement under the account type
+#[tauri::command]
+pub async fn get_margin_summary(broker: tauri::State<'_, BrokerHandle>) -> Result<MarginSummary, String> {
+    let broker = broker.lock_for("get_margin_summary")?;
+    Ok(broker.margin_summary())
+}
+
+/// Switch the account between cash and Reg-T margin, optionally replacing the margin rates
+#[tauri::command]
+pub async fn set_account_type(
+    broker: tauri::State<'_, BrokerHandle>,
+    account_type: AccountType,
+    rates: Option<MarginRates>,
+) -> Result<MarginSummary, String> {
+    let mut broker = broker.lock_for("set_account_type")?;
+    broker.set_account_type(account_type, rates)
+}
+
+/// How long broker commands wait for a busy broker before failing with a busy error
+#[tauri::command]
+pub async fn set_broker_lock_timeout(
+    broker: tauri::State<'_, BrokerHandle>,
+    timeout_ms: u64,
+) -> Result<(), String> {
+    if timeout_ms == 0 {
+        return Err("Lock timeout must be at least 1ms".to_string());
+    }
+    broker.set_lock_timeout(std::time::Duration::from_millis(timeout_ms));
+    Ok(())
+}
+
+//
+// ---------- Account Statements ----------
+//
+
+#[tauri::command]
+pub async fn record_cash_adjustment(
+    broker: tauri::State<'_, BrokerHandle>,
+    kind: AdjustmentKind,
+    amount: f64,
+    symbol: Option<String>,
+    note: Option<String>,
+) -> Result<CashAdjustment, String> {
+    let mut broker = broker.lock_for("record_cash_adjustment")?;
+    broker.record_cash_adjustment(kind, amount, symbol, note)
+}
+
+/// Broker records a statement is built from, copied so the build runs without the broker
+struct StatementInputs {
+    trades: Vec<Trade>,
+    summaries: Vec<DailySummary>,
+    adjustments: Vec<CashAdjustment>,
+    splits: Vec<StockSplit>,
+    benchmark: Option<BenchmarkAccount>,
+}
+
+impl StatementInputs {
+    fn collect(broker: &BrokerHandle, year: i32, month: u32) -> Result<Self, String> {
+        let (period_start, _) = month_bounds(year, month)?;
+        let broker = broker.lock_for("generate_statement")?;
+        let today = chrono::DateTime::from_timestamp(broker.now(), 0).unwrap_or_default().date_naive();
+        if period_start > today {
+            return Err(format!("Cannot generate a statement for {:04}-{:02} before it starts", year, month));
+        }
+        Ok(Self {
+            trades: broker.trades.clone(),
+            summaries: broker.daily_summaries.clone(),
+            adjustments: broker.cash_adjustments.clone(),
+            splits: broker.get_applied_splits(),
+            benchmark: broker.benchmark.clone(),
+        })
+    }
+
+    fn build(&self, year: i32, month: u32) -> Result<Statement, String> {
+        let mut statement = build_statement(year, month, &self.trades, &self.summaries, &self.adjustments, &self.splits)?;
+        if let Some(benchmark) = &self.benchmark {
+            statement.compare_with(benchmark);
+        }
+        Ok(statement)
+    }
+}
+
+/// Build the statement for a closed or in-progress month and store it, replacing any
+/// earlier copy for that month
+#[tauri::command]
+pub async fn generate_statement(
+    app: tauri::AppHandle,
+    broker: tauri::State<'_, BrokerHandle>,
+    year: i32,
+    month: u32,
+) -> Result<Statement, String> {
+    let statement = StatementInputs::collect(&broker, year, month)?.build(year, month)?;
+    StatementStore::open(&app)?.save(&statement)?;
+    Ok(statement)
+}
+
+/// `generate_statement` as a background job; returns the job id. A job cancelled
+/// before the build finishes stores nothing.
+#[tauri::command]
+pub async fn start_statement_generation(
+    app: tauri::AppHandle,
+    broker: tauri::State<'_, BrokerHandle>,
+    jobs: tauri::State<'_, JobRegistry>,
+    year: i32,
+    month: u32,
+) -> Result<String, String> {
+    let inputs = StatementInputs::collect(&broker, year, month)?;
+    let store = StatementStore::open(&app)?;
+    Ok(jobs.spawn(BackgroundJobKind::Statement, None, move |job| {
+        let statement = inputs.build(year, month)?;
+        if job.is_cancelled() {
+            return Ok(JobOutput::default());
+        }
+        store.save(&statement)?;
+        Ok(JobOutput { rows: statement.trade_count as u64, ..JobOutput::default() })
+    }))
+}
+
+//
+// ---------- Journal export ----------
+//
+
+/// Stream the journal trades `spec` selects to `spec.path` in the background; returns
+/// the job id. Progress events report rows written after each chunk.
+#[tauri::command]
+pub async fn start_export(
+    broker: tauri::State<'_, BrokerHandle>,
+    jobs: tauri::State<'_, JobRegistry>,
+    spec: ExportSpec,
+) -> Result<String, String> {
+    spec.validate()?;
+    let query = TradeQuery { symbol: spec.query.symbol.as_deref().map(normalize_symbol).transpose()?, ..spec.query.clone() };
+    let journal = broker.lock_for("start_export")?.journal_store()?;
+    let path = PathBuf::from(&spec.path);
+    Ok(jobs.spawn(BackgroundJobKind::Export, Some(spec.path.clone()), move |job| {
+        let result = export_journal(journal.as_ref(), &query, spec.format, &path, job);
+        if result.is_err() || job.is_cancelled() {
+            let _ = std::fs::remove_file(&path);
+        }
+        result
+    }))
+}
+
+fn export_journal(journal: &dyn JournalStore, query: &TradeQuery, format: ExportFormat, path: &Path, job: &JobContext) -> Result<JobOutput, String> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let file = std::fs::File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let mut exporter = ChunkedExporter::new(file, format, EXPORT_CHUNK_ROWS)?;
+    journal.stream_trades(query, &mut |trade| {
+        if let Some((rows, bytes)) = exporter.push(&trade)? {
+            job.progress(rows, bytes);
+        }
+        Ok(!job.is_cancelled())
+    })?;
+    let (rows, bytes) = exporter.finish()?;
+    Ok(JobOutput { rows, bytes, path: Some(path.display().to_string()), ..JobOutput::default() })
+}
+
+/// Status of an export or any other background job, with its row count and file size
+/// once complete
+#[tauri::command]
+pub async fn get_export_status(jobs: tauri::State<'_, JobRegistry>, job_id: String) -> Result<BackgroundJob, String> {
+    jobs.status(&job_id)
+}
+
+/// Stop a running export (or other background job); an export's partial file is removed
+#[tauri::command]
+pub async fn cancel_export(jobs: tauri::State<'_, JobRegistry>, job_id: String) -> Result<(), String> {
+    jobs.cancel(&job_id)
+}
+
+#[tauri::command]
+pub async fn list_statements(app: tauri::AppHandle) -> Result<Vec<StatementEntry>, String> {
+    Ok(StatementStore::open(&app)?.list())
+}
+
+#[tauri::command]
+pub async fn get_statement(app: tauri::AppHandle, year: i32, month: u32) -> Result<Statement, String> {
+    StatementStore::open(&app)?.load(year, month)
+}
@@ -0,0 +1,81 @@
+// src-tauri/src/commands/calendar.rs
+// Market calendar commands
+
+use super::state::BrokerHandle;
+use crate::engine::calendar::TradingSession;
+
+#[tauri::command]
+pub async fn get_current_session(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<TradingSession, String> {
+    let broker = broker.lock_for("get_current_session")?;
+    Ok(broker.get_current_session())
+}
+
+#[tauri::command]
+pub async fn is_market_open(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<bool, String> {
+    let broker = broker.lock_for("is_market_open")?;
+    Ok(broker.is_market_open())
+}
+
+#[tauri::command]
+pub async fn get_next_session_start(
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<Option<i64>, String> {
+    let broker = broker.lock_for("get_next_session_start")?;
+    Ok(broker.get_next_session_start())
+}
+
+#[tauri::command]
+pub async fn configure_extended_hours(
+    broker: tauri::State<'_, BrokerHandle>,
+    premarket: bool,
+    afterhours: bool,
+) -> Result<(), String> {
+    let mut broker = broker.lock_for("configure_extended_hours")?;
+    broker.configure_extended_hours(premarket, afterhours);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_holiday_trading(
+    broker: tauri::State<'_, BrokerHandle>,
+    enabled: bool,
+) -> Result<(), String> {
+    let mut broker = broker.lock_for("set_holiday_trading")?;
+    broker.set_holiday_trading(enabled);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn add_custom_holiday(
+    broker: tauri::State<'_, BrokerHandle>,
+    date: String, // MM/DD/YYYY format
+    name: String,
+    is_early_close: bool,
+) -> Result<(), String> {
+    let naive_date = parse_holiday_date(&date)?;
+    let mut broker = broker.lock_for("add_custom_holiday")?;
+    broker.add_custom_holiday(naive_date, name, is_early_close);
+    Ok(())
+}
+
+/// Parse a MM/DD/YYYY holiday date
+pub fn parse_holiday_date(date: &str) -> Result<chrono::NaiveDate, String> {
+    let date_parts: Vec<&str> = date.split('/').collect();
+    if date_parts.len() != 3 {
+        return Err("Date must be in MM/DD/YYYY format".to_string());
+    }
+
+    let month: u32 = date_parts[0].parse()
+        .map_err(|_| "Invalid month".to_string())?;
+    let day: u32 = date_parts[1].parse()
+        .map_err(|_| "Invalid day".to_string())?;
+    let year: i32 = date_parts[2].parse()
+        .map_err(|_| "Invalid year".to_string())?;
+
+    chrono::NaiveDate::from_ymd_opt(year, month, day)
+        .ok_or("Invalid date".to_string())
+}
@@ -0,0 +1,606 @@
+// src-tauri/src/commands/data.rs
+// Market data commands: provider history and keys, streaming, bulk downloads, the
+// daily bar refresh and option chain snapshots
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tauri::{Emitter, Listener, Manager};
+
+use super::display::Presented;
+use super::state::{BrokerHandle, ProviderRegistry, ProviderStatus};
+use crate::engine::broker::PaperBroker;
+use crate::engine::calendar::MarketCalendar;
+use crate::engine::news_halt::NewsHaltMonitor;
+use crate::engine::news_impact::{self, NewsImpactAnalysis, NEWS_IMPACT_CACHE_SECONDS};
+use crate::engine::option_symbol::{self, OptionSymbolFormat, OptionSymbolParser};
+use crate::engine::rates::{RateInputs, RatePoint};
+use crate::engine::symbols::normalize_symbol;
+use crate::engine::watchlist::{SymbolFlag, WatchlistChange, WatchlistService};
+use crate::market_data::dates;
+use crate::provider::alphavantage as av;
+use crate::provider::diagnostics::{self, ProviderDiagnostics};
+use crate::provider::entitlements::EntitlementProfile;
+use crate::provider::polygon as poly;
+use crate::provider::yahoo as yfin;
+use crate::providers::microstructure::MicrostructureStats;
+use crate::providers::polygon::{self as polygon_stream, OhlcBar, SubscriptionTier};
+use crate::providers::quote_consensus::{QuoteConsensusConfig, QuoteDiagnostics, QuoteVerification};
+use crate::providers::tick_filter::TickFilterConfig;
+use crate::storage::api_budget::{self, ApiBudget, ApiLimits, ApiUsage, RequestEstimate};
+use crate::storage::cache::{self, FileCache};
+use crate::storage::chain_snapshots::{ChainCoverage, ChainRecorder, ChainRecorderConfig, SnapshotEntry, SnapshotSaved};
+use crate::storage::daily_refresh::{DailyRefreshConfig, DailyRefreshReport, DailyRefresher, VolatilityProfile};
+use crate::storage::downloads::{DownloadJob, DownloadManager};
+use crate::storage::journal_store;
+
+/// Save the Polygon key, then probe what its plan covers in the background; the
+/// profile arrives as "provider_entitlements_changed"
+#[tauri::command]
+pub async fn save_api_key(providers: tauri::State<'_, ProviderRegistry>, key: String) -> Result<(), String> {
+    let app = providers.app()?;
+    poly::save_polygon_key(app, key).await?;
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = poly::probe_entitlements(&app).await {
+            eprintln!("Probing the Polygon key's plan failed: {}", e);
+        }
+    });
+    Ok(())
+}
+
+/// Probe what the configured Polygon key's plan covers (delayed or realtime, asset
+/// classes, history depth) and cache the profile; run again after a plan upgrade
+#[tauri::command]
+pub async fn test_provider_key(providers: tauri::State<'_, ProviderRegistry>) -> Result<EntitlementProfile, String> {
+    poly::probe_entitlements(providers.app()?).await
+}
+
+/// The live stream, Polygon's quote-check standing and the key's cached entitlements
+#[tauri::command]
+pub async fn get_provider_status(providers: tauri::State<'_, ProviderRegistry>) -> Result<ProviderStatus, String> {
+    Ok(providers.provider_status().await)
+}
+
+#[tauri::command]
+pub async fn fetch_history(
+    providers: tauri::State<'_, ProviderRegistry>,
+    symbol: String,
+    start: String,
+    end: String,
+    interval: Option<String>,
+    acknowledge_large_request: Option<bool>,
+    extended_hours: Option<bool>,
+) -> Result<Presented<Vec<poly::Bar>>, String> {
+    let acknowledged = acknowledge_large_request.unwrap_or(false);
+    let extended_hours = extended_hours.unwrap_or(false);
+    let (start, end) = (dates::parse(&start)?, dates::parse(&end)?);
+    let candles = poly::fetch_history_budgeted(providers.app()?, symbol, start, end, interval, acknowledged, extended_hours).await?;
+    Ok(Presented(candles.iter().map(poly::Bar::from).collect()))
+}
+
+/// Calls and bars `fetch_history` would cost for this range, before making it
+#[tauri::command]
+pub fn estimate_history_request(
+    symbol: String,
+    start: String,
+    end: String,
+    interval: Option<String>,
+    extended_hours: Option<bool>,
+) -> Result<RequestEstimate, String> {
+    let symbol = normalize_symbol(&symbol)?;
+    let (start, end) = (dates::parse(&start)?, dates::parse(&end)?);
+    Ok(poly::estimate_request(&symbol, start, end, interval.as_deref(), extended_hours.unwrap_or(false), &MarketCalendar::new()))
+}
+
+/// Today's API calls per provider, the configured limits and the largest recent requests
+#[tauri::command]
+pub async fn get_api_usage(providers: tauri::State<'_, ProviderRegistry>) -> Result<ApiUsage, String> {
+    ApiBudget::open(providers.app()?)?.usage(api_budget::usage_day())
+}
+
+#[tauri::command]
+pub async fn set_api_limits(providers: tauri::State<'_, ProviderRegistry>, limits: ApiLimits) -> Result<ApiLimits, String> {
+    ApiBudget::open(providers.app()?)?.set_limits(limits)
+}
+
+/// Recent provider responses that failed to parse, newest first, any response fields
+/// the providers started sending that the parsers do not know, and the Polygon key's
+/// entitlements
+#[tauri::command]
+pub async fn get_provider_parse_errors(providers: tauri::State<'_, ProviderRegistry>) -> Result<ProviderDiagnostics, String> {
+    let app = providers.app()?;
+    let dir = diagnostics::capture_dir(app).ok_or("Cache directory is not available")?;
+    let mut diagnostics = diagnostics::load_diagnostics(&dir)?;
+    diagnostics.entitlements = poly::current_entitlements(app).await;
+    Ok(diagnostics)
+}
+
+/// Compare Polygon's last trade for each symbol with Yahoo's; each symbol costs one
+/// Polygon call
+#[tauri::command]
+pub async fn verify_quotes(providers: tauri::State<'_, ProviderRegistry>, symbols: Vec<String>) -> Result<QuoteVerification, String> {
+    let symbols: Vec<String> = symbols.iter().map(|s| normalize_symbol(s)).collect::<Result<_, _>>()?;
+    providers.verify_quotes(&symbols).await
+}
+
+#[tauri::command]
+pub async fn get_quote_consensus_config(providers: tauri::State<'_, ProviderRegistry>) -> Result<QuoteConsensusConfig, String> {
+    Ok(providers.quote_consensus().lock().await.config().clone())
+}
+
+#[tauri::command]
+pub async fn set_quote_consensus_config(
+    providers: tauri::State<'_, ProviderRegistry>,
+    config: QuoteConsensusConfig,
+) -> Result<QuoteConsensusConfig, String> {
+    providers.set_quote_consensus_config(config).await
+}
+
+/// Degraded providers, stale streaks and recent quote discrepancies, newest first
+#[tauri::command]
+pub async fn get_quote_diagnostics(providers: tauri::State<'_, ProviderRegistry>) -> Result<QuoteDiagnostics, String> {
+    Ok(providers.quote_consensus().lock().await.diagnostics())
+}
+
+/// Bars kept in the journal store from earlier history fetches, for sessions `from`
+/// through `to`; `interval` is "1d" or "1h"
+#[tauri::command]
+pub async fn query_cached_bars(
+    providers: tauri::State<'_, ProviderRegistry>,
+    symbol: String,
+    interval: String,
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+) -> Result<Presented<Vec<poly::Bar>>, String> {
+    let symbol = normalize_symbol(&symbol)?;
+    let cache = FileCache::new(providers.app()?)?;
+    let backend = journal_store::configured_backend(&cache);
+    let store = journal_store::open_store(cache, backend);
+    let bounds = (journal_store::session_start(from), to.succ_opt().and_then(journal_store::session_start));
+    let (Some(start), Some(end)) = bounds else {
+        return Err(format!("Invalid range {} to {}", from, to));
+    };
+    let candles = store.query_bars(&symbol, &interval, start, end - 1)?;
+    Ok(Presented(candles.iter().map(poly::Bar::from).collect()))
+}
+
+/// Write a series from the file bar cache out as JSON for debugging or export; returns
+/// the path written
+#[tauri::command]
+pub async fn export_cached_bars_json(
+    providers: tauri::State<'_, ProviderRegistry>,
+    symbol: String,
+    interval: String,
+) -> Result<String, String> {
+    let symbol = normalize_symbol(&symbol)?;
+    let cache = FileCache::new(providers.app()?)?;
+    let path = cache.export_bars_json(&symbol, &interval)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub async fn fetch_history_yahoo(
+    providers: tauri::State<'_, ProviderRegistry>,
+    symbol: String,
+    start: String,
+    end: String,
+) -> Result<Presented<Vec<yfin::YBar>>, String> {
+    let capture_dir = providers.app().ok().and_then(diagnostics::capture_dir);
+    let candles = yfin::yahoo_history(symbol, dates::parse(&start)?, dates::parse(&end)?, capture_dir.as_deref()).await?;
+    Ok(Presented(candles.iter().map(yfin::YBar::from).collect()))
+}
+
+#[tauri::command]
+pub async fn fetch_news(providers: tauri::State<'_, ProviderRegistry>, symbol: String, days: u32) -> Result<(f64, Vec<poly::NewsItem>), String> {
+    let app = providers.app()?;
+    let (sentiment, articles) = poly::fetch_news(app, symbol.clone(), days).await?;
+    // News on held and watchlist symbols can halt the strategy's entries in them
+    let symbol = symbol.to_uppercase();
+    if tracked_daily_symbols(app).contains(&symbol) {
+        app.state::<NewsHaltMonitor>().observe(&symbol, &articles, chrono::Utc::now().timestamp());
+    }
+    Ok((sentiment, articles))
+}
+
+/// Price moves over the 1, 3 and 5 trading days after each recent article and how they
+/// line up with article sentiment. Results are cached for an hour.
+#[tauri::command]
+pub async fn analyze_news_impact(
+    providers: tauri::State<'_, ProviderRegistry>,
+    symbol: String,
+    lookback_days: u32,
+) -> Result<NewsImpactAnalysis, String> {
+    let app = providers.app()?;
+    let symbol = normalize_symbol(&symbol)?;
+    let key = cache::cache_key_for_news_impact(&symbol, lookback_days);
+    if let Some(analysis) = FileCache::new(app)?.get::<NewsImpactAnalysis>(&key)? {
+        return Ok(analysis);
+    }
+
+    let (_, news) = poly::fetch_news(app, symbol.clone(), lookback_days).await?;
+    // A week of bars before the lookback gives the oldest articles a prior close
+    let end = chrono::Utc::now().date_naive();
+    let start = end - chrono::Duration::days(lookback_days as i64 + 7);
+    let candles = poly::fetch_history(app, symbol.clone(), start, end, Some("1day".into())).await?;
+
+    let analysis = news_impact::analyze_news_impact(&symbol, &news, &candles);
+    FileCache::new(app)?.set(&key, analysis.clone(), Some(NEWS_IMPACT_CACHE_SECONDS))?;
+    Ok(analysis)
+}
+
+#[tauri::command]
+pub async fn save_alphavantage_key(providers: tauri::State<'_, ProviderRegistry>, key: String) -> Result<(), String> {
+    av::save_alphavantage_key(providers.app()?, key).await
+}
+
+#[tauri::command]
+pub async fn fetch_historical_option_chain(
+    providers: tauri::State<'_, ProviderRegistry>,
+    symbol: String,
+    date: Option<String>,
+) -> Result<Presented<av::OptionChain>, String> {
+    let date = date.as_deref().map(dates::parse).transpose()?;
+    av::fetch_option_chain(providers.app()?, symbol, date).await.map(Presented)
+}
+
+#[tauri::command]
+pub async fn fetch_earnings_calendar(
+    providers: tauri::State<'_, ProviderRegistry>,
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: Option<String>,
+    horizon: Option<String>,
+) -> Result<Presented<Vec<av::EarningsEvent>>, String> {
+    let events = av::fetch_earnings_calendar(providers.app()?, symbol, horizon).await?;
+    // Held positions reporting soon are badged from these dates
+    broker.lock_for("fetch_earnings_calendar")?.record_earnings(&events);
+    Ok(Presented(events))
+}
+
+/// Set the risk-free curve from the latest Treasury yields across TREASURY_MATURITIES
+#[tauri::command]
+pub async fn fetch_rate_curve(
+    providers: tauri::State<'_, ProviderRegistry>,
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<Presented<RateInputs>, String> {
+    let yields = av::fetch_treasury_yields(providers.app()?).await?;
+    // Quoted as annual percentages; the curve holds continuous rates
+    let points = yields.iter().map(|y| RatePoint { tenor_years: y.tenor_years, rate: (1.0 + y.yield_pct / 100.0).ln() }).collect();
+    let as_of = yields.iter().map(|y| y.as_of).max();
+    broker.lock_for("fetch_rate_curve")?.set_rate_curve(points, as_of).map(Presented)
+}
+
+/// `symbol`'s dividend history; its trailing twelve months set the yield options on it are priced with
+#[tauri::command]
+pub async fn fetch_dividends(
+    providers: tauri::State<'_, ProviderRegistry>,
+    broker: tauri::State<'_, BrokerHandle>,
+    symbol: String,
+) -> Result<Presented<Vec<av::DividendEvent>>, String> {
+    let events = av::fetch_dividends(providers.app()?, symbol.clone()).await?;
+    broker.lock_for("fetch_dividends")?.record_dividends(&symbol, &events)?;
+    Ok(Presented(events))
+}
+
+#[tauri::command]
+pub async fn fetch_polygon_bars(
+    symbol: String,
+    from: String,
+    to: String,
+    apikey: String
+) -> serde_json::Value {
+    // Construct Polygon API URL
+    let url = format!(
+        "https://api.polygon.io/v2/aggs/ticker/{}/range/1/day/{}/{}?adjusted=true&sort=asc&apikey={}",
+        symbol.to_uppercase(),
+        from,
+        to,
+        apikey
+    );
+
+    // Make HTTP request
+    match reqwest::get(&url).await {
+        Ok(response) => {
+            match response.json::<serde_json::Value>().await {
+                Ok(data) => data,
+                Err(e) => {
+                    eprintln!("Failed to parse Polygon response: {}", e);
+                    serde_json::json!({
+                        "status": "ERROR",
+                        "error": "Failed to parse response"
+                    })
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to fetch from Polygon: {}", e);
+            serde_json::json!({
+                "status": "ERROR",
+                "error": format!("HTTP request failed: {}", e)
+            })
+        }
+    }
+}
+
+// Additional command stubs to prevent "command not found" errors
+#[tauri::command]
+pub async fn fetch_option_chain(_symbol: String, _expiry: String) -> serde_json::Value {
+    serde_json::json!({
+        "status": "stub",
+        "chains": []
+    })
+}
+
+#[tauri::command]
+pub async fn fetch_option_quotes(_symbols: Vec<String>) -> serde_json::Value {
+    serde_json::json!({
+        "status": "stub",
+        "quotes": []
+    })
+}
+
+/// Convert an option symbol between the Polygon/OCC and Bloomberg formats
+#[tauri::command]
+pub fn normalize_option_symbol(symbol: String, target_format: OptionSymbolFormat) -> Result<String, String> {
+    option_symbol::normalize_option_symbol(&symbol, target_format)
+}
+
+#[tauri::command]
+pub async fn store_api_key(providers: tauri::State<'_, ProviderRegistry>, key: String) -> Result<(), String> {
+    // Alias for save_api_key for backward compatibility
+    save_api_key(providers, key).await
+}
+
+#[tauri::command]
+pub async fn test_api_connection() -> Result<String, String> {
+    Ok("Connection test not implemented".to_string())
+}
+
+//
+// ---------- Realtime Data & Streaming ----------
+//
+
+#[tauri::command]
+pub async fn fetch_ohlc(
+    providers: tauri::State<'_, ProviderRegistry>,
+    symbol: String,
+    start: String,
+    end: String,
+    tf: String,
+) -> Result<Vec<OhlcBar>, String> {
+    let provider = providers.polygon()?;
+    let candles = provider.fetch_ohlc(&symbol, dates::parse(&start)?, dates::parse(&end)?, &tf).await?;
+    Ok(candles.iter().map(OhlcBar::aggregate).collect())
+}
+
+#[tauri::command]
+pub async fn start_stream(
+    providers: tauri::State<'_, ProviderRegistry>,
+    symbols: Vec<String>,
+) -> Result<(), String> {
+    providers.start_stream(symbols).await
+}
+
+/// Stream `symbols` with the tier named for each ("priority", "standard", "background",
+/// or "auto"). Symbols with open positions are always Priority; "auto" puts stream-flagged
+/// watchlist symbols in Standard and the rest in Background.
+#[tauri::command]
+pub async fn subscribe_symbols_tiered(
+    broker: tauri::State<'_, BrokerHandle>,
+    watchlists: tauri::State<'_, WatchlistService>,
+    providers: tauri::State<'_, ProviderRegistry>,
+    symbols: HashMap<String, String>,
+) -> Result<(), String> {
+    let open_positions = held_underlyings(&*broker.lock_for("subscribe_symbols_tiered")?);
+    let watchlist: HashSet<String> = watchlists.symbols_with(SymbolFlag::Stream).into_iter().collect();
+
+    let mut tiered = HashMap::new();
+    for (symbol, tier) in symbols {
+        let symbol = normalize_symbol(&symbol)?;
+        let tier = SubscriptionTier::assign(&symbol, SubscriptionTier::parse(&tier)?, &open_positions, &watchlist);
+        tiered.insert(symbol, tier);
+    }
+    providers.start_stream_tiered(tiered).await
+}
+
+/// Follow "watchlist_changed" on the running stream: newly stream-flagged symbols are
+/// subscribed at Standard (Priority when held) and cleared ones unsubscribed unless held,
+/// all over the open socket
+pub fn attach_watchlist_stream(app: &tauri::AppHandle) {
+    let handle = app.clone();
+    app.listen_any("watchlist_changed", move |event| {
+        let Ok(change) = serde_json::from_str::<WatchlistChange>(event.payload()) else {
+            return;
+        };
+        if change.stream.is_empty() {
+            return;
+        }
+        let app = handle.clone();
+        tauri::async_runtime::spawn(async move {
+            let held = app
+                .state::<BrokerHandle>()
+                .lock_for("watchlist_stream")
+                .map(|broker| held_underlyings(&broker))
+                .unwrap_or_default();
+            let watchlist: HashSet<String> = change.stream.subscribe.iter().cloned().collect();
+            let subscribe = change
+                .stream
+                .subscribe
+                .iter()
+                .map(|s| (s.clone(), SubscriptionTier::assign(s, None, &held, &watchlist)))
+                .collect();
+            let unsubscribe = change.stream.unsubscribe.into_iter().filter(|s| !held.contains(s)).collect();
+            if let Err(e) = app.state::<ProviderRegistry>().update_stream_subscriptions(subscribe, unsubscribe).await {
+                eprintln!("Updating stream subscriptions from the watchlist failed: {}", e);
+            }
+        });
+    });
+}
+
+/// Symbols with open positions, options as their underlying
+fn held_underlyings(broker: &PaperBroker) -> HashSet<String> {
+    broker
+        .get_portfolio()
+        .positions
+        .values()
+        .filter(|p| p.quantity != 0)
+        .map(|p| OptionSymbolParser::parse(&p.symbol).map_or_else(|| p.symbol.clone(), |d| d.underlying))
+        .collect()
+}
+
+#[tauri::command]
+pub async fn stop_stream(providers: tauri::State<'_, ProviderRegistry>) -> Result<(), String> {
+    providers.stop_stream().await?;
+    let _ = providers.app()?.emit("stream_stop_requested", ());
+    Ok(())
+}
+
+/// Rolling 1-minute trade and quote statistics from the live stream
+#[tauri::command]
+pub async fn get_microstructure_stats(
+    providers: tauri::State<'_, ProviderRegistry>,
+    symbol: String,
+) -> Result<MicrostructureStats, String> {
+    let symbol = normalize_symbol(&symbol)?;
+    polygon_stream::microstructure_stats(providers.microstructure(), &symbol)
+        .await
+        .ok_or_else(|| format!("No streamed data for {}", symbol))
+}
+
+#[tauri::command]
+pub async fn get_tick_filter_config(providers: tauri::State<'_, ProviderRegistry>) -> Result<TickFilterConfig, String> {
+    Ok(providers.tick_filter().lock().await.config().clone())
+}
+
+/// Set how far a streamed print may stray from the last accepted price before it is
+/// quarantined as a `suspect_tick` instead of published
+#[tauri::command]
+pub async fn set_tick_filter_config(
+    providers: tauri::State<'_, ProviderRegistry>,
+    config: TickFilterConfig,
+) -> Result<TickFilterConfig, String> {
+    let mut filter = providers.tick_filter().lock().await;
+    filter.set_config(config)?;
+    Ok(filter.config().clone())
+}
+
+//
+// ---------- bulk history downloads ----------
+//
+
+#[tauri::command]
+pub async fn queue_history_download(
+    downloads: tauri::State<'_, DownloadManager>,
+    symbols: Vec<String>,
+    start: String,
+    end: String,
+    interval: Option<String>,
+) -> Result<Presented<DownloadJob>, String> {
+    let interval = interval.unwrap_or_else(|| "1day".to_string());
+    downloads.queue(symbols, &start, &end, &interval).await.map(Presented)
+}
+
+/// Warm_cache-flagged watchlist symbols and held symbols, the ones the daily refresh
+/// keeps warm. A busy broker leaves its symbols out of this run.
+pub fn tracked_daily_symbols(app: &tauri::AppHandle) -> Vec<String> {
+    let mut symbols: HashSet<String> = app
+        .state::<BrokerHandle>()
+        .lock_for("tracked_daily_symbols")
+        .map(|broker| held_underlyings(&broker))
+        .unwrap_or_default();
+    symbols.extend(app.state::<WatchlistService>().symbols_with(SymbolFlag::WarmCache));
+    let mut symbols: Vec<String> = symbols.into_iter().filter_map(|s| normalize_symbol(&s).ok()).collect();
+    symbols.sort();
+    symbols
+}
+
+/// Fetch the latest finished session's daily bars for `symbols` (watchlist and held
+/// symbols when omitted) and recompute their volatility profiles
+#[tauri::command]
+pub async fn refresh_daily_data(refresher: tauri::State<'_, DailyRefresher>, symbols: Option<Vec<String>>) -> Result<DailyRefreshReport, String> {
+    refresher.refresh_now(symbols).await
+}
+
+#[tauri::command]
+pub async fn get_daily_refresh_config(refresher: tauri::State<'_, DailyRefresher>) -> Result<DailyRefreshConfig, String> {
+    Ok(refresher.config().await)
+}
+
+#[tauri::command]
+pub async fn configure_daily_refresh(
+    refresher: tauri::State<'_, DailyRefresher>,
+    config: DailyRefreshConfig,
+) -> Result<DailyRefreshConfig, String> {
+    refresher.configure(config).await
+}
+
+/// ATR and realized volatility per symbol as of the last refresh
+#[tauri::command]
+pub async fn get_volatility_profiles(refresher: tauri::State<'_, DailyRefresher>) -> Result<BTreeMap<String, VolatilityProfile>, String> {
+    refresher.profiles()
+}
+
+#[tauri::command]
+pub async fn get_download_jobs(downloads: tauri::State<'_, DownloadManager>) -> Result<Presented<Vec<DownloadJob>>, String> {
+    Ok(Presented(downloads.list().await))
+}
+
+#[tauri::command]
+pub async fn pause_download_job(downloads: tauri::State<'_, DownloadManager>, job_id: String) -> Result<Presented<DownloadJob>, String> {
+    downloads.pause(&job_id).await.map(Presented)
+}
+
+#[tauri::command]
+pub async fn resume_download_job(downloads: tauri::State<'_, DownloadManager>, job_id: String) -> Result<Presented<DownloadJob>, String> {
+    downloads.resume(&job_id).await.map(Presented)
+}
+
+#[tauri::command]
+pub async fn cancel_download_job(downloads: tauri::State<'_, DownloadManager>, job_id: String) -> Result<Presented<DownloadJob>, String> {
+    downloads.cancel(&job_id).await.map(Presented)
+}
+
+//
+// ---------- option chain snapshots ----------
+//
+
+#[tauri::command]
+pub async fn snapshot_option_chain(recorder: tauri::State<'_, ChainRecorder>, symbol: String) -> Result<SnapshotSaved, String> {
+    recorder.snapshot(&symbol).await
+}
+
+#[tauri::command]
+pub async fn list_chain_snapshots(recorder: tauri::State<'_, ChainRecorder>, symbol: String) -> Result<Vec<SnapshotEntry>, String> {
+    Ok(recorder.list(&symbol).await)
+}
+
+#[tauri::command]
+pub async fn get_chain_snapshot(
+    recorder: tauri::State<'_, ChainRecorder>,
+    symbol: String,
+    date: String,
+) -> Result<Presented<Option<av::OptionChain>>, String> {
+    recorder.load(&symbol, dates::parse(&date)?).await.map(Presented)
+}
+
+/// Per-day real vs modeled chain data for an options backtest range
+#[tauri::command]
+pub async fn get_chain_coverage(
+    recorder: tauri::State<'_, ChainRecorder>,
+    symbol: String,
+    start: String,
+    end: String,
+) -> Result<ChainCoverage, String> {
+    Ok(recorder.coverage(&symbol, dates::parse(&start)?, dates::parse(&end)?).await)
+}
+
+#[tauri::command]
+pub async fn get_chain_recorder_config(recorder: tauri::State<'_, ChainRecorder>) -> Result<ChainRecorderConfig, String> {
+    Ok(recorder.config().await)
+}
+
+#[tauri::command]
+pub async fn configure_chain_recorder(
+    recorder: tauri::State<'_, ChainRecorder>,
+    config: ChainRecorderConfig,
+) -> Result<ChainRecorderConfig, String> {
+    recorder.configure(config).await
+}
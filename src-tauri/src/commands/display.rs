@@ -0,0 +1,206 @@
+// src-tauri/src/commands/display.rs
+// Presentation of command responses. Engine types carry native dates (YYYY-MM-DD, or
+// epoch seconds for bars); the UI still reads the string fields they used to carry, such
+// as an option's "expiry" and an equity point's "t". Responses wrapped in `Presented`
+// get those fields back, computed from the native ones in the user's date format, next
+// to the native fields. They are deprecated: new UI code reads the native fields. The
+// format also holds the decimal separator and currency placement the UI shows amounts
+// with; amounts stay numbers in every response.
+
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::sync::RwLock;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DateFormat {
+    #[default]
+    MonthDayYear, // 01/19/2024, what the legacy fields always held
+    DayMonthYear, // 19/01/2024
+    Iso,          // 2024-01-19
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DecimalSeparator {
+    #[default]
+    Point, // 1,234.56
+    Comma, // 1.234,56
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CurrencyPosition {
+    #[default]
+    Before, // $12.50
+    After,  // 12,50 €
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DisplayFormat {
+    pub date_format: DateFormat,
+    pub decimal_separator: DecimalSeparator,
+    pub currency_symbol: String,
+    pub currency_position: CurrencyPosition,
+}
+
+impl Default for DisplayFormat {
+    fn default() -> Self {
+        Self {
+            date_format: DateFormat::default(),
+            decimal_separator: DecimalSeparator::default(),
+            currency_symbol: "$".to_string(),
+            currency_position: CurrencyPosition::default(),
+        }
+    }
+}
+
+impl DisplayFormat {
+    pub fn validate(&self) -> Result<(), String> {
+        let symbol = self.currency_symbol.trim();
+        if symbol.is_empty() || symbol.chars().count() > 4 {
+            return Err("Currency symbol must be 1 to 4 characters".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn date(&self, date: NaiveDate) -> String {
+        let pattern = match self.date_format {
+            DateFormat::MonthDayYear => "%m/%d/%Y",
+            DateFormat::DayMonthYear => "%d/%m/%Y",
+            DateFormat::Iso => "%Y-%m-%d",
+        };
+        date.format(pattern).to_string()
+    }
+}
+
+static CURRENT: RwLock<Option<DisplayFormat>> = RwLock::new(None);
+
+/// The format responses are presented in
+pub fn current() -> DisplayFormat {
+    CURRENT.read().ok().and_then(|f| f.clone()).unwrap_or_default()
+}
+
+pub fn set_current(format: DisplayFormat) {
+    if let Ok(mut current) = CURRENT.write() {
+        *current = Some(format);
+    }
+}
+
+/// A deprecated string field computed from a native date field, on objects that also
+/// carry every field in `alongside`
+struct LegacyField {
+    native: &'static str,
+    legacy: &'static str,
+    alongside: &'static [&'static str],
+}
+
+const LEGACY_FIELDS: &[LegacyField] = &[
+    // Option details, assignments, expirations and chain contracts
+    LegacyField { native: "expiry_date", legacy: "expiry", alongside: &["strike"] },
+    // Equity curve points
+    LegacyField { native: "date", legacy: "t", alongside: &["equity", "drawdown"] },
+    // History bars, timed in epoch seconds
+    LegacyField { native: "timestamp", legacy: "date", alongside: &["o", "c"] },
+    // Option chains
+    LegacyField { native: "as_of", legacy: "as_of_date", alongside: &["underlying_symbol"] },
+    LegacyField { native: "expiries", legacy: "expiry_dates", alongside: &["underlying_symbol"] },
+    // Earnings events
+    LegacyField { native: "report_on", legacy: "report_date", alongside: &["fiscal_period_end"] },
+    LegacyField { native: "fiscal_period_end", legacy: "fiscal_date_ending", alongside: &["report_on"] },
+    // Backtest params, optimization specs and results
+    LegacyField { native: "start_on", legacy: "start_date", alongside: &["ticker", "initial_capital"] },
+    LegacyField { native: "end_on", legacy: "end_date", alongside: &["ticker", "initial_capital"] },
+    LegacyField { native: "start_on", legacy: "start_date", alongside: &["symbols", "search_space"] },
+    LegacyField { native: "end_on", legacy: "end_date", alongside: &["symbols", "search_space"] },
+    LegacyField { native: "start_on", legacy: "start", alongside: &["equity_curve"] },
+    LegacyField { native: "end_on", legacy: "end", alongside: &["equity_curve"] },
+    // Download jobs
+    LegacyField { native: "start_on", legacy: "start", alongside: &["tasks", "interval"] },
+    LegacyField { native: "end_on", legacy: "end", alongside: &["tasks", "interval"] },
+    // Pair round trips
+    LegacyField { native: "entry_on", legacy: "entry_date", alongside: &["entry_z", "exit_reason"] },
+    LegacyField { native: "exit_on", legacy: "exit_date", alongside: &["entry_z", "exit_reason"] },
+];
+
+/// A native date, or a list of them, as display strings; None for anything else
+fn legacy_value(native: &Value, format: &DisplayFormat) -> Option<Value> {
+    match native {
+        Value::String(text) => NaiveDate::parse_from_str(text, "%Y-%m-%d").ok().map(|d| Value::String(format.date(d))),
+        Value::Number(seconds) => DateTime::from_timestamp(seconds.as_i64()?, 0).map(|t| Value::String(format.date(t.date_naive()))),
+        Value::Array(items) => items.iter().map(|item| legacy_value(item, format)).collect::<Option<Vec<_>>>().map(Value::Array),
+        _ => None,
+    }
+}
+
+fn add_to_object(object: &mut Map<String, Value>, format: &DisplayFormat) {
+    for field in LEGACY_FIELDS {
+        if object.contains_key(field.legacy) || !field.alongside.iter().all(|key| object.contains_key(*key)) {
+            continue;
+        }
+        if let Some(legacy) = object.get(field.native).and_then(|native| legacy_value(native, format)) {
+            object.insert(field.legacy.to_string(), legacy);
+        }
+    }
+}
+
+/// Add the legacy fields throughout `value`
+pub fn add_legacy_fields(value: &mut Value, format: &DisplayFormat) {
+    match value {
+        Value::Object(object) => {
+            object.values_mut().for_each(|child| add_legacy_fields(child, format));
+            add_to_object(object, format);
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| add_legacy_fields(item, format)),
+        _ => {}
+    }
+}
+
+/// A command response or event payload serialized with the legacy fields in the current format
+#[derive(Clone)]
+pub struct Presented<T>(pub T);
+
+impl<T: Serialize> Serialize for Presented<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut value = serde_json::to_value(&self.0).map_err(serde::ser::Error::custom)?;
+        add_legacy_fields(&mut value, &current());
+        value.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_legacy_fields_follow_the_date_format() {
+        let response = json!({
+            "orders": [{"symbol": "XYZ240119P00090000", "option_details": {"underlying": "XYZ", "strike": 90.0, "expiry_date": "2024-01-19"}}],
+            "curve": [{"date": "2024-01-02", "equity": 100.0, "drawdown": 0.0}],
+            "bars": [{"timestamp": 1704153600, "o": 1.0, "h": 1.0, "l": 1.0, "c": 1.0, "v": 0.0}],
+            "summary": {"date": "2024-01-02", "cash": 5.0},
+            "chain": {"underlying_symbol": "XYZ", "as_of": "2024-01-02", "expiries": ["2024-01-19", "2024-02-16"]},
+        });
+
+        let mut legacy = response.clone();
+        add_legacy_fields(&mut legacy, &DisplayFormat::default());
+        assert_eq!(legacy["orders"][0]["option_details"]["expiry"], "01/19/2024");
+        assert_eq!(legacy["orders"][0]["option_details"]["expiry_date"], "2024-01-19");
+        assert_eq!(legacy["curve"][0]["t"], "01/02/2024");
+        assert_eq!(legacy["bars"][0]["date"], "01/02/2024");
+        assert_eq!(legacy["chain"]["expiry_dates"], json!(["01/19/2024", "02/16/2024"]));
+        // Only objects of the right shape gain a field
+        assert!(legacy["summary"].get("t").is_none());
+
+        let european = DisplayFormat { date_format: DateFormat::DayMonthYear, currency_symbol: "€".to_string(), ..DisplayFormat::default() };
+        assert!(european.validate().is_ok());
+        let mut value = response;
+        add_legacy_fields(&mut value, &european);
+        assert_eq!(value["orders"][0]["option_details"]["expiry"], "19/01/2024");
+        assert_eq!(value["chain"]["as_of_date"], "02/01/2024");
+        assert!(DisplayFormat { currency_symbol: String::new(), ..DisplayFormat::default() }.validate().is_err());
+    }
+}
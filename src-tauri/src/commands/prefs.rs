@@ -0,0 +1,407 @@
+// src-tauri/src/commands/prefs.rs
+// Utility, preference and configuration bundle commands
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use super::backtest::BacktestParams;
+use super::display::{self, DisplayFormat, Presented};
+use super::state::{block_on, BrokerHandle, ProviderRegistry, StrategyLoopHandle};
+use crate::engine::broker::PaperBroker;
+use crate::engine::calendar::MarketCalendar;
+use crate::engine::r#loop::StrategyLoopConfig;
+use crate::engine::risk::RiskLimits;
+use crate::engine::types::BrokerConfig;
+use crate::provider::alphavantage as av;
+use crate::provider::polygon as poly;
+use crate::storage::config_bundle::{self as bundle, BundleManifest, ConfigBundle, ImportReport};
+use crate::storage::journal_store::{self, MigrationReport, StorageBackendKind};
+use crate::storage::ui_state::UiStateStore;
+
+// Backtest defaults live in the UI state store; config.json is their pre-store home
+pub const BACKTEST_NAMESPACE: &str = "backtest";
+const BACKTEST_DEFAULTS_KEY: &str = "defaults";
+const DISPLAY_NAMESPACE: &str = "display";
+const DISPLAY_FORMAT_KEY: &str = "format";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PingResponse {
+    ok: bool,
+    ts: u64,
+}
+
+#[tauri::command]
+pub async fn ping() -> PingResponse {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    PingResponse { ok: true, ts }
+}
+
+#[tauri::command]
+pub async fn load_preferences(providers: tauri::State<'_, ProviderRegistry>) -> Result<Presented<Option<BacktestParams>>, String> {
+    read_preferences(&providers).map(Presented)
+}
+
+#[tauri::command]
+pub async fn save_preferences(
+    providers: tauri::State<'_, ProviderRegistry>,
+    preferences: BacktestParams,
+) -> Result<(), String> {
+    write_preferences(&providers, &preferences)
+}
+
+pub fn read_preferences(providers: &ProviderRegistry) -> Result<Option<BacktestParams>, String> {
+    ui_state(providers)?
+        .get(BACKTEST_NAMESPACE, BACKTEST_DEFAULTS_KEY)?
+        .map(|v| serde_json::from_value(v).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+pub fn write_preferences(providers: &ProviderRegistry, preferences: &BacktestParams) -> Result<(), String> {
+    let v = serde_json::json!({
+        "ticker": preferences.ticker,
+        "start_on": preferences.start_date,
+        "end_on": preferences.end_date,
+        "strategy": preferences.strategy,
+        "initial_capital": preferences.initial_capital,
+        "seed": preferences.seed,
+        "warm_job_id": preferences.warm_job_id,
+        "transaction_costs": preferences.transaction_costs,
+        "cost_model": preferences.cost_model,
+        "pair": preferences.pair
+    });
+    ui_state(providers)?.set(BACKTEST_NAMESPACE, BACKTEST_DEFAULTS_KEY, &v.to_string())?;
+    Ok(())
+}
+
+/// The UI state store. The first open moves an old config.json's backtest defaults into
+/// the "backtest" namespace and renames the file to config.json.migrated.
+pub fn ui_state(providers: &ProviderRegistry) -> Result<UiStateStore, String> {
+    let store = UiStateStore::new(providers.ui_state_path());
+    let legacy = providers.prefs_path();
+    if legacy.exists() {
+        let text = fs::read_to_string(&legacy).map_err(|e| e.to_string())?;
+        match serde_json::from_str::<BacktestParams>(&text) {
+            Ok(_) if store.get(BACKTEST_NAMESPACE, BACKTEST_DEFAULTS_KEY)?.is_some() => {}
+            Ok(_) => {
+                store.set(BACKTEST_NAMESPACE, BACKTEST_DEFAULTS_KEY, &text)?;
+            }
+            // Left in place for the user to fix; the store works without it
+            Err(e) => {
+                eprintln!("Not migrating {}: {}", legacy.display(), e);
+                return Ok(store);
+            }
+        }
+        fs::rename(&legacy, legacy.with_extension("json.migrated")).map_err(|e| e.to_string())?;
+    }
+    Ok(store)
+}
+
+//
+// ---------- Display format ----------
+//
+
+/// How responses present dates, and how the UI shows decimals and currency
+#[tauri::command]
+pub async fn get_display_format(providers: tauri::State<'_, ProviderRegistry>) -> Result<DisplayFormat, String> {
+    Ok(read_display_format(&providers)?.unwrap_or_default())
+}
+
+/// Store `format` and present every later response in it
+#[tauri::command]
+pub async fn set_display_format(providers: tauri::State<'_, ProviderRegistry>, format: DisplayFormat) -> Result<DisplayFormat, String> {
+    format.validate()?;
+    let value = serde_json::to_string(&format).map_err(|e| e.to_string())?;
+    ui_state(&providers)?.set(DISPLAY_NAMESPACE, DISPLAY_FORMAT_KEY, &value)?;
+    display::set_current(format.clone());
+    Ok(format)
+}
+
+fn read_display_format(providers: &ProviderRegistry) -> Result<Option<DisplayFormat>, String> {
+    ui_state(providers)?
+        .get(DISPLAY_NAMESPACE, DISPLAY_FORMAT_KEY)?
+        .map(|v| serde_json::from_value(v).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+/// Present responses in the stored format from startup on; the default when none is stored
+pub fn restore_display_format(providers: &ProviderRegistry) {
+    match read_display_format(providers) {
+        Ok(Some(format)) => display::set_current(format),
+        Ok(None) => {}
+        Err(e) => eprintln!("Using the default display format: {}", e),
+    }
+}
+
+//
+// ---------- UI state ----------
+//
+
+/// Store `value_json` (any JSON document) under `namespace`/`key`. Returns the
+/// "namespace/key" entries evicted to stay within the store's budget.
+#[tauri::command]
+pub async fn set_ui_state(
+    providers: tauri::State<'_, ProviderRegistry>,
+    namespace: String,
+    key: String,
+    value_json: String,
+) -> Result<Vec<String>, String> {
+    ui_state(&providers)?.set(&namespace, &key, &value_json)
+}
+
+#[tauri::command]
+pub async fn get_ui_state(
+    providers: tauri::State<'_, ProviderRegistry>,
+    namespace: String,
+    key: String,
+) -> Result<Option<serde_json::Value>, String> {
+    ui_state(&providers)?.get(&namespace, &key)
+}
+
+#[tauri::command]
+pub async fn get_ui_namespace(
+    providers: tauri::State<'_, ProviderRegistry>,
+    namespace: String,
+) -> Result<BTreeMap<String, serde_json::Value>, String> {
+    ui_state(&providers)?.get_namespace(&namespace)
+}
+
+#[tauri::command]
+pub async fn clear_ui_state(providers: tauri::State<'_, ProviderRegistry>, namespace: String) -> Result<usize, String> {
+    ui_state(&providers)?.clear_namespace(&namespace)
+}
+
+//
+// ---------- Storage backend ----------
+//
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StorageBackendStatus {
+    pub configured: StorageBackendKind,
+    pub active: StorageBackendKind, // The file backend when SQLite could not be opened
+    pub sqlite_available: bool,     // Built with the "sqlite" feature
+}
+
+fn storage_backend_status(broker: &PaperBroker) -> Result<StorageBackendStatus, String> {
+    Ok(StorageBackendStatus {
+        configured: journal_store::configured_backend(&broker.journal_storage()?),
+        active: broker.journal_store()?.backend(),
+        sqlite_available: journal_store::sqlite_available(),
+    })
+}
+
+#[tauri::command]
+pub async fn get_storage_backend(broker: tauri::State<'_, BrokerHandle>) -> Result<StorageBackendStatus, String> {
+    let broker = broker.lock_for("get_storage_backend")?;
+    storage_backend_status(&broker)
+}
+
+/// Keep the trade journal, equity history and bar cache in `backend` from now on.
+/// SQLite is available once migrate_journal_to_sqlite has run.
+#[tauri::command]
+pub async fn set_storage_backend(
+    broker: tauri::State<'_, BrokerHandle>,
+    backend: StorageBackendKind,
+) -> Result<StorageBackendStatus, String> {
+    let mut broker = broker.lock_for("set_storage_backend")?;
+    broker.switch_journal_backend(backend)?;
+    storage_backend_status(&broker)
+}
+
+/// Import the trade journal, daily summaries and history cache into the SQLite
+/// database and verify the row counts. Runs once; the file backend stays active until
+/// set_storage_backend switches.
+#[tauri::command]
+pub async fn migrate_journal_to_sqlite(
+    providers: tauri::State<'_, ProviderRegistry>,
+    broker: tauri::State<'_, BrokerHandle>,
+) -> Result<MigrationReport, String> {
+    let (storage, summaries) = {
+        let broker = broker.lock_for("migrate_journal_to_sqlite")?;
+        (broker.journal_storage()?, broker.daily_summaries.clone())
+    };
+    let bars = poly::cached_history_series(providers.app()?)?;
+    journal_store::migrate_to_sqlite(&storage, &summaries, &bars)
+}
+
+//
+// ---------- Configuration bundles ----------
+//
+
+fn collect_configuration(
+    providers: &ProviderRegistry,
+    broker: &PaperBroker,
+    loop_config: &StrategyLoopConfig,
+) -> Result<BTreeMap<String, serde_json::Value>, String> {
+    let mut sections = BTreeMap::new();
+    let to_value = |v: serde_json::Result<serde_json::Value>| v.map_err(|e| e.to_string());
+
+    if let Some(preferences) = ui_state(providers)?.get(BACKTEST_NAMESPACE, BACKTEST_DEFAULTS_KEY)? {
+        sections.insert(bundle::SECTION_PREFERENCES.to_string(), preferences);
+    }
+    sections.insert(bundle::SECTION_RISK_LIMITS.to_string(), to_value(serde_json::to_value(&broker.risk_engine.limits))?);
+    sections.insert(bundle::SECTION_STRATEGY_LOOP.to_string(), to_value(serde_json::to_value(loop_config))?);
+    sections.insert(bundle::SECTION_FEE_SCHEDULE.to_string(), to_value(serde_json::to_value(&broker.config))?);
+    sections.insert(bundle::SECTION_CALENDAR.to_string(), to_value(serde_json::to_value(&broker.market_calendar))?);
+
+    let keys = providers.stored_keys()?;
+    if keys.as_object().is_some_and(|k| !k.is_empty()) {
+        sections.insert(bundle::SECTION_SECRETS.to_string(), keys);
+    }
+
+    Ok(sections)
+}
+
+fn write_bundle(path: &Path, config: &ConfigBundle) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(config).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn export_configuration(
+    providers: tauri::State<'_, ProviderRegistry>,
+    broker: tauri::State<'_, BrokerHandle>,
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+    path: String,
+    include_secrets: bool,
+) -> Result<BundleManifest, String> {
+    let loop_config = {
+        let loop_guard = strategy_loop.lock()?;
+        block_on(loop_guard.get_config())
+    };
+    let sections = {
+        let broker = broker.lock_for("export_configuration")?;
+        collect_configuration(&providers, &broker, &loop_config)?
+    };
+
+    let config = ConfigBundle::new(sections, include_secrets);
+    write_bundle(Path::new(&path), &config)?;
+    Ok(config.manifest)
+}
+
+/// Validate a bundle and diff it against the current configuration. Unless `dry_run`,
+/// back up the current configuration and apply the selected sections; every section is
+/// parsed before anything changes, and the loop config is rolled back if a later step fails.
+#[tauri::command]
+pub fn import_configuration(
+    providers: tauri::State<'_, ProviderRegistry>,
+    broker: tauri::State<'_, BrokerHandle>,
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+    path: String,
+    sections: Option<Vec<String>>,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let incoming: ConfigBundle = serde_json::from_str(&text).map_err(|e| format!("Invalid configuration bundle: {}", e))?;
+    incoming.validate()?;
+    let selected = incoming.selected_sections(sections.as_deref())?;
+
+    let mut loop_guard = strategy_loop.lock()?;
+    let mut broker = broker.lock_for("import_configuration")?;
+    let loop_config = block_on(loop_guard.get_config());
+    let current = collect_configuration(&providers, &broker, &loop_config)?;
+
+    let mut report = ImportReport {
+        dry_run,
+        bundle_created_at: incoming.manifest.created_at,
+        sections: selected
+            .iter()
+            .map(|name| bundle::diff_section(name, current.get(name).unwrap_or(&serde_json::Value::Null), &incoming.sections[name]))
+            .collect(),
+        applied: Vec::new(),
+        backup_path: None,
+    };
+    if dry_run {
+        return Ok(report);
+    }
+
+    // Parse everything up front so a bad section aborts before any change
+    let section = |name: &str| selected.iter().any(|s| s == name).then(|| incoming.sections[name].clone());
+    let parse_err = |name: &str, e: serde_json::Error| format!("Section {} is invalid: {}", name, e);
+    let preferences = section(bundle::SECTION_PREFERENCES)
+        .map(|v| serde_json::from_value::<BacktestParams>(v).map_err(|e| parse_err(bundle::SECTION_PREFERENCES, e)))
+        .transpose()?;
+    let risk_limits = section(bundle::SECTION_RISK_LIMITS)
+        .map(|v| serde_json::from_value::<RiskLimits>(v).map_err(|e| parse_err(bundle::SECTION_RISK_LIMITS, e)))
+        .transpose()?;
+    let new_loop_config = section(bundle::SECTION_STRATEGY_LOOP)
+        .map(|v| serde_json::from_value::<StrategyLoopConfig>(v).map_err(|e| parse_err(bundle::SECTION_STRATEGY_LOOP, e)))
+        .transpose()?;
+    let fee_schedule = section(bundle::SECTION_FEE_SCHEDULE)
+        .map(|v| serde_json::from_value::<BrokerConfig>(v).map_err(|e| parse_err(bundle::SECTION_FEE_SCHEDULE, e)))
+        .transpose()?;
+    if let Some(config) = &fee_schedule {
+        config.order_limits.validate().map_err(|e| format!("Section {} is invalid: {}", bundle::SECTION_FEE_SCHEDULE, e))?;
+    }
+    let calendar = section(bundle::SECTION_CALENDAR)
+        .map(|v| serde_json::from_value::<MarketCalendar>(v).map_err(|e| parse_err(bundle::SECTION_CALENDAR, e)))
+        .transpose()?;
+    let secrets = section(bundle::SECTION_SECRETS)
+        .map(|v| serde_json::from_value::<HashMap<String, String>>(v).map_err(|e| parse_err(bundle::SECTION_SECRETS, e)))
+        .transpose()?;
+
+    // Pre-import backup of everything, secrets included, next to the app config
+    let backup_path = providers
+        .backups_dir()
+        .join(format!("config-backup-{}.json", chrono::Utc::now().format("%Y%m%d-%H%M%S")));
+    write_bundle(&backup_path, &ConfigBundle::new(current, true))?;
+    report.backup_path = Some(backup_path.display().to_string());
+
+    // Fallible steps first: the loop refuses config changes while running
+    if let Some(config) = new_loop_config {
+        block_on(loop_guard.update_config(config))?;
+        report.applied.push(bundle::SECTION_STRATEGY_LOOP.to_string());
+    }
+    if let Some(preferences) = preferences {
+        if let Err(e) = write_preferences(&providers, &preferences) {
+            if report.applied.iter().any(|s| s == bundle::SECTION_STRATEGY_LOOP) {
+                let _ = block_on(loop_guard.update_config(loop_config));
+            }
+            return Err(format!("Failed to write preferences, import rolled back: {}", e));
+        }
+        report.applied.push(bundle::SECTION_PREFERENCES.to_string());
+    }
+
+    if let Some(limits) = risk_limits {
+        broker.risk_engine.limits = limits;
+        report.applied.push(bundle::SECTION_RISK_LIMITS.to_string());
+    }
+    if let Some(config) = fee_schedule {
+        broker.config = config;
+        report.applied.push(bundle::SECTION_FEE_SCHEDULE.to_string());
+    }
+    if let Some(calendar) = calendar {
+        broker.market_calendar = calendar;
+        report.applied.push(bundle::SECTION_CALENDAR.to_string());
+    }
+    if let Err(e) = broker.save_state() {
+        eprintln!("Failed to save broker state after import: {}", e);
+    }
+
+    // Keys go back through the providers' own key storage
+    if let Some(secrets) = secrets {
+        let app = providers.app()?;
+        block_on(async {
+            for (provider, key) in secrets {
+                match provider.as_str() {
+                    "polygon" => poly::save_polygon_key(app, key).await?,
+                    "alphavantage" => av::save_alphavantage_key(app, key).await?,
+                    _ => {}
+                }
+            }
+            Ok::<(), String>(())
+        })?;
+        report.applied.push(bundle::SECTION_SECRETS.to_string());
+    }
+
+    Ok(report)
+}
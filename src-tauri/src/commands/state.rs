@@ -0,0 +1,615 @@
+// src-tauri/src/commands/state.rs
+// Managed state shared by the command modules. Each wrapper is a plain struct so
+// command logic can be exercised in tests without a running Tauri app.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+use tauri::{Emitter, Manager};
+use uuid::Uuid;
+
+use crate::engine::broker::PaperBroker;
+use crate::engine::r#loop::StrategyLoop;
+use crate::providers::microstructure::MicrostructureStore;
+use crate::providers::quote_consensus::{
+    self, PolygonQuotes, QuoteConsensus, QuoteConsensusConfig, QuoteConsensusHandle, QuoteVerification, YahooQuotes,
+};
+use crate::providers::tick_filter::TickFilterHandle;
+use crate::provider::diagnostics;
+use crate::provider::entitlements::{Entitlement, EntitlementProfile};
+use crate::provider::polygon as poly;
+use crate::storage::api_budget::{self, ApiBudget};
+use crate::storage::cache::FileCache;
+use crate::storage::downloads::MIN_REQUEST_INTERVAL_SECS;
+use crate::providers::polygon::{PolygonProvider, SubscriptionTier, SubscriptionTiers};
+
+pub const DEFAULT_BROKER_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_RETRY_MAX: Duration = Duration::from_millis(16);
+
+/// Why a broker command could not get the broker
+#[derive(Debug, Clone, PartialEq)]
+pub enum BrokerLockError {
+    /// Another command held the broker for the whole timeout
+    Busy {
+        operation: &'static str,
+        held_by: Option<&'static str>,
+        held_for: Duration,
+        timeout: Duration,
+    },
+    Poisoned(String),
+}
+
+impl fmt::Display for BrokerLockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BrokerLockError::Busy { operation, held_by, held_for, timeout } => write!(
+                f,
+                "Broker busy: {} gave up after {}ms; {} has held the broker for {}ms",
+                operation,
+                timeout.as_millis(),
+                held_by.unwrap_or("another operation"),
+                held_for.as_millis()
+            ),
+            BrokerLockError::Poisoned(e) => write!(f, "Lock error: {}", e),
+        }
+    }
+}
+
+impl From<BrokerLockError> for String {
+    fn from(e: BrokerLockError) -> Self {
+        e.to_string()
+    }
+}
+
+/// The paper broker the command layer trades against. Every lock names the
+/// operation taking it, so a command that times out can say what it waited on.
+pub struct BrokerHandle {
+    broker: Mutex<PaperBroker>,
+    holder: Mutex<Option<(&'static str, Instant)>>,
+    timeout_ms: AtomicU64,
+}
+
+impl BrokerHandle {
+    pub fn new(broker: PaperBroker) -> Self {
+        Self {
+            broker: Mutex::new(broker),
+            holder: Mutex::new(None),
+            timeout_ms: AtomicU64::new(DEFAULT_BROKER_LOCK_TIMEOUT.as_millis() as u64),
+        }
+    }
+
+    pub fn lock_timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms.load(Ordering::Relaxed))
+    }
+
+    pub fn set_lock_timeout(&self, timeout: Duration) {
+        self.timeout_ms.store(timeout.as_millis().max(1) as u64, Ordering::Relaxed);
+    }
+
+    /// Lock the broker for `operation`, waiting at most the lock timeout
+    pub fn lock_for(&self, operation: &'static str) -> Result<BrokerGuard<'_>, BrokerLockError> {
+        let timeout = self.lock_timeout();
+        let started = Instant::now();
+        let mut retry = Duration::from_millis(1);
+
+        loop {
+            match self.broker.try_lock() {
+                Ok(guard) => {
+                    self.set_holder(Some((operation, Instant::now())));
+                    return Ok(BrokerGuard { guard, holder: &self.holder });
+                }
+                Err(TryLockError::Poisoned(e)) => return Err(BrokerLockError::Poisoned(e.to_string())),
+                Err(TryLockError::WouldBlock) if started.elapsed() >= timeout => {
+                    let holder = *self.holder.lock().unwrap_or_else(|e| e.into_inner());
+                    return Err(BrokerLockError::Busy {
+                        operation,
+                        held_by: holder.map(|(name, _)| name),
+                        held_for: holder.map(|(_, since)| since.elapsed()).unwrap_or_default(),
+                        timeout,
+                    });
+                }
+                Err(TryLockError::WouldBlock) => {
+                    std::thread::sleep(retry.min(timeout.saturating_sub(started.elapsed())));
+                    retry = (retry * 2).min(LOCK_RETRY_MAX);
+                }
+            }
+        }
+    }
+
+    /// Operation holding the broker right now, if any
+    #[cfg(test)]
+    pub fn held_by(&self) -> Option<&'static str> {
+        self.holder.lock().unwrap_or_else(|e| e.into_inner()).map(|(name, _)| name)
+    }
+
+    fn set_holder(&self, holder: Option<(&'static str, Instant)>) {
+        *self.holder.lock().unwrap_or_else(|e| e.into_inner()) = holder;
+    }
+}
+
+/// Broker access for one operation; clears the holder before the lock is released
+pub struct BrokerGuard<'a> {
+    guard: MutexGuard<'a, PaperBroker>,
+    holder: &'a Mutex<Option<(&'static str, Instant)>>,
+}
+
+impl Deref for BrokerGuard<'_> {
+    type Target = PaperBroker;
+
+    fn deref(&self) -> &PaperBroker {
+        &self.guard
+    }
+}
+
+impl DerefMut for BrokerGuard<'_> {
+    fn deref_mut(&mut self) -> &mut PaperBroker {
+        &mut self.guard
+    }
+}
+
+impl Drop for BrokerGuard<'_> {
+    fn drop(&mut self) {
+        *self.holder.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+}
+
+/// The strategy loop; its async API is driven through `block_on` from sync commands
+pub struct StrategyLoopHandle(Mutex<StrategyLoop>);
+
+impl StrategyLoopHandle {
+    pub fn new(strategy_loop: StrategyLoop) -> Self {
+        Self(Mutex::new(strategy_loop))
+    }
+
+    pub fn lock(&self) -> Result<MutexGuard<'_, StrategyLoop>, String> {
+        self.0.lock().map_err(|e| format!("Lock error: {}", e))
+    }
+
+    /// The loop, unless a command is holding it right now
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, StrategyLoop>> {
+        self.0.try_lock().ok()
+    }
+}
+
+/// Run a future to completion from a sync command on the multi-threaded runtime
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+}
+
+/// Market data providers and the app config directory. Providers that need the
+/// app handle (key storage, caches, event streams) are unavailable when offline.
+pub struct ProviderRegistry {
+    app: Option<tauri::AppHandle>,
+    config_dir: PathBuf,
+    microstructure: MicrostructureStore, // Shared by every stream the registry starts
+    tiers: SubscriptionTiers,            // Likewise
+    tick_filter: TickFilterHandle,       // Likewise
+    quote_consensus: QuoteConsensusHandle,
+    stream: Arc<tokio::sync::Mutex<Option<PolygonProvider>>>, // The live stream, if one was started
+}
+
+/// The live stream as the supervisor reports it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StreamStatus {
+    pub streaming: bool,
+    pub connected: bool,
+    pub symbols: Vec<String>,
+    pub reconnect_attempts: u32,
+}
+
+/// The live stream, whether quote checks have Polygon degraded, and what its key's
+/// plan covers
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProviderStatus {
+    pub stream: StreamStatus,
+    pub polygon_degraded: bool,
+    pub entitlements: Option<EntitlementProfile>, // None until the configured key is probed
+}
+
+const QUOTE_CONSENSUS_CONFIG_KEY: &str = "quote_consensus_config";
+
+impl ProviderRegistry {
+    pub fn new(app: &tauri::AppHandle) -> Result<Self, String> {
+        let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?.join("trading-app");
+        let quote_config = FileCache::new(app)
+            .and_then(|mut cache| cache.get(QUOTE_CONSENSUS_CONFIG_KEY))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let quote_log = config_dir.join(quote_consensus::DISCREPANCY_LOG_FILE);
+        Ok(Self {
+            app: Some(app.clone()),
+            config_dir,
+            microstructure: MicrostructureStore::default(),
+            tiers: SubscriptionTiers::default(),
+            tick_filter: TickFilterHandle::default(),
+            quote_consensus: Arc::new(tokio::sync::Mutex::new(QuoteConsensus::new(quote_config, quote_log))),
+            stream: Arc::default(),
+        })
+    }
+
+    /// Registry without network providers, rooted at `config_dir`
+    #[cfg(test)]
+    pub fn offline(config_dir: PathBuf) -> Self {
+        let quote_log = config_dir.join(quote_consensus::DISCREPANCY_LOG_FILE);
+        Self {
+            app: None,
+            config_dir,
+            microstructure: MicrostructureStore::default(),
+            tiers: SubscriptionTiers::default(),
+            tick_filter: TickFilterHandle::default(),
+            quote_consensus: Arc::new(tokio::sync::Mutex::new(QuoteConsensus::new(QuoteConsensusConfig::default(), quote_log))),
+            stream: Arc::default(),
+        }
+    }
+
+    pub fn app(&self) -> Result<&tauri::AppHandle, String> {
+        self.app.as_ref().ok_or_else(|| "Data providers are not available offline".to_string())
+    }
+
+    pub fn polygon(&self) -> Result<PolygonProvider, String> {
+        Ok(PolygonProvider::new(self.app()?.clone())
+            .with_microstructure(self.microstructure.clone())
+            .with_subscription_tiers(self.tiers.clone())
+            .with_tick_filter(self.tick_filter.clone()))
+    }
+
+    /// Stream `symbols`, replacing the stream started before. Refused up front when the
+    /// key's plan has no realtime websockets.
+    pub async fn start_stream(&self, symbols: Vec<String>) -> Result<(), String> {
+        poly::require_entitlement(self.app()?, Entitlement::RealtimeStream).await?;
+        let mut stream = self.stream.lock().await;
+        if let Some(mut running) = stream.take() {
+            running.stop_stream().await?;
+        }
+        let mut provider = self.polygon()?;
+        provider.start_stream(symbols).await?;
+        *stream = Some(provider);
+        Ok(())
+    }
+
+    /// Stream `symbols` at their tiers, replacing the stream started before
+    pub async fn start_stream_tiered(&self, symbols: HashMap<String, SubscriptionTier>) -> Result<(), String> {
+        poly::require_entitlement(self.app()?, Entitlement::RealtimeStream).await?;
+        let mut stream = self.stream.lock().await;
+        if let Some(mut running) = stream.take() {
+            running.stop_stream().await?;
+        }
+        let mut provider = self.polygon()?;
+        provider.start_stream_tiered(symbols).await?;
+        *stream = Some(provider);
+        Ok(())
+    }
+
+    /// Subscribe and unsubscribe on the running stream without reconnecting; nothing to
+    /// do when no stream is running
+    pub async fn update_stream_subscriptions(
+        &self,
+        subscribe: HashMap<String, SubscriptionTier>,
+        unsubscribe: Vec<String>,
+    ) -> Result<(), String> {
+        match self.stream.lock().await.as_mut() {
+            Some(provider) if provider.is_streaming() => provider.update_subscriptions(subscribe, unsubscribe).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Stop the live stream; returns the symbols it was streaming
+    pub async fn stop_stream(&self) -> Result<Vec<String>, String> {
+        let Some(mut running) = self.stream.lock().await.take() else {
+            return Ok(Vec::new());
+        };
+        let symbols = running.subscribed_symbols().await;
+        running.stop_stream().await?;
+        Ok(symbols)
+    }
+
+    pub async fn stream_status(&self) -> StreamStatus {
+        match self.stream.lock().await.as_ref() {
+            Some(provider) => {
+                let connection = provider.get_connection_status().await;
+                StreamStatus {
+                    streaming: provider.is_streaming(),
+                    connected: connection.connected,
+                    symbols: provider.subscribed_symbols().await,
+                    reconnect_attempts: connection.reconnect_attempts,
+                }
+            }
+            None => StreamStatus { streaming: false, connected: false, symbols: Vec::new(), reconnect_attempts: 0 },
+        }
+    }
+
+    pub async fn provider_status(&self) -> ProviderStatus {
+        let entitlements = match &self.app {
+            Some(app) => poly::current_entitlements(app).await,
+            None => None,
+        };
+        ProviderStatus {
+            stream: self.stream_status().await,
+            polygon_degraded: self.is_degraded(api_budget::POLYGON).await,
+            entitlements,
+        }
+    }
+
+    /// Stale symbols of the live stream by tier; sends "stale_data_alert" when any are
+    pub async fn check_stream_staleness(&self) -> BTreeMap<SubscriptionTier, Vec<String>> {
+        match self.stream.lock().await.as_ref() {
+            Some(provider) if provider.is_streaming() => provider.check_data_staleness_by_tier().await,
+            _ => BTreeMap::new(),
+        }
+    }
+
+    pub fn microstructure(&self) -> &MicrostructureStore {
+        &self.microstructure
+    }
+
+    pub fn tick_filter(&self) -> &TickFilterHandle {
+        &self.tick_filter
+    }
+
+    pub fn quote_consensus(&self) -> &QuoteConsensusHandle {
+        &self.quote_consensus
+    }
+
+    /// Whether quote checks have marked `provider` degraded
+    pub async fn is_degraded(&self, provider: &str) -> bool {
+        self.quote_consensus.lock().await.is_degraded(provider)
+    }
+
+    pub async fn set_quote_consensus_config(&self, config: QuoteConsensusConfig) -> Result<QuoteConsensusConfig, String> {
+        let config = config.validated()?;
+        if let Some(app) = &self.app {
+            FileCache::new(app).and_then(|mut cache| cache.set(QUOTE_CONSENSUS_CONFIG_KEY, config.clone(), None))?;
+        }
+        let mut consensus = self.quote_consensus.lock().await;
+        consensus.set_config(config)?;
+        Ok(consensus.config().clone())
+    }
+
+    /// Compare Polygon's last trades for `symbols` with Yahoo's
+    pub async fn verify_quotes(&self, symbols: &[String]) -> Result<QuoteVerification, String> {
+        Ok(verify_quotes_with(self.app()?, &self.quote_consensus, symbols).await)
+    }
+
+    /// Run the configured quote checks every `interval_minutes` while enabled, one symbol
+    /// per Polygon request interval. A run is skipped when it would dip into the calls
+    /// reserved for everything else.
+    pub fn start_quote_checks(&self) -> Result<JoinHandle<()>, String> {
+        let app = self.app()?.clone();
+        let consensus = self.quote_consensus.clone();
+        Ok(tauri::async_runtime::spawn(async move {
+            loop {
+                let config = consensus.lock().await.config().clone();
+                if config.enabled && !config.symbols.is_empty() {
+                    let today = api_budget::usage_day();
+                    let remaining = ApiBudget::open(&app).and_then(|budget| budget.remaining(api_budget::POLYGON, today));
+                    match remaining {
+                        Ok(remaining) if remaining.saturating_sub(config.reserved_calls) as usize >= config.symbols.len() => {
+                            for symbol in &config.symbols {
+                                let verification = verify_quotes_with(&app, &consensus, std::slice::from_ref(symbol)).await;
+                                if let Some(e) = verification.checks.iter().find_map(|c| c.error.as_ref()) {
+                                    eprintln!("Quote check for {} failed: {}", symbol, e);
+                                }
+                                tokio::time::sleep(Duration::from_secs(MIN_REQUEST_INTERVAL_SECS)).await;
+                            }
+                        }
+                        Ok(remaining) => eprintln!("Skipping quote checks: {} Polygon calls left today", remaining),
+                        Err(e) => eprintln!("Skipping quote checks: {}", e),
+                    }
+                }
+                tokio::time::sleep(Duration::from_secs(config.interval_minutes.max(1) * 60)).await;
+            }
+        }))
+    }
+
+    pub fn prefs_path(&self) -> PathBuf {
+        self.config_dir.join("config.json")
+    }
+
+    pub fn ui_state_path(&self) -> PathBuf {
+        self.config_dir.join("ui_state.json")
+    }
+
+    pub fn backtests_dir(&self) -> PathBuf {
+        self.config_dir.join("backtests")
+    }
+
+    /// Configuration backups, written before each import
+    pub fn backups_dir(&self) -> PathBuf {
+        self.config_dir.join("backups")
+    }
+
+    /// Stored provider API keys; none when offline
+    pub fn stored_keys(&self) -> Result<serde_json::Value, String> {
+        match &self.app {
+            Some(app) => poly::read_stored_keys(app),
+            None => Ok(serde_json::json!({})),
+        }
+    }
+}
+
+/// Run one verification and send "quote_discrepancy" for each discrepancy and
+/// "provider_degraded" when the primary is newly marked degraded
+async fn verify_quotes_with(app: &tauri::AppHandle, consensus: &QuoteConsensusHandle, symbols: &[String]) -> QuoteVerification {
+    let primary = PolygonQuotes(app.clone());
+    let secondary = YahooQuotes(diagnostics::capture_dir(app));
+    let verification = quote_consensus::verify(consensus, &primary, &secondary, symbols, chrono::Utc::now().timestamp()).await;
+    for check in verification.checks.iter().filter(|c| c.discrepancy) {
+        let _ = app.emit("quote_discrepancy", check);
+    }
+    if let Some(degraded) = &verification.degraded {
+        let _ = app.emit("provider_degraded", degraded);
+    }
+    verification
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum BackgroundJobKind {
+    Export,
+    Statement,
+    Optimization,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum BackgroundJobStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// A long-running command working in the background. Progress arrives as
+/// "job_progress" events and the finished job as "job_complete".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackgroundJob {
+    pub id: String,
+    pub kind: BackgroundJobKind,
+    pub status: BackgroundJobStatus,
+    pub rows_written: u64,
+    pub bytes_written: u64,
+    pub total_rows: Option<u64>, // When the job knows its size up front
+    pub path: Option<String>,
+    pub result_id: Option<String>, // Stored result the job produced, e.g. an optimization
+    pub error: Option<String>,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+/// What a job produced: rows, bytes and where they went
+#[derive(Debug, Clone, Default)]
+pub struct JobOutput {
+    pub rows: u64,
+    pub bytes: u64,
+    pub path: Option<String>,
+    pub result_id: Option<String>,
+}
+
+type JobTable = Arc<Mutex<HashMap<String, (BackgroundJob, Arc<AtomicBool>)>>>;
+
+/// Exports, statement generation and other long commands run here, sharing one status
+/// and cancellation model
+#[derive(Clone)]
+pub struct JobRegistry {
+    app: Option<tauri::AppHandle>,
+    jobs: JobTable,
+}
+
+/// Handed to a running job for reporting progress and checking for cancellation
+pub struct JobContext {
+    id: String,
+    cancelled: Arc<AtomicBool>,
+    registry: JobRegistry,
+}
+
+impl JobContext {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn progress(&self, rows: u64, bytes: u64) {
+        if let Some(job) = self.registry.update(&self.id, |job| {
+            job.rows_written = rows;
+            job.bytes_written = bytes;
+        }) {
+            self.registry.emit("job_progress", &job);
+        }
+    }
+
+    pub fn set_total(&self, rows: u64) {
+        self.registry.update(&self.id, |job| job.total_rows = Some(rows));
+    }
+}
+
+impl JobRegistry {
+    pub fn new(app: &tauri::AppHandle) -> Self {
+        Self { app: Some(app.clone()), jobs: JobTable::default() }
+    }
+
+    /// Run `work` on a blocking thread; returns the job id at once. A job cancelled
+    /// while running ends Cancelled whatever `work` returns.
+    pub fn spawn<F>(&self, kind: BackgroundJobKind, path: Option<String>, work: F) -> String
+    where
+        F: FnOnce(&JobContext) -> Result<JobOutput, String> + Send + 'static,
+    {
+        let id = Uuid::new_v4().to_string();
+        let job = BackgroundJob {
+            id: id.clone(),
+            kind,
+            status: BackgroundJobStatus::Running,
+            rows_written: 0,
+            bytes_written: 0,
+            total_rows: None,
+            path,
+            result_id: None,
+            error: None,
+            started_at: chrono::Utc::now().timestamp(),
+            finished_at: None,
+        };
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner()).insert(id.clone(), (job, cancelled.clone()));
+
+        let context = JobContext { id: id.clone(), cancelled, registry: self.clone() };
+        tauri::async_runtime::spawn_blocking(move || {
+            let result = work(&context);
+            let cancelled = context.is_cancelled();
+            let finished = context.registry.update(&context.id, |job| {
+                job.finished_at = Some(chrono::Utc::now().timestamp());
+                match result {
+                    _ if cancelled => job.status = BackgroundJobStatus::Cancelled,
+                    Ok(output) => {
+                        job.status = BackgroundJobStatus::Completed;
+                        job.rows_written = output.rows;
+                        job.bytes_written = output.bytes;
+                        job.path = output.path.or(job.path.take());
+                        job.result_id = output.result_id;
+                    }
+                    Err(e) => {
+                        job.status = BackgroundJobStatus::Failed;
+                        job.error = Some(e);
+                    }
+                }
+            });
+            if let Some(job) = finished {
+                context.registry.emit("job_complete", &job);
+            }
+        });
+        id
+    }
+
+    pub fn status(&self, id: &str) -> Result<BackgroundJob, String> {
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        jobs.get(id).map(|(job, _)| job.clone()).ok_or_else(|| format!("No job {}", id))
+    }
+
+    /// Ask a running job to stop; it finishes as Cancelled once it notices
+    pub fn cancel(&self, id: &str) -> Result<(), String> {
+        let jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let (job, cancelled) = jobs.get(id).ok_or_else(|| format!("No job {}", id))?;
+        if job.status != BackgroundJobStatus::Running {
+            return Err(format!("Job {} has already finished", id));
+        }
+        cancelled.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn update(&self, id: &str, change: impl FnOnce(&mut BackgroundJob)) -> Option<BackgroundJob> {
+        let mut jobs = self.jobs.lock().unwrap_or_else(|e| e.into_inner());
+        let (job, _) = jobs.get_mut(id)?;
+        change(job);
+        Some(job.clone())
+    }
+
+    fn emit(&self, event: &str, job: &BackgroundJob) {
+        if let Some(app) = &self.app {
+            let _ = app.emit(event, job);
+        }
+    }
+}
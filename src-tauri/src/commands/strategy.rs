@@ -0,0 +1,419 @@
+// src-tauri/src/commands/strategy.rs
+// Strategy loop commands. The loop's API is async; these run it to completion.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use tauri::Manager;
+
+use super::prefs::ui_state;
+use super::state::{block_on, BrokerHandle, ProviderRegistry, StrategyLoopHandle};
+use crate::engine::bars::Timeframe;
+use crate::engine::decision_outcomes::{signal_performance, DecisionReport, SignalPerformance};
+use crate::engine::indicators::{IndicatorSeries, IndicatorSpec};
+use crate::engine::news_halt::{NewsHalt, NewsHaltConfig, NewsHaltMonitor, NewsHaltRule};
+use crate::engine::promotion::{PromotionChecklist, PromotionCheck, PromotionFacts, PromotionRecord};
+use crate::engine::risk::RiskLimits;
+use crate::engine::r#loop::{DeadLetterEntry, LoopState, StrategyLoopConfig};
+use crate::engine::symbols::normalize_symbol;
+use crate::engine::scanner::{self, SavedScan, ScanFilter, ScanResult};
+use crate::engine::watchlist::{SymbolFlags, WatchlistService, Watchlists, DEFAULT_WATCHLIST};
+use crate::providers::polygon::OhlcBar;
+use crate::storage::cache::FileCache;
+use crate::storage::config_bundle::ConfigChange;
+use crate::storage::strategy_presets::{PresetSource, PresetStore, StrategyPreset};
+
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Check the strategy loop's heartbeat and the live stream's staleness from a plain
+/// thread, so a stuck runtime worker cannot also stop the watchdog. The thread exits
+/// at its next check once `stop` is set.
+pub fn start_watchdog(app: &tauri::AppHandle, stop: Arc<AtomicBool>) -> std::thread::JoinHandle<()> {
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(WATCHDOG_INTERVAL);
+        if stop.load(Ordering::SeqCst) {
+            return;
+        }
+        // A command holding the loop (start, warm-up, config) is not a stall; check next time
+        let handle = app.state::<StrategyLoopHandle>();
+        if let Some(mut strategy_loop) = handle.try_lock() {
+            tauri::async_runtime::block_on(strategy_loop.check_liveness());
+        };
+        tauri::async_runtime::block_on(app.state::<ProviderRegistry>().check_stream_staleness());
+    })
+}
+
+#[tauri::command]
+pub fn start_strategy_loop(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+) -> Result<(), String> {
+    let mut loop_guard = strategy_loop.lock()?;
+    block_on(loop_guard.start())
+}
+
+#[tauri::command]
+pub fn stop_strategy_loop(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+) -> Result<(), String> {
+    let mut loop_guard = strategy_loop.lock()?;
+    block_on(loop_guard.stop())
+}
+
+#[tauri::command]
+pub fn get_strategy_loop_state(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+) -> Result<LoopState, String> {
+    let loop_guard = strategy_loop.lock()?;
+    Ok(block_on(loop_guard.get_state()))
+}
+
+#[tauri::command]
+pub fn get_strategy_loop_config(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+) -> Result<StrategyLoopConfig, String> {
+    let loop_guard = strategy_loop.lock()?;
+    Ok(block_on(loop_guard.get_config()))
+}
+
+#[tauri::command]
+pub fn update_strategy_loop_config(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+    config: StrategyLoopConfig,
+) -> Result<(), String> {
+    let mut loop_guard = strategy_loop.lock()?;
+    block_on(loop_guard.update_config(config))
+}
+
+#[tauri::command]
+pub fn reset_strategy_loop_state(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+) -> Result<(), String> {
+    let mut loop_guard = strategy_loop.lock()?;
+    block_on(loop_guard.reset_state())
+}
+
+#[tauri::command]
+pub fn get_strategy_bars(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+    symbol: String,
+    timeframe_minutes: u32,
+) -> Result<Vec<OhlcBar>, String> {
+    let loop_guard = strategy_loop.lock()?;
+    let candles = block_on(loop_guard.get_bars_at_timeframe(&symbol, timeframe_minutes));
+    Ok(candles.iter().map(OhlcBar::from).collect())
+}
+
+/// `indicator` on `symbol`'s `timeframe` bars, the values the signals were given, from
+/// `from` through `to` (bar timestamps). `downsampled` reads the series multi-timeframe
+/// signals use, built from 1-minute bars.
+#[tauri::command]
+pub fn get_indicator_series(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+    symbol: String,
+    timeframe: Timeframe,
+    indicator: IndicatorSpec,
+    from: Option<i64>,
+    to: Option<i64>,
+    downsampled: Option<bool>,
+) -> Result<IndicatorSeries, String> {
+    let symbol = normalize_symbol(&symbol)?;
+    let loop_guard = strategy_loop.lock()?;
+    block_on(loop_guard.indicator_series(&symbol, timeframe, downsampled.unwrap_or(false), indicator, (from, to)))
+}
+
+#[tauri::command]
+pub fn get_dead_letter_queue(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+) -> Result<Vec<DeadLetterEntry>, String> {
+    let loop_guard = strategy_loop.lock()?;
+    Ok(block_on(loop_guard.get_dead_letter_queue()))
+}
+
+#[tauri::command]
+pub fn clear_dead_letter_queue(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+) -> Result<u32, String> {
+    let loop_guard = strategy_loop.lock()?;
+    Ok(block_on(loop_guard.clear_dead_letter_queue()))
+}
+
+#[tauri::command]
+pub fn resume_symbol(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+    symbol: String,
+) -> Result<(), String> {
+    let loop_guard = strategy_loop.lock()?;
+    block_on(loop_guard.resume_symbol(&symbol.to_uppercase()))
+}
+
+#[tauri::command]
+pub fn get_news_halt_config(news_halts: tauri::State<'_, NewsHaltMonitor>) -> Result<NewsHaltConfig, String> {
+    news_halts.config()
+}
+
+#[tauri::command]
+pub fn configure_news_halts(
+    news_halts: tauri::State<'_, NewsHaltMonitor>,
+    config: NewsHaltConfig,
+) -> Result<NewsHaltConfig, String> {
+    news_halts.configure(config)
+}
+
+/// Set `symbol`'s rule (the default rule when omitted); no rule puts the symbol back
+/// on the default
+#[tauri::command]
+pub fn set_news_halt_rule(
+    news_halts: tauri::State<'_, NewsHaltMonitor>,
+    symbol: Option<String>,
+    rule: Option<NewsHaltRule>,
+) -> Result<NewsHaltConfig, String> {
+    news_halts.set_rule(symbol.as_deref(), rule)
+}
+
+#[tauri::command]
+pub fn get_news_halts(news_halts: tauri::State<'_, NewsHaltMonitor>) -> Result<Vec<NewsHalt>, String> {
+    Ok(news_halts.active_halts(chrono::Utc::now().timestamp()))
+}
+
+/// Lift a news halt before its cool-off ends
+#[tauri::command]
+pub fn clear_news_halt(news_halts: tauri::State<'_, NewsHaltMonitor>, symbol: String) -> Result<NewsHalt, String> {
+    news_halts.clear(&symbol.to_uppercase(), chrono::Utc::now().timestamp())
+}
+
+#[tauri::command]
+pub fn get_watchlists(watchlists: tauri::State<'_, WatchlistService>) -> Result<Watchlists, String> {
+    watchlists.get()
+}
+
+#[tauri::command]
+pub fn create_watchlist(watchlists: tauri::State<'_, WatchlistService>, name: String) -> Result<Watchlists, String> {
+    watchlists.create(&name)
+}
+
+/// Append `symbol` to `list` ("main" when omitted) with `flags`, all on when omitted
+#[tauri::command]
+pub fn add_to_watchlist(
+    watchlists: tauri::State<'_, WatchlistService>,
+    symbol: String,
+    list: Option<String>,
+    flags: Option<SymbolFlags>,
+) -> Result<Watchlists, String> {
+    watchlists.add(list.as_deref().unwrap_or(DEFAULT_WATCHLIST), &symbol, flags.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn remove_from_watchlist(
+    watchlists: tauri::State<'_, WatchlistService>,
+    symbol: String,
+    list: Option<String>,
+) -> Result<Watchlists, String> {
+    watchlists.remove(list.as_deref().unwrap_or(DEFAULT_WATCHLIST), &symbol)
+}
+
+/// Replace the flags of `symbol` on `list` ("main" when omitted); a cleared trade flag
+/// stops the running loop evaluating it from its next symbol on
+#[tauri::command]
+pub fn set_symbol_flags(
+    watchlists: tauri::State<'_, WatchlistService>,
+    symbol: String,
+    flags: SymbolFlags,
+    list: Option<String>,
+) -> Result<Watchlists, String> {
+    watchlists.set_flags(list.as_deref().unwrap_or(DEFAULT_WATCHLIST), &symbol, flags)
+}
+
+#[tauri::command]
+pub fn run_scan(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+    filters: ScanFilter,
+    symbols: Option<Vec<String>>,
+) -> Result<ScanResult, String> {
+    let mut loop_guard = strategy_loop.lock()?;
+    block_on(loop_guard.run_scan(&filters, symbols))
+}
+
+#[tauri::command]
+pub fn save_scan(
+    app: tauri::AppHandle,
+    name: String,
+    filters: ScanFilter,
+    symbols: Option<Vec<String>>,
+) -> Result<(), String> {
+    let mut cache = FileCache::new(&app)?;
+    scanner::save_scan(&mut cache, SavedScan { name: name.trim().to_string(), filter: filters, symbols })
+}
+
+#[tauri::command]
+pub fn list_scans(app: tauri::AppHandle) -> Result<Vec<SavedScan>, String> {
+    scanner::load_saved_scans(&mut FileCache::new(&app)?)
+}
+
+#[tauri::command]
+pub fn run_saved_scan(
+    app: tauri::AppHandle,
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+    name: String,
+) -> Result<ScanResult, String> {
+    let scan = scanner::load_saved_scans(&mut FileCache::new(&app)?)?
+        .into_iter()
+        .find(|s| s.name == name.trim())
+        .ok_or_else(|| format!("No saved scan named '{}'", name))?;
+    let mut loop_guard = strategy_loop.lock()?;
+    block_on(loop_guard.run_scan(&scan.filter, scan.symbols))
+}
+
+/// Strategy decisions made on sessions `from` through `to` with their outcomes; decisions
+/// whose positions are still held come back marked open
+#[tauri::command]
+pub fn get_decision_outcomes(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+    symbol: Option<String>,
+) -> Result<Vec<DecisionReport>, String> {
+    let symbol = symbol.as_deref().map(normalize_symbol).transpose()?;
+    let loop_guard = strategy_loop.lock()?;
+    block_on(loop_guard.get_decision_outcomes(from, to, symbol.as_deref()))
+}
+
+/// Per-signal results of the entry decisions made on sessions `from` through `to`
+#[tauri::command]
+pub fn get_signal_performance(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+) -> Result<Vec<SignalPerformance>, String> {
+    let loop_guard = strategy_loop.lock()?;
+    Ok(signal_performance(&block_on(loop_guard.get_decision_outcomes(from, to, None))?))
+}
+
+//
+// ---------- Presets ----------
+//
+
+fn presets(providers: &ProviderRegistry) -> Result<PresetStore, String> {
+    Ok(PresetStore::new(ui_state(providers)?))
+}
+
+#[tauri::command]
+pub fn save_strategy_preset(
+    providers: tauri::State<'_, ProviderRegistry>,
+    name: String,
+    config: StrategyLoopConfig,
+    description: Option<String>,
+) -> Result<StrategyPreset, String> {
+    presets(&providers)?.save(name.trim(), description.as_deref().unwrap_or(""), PresetSource::User, config)
+}
+
+#[tauri::command]
+pub fn list_strategy_presets(providers: tauri::State<'_, ProviderRegistry>) -> Result<Vec<StrategyPreset>, String> {
+    presets(&providers)?.list()
+}
+
+/// The preset's config for review; nothing is applied
+#[tauri::command]
+pub fn load_strategy_preset(providers: tauri::State<'_, ProviderRegistry>, name: String) -> Result<StrategyPreset, String> {
+    presets(&providers)?.get(name.trim())
+}
+
+/// Stop the loop if it is running and switch to the preset's config. Built-in presets
+/// keep the current watchlist, pairs and enabled flag. Returns the changed fields, which
+/// are also written to the strategy log.
+#[tauri::command]
+pub fn apply_strategy_preset(
+    providers: tauri::State<'_, ProviderRegistry>,
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+    name: String,
+) -> Result<Vec<ConfigChange>, String> {
+    let preset = presets(&providers)?.get(name.trim())?;
+    let mut loop_guard = strategy_loop.lock()?;
+    let mut config = preset.config.clone();
+    if preset.read_only() {
+        let current = block_on(loop_guard.get_config());
+        config.enabled = current.enabled;
+        config.symbol_rules = current.symbol_rules;
+        config.pairs = current.pairs;
+    }
+    block_on(loop_guard.apply_config(config, &preset.name))
+}
+
+#[tauri::command]
+pub fn delete_strategy_preset(providers: tauri::State<'_, ProviderRegistry>, name: String) -> Result<(), String> {
+    presets(&providers)?.delete(name.trim())
+}
+
+//
+// ---------- Live promotion ----------
+//
+
+/// Age in whole days of the newest file in `dir`, None when it holds none
+fn newest_backup_age_days(dir: &Path) -> Option<i64> {
+    let newest = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+        .max()?;
+    let age = SystemTime::now().duration_since(newest).unwrap_or_default();
+    Some((age.as_secs() / 86_400) as i64)
+}
+
+/// Run the checklist for turning dry_run off. `waivers` lets failing items through,
+/// each with a reason; `acknowledge_default_limits` accepts trading under the default
+/// risk limits. A ready checklist carries the token for confirm_live_promotion.
+#[tauri::command]
+pub fn request_live_promotion(
+    providers: tauri::State<'_, ProviderRegistry>,
+    broker: tauri::State<'_, BrokerHandle>,
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+    waivers: Option<BTreeMap<PromotionCheck, String>>,
+    acknowledge_default_limits: Option<bool>,
+) -> Result<PromotionChecklist, String> {
+    let stream = block_on(providers.stream_status());
+    let mut facts = PromotionFacts {
+        defaults_acknowledged: acknowledge_default_limits.unwrap_or(false),
+        stream_connected: stream.streaming && stream.connected,
+        stream_detail: match (stream.streaming, stream.connected) {
+            (false, _) => "Live stream is not running".to_string(),
+            (true, false) => format!("Live stream is reconnecting (attempt {})", stream.reconnect_attempts),
+            (true, true) => format!("Live stream connected, {} symbols", stream.symbols.len()),
+        },
+        backup_age_days: newest_backup_age_days(&providers.backups_dir()),
+        ..PromotionFacts::default()
+    };
+    {
+        let broker = broker.lock_for("request_live_promotion")?;
+        facts.limits_customized = broker.risk_engine.limits != RiskLimits::default();
+        facts.circuit_breaker_active = broker.risk_engine.is_circuit_breaker_active();
+    }
+    let mut loop_guard = strategy_loop.lock()?;
+    block_on(loop_guard.request_live_promotion(facts, &waivers.unwrap_or_default()))
+}
+
+/// Turn dry_run off with the token of a ready checklist
+#[tauri::command]
+pub fn confirm_live_promotion(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+    token: String,
+) -> Result<PromotionRecord, String> {
+    let mut loop_guard = strategy_loop.lock()?;
+    block_on(loop_guard.confirm_live_promotion(token.trim()))
+}
+
+/// Stop placing orders at once and go back to dry run
+#[tauri::command]
+pub fn demote_to_dry_run(
+    strategy_loop: tauri::State<'_, StrategyLoopHandle>,
+    reason: String,
+) -> Result<PromotionRecord, String> {
+    let mut loop_guard = strategy_loop.lock()?;
+    block_on(loop_guard.demote_to_dry_run(&reason))
+}
+
+#[tauri::command]
+pub fn get_promotion_history(strategy_loop: tauri::State<'_, StrategyLoopHandle>) -> Result<Vec<PromotionRecord>, String> {
+    strategy_loop.lock()?.promotion_history()
+}
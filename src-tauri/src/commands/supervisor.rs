@@ -0,0 +1,466 @@
+// src-tauri/src/commands/supervisor.rs
+// The long-running background subsystems: the live stream, the watchdog, the strategy
+// loop, the journal's storage worker and the maintenance schedulers. Each registers
+// with the supervisor, which can stop one in order (flushing what it holds) and start
+// it fresh without restarting the app. Subsystems that depend on a restarted one are
+// stopped before it and started after it. `get_subsystems` is the diagnostics view.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+use tauri::Manager;
+
+use super::state::{block_on, ProviderRegistry, StrategyLoopHandle};
+use super::strategy;
+use crate::storage::chain_snapshots::ChainRecorder;
+use crate::storage::daily_refresh::DailyRefresher;
+use crate::storage::downloads::DownloadManager;
+use crate::storage::journal_writer::JournalWriter;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SubsystemState {
+    Running,
+    Stopped,
+    Failed, // Not running, and the last start or stop failed
+}
+
+/// What a subsystem reports about itself
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SubsystemHealth {
+    pub running: bool,
+    pub detail: Option<String>,
+    pub error: Option<String>, // Reported by the subsystem itself, e.g. a failed write
+}
+
+pub trait Subsystem: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Subsystems this one must restart after, since it holds onto what they provide
+    fn depends_on(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn start(&self) -> Result<(), String>;
+
+    /// Stop in order, writing out or releasing whatever the subsystem holds
+    fn stop(&self) -> Result<(), String>;
+
+    fn health(&self) -> SubsystemHealth;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SubsystemStatus {
+    pub name: String,
+    pub state: SubsystemState,
+    pub detail: Option<String>,
+    pub started_at: Option<i64>,
+    pub uptime_secs: Option<i64>,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+    pub depends_on: Vec<String>,
+}
+
+struct Supervised {
+    subsystem: Arc<dyn Subsystem>,
+    started_at: Option<i64>, // When the supervisor started it or first saw it running
+    restarts: u32,
+    last_error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Supervisor {
+    entries: Mutex<Vec<Supervised>>,
+}
+
+impl Supervisor {
+    /// Add `subsystem`, starting it now when `start` is set
+    pub fn register(&self, subsystem: Arc<dyn Subsystem>, start: bool, now: i64) -> Result<(), String> {
+        let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+        if entries.iter().any(|e| e.subsystem.name() == subsystem.name()) {
+            return Err(format!("Subsystem {} is already registered", subsystem.name()));
+        }
+        let mut entry = Supervised { subsystem, started_at: None, restarts: 0, last_error: None };
+        if start {
+            match entry.subsystem.start() {
+                Ok(()) => entry.started_at = Some(now),
+                Err(e) => entry.last_error = Some(e),
+            }
+        }
+        entries.push(entry);
+        Ok(())
+    }
+
+    pub fn statuses(&self, now: i64) -> Result<Vec<SubsystemStatus>, String> {
+        let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+        Ok(entries.iter_mut().map(|entry| status_of(entry, now)).collect())
+    }
+
+    /// Stop `name` and everything depending on it (dependents first), then start them
+    /// again in the opposite order. Returns the restarted subsystems' statuses.
+    pub fn restart(&self, name: &str, now: i64) -> Result<Vec<SubsystemStatus>, String> {
+        let mut entries = self.entries.lock().map_err(|e| e.to_string())?;
+        let Some(target) = entries.iter().position(|e| e.subsystem.name() == name) else {
+            let known: Vec<&str> = entries.iter().map(|e| e.subsystem.name()).collect();
+            return Err(format!("Unknown subsystem {}; expected one of {}", name, known.join(", ")));
+        };
+
+        // The target, then whatever depends on it directly or through another
+        let mut order = vec![target];
+        let mut i = 0;
+        while i < order.len() {
+            let restarted = entries[order[i]].subsystem.name();
+            for (index, entry) in entries.iter().enumerate() {
+                if entry.subsystem.depends_on().contains(&restarted) && !order.contains(&index) {
+                    order.push(index);
+                }
+            }
+            i += 1;
+        }
+
+        let mut errors = Vec::new();
+        for &index in order.iter().rev() {
+            let entry = &mut entries[index];
+            if let Err(e) = entry.subsystem.stop() {
+                errors.push(format!("{} did not stop cleanly: {}", entry.subsystem.name(), e));
+                entry.last_error = Some(e);
+            }
+            entry.started_at = None;
+        }
+        for &index in &order {
+            let entry = &mut entries[index];
+            entry.restarts += 1;
+            match entry.subsystem.start() {
+                Ok(()) => entry.started_at = Some(now),
+                Err(e) => {
+                    errors.push(format!("{} failed to start: {}", entry.subsystem.name(), e));
+                    entry.last_error = Some(e);
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors.join("; "));
+        }
+        Ok(order.iter().map(|&index| status_of(&mut entries[index], now)).collect())
+    }
+}
+
+fn status_of(entry: &mut Supervised, now: i64) -> SubsystemStatus {
+    let health = entry.subsystem.health();
+    if health.error.is_some() {
+        entry.last_error = health.error.clone();
+    }
+    // Started outside the supervisor (the stream, the strategy loop) or since stopped
+    if !health.running {
+        entry.started_at = None;
+    } else if entry.started_at.is_none() {
+        entry.started_at = Some(now);
+    }
+    let state = match (health.running, &entry.last_error) {
+        (true, _) => SubsystemState::Running,
+        (false, Some(_)) => SubsystemState::Failed,
+        (false, None) => SubsystemState::Stopped,
+    };
+    SubsystemStatus {
+        name: entry.subsystem.name().to_string(),
+        state,
+        detail: health.detail,
+        started_at: entry.started_at,
+        uptime_secs: entry.started_at.map(|t| (now - t).max(0)),
+        restarts: entry.restarts,
+        last_error: entry.last_error.clone(),
+        depends_on: entry.subsystem.depends_on().iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+/// The live market data stream. Stopping remembers its symbols, so a restart
+/// resubscribes them at the same tiers; it does not start one that was never started.
+pub struct StreamSubsystem {
+    app: tauri::AppHandle,
+    symbols: Mutex<Vec<String>>,
+}
+
+impl StreamSubsystem {
+    pub fn new(app: &tauri::AppHandle) -> Self {
+        Self { app: app.clone(), symbols: Mutex::new(Vec::new()) }
+    }
+}
+
+impl Subsystem for StreamSubsystem {
+    fn name(&self) -> &'static str {
+        "stream"
+    }
+
+    fn start(&self) -> Result<(), String> {
+        let symbols = std::mem::take(&mut *self.symbols.lock().map_err(|e| e.to_string())?);
+        if symbols.is_empty() {
+            return Ok(());
+        }
+        block_on(self.app.state::<ProviderRegistry>().start_stream(symbols))
+    }
+
+    fn stop(&self) -> Result<(), String> {
+        let symbols = block_on(self.app.state::<ProviderRegistry>().stop_stream())?;
+        if !symbols.is_empty() {
+            *self.symbols.lock().map_err(|e| e.to_string())? = symbols;
+        }
+        Ok(())
+    }
+
+    fn health(&self) -> SubsystemHealth {
+        let status = block_on(self.app.state::<ProviderRegistry>().stream_status());
+        let detail = if status.streaming {
+            format!(
+                "{} symbols, {}",
+                status.symbols.len(),
+                if status.connected { "connected".to_string() } else { format!("reconnecting (attempt {})", status.reconnect_attempts) }
+            )
+        } else {
+            "Not streaming".to_string()
+        };
+        SubsystemHealth { running: status.streaming, detail: Some(detail), error: None }
+    }
+}
+
+/// The strategy loop heartbeat and stream staleness watchdog. Restarted with the
+/// stream, so a fresh stream gets a full interval before its first check.
+pub struct WatchdogSubsystem {
+    app: tauri::AppHandle,
+    running: Mutex<Option<(Arc<AtomicBool>, std::thread::JoinHandle<()>)>>,
+}
+
+impl WatchdogSubsystem {
+    pub fn new(app: &tauri::AppHandle) -> Self {
+        Self { app: app.clone(), running: Mutex::new(None) }
+    }
+}
+
+impl Subsystem for WatchdogSubsystem {
+    fn name(&self) -> &'static str {
+        "watchdog"
+    }
+
+    fn depends_on(&self) -> &'static [&'static str] {
+        &["stream"]
+    }
+
+    fn start(&self) -> Result<(), String> {
+        let mut running = self.running.lock().map_err(|e| e.to_string())?;
+        if running.as_ref().is_some_and(|(_, thread)| !thread.is_finished()) {
+            return Ok(());
+        }
+        let stop = Arc::new(AtomicBool::new(false));
+        *running = Some((stop.clone(), strategy::start_watchdog(&self.app, stop)));
+        Ok(())
+    }
+
+    // The thread notices at its next check; a new one can start meanwhile
+    fn stop(&self) -> Result<(), String> {
+        if let Some((stop, _)) = self.running.lock().map_err(|e| e.to_string())?.take() {
+            stop.store(true, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+
+    fn health(&self) -> SubsystemHealth {
+        let running = self.running.lock().is_ok_and(|r| r.as_ref().is_some_and(|(_, thread)| !thread.is_finished()));
+        SubsystemHealth { running, detail: None, error: None }
+    }
+}
+
+/// The strategy loop. A restart stops it and starts it again, so it leaves the loop
+/// running even when it was stopped before.
+pub struct StrategyLoopSubsystem {
+    app: tauri::AppHandle,
+}
+
+impl StrategyLoopSubsystem {
+    pub fn new(app: &tauri::AppHandle) -> Self {
+        Self { app: app.clone() }
+    }
+}
+
+impl Subsystem for StrategyLoopSubsystem {
+    fn name(&self) -> &'static str {
+        "strategy_loop"
+    }
+
+    fn start(&self) -> Result<(), String> {
+        let handle = self.app.state::<StrategyLoopHandle>();
+        let mut strategy_loop = handle.lock()?;
+        if strategy_loop.is_running() {
+            return Ok(());
+        }
+        block_on(strategy_loop.start())
+    }
+
+    fn stop(&self) -> Result<(), String> {
+        let handle = self.app.state::<StrategyLoopHandle>();
+        let mut strategy_loop = handle.lock()?;
+        block_on(strategy_loop.stop())
+    }
+
+    fn health(&self) -> SubsystemHealth {
+        let handle = self.app.state::<StrategyLoopHandle>();
+        let health = match handle.lock() {
+            Ok(strategy_loop) => SubsystemHealth { running: strategy_loop.is_running(), detail: None, error: None },
+            Err(e) => SubsystemHealth { running: false, detail: None, error: Some(e) },
+        };
+        health
+    }
+}
+
+/// The journal's storage worker. Stopping writes out every queued trade first.
+pub struct StorageSubsystem {
+    writer: Arc<JournalWriter>,
+}
+
+impl StorageSubsystem {
+    pub fn new(writer: Arc<JournalWriter>) -> Self {
+        Self { writer }
+    }
+}
+
+impl Subsystem for StorageSubsystem {
+    fn name(&self) -> &'static str {
+        "storage"
+    }
+
+    fn start(&self) -> Result<(), String> {
+        self.writer.start()
+    }
+
+    fn stop(&self) -> Result<(), String> {
+        let flushed = self.writer.stop()?;
+        if flushed > 0 {
+            println!("Storage worker stopped; wrote {} queued trades", flushed);
+        }
+        Ok(())
+    }
+
+    fn health(&self) -> SubsystemHealth {
+        let status = self.writer.status();
+        SubsystemHealth {
+            running: status.running,
+            detail: Some(format!("{} queued, {} written", status.pending, status.written)),
+            error: status.last_error,
+        }
+    }
+}
+
+/// History downloads, the daily bar refresh, the option chain recorder and quote
+/// checks. Stopping aborts them at their next wait; each picks up from its saved
+/// state when started again.
+pub struct MaintenanceSubsystem {
+    app: tauri::AppHandle,
+    tasks: Mutex<Vec<(&'static str, JoinHandle<()>)>>,
+}
+
+impl MaintenanceSubsystem {
+    pub fn new(app: &tauri::AppHandle) -> Self {
+        Self { app: app.clone(), tasks: Mutex::new(Vec::new()) }
+    }
+}
+
+impl Subsystem for MaintenanceSubsystem {
+    fn name(&self) -> &'static str {
+        "maintenance"
+    }
+
+    fn start(&self) -> Result<(), String> {
+        let mut tasks = self.tasks.lock().map_err(|e| e.to_string())?;
+        if !tasks.is_empty() {
+            return Ok(());
+        }
+        tasks.push(("downloads", self.app.state::<DownloadManager>().start_worker()));
+        tasks.push(("daily_refresh", self.app.state::<DailyRefresher>().start_scheduler()));
+        if let Some(recorder) = self.app.try_state::<ChainRecorder>() {
+            tasks.push(("chain_recorder", recorder.start_scheduler()));
+        }
+        tasks.push(("quote_checks", self.app.state::<ProviderRegistry>().start_quote_checks()?));
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), String> {
+        for (_, task) in self.tasks.lock().map_err(|e| e.to_string())?.drain(..) {
+            task.abort();
+        }
+        Ok(())
+    }
+
+    fn health(&self) -> SubsystemHealth {
+        let names: Vec<&str> = self.tasks.lock().map(|tasks| tasks.iter().map(|(name, _)| *name).collect()).unwrap_or_default();
+        SubsystemHealth { running: !names.is_empty(), detail: (!names.is_empty()).then(|| names.join(", ")), error: None }
+    }
+}
+
+#[tauri::command]
+pub fn get_subsystems(supervisor: tauri::State<'_, Supervisor>) -> Result<Vec<SubsystemStatus>, String> {
+    supervisor.statuses(chrono::Utc::now().timestamp())
+}
+
+/// Stop `name` in order and start it fresh, with the subsystems that depend on it
+#[tauri::command]
+pub fn restart_subsystem(supervisor: tauri::State<'_, Supervisor>, name: String) -> Result<Vec<SubsystemStatus>, String> {
+    supervisor.restart(&name, chrono::Utc::now().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Recorded {
+        name: &'static str,
+        depends_on: &'static [&'static str],
+        running: AtomicBool,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Subsystem for Recorded {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+        fn depends_on(&self) -> &'static [&'static str] {
+            self.depends_on
+        }
+        fn start(&self) -> Result<(), String> {
+            self.running.store(true, Ordering::SeqCst);
+            self.log.lock().unwrap().push(format!("start {}", self.name));
+            Ok(())
+        }
+        fn stop(&self) -> Result<(), String> {
+            self.running.store(false, Ordering::SeqCst);
+            self.log.lock().unwrap().push(format!("stop {}", self.name));
+            Ok(())
+        }
+        fn health(&self) -> SubsystemHealth {
+            SubsystemHealth { running: self.running.load(Ordering::SeqCst), detail: None, error: None }
+        }
+    }
+
+    #[test]
+    fn test_restart_stops_dependents_first_and_starts_them_after() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let supervisor = Supervisor::default();
+        let subsystem = |name, depends_on| Arc::new(Recorded { name, depends_on, running: AtomicBool::new(false), log: log.clone() });
+        supervisor.register(subsystem("stream", &[]), false, 100).unwrap();
+        supervisor.register(subsystem("watchdog", &["stream"]), true, 100).unwrap();
+        supervisor.register(subsystem("storage", &[]), true, 100).unwrap();
+        assert!(supervisor.register(subsystem("storage", &[]), false, 100).is_err());
+        log.lock().unwrap().clear();
+
+        let restarted = supervisor.restart("stream", 160).unwrap();
+        assert_eq!(*log.lock().unwrap(), vec!["stop watchdog", "stop stream", "start stream", "start watchdog"]);
+        let names: Vec<&str> = restarted.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["stream", "watchdog"]);
+
+        let statuses = supervisor.statuses(200).unwrap();
+        let storage = statuses.iter().find(|s| s.name == "storage").unwrap();
+        assert_eq!((storage.state, storage.restarts, storage.uptime_secs), (SubsystemState::Running, 0, Some(100)));
+        let watchdog = statuses.iter().find(|s| s.name == "watchdog").unwrap();
+        assert_eq!((watchdog.restarts, watchdog.uptime_secs, watchdog.depends_on.clone()), (1, Some(40), vec!["stream".to_string()]));
+
+        assert!(supervisor.restart("nothing", 200).unwrap_err().contains("stream, watchdog, storage"));
+    }
+}
@@ -0,0 +1,407 @@
+// src-tauri/src/commands/tests.rs
+// Command-layer tests through the state facades, one representative path per domain
+
+use std::sync::{mpsc, Arc, Barrier};
+use std::time::{Duration, Instant};
+
+use super::state::{block_on, BrokerHandle, BrokerLockError, ProviderRegistry};
+use super::display::Presented;
+use super::{backtest, broker, calendar, data, prefs};
+use crate::engine::broker::PaperBroker;
+use crate::engine::r#loop::StrategyLoopConfig;
+use crate::engine::statements::build_statement;
+use crate::engine::types::{DailySummary, InstrumentType, MarketData, OrderRequest, OrderSide, OrderType, Position, TimeInForce, Trade, VenueType};
+use crate::market_data::types::Candle;
+use crate::provider::{polygon as poly, yahoo as yfin};
+use crate::providers::polygon::OhlcBar;
+use crate::storage::downloads::DownloadJob;
+
+fn offline_registry() -> ProviderRegistry {
+    ProviderRegistry::offline(std::env::temp_dir().join(format!("commands-test-{}", uuid::Uuid::new_v4())))
+}
+
+#[tokio::test]
+async fn test_backtest_payloads() {
+    let sample = serde_json::to_value(backtest::get_sample_backtest_result().await).unwrap();
+    for key in ["strategy", "symbol", "start", "end", "capital", "cagr", "trades", "win_rate", "max_dd", "equity_curve"] {
+        assert!(sample.get(key).is_some(), "missing {}", key);
+    }
+
+    let params = backtest::BacktestParams {
+        ticker: "SPY".into(),
+        start_date: chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+        end_date: chrono::NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+        strategy: "BuyHold".into(),
+        initial_capital: 10_000.0,
+        seed: None,
+        warm_job_id: None,
+        transaction_costs: Default::default(),
+        cost_model: None,
+        pair: None,
+        signal_orders: Default::default(),
+    };
+    let candles: Vec<Candle> = [100.0, 110.0, 99.0, 105.0]
+        .iter()
+        .enumerate()
+        .map(|(i, close)| daily_candle(i as i64, *close))
+        .collect();
+    let summary = backtest::summarize_backtest(&params, &candles);
+    assert_eq!(summary.equity_curve.len(), 4);
+    assert!((summary.equity_curve[1].equity - 11_000.0).abs() < 1e-6);
+    assert!((summary.max_dd + 0.1).abs() < 1e-9);
+    assert_eq!((summary.trades, summary.win_rate), (3, 2.0 / 3.0));
+    assert_eq!(
+        serde_json::to_value(Presented(&summary.equity_curve[1])).unwrap(),
+        serde_json::json!({
+            "date": "2024-01-03", "t": "01/03/2024", "equity": 11_000.0, "drawdown": 0.0, "rolling_vol_20d": null, "rolling_sharpe_20d": null,
+            "realized_equity": 10_000.0, "realized_drawdown": 0.0, "missing_bar": false
+        })
+    );
+
+    assert!(backtest::summarize_backtest(&params, &candles[..1]).equity_curve.is_empty());
+}
+
+/// Daily SPY candle `day` sessions after 01/02/2024
+fn daily_candle(day: i64, close: f64) -> Candle {
+    Candle::new(1704153600 + day * 86400, close - 1.0, close + 1.5, close - 1.75, close, 1_500_000)
+        .with_symbol("SPY")
+        .with_interval("1d")
+}
+
+// Candles are internal; these pin the JSON each command and event has always returned
+#[test]
+fn test_bar_payload_snapshots() {
+    let candle = daily_candle(0, 101.0);
+
+    // History bars are timed natively; presented, they keep the date the UI reads
+    let history = serde_json::json!({ "timestamp": 1704153600, "o": 100.0, "h": 102.5, "l": 99.25, "c": 101.0, "v": 1_500_000.0 });
+    assert_eq!(serde_json::to_value(poly::Bar::from(&candle)).unwrap(), history);
+    assert_eq!(serde_json::to_value(yfin::YBar::from(&candle)).unwrap(), history);
+    let mut presented = history;
+    presented["date"] = "01/02/2024".into();
+    assert_eq!(serde_json::to_value(Presented(yfin::YBar::from(&candle))).unwrap(), presented);
+
+    // fetch_ohlc and backfill_data forward Polygon's millisecond timestamps...
+    assert_eq!(
+        serde_json::to_value(OhlcBar::aggregate(&candle)).unwrap(),
+        serde_json::json!({
+            "symbol": "SPY", "timestamp": 1704153600_000_i64, "open": 100.0, "high": 102.5, "low": 99.25, "close": 101.0, "volume": 1_500_000
+        })
+    );
+    // ...while bars built by the strategy loop have always been in seconds
+    assert_eq!(
+        serde_json::to_value(OhlcBar::from(&candle)).unwrap(),
+        serde_json::json!({
+            "symbol": "SPY", "timestamp": 1704153600, "open": 100.0, "high": 102.5, "low": 99.25, "close": 101.0, "volume": 1_500_000
+        })
+    );
+}
+
+/// Whether `text` holds an MM/DD/YYYY date anywhere
+fn has_legacy_date(text: &str) -> bool {
+    text.as_bytes().windows(10).any(|w| {
+        w.iter().enumerate().all(|(i, b)| if i == 2 || i == 5 { *b == b'/' } else { b.is_ascii_digit() })
+    })
+}
+
+// Dates below the command boundary are native; only presented responses carry the
+// display format, and state written in the old format migrates on load
+#[test]
+fn test_no_formatted_dates_below_the_command_boundary() {
+    let mut broker = PaperBroker::new(100_000.0);
+    broker.set_sim_clock(Some(1704207600)); // 10:00 ET on 01/02/2024, the 240102 expiry
+    broker.update_market_data(MarketData {
+        symbol: "XYZ".to_string(),
+        last_price: 45.0,
+        bid: Some(44.99),
+        ask: Some(45.01),
+        bid_size: Some(1000),
+        ask_size: Some(1000),
+        volume: None,
+        timestamp: 1704207600,
+    });
+    for (symbol, quantity) in [("XYZ240102C00040000", 1), ("XYZ240102P00050000", -1), ("XYZ240119C00045000", 1)] {
+        let mut position = Position::new(symbol.to_string());
+        (position.quantity, position.avg_cost) = (quantity, 1.0);
+        broker.positions.insert(symbol.to_string(), position);
+    }
+    let report = broker.process_option_expirations();
+    assert_eq!(report.assigned.len(), 2);
+
+    let state = serde_json::to_string(&broker).unwrap();
+    assert!(state.contains("\"expiry_date\":\"2024-01-02\""));
+    assert!(!has_legacy_date(&state), "{}", state);
+    let trades = serde_json::to_string(&broker.trades).unwrap();
+    assert!(!has_legacy_date(&trades));
+    assert!(has_legacy_date(&serde_json::to_string(&Presented(&broker.trades)).unwrap()));
+
+    let job = DownloadJob::new(vec!["SPY".into()], "01/02/2024", "2024-03-28", "1day").unwrap();
+    assert!(!has_legacy_date(&serde_json::to_string(&job).unwrap()));
+    let summary = backtest::summarize_backtest(&preferences_for("SPY"), &(0..4).map(|i| daily_candle(i, 100.0 + i as f64)).collect::<Vec<_>>());
+    assert!(!has_legacy_date(&serde_json::to_string(&summary).unwrap()));
+
+    // A trade journaled before the switch reads back native
+    let option_trade = broker.trades.iter().find(|t| t.option_details.is_some()).unwrap();
+    let mut legacy = serde_json::to_value(option_trade).unwrap();
+    let details = legacy["option_details"].as_object_mut().unwrap();
+    details.remove("expiry_date");
+    details.insert("expiry".to_string(), "01/02/2024".into());
+    let migrated: Trade = serde_json::from_value(legacy).unwrap();
+    assert_eq!(serde_json::to_value(&migrated).unwrap(), serde_json::to_value(option_trade).unwrap());
+    let point: backtest::EquityPoint = serde_json::from_str(r#"{"t": "01/03/2024", "equity": 1.0, "drawdown": 0.0}"#).unwrap();
+    assert_eq!(point.date, chrono::NaiveDate::from_ymd_opt(2024, 1, 3).unwrap());
+}
+
+fn preferences_for(ticker: &str) -> backtest::BacktestParams {
+    backtest::BacktestParams {
+        ticker: ticker.into(),
+        start_date: chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+        end_date: chrono::NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+        strategy: "BuyHold".into(),
+        initial_capital: 10_000.0,
+        seed: None,
+        warm_job_id: None,
+        transaction_costs: Default::default(),
+        cost_model: None,
+        pair: None,
+        signal_orders: Default::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_prefs_roundtrip_through_registry() {
+    let providers = offline_registry();
+    assert!(prefs::read_preferences(&providers).unwrap().is_none());
+
+    let preferences = backtest::BacktestParams {
+        ticker: "QQQ".into(),
+        start_date: chrono::NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
+        end_date: chrono::NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(),
+        strategy: "PMCC".into(),
+        initial_capital: 50_000.0,
+        seed: Some(7),
+        warm_job_id: None,
+        transaction_costs: backtest::TransactionCostModel { commission_per_share: 0.005, ..Default::default() },
+        cost_model: Some(backtest::BacktestCostModel { slippage_bps: 20.0, ..Default::default() }),
+        pair: None,
+        signal_orders: Default::default(),
+    };
+    prefs::write_preferences(&providers, &preferences).unwrap();
+    let loaded = prefs::read_preferences(&providers).unwrap().unwrap();
+    assert_eq!((loaded.ticker.as_str(), loaded.seed), ("QQQ", Some(7)));
+    assert_eq!(loaded.transaction_costs, preferences.transaction_costs);
+    assert_eq!(loaded.cost_model, preferences.cost_model);
+
+    let ping = serde_json::to_value(prefs::ping().await).unwrap();
+    assert_eq!(ping["ok"], true);
+    assert!(providers.stored_keys().unwrap().as_object().unwrap().is_empty());
+
+    let _ = std::fs::remove_dir_all(providers.prefs_path().parent().unwrap());
+}
+
+#[tokio::test]
+async fn test_legacy_preferences_migrate_into_ui_state() {
+    let providers = offline_registry();
+    let legacy = providers.prefs_path();
+    std::fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+    std::fs::write(
+        &legacy,
+        r#"{"ticker":"SPY","start_date":"01/01/2024","end_date":"06/30/2024","strategy":"PMCC","initial_capital":25000.0,"seed":3}"#,
+    )
+    .unwrap();
+
+    let store = prefs::ui_state(&providers).unwrap();
+    assert!(!legacy.exists());
+    assert!(legacy.with_extension("json.migrated").exists());
+    assert_eq!(store.get_namespace(prefs::BACKTEST_NAMESPACE).unwrap()["defaults"]["ticker"], "SPY");
+
+    // The old commands read and write through the store
+    let mut loaded = prefs::read_preferences(&providers).unwrap().unwrap();
+    assert_eq!((loaded.ticker.as_str(), loaded.seed), ("SPY", Some(3)));
+    loaded.ticker = "IWM".into();
+    prefs::write_preferences(&providers, &loaded).unwrap();
+    assert_eq!(prefs::read_preferences(&providers).unwrap().unwrap().ticker, "IWM");
+    assert!(!legacy.exists());
+
+    store.set("chart", "timeframe", r#""1W""#).unwrap();
+    assert_eq!(store.clear_namespace("chart").unwrap(), 1);
+    assert!(prefs::read_preferences(&providers).unwrap().is_some());
+
+    let _ = std::fs::remove_dir_all(legacy.parent().unwrap());
+}
+
+#[tokio::test]
+async fn test_data_stubs_and_offline_providers() {
+    assert_eq!(data::fetch_option_chain("SPY".into(), "01/19/2024".into()).await["status"], "stub");
+    assert_eq!(data::fetch_option_quotes(vec![]).await["quotes"], serde_json::json!([]));
+    assert_eq!(data::test_api_connection().await.unwrap(), "Connection test not implemented");
+
+    let providers = offline_registry();
+    assert!(providers.app().is_err());
+    assert!(providers.polygon().is_err());
+}
+
+#[test]
+fn test_broker_commands_through_handle() {
+    let broker = BrokerHandle::new(PaperBroker::new(100_000.0));
+    {
+        let broker = broker.lock_for("portfolio").unwrap();
+        assert_eq!(broker.get_portfolio().cash, 100_000.0);
+        assert!(broker.get_trades().is_empty());
+    }
+    assert!(broker.lock_for("close_position").unwrap().close_position("SPY").is_err());
+}
+
+#[test]
+fn test_equity_history_reports_both_drawdowns() {
+    // A closed loss of 3,000 while an open position gains 2,500 of it back
+    let summary = |day: u32, ending_equity: f64, unrealized_pnl: f64, realized_equity: Option<f64>| DailySummary {
+        date: chrono::NaiveDate::from_ymd_opt(2024, 3, day).unwrap(),
+        starting_equity: 10_000.0,
+        ending_equity,
+        cash: ending_equity - unrealized_pnl,
+        unrealized_pnl,
+        marks: Default::default(),
+        open_auction_fills: 0,
+        close_auction_fills: 0,
+        realized_equity,
+        equity_range: None,
+        benchmark_day_pnl: None,
+    };
+    // The first session predates realized equity being recorded
+    let summaries = [summary(4, 10_000.0, 0.0, None), summary(5, 9_500.0, 2_500.0, Some(7_000.0)), summary(6, 10_200.0, 0.0, Some(10_200.0))];
+
+    let mut curve = broker::session_equity_curve(&summaries);
+    assert_eq!(curve[0].realized_equity, Some(10_000.0));
+    let (max_dd, max_dd_realized) = backtest::fill_drawdowns(&mut curve);
+    assert!((max_dd + 0.05).abs() < 1e-9);
+    assert!((max_dd_realized + 0.3).abs() < 1e-9);
+    assert_eq!(curve[1].realized_drawdown.map(|d| (d * 100.0).round()), Some(-30.0));
+    assert_eq!(curve[2].realized_drawdown, Some(0.0));
+}
+
+#[test]
+fn test_busy_broker_names_the_holder() {
+    let broker = BrokerHandle::new(PaperBroker::new(100_000.0));
+    broker.set_lock_timeout(Duration::from_millis(20));
+
+    let guard = broker.lock_for("generate_statement").unwrap();
+    assert_eq!(broker.held_by(), Some("generate_statement"));
+    match broker.lock_for("portfolio") {
+        Err(BrokerLockError::Busy { operation, held_by, timeout, .. }) => {
+            assert_eq!((operation, held_by, timeout), ("portfolio", Some("generate_statement"), Duration::from_millis(20)));
+        }
+        other => panic!("expected a busy error, got {:?}", other.map(|_| ())),
+    }
+
+    drop(guard);
+    assert_eq!(broker.held_by(), None);
+    assert!(broker.lock_for("portfolio").is_ok());
+}
+
+// 100 mixed commands at once: each must get the broker within the timeout and the
+// whole batch must finish, so a lock-ordering bug fails here instead of hanging
+#[test]
+fn test_concurrent_broker_commands_stay_within_lock_timeout() {
+    const COMMANDS: usize = 100;
+    let budget = Duration::from_secs(2);
+    let broker = Arc::new(BrokerHandle::new(PaperBroker::new(100_000.0)));
+    broker.set_lock_timeout(budget);
+    broker.lock_for("update_market_data").unwrap().set_sim_clock(Some(1704207600));
+
+    let (done_tx, done_rx) = mpsc::channel();
+    let handle = Arc::clone(&broker);
+    std::thread::spawn(move || {
+        let start = Arc::new(Barrier::new(COMMANDS));
+        let workers: Vec<_> = (0..COMMANDS)
+            .map(|i| {
+                let (broker, start) = (Arc::clone(&handle), Arc::clone(&start));
+                std::thread::spawn(move || -> Result<Duration, String> {
+                    start.wait();
+                    let began = Instant::now();
+                    match i % 5 {
+                        0 => {
+                            let price = 100.0 + (i % 7) as f64 * 0.01;
+                            broker.lock_for("update_market_data")?.update_market_data(MarketData {
+                                symbol: "AAPL".to_string(),
+                                last_price: price,
+                                bid: Some(price),
+                                ask: Some(price + 0.02),
+                                bid_size: Some(500),
+                                ask_size: Some(500),
+                                volume: None,
+                                timestamp: 1704207600,
+                            });
+                        }
+                        1 => {
+                            let request = OrderRequest {
+                                symbol: "AAPL".to_string(),
+                                side: OrderSide::Buy,
+                                order_type: OrderType::Limit,
+                                quantity: 10,
+                                price: Some(99.0),
+                                stop_price: None,
+                                time_in_force: TimeInForce::Day,
+                                client_order_id: None,
+                                instrument_type: InstrumentType::Stock,
+                                option_details: None,
+                                preferred_venue: VenueType::Smart,
+                                expire_at: None,
+                                auto_round: false,
+                                override_token: None,
+                                allocation: None,
+                                residual_policy: None,
+                            };
+                            let _ = broker.lock_for("paper_order")?.place_order(request);
+                        }
+                        2 => {
+                            let snapshot = broker.lock_for("enhanced_portfolio")?.valuation_snapshot();
+                            snapshot.enhanced_portfolio();
+                        }
+                        3 => {
+                            broker.lock_for("get_symbol_pnl")?.get_symbol_pnl();
+                        }
+                        _ => {
+                            let trades = broker.lock_for("generate_statement")?.trades.clone();
+                            build_statement(2024, 1, &trades, &[], &[], &[])?;
+                        }
+                    }
+                    Ok(began.elapsed())
+                })
+            })
+            .collect();
+        let results: Vec<Result<Duration, String>> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+        let _ = done_tx.send(results);
+    });
+
+    let results = done_rx.recv_timeout(budget * 5).expect("broker commands deadlocked");
+    assert_eq!(results.len(), COMMANDS);
+    for result in results {
+        assert!(result.unwrap() < budget);
+    }
+    assert_eq!(broker.held_by(), None);
+}
+
+#[test]
+fn test_calendar_commands_through_handle() {
+    let broker = BrokerHandle::new(PaperBroker::new(100_000.0));
+    let date = calendar::parse_holiday_date("01/02/2024").unwrap();
+    assert!(broker.lock_for("is_market_open").unwrap().market_calendar.is_trading_day(date));
+
+    broker.lock_for("add_custom_holiday").unwrap().add_custom_holiday(date, "Test Closure".into(), false);
+    assert!(!broker.lock_for("is_market_open").unwrap().market_calendar.is_trading_day(date));
+
+    assert_eq!(calendar::parse_holiday_date("2024-01-02").unwrap_err(), "Date must be in MM/DD/YYYY format");
+    assert_eq!(calendar::parse_holiday_date("02/30/2024").unwrap_err(), "Invalid date");
+}
+
+// StrategyLoop needs an AppHandle, so the loop handle itself is not built here;
+// this covers the sync-to-async bridge every strategy command goes through.
+#[tokio::test(flavor = "multi_thread")]
+async fn test_strategy_bridge_and_config_payload() {
+    let config = block_on(async { StrategyLoopConfig::default() });
+    let value = serde_json::to_value(&config).unwrap();
+    let roundtrip: StrategyLoopConfig = serde_json::from_value(value.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&roundtrip).unwrap(), value);
+    assert_eq!(value["enabled"], false);
+}
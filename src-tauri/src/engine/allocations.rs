@@ -0,0 +1,426 @@
+// src-tauri/src/engine/allocations.rs
+// Virtual sub-accounts inside the paper account. Each allocation is a named bucket of
+// capital with its own cash and the positions its orders opened; orders join a bucket by
+// naming it or by their client_order_id prefix, and the broker keeps a bucket's exposure
+// and cash use within its equity. Capital moves between buckets, and between a bucket
+// and the unallocated rest of the account, with journaled transfers.
+
+use super::types::{Fill, OrderSide, Position};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AllocationEquityPoint {
+    pub date: NaiveDate,
+    pub equity: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Allocation {
+    pub name: String,
+    pub client_order_prefix: Option<String>, // Orders whose client_order_id starts with this join the bucket
+    pub capital: f64,                        // Net of transfers in and out
+    pub cash: f64,
+    pub positions: HashMap<String, Position>, // Opened by the bucket's orders
+    pub realized_pnl: f64,
+    pub commissions: f64,
+    pub equity_history: Vec<AllocationEquityPoint>, // At each session's close, oldest first
+    pub created_at: i64,
+}
+
+impl Allocation {
+    pub fn exposure(&self) -> f64 {
+        self.positions.values().map(|p| p.market_value.abs()).sum()
+    }
+
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.positions.values().map(|p| p.unrealized_pnl).sum()
+    }
+
+    pub fn equity(&self) -> f64 {
+        self.cash + self.positions.values().map(|p| p.market_value).sum::<f64>()
+    }
+}
+
+/// Capital moved between buckets; None is the unallocated rest of the account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllocationTransfer {
+    pub id: String,
+    pub timestamp: i64,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub amount: f64,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AllocationBook {
+    pub allocations: BTreeMap<String, Allocation>,
+    pub transfers: Vec<AllocationTransfer>, // Oldest first
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllocationReport {
+    pub name: String,
+    pub client_order_prefix: Option<String>,
+    pub capital: f64,
+    pub cash: f64,
+    pub equity: f64,
+    pub exposure: f64,
+    pub reserved: f64,  // Open orders' notional, held against the cap
+    pub available: f64, // Notional the bucket can still add
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub commissions: f64,
+    pub positions: Vec<Position>,
+    pub equity_history: Vec<AllocationEquityPoint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllocationsReport {
+    pub account_equity: f64,
+    pub unallocated_equity: f64,
+    pub allocations: Vec<AllocationReport>,
+    pub transfers: Vec<AllocationTransfer>, // Newest first
+}
+
+/// A bucket's open orders, as (side, remaining notional)
+pub type OpenOrderNotional = (OrderSide, f64);
+
+impl AllocationBook {
+    pub fn get(&self, name: &str) -> Result<&Allocation, String> {
+        self.allocations.get(name).ok_or_else(|| format!("No allocation named {}", name))
+    }
+
+    /// Equity in buckets, taken out of `account_equity` for what is left unallocated
+    pub fn allocated_equity(&self) -> f64 {
+        self.allocations.values().map(Allocation::equity).sum()
+    }
+
+    /// The bucket an order belongs to: the one it names, otherwise the one with the
+    /// longest prefix of its client_order_id
+    pub fn resolve(&self, explicit: Option<&str>, client_order_id: Option<&str>) -> Result<Option<String>, String> {
+        if let Some(name) = explicit {
+            return self.get(name).map(|a| Some(a.name.clone()));
+        }
+        let Some(client_order_id) = client_order_id else {
+            return Ok(None);
+        };
+        Ok(self
+            .allocations
+            .values()
+            .filter_map(|a| a.client_order_prefix.as_deref().filter(|p| client_order_id.starts_with(p)).map(|p| (p.len(), &a.name)))
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, name)| name.clone()))
+    }
+
+    pub fn create(
+        &mut self,
+        name: &str,
+        capital: f64,
+        client_order_prefix: Option<String>,
+        account_equity: f64,
+        now: i64,
+    ) -> Result<&Allocation, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Allocation name is empty".to_string());
+        }
+        if self.allocations.contains_key(name) {
+            return Err(format!("Allocation {} already exists", name));
+        }
+        let prefix = client_order_prefix.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+        if let Some(prefix) = &prefix {
+            if let Some(other) = self.allocations.values().find(|a| a.client_order_prefix.as_ref() == Some(prefix)) {
+                return Err(format!("Allocation {} already uses the prefix {}", other.name, prefix));
+            }
+        }
+        self.check_unallocated(capital, account_equity)?;
+        self.allocations.insert(name.to_string(), Allocation {
+            name: name.to_string(),
+            client_order_prefix: prefix,
+            capital,
+            cash: capital,
+            positions: HashMap::new(),
+            realized_pnl: 0.0,
+            commissions: 0.0,
+            equity_history: Vec::new(),
+            created_at: now,
+        });
+        self.transfers.push(AllocationTransfer {
+            id: Uuid::new_v4().to_string(),
+            timestamp: now,
+            from: None,
+            to: Some(name.to_string()),
+            amount: capital,
+            note: Some("created".to_string()),
+        });
+        self.get(name)
+    }
+
+    /// Close a flat bucket, returning its cash to the unallocated rest of the account
+    pub fn remove(&mut self, name: &str, now: i64) -> Result<Allocation, String> {
+        let allocation = self.get(name)?;
+        if !allocation.positions.is_empty() {
+            return Err(format!("Allocation {} still holds {}", name, allocation.positions.keys().cloned().collect::<Vec<_>>().join(", ")));
+        }
+        let allocation = self.allocations.remove(name).expect("checked above");
+        self.transfers.push(AllocationTransfer {
+            id: Uuid::new_v4().to_string(),
+            timestamp: now,
+            from: Some(allocation.name.clone()),
+            to: None,
+            amount: allocation.cash,
+            note: Some("removed".to_string()),
+        });
+        Ok(allocation)
+    }
+
+    fn check_unallocated(&self, amount: f64, account_equity: f64) -> Result<(), String> {
+        if !amount.is_finite() || amount <= 0.0 {
+            return Err("Amount must be positive".to_string());
+        }
+        let unallocated = account_equity - self.allocated_equity();
+        if amount > unallocated + 1e-9 {
+            return Err(format!("Only ${:.2} of the account is unallocated", unallocated.max(0.0)));
+        }
+        Ok(())
+    }
+
+    /// Cash that can move out of `from` (None is the unallocated rest of the account): a
+    /// bucket can only give up cash its positions and open buy orders are not using
+    pub fn free_to_move(&self, from: Option<&str>, account_equity: f64, reserved_buys: f64) -> Result<f64, String> {
+        match from {
+            None => Ok((account_equity - self.allocated_equity()).max(0.0)),
+            Some(name) => Ok((self.get(name)?.cash - reserved_buys).max(0.0)),
+        }
+    }
+
+    /// Move `amount` of cash from one bucket to another, up to the `free` cash in `from`
+    pub fn rebalance(
+        &mut self,
+        from: Option<&str>,
+        to: Option<&str>,
+        amount: f64,
+        free: f64,
+        note: Option<String>,
+        now: i64,
+    ) -> Result<AllocationTransfer, String> {
+        if from == to {
+            return Err("Transfer from and to the same allocation".to_string());
+        }
+        for name in [from, to].into_iter().flatten() {
+            self.get(name)?;
+        }
+        if !amount.is_finite() || amount <= 0.0 {
+            return Err("Amount must be positive".to_string());
+        }
+        if amount > free + 1e-9 {
+            return Err(format!("{} has ${:.2} of cash free to move", from.unwrap_or("The unallocated account"), free));
+        }
+        for (name, sign) in [(from, -1.0), (to, 1.0)] {
+            if let Some(allocation) = name.and_then(|n| self.allocations.get_mut(n)) {
+                allocation.capital += sign * amount;
+                allocation.cash += sign * amount;
+            }
+        }
+        let transfer = AllocationTransfer {
+            id: Uuid::new_v4().to_string(),
+            timestamp: now,
+            from: from.map(str::to_string),
+            to: to.map(str::to_string),
+            amount,
+            note,
+        };
+        self.transfers.push(transfer.clone());
+        Ok(transfer)
+    }
+
+    /// Whether an order in bucket `name` for `quantity` more (signed: positive buys) of
+    /// `symbol` at `price` fits the bucket, given its other open orders
+    pub fn check_order(
+        &self,
+        name: &str,
+        symbol: &str,
+        quantity: i64,
+        price: f64,
+        open_orders: &[OpenOrderNotional],
+        allow_short: bool,
+    ) -> Result<(), String> {
+        let allocation = self.get(name)?;
+        let held = allocation.positions.get(symbol).map_or(0, |p| p.quantity);
+        if quantity < 0 && held + quantity < 0 && !allow_short {
+            return Err(format!("Allocation {} holds {} {}; it cannot sell {}", name, held.max(0), symbol, -quantity));
+        }
+        // Only the part of the order that grows the position counts against the cap
+        let added = ((held + quantity).abs() - held.abs()).max(0) as f64 * price;
+        if added == 0.0 {
+            return Ok(());
+        }
+        let reserved: f64 = open_orders.iter().map(|(_, notional)| notional).sum();
+        let equity = allocation.equity();
+        let exposure = allocation.exposure() + reserved + added;
+        if exposure > equity + 1e-9 {
+            return Err(format!(
+                "Allocation {} would hold ${:.2} of exposure on ${:.2} of equity",
+                name, exposure, equity
+            ));
+        }
+        let reserved_buys: f64 = open_orders.iter().filter(|(side, _)| *side == OrderSide::Buy).map(|(_, notional)| notional).sum();
+        let cost = quantity as f64 * price;
+        if quantity > 0 && cost + reserved_buys > allocation.cash + 1e-9 {
+            return Err(format!(
+                "Allocation {} has ${:.2} of cash for a ${:.2} order",
+                name, (allocation.cash - reserved_buys).max(0.0), cost
+            ));
+        }
+        Ok(())
+    }
+
+    /// Notional bucket `name` can still add given its open orders
+    pub fn available(&self, name: &str, open_orders: &[OpenOrderNotional]) -> Result<f64, String> {
+        let allocation = self.get(name)?;
+        let reserved: f64 = open_orders.iter().map(|(_, notional)| notional).sum();
+        let reserved_buys: f64 = open_orders.iter().filter(|(side, _)| *side == OrderSide::Buy).map(|(_, notional)| notional).sum();
+        let room = allocation.equity() - allocation.exposure() - reserved;
+        Ok(room.min(allocation.cash - reserved_buys).max(0.0))
+    }
+
+    /// Book a fill of one of bucket `name`'s orders
+    pub fn apply_fill(&mut self, name: &str, fill: &Fill) {
+        let Some(allocation) = self.allocations.get_mut(name) else {
+            return;
+        };
+        let position = allocation.positions.entry(fill.symbol.clone()).or_insert_with(|| Position::new(fill.symbol.clone()));
+        allocation.realized_pnl += position.apply_fill(fill);
+        if position.quantity == 0 {
+            allocation.positions.remove(&fill.symbol);
+        }
+        allocation.cash += match fill.side {
+            OrderSide::Buy => -(fill.price * fill.quantity as f64),
+            OrderSide::Sell => fill.price * fill.quantity as f64,
+        } - fill.commission;
+        allocation.commissions += fill.commission;
+    }
+
+    pub fn mark(&mut self, symbol: &str, price: f64) {
+        for allocation in self.allocations.values_mut() {
+            if let Some(position) = allocation.positions.get_mut(symbol) {
+                position.update_market_data(price);
+            }
+        }
+    }
+
+    /// Record each bucket's equity at the close of `date`
+    pub fn close_session(&mut self, date: NaiveDate) {
+        for allocation in self.allocations.values_mut() {
+            if allocation.equity_history.last().is_none_or(|p| p.date < date) {
+                allocation.equity_history.push(AllocationEquityPoint { date, equity: allocation.equity() });
+            }
+        }
+    }
+
+    pub fn report(&self, account_equity: f64, open_orders: &HashMap<String, Vec<OpenOrderNotional>>) -> AllocationsReport {
+        let allocations = self
+            .allocations
+            .values()
+            .map(|a| {
+                let orders = open_orders.get(&a.name).map(Vec::as_slice).unwrap_or(&[]);
+                let mut positions: Vec<Position> = a.positions.values().cloned().collect();
+                positions.sort_by(|x, y| x.symbol.cmp(&y.symbol));
+                AllocationReport {
+                    name: a.name.clone(),
+                    client_order_prefix: a.client_order_prefix.clone(),
+                    capital: a.capital,
+                    cash: a.cash,
+                    equity: a.equity(),
+                    exposure: a.exposure(),
+                    reserved: orders.iter().map(|(_, notional)| notional).sum(),
+                    available: self.available(&a.name, orders).unwrap_or(0.0),
+                    realized_pnl: a.realized_pnl,
+                    unrealized_pnl: a.unrealized_pnl(),
+                    commissions: a.commissions,
+                    positions,
+                    equity_history: a.equity_history.clone(),
+                }
+            })
+            .collect();
+        AllocationsReport {
+            account_equity,
+            unallocated_equity: account_equity - self.allocated_equity(),
+            allocations,
+            transfers: self.transfers.iter().rev().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::types::InstrumentType;
+
+    fn fill(symbol: &str, side: OrderSide, quantity: i64, price: f64) -> Fill {
+        Fill {
+            id: "f".to_string(),
+            order_id: "o".to_string(),
+            symbol: symbol.to_string(),
+            side,
+            quantity,
+            price,
+            timestamp: 0,
+            commission: 1.0,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            leg_number: None,
+            at_open: false,
+            at_close: false,
+            entry_quality: None,
+            venue: None,
+            fill_latency_ms: 0,
+        }
+    }
+
+    #[test]
+    fn test_bucket_accounting_and_transfers() {
+        let mut book = AllocationBook::default();
+        book.create("momentum", 30_000.0, Some("mom_".to_string()), 100_000.0, 0).unwrap();
+        book.create("meanrev", 20_000.0, Some("mr_".to_string()), 100_000.0, 0).unwrap();
+        assert!(book.create("big", 60_000.0, None, 100_000.0, 0).unwrap_err().contains("$50000.00"));
+        assert!(book.create("dup", 1.0, Some("mr_".to_string()), 100_000.0, 0).is_err());
+
+        assert_eq!(book.resolve(None, Some("mom_123")).unwrap().as_deref(), Some("momentum"));
+        assert_eq!(book.resolve(None, Some("manual")).unwrap(), None);
+        assert_eq!(book.resolve(Some("meanrev"), Some("mom_1")).unwrap().as_deref(), Some("meanrev"));
+        assert!(book.resolve(Some("nope"), None).is_err());
+
+        // 100 shares bought at $100, marked at $110, half sold at $120
+        book.apply_fill("momentum", &fill("ACME", OrderSide::Buy, 100, 100.0));
+        book.mark("ACME", 110.0);
+        let momentum = book.get("momentum").unwrap();
+        assert_eq!((momentum.cash, momentum.exposure(), momentum.equity()), (19_999.0, 11_000.0, 30_999.0));
+        book.apply_fill("momentum", &fill("ACME", OrderSide::Sell, 50, 120.0));
+        let momentum = book.get("momentum").unwrap();
+        assert_eq!((momentum.realized_pnl, momentum.commissions), (1_000.0, 2.0));
+        assert_eq!(momentum.positions["ACME"].quantity, 50);
+
+        // Cash in use stays put; free cash moves and is journaled
+        let free = book.free_to_move(Some("momentum"), 100_000.0, 0.0).unwrap();
+        assert_eq!(free, 25_998.0);
+        assert!(book.rebalance(Some("momentum"), Some("meanrev"), 30_000.0, free, None, 1).is_err());
+        let transfer = book.rebalance(Some("momentum"), Some("meanrev"), 5_000.0, free, Some("trim".to_string()), 1).unwrap();
+        assert_eq!((transfer.from.as_deref(), transfer.amount), (Some("momentum"), 5_000.0));
+        assert_eq!((book.get("momentum").unwrap().capital, book.get("meanrev").unwrap().capital), (25_000.0, 25_000.0));
+        assert!(book.remove("momentum", 2).is_err());
+        let removed = book.remove("meanrev", 2).unwrap();
+        assert_eq!(removed.cash, 25_000.0);
+        assert_eq!(book.transfers.len(), 4);
+
+        book.close_session(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        let report = book.report(100_000.0, &HashMap::new());
+        assert_eq!(report.allocations.len(), 1);
+        assert_eq!(report.allocations[0].equity_history.len(), 1);
+        assert_eq!(report.transfers[0].note.as_deref(), Some("removed"));
+    }
+}
@@ -0,0 +1,230 @@
+// src-tauri/src/engine/analytics.rs
+// Bar analytics helpers shared by the strategy loop, and randomness tests for
+// backtest equity curves
+
+use super::bars::Timeframe;
+use super::metrics::period_returns;
+use super::mtm::normal_cdf;
+use crate::market_data::types::Candle;
+use serde::{Deserialize, Serialize};
+
+const RUNS_TEST_SIGNIFICANCE: f64 = 0.05;
+
+/// Aggregate 1-minute bars into `target_timeframe_minutes` bars. Bars are grouped by
+/// aligned interval; a trailing incomplete interval is returned as a partial bar.
+/// Accepts second or millisecond timestamps (Polygon aggregates use milliseconds).
+pub fn downsample_bars(bars: &[Candle], target_timeframe_minutes: u32) -> Vec<Candle> {
+    if target_timeframe_minutes <= 1 || bars.is_empty() {
+        return bars.to_vec();
+    }
+
+    let millis = bars[0].timestamp > 10_000_000_000;
+    let bucket_size = target_timeframe_minutes as i64 * 60 * if millis { 1000 } else { 1 };
+
+    let interval = Timeframe::from_minutes(target_timeframe_minutes)
+        .map(|tf| tf.label().to_string())
+        .unwrap_or_else(|| format!("{}m", target_timeframe_minutes));
+    let mut result: Vec<Candle> = Vec::new();
+    let mut current_bucket: Option<i64> = None;
+
+    for bar in bars {
+        let bucket = bar.timestamp.div_euclid(bucket_size);
+
+        match result.last_mut() {
+            Some(agg) if current_bucket == Some(bucket) => {
+                agg.high = agg.high.max(bar.high);
+                agg.low = agg.low.min(bar.low);
+                agg.close = bar.close;
+                agg.volume += bar.volume;
+            }
+            _ => {
+                current_bucket = Some(bucket);
+                result.push(Candle { interval: Some(interval.clone()), ..bar.clone() });
+            }
+        }
+    }
+
+    result
+}
+
+/// Wald-Wolfowitz runs test on the signs of an equity curve's period returns
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunsTestResult {
+    pub n_runs: u32,
+    pub expected_runs: f64,
+    pub z_score: f64,
+    pub p_value: f64,    // Two-sided
+    pub is_random: bool, // p_value >= 0.05
+    pub interpretation: String,
+}
+
+/// Count runs of consecutive up and down periods and z-test the count against what
+/// random signs would give. Flat periods are skipped. When every period moves the
+/// same way the conditional variance is zero, so the fair-coin moments are used.
+pub fn runs_test(equity_curve: &[f64]) -> RunsTestResult {
+    let signs: Vec<bool> = period_returns(equity_curve).into_iter().filter(|r| *r != 0.0).map(|r| r > 0.0).collect();
+    let n = signs.len() as f64;
+    let n_runs = if signs.is_empty() { 0 } else { 1 + signs.windows(2).filter(|w| w[0] != w[1]).count() as u32 };
+
+    if signs.len() < 2 {
+        return RunsTestResult {
+            n_runs,
+            expected_runs: n_runs as f64,
+            z_score: 0.0,
+            p_value: 1.0,
+            is_random: true,
+            interpretation: "Too few price changes to test".to_string(),
+        };
+    }
+
+    let ups = signs.iter().filter(|up| **up).count() as f64;
+    let downs = n - ups;
+    let (expected_runs, variance) = if ups > 0.0 && downs > 0.0 {
+        let two_ud = 2.0 * ups * downs;
+        (two_ud / n + 1.0, two_ud * (two_ud - n) / (n * n * (n - 1.0)))
+    } else {
+        ((n + 1.0) / 2.0, (n - 1.0) / 4.0)
+    };
+
+    let z_score = if variance > 0.0 { (n_runs as f64 - expected_runs) / variance.sqrt() } else { 0.0 };
+    let p_value = (2.0 * (1.0 - normal_cdf(z_score.abs()))).clamp(0.0, 1.0);
+    let is_random = p_value >= RUNS_TEST_SIGNIFICANCE;
+    let interpretation = if is_random {
+        format!("{} runs against {:.1} expected: consistent with random returns", n_runs, expected_runs)
+    } else if z_score < 0.0 {
+        format!("{} runs against {:.1} expected: returns cluster in streaks (trending)", n_runs, expected_runs)
+    } else {
+        format!("{} runs against {:.1} expected: returns alternate (mean reverting)", n_runs, expected_runs)
+    };
+
+    RunsTestResult { n_runs, expected_runs, z_score, p_value, is_random, interpretation }
+}
+
+/// Correlation of each period's return with the previous period's; 0.0 when undefined
+pub fn autocorrelation_lag1(equity_curve: &[f64]) -> f64 {
+    let returns = period_returns(equity_curve);
+    if returns.len() < 3 {
+        return 0.0;
+    }
+
+    let (previous, current) = (&returns[..returns.len() - 1], &returns[1..]);
+    let mean = |xs: &[f64]| xs.iter().sum::<f64>() / xs.len() as f64;
+    let (mean_prev, mean_cur) = (mean(previous), mean(current));
+    let (mut covariance, mut var_prev, mut var_cur) = (0.0, 0.0, 0.0);
+    for (p, c) in previous.iter().zip(current) {
+        covariance += (p - mean_prev) * (c - mean_cur);
+        var_prev += (p - mean_prev).powi(2);
+        var_cur += (c - mean_cur).powi(2);
+    }
+
+    if var_prev <= 0.0 || var_cur <= 0.0 {
+        return 0.0;
+    }
+    covariance / (var_prev * var_cur).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::simulation::SimRng;
+
+    fn minute_bar(timestamp: i64, open: f64, high: f64, low: f64, close: f64, volume: u64) -> Candle {
+        Candle::new(timestamp, open, high, low, close, volume).with_symbol("AAPL").with_interval("1m")
+    }
+
+    #[test]
+    fn test_downsample_ohlcv_aggregation() {
+        let start = 1704207600; // 15:00 UTC, aligned to 5 minutes
+        let bars: Vec<Candle> = (0..5)
+            .map(|i| {
+                let base = 100.0 + i as f64;
+                minute_bar(start + i * 60, base, base + 2.0, base - 1.0, base + 0.5, 100 * (i as u64 + 1))
+            })
+            .collect();
+
+        let result = downsample_bars(&bars, 5);
+        assert_eq!(result.len(), 1);
+        let bar = &result[0];
+        assert_eq!(bar.timestamp, start);
+        assert_eq!(bar.open, 100.0);
+        assert_eq!(bar.high, 106.0);
+        assert_eq!(bar.low, 99.0);
+        assert_eq!(bar.close, 104.5);
+        assert_eq!(bar.volume, 1500);
+    }
+
+    #[test]
+    fn test_downsample_partial_bar_and_gaps() {
+        let start = 1704207600;
+        // 7 minutes with 15:03 missing: one full 5m bar (4 bars) and a partial one
+        let bars: Vec<Candle> = [0, 1, 2, 4, 5, 6]
+            .iter()
+            .map(|&m| minute_bar(start + m * 60, 10.0 + m as f64, 11.0 + m as f64, 9.0, 10.5 + m as f64, 10))
+            .collect();
+
+        let result = downsample_bars(&bars, 5);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].volume, 40);
+        assert_eq!(result[0].close, 14.5);
+
+        // Partial bar keeps the first bar's timestamp and the data seen so far
+        assert_eq!(result[1].timestamp, start + 300);
+        assert_eq!(result[1].open, 15.0);
+        assert_eq!(result[1].close, 16.5);
+        assert_eq!(result[1].volume, 20);
+    }
+
+    #[test]
+    fn test_downsample_millisecond_timestamps() {
+        let start = 1704207600_000;
+        let bars: Vec<Candle> = (0..120)
+            .map(|i| minute_bar(start + i * 60_000, 50.0, 51.0, 49.0, 50.0, 1))
+            .collect();
+
+        let hourly = downsample_bars(&bars, 60);
+        assert_eq!(hourly.len(), 2);
+        assert_eq!(hourly[1].timestamp, start + 3_600_000);
+        assert_eq!(hourly[1].volume, 60);
+
+        assert_eq!(downsample_bars(&bars, 1).len(), 120);
+    }
+
+    #[test]
+    fn test_runs_test_on_trending_series() {
+        // Steady gains: one run of 99 ups
+        let curve: Vec<f64> = (0..100).map(|i| 100.0 * 1.001f64.powi(i)).collect();
+        let result = runs_test(&curve);
+        assert_eq!(result.n_runs, 1);
+        assert!((result.expected_runs - 50.0).abs() < 1e-9);
+        assert!(result.z_score < -9.0);
+        assert!(result.p_value < 1e-6);
+        assert!(!result.is_random);
+        assert!(result.interpretation.contains("trending"));
+
+        // Strict alternation is just as non-random, in the other direction
+        let zigzag: Vec<f64> = (0..100).map(|i| if i % 2 == 0 { 100.0 } else { 101.0 }).collect();
+        let result = runs_test(&zigzag);
+        assert_eq!(result.n_runs, 99);
+        assert!(result.z_score > 9.0 && !result.is_random);
+        assert!(autocorrelation_lag1(&zigzag) < -0.99);
+    }
+
+    #[test]
+    fn test_runs_test_on_random_walk() {
+        let mut rng = SimRng::new(42);
+        let mut curve = vec![100.0];
+        for _ in 0..500 {
+            let last = *curve.last().unwrap();
+            curve.push(last * (1.0 + 0.01 * rng.next_normal()));
+        }
+
+        let result = runs_test(&curve);
+        assert!(result.z_score.abs() < 1.5, "z = {}", result.z_score);
+        assert!(result.p_value > 0.1);
+        assert!(result.is_random);
+        assert!(autocorrelation_lag1(&curve).abs() < 0.1);
+
+        assert_eq!(runs_test(&[100.0, 101.0]).p_value, 1.0);
+        assert_eq!(autocorrelation_lag1(&[100.0, 101.0]), 0.0);
+    }
+}
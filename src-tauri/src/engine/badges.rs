@@ -0,0 +1,217 @@
+// src-tauri/src/engine/badges.rs
+// Risk badges on open positions, so the UI can flag the risky ones without restating the
+// rules. Each badge is worked out from figures the broker already keeps: the risk
+// limits' concentration line, the margin pairing of short options, option expiries, the
+// earnings calendar, mark freshness, the best mark since entry and allocation buckets.
+// Badges are set on the portfolio's positions at read time, like mark freshness, and a
+// monitor reports the positions whose set of badges moved.
+
+use super::freshness::FreshnessClass;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum BadgeKind {
+    Concentration,  // Over the concentration line, as a share of equity
+    UndefinedRisk,  // Short option contracts no spread or shares cover
+    Expiring,
+    Earnings,       // The underlying reports within the event window
+    StaleData,
+    Drawdown,       // Down from the best mark since entry
+    OverAllocation, // Held by a bucket whose exposure is over its equity
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RiskBadge {
+    pub kind: BadgeKind,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct BadgeConfig {
+    pub concentration_pct: Option<f64>, // Of equity; None follows the risk limits' max_portfolio_concentration
+    pub expiring_within_days: i64,
+    pub earnings_window_days: i64,      // Report dates this many days ahead or sooner, today included
+    pub drawdown_pct: f64,              // From the position's best mark since entry
+}
+
+impl Default for BadgeConfig {
+    fn default() -> Self {
+        Self { concentration_pct: None, expiring_within_days: 5, earnings_window_days: 7, drawdown_pct: 0.10 }
+    }
+}
+
+/// What the broker knows about one position when badging it
+#[derive(Debug, Clone, Default)]
+pub struct PositionRisk {
+    pub weight: f64,                     // |market value| over equity
+    pub undefined_risk: bool,
+    pub days_to_expiry: Option<i64>,     // Options only
+    pub days_to_earnings: Option<i64>,   // Next report of the underlying, today or later
+    pub freshness: FreshnessClass,
+    pub drawdown: Option<f64>,           // Fraction below the best mark since entry
+    pub over_allocation: Option<String>, // Bucket holding the position, when over its equity
+}
+
+impl BadgeConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.concentration_pct.is_some_and(|p| !(p > 0.0 && p <= 1.0)) {
+            return Err("concentration_pct must be in (0, 1]".to_string());
+        }
+        if self.expiring_within_days < 0 || self.earnings_window_days < 0 {
+            return Err("Day windows cannot be negative".to_string());
+        }
+        if !(self.drawdown_pct > 0.0 && self.drawdown_pct < 1.0) {
+            return Err("drawdown_pct must be in (0, 1)".to_string());
+        }
+        Ok(())
+    }
+
+    /// Badges of a position, in kind order; `concentration_limit` is the risk limits' line
+    pub fn badges(&self, risk: &PositionRisk, concentration_limit: f64) -> Vec<RiskBadge> {
+        let mut badges = Vec::new();
+        let mut add = |kind, detail: String| badges.push(RiskBadge { kind, detail });
+        let concentration = self.concentration_pct.unwrap_or(concentration_limit);
+        if risk.weight > concentration {
+            add(BadgeKind::Concentration, format!("{:.1}% of equity, over {:.1}%", risk.weight * 100.0, concentration * 100.0));
+        }
+        if risk.undefined_risk {
+            add(BadgeKind::UndefinedRisk, "Short option without a covering spread or shares".to_string());
+        }
+        if let Some(days) = risk.days_to_expiry.filter(|d| (0..=self.expiring_within_days).contains(d)) {
+            add(BadgeKind::Expiring, format!("Expires in {} days", days));
+        }
+        if let Some(days) = risk.days_to_earnings.filter(|d| (0..=self.earnings_window_days).contains(d)) {
+            add(BadgeKind::Earnings, format!("Earnings in {} days", days));
+        }
+        if risk.freshness == FreshnessClass::Stale {
+            add(BadgeKind::StaleData, "Mark is stale".to_string());
+        }
+        if let Some(drawdown) = risk.drawdown.filter(|d| *d > self.drawdown_pct) {
+            add(BadgeKind::Drawdown, format!("{:.1}% below its best mark since entry", drawdown * 100.0));
+        }
+        if let Some(bucket) = &risk.over_allocation {
+            add(BadgeKind::OverAllocation, format!("Allocation {} holds more exposure than equity", bucket));
+        }
+        badges
+    }
+}
+
+/// Badge counts across the portfolio
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BadgeSummary {
+    pub counts: BTreeMap<BadgeKind, usize>, // Positions carrying each kind
+    pub flagged_positions: usize,           // Positions with any badge
+}
+
+impl BadgeSummary {
+    pub fn summarize<'a>(badges: impl IntoIterator<Item = &'a [RiskBadge]>) -> Self {
+        let mut summary = Self::default();
+        for position in badges {
+            for badge in position {
+                *summary.counts.entry(badge.kind).or_default() += 1;
+            }
+            summary.flagged_positions += usize::from(!position.is_empty());
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BadgeTransition {
+    pub symbol: String,
+    pub added: Vec<BadgeKind>,
+    pub removed: Vec<BadgeKind>,
+}
+
+/// Payload of "position_badges_changed"
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BadgeChange {
+    pub summary: BadgeSummary,
+    pub transitions: Vec<BadgeTransition>, // By symbol
+}
+
+/// Badge sets last reported, so the event fires on changes only
+#[derive(Debug, Clone, Default)]
+pub struct BadgeMonitor {
+    kinds: Option<BTreeMap<String, BTreeSet<BadgeKind>>>,
+}
+
+impl BadgeMonitor {
+    /// Record each position's badge kinds; returns the change when any position's set
+    /// differs from the last observation. A position opening or closing with badges is a
+    /// change; the first observation only sets the baseline.
+    pub fn observe(&mut self, summary: &BadgeSummary, kinds: BTreeMap<String, BTreeSet<BadgeKind>>) -> Option<BadgeChange> {
+        let previous = self.kinds.replace(kinds.clone())?;
+        let none = BTreeSet::new();
+        let symbols: BTreeSet<&String> = previous.keys().chain(kinds.keys()).collect();
+        let transitions: Vec<BadgeTransition> = symbols
+            .into_iter()
+            .filter_map(|symbol| {
+                let (before, after) = (previous.get(symbol).unwrap_or(&none), kinds.get(symbol).unwrap_or(&none));
+                (before != after).then(|| BadgeTransition {
+                    symbol: symbol.clone(),
+                    added: after.difference(before).copied().collect(),
+                    removed: before.difference(after).copied().collect(),
+                })
+            })
+            .collect();
+        (!transitions.is_empty()).then(|| BadgeChange { summary: summary.clone(), transitions })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_badge_trips_and_counts_aggregate() {
+        let config = BadgeConfig::default();
+        let quiet = PositionRisk { weight: 0.05, days_to_expiry: Some(30), days_to_earnings: Some(20), drawdown: Some(0.02), ..PositionRisk::default() };
+        assert!(config.badges(&quiet, 0.25).is_empty());
+
+        let trips = [
+            (PositionRisk { weight: 0.30, ..quiet.clone() }, BadgeKind::Concentration),
+            (PositionRisk { undefined_risk: true, ..quiet.clone() }, BadgeKind::UndefinedRisk),
+            (PositionRisk { days_to_expiry: Some(5), ..quiet.clone() }, BadgeKind::Expiring),
+            (PositionRisk { days_to_earnings: Some(0), ..quiet.clone() }, BadgeKind::Earnings),
+            (PositionRisk { freshness: FreshnessClass::Stale, ..quiet.clone() }, BadgeKind::StaleData),
+            (PositionRisk { drawdown: Some(0.15), ..quiet.clone() }, BadgeKind::Drawdown),
+            (PositionRisk { over_allocation: Some("momentum".to_string()), ..quiet.clone() }, BadgeKind::OverAllocation),
+        ];
+        let mut badged: Vec<Vec<RiskBadge>> = trips
+            .iter()
+            .map(|(risk, kind)| {
+                let badges = config.badges(risk, 0.25);
+                assert_eq!(badges.iter().map(|b| b.kind).collect::<Vec<_>>(), vec![*kind]);
+                badges
+            })
+            .collect();
+        // A configured line replaces the risk limit's; delayed marks and past expiries do not badge
+        assert!(BadgeConfig { concentration_pct: Some(0.40), ..config.clone() }.badges(&trips[0].0, 0.25).is_empty());
+        assert!(config.badges(&PositionRisk { freshness: FreshnessClass::Delayed, days_to_expiry: Some(-1), ..quiet.clone() }, 0.25).is_empty());
+        assert!(BadgeConfig { drawdown_pct: 1.5, ..config.clone() }.validate().is_err());
+
+        let both = PositionRisk { weight: 0.5, days_to_earnings: Some(3), ..quiet.clone() };
+        badged.push(config.badges(&both, 0.25));
+        badged.push(Vec::new());
+        let summary = BadgeSummary::summarize(badged.iter().map(Vec::as_slice));
+        assert_eq!(summary.flagged_positions, 8);
+        assert_eq!(summary.counts[&BadgeKind::Concentration], 2);
+        assert_eq!(summary.counts[&BadgeKind::Earnings], 2);
+        assert_eq!(summary.counts[&BadgeKind::OverAllocation], 1);
+        assert_eq!(summary.counts.values().sum::<usize>(), 9);
+
+        let mut monitor = BadgeMonitor::default();
+        let kinds = |pairs: &[(&str, &[BadgeKind])]| pairs.iter().map(|(s, k)| (s.to_string(), k.iter().copied().collect())).collect::<BTreeMap<_, _>>();
+        assert_eq!(monitor.observe(&summary, kinds(&[("AAA", &[BadgeKind::Drawdown])])), None);
+        assert_eq!(monitor.observe(&summary, kinds(&[("AAA", &[BadgeKind::Drawdown])])), None);
+        let change = monitor.observe(&summary, kinds(&[("AAA", &[BadgeKind::Concentration]), ("BBB", &[BadgeKind::Expiring])])).unwrap();
+        assert_eq!(change.transitions, vec![
+            BadgeTransition { symbol: "AAA".to_string(), added: vec![BadgeKind::Concentration], removed: vec![BadgeKind::Drawdown] },
+            BadgeTransition { symbol: "BBB".to_string(), added: vec![BadgeKind::Expiring], removed: vec![] },
+        ]);
+    }
+}
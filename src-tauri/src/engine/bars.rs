@@ -0,0 +1,258 @@
+// src-tauri/src/engine/bars.rs
+// Multi-timeframe bar builder fed from the same tick/minute stream
+
+use super::indicators::IndicatorCache;
+use crate::market_data::types::{epoch_seconds, Candle};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Timeframe {
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[default]
+    #[serde(rename = "5m")]
+    FiveMinute,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+impl Timeframe {
+    pub fn seconds(&self) -> i64 {
+        match self {
+            Timeframe::OneMinute => 60,
+            Timeframe::FiveMinute => 300,
+            Timeframe::OneHour => 3600,
+            Timeframe::OneDay => 86400,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Timeframe::OneMinute => "1m",
+            Timeframe::FiveMinute => "5m",
+            Timeframe::OneHour => "1h",
+            Timeframe::OneDay => "1d",
+        }
+    }
+
+    /// Timeframe string understood by `PolygonProvider::fetch_ohlc`
+    pub fn polygon_timeframe(&self) -> &'static str {
+        match self {
+            Timeframe::OneMinute => "1M",
+            Timeframe::FiveMinute => "5M",
+            Timeframe::OneHour => "1H",
+            Timeframe::OneDay => "1D",
+        }
+    }
+
+    /// Approximate number of bars in one regular session (6.5 hours)
+    pub fn bars_per_session(&self) -> usize {
+        match self {
+            Timeframe::OneMinute => 390,
+            Timeframe::FiveMinute => 78,
+            Timeframe::OneHour => 7,
+            Timeframe::OneDay => 1,
+        }
+    }
+
+    pub fn from_minutes(minutes: u32) -> Option<Timeframe> {
+        match minutes {
+            1 => Some(Timeframe::OneMinute),
+            5 => Some(Timeframe::FiveMinute),
+            60 => Some(Timeframe::OneHour),
+            1440 => Some(Timeframe::OneDay),
+            _ => None,
+        }
+    }
+
+    pub fn bucket_start(&self, timestamp: i64) -> i64 {
+        let secs = self.seconds();
+        timestamp.div_euclid(secs) * secs
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct BarSeries {
+    completed: Vec<Candle>,
+    forming: Option<Candle>,
+}
+
+pub struct BarBuilder {
+    timeframes: Vec<Timeframe>,
+    series: HashMap<(String, Timeframe), BarSeries>,
+    max_bars: usize,
+    indicators: IndicatorCache, // Computed from these bars, per symbol and series
+}
+
+impl BarBuilder {
+    pub fn new(timeframes: Vec<Timeframe>, max_bars: usize) -> Self {
+        let mut timeframes = timeframes;
+        timeframes.sort();
+        timeframes.dedup();
+
+        Self {
+            timeframes,
+            series: HashMap::new(),
+            max_bars,
+            indicators: IndicatorCache::default(),
+        }
+    }
+
+    /// Replace the tracked timeframes, dropping series that are no longer needed
+    pub fn set_timeframes(&mut self, timeframes: Vec<Timeframe>) {
+        let mut timeframes = timeframes;
+        timeframes.sort();
+        timeframes.dedup();
+        self.series.retain(|(_, tf), _| timeframes.contains(tf));
+        self.timeframes = timeframes;
+        self.indicators.clear();
+    }
+
+    /// Apply a trade or minute-bar close to every tracked timeframe
+    pub fn on_tick(&mut self, symbol: &str, timestamp: i64, price: f64, volume: i64) {
+        let timestamp = epoch_seconds(timestamp);
+        let volume = volume.max(0) as u64;
+
+        for &timeframe in &self.timeframes {
+            let bucket = timeframe.bucket_start(timestamp);
+            let series = self.series.entry((symbol.to_string(), timeframe)).or_default();
+
+            match series.forming.as_mut() {
+                Some(bar) if bar.timestamp == bucket => {
+                    bar.high = bar.high.max(price);
+                    bar.low = bar.low.min(price);
+                    bar.close = price;
+                    bar.volume += volume;
+                }
+                Some(bar) if bar.timestamp > bucket => {
+                    // Late tick for an already-closed bucket; ignore
+                }
+                _ => {
+                    if let Some(done) = series.forming.take() {
+                        series.completed.push(done);
+                    }
+                    series.forming = Some(
+                        Candle::new(bucket, price, price, price, price, volume)
+                            .with_symbol(symbol)
+                            .with_interval(timeframe.label()),
+                    );
+                }
+            }
+
+            if series.completed.len() > self.max_bars {
+                let excess = series.completed.len() - self.max_bars;
+                series.completed.drain(..excess);
+            }
+        }
+    }
+
+    /// Seed completed history (e.g. daily bars from the bar cache). Bars at or after
+    /// the current forming bucket are skipped so intraday updates stay authoritative.
+    pub fn seed(&mut self, symbol: &str, timeframe: Timeframe, bars: &[Candle]) {
+        // History before the bars the indicators have seen; they start over
+        self.indicators.clear_symbol(symbol);
+        let series = self.series.entry((symbol.to_string(), timeframe)).or_default();
+        let forming_start = series.forming.as_ref().map(|b| b.timestamp).unwrap_or(i64::MAX);
+
+        let mut merged: Vec<Candle> = bars
+            .iter()
+            .map(|b| Candle {
+                timestamp: timeframe.bucket_start(epoch_seconds(b.timestamp)),
+                interval: Some(timeframe.label().to_string()),
+                ..b.clone()
+            })
+            .filter(|b| b.timestamp < forming_start)
+            .collect();
+        merged.extend(series.completed.drain(..));
+        merged.sort_by_key(|b| b.timestamp);
+        merged.dedup_by_key(|b| b.timestamp);
+
+        if merged.len() > self.max_bars {
+            let excess = merged.len() - self.max_bars;
+            merged.drain(..excess);
+        }
+        series.completed = merged;
+    }
+
+    /// Completed bars followed by the forming bar, oldest first
+    pub fn bars(&self, symbol: &str, timeframe: Timeframe) -> Vec<Candle> {
+        match self.series.get(&(symbol.to_string(), timeframe)) {
+            Some(series) => {
+                let mut bars = series.completed.clone();
+                if let Some(ref forming) = series.forming {
+                    bars.push(forming.clone());
+                }
+                bars
+            }
+            None => Vec::new(),
+        }
+    }
+
+    pub fn indicators_mut(&mut self) -> &mut IndicatorCache {
+        &mut self.indicators
+    }
+
+    pub fn snapshot(&self, symbol: &str) -> HashMap<Timeframe, Vec<Candle>> {
+        self.timeframes
+            .iter()
+            .map(|&tf| (tf, self.bars(symbol, tf)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn daily_bar(timestamp: i64, close: f64) -> Candle {
+        Candle::new(timestamp, close, close, close, close, 1000).with_symbol("AAPL")
+    }
+
+    #[test]
+    fn test_parallel_aggregation() {
+        let mut builder = BarBuilder::new(vec![Timeframe::FiveMinute, Timeframe::OneHour], 100);
+        let start = 1704207600; // 2024-01-02 15:00 UTC
+
+        // One tick per minute for 65 minutes, price rising by 1 each minute
+        for i in 0..65 {
+            builder.on_tick("AAPL", start + i * 60, 100.0 + i as f64, 10);
+        }
+
+        let five = builder.bars("AAPL", Timeframe::FiveMinute);
+        assert_eq!(five.len(), 13); // 12 completed + 1 forming
+        assert_eq!(five[0].open, 100.0);
+        assert_eq!(five[0].close, 104.0);
+        assert_eq!(five[0].volume, 50);
+
+        let hourly = builder.bars("AAPL", Timeframe::OneHour);
+        assert_eq!(hourly.len(), 2);
+        assert_eq!(hourly[0].high, 159.0);
+        assert_eq!(hourly[0].low, 100.0);
+        assert_eq!(hourly[1].open, 160.0); // forming bar
+    }
+
+    #[test]
+    fn test_seed_keeps_forming_daily_bar() {
+        let mut builder = BarBuilder::new(vec![Timeframe::OneDay], 10);
+        let today = 1704153600; // 2024-01-02 00:00 UTC
+
+        builder.on_tick("AAPL", today + 15 * 3600, 190.0, 100);
+        builder.on_tick("AAPL", today + 16 * 3600, 192.0, 100);
+
+        // History in milliseconds, including a stale copy of today
+        let history: Vec<Candle> = (1..=3)
+            .map(|d| daily_bar((today - d * 86400) * 1000, 180.0 + d as f64))
+            .chain(std::iter::once(daily_bar(today * 1000, 1.0)))
+            .collect();
+        builder.seed("AAPL", Timeframe::OneDay, &history);
+
+        let bars = builder.bars("AAPL", Timeframe::OneDay);
+        assert_eq!(bars.len(), 4);
+        assert!(bars.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+        assert_eq!(bars[3].close, 192.0);
+        assert_eq!(bars[3].volume, 200);
+    }
+}
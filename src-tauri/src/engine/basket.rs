@@ -0,0 +1,354 @@
+// src-tauri/src/engine/basket.rs
+// One strategy backtested across a basket of symbols: each symbol's run ranked on its
+// own, and the runs combined into a portfolio with the starting capital split between
+// them by equal weight or inverse volatility.
+
+use super::metrics::{annualized_cagr, pearson_correlation, period_returns, sharpe_ratio, TRADING_DAYS_PER_YEAR};
+use crate::commands::backtest::{fill_drawdowns, fill_rolling_stats, BacktestSummary, EquityPoint};
+use crate::market_data::dates;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BasketAllocation {
+    #[default]
+    EqualWeight,
+    VolatilityWeighted, // Weight inversely proportional to each symbol's price volatility
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BasketRankMetric {
+    #[default]
+    Sharpe,
+    Cagr,
+    MaxDrawdown, // Shallowest first
+    NetPnl,
+}
+
+/// A symbol's own run and its share of the combined portfolio
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BasketMember {
+    pub symbol: String,
+    pub rank: usize, // 1 is best on the basket's rank_by metric
+    pub weight: f64, // Share of the starting capital in the combined portfolio
+    pub run_id: Option<String>, // Saved full result, loaded by get_backtest
+    pub cagr: f64,
+    pub max_dd: f64,
+    pub sharpe: f64,
+    pub trades: u32,
+    pub net_pnl: f64,
+    pub total_transaction_costs: f64,
+    pub volatility: f64, // Annualized, of the symbol's daily closes
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BasketError {
+    pub symbol: String,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SymbolCorrelation {
+    pub first: String,
+    pub second: String,
+    pub correlation: f64, // Of the two runs' daily returns
+}
+
+/// A symbol's finished run with the volatility allocation weighs it by
+pub struct BasketRun {
+    pub summary: BacktestSummary,
+    pub volatility: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BasketBacktest {
+    pub id: Option<String>,
+    pub created_at: i64,
+    pub strategy: String,
+    #[serde(rename = "start_on", alias = "start", deserialize_with = "dates::lenient")]
+    pub start: NaiveDate,
+    #[serde(rename = "end_on", alias = "end", deserialize_with = "dates::lenient")]
+    pub end: NaiveDate,
+    pub capital: f64,
+    pub allocation: BasketAllocation,
+    pub rank_by: BasketRankMetric,
+    pub members: Vec<BasketMember>, // Best first
+    pub errors: Vec<BasketError>,   // Symbols that produced no run
+    pub equity_curve: Vec<EquityPoint>, // The weighted runs summed per date
+    pub cagr: f64,
+    pub max_dd: f64,
+    pub max_dd_realized: f64,
+    pub sharpe: f64,
+    pub average_member_sharpe: f64,
+    pub diversification_benefit: f64, // sharpe - average_member_sharpe
+    pub correlations: Vec<SymbolCorrelation>, // Each pair of members once
+    pub average_correlation: f64,
+}
+
+/// Annualized standard deviation of daily returns of `closes`; 0.0 under two returns
+pub fn annualized_volatility(closes: &[f64]) -> f64 {
+    let returns = period_returns(closes);
+    if returns.len() < 2 {
+        return 0.0;
+    }
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    variance.sqrt() * TRADING_DAYS_PER_YEAR.sqrt()
+}
+
+/// Capital shares summing to 1. Volatility weighting falls back to equal weights when
+/// a symbol has no measurable volatility.
+pub fn allocation_weights(allocation: BasketAllocation, volatilities: &[f64]) -> Vec<f64> {
+    let n = volatilities.len();
+    let inverse_vol = volatilities.iter().all(|v| v.is_finite() && *v > 0.0);
+    match allocation {
+        BasketAllocation::VolatilityWeighted if inverse_vol => {
+            let total: f64 = volatilities.iter().map(|v| 1.0 / v).sum();
+            volatilities.iter().map(|v| 1.0 / v / total).collect()
+        }
+        _ => vec![1.0 / n as f64; n],
+    }
+}
+
+/// A member's allocated share of its run on one date
+struct WeightedPoint {
+    equity: f64,
+    realized_equity: Option<f64>,
+    carried: bool, // The member had no bar that day
+}
+
+/// Each member's equity over the union of the members' dates, scaled from `capital`
+/// to its weight's share. A member holds its allocated capital before its first point
+/// and its last equity after its last one.
+fn weighted_series(members: &[(f64, &[EquityPoint])], capital: f64) -> (Vec<NaiveDate>, Vec<Vec<WeightedPoint>>) {
+    let dates: Vec<NaiveDate> = members
+        .iter()
+        .flat_map(|(_, curve)| curve.iter().map(|p| p.date))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let series = members
+        .iter()
+        .map(|(weight, curve)| {
+            let mut points = curve.iter().map(|p| (p.date, p)).peekable();
+            let mut current: Option<&EquityPoint> = None;
+            dates
+                .iter()
+                .map(|date| {
+                    let mut on_date = false;
+                    while let Some((_, point)) = points.next_if(|(d, _)| d <= date) {
+                        current = Some(point);
+                        on_date = true;
+                    }
+                    match current {
+                        Some(point) => WeightedPoint {
+                            equity: weight * point.equity,
+                            realized_equity: point.realized_equity.map(|r| weight * r),
+                            carried: point.missing_bar || !on_date,
+                        },
+                        None => WeightedPoint { equity: weight * capital, realized_equity: Some(weight * capital), carried: true },
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    (dates, series)
+}
+
+/// Rank the runs, split `capital` between them and combine their curves. Every run
+/// is assumed to have started from `capital`.
+pub fn aggregate_basket(
+    runs: Vec<(String, Result<BasketRun, String>)>,
+    capital: f64,
+    allocation: BasketAllocation,
+    rank_by: BasketRankMetric,
+    now: i64,
+) -> BasketBacktest {
+    let mut errors = Vec::new();
+    let mut succeeded = Vec::new();
+    for (symbol, run) in runs {
+        match run {
+            Ok(run) => succeeded.push(run),
+            Err(error) => errors.push(BasketError { symbol, error }),
+        }
+    }
+    errors.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    succeeded.sort_by(|a, b| a.summary.symbol.cmp(&b.summary.symbol));
+
+    let weights = allocation_weights(allocation, &succeeded.iter().map(|r| r.volatility).collect::<Vec<_>>());
+    let curves: Vec<(f64, &[EquityPoint])> = weights.iter().zip(&succeeded).map(|(w, r)| (*w, r.summary.equity_curve.as_slice())).collect();
+    let (dates, series) = weighted_series(&curves, capital);
+
+    let mut equity_curve: Vec<EquityPoint> = dates
+        .iter()
+        .enumerate()
+        .map(|(i, date)| {
+            let realized: Option<f64> = series.iter().map(|s| s[i].realized_equity).sum();
+            EquityPoint {
+                date: *date,
+                equity: series.iter().map(|s| s[i].equity).sum(),
+                drawdown: 0.0,
+                rolling_vol_20d: None,
+                rolling_sharpe_20d: None,
+                realized_equity: realized,
+                realized_drawdown: None,
+                missing_bar: series.iter().any(|s| s[i].carried),
+                benchmark_equity: None,
+            }
+        })
+        .collect();
+    let (max_dd, max_dd_realized) = fill_drawdowns(&mut equity_curve);
+    fill_rolling_stats(&mut equity_curve);
+    let equities: Vec<f64> = equity_curve.iter().map(|p| p.equity).collect();
+    let span = match (dates.first(), dates.last()) {
+        (Some(first), Some(last)) => (*last - *first).num_days().max(0) as usize,
+        _ => 0,
+    };
+
+    let member_returns: Vec<Vec<f64>> = series.iter().map(|s| period_returns(&s.iter().map(|p| p.equity).collect::<Vec<_>>())).collect();
+    let mut correlations = Vec::new();
+    for i in 0..succeeded.len() {
+        for j in i + 1..succeeded.len() {
+            correlations.push(SymbolCorrelation {
+                first: succeeded[i].summary.symbol.clone(),
+                second: succeeded[j].summary.symbol.clone(),
+                correlation: pearson_correlation(&member_returns[i], &member_returns[j]),
+            });
+        }
+    }
+
+    let mut members: Vec<BasketMember> = succeeded
+        .iter()
+        .zip(&weights)
+        .map(|(run, weight)| {
+            let summary = &run.summary;
+            BasketMember {
+                symbol: summary.symbol.clone(),
+                rank: 0,
+                weight: *weight,
+                run_id: summary.id.clone(),
+                cagr: summary.cagr,
+                max_dd: summary.max_dd,
+                sharpe: sharpe_ratio(&summary.equity_curve.iter().map(|p| p.equity).collect::<Vec<_>>()),
+                trades: summary.trades,
+                net_pnl: summary.net_pnl,
+                total_transaction_costs: summary.total_transaction_costs,
+                volatility: run.volatility,
+            }
+        })
+        .collect();
+    let score = |m: &BasketMember| match rank_by {
+        BasketRankMetric::Sharpe => m.sharpe,
+        BasketRankMetric::Cagr => m.cagr,
+        BasketRankMetric::MaxDrawdown => m.max_dd,
+        BasketRankMetric::NetPnl => m.net_pnl,
+    };
+    members.sort_by(|a, b| score(b).total_cmp(&score(a)).then_with(|| a.symbol.cmp(&b.symbol)));
+    for (i, member) in members.iter_mut().enumerate() {
+        member.rank = i + 1;
+    }
+
+    let sharpe = sharpe_ratio(&equities);
+    let average_member_sharpe =
+        if members.is_empty() { 0.0 } else { members.iter().map(|m| m.sharpe).sum::<f64>() / members.len() as f64 };
+    let average_correlation = if correlations.is_empty() {
+        0.0
+    } else {
+        correlations.iter().map(|c| c.correlation).sum::<f64>() / correlations.len() as f64
+    };
+    let (strategy, start, end) = succeeded
+        .first()
+        .map(|r| (r.summary.strategy.clone(), r.summary.start, r.summary.end))
+        .unwrap_or_default();
+
+    BasketBacktest {
+        id: None,
+        created_at: now,
+        strategy,
+        start,
+        end,
+        capital,
+        allocation,
+        rank_by,
+        members,
+        errors,
+        cagr: match (equities.first(), equities.last()) {
+            (Some(_), Some(last)) => annualized_cagr(capital, *last, span),
+            _ => 0.0,
+        },
+        equity_curve,
+        max_dd,
+        max_dd_realized,
+        sharpe,
+        average_member_sharpe,
+        diversification_benefit: sharpe - average_member_sharpe,
+        correlations,
+        average_correlation,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::backtest::{summarize_backtest, BacktestParams};
+    use crate::market_data::types::Candle;
+
+    fn run(symbol: &str, closes: &[f64]) -> BasketRun {
+        let params = BacktestParams {
+            ticker: symbol.into(),
+            start_date: NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2024, 1, 12).unwrap(),
+            strategy: "BuyHold".into(),
+            initial_capital: 100_000.0,
+            seed: None,
+            warm_job_id: None,
+            transaction_costs: Default::default(),
+            cost_model: None,
+            pair: None,
+            signal_orders: Default::default(),
+        };
+        // Jan 2 2024 onwards, weekdays only
+        let days = [2, 3, 4, 5, 8, 9, 10, 11, 12];
+        let candles: Vec<Candle> = closes
+            .iter()
+            .zip(days)
+            .map(|(close, day)| Candle::new(1_704_067_200 + (day - 1) * 86_400, *close, *close, *close, *close, 1_000_000))
+            .collect();
+        BasketRun { summary: summarize_backtest(&params, &candles), volatility: annualized_volatility(closes) }
+    }
+
+    #[test]
+    fn test_basket_combines_weighted_runs_and_reports_failures() {
+        // Mirror images: each gains when the other loses
+        let up = [100.0, 102.0, 101.0, 104.0, 103.0, 106.0, 105.0, 108.0, 110.0];
+        let down = [100.0, 98.0, 99.0, 96.0, 97.0, 94.0, 95.0, 92.0, 90.0];
+        let runs = vec![
+            ("UP".to_string(), Ok(run("UP", &up))),
+            ("NODATA".to_string(), Err("No bars for NODATA".to_string())),
+            ("DOWN".to_string(), Ok(run("DOWN", &down))),
+        ];
+
+        let basket = aggregate_basket(runs, 100_000.0, BasketAllocation::EqualWeight, BasketRankMetric::Cagr, 1_704_900_000);
+        assert_eq!(basket.errors, vec![BasketError { symbol: "NODATA".into(), error: "No bars for NODATA".into() }]);
+        assert_eq!(basket.members.iter().map(|m| (m.symbol.as_str(), m.rank, m.weight)).collect::<Vec<_>>(), vec![("UP", 1, 0.5), ("DOWN", 2, 0.5)]);
+        // Half of each run: (110,000 + 90,000) / 2
+        assert_eq!(basket.equity_curve.len(), 9);
+        assert!((basket.equity_curve.last().unwrap().equity - 100_000.0).abs() < 1e-6);
+        assert!(basket.correlations[0].correlation < -0.99);
+        assert!(basket.max_dd > basket.members[0].max_dd.min(basket.members[1].max_dd));
+        assert!((basket.diversification_benefit - (basket.sharpe - basket.average_member_sharpe)).abs() < 1e-12);
+
+        // Inverse volatility puts more in the quieter symbol
+        let quiet = [100.0, 100.5, 100.2, 100.8, 100.6, 101.0, 100.9, 101.3, 101.5];
+        let runs = vec![("UP".to_string(), Ok(run("UP", &up))), ("QUIET".to_string(), Ok(run("QUIET", &quiet)))];
+        let basket = aggregate_basket(runs, 100_000.0, BasketAllocation::VolatilityWeighted, BasketRankMetric::MaxDrawdown, 1_704_900_000);
+        let weight = |symbol: &str| basket.members.iter().find(|m| m.symbol == symbol).unwrap().weight;
+        assert!(weight("QUIET") > weight("UP"));
+        assert!((weight("QUIET") + weight("UP") - 1.0).abs() < 1e-12);
+        assert_eq!(basket.members[0].symbol, "QUIET"); // Shallower drawdown ranks first
+        assert_eq!(allocation_weights(BasketAllocation::VolatilityWeighted, &[0.2, 0.0]), vec![0.5, 0.5]);
+    }
+}
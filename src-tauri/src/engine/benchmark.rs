@@ -0,0 +1,182 @@
+// src-tauri/src/engine/benchmark.rs
+// Shadow buy-and-hold account for judging the paper account against doing nothing. It
+// puts the paper account's equity into one symbol on the start date, buys or sells it
+// with every later deposit and withdrawal, and is marked at each session's close.
+// Enabling it for a past date replays the symbol's daily bars and the transfers since.
+
+use super::position_history::session_date;
+use crate::market_data::types::Candle;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BenchmarkPoint {
+    pub date: NaiveDate,
+    pub close: f64,
+    pub shares: f64,
+    pub equity: f64,
+    pub day_pnl: f64,  // Excluding the session's transfers
+    pub transfer: f64, // Deposited (positive) or withdrawn during the session
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BenchmarkAccount {
+    pub symbol: String,
+    pub start_date: NaiveDate,
+    pub starting_equity: f64, // The paper account's equity going into the start date
+    pub points: Vec<BenchmarkPoint>, // One per marked session, oldest first
+}
+
+/// The benchmark against the paper account over a statement period. Returns take
+/// transfers out of the change in equity.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BenchmarkComparison {
+    pub symbol: String,
+    pub starting_equity: f64,
+    pub ending_equity: f64,
+    pub transfers: f64,
+    pub benchmark_return: f64,
+    pub account_return: f64,
+    pub relative_return: f64, // Account less benchmark
+}
+
+/// Net transfers in sessions after `after` through `through`
+pub fn transfer_total(transfers: &[(NaiveDate, f64)], after: NaiveDate, through: NaiveDate) -> f64 {
+    transfers.iter().filter(|(date, _)| *date > after && *date <= through).map(|(_, amount)| amount).sum()
+}
+
+impl BenchmarkAccount {
+    pub fn new(symbol: &str, start_date: NaiveDate, starting_equity: f64) -> Self {
+        Self { symbol: symbol.to_string(), start_date, starting_equity, points: Vec::new() }
+    }
+
+    /// Replay daily `bars` from `start_date` up to but not including `until`, moving
+    /// `transfers` (session date, signed amount) in or out at each session's close
+    pub fn reconstruct(
+        symbol: &str,
+        start_date: NaiveDate,
+        starting_equity: f64,
+        bars: &[Candle],
+        transfers: &[(NaiveDate, f64)],
+        until: NaiveDate,
+    ) -> Result<Self, String> {
+        if starting_equity.is_nan() || starting_equity <= 0.0 {
+            return Err(format!("The account had no equity to invest on {}", start_date));
+        }
+        let mut account = Self::new(symbol, start_date, starting_equity);
+        let mut bars: Vec<&Candle> = bars.iter().collect();
+        bars.sort_by_key(|b| b.timestamp);
+        for bar in bars {
+            let date = session_date(bar.timestamp);
+            if date < start_date || date >= until {
+                continue;
+            }
+            let transfer = transfer_total(transfers, account.last_marked(), date);
+            account.mark(date, bar.close, transfer);
+        }
+        if account.points.is_empty() {
+            return Err(format!("No {} bars from {} to mark the benchmark with", symbol, start_date));
+        }
+        Ok(account)
+    }
+
+    /// Last session marked; the day before the start until the first mark
+    pub fn last_marked(&self) -> NaiveDate {
+        self.points.last().map_or(self.start_date.pred_opt().unwrap_or(self.start_date), |p| p.date)
+    }
+
+    /// Mark session `date` at `close`, first buying or selling `transfer` worth; sessions
+    /// already marked and unusable prices are ignored
+    pub fn mark(&mut self, date: NaiveDate, close: f64, transfer: f64) -> Option<&BenchmarkPoint> {
+        if date < self.start_date || self.points.last().is_some_and(|p| date <= p.date) || close.is_nan() || close <= 0.0 {
+            return None;
+        }
+        let (shares, previous_equity) = match self.points.last() {
+            Some(last) => (last.shares, last.equity),
+            None => (self.starting_equity / close, self.starting_equity),
+        };
+        let shares = (shares + transfer / close).max(0.0);
+        let equity = shares * close;
+        self.points.push(BenchmarkPoint { date, close, shares, equity, day_pnl: equity - previous_equity - transfer, transfer });
+        self.points.last()
+    }
+
+    /// Equity at the close of the last session marked on or before `date`
+    pub fn equity_on(&self, date: NaiveDate) -> Option<f64> {
+        self.points.iter().take_while(|p| p.date <= date).last().map(|p| p.equity)
+    }
+
+    pub fn day_pnl_on(&self, date: NaiveDate) -> Option<f64> {
+        self.points.iter().find(|p| p.date == date).map(|p| p.day_pnl)
+    }
+
+    /// The benchmark's sessions `from` through `to` next to the paper account's equity
+    /// going in and out of them and its net transfers; None when none were marked
+    pub fn compare(&self, from: NaiveDate, to: NaiveDate, account_start: f64, account_end: f64, account_transfers: f64) -> Option<BenchmarkComparison> {
+        let in_period: Vec<&BenchmarkPoint> = self.points.iter().filter(|p| p.date >= from && p.date <= to).collect();
+        let (first, last) = (in_period.first()?, in_period.last()?);
+        let starting_equity = self.points.iter().take_while(|p| p.date < from).last()
+            .map_or(first.equity - first.transfer - first.day_pnl, |p| p.equity);
+        let transfers: f64 = in_period.iter().map(|p| p.transfer).sum();
+        let period_return = |start: f64, end: f64, moved: f64| if start > 0.0 { (end - start - moved) / start } else { 0.0 };
+        let benchmark_return = period_return(starting_equity, last.equity, transfers);
+        let account_return = period_return(account_start, account_end, account_transfers);
+        Some(BenchmarkComparison {
+            symbol: self.symbol.clone(),
+            starting_equity,
+            ending_equity: last.equity,
+            transfers,
+            benchmark_return,
+            account_return,
+            relative_return: account_return - benchmark_return,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconstruction_replays_a_mid_period_deposit() {
+        // Weekdays from Tue 2024-01-02 at 10:00 ET, the price rising $1 a session
+        let day = |d: u32| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let sessions = [2, 3, 4, 5, 8, 9, 10];
+        let bars: Vec<Candle> = sessions
+            .iter()
+            .enumerate()
+            .map(|(i, d)| {
+                let close = 100.0 + i as f64;
+                Candle::new(1_704_207_600 + (*d as i64 - 2) * 86_400, close, close, close, close, 1_000)
+            })
+            .collect();
+        // A deposit before the start is already in the starting equity; one over the
+        // weekend goes in at Monday's close, and a withdrawal after `until` is not seen
+        let transfers = [(day(1), 50_000.0), (day(6), 5_200.0), (day(10), -1_000.0)];
+        let account = BenchmarkAccount::reconstruct("SPY", day(3), 10_100.0, &bars, &transfers, day(10)).unwrap();
+
+        let dates: Vec<NaiveDate> = account.points.iter().map(|p| p.date).collect();
+        assert_eq!(dates, vec![day(3), day(4), day(5), day(8), day(9)]);
+        assert!((account.points[0].shares - 100.0).abs() < 1e-9); // $10,100 at $101
+        assert_eq!(account.points[0].day_pnl, 0.0);
+        let monday = &account.points[3];
+        assert_eq!(monday.transfer, 5_200.0);
+        assert!((monday.shares - 150.0).abs() < 1e-9); // $5,200 more at $104
+        assert!((monday.day_pnl - 100.0).abs() < 1e-9); // The deposit is not P&L
+        assert!((account.equity_on(day(9)).unwrap() - 150.0 * 105.0).abs() < 1e-9);
+        assert_eq!(account.equity_on(day(2)), None);
+
+        // Marking forward only takes new sessions
+        let mut forward = account.clone();
+        assert!(forward.mark(day(9), 200.0, 0.0).is_none());
+        let next = forward.mark(day(10), 106.0, -1_060.0).unwrap();
+        assert!((next.shares - 140.0).abs() < 1e-9 && (next.day_pnl - 150.0).abs() < 1e-9);
+
+        // Over the period the benchmark made $450 on $10,100 plus the deposit
+        let comparison = account.compare(day(1), day(31), 10_100.0, 16_000.0, 5_200.0).unwrap();
+        assert_eq!((comparison.starting_equity, comparison.transfers), (10_100.0, 5_200.0));
+        assert!((comparison.benchmark_return - 450.0 / 10_100.0).abs() < 1e-12);
+        assert!((comparison.relative_return - 250.0 / 10_100.0).abs() < 1e-12);
+        assert!(account.compare(day(20), day(31), 1.0, 1.0, 0.0).is_none());
+    }
+}
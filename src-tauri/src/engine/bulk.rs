@@ -0,0 +1,88 @@
+// src-tauri/src/engine/bulk.rs
+// Bulk order operations: cancel every matching working order, close every matching
+// position, or both at once to go flat. Each runs under one hold of the broker lock and
+// is logged as a single parent record listing what happened to each order and position
+// it touched. Closing orders are tagged "bulk" and carry the parent id in their client
+// order id, so their trades lead back to the sweep.
+
+use super::mtm::{is_option_symbol, parse_option_symbol};
+use super::types::{InstrumentType, OrderSide, OrderStatus, Position};
+use serde::{Deserialize, Serialize};
+
+pub const BULK_TAG: &str = "bulk";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkActionKind {
+    CancelAllOrders,
+    CloseAllPositions,
+    GoFlat, // Cancel everything, then close everything
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkOutcome {
+    Canceled,
+    Filled,    // Filled before the cancel reached it
+    Submitted, // A closing order went out
+    Failed,
+    Skipped, // A spread's long leg whose short was not bought back
+}
+
+/// What the bulk action did to one order or position
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BulkChild {
+    pub symbol: String,
+    pub order_id: Option<String>, // The canceled order, or the closing one placed
+    pub side: Option<OrderSide>,  // Of a closing order
+    pub quantity: i64,
+    pub outcome: BulkOutcome,
+    pub status: Option<OrderStatus>, // Of the order afterwards
+    pub message: String,
+}
+
+/// Parent record of a bulk action; emitted as "bulk_action_completed"
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BulkAction {
+    pub id: String,
+    pub kind: BulkActionKind,
+    pub timestamp: i64,
+    pub session_date: chrono::NaiveDate,
+    pub children: Vec<BulkChild>,
+}
+
+impl BulkAction {
+    pub fn ids_with(&self, outcome: BulkOutcome) -> Vec<&str> {
+        self.children.iter().filter(|c| c.outcome == outcome).filter_map(|c| c.order_id.as_deref()).collect()
+    }
+}
+
+/// Which positions close_all_positions closes; every set field must match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PositionFilter {
+    #[serde(default)]
+    pub symbol: Option<String>, // The position's symbol, or an option's underlying
+    #[serde(default)]
+    pub instrument_type: Option<InstrumentType>,
+    #[serde(default)]
+    pub long_only: bool,
+    #[serde(default)]
+    pub short_only: bool,
+}
+
+impl PositionFilter {
+    pub fn matches(&self, position: &Position) -> bool {
+        let symbol = &position.symbol;
+        let option = parse_option_symbol(symbol).filter(|_| is_option_symbol(symbol));
+        let instrument_type = if option.is_some() { InstrumentType::Option } else { InstrumentType::Stock };
+        self.symbol.as_ref().is_none_or(|s| s == symbol || option.as_ref().is_some_and(|d| &d.underlying == s))
+            && self.instrument_type.as_ref().is_none_or(|t| *t == instrument_type)
+            && (!self.long_only || position.quantity > 0)
+            && (!self.short_only || position.quantity < 0)
+    }
+}
+
+/// Client order id of a bulk action's `sequence`th closing order
+pub fn child_order_id(parent_id: &str, sequence: u32) -> String {
+    format!("{}_{}_{}", BULK_TAG, parent_id, sequence)
+}
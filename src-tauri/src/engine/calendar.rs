@@ -5,6 +5,9 @@ use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc, NaiveDate, NaiveTime, NaiveDateTime, Datelike, Weekday, TimeZone};
 use chrono_tz::US::Eastern;
 
+// Longest span `session_seconds_between` looks back over
+const SESSION_LOOKBACK_DAYS: i64 = 14;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum MarketSession {
     PreMarket,    // 4:00 AM - 9:30 AM ET
@@ -250,6 +253,23 @@ impl MarketCalendar {
         None
     }
 
+    /// First regular-session close after `timestamp` (16:00 ET, 13:00 on early closes)
+    pub fn next_regular_close(&self, timestamp: i64) -> Option<i64> {
+        let mut date = DateTime::from_timestamp(timestamp, 0)?.with_timezone(&Eastern).date_naive();
+        for _ in 0..10 {
+            if self.is_trading_day(date) {
+                let open = Eastern.from_local_datetime(&date.and_hms_opt(9, 30, 0)?).single()?;
+                let session = self.get_session_info(open.with_timezone(&Utc));
+                let close = Eastern.from_local_datetime(&date.and_time(session.end_time)).single()?.timestamp();
+                if close > timestamp {
+                    return Some(close);
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
     /// Check if a specific date is a trading day
     pub fn is_trading_day(&self, date: NaiveDate) -> bool {
         // Check weekend
@@ -260,7 +280,7 @@ impl MarketCalendar {
         // Check holidays
         if let Some(holiday) = self.holidays.iter().find(|h| h.date == date) {
             if holiday.holiday_type == HolidayType::Full {
-                return !self.allow_holiday_trading;
+                return self.allow_holiday_trading;
             }
         }
         
@@ -282,6 +302,72 @@ impl MarketCalendar {
         trading_days
     }
 
+    /// First and last trading days in `start_date..=end_date`; None when none trade
+    pub fn trading_range(&self, start_date: NaiveDate, end_date: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        let days = self.get_trading_days(start_date, end_date);
+        Some((*days.first()?, *days.last()?))
+    }
+
+    /// Epoch seconds `date`'s session opens and closes: 9:30 to 16:00 ET (13:00 on early
+    /// closes), or from 4:00 through after hours (20:00) with `extended_hours`. None when
+    /// the date does not trade.
+    pub fn session_bounds(&self, date: NaiveDate, extended_hours: bool) -> Option<(i64, i64)> {
+        if !self.is_trading_day(date) {
+            return None;
+        }
+        let early_close = self.get_holiday(date).is_some_and(|h| h.holiday_type == HolidayType::EarlyClose);
+        let open = if extended_hours { (4, 0) } else { (9, 30) };
+        let close = match (early_close, extended_hours) {
+            (true, _) => (13, 0), // No after-hours session on early closes
+            (false, true) => (20, 0),
+            (false, false) => (16, 0),
+        };
+        let at = |(h, m): (u32, u32)| Eastern.from_local_datetime(&date.and_hms_opt(h, m, 0)?).single().map(|t| t.timestamp());
+        Some((at(open)?, at(close)?))
+    }
+
+    /// Bars of `interval_minutes` expected over `start_date..=end_date`: one per trading
+    /// day for daily bars (0 minutes), otherwise the clock-aligned intervals overlapping
+    /// each day's session
+    pub fn expected_bars(&self, start_date: NaiveDate, end_date: NaiveDate, interval_minutes: u32, extended_hours: bool) -> u64 {
+        let days = self.get_trading_days(start_date, end_date);
+        if interval_minutes == 0 {
+            return days.len() as u64;
+        }
+        let interval = interval_minutes as i64 * 60;
+        days.iter()
+            .filter_map(|date| self.session_bounds(*date, extended_hours))
+            .map(|(open, close)| ((close - 1).div_euclid(interval) - open.div_euclid(interval) + 1) as u64)
+            .sum()
+    }
+
+    /// Whether a bar starting at `timestamp` and lasting `duration_seconds` overlaps its
+    /// day's session
+    pub fn bar_in_session(&self, timestamp: i64, duration_seconds: i64, extended_hours: bool) -> bool {
+        let Some(date) = DateTime::from_timestamp(timestamp, 0).map(|dt| dt.with_timezone(&Eastern).date_naive()) else {
+            return false;
+        };
+        self.session_bounds(date, extended_hours)
+            .is_some_and(|(open, close)| timestamp < close && timestamp + duration_seconds.max(1) > open)
+    }
+
+    /// Seconds between `from` and `to` that fell inside a session, so quiet nights,
+    /// weekends and holidays do not count as time without data. Spans longer than
+    /// `SESSION_LOOKBACK_DAYS` are counted over their last days only.
+    pub fn session_seconds_between(&self, from: i64, to: i64, extended_hours: bool) -> i64 {
+        let from = from.max(to - SESSION_LOOKBACK_DAYS * 86400);
+        let (Some(start), Some(end)) = (DateTime::from_timestamp(from, 0), DateTime::from_timestamp(to, 0)) else {
+            return 0;
+        };
+        let (start, end) = (start.with_timezone(&Eastern).date_naive(), end.with_timezone(&Eastern).date_naive());
+        start
+            .iter_days()
+            .take_while(|date| *date <= end)
+            .filter_map(|date| self.session_bounds(date, extended_hours))
+            .map(|(open, close)| (close.min(to) - open.max(from)).max(0))
+            .sum()
+    }
+
     /// Get 2024 US market holidays
     fn get_2024_holidays() -> Vec<MarketHoliday> {
         vec![
@@ -507,4 +593,30 @@ mod tests {
         assert!(session.is_holiday);
         assert_eq!(session.holiday_name, Some("Custom Holiday".to_string()));
     }
+
+    #[test]
+    fn test_session_aware_request_ranges() {
+        let calendar = MarketCalendar::default();
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        let at = |m, d, h, min| Eastern.with_ymd_and_hms(2024, m, d, h, min, 0).unwrap().timestamp();
+
+        // Holiday Monday and the weekend are trimmed off both ends
+        assert_eq!(calendar.trading_range(date(1, 13), date(1, 21)), Some((date(1, 16), date(1, 19))));
+        assert_eq!(calendar.trading_range(date(1, 13), date(1, 15)), None);
+
+        // Hourly buckets: 7 in the regular session, 16 extended, 4 on an early close
+        assert_eq!(calendar.expected_bars(date(1, 2), date(1, 2), 60, false), 7);
+        assert_eq!(calendar.expected_bars(date(1, 2), date(1, 2), 60, true), 16);
+        assert_eq!(calendar.expected_bars(date(11, 29), date(11, 29), 60, false), 4);
+        assert_eq!(calendar.expected_bars(date(1, 1), date(1, 5), 0, false), 4);
+
+        // The 9:00 bar overlaps the open; the 8:00 bar only the extended session
+        assert!(calendar.bar_in_session(at(1, 2, 9, 0), 3600, false));
+        assert!(!calendar.bar_in_session(at(1, 2, 8, 0), 3600, false));
+        assert!(calendar.bar_in_session(at(1, 2, 8, 0), 3600, true));
+
+        // Friday close to Monday 3am is no session time; to 9:35 is five minutes
+        assert_eq!(calendar.session_seconds_between(at(1, 5, 16, 0), at(1, 8, 3, 0), false), 0);
+        assert_eq!(calendar.session_seconds_between(at(1, 5, 16, 0), at(1, 8, 9, 35), false), 300);
+    }
 }
@@ -0,0 +1,349 @@
+// src-tauri/src/engine/decision_outcomes.rs
+// Strategy decisions tied to what their orders did. Orders placed for a SignalEvaluation
+// carry its id onto their trades, so the round trips those trades opened are the
+// decision's outcome once the last of them has closed. Signal performance is built on
+// these records rather than on matching signals to trades by symbol and time.
+
+use super::position_history::session_date;
+use super::r#loop::{DecisionAction, SignalEvaluation};
+use super::round_trips::{round_trips, RoundTrip};
+use super::types::{DailySummary, StockSplit, Trade};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A decision that placed orders, as written to the decision journal
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DecisionRecord {
+    pub evaluation_id: String,
+    pub symbol: String, // Symbol, or "FIRST/SECOND" for a pair
+    pub timestamp: i64,
+    pub bar_timestamp: i64,
+    pub action: DecisionAction,
+    pub reason: String,
+    pub signals: Vec<String>, // Labels of the signals that agreed with the action
+    pub orders: usize,
+    #[serde(default)]
+    pub outcome: Option<DecisionOutcome>, // Written once everything it opened has closed
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DecisionOutcome {
+    pub evaluation_id: String,
+    pub entry_trade_ids: Vec<String>,
+    pub exit_trade_ids: Vec<String>,
+    pub quantity: i64, // Units opened, summed over legs
+    pub pnl: f64,      // Net of commission
+    pub return_pct: f64,
+    pub opened_at: i64,
+    pub closed_at: i64,
+    pub holding_seconds: i64,
+    pub mae: Option<f64>, // Worst unrealized return on the daily marks while held; <= 0. None when no session closed with it open
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionStatus {
+    Unfilled, // No order of the decision has filled
+    Open,     // Some of what it opened is still held
+    Closed,   // Everything it opened has closed; the outcome is final
+    Exit,     // Its fills only closed positions earlier decisions opened
+}
+
+/// A journal record joined with where its position stands
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DecisionReport {
+    #[serde(flatten)]
+    pub record: DecisionRecord,
+    pub status: DecisionStatus,
+    pub open_quantity: i64,
+    pub realized_pnl: f64, // What it opened and has closed so far; for exits, the P&L of what they closed
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SignalPerformance {
+    pub signal: String,
+    pub decisions: usize,
+    pub closed: usize,
+    pub open: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub win_rate: f64, // Over closed decisions
+    pub total_pnl: f64,
+    pub avg_pnl: f64,
+    pub avg_holding_seconds: f64,
+    pub avg_mae: Option<f64>,
+}
+
+impl DecisionRecord {
+    pub fn from_evaluation(evaluation: &SignalEvaluation) -> Self {
+        Self {
+            evaluation_id: evaluation.id.clone(),
+            symbol: evaluation.symbol.clone(),
+            timestamp: evaluation.timestamp,
+            bar_timestamp: evaluation.bar_timestamp,
+            action: evaluation.decision.action.clone(),
+            reason: evaluation.decision.reason.clone(),
+            signals: evaluation.decision.contributing_signals.clone(),
+            orders: evaluation.decision.orders.len(),
+            outcome: None,
+        }
+    }
+}
+
+/// Where each record's position stands in `trades`. Records without an outcome that
+/// have closed come back with one.
+pub fn settle_decisions(
+    records: &[DecisionRecord],
+    trades: &[Trade],
+    splits: &[StockSplit],
+    summaries: &[DailySummary],
+) -> Vec<DecisionReport> {
+    let trips = round_trips(trades, splits);
+    let mut filled: BTreeMap<&str, i64> = BTreeMap::new();
+    for trade in trades {
+        if let Some(id) = &trade.evaluation_id {
+            *filled.entry(id.as_str()).or_insert(0) += trade.quantity;
+        }
+    }
+    records.iter().map(|record| settle(record, filled.get(record.evaluation_id.as_str()).copied().unwrap_or(0), &trips, summaries)).collect()
+}
+
+fn settle(record: &DecisionRecord, filled: i64, trips: &[RoundTrip], summaries: &[DailySummary]) -> DecisionReport {
+    let id = Some(&record.evaluation_id);
+    let opened_trips: Vec<&RoundTrip> = trips.iter().filter(|t| t.entry_evaluation_id.as_ref() == id).collect();
+    let closing_trips: Vec<&RoundTrip> = trips.iter().filter(|t| t.exit_evaluation_id.as_ref() == id).collect();
+    let closed_by = closing_trips.iter().map(|t| t.quantity.abs()).sum::<i64>();
+    let opened = filled - closed_by;
+    // A split restates lots, so what closed can exceed the units the decision bought
+    let open_quantity = (opened - opened_trips.iter().map(|t| t.quantity.abs()).sum::<i64>()).max(0);
+
+    let mut record = record.clone();
+    let status = if record.outcome.is_some() {
+        DecisionStatus::Closed
+    } else if filled == 0 {
+        DecisionStatus::Unfilled
+    } else if opened <= 0 {
+        DecisionStatus::Exit
+    } else if open_quantity > 0 || opened_trips.is_empty() {
+        DecisionStatus::Open
+    } else {
+        record.outcome = Some(outcome(&record.evaluation_id, opened, &opened_trips, summaries));
+        DecisionStatus::Closed
+    };
+    let realized_pnl = match (&record.outcome, status) {
+        (Some(outcome), _) => outcome.pnl,
+        (None, DecisionStatus::Exit) => closing_trips.iter().map(|t| t.pnl).sum(),
+        _ => opened_trips.iter().map(|t| t.pnl).sum(),
+    };
+    DecisionReport { record, status, open_quantity, realized_pnl }
+}
+
+fn outcome(evaluation_id: &str, quantity: i64, trips: &[&RoundTrip], summaries: &[DailySummary]) -> DecisionOutcome {
+    let ids = |id: fn(&RoundTrip) -> &String| {
+        let mut ids: Vec<String> = Vec::new();
+        for trip in trips {
+            if !ids.contains(id(trip)) {
+                ids.push(id(trip).clone());
+            }
+        }
+        ids
+    };
+    let pnl = trips.iter().map(|t| t.pnl).sum::<f64>();
+    let notional = trips.iter().map(|t| t.entry_price * t.quantity.abs() as f64).sum::<f64>();
+    let opened_at = trips.iter().map(|t| t.entry_timestamp).min().unwrap_or_default();
+    let closed_at = trips.iter().map(|t| t.exit_timestamp).max().unwrap_or_default();
+    DecisionOutcome {
+        evaluation_id: evaluation_id.to_string(),
+        entry_trade_ids: ids(|t| &t.entry_trade_id),
+        exit_trade_ids: ids(|t| &t.exit_trade_id),
+        quantity,
+        pnl,
+        return_pct: if notional > 0.0 { trips.iter().map(|t| t.return_pct * t.entry_price * t.quantity.abs() as f64).sum::<f64>() / notional } else { 0.0 },
+        opened_at,
+        closed_at,
+        holding_seconds: closed_at - opened_at,
+        mae: adverse_excursion(trips, summaries),
+    }
+}
+
+/// The worst unrealized return across the sessions that closed with any of `trips` held,
+/// from each summary's mark of the symbol
+fn adverse_excursion(trips: &[&RoundTrip], summaries: &[DailySummary]) -> Option<f64> {
+    let mut worst: Option<f64> = None;
+    for summary in summaries {
+        let held: Vec<(&RoundTrip, f64)> = trips
+            .iter()
+            .filter(|t| t.entry_date <= summary.date && summary.date < t.exit_date)
+            .filter_map(|t| summary.marks.get(&t.symbol).map(|mark| (*t, *mark)))
+            .collect();
+        let notional = held.iter().map(|(t, _)| t.entry_price * t.quantity.abs() as f64).sum::<f64>();
+        if held.is_empty() || notional <= 0.0 {
+            continue;
+        }
+        let unrealized = held.iter().map(|(t, mark)| (mark - t.entry_price) * t.quantity as f64).sum::<f64>();
+        worst = Some(worst.unwrap_or(0.0).min(unrealized / notional));
+    }
+    worst
+}
+
+/// Decisions made on sessions `from` through `to`, optionally those on `symbol` (alone
+/// or as a pair leg)
+pub fn filter_decisions(reports: Vec<DecisionReport>, from: chrono::NaiveDate, to: chrono::NaiveDate, symbol: Option<&str>) -> Vec<DecisionReport> {
+    reports
+        .into_iter()
+        .filter(|r| (from..=to).contains(&session_date(r.record.timestamp)))
+        .filter(|r| symbol.is_none_or(|symbol| r.record.symbol.split('/').any(|s| s == symbol)))
+        .collect()
+}
+
+/// Results of the entry decisions each signal contributed to, best total P&L first
+pub fn signal_performance(reports: &[DecisionReport]) -> Vec<SignalPerformance> {
+    let mut by_signal: BTreeMap<&str, Vec<&DecisionReport>> = BTreeMap::new();
+    for report in reports.iter().filter(|r| matches!(r.status, DecisionStatus::Open | DecisionStatus::Closed)) {
+        for signal in &report.record.signals {
+            by_signal.entry(signal.as_str()).or_default().push(report);
+        }
+    }
+
+    let mut performance: Vec<SignalPerformance> = by_signal
+        .into_iter()
+        .map(|(signal, reports)| {
+            let outcomes: Vec<&DecisionOutcome> = reports.iter().filter_map(|r| r.record.outcome.as_ref()).collect();
+            let closed = outcomes.len();
+            let wins = outcomes.iter().filter(|o| o.pnl > 0.0).count();
+            let total_pnl = outcomes.iter().map(|o| o.pnl).sum::<f64>();
+            let maes: Vec<f64> = outcomes.iter().filter_map(|o| o.mae).collect();
+            let per_closed = |total: f64| if closed > 0 { total / closed as f64 } else { 0.0 };
+            SignalPerformance {
+                signal: signal.to_string(),
+                decisions: reports.len(),
+                closed,
+                open: reports.len() - closed,
+                wins,
+                losses: outcomes.iter().filter(|o| o.pnl < 0.0).count(),
+                win_rate: per_closed(wins as f64),
+                total_pnl,
+                avg_pnl: per_closed(total_pnl),
+                avg_holding_seconds: per_closed(outcomes.iter().map(|o| o.holding_seconds as f64).sum()),
+                avg_mae: (!maes.is_empty()).then(|| maes.iter().sum::<f64>() / maes.len() as f64),
+            }
+        })
+        .collect();
+    performance.sort_by(|a, b| b.total_pnl.total_cmp(&a.total_pnl));
+    performance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::types::{InstrumentType, OrderSide};
+    use chrono::NaiveDate;
+
+    const DAY: i64 = 86400;
+    const START: i64 = 1704207600; // 01/02/2024 10:00 ET
+
+    fn trade(id: &str, side: OrderSide, quantity: i64, price: f64, day: i64, evaluation_id: Option<&str>) -> Trade {
+        Trade {
+            id: id.to_string(),
+            symbol: "ABC".to_string(),
+            side,
+            quantity,
+            price,
+            timestamp: START + day * DAY,
+            order_id: format!("order-{}", id),
+            commission: 0.0,
+            net_amount: 0.0,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            leg_number: None,
+            assignment_id: None,
+            tag: None,
+            evaluation_id: evaluation_id.map(str::to_string),
+        }
+    }
+
+    fn record(evaluation_id: &str, action: DecisionAction, day: i64) -> DecisionRecord {
+        DecisionRecord {
+            evaluation_id: evaluation_id.to_string(),
+            symbol: "ABC".to_string(),
+            timestamp: START + day * DAY,
+            bar_timestamp: START + day * DAY,
+            action,
+            reason: String::new(),
+            signals: vec!["Breakout".to_string()],
+            orders: 1,
+            outcome: None,
+        }
+    }
+
+    fn summary(day: i64, mark: f64) -> DailySummary {
+        DailySummary {
+            date: session_date(START + day * DAY),
+            starting_equity: 0.0,
+            ending_equity: 0.0,
+            cash: 0.0,
+            unrealized_pnl: 0.0,
+            marks: BTreeMap::from([("ABC".to_string(), mark)]),
+            open_auction_fills: 0,
+            close_auction_fills: 0,
+            realized_equity: None,
+            equity_range: None,
+            benchmark_day_pnl: None,
+        }
+    }
+
+    #[test]
+    fn test_entry_decisions_settle_when_what_they_opened_closes() {
+        let records = vec![
+            record("buy-1", DecisionAction::Buy, 0),
+            record("buy-2", DecisionAction::Buy, 1),
+            record("close", DecisionAction::Close, 4),
+            record("buy-3", DecisionAction::Buy, 5),
+            record("buy-4", DecisionAction::Buy, 6),
+        ];
+        let trades = vec![
+            trade("1", OrderSide::Buy, 100, 10.0, 0, Some("buy-1")),
+            trade("2", OrderSide::Buy, 100, 12.0, 1, Some("buy-2")),
+            trade("3", OrderSide::Sell, 150, 11.0, 4, Some("close")),
+            trade("4", OrderSide::Buy, 10, 20.0, 5, Some("buy-3")),
+        ];
+        let summaries = vec![summary(0, 9.0), summary(1, 9.5), summary(2, 11.5), summary(3, 10.5)];
+        let reports = settle_decisions(&records, &trades, &[], &summaries);
+        let statuses: Vec<_> = reports.iter().map(|r| (r.status, r.open_quantity)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (DecisionStatus::Closed, 0),
+                (DecisionStatus::Open, 50),
+                (DecisionStatus::Exit, 0),
+                (DecisionStatus::Open, 10),
+                (DecisionStatus::Unfilled, 0),
+            ]
+        );
+
+        let outcome = reports[0].record.outcome.as_ref().unwrap();
+        assert_eq!((outcome.entry_trade_ids.as_slice(), outcome.exit_trade_ids.as_slice()), (&["1".to_string()][..], &["3".to_string()][..]));
+        assert!((outcome.pnl - 100.0).abs() < 1e-9);
+        assert_eq!(outcome.holding_seconds, 4 * DAY);
+        // Marked at 9.00 after the first session against a 10.00 entry
+        assert!((outcome.mae.unwrap() + 0.1).abs() < 1e-9);
+        // The second buy's first 50 closed at a loss; the rest is still open
+        assert!((reports[1].realized_pnl + 50.0).abs() < 1e-9);
+        assert!((reports[2].realized_pnl - 50.0).abs() < 1e-9);
+
+        // A settled outcome is kept as written
+        let mut settled = records.clone();
+        settled[0].outcome = Some(DecisionOutcome { pnl: 1.0, ..outcome.clone() });
+        assert_eq!(settle_decisions(&settled, &trades, &[], &[])[0].realized_pnl, 1.0);
+
+        let performance = signal_performance(&reports);
+        assert_eq!(performance.len(), 1);
+        let breakout = &performance[0];
+        assert_eq!((breakout.decisions, breakout.closed, breakout.open, breakout.wins), (3, 1, 2, 1));
+        assert!((breakout.total_pnl - 100.0).abs() < 1e-9);
+
+        let day = |d: i64| session_date(START + d * DAY);
+        assert_eq!(filter_decisions(reports.clone(), day(1), day(4), Some("ABC")).len(), 2);
+        assert!(filter_decisions(reports, NaiveDate::MIN, NaiveDate::MAX, Some("XYZ")).is_empty());
+    }
+}
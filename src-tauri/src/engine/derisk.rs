@@ -0,0 +1,383 @@
+// src-tauri/src/engine/derisk.rs
+// De-risking plans: rank positions and preview the reductions needed to reach a target exposure
+
+use super::types::*;
+use super::mtm::{is_option_symbol, parse_option_symbol, PortfolioGreeks};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum DeriskPriority {
+    #[default]
+    LargestLoss,
+    LargestExposure,
+    HighestVolatility,
+}
+
+/// A position, or a long/short option pair, that is reduced as one unit.
+/// Spread legs are ordered short first so the short is always bought back
+/// before the long is sold.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeriskUnit {
+    pub legs: Vec<String>,
+    pub is_spread: bool,
+    pub quantity: i64,       // Shares/contracts per leg available to reduce
+    pub exposure: f64,       // Sum of |market value| across legs
+    pub unrealized_pnl: f64,
+    pub volatility: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeriskStep {
+    pub sequence: u32,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub quantity: i64,
+    pub reference_price: f64,
+    pub estimated_proceeds: f64, // Positive for sells, negative for buy-to-close
+    pub exposure_reduction: f64,
+    pub spread_group: Option<u32>, // Steps sharing a group close the same spread
+    pub reason: String,
+    pub instrument_type: InstrumentType,
+    pub option_details: Option<OptionDetails>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeriskPlan {
+    pub id: String,
+    pub created_at: i64,
+    pub priority: DeriskPriority,
+    pub target_exposure_pct: f64, // Target gross exposure as a fraction of equity
+    pub equity: f64,
+    pub gross_exposure_before: f64,
+    pub target_gross_exposure: f64,
+    pub gross_exposure_after: f64,
+    pub estimated_net_proceeds: f64,
+    pub steps: Vec<DeriskStep>,
+    pub greeks_before: PortfolioGreeks,
+    pub greeks_after: PortfolioGreeks,
+    pub target_reached: bool,
+}
+
+/// Group positions into reduction units and order them by `priority`.
+/// This is the single ranking used for de-risking advice.
+pub fn rank_positions(
+    positions: &HashMap<String, Position>,
+    priority: DeriskPriority,
+    volatility: &dyn Fn(&str) -> f64,
+) -> Vec<DeriskUnit> {
+    let mut units = build_units(positions, volatility);
+
+    units.sort_by(|a, b| {
+        let ordering = match priority {
+            DeriskPriority::LargestLoss => a.unrealized_pnl.total_cmp(&b.unrealized_pnl),
+            DeriskPriority::LargestExposure => b.exposure.total_cmp(&a.exposure),
+            DeriskPriority::HighestVolatility => b.volatility
+                .total_cmp(&a.volatility)
+                .then(b.exposure.total_cmp(&a.exposure)),
+        };
+        ordering.then_with(|| a.legs.cmp(&b.legs))
+    });
+
+    units
+}
+
+/// Whole-share/contract reductions, in ranked order, that bring gross exposure
+/// down to `target_gross_exposure`
+pub fn plan_steps(
+    units: &[DeriskUnit],
+    positions: &HashMap<String, Position>,
+    gross_exposure: f64,
+    target_gross_exposure: f64,
+) -> Vec<DeriskStep> {
+    let mut steps = Vec::new();
+    let mut excess = gross_exposure - target_gross_exposure;
+    let mut spread_group = 0;
+
+    for unit in units {
+        if excess <= 1e-9 {
+            break;
+        }
+        if unit.quantity <= 0 || unit.exposure <= 0.0 {
+            continue;
+        }
+
+        let per_unit = unit.exposure / unit.quantity as f64;
+        let quantity = ((excess / per_unit).ceil() as i64).clamp(1, unit.quantity);
+        let group = if unit.is_spread {
+            spread_group += 1;
+            Some(spread_group)
+        } else {
+            None
+        };
+
+        push_unit_steps(&mut steps, unit, quantity, group, positions);
+        excess -= quantity as f64 * per_unit;
+    }
+
+    steps
+}
+
+/// Steps closing every unit in full, spreads first. A spread's short leg still comes
+/// before its long, so a sweep that stops part way never leaves a short uncovered.
+pub fn flatten_steps(units: &[DeriskUnit], positions: &HashMap<String, Position>) -> Vec<DeriskStep> {
+    let mut steps = Vec::new();
+    let mut spread_group = 0;
+    let (spreads, singles): (Vec<&DeriskUnit>, Vec<&DeriskUnit>) = units.iter().filter(|u| u.quantity > 0).partition(|u| u.is_spread);
+
+    for unit in spreads.into_iter().chain(singles) {
+        let group = unit.is_spread.then(|| {
+            spread_group += 1;
+            spread_group
+        });
+        push_unit_steps(&mut steps, unit, unit.quantity, group, positions);
+    }
+
+    steps
+}
+
+/// One step per leg of `unit`, each trading `quantity` toward flat
+fn push_unit_steps(
+    steps: &mut Vec<DeriskStep>,
+    unit: &DeriskUnit,
+    quantity: i64,
+    group: Option<u32>,
+    positions: &HashMap<String, Position>,
+) {
+    for symbol in &unit.legs {
+        let Some(position) = positions.get(symbol) else {
+            continue;
+        };
+        let price = position.last_price;
+        let (side, proceeds) = if position.quantity > 0 {
+            (OrderSide::Sell, quantity as f64 * price)
+        } else {
+            (OrderSide::Buy, -(quantity as f64 * price))
+        };
+        let reason = match (unit.is_spread, position.quantity > 0) {
+            (true, false) => "Buy back short spread leg".to_string(),
+            (true, true) => "Sell long spread leg".to_string(),
+            (false, true) => "Reduce long position".to_string(),
+            (false, false) => "Cover short position".to_string(),
+        };
+
+        steps.push(DeriskStep {
+            sequence: steps.len() as u32 + 1,
+            symbol: symbol.clone(),
+            side,
+            quantity,
+            reference_price: price,
+            estimated_proceeds: proceeds,
+            exposure_reduction: quantity as f64 * price.abs(),
+            spread_group: group,
+            reason,
+            instrument_type: if is_option_symbol(symbol) { InstrumentType::Option } else { InstrumentType::Stock },
+            option_details: parse_option_symbol(symbol),
+        });
+    }
+}
+
+/// Positions as they would look once every step is filled at its reference price
+pub fn apply_steps(positions: &HashMap<String, Position>, steps: &[DeriskStep]) -> HashMap<String, Position> {
+    let mut result = positions.clone();
+
+    for step in steps {
+        if let Some(position) = result.get_mut(&step.symbol) {
+            match step.side {
+                OrderSide::Sell => position.quantity -= step.quantity,
+                OrderSide::Buy => position.quantity += step.quantity,
+            }
+            let price = position.last_price;
+            position.update_market_data(price);
+        }
+    }
+
+    result.retain(|_, p| p.quantity != 0);
+    result
+}
+
+fn build_units(positions: &HashMap<String, Position>, volatility: &dyn Fn(&str) -> f64) -> Vec<DeriskUnit> {
+    let mut remaining: BTreeMap<String, i64> = positions
+        .iter()
+        .filter(|(_, p)| p.quantity != 0)
+        .map(|(s, p)| (s.clone(), p.quantity))
+        .collect();
+    let mut units = Vec::new();
+
+    // Pair long and short contracts on the same underlying, expiry and type
+    let mut groups: BTreeMap<(String, chrono::NaiveDate, String), (Vec<String>, Vec<String>)> = BTreeMap::new();
+    for (symbol, quantity) in &remaining {
+        if !is_option_symbol(symbol) {
+            continue;
+        }
+        if let Some(details) = parse_option_symbol(symbol) {
+            let key = (details.underlying, details.expiry, format!("{:?}", details.option_type));
+            let entry = groups.entry(key).or_default();
+            if *quantity > 0 {
+                entry.0.push(symbol.clone());
+            } else {
+                entry.1.push(symbol.clone());
+            }
+        }
+    }
+
+    for (longs, shorts) in groups.values() {
+        let (mut i, mut j) = (0, 0);
+        while i < longs.len() && j < shorts.len() {
+            let long_qty = remaining[&longs[i]];
+            let short_qty = -remaining[&shorts[j]];
+            let paired = long_qty.min(short_qty);
+
+            units.push(make_unit(positions, vec![shorts[j].clone(), longs[i].clone()], paired, volatility));
+            *remaining.get_mut(&longs[i]).unwrap() -= paired;
+            *remaining.get_mut(&shorts[j]).unwrap() += paired;
+
+            if remaining[&longs[i]] == 0 {
+                i += 1;
+            }
+            if remaining[&shorts[j]] == 0 {
+                j += 1;
+            }
+        }
+    }
+
+    for (symbol, quantity) in &remaining {
+        if *quantity != 0 {
+            units.push(make_unit(positions, vec![symbol.clone()], quantity.abs(), volatility));
+        }
+    }
+
+    units
+}
+
+fn make_unit(
+    positions: &HashMap<String, Position>,
+    legs: Vec<String>,
+    quantity: i64,
+    volatility: &dyn Fn(&str) -> f64,
+) -> DeriskUnit {
+    let mut exposure = 0.0;
+    let mut unrealized_pnl = 0.0;
+    let mut vol: f64 = 0.0;
+
+    for symbol in &legs {
+        let position = &positions[symbol];
+        let share = quantity as f64 / position.quantity.abs() as f64;
+        exposure += quantity as f64 * position.last_price.abs();
+        unrealized_pnl += position.unrealized_pnl * share;
+
+        let underlying = parse_option_symbol(symbol)
+            .filter(|_| is_option_symbol(symbol))
+            .map(|d| d.underlying)
+            .unwrap_or_else(|| symbol.clone());
+        vol = vol.max(volatility(&underlying));
+    }
+
+    DeriskUnit {
+        is_spread: legs.len() > 1,
+        legs,
+        quantity,
+        exposure,
+        unrealized_pnl,
+        volatility: vol,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(symbol: &str, quantity: i64, avg_cost: f64, price: f64) -> Position {
+        let mut position = Position::new(symbol.to_string());
+        position.quantity = quantity;
+        position.avg_cost = avg_cost;
+        position.update_market_data(price);
+        position
+    }
+
+    fn book() -> HashMap<String, Position> {
+        [
+            position("AAPL", 100, 150.0, 140.0),                // -1000, 14000 exposure
+            position("MSFT", 20, 300.0, 330.0),                 // +600, 6600 exposure
+            position("SPY240621C00500000", 5, 10.0, 12.0),      // long leg
+            position("SPY240621C00510000", -5, 6.0, 7.0),       // short leg
+        ]
+        .into_iter()
+        .map(|p| (p.symbol.clone(), p))
+        .collect()
+    }
+
+    #[test]
+    fn test_ranking_priorities() {
+        let positions = book();
+        let vol = |s: &str| if s == "MSFT" { 0.40 } else { 0.20 };
+
+        let by_loss = rank_positions(&positions, DeriskPriority::LargestLoss, &vol);
+        assert_eq!(by_loss[0].legs, vec!["AAPL"]);
+        assert_eq!(by_loss.len(), 3);
+
+        let by_exposure = rank_positions(&positions, DeriskPriority::LargestExposure, &vol);
+        assert_eq!(by_exposure[0].legs, vec!["AAPL"]);
+        assert_eq!(by_exposure[1].legs, vec!["MSFT"]);
+
+        let by_vol = rank_positions(&positions, DeriskPriority::HighestVolatility, &vol);
+        assert_eq!(by_vol[0].legs, vec!["MSFT"]);
+    }
+
+    #[test]
+    fn test_spread_legs_paired_short_first() {
+        let positions = book();
+        let units = rank_positions(&positions, DeriskPriority::LargestLoss, &|_| 0.25);
+        let spread = units.iter().find(|u| u.is_spread).unwrap();
+        assert_eq!(spread.legs, vec!["SPY240621C00510000", "SPY240621C00500000"]);
+        assert_eq!(spread.quantity, 5);
+
+        // Reduce only the spread
+        let steps = plan_steps(&[spread.clone()], &positions, 95.0, 0.0);
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].side, OrderSide::Buy);
+        assert_eq!(steps[0].symbol, "SPY240621C00510000");
+        assert_eq!(steps[1].side, OrderSide::Sell);
+        assert_eq!(steps[0].quantity, steps[1].quantity);
+        assert_eq!(steps[0].spread_group, steps[1].spread_group);
+    }
+
+    #[test]
+    fn test_flatten_closes_spreads_first_short_leg_leading() {
+        let mut positions = book();
+        positions.insert("SPY240621C00500000".to_string(), position("SPY240621C00500000", 8, 10.0, 12.0));
+        let units = rank_positions(&positions, DeriskPriority::LargestExposure, &|_| 0.25);
+
+        // The paired five go first, short leg leading; the three unpaired longs close on their own
+        let steps = flatten_steps(&units, &positions);
+        let order: Vec<(&str, &OrderSide, i64)> = steps.iter().map(|s| (s.symbol.as_str(), &s.side, s.quantity)).collect();
+        assert_eq!(order, vec![
+            ("SPY240621C00510000", &OrderSide::Buy, 5),
+            ("SPY240621C00500000", &OrderSide::Sell, 5),
+            ("AAPL", &OrderSide::Sell, 100),
+            ("MSFT", &OrderSide::Sell, 20),
+            ("SPY240621C00500000", &OrderSide::Sell, 3),
+        ]);
+        assert_eq!((steps[0].spread_group, steps[1].spread_group, steps[2].spread_group), (Some(1), Some(1), None));
+        assert!(apply_steps(&positions, &steps).is_empty());
+    }
+
+    #[test]
+    fn test_plan_uses_whole_shares_and_reaches_target() {
+        let positions = book();
+        let units = rank_positions(&positions, DeriskPriority::LargestExposure, &|_| 0.25);
+        let gross: f64 = positions.values().map(|p| p.market_value.abs()).sum();
+
+        // Shed 5,000 of exposure: AAPL at $140 needs ceil(5000 / 140) = 36 shares
+        let steps = plan_steps(&units, &positions, gross, gross - 5000.0);
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].symbol, "AAPL");
+        assert_eq!(steps[0].quantity, 36);
+        assert_eq!(steps[0].estimated_proceeds, 36.0 * 140.0);
+
+        let after = apply_steps(&positions, &steps);
+        let gross_after: f64 = after.values().map(|p| p.market_value.abs()).sum();
+        assert!(gross_after <= gross - 5000.0);
+        assert_eq!(after["AAPL"].quantity, 64);
+    }
+}
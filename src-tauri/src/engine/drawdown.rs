@@ -0,0 +1,186 @@
+// src-tauri/src/engine/drawdown.rs
+// High-water marks and drawdown of the live account. The broker records equity here on
+// every mark and fill, and the daily roll takes the session's peak and trough. Deposits
+// and withdrawals shift the marks by the amount moved, so a transfer neither recovers
+// nor deepens a drawdown.
+
+use serde::{Deserialize, Serialize};
+
+/// When the broker emits "drawdown_alert"
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DrawdownAlertConfig {
+    pub max_drawdown_pct: Option<f64>, // Alert once drawdown from the all-time high passes this, e.g. 0.1
+    pub new_high: bool,                // Alert on new all-time-high equity, at most once a session
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawdownAlertKind {
+    DrawdownExceeded,
+    NewAllTimeHigh,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DrawdownAlert {
+    pub kind: DrawdownAlertKind,
+    pub equity: f64,
+    pub high_water_mark: f64,
+    pub drawdown_pct: f64, // <= 0
+    pub timestamp: i64,
+}
+
+/// High-water marks and drawdowns as reported with the portfolio and risk status
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DrawdownStatus {
+    pub equity: f64,
+    pub all_time_high: f64,
+    pub all_time_high_at: i64,
+    pub ytd_high: f64,
+    pub drawdown_pct: f64,     // From the all-time high, <= 0
+    pub ytd_drawdown_pct: f64, // From the year's high, <= 0
+    pub session_peak: Option<f64>,
+    pub session_trough: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DrawdownTracker {
+    pub all_time_high: f64, // 0.0 until the first sample
+    pub all_time_high_at: i64,
+    pub ytd_high: f64,
+    pub ytd_year: i32,
+    pub equity: f64, // Last sample
+    pub updated_at: i64,
+    pub session_peak: Option<f64>,
+    pub session_trough: Option<f64>,
+    pub alerts: DrawdownAlertConfig,
+    drawdown_alerted: bool,       // Until drawdown comes back within the threshold
+    new_high_alerted_today: bool,
+}
+
+fn drawdown_from(high: f64, equity: f64) -> f64 {
+    if high > 0.0 {
+        ((equity - high) / high).min(0.0)
+    } else {
+        0.0
+    }
+}
+
+impl DrawdownTracker {
+    /// Take an equity sample at `timestamp` in session year `year`; returns the alerts it
+    /// set off
+    pub fn record(&mut self, equity: f64, timestamp: i64, year: i32) -> Vec<DrawdownAlert> {
+        if !equity.is_finite() {
+            return Vec::new();
+        }
+        let first = self.all_time_high <= 0.0;
+        if year != self.ytd_year {
+            self.ytd_year = year;
+            self.ytd_high = equity;
+        }
+        self.ytd_high = self.ytd_high.max(equity);
+        self.session_peak = Some(self.session_peak.map_or(equity, |p| p.max(equity)));
+        self.session_trough = Some(self.session_trough.map_or(equity, |t| t.min(equity)));
+        self.equity = equity;
+        self.updated_at = timestamp;
+
+        let mut alerts = Vec::new();
+        if equity > self.all_time_high {
+            self.all_time_high = equity;
+            self.all_time_high_at = timestamp;
+            if !first && self.alerts.new_high && !self.new_high_alerted_today {
+                self.new_high_alerted_today = true;
+                alerts.push(self.alert(DrawdownAlertKind::NewAllTimeHigh));
+            }
+        }
+        if let Some(limit) = self.alerts.max_drawdown_pct {
+            let exceeded = -self.drawdown_pct() > limit;
+            if exceeded && !self.drawdown_alerted {
+                alerts.push(self.alert(DrawdownAlertKind::DrawdownExceeded));
+            }
+            self.drawdown_alerted = exceeded;
+        }
+        alerts
+    }
+
+    fn alert(&self, kind: DrawdownAlertKind) -> DrawdownAlert {
+        DrawdownAlert {
+            kind,
+            equity: self.equity,
+            high_water_mark: self.all_time_high,
+            drawdown_pct: self.drawdown_pct(),
+            timestamp: self.updated_at,
+        }
+    }
+
+    /// Shift every mark by a deposit (positive) or withdrawal (negative) so the dollar
+    /// drawdown is unchanged by the transfer
+    pub fn apply_transfer(&mut self, amount: f64) {
+        if self.all_time_high <= 0.0 {
+            return;
+        }
+        self.all_time_high = (self.all_time_high + amount).max(0.0);
+        self.ytd_high = (self.ytd_high + amount).max(0.0);
+        self.equity += amount;
+        self.session_peak = self.session_peak.map(|p| p + amount);
+        self.session_trough = self.session_trough.map(|t| t + amount);
+    }
+
+    /// The closing session's (peak, trough) equity; the next session starts fresh
+    pub fn close_session(&mut self) -> Option<(f64, f64)> {
+        self.new_high_alerted_today = false;
+        self.session_peak.take().zip(self.session_trough.take())
+    }
+
+    pub fn drawdown_pct(&self) -> f64 {
+        drawdown_from(self.all_time_high, self.equity)
+    }
+
+    pub fn status(&self) -> DrawdownStatus {
+        DrawdownStatus {
+            equity: self.equity,
+            all_time_high: self.all_time_high,
+            all_time_high_at: self.all_time_high_at,
+            ytd_high: self.ytd_high,
+            drawdown_pct: self.drawdown_pct(),
+            ytd_drawdown_pct: drawdown_from(self.ytd_high, self.equity),
+            session_peak: self.session_peak,
+            session_trough: self.session_trough,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marks_alerts_and_session_range() {
+        let mut tracker = DrawdownTracker {
+            alerts: DrawdownAlertConfig { max_drawdown_pct: Some(0.1), new_high: true },
+            ..DrawdownTracker::default()
+        };
+        assert!(tracker.record(100.0, 1, 2024).is_empty()); // The first sample sets the marks
+        let alerts = tracker.record(110.0, 2, 2024);
+        assert_eq!(alerts.iter().map(|a| a.kind).collect::<Vec<_>>(), vec![DrawdownAlertKind::NewAllTimeHigh]);
+        assert!(tracker.record(120.0, 3, 2024).is_empty()); // Once a session
+
+        assert!(tracker.record(110.0, 4, 2024).is_empty());
+        let alerts = tracker.record(105.0, 5, 2024);
+        assert_eq!(alerts[0].kind, DrawdownAlertKind::DrawdownExceeded);
+        assert!((alerts[0].drawdown_pct + 0.125).abs() < 1e-12);
+        assert!(tracker.record(100.0, 6, 2024).is_empty()); // Still past the threshold
+        assert!(tracker.record(115.0, 7, 2024).is_empty()); // Back within it re-arms
+        assert_eq!(tracker.record(104.0, 8, 2024).len(), 1);
+
+        assert_eq!(tracker.close_session(), Some((120.0, 100.0)));
+        assert_eq!(tracker.close_session(), None);
+
+        // A new year resets the year-to-date high but not the all-time one
+        tracker.record(104.0, 9, 2025);
+        let status = tracker.status();
+        assert_eq!((status.all_time_high, status.ytd_high, status.ytd_drawdown_pct), (120.0, 104.0, 0.0));
+        assert!((status.drawdown_pct + 16.0 / 120.0).abs() < 1e-12);
+    }
+}
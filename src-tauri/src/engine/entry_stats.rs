@@ -0,0 +1,186 @@
+// src-tauri/src/engine/entry_stats.rs
+// Running "since entry" statistics for each open position: the average entry price of
+// the fills that built it, the market's VWAP since the first of them, the best and worst
+// marks, and the R-multiple once an initial stop is known. The broker feeds it every
+// fill, trade print and accepted market data point, and starts over when a position
+// closes or flips. Kept beside the positions rather than in them so the saved Position
+// stays small.
+
+use super::types::{Fill, OrderSide};
+use serde::{Deserialize, Serialize};
+
+/// What the market VWAP since entry was weighted by
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VwapSource {
+    #[default]
+    None,  // No volume seen yet
+    Ticks, // Trade prints and their sizes
+    Bars,  // Marks weighted by the volume they carried, when no prints arrive
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EntryTracker {
+    pub symbol: String,
+    pub direction: i64, // 1 long, -1 short
+    pub opened_at: i64,
+    pub entry_quantity: i64,
+    pub entry_notional: f64, // Price times quantity of the fills that built the position
+    pub market_notional: f64,
+    pub market_volume: f64,
+    pub vwap_source: VwapSource,
+    pub high_mark: f64,
+    pub low_mark: f64,
+    pub initial_stop: Option<f64>, // First stop known after entry; later moves do not change it
+}
+
+/// A position's since-entry figures as shown with the enhanced portfolio
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PositionStats {
+    pub symbol: String,
+    pub opened_at: i64,
+    pub time_in_position_secs: i64,
+    pub entry_vwap: f64,
+    pub market_vwap: Option<f64>,
+    pub vwap_source: VwapSource,
+    pub entry_vs_market_vwap: Option<f64>, // Per share, positive when the entry beat the market's VWAP
+    pub highest_mark: f64,
+    pub lowest_mark: f64,
+    pub max_favorable_excursion: f64, // Per share from the entry VWAP, >= 0
+    pub max_adverse_excursion: f64,   // Per share from the entry VWAP, >= 0
+    pub initial_stop: Option<f64>,
+    pub r_multiple: Option<f64>, // Open gain over the initial risk per share
+}
+
+impl EntryTracker {
+    /// Tracker for a position opened at `price`
+    pub fn open(symbol: &str, direction: i64, quantity: i64, price: f64, opened_at: i64) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            direction: direction.signum(),
+            opened_at,
+            entry_quantity: quantity.abs(),
+            entry_notional: price * quantity.abs() as f64,
+            market_notional: 0.0,
+            market_volume: 0.0,
+            vwap_source: VwapSource::None,
+            high_mark: price,
+            low_mark: price,
+            initial_stop: None,
+        }
+    }
+
+    /// Fold in a fill that leaves the position open on the same side; fills that add to
+    /// it count toward the entry price, reductions only mark
+    pub fn on_fill(&mut self, fill: &Fill) {
+        let adds = matches!((&fill.side, self.direction), (OrderSide::Buy, 1) | (OrderSide::Sell, -1));
+        if adds {
+            self.entry_quantity += fill.quantity;
+            self.entry_notional += fill.price * fill.quantity as f64;
+        }
+        self.mark(fill.price);
+    }
+
+    /// A trade print from the tick stream
+    pub fn on_print(&mut self, price: f64, size: i64) {
+        if price <= 0.0 {
+            return;
+        }
+        if self.vwap_source != VwapSource::Ticks {
+            // Prints replace any bar approximation from here on
+            self.market_notional = 0.0;
+            self.market_volume = 0.0;
+            self.vwap_source = VwapSource::Ticks;
+        }
+        self.market_notional += price * size.max(0) as f64;
+        self.market_volume += size.max(0) as f64;
+        self.mark(price);
+    }
+
+    /// An accepted market data point; its volume only weighs in while no prints arrive
+    pub fn on_market_data(&mut self, price: f64, volume: Option<i64>) {
+        if price <= 0.0 {
+            return;
+        }
+        if let Some(volume) = volume.filter(|v| *v > 0) {
+            if self.vwap_source != VwapSource::Ticks {
+                self.market_notional += price * volume as f64;
+                self.market_volume += volume as f64;
+                self.vwap_source = VwapSource::Bars;
+            }
+        }
+        self.mark(price);
+    }
+
+    fn mark(&mut self, price: f64) {
+        if price > 0.0 {
+            self.high_mark = self.high_mark.max(price);
+            self.low_mark = self.low_mark.min(price);
+        }
+    }
+
+    /// Take `stop` as the initial stop if none is known yet and it is on the losing side
+    pub fn note_stop(&mut self, stop: Option<f64>) {
+        let Some(stop) = stop else { return };
+        if self.initial_stop.is_none() && (self.entry_vwap() - stop) * self.direction as f64 > 0.0 {
+            self.initial_stop = Some(stop);
+        }
+    }
+
+    /// Restate in post-split shares; notionals are unchanged
+    pub fn apply_split(&mut self, ratio: f64) {
+        self.entry_quantity = (self.entry_quantity as f64 * ratio).round() as i64;
+        self.market_volume *= ratio;
+        self.high_mark /= ratio;
+        self.low_mark /= ratio;
+        self.initial_stop = self.initial_stop.map(|p| p / ratio);
+    }
+
+    pub fn entry_vwap(&self) -> f64 {
+        if self.entry_quantity > 0 {
+            self.entry_notional / self.entry_quantity as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Fraction `mark` is below the best mark since entry, 0 at or beyond it
+    pub fn drawdown_from_best(&self, mark: f64) -> f64 {
+        let best = if self.direction > 0 { self.high_mark } else { self.low_mark };
+        if best <= 0.0 || mark <= 0.0 {
+            return 0.0;
+        }
+        ((best - mark) * self.direction as f64 / best).max(0.0)
+    }
+
+    pub fn market_vwap(&self) -> Option<f64> {
+        (self.market_volume > 0.0).then(|| self.market_notional / self.market_volume)
+    }
+
+    pub fn stats(&self, mark: f64, now: i64) -> PositionStats {
+        let direction = self.direction as f64;
+        let entry = self.entry_vwap();
+        let market_vwap = self.market_vwap();
+        let (best, worst) = if self.direction > 0 { (self.high_mark, self.low_mark) } else { (self.low_mark, self.high_mark) };
+        let r_multiple = self.initial_stop.and_then(|stop| {
+            let risk = (entry - stop) * direction;
+            (risk > 0.0).then(|| (mark - entry) * direction / risk)
+        });
+
+        PositionStats {
+            symbol: self.symbol.clone(),
+            opened_at: self.opened_at,
+            time_in_position_secs: (now - self.opened_at).max(0),
+            entry_vwap: entry,
+            market_vwap,
+            vwap_source: self.vwap_source,
+            entry_vs_market_vwap: market_vwap.map(|vwap| (vwap - entry) * direction),
+            highest_mark: self.high_mark,
+            lowest_mark: self.low_mark,
+            max_favorable_excursion: ((best - entry) * direction).max(0.0),
+            max_adverse_excursion: ((entry - worst) * direction).max(0.0),
+            initial_stop: self.initial_stop,
+            r_multiple,
+        }
+    }
+}
@@ -0,0 +1,273 @@
+// src-tauri/src/engine/equity_history.rs
+// Sampled account equity in retention tiers. The broker samples equity at a set cadence;
+// the daily roll compacts samples older than the full-resolution window into 15-minute
+// buckets, and those older than the 15-minute window into daily ones. Reads stitch the
+// tiers back into one series and report the resolution of each stretch.
+
+use super::metrics::{ohlc_drawdown_bound, ohlc_max_drawdown, time_weighted_volatility};
+use super::position_history::session_date;
+use super::types::DailySummary;
+use chrono::{Datelike, NaiveDate, TimeZone};
+use chrono_tz::America::New_York;
+use serde::{Deserialize, Serialize};
+
+pub const FIFTEEN_MINUTES: i64 = 15 * 60;
+const DAY_SECONDS: i64 = 86_400;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum EquityResolution {
+    Full, // One bucket per sample
+    FifteenMinute,
+    Daily, // One bucket per session
+}
+
+/// How often equity is sampled and how long each resolution is kept
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct EquityRetention {
+    pub sample_cadence_seconds: i64,
+    pub full_resolution_days: i64,  // Samples younger than this stay as taken
+    pub fifteen_minute_days: i64,   // 15-minute buckets younger than this are not merged into days
+}
+
+impl Default for EquityRetention {
+    fn default() -> Self {
+        Self { sample_cadence_seconds: 60, full_resolution_days: 14, fifteen_minute_days: 90 }
+    }
+}
+
+impl EquityRetention {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.sample_cadence_seconds <= 0 {
+            return Err("sample_cadence_seconds must be positive".to_string());
+        }
+        if self.full_resolution_days < 1 || self.fifteen_minute_days < self.full_resolution_days {
+            return Err("Retention windows must be at least a day, the 15-minute one no shorter than the full-resolution one".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct EquitySample {
+    pub timestamp: i64,
+    pub equity: f64,
+    pub unrealized_pnl: f64, // Equity less this is realized-only equity
+}
+
+/// Equity over a stretch of samples; a single sample at full resolution
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EquityBucket {
+    pub start: i64, // First sample's time
+    pub end: i64,   // Last sample's time
+    pub resolution: EquityResolution,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub realized_equity: f64, // At the close
+    pub unrealized_pnl: f64,  // At the close
+    pub samples: usize,
+}
+
+impl EquityBucket {
+    pub fn from_sample(sample: &EquitySample) -> Self {
+        Self {
+            start: sample.timestamp,
+            end: sample.timestamp,
+            resolution: EquityResolution::Full,
+            open: sample.equity,
+            high: sample.equity,
+            low: sample.equity,
+            close: sample.equity,
+            realized_equity: sample.equity - sample.unrealized_pnl,
+            unrealized_pnl: sample.unrealized_pnl,
+            samples: 1,
+        }
+    }
+
+    /// A session from its recorded summary, for history from before sampling began
+    pub fn from_summary(summary: &DailySummary) -> Self {
+        let at = |hour: u32, minute: u32| {
+            summary.date.and_hms_opt(hour, minute, 0)
+                .and_then(|t| New_York.from_local_datetime(&t).earliest())
+                .map_or(0, |t| t.timestamp())
+        };
+        let (open, close) = (summary.starting_equity, summary.ending_equity);
+        let (high, low) = summary.equity_range.unwrap_or((open.max(close), open.min(close)));
+        Self {
+            start: at(9, 30),
+            end: at(16, 0),
+            resolution: EquityResolution::Daily,
+            open,
+            high: high.max(open).max(close),
+            low: low.min(open).min(close),
+            close,
+            realized_equity: summary.realized_equity.unwrap_or(close - summary.unrealized_pnl),
+            unrealized_pnl: summary.unrealized_pnl,
+            samples: 1,
+        }
+    }
+
+    /// Which `resolution` bucket this one falls in: the 15-minute boundary or session day
+    fn key(&self, resolution: EquityResolution) -> i64 {
+        match resolution {
+            EquityResolution::Full => self.start,
+            EquityResolution::FifteenMinute => self.start - self.start.rem_euclid(FIFTEEN_MINUTES),
+            EquityResolution::Daily => session_date(self.start).num_days_from_ce() as i64,
+        }
+    }
+
+    fn absorb(&mut self, later: &EquityBucket) {
+        self.end = later.end;
+        self.high = self.high.max(later.high);
+        self.low = self.low.min(later.low);
+        self.close = later.close;
+        self.realized_equity = later.realized_equity;
+        self.unrealized_pnl = later.unrealized_pnl;
+        self.samples += later.samples;
+    }
+}
+
+/// Append `incoming` (oldest first) to `tier`, merging into `resolution` buckets
+fn merge_into(tier: &mut Vec<EquityBucket>, incoming: impl IntoIterator<Item = EquityBucket>, resolution: EquityResolution) {
+    for bucket in incoming {
+        match tier.last_mut() {
+            Some(last) if last.key(resolution) == bucket.key(resolution) => last.absorb(&bucket),
+            _ => tier.push(EquityBucket { resolution, ..bucket }),
+        }
+    }
+}
+
+/// A run of consecutive buckets at one resolution
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EquitySegment {
+    pub resolution: EquityResolution,
+    pub start: i64,
+    pub end: i64,
+    pub points: usize,
+}
+
+/// The stitched series with metrics that account for its mixed resolution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampledEquity {
+    pub buckets: Vec<EquityBucket>, // Oldest first
+    pub segments: Vec<EquitySegment>,
+    pub max_dd: f64,            // <= 0, from bucket highs and lows
+    pub max_dd_bound: f64,      // The full-resolution max drawdown is at most this much deeper
+    pub annualized_vol: f64,    // Time-weighted
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct EquityTiers {
+    pub full: Vec<EquitySample>,
+    pub fifteen_minute: Vec<EquityBucket>,
+    pub daily: Vec<EquityBucket>,
+}
+
+impl EquityTiers {
+    /// Move samples and buckets that have aged out of their window down a tier; returns
+    /// whether anything moved. Cutoffs fall on bucket boundaries, so no bucket is split
+    /// across tiers.
+    pub fn compact(&mut self, now: i64, retention: &EquityRetention) -> bool {
+        let full_cutoff = now - retention.full_resolution_days * DAY_SECONDS;
+        let full_cutoff = full_cutoff - full_cutoff.rem_euclid(FIFTEEN_MINUTES);
+        let aged = self.full.iter().take_while(|s| s.timestamp < full_cutoff).count();
+        let aged_samples: Vec<EquitySample> = self.full.drain(..aged).collect();
+        merge_into(&mut self.fifteen_minute, aged_samples.iter().map(EquityBucket::from_sample), EquityResolution::FifteenMinute);
+
+        let day_cutoff = session_date(now - retention.fifteen_minute_days * DAY_SECONDS);
+        let aged_buckets = self.fifteen_minute.iter().take_while(|b| session_date(b.start) < day_cutoff).count();
+        let aged_buckets: Vec<EquityBucket> = self.fifteen_minute.drain(..aged_buckets).collect();
+        let moved = aged > 0 || !aged_buckets.is_empty();
+        merge_into(&mut self.daily, aged_buckets, EquityResolution::Daily);
+        moved
+    }
+
+    /// Every tier as one series over sessions `from` through `to`, oldest first
+    pub fn stitch(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> SampledEquity {
+        let in_range = |b: &EquityBucket| {
+            let date = session_date(b.start);
+            from.is_none_or(|f| date >= f) && to.is_none_or(|t| date <= t)
+        };
+        let mut buckets: Vec<EquityBucket> = self.daily.iter()
+            .chain(&self.fifteen_minute)
+            .cloned()
+            .chain(self.full.iter().map(EquityBucket::from_sample))
+            .filter(in_range)
+            .collect();
+        buckets.sort_by_key(|b| b.start);
+
+        let mut segments: Vec<EquitySegment> = Vec::new();
+        for bucket in &buckets {
+            match segments.last_mut() {
+                Some(segment) if segment.resolution == bucket.resolution => {
+                    segment.end = bucket.end;
+                    segment.points += 1;
+                }
+                _ => segments.push(EquitySegment { resolution: bucket.resolution, start: bucket.start, end: bucket.end, points: 1 }),
+            }
+        }
+
+        let ohlc: Vec<(f64, f64, f64)> = buckets.iter().map(|b| (b.open, b.high, b.low)).collect();
+        let closes: Vec<(i64, f64)> = buckets.iter().map(|b| (b.end, b.close)).collect();
+        SampledEquity {
+            max_dd: ohlc_max_drawdown(&ohlc),
+            max_dd_bound: ohlc_drawdown_bound(&ohlc),
+            annualized_vol: time_weighted_volatility(&closes),
+            buckets,
+            segments,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::metrics::calc_drawdown_series;
+
+    #[test]
+    fn test_compaction_keeps_drawdown_within_bucket_bound() {
+        // 60 days of minute samples through the regular session, wandering with a selloff
+        let first_open = 1_704_205_800; // 2024-01-02 09:30 ET
+        let samples: Vec<EquitySample> = (0..60)
+            .flat_map(|day| (0..390).map(move |minute| (day, minute)))
+            .map(|(day, minute)| {
+                let step = (day * 390 + minute) as f64;
+                let selloff = if (20..30).contains(&day) { -3_000.0 * ((day - 20) as f64 + minute as f64 / 390.0) } else { 0.0 };
+                let equity = 100_000.0 + (step * 0.05).sin() * 400.0 + (step * 0.003).cos() * 1_500.0 + selloff.max(-20_000.0);
+                EquitySample { timestamp: first_open + day * DAY_SECONDS + minute * 60, equity, unrealized_pnl: equity * 0.1 }
+            })
+            .collect();
+        let now = samples.last().unwrap().timestamp + 60;
+        let (_, full_dd) = calc_drawdown_series(&samples.iter().map(|s| s.equity).collect::<Vec<_>>());
+
+        let mut tiers = EquityTiers { full: samples.clone(), ..EquityTiers::default() };
+        let retention = EquityRetention { sample_cadence_seconds: 60, full_resolution_days: 14, fifteen_minute_days: 30 };
+        assert!(tiers.compact(now, &retention));
+        assert!(!tiers.compact(now, &retention)); // Nothing left to age out
+        assert!(tiers.full.iter().all(|s| s.timestamp >= now - 14 * DAY_SECONDS - FIFTEEN_MINUTES));
+        assert!(tiers.fifteen_minute.iter().all(|b| b.start % FIFTEEN_MINUTES == 0 && b.samples == 15));
+        assert!(!tiers.daily.is_empty() && tiers.daily.iter().all(|b| b.samples == 390));
+
+        let stitched = tiers.stitch(None, None);
+        let resolutions: Vec<EquityResolution> = stitched.segments.iter().map(|s| s.resolution).collect();
+        assert_eq!(resolutions, vec![EquityResolution::Daily, EquityResolution::FifteenMinute, EquityResolution::Full]);
+        assert_eq!(stitched.segments.iter().map(|s| s.points).sum::<usize>(), stitched.buckets.len());
+        assert_eq!(stitched.buckets.iter().map(|b| b.samples).sum::<usize>(), samples.len());
+        let last = stitched.buckets.last().unwrap();
+        assert_eq!((last.close, last.realized_equity), (samples.last().unwrap().equity, samples.last().unwrap().equity * 0.9));
+
+        // Never deeper than the real drawdown, and shallower by at most the bound
+        assert!(stitched.max_dd >= full_dd - 1e-12);
+        assert!(stitched.max_dd - full_dd <= stitched.max_dd_bound + 1e-12, "{} {} {}", stitched.max_dd, full_dd, stitched.max_dd_bound);
+        assert!(stitched.max_dd_bound > 0.0 && stitched.max_dd_bound < 0.1);
+
+        // Range reads only return the sessions asked for
+        let day = session_date(first_open + 40 * DAY_SECONDS);
+        let one_day = tiers.stitch(Some(day), Some(day));
+        assert!(one_day.buckets.iter().all(|b| session_date(b.start) == day));
+        assert_eq!(one_day.buckets.iter().map(|b| b.samples).sum::<usize>(), 390);
+    }
+}
@@ -0,0 +1,179 @@
+// src-tauri/src/engine/freshness.rs
+// How current the marks behind the portfolio are. Each position remembers the timestamp
+// of the quote it was last marked with; its age at read time is classed live, delayed or
+// stale for the current session. The live line differs by session; the stale line is
+// always the stale-quote guard's max_quote_age_seconds, so a quote too old to fill
+// against is exactly a stale mark. While the calendar has the market shut nothing is
+// expected to move, and marks are classed closed rather than stale.
+
+use super::calendar::MarketSession;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum FreshnessClass {
+    Closed, // Market shut; age not judged
+    #[default]
+    Live,
+    Delayed,
+    Stale,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FreshnessConfig {
+    pub live_secs: i64,            // Younger marks are live in the regular session
+    pub extended_live_secs: i64,   // Pre-market and after hours, where quotes update less often
+    pub refuse_stale_orders: bool, // Reject new orders in a symbol whose quote is stale
+}
+
+impl Default for FreshnessConfig {
+    fn default() -> Self {
+        Self { live_secs: 5, extended_live_secs: 30, refuse_stale_orders: false }
+    }
+}
+
+impl FreshnessConfig {
+    pub fn validate(&self, max_quote_age_seconds: i64) -> Result<(), String> {
+        if self.live_secs <= 0 || self.extended_live_secs <= 0 {
+            return Err("Live thresholds must be positive".to_string());
+        }
+        if self.live_secs.max(self.extended_live_secs) > max_quote_age_seconds {
+            return Err(format!("Live thresholds cannot exceed max_quote_age_seconds ({})", max_quote_age_seconds));
+        }
+        Ok(())
+    }
+
+    /// (live, stale) thresholds for `session`, None while the market is shut
+    pub fn thresholds(&self, session: &MarketSession, trading: bool, max_quote_age_seconds: i64) -> Option<(i64, i64)> {
+        match session {
+            _ if !trading => None,
+            MarketSession::Regular => Some((self.live_secs, max_quote_age_seconds)),
+            MarketSession::PreMarket | MarketSession::AfterHours => Some((self.extended_live_secs, max_quote_age_seconds)),
+            MarketSession::Closed => None,
+        }
+    }
+}
+
+/// Class of a mark `age_secs` old; stale only past the stale threshold, as with the guard
+pub fn classify(age_secs: i64, thresholds: Option<(i64, i64)>) -> FreshnessClass {
+    match thresholds {
+        None => FreshnessClass::Closed,
+        Some((live, _)) if age_secs < live => FreshnessClass::Live,
+        Some((_, stale)) if age_secs > stale => FreshnessClass::Stale,
+        Some(_) => FreshnessClass::Delayed,
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PortfolioFreshness {
+    pub class: FreshnessClass, // Worst of the positions; live with none held
+    pub worst_symbol: Option<String>,
+    pub worst_age_secs: Option<i64>,
+    pub live: usize,
+    pub delayed: usize,
+    pub stale: usize,
+    pub closed: usize,
+    pub as_of: i64,
+}
+
+impl PortfolioFreshness {
+    /// Summary of (symbol, class, age) for every held position
+    pub fn summarize<'a>(marks: impl IntoIterator<Item = (&'a str, FreshnessClass, i64)>, as_of: i64) -> Self {
+        let mut summary = Self { as_of, ..Self::default() };
+        let mut worst: Option<(FreshnessClass, i64, &str)> = None;
+        for (symbol, class, age) in marks {
+            match class {
+                FreshnessClass::Live => summary.live += 1,
+                FreshnessClass::Delayed => summary.delayed += 1,
+                FreshnessClass::Stale => summary.stale += 1,
+                FreshnessClass::Closed => summary.closed += 1,
+            }
+            // Oldest of the worst class; the symbol breaks ties so the pick is stable
+            if worst.is_none_or(|w| (class, age, std::cmp::Reverse(symbol)) > (w.0, w.1, std::cmp::Reverse(w.2))) {
+                worst = Some((class, age, symbol));
+            }
+        }
+        if let Some((class, age, symbol)) = worst {
+            summary.class = class;
+            summary.worst_symbol = Some(symbol.to_string());
+            summary.worst_age_secs = Some(age);
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FreshnessTransition {
+    pub symbol: String,
+    pub from: FreshnessClass,
+    pub to: FreshnessClass,
+}
+
+/// Payload of "portfolio_freshness_changed"
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FreshnessChange {
+    pub freshness: PortfolioFreshness,
+    pub transitions: Vec<FreshnessTransition>, // Held positions whose class moved, by symbol
+}
+
+/// Last classes reported, so the event fires on transitions only
+#[derive(Debug, Clone, Default)]
+pub struct FreshnessMonitor {
+    classes: HashMap<String, FreshnessClass>,
+    portfolio: Option<FreshnessClass>,
+}
+
+impl FreshnessMonitor {
+    /// Record the current classes; returns the change when the portfolio's class or that
+    /// of a position still held has moved. Opening or closing a position alone is not one.
+    pub fn observe(&mut self, freshness: &PortfolioFreshness, classes: HashMap<String, FreshnessClass>) -> Option<FreshnessChange> {
+        let mut transitions: Vec<FreshnessTransition> = classes
+            .iter()
+            .filter_map(|(symbol, &to)| {
+                let from = *self.classes.get(symbol)?;
+                (from != to).then(|| FreshnessTransition { symbol: symbol.clone(), from, to })
+            })
+            .collect();
+        transitions.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        let portfolio_moved = self.portfolio.is_some_and(|c| c != freshness.class);
+        self.classes = classes;
+        self.portfolio = Some(freshness.class);
+        (portfolio_moved || !transitions.is_empty()).then(|| FreshnessChange { freshness: freshness.clone(), transitions })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classes_by_session_and_transition_debounce() {
+        let config = FreshnessConfig::default();
+        let regular = config.thresholds(&MarketSession::Regular, true, 120);
+        assert_eq!(classify(2, regular), FreshnessClass::Live);
+        assert_eq!(classify(120, regular), FreshnessClass::Delayed);
+        assert_eq!(classify(121, regular), FreshnessClass::Stale);
+        assert_eq!(classify(10, config.thresholds(&MarketSession::AfterHours, true, 120)), FreshnessClass::Live);
+        // A holiday or disabled extended hours shut the market whatever the clock says
+        assert_eq!(classify(50_000, config.thresholds(&MarketSession::Regular, false, 120)), FreshnessClass::Closed);
+        assert!(FreshnessConfig { live_secs: 200, ..config }.validate(120).is_err());
+
+        let summary = PortfolioFreshness::summarize(
+            [("AAA", FreshnessClass::Live, 1), ("BBB", FreshnessClass::Stale, 300), ("CCC", FreshnessClass::Stale, 900)],
+            10,
+        );
+        assert_eq!((summary.class, summary.worst_symbol.as_deref(), summary.worst_age_secs), (FreshnessClass::Stale, Some("CCC"), Some(900)));
+        assert_eq!((summary.live, summary.delayed, summary.stale, summary.closed), (1, 0, 2, 0));
+
+        let mut monitor = FreshnessMonitor::default();
+        let classes = |pairs: &[(&str, FreshnessClass)]| pairs.iter().map(|(s, c)| (s.to_string(), *c)).collect::<HashMap<_, _>>();
+        let live = PortfolioFreshness { class: FreshnessClass::Live, ..PortfolioFreshness::default() };
+        assert_eq!(monitor.observe(&live, classes(&[("AAA", FreshnessClass::Live)])), None);
+        assert_eq!(monitor.observe(&live, classes(&[("AAA", FreshnessClass::Live), ("BBB", FreshnessClass::Live)])), None);
+        let change = monitor.observe(&live, classes(&[("AAA", FreshnessClass::Delayed), ("BBB", FreshnessClass::Live)])).unwrap();
+        assert_eq!(change.transitions, vec![FreshnessTransition { symbol: "AAA".to_string(), from: FreshnessClass::Live, to: FreshnessClass::Delayed }]);
+        assert_eq!(monitor.observe(&live, classes(&[("AAA", FreshnessClass::Delayed), ("BBB", FreshnessClass::Live)])), None);
+    }
+}
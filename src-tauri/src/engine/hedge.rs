@@ -0,0 +1,340 @@
+// src-tauri/src/engine/hedge.rs
+// Delta hedge suggestions: per-underlying share or put trades that bring net delta back inside a band
+
+use super::types::*;
+use super::mtm::{is_option_symbol, parse_option_symbol, MtMEngine, PortfolioGreeks};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+pub const BOARD_LOT: i64 = 100;
+
+/// Net Greeks of every position on one underlying, with options valued at the underlying's quote
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnderlyingGreeks {
+    pub underlying: String,
+    pub underlying_price: Option<f64>,
+    pub greeks: PortfolioGreeks,
+}
+
+/// Alternative to the share hedge: buy puts on the underlying
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionHedge {
+    pub symbol: String,
+    pub contracts: i64,
+    pub premium: f64,            // Per-share quote; a contract costs premium * multiplier
+    pub delta_per_contract: f64,
+    pub estimated_cost: f64,
+    pub greeks_after: PortfolioGreeks,
+    pub option_details: OptionDetails,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HedgeSuggestion {
+    pub underlying: String,
+    pub underlying_price: f64,
+    pub greeks_before: PortfolioGreeks,
+    pub within_band: bool,
+    pub side: Option<OrderSide>, // None when no share trade is needed
+    pub shares: i64,             // Board-lot rounded
+    pub estimated_cost: f64,     // Cash spent on the share hedge; negative when selling
+    pub greeks_after: PortfolioGreeks,
+    pub option_hedge: Option<OptionHedge>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HedgePlan {
+    pub id: String,
+    pub created_at: i64,
+    pub target_delta: f64, // Per underlying, in shares
+    pub band: f64,
+    pub greeks_before: PortfolioGreeks,
+    pub suggestions: Vec<HedgeSuggestion>,
+    pub message: String,
+}
+
+/// Group positions by underlying and sum their Greeks. Stocks contribute delta only.
+pub fn greeks_by_underlying(
+    engine: &MtMEngine,
+    positions: &HashMap<String, Position>,
+    market_data: &HashMap<String, MarketData>,
+) -> Vec<UnderlyingGreeks> {
+    let mut groups: BTreeMap<String, PortfolioGreeks> = BTreeMap::new();
+    let price_of = |underlying: &str| -> Option<f64> {
+        market_data
+            .get(underlying)
+            .map(|data| engine.get_mid_price(data))
+            .or_else(|| positions.get(underlying).map(|p| p.last_price))
+            .filter(|price| *price > 0.0)
+    };
+
+    for (symbol, position) in positions {
+        if position.quantity == 0 {
+            continue;
+        }
+
+        match parse_option_symbol(symbol).filter(|_| is_option_symbol(symbol)) {
+            Some(details) => {
+                let entry = groups.entry(details.underlying.clone()).or_default();
+                if let Some(price) = price_of(&details.underlying) {
+                    let greeks = engine.calculate_option_greeks(&details, price, position.quantity);
+                    add_greeks(entry, greeks.delta, greeks.gamma, greeks.theta, greeks.vega, greeks.rho);
+                }
+            }
+            None => {
+                let entry = groups.entry(symbol.clone()).or_default();
+                entry.delta += position.quantity as f64;
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(underlying, greeks)| UnderlyingGreeks {
+            underlying_price: price_of(&underlying),
+            underlying,
+            greeks,
+        })
+        .collect()
+}
+
+/// Share trades (and optional put alternatives) that move each non-linear underlying's
+/// delta to `target_delta`. Underlyings already within `band` of the target are reported
+/// without a trade.
+pub fn suggest_hedges(
+    engine: &MtMEngine,
+    positions: &HashMap<String, Position>,
+    market_data: &HashMap<String, MarketData>,
+    target_delta: f64,
+    band: f64,
+    put_delta: Option<f64>,
+) -> Vec<HedgeSuggestion> {
+    let mut suggestions = Vec::new();
+
+    for group in greeks_by_underlying(engine, positions, market_data) {
+        // Stock-only underlyings are linear; the position itself is the hedge
+        if group.greeks.gamma.abs() < 1e-9 {
+            continue;
+        }
+
+        let mut suggestion = HedgeSuggestion {
+            underlying: group.underlying.clone(),
+            underlying_price: group.underlying_price.unwrap_or(0.0),
+            greeks_before: group.greeks.clone(),
+            within_band: false,
+            side: None,
+            shares: 0,
+            estimated_cost: 0.0,
+            greeks_after: group.greeks.clone(),
+            option_hedge: None,
+            note: None,
+        };
+
+        let Some(price) = group.underlying_price else {
+            suggestion.note = Some(format!("No quote for {}; cannot size a hedge", group.underlying));
+            suggestions.push(suggestion);
+            continue;
+        };
+
+        let needed = target_delta - group.greeks.delta;
+        if needed.abs() <= band {
+            suggestion.within_band = true;
+            suggestions.push(suggestion);
+            continue;
+        }
+
+        let shares = round_to_lot(needed);
+        if shares == 0 {
+            suggestion.note = Some(format!("Adjustment of {:.1} delta is smaller than half a board lot", needed));
+        } else {
+            suggestion.side = Some(if shares > 0 { OrderSide::Buy } else { OrderSide::Sell });
+            suggestion.shares = shares.abs();
+            suggestion.estimated_cost = shares as f64 * price;
+            suggestion.greeks_after.delta += shares as f64;
+        }
+
+        // Puts only reduce delta
+        if let Some(put_delta) = put_delta.filter(|_| needed < 0.0) {
+            suggestion.option_hedge = put_hedge(engine, market_data, &group, price, needed, put_delta);
+            if suggestion.option_hedge.is_none() {
+                suggestion.note.get_or_insert_with(|| format!("No quoted {} puts to hedge with", group.underlying));
+            }
+        }
+
+        suggestions.push(suggestion);
+    }
+
+    suggestions
+}
+
+fn round_to_lot(shares: f64) -> i64 {
+    (shares / BOARD_LOT as f64).round() as i64 * BOARD_LOT
+}
+
+fn add_greeks(total: &mut PortfolioGreeks, delta: f64, gamma: f64, theta: f64, vega: f64, rho: f64) {
+    total.delta += delta;
+    total.gamma += gamma;
+    total.theta += theta;
+    total.vega += vega;
+    total.rho += rho;
+}
+
+/// Quoted put on the underlying whose delta is closest to `put_delta`, sized to cover `needed`
+fn put_hedge(
+    engine: &MtMEngine,
+    market_data: &HashMap<String, MarketData>,
+    group: &UnderlyingGreeks,
+    underlying_price: f64,
+    needed: f64,
+    put_delta: f64,
+) -> Option<OptionHedge> {
+    let (symbol, details, premium, greeks) = market_data
+        .iter()
+        .filter(|(symbol, _)| is_option_symbol(symbol))
+        .filter_map(|(symbol, data)| {
+            let details = parse_option_symbol(symbol)?;
+            if details.underlying != group.underlying || details.option_type != OptionType::Put {
+                return None;
+            }
+            let greeks = engine.calculate_option_greeks(&details, underlying_price, 1);
+            let premium = engine.get_mid_price(data);
+            (greeks.delta < 0.0 && premium > 0.0).then_some((symbol.clone(), details, premium, greeks))
+        })
+        .min_by(|a, b| {
+            let per_share = |g: &super::mtm::PositionGreeks, d: &OptionDetails| g.delta / d.multiplier as f64;
+            (per_share(&a.3, &a.1) - put_delta).abs()
+                .total_cmp(&(per_share(&b.3, &b.1) - put_delta).abs())
+                .then_with(|| a.0.cmp(&b.0))
+        })?;
+
+    let contracts = (needed / greeks.delta).round() as i64;
+    if contracts <= 0 {
+        return None;
+    }
+
+    let mut greeks_after = group.greeks.clone();
+    let scale = contracts as f64;
+    add_greeks(
+        &mut greeks_after,
+        greeks.delta * scale,
+        greeks.gamma * scale,
+        greeks.theta * scale,
+        greeks.vega * scale,
+        greeks.rho * scale,
+    );
+
+    Some(OptionHedge {
+        estimated_cost: contracts as f64 * premium * details.multiplier as f64,
+        delta_per_contract: greeks.delta,
+        symbol,
+        contracts,
+        premium,
+        greeks_after,
+        option_details: details,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(symbol: &str, quantity: i64, price: f64) -> Position {
+        let mut position = Position::new(symbol.to_string());
+        position.quantity = quantity;
+        position.avg_cost = price;
+        position.update_market_data(price);
+        position
+    }
+
+    fn quote(symbol: &str, price: f64) -> (String, MarketData) {
+        (symbol.to_string(), MarketData {
+            symbol: symbol.to_string(),
+            last_price: price,
+            bid: Some(price),
+            ask: Some(price),
+            bid_size: None,
+            ask_size: None,
+            volume: None,
+            timestamp: 0,
+        })
+    }
+
+    #[test]
+    fn test_short_puts_hedged_with_board_lots() {
+        let engine = MtMEngine::new();
+        // 10 short ATM puts are long delta (~ +400 shares)
+        let positions: HashMap<String, Position> = [position("SPY300621P00500000", -10, 40.0)]
+            .into_iter()
+            .map(|p| (p.symbol.clone(), p))
+            .collect();
+        let market_data: HashMap<String, MarketData> = [quote("SPY", 500.0), quote("SPY300621P00500000", 40.0)].into_iter().collect();
+
+        let groups = greeks_by_underlying(&engine, &positions, &market_data);
+        assert_eq!(groups.len(), 1);
+        let delta = groups[0].greeks.delta;
+        assert!(delta > 0.0);
+
+        let suggestions = suggest_hedges(&engine, &positions, &market_data, 0.0, 50.0, None);
+        let s = &suggestions[0];
+        assert_eq!(s.side, Some(OrderSide::Sell));
+        assert_eq!(s.shares % BOARD_LOT, 0);
+        assert_eq!(s.shares, round_to_lot(delta));
+        assert_eq!(s.estimated_cost, -(s.shares as f64) * 500.0);
+        assert!((s.greeks_after.delta - (delta - s.shares as f64)).abs() < 1e-9);
+        assert_eq!(s.greeks_after.gamma, s.greeks_before.gamma);
+
+        // A wide band leaves it alone
+        let relaxed = suggest_hedges(&engine, &positions, &market_data, 0.0, delta + 1.0, None);
+        assert!(relaxed[0].within_band);
+        assert!(relaxed[0].side.is_none());
+    }
+
+    #[test]
+    fn test_put_alternative_and_mixed_underlyings() {
+        let engine = MtMEngine::new();
+        let positions: HashMap<String, Position> = [
+            position("SPY300621P00500000", -10, 40.0),
+            position("QQQ300621C00400000", -5, 60.0), // short calls: short delta
+            position("AAPL", 100, 150.0),             // linear, never hedged
+        ]
+        .into_iter()
+        .map(|p| (p.symbol.clone(), p))
+        .collect();
+        let market_data: HashMap<String, MarketData> = [
+            quote("SPY", 500.0),
+            quote("QQQ", 400.0),
+            quote("SPY300621P00500000", 40.0),
+            quote("SPY300621P00400000", 15.0),
+        ]
+        .into_iter()
+        .collect();
+
+        let suggestions = suggest_hedges(&engine, &positions, &market_data, 0.0, 10.0, Some(-0.15));
+        let underlyings: Vec<&str> = suggestions.iter().map(|s| s.underlying.as_str()).collect();
+        assert_eq!(underlyings, vec!["QQQ", "SPY"]);
+
+        let qqq = &suggestions[0];
+        assert_eq!(qqq.side, Some(OrderSide::Buy));
+        assert!(qqq.option_hedge.is_none()); // Puts cannot add delta
+
+        let spy = &suggestions[1];
+        let put = spy.option_hedge.as_ref().unwrap();
+        assert_eq!(put.symbol, "SPY300621P00400000"); // The OTM put is nearer -0.15
+        assert!(put.contracts > 0);
+        assert_eq!(put.estimated_cost, put.contracts as f64 * 15.0 * 100.0);
+        assert!(put.greeks_after.delta.abs() < spy.greeks_before.delta.abs());
+        assert!(put.greeks_after.vega > spy.greeks_before.vega);
+    }
+
+    #[test]
+    fn test_stock_only_book_is_linear() {
+        let engine = MtMEngine::new();
+        let positions: HashMap<String, Position> = [position("AAPL", 300, 150.0)]
+            .into_iter()
+            .map(|p| (p.symbol.clone(), p))
+            .collect();
+        let market_data: HashMap<String, MarketData> = [quote("AAPL", 150.0)].into_iter().collect();
+
+        assert!(suggest_hedges(&engine, &positions, &market_data, 0.0, 10.0, Some(-0.3)).is_empty());
+    }
+}
@@ -3,15 +3,36 @@
 
 use super::types::*;
 use super::broker::PaperBroker;
-use crate::storage::cache::FileCache;
-use crate::providers::polygon::OhlcBar;
+use super::bars::{BarBuilder, Timeframe};
+use super::analytics;
+use super::indicators::{IndicatorCache, IndicatorKind, IndicatorSeries, IndicatorSpec, IndicatorValues};
+use super::news_halt::{NewsHalt, NewsHaltMonitor};
+use super::rejections::RejectionSource;
+use super::decision_outcomes::{filter_decisions, settle_decisions, DecisionOutcome, DecisionRecord, DecisionReport};
+use super::option_symbol::OptionSymbolParser;
+use super::position_history::session_date;
+use super::promotion::{self, PromotionAction, PromotionChecklist, PromotionCheck, PromotionConfig, PromotionFacts, PromotionRecord, PROMOTION_LOG_KEY};
+use super::shadow_ledger::ShadowLedger;
+use super::pairs::{align_closes, leg_quantities, spread_zscores, AlignedClose, PairAction, PairConfig, PairDirection};
+use super::scanner::{self, ScanFilter, ScanResult};
+use super::symbols::normalize_symbol;
+use super::watchlist::{SharedWatchlists, SymbolFlag, WatchlistService};
+use crate::storage::cache::{self, FileCache};
+use crate::storage::config_bundle::{self, ConfigChange};
+use crate::storage::decision_journal::DecisionJournal;
+use crate::market_data::types::Candle;
+use crate::provider::polygon as poly;
+use crate::providers::polygon::{OhlcBar, PolygonProvider, RealTimeTick};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
-use chrono::{DateTime, Utc, NaiveDateTime};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::America::New_York;
 use tokio::time::{sleep, Duration, Instant};
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Listener};
+use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StrategyLoopConfig {
@@ -21,6 +42,218 @@ pub struct StrategyLoopConfig {
     pub cooldown_seconds: u64,       // Minimum time between signals for same symbol
     pub log_level: LogLevel,
     pub dry_run: bool,               // Log decisions but don't place orders
+    #[serde(default = "default_retry_on_error")]
+    pub retry_on_error: bool,        // Re-queue failed bars for the next iteration
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,            // Attempts before a bar moves to the dead letter queue
+    #[serde(default = "default_signals")]
+    pub signals: Vec<SignalConfig>,
+    #[serde(default)]
+    pub symbol_rules: HashMap<String, CombinationRule>, // Per symbol; which symbols trade comes from the watchlist
+    #[serde(default)]
+    pub default_rule: CombinationRule,
+    #[serde(default = "default_multi_timeframe_signals")]
+    pub multi_timeframe_signals: HashMap<u32, Vec<String>>, // Minutes -> signals run on downsampled 1m bars
+    #[serde(default = "default_quarantine_after_errors")]
+    pub quarantine_after_errors: u32, // Consecutive errors before a symbol is quarantined
+    #[serde(default = "default_quarantine_minutes")]
+    pub quarantine_minutes: u64,      // Cool-off before a quarantined symbol is evaluated again
+    #[serde(default = "default_max_quarantines")]
+    pub max_quarantines: u32,         // Quarantines before the symbol needs resume_symbol
+    #[serde(default)]
+    pub regime_filter: MarketRegimeFilter,
+    #[serde(default = "default_expiry_alert_days")]
+    pub expiry_alert_days: i32,       // Alert on option positions expiring within this many days
+    #[serde(default)]
+    pub composite_signals: Vec<CompositeSignal>,
+    #[serde(default)]
+    pub watchdog: WatchdogConfig,
+    #[serde(default)]
+    pub pairs: Vec<PairConfig>, // Watchlist entries trading two legs together; their legs are not evaluated alone
+    #[serde(default)]
+    pub rejection_backoff: RejectionBackoff,
+    #[serde(default)]
+    pub allocation: Option<String>, // Sub-account the loop trades from; its positions and room size entries
+    #[serde(default)]
+    pub promotion: PromotionConfig, // Checklist thresholds for turning dry_run off
+}
+
+/// Stops the loop re-sending entries the risk checks keep turning away
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RejectionBackoff {
+    pub enabled: bool,
+    pub max_risk_rejections: u32, // Risk check rejections of a symbol's orders in a session before its entries are skipped
+}
+
+impl Default for RejectionBackoff {
+    fn default() -> Self {
+        Self { enabled: true, max_risk_rejections: 3 }
+    }
+}
+
+/// What the watchdog does when the loop's heartbeat goes quiet
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    pub grace_seconds: u64,           // Lateness allowed beyond one cadence before the loop counts as stalled
+    pub auto_restart: bool,
+    pub max_restarts: u32,            // Automatic restarts per manual start
+    pub restart_backoff_seconds: u64, // Minimum gap after the first restart; doubles after each one
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            grace_seconds: 60,
+            auto_restart: true,
+            max_restarts: 3,
+            restart_backoff_seconds: 30,
+        }
+    }
+}
+
+/// Daily-bar regime checks applied to signals before they are combined
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MarketRegimeFilter {
+    pub enabled: bool,
+    pub min_adr_pct: f64,      // Skip when the current daily range is below this percent of the low
+    pub max_adr_pct: f64,      // Skip when it is above this (news, halts, gaps)
+    pub min_volume_ratio: f64, // Current daily volume over the prior 20-day average
+    pub require_trend: bool,   // Longs only above the 200 EMA trend, shorts only below
+}
+
+impl Default for MarketRegimeFilter {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_adr_pct: 0.5,
+            max_adr_pct: 10.0,
+            min_volume_ratio: 0.5,
+            require_trend: false,
+        }
+    }
+}
+
+impl MarketRegimeFilter {
+    /// Daily bars needed, including the current one
+    pub fn lookback(&self) -> usize {
+        if self.require_trend {
+            REGIME_SLOW_EMA
+        } else {
+            REGIME_VOLUME_DAYS + 1
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FilteredSignal {
+    pub signal: String, // Label, or "*" when the whole bar was filtered
+    pub reason: String,
+}
+
+const REGIME_VOLUME_DAYS: usize = 20;
+const REGIME_FAST_EMA: usize = 50;
+const REGIME_SLOW_EMA: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalConfig {
+    pub name: String,
+    pub timeframe: Timeframe,
+    pub lookback: usize, // Bars of history the signal needs
+    #[serde(default = "default_signal_weight")]
+    pub weight: f64,
+}
+
+/// Weighted blend of evaluated signals, reported as one extra signal. Entries name a
+/// signal ("RSI") or a signal on a specific timeframe ("RSI@5m").
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompositeSignal {
+    pub signals: Vec<(String, f64)>, // Signal name or label -> weight
+    pub threshold: f64,              // Score needed for a directional call, 0..1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum CombinationRule {
+    AllMustAgree,
+    WeightedVote { threshold: f64 },
+}
+
+impl Default for CombinationRule {
+    fn default() -> Self {
+        CombinationRule::WeightedVote { threshold: 0.3 }
+    }
+}
+
+fn default_signal_weight() -> f64 {
+    1.0
+}
+
+fn default_signals() -> Vec<SignalConfig> {
+    vec![
+        SignalConfig { name: "SMA_Crossover".to_string(), timeframe: Timeframe::FiveMinute, lookback: 20, weight: 1.0 },
+        SignalConfig { name: "Volume_Spike".to_string(), timeframe: Timeframe::FiveMinute, lookback: 20, weight: 0.5 },
+        SignalConfig { name: "Trend".to_string(), timeframe: Timeframe::OneDay, lookback: 20, weight: 1.5 },
+    ]
+}
+
+fn default_multi_timeframe_signals() -> HashMap<u32, Vec<String>> {
+    HashMap::from([
+        (5, vec!["RSI".to_string()]),
+        (60, vec!["MACD".to_string()]),
+    ])
+}
+
+/// Bars of history a signal needs when it is not configured explicitly
+fn default_lookback(signal_name: &str) -> usize {
+    match signal_name {
+        "MACD" => 35, // 26-period slow EMA plus 9-period signal line
+        "RSI" => 15,
+        _ => 20,
+    }
+}
+
+/// Indicator cache series for a timeframe's bars, or for 1-minute bars downsampled to it
+pub(crate) fn indicator_series_key(timeframe: Timeframe, downsampled: bool) -> String {
+    match downsampled {
+        true => format!("{} from 1m", timeframe.label()),
+        false => timeframe.label().to_string(),
+    }
+}
+
+fn market_close() -> NaiveTime {
+    NaiveTime::from_hms_opt(16, 0, 0).unwrap()
+}
+
+// Enough 1-minute bars for the hourly MACD lookback (~6 sessions)
+const MAX_BARS_PER_TIMEFRAME: usize = 2400;
+const SHADOW_LEDGER_KEY: &str = "shadow_ledger";
+
+fn default_quarantine_after_errors() -> u32 {
+    5
+}
+
+fn default_quarantine_minutes() -> u64 {
+    30
+}
+
+fn default_max_quarantines() -> u32 {
+    3
+}
+
+fn default_expiry_alert_days() -> i32 {
+    3
+}
+
+fn default_retry_on_error() -> bool {
+    true
+}
+
+fn default_max_retries() -> u32 {
+    3
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -41,6 +274,8 @@ pub struct BarCloseEvent {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignalEvaluation {
+    #[serde(default)]
+    pub id: String, // Carried by the trades of the orders it placed
     pub symbol: String,
     pub timestamp: i64,
     pub bar_timestamp: i64,
@@ -55,6 +290,69 @@ pub struct SignalResult {
     pub direction: SignalDirection,
     pub confidence: f64,
     pub metadata: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub timeframe: Timeframe,
+    #[serde(default = "default_signal_weight")]
+    pub weight: f64,
+}
+
+impl SignalResult {
+    /// Journal label, e.g. "RSI@5m"
+    pub fn label(&self) -> String {
+        format!("{}@{}", self.name, self.timeframe.label())
+    }
+}
+
+/// Blend evaluated signals into one result. The score is the weighted sum of
+/// direction * confidence over the total weight, so a missing component counts as
+/// neutral; confidence is the score relative to the most the present components
+/// could have scored.
+pub fn compute_composite_signal(signal_results: &[SignalResult], composite: &CompositeSignal) -> SignalResult {
+    let mut total_weight = 0.0;
+    let mut present_weight = 0.0;
+    let mut weighted = 0.0;
+    let mut timeframe: Option<Timeframe> = None;
+    let mut components = serde_json::Map::new();
+
+    for (name, weight) in &composite.signals {
+        let weight = weight.abs();
+        total_weight += weight;
+        let Some(result) = signal_results.iter().find(|s| s.label() == *name || s.name == *name) else {
+            continue;
+        };
+        let sign = match result.direction {
+            SignalDirection::Long => 1.0,
+            SignalDirection::Short => -1.0,
+            SignalDirection::Neutral => 0.0,
+        };
+        present_weight += weight;
+        weighted += weight * sign * result.confidence;
+        timeframe = Some(timeframe.map_or(result.timeframe, |t| t.min(result.timeframe)));
+        components.insert(result.label(), serde_json::json!(weight * sign * result.confidence));
+    }
+
+    let score = if total_weight > 0.0 { weighted / total_weight } else { 0.0 };
+    let max_score = if total_weight > 0.0 { present_weight / total_weight } else { 0.0 };
+    let direction = if score > composite.threshold {
+        SignalDirection::Long
+    } else if score < -composite.threshold {
+        SignalDirection::Short
+    } else {
+        SignalDirection::Neutral
+    };
+
+    let names: Vec<&str> = composite.signals.iter().map(|(name, _)| name.as_str()).collect();
+    SignalResult {
+        name: format!("Composite({})", names.join("+")),
+        direction,
+        confidence: if max_score > 0.0 { score.abs() / max_score } else { 0.0 },
+        metadata: HashMap::from([
+            ("score".to_string(), serde_json::json!(score)),
+            ("components".to_string(), serde_json::Value::Object(components)),
+        ]),
+        timeframe: timeframe.unwrap_or_default(),
+        weight: default_signal_weight(),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -70,6 +368,8 @@ pub struct StrategyDecision {
     pub reason: String,
     pub orders: Vec<OrderRequest>,
     pub risk_assessment: RiskAssessment,
+    #[serde(default)]
+    pub contributing_signals: Vec<String>, // Labels of the signals that agreed with the action
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -100,6 +400,186 @@ pub struct LoopState {
     pub execution_count: u64,
     pub error_count: u64,
     pub last_error: Option<String>,
+    #[serde(default)]
+    pub retry_queue: VecDeque<(String, String, u32)>, // (symbol, bar_key, attempt_count)
+    #[serde(default)]
+    pub dead_letter_queue: Vec<DeadLetterEntry>,
+    #[serde(default)]
+    pub first_failed_at: HashMap<String, i64>, // bar_key -> time of first failure
+    #[serde(default)]
+    pub symbol_health: HashMap<String, SymbolHealth>,
+    #[serde(default)]
+    pub last_expiry_check_date: Option<NaiveDate>, // Session of the last end-of-day expiration run
+    #[serde(default)]
+    pub last_expiry_alert_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub last_heartbeat: i64, // Start of the most recent cycle
+    #[serde(default = "default_healthy")]
+    pub healthy: bool,       // False while the watchdog sees a stalled loop
+    #[serde(default)]
+    pub restart_count: u32,
+    #[serde(default)]
+    pub restarts: Vec<LoopRestart>,
+    #[serde(default)]
+    pub shadow: ShadowLedger, // What dry run would have traded
+}
+
+fn default_healthy() -> bool {
+    true
+}
+
+/// An automatic restart by the watchdog
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LoopRestart {
+    pub timestamp: i64,
+    pub attempt: u32, // 1-based, counted since the last manual start
+    pub reason: String,
+    pub error: Option<String>, // Set when the restart itself failed
+}
+
+/// Outcome of one watchdog check
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LivenessCheck {
+    Idle, // Loop not running or watchdog disabled
+    Healthy,
+    Recovered, // Heartbeat resumed after a stall
+    Stalled { silent_seconds: i64, newly_stalled: bool, restart: bool },
+}
+
+/// Heartbeat bookkeeping for the strategy loop. The loop task stores the time at the
+/// top of each cycle; the watchdog compares it against the cadence plus grace.
+#[derive(Debug, Default)]
+pub struct LoopWatchdog {
+    heartbeat: Arc<AtomicI64>,
+    stalled: bool,
+    restarts: u32, // Since the last manual start
+    last_restart_at: Option<i64>,
+}
+
+impl LoopWatchdog {
+    pub fn heartbeat(&self) -> Arc<AtomicI64> {
+        self.heartbeat.clone()
+    }
+
+    pub fn last_heartbeat(&self) -> i64 {
+        self.heartbeat.load(Ordering::Relaxed)
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+
+    /// Manual start or stop: count the loop as alive from `now` and refill the restart budget
+    pub fn reset(&mut self, now: i64) {
+        self.heartbeat.store(now, Ordering::Relaxed);
+        self.stalled = false;
+        self.restarts = 0;
+        self.last_restart_at = None;
+    }
+
+    /// `task_finished` is true when the loop task ended on its own (a panic); that is a
+    /// stall regardless of the last heartbeat
+    pub fn check(&mut self, policy: &WatchdogConfig, cadence_minutes: u64, task_finished: bool, now: i64) -> LivenessCheck {
+        let silent_seconds = now - self.last_heartbeat();
+        let deadline = (cadence_minutes * 60 + policy.grace_seconds) as i64;
+        if !task_finished && silent_seconds <= deadline {
+            let recovered = std::mem::replace(&mut self.stalled, false);
+            return if recovered { LivenessCheck::Recovered } else { LivenessCheck::Healthy };
+        }
+
+        let newly_stalled = !std::mem::replace(&mut self.stalled, true);
+        let backoff_over = self.last_restart_at.is_none_or(|at| {
+            let backoff = policy.restart_backoff_seconds.saturating_mul(1u64 << (self.restarts - 1).min(16));
+            now >= at + backoff as i64
+        });
+        let restart = policy.auto_restart && self.restarts < policy.max_restarts && backoff_over;
+        LivenessCheck::Stalled { silent_seconds, newly_stalled, restart }
+    }
+
+    pub fn record_restart(&mut self, now: i64) {
+        self.restarts += 1;
+        self.last_restart_at = Some(now);
+        self.heartbeat.store(now, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DeadLetterEntry {
+    pub symbol: String,
+    pub bar_key: String,
+    pub error: String,
+    pub attempts: u32,
+    pub first_failed_at: i64,
+    #[serde(default)]
+    pub error_class: ErrorClass,
+}
+
+/// Where a bar failure came from, so a provider outage can be told apart from a strategy bug
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ErrorClass {
+    Data,           // Missing or bad market data
+    OrderPlacement, // Broker rejected an order
+    #[default]
+    Internal,       // Signal evaluation, sizing or other loop logic
+}
+
+impl ErrorClass {
+    /// Journal category for errors of this class
+    pub fn category(&self) -> &'static str {
+        match self {
+            ErrorClass::Data => "data_error",
+            ErrorClass::OrderPlacement => "order_error",
+            ErrorClass::Internal => "internal_error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BarError {
+    pub class: ErrorClass,
+    pub message: String,
+}
+
+impl BarError {
+    pub fn new(class: ErrorClass, message: impl Into<String>) -> Self {
+        Self { class, message: message.into() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum SymbolStatus {
+    Healthy,
+    Erroring,    // Some consecutive errors, below the quarantine threshold
+    Quarantined, // Skipped until quarantined_until
+    Probation,   // Cool-off elapsed; the next success lifts the quarantine
+    Suspended,   // Too many quarantines; needs resume_symbol
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SymbolHealth {
+    pub consecutive_errors: u32,
+    pub last_error: Option<String>,
+    pub last_error_class: Option<ErrorClass>,
+    pub last_error_at: Option<i64>,
+    pub quarantined_until: Option<i64>,
+    pub quarantine_count: u32,
+    pub suspended: bool,
+}
+
+impl SymbolHealth {
+    pub fn status(&self, now: i64) -> SymbolStatus {
+        match self.quarantined_until {
+            _ if self.suspended => SymbolStatus::Suspended,
+            Some(until) if now < until => SymbolStatus::Quarantined,
+            Some(_) => SymbolStatus::Probation,
+            None if self.consecutive_errors > 0 => SymbolStatus::Erroring,
+            None => SymbolStatus::Healthy,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -117,9 +597,14 @@ pub struct StrategyLoop {
     config: StrategyLoopConfig,
     state: Arc<Mutex<LoopState>>,
     broker: Arc<Mutex<PaperBroker>>,
+    bar_builder: Arc<Mutex<BarBuilder>>,
     app_handle: AppHandle,
     storage: Option<FileCache>,
     loop_handle: Option<tokio::task::JoinHandle<()>>,
+    watchdog: LoopWatchdog,
+    news_halts: NewsHaltMonitor,
+    watchlists: WatchlistService,
+    pending_promotion: Option<PromotionChecklist>, // Last checklist from request_live_promotion
 }
 
 impl Default for StrategyLoopConfig {
@@ -131,14 +616,240 @@ impl Default for StrategyLoopConfig {
             cooldown_seconds: 300, // 5 minutes
             log_level: LogLevel::Info,
             dry_run: true,
+            retry_on_error: default_retry_on_error(),
+            max_retries: default_max_retries(),
+            signals: default_signals(),
+            symbol_rules: HashMap::new(),
+            default_rule: CombinationRule::default(),
+            multi_timeframe_signals: default_multi_timeframe_signals(),
+            quarantine_after_errors: default_quarantine_after_errors(),
+            quarantine_minutes: default_quarantine_minutes(),
+            max_quarantines: default_max_quarantines(),
+            regime_filter: MarketRegimeFilter::default(),
+            expiry_alert_days: default_expiry_alert_days(),
+            composite_signals: Vec::new(),
+            watchdog: WatchdogConfig::default(),
+            pairs: Vec::new(),
+            rejection_backoff: RejectionBackoff::default(),
+            allocation: None,
+            promotion: PromotionConfig::default(),
+        }
+    }
+}
+
+impl StrategyLoopConfig {
+    pub fn required_timeframes(&self) -> Vec<Timeframe> {
+        let mut timeframes: Vec<Timeframe> = self.signals.iter().map(|s| s.timeframe).collect();
+        if self.regime_filter.enabled || !self.pairs.is_empty() {
+            timeframes.push(Timeframe::OneDay);
+        }
+        timeframes.sort();
+        timeframes.dedup();
+        timeframes
+    }
+
+    /// Timeframes the bar builder tracks: signal timeframes plus the 1-minute
+    /// series that multi-timeframe signals are downsampled from
+    pub fn builder_timeframes(&self) -> Vec<Timeframe> {
+        let mut timeframes = self.required_timeframes();
+        if !self.multi_timeframe_signals.is_empty() {
+            timeframes.insert(0, Timeframe::OneMinute);
+            timeframes.dedup();
+        }
+        timeframes
+    }
+
+    /// Key the watchlist by canonical symbol; two spellings of one symbol are an error
+    pub fn normalize_watchlist(&mut self) -> Result<(), String> {
+        let mut rules = HashMap::new();
+        for (symbol, rule) in self.symbol_rules.drain() {
+            let canonical = normalize_symbol(&symbol)?;
+            if rules.insert(canonical.clone(), rule).is_some() {
+                return Err(format!("Watchlist lists {} more than once", canonical));
+            }
+        }
+        self.symbol_rules = rules;
+
+        let mut labels = HashSet::new();
+        for pair in self.pairs.iter_mut() {
+            *pair = pair.normalized()?;
+            if !labels.insert(pair.label()) {
+                return Err(format!("Watchlist lists the {} pair more than once", pair.label()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `symbol` is a leg of a configured pair
+    pub fn is_pair_leg(&self, symbol: &str) -> bool {
+        self.pairs.iter().any(|p| p.first == symbol || p.second == symbol)
+    }
+
+    pub fn rule_for(&self, symbol: &str) -> &CombinationRule {
+        self.symbol_rules.get(symbol).unwrap_or(&self.default_rule)
+    }
+
+    /// Calendar days of history needed to cover the longest lookback on a timeframe
+    pub fn history_days(&self, timeframe: Timeframe) -> i64 {
+        let lookback = self.signals
+            .iter()
+            .filter(|s| s.timeframe == timeframe)
+            .map(|s| s.lookback)
+            .chain((self.regime_filter.enabled && timeframe == Timeframe::OneDay).then(|| self.regime_filter.lookback()))
+            .chain(self.pairs.iter().filter(|_| timeframe == Timeframe::OneDay).map(|p| p.lookback))
+            .max()
+            .unwrap_or(0);
+        let sessions = lookback.div_ceil(timeframe.bars_per_session()) as i64;
+
+        // Weekends plus a small buffer for holidays
+        sessions * 7 / 5 + 3
+    }
+
+    /// History window for warm-up, driven by the largest timeframe's lookback
+    pub fn warmup_days(&self) -> i64 {
+        self.required_timeframes()
+            .into_iter()
+            .map(|tf| self.history_days(tf))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl LoopState {
+    /// Expirations run once per session date, after the close
+    pub fn expiry_check_due(&self, session: NaiveDate, time: NaiveTime, market_open: bool) -> bool {
+        !market_open && time >= market_close() && self.last_expiry_check_date != Some(session)
+    }
+
+    /// Record a failed bar. Returns the dead letter entry when retries are exhausted.
+    pub fn record_failure(
+        &mut self,
+        symbol: &str,
+        bar_key: &str,
+        error: &BarError,
+        attempts: u32,
+        config: &StrategyLoopConfig,
+        now: i64,
+    ) -> Option<DeadLetterEntry> {
+        let (error_class, error) = (error.class, error.message.as_str());
+        self.error_count += 1;
+        self.last_error = Some(error.to_string());
+
+        if !config.retry_on_error {
+            return None;
+        }
+
+        let first_failed_at = *self.first_failed_at.entry(bar_key.to_string()).or_insert(now);
+
+        if attempts < config.max_retries {
+            self.retry_queue.push_back((symbol.to_string(), bar_key.to_string(), attempts));
+            return None;
+        }
+
+        self.first_failed_at.remove(bar_key);
+        let entry = DeadLetterEntry {
+            symbol: symbol.to_string(),
+            bar_key: bar_key.to_string(),
+            error: error.to_string(),
+            attempts,
+            first_failed_at,
+            error_class,
+        };
+        self.dead_letter_queue.push(entry.clone());
+        Some(entry)
+    }
+
+    /// Clear failure tracking for a bar that succeeded on retry
+    pub fn record_retry_success(&mut self, bar_key: &str) {
+        self.first_failed_at.remove(bar_key);
+    }
+
+    /// Whether the loop should skip `symbol` this cycle
+    pub fn is_symbol_blocked(&self, symbol: &str, now: i64) -> bool {
+        self.symbol_health
+            .get(symbol)
+            .map(|h| matches!(h.status(now), SymbolStatus::Quarantined | SymbolStatus::Suspended))
+            .unwrap_or(false)
+    }
+
+    /// Count an error against `symbol` and escalate. Returns the new status when the
+    /// symbol was just quarantined or suspended.
+    pub fn record_symbol_error(
+        &mut self,
+        symbol: &str,
+        error: &BarError,
+        config: &StrategyLoopConfig,
+        now: i64,
+    ) -> Option<SymbolStatus> {
+        let health = self.symbol_health.entry(symbol.to_string()).or_default();
+        health.consecutive_errors += 1;
+        health.last_error = Some(error.message.clone());
+        health.last_error_class = Some(error.class);
+        health.last_error_at = Some(now);
+
+        // Below the threshold, or already sitting out a quarantine
+        let on_probation = health.status(now) == SymbolStatus::Probation;
+        if config.quarantine_after_errors == 0
+            || (!on_probation && health.consecutive_errors < config.quarantine_after_errors)
+            || health.status(now) == SymbolStatus::Quarantined
+            || health.suspended
+        {
+            return None;
+        }
+
+        health.quarantine_count += 1;
+        if health.quarantine_count >= config.max_quarantines {
+            health.suspended = true;
+            Some(SymbolStatus::Suspended)
+        } else {
+            health.quarantined_until = Some(now + config.quarantine_minutes as i64 * 60);
+            Some(SymbolStatus::Quarantined)
+        }
+    }
+
+    /// Reset the error streak after a successful evaluation. Returns true when this
+    /// lifted a quarantine.
+    pub fn record_symbol_success(&mut self, symbol: &str) -> bool {
+        let Some(health) = self.symbol_health.get_mut(symbol) else {
+            return false;
+        };
+        let lifted = health.quarantined_until.take().is_some();
+        health.consecutive_errors = 0;
+        if health.quarantine_count == 0 {
+            self.symbol_health.remove(symbol);
         }
+        lifted
+    }
+
+    /// Clear all error tracking for a symbol, including a suspension
+    pub fn resume_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        self.symbol_health
+            .remove(symbol)
+            .map(|_| ())
+            .ok_or_else(|| format!("{} has no recorded errors", symbol))
+    }
+
+    pub fn take_retries(&mut self) -> Vec<(String, String, u32)> {
+        self.retry_queue.drain(..).collect()
+    }
+
+    pub fn clear_dead_letter_queue(&mut self) -> u32 {
+        let cleared = self.dead_letter_queue.len() as u32;
+        self.dead_letter_queue.clear();
+        cleared
     }
 }
 
 impl StrategyLoop {
     pub fn new(broker: Arc<Mutex<PaperBroker>>, app_handle: AppHandle) -> Self {
+        let config = StrategyLoopConfig::default();
+        let bar_builder = BarBuilder::new(config.builder_timeframes(), MAX_BARS_PER_TIMEFRAME);
+        let news_halts = NewsHaltMonitor::new(app_handle.clone());
+        let watchlists = WatchlistService::new(app_handle.clone());
+        let shadow = FileCache::new(&app_handle).and_then(|mut cache| cache.get(SHADOW_LEDGER_KEY)).ok().flatten().unwrap_or_default();
+
         Self {
-            config: StrategyLoopConfig::default(),
+            config,
             state: Arc::new(Mutex::new(LoopState {
                 running: false,
                 last_execution: 0,
@@ -147,28 +858,189 @@ impl StrategyLoop {
                 execution_count: 0,
                 error_count: 0,
                 last_error: None,
+                retry_queue: VecDeque::new(),
+                dead_letter_queue: Vec::new(),
+                first_failed_at: HashMap::new(),
+                symbol_health: HashMap::new(),
+                last_expiry_check_date: None,
+                last_expiry_alert_date: None,
+                last_heartbeat: 0,
+                healthy: true,
+                restart_count: 0,
+                restarts: Vec::new(),
+                shadow,
             })),
             broker,
+            bar_builder: Arc::new(Mutex::new(bar_builder)),
             app_handle,
             storage: None,
             loop_handle: None,
+            watchdog: LoopWatchdog::default(),
+            news_halts,
+            watchlists,
+            pending_promotion: None,
         }
     }
 
     pub fn with_config(mut self, config: StrategyLoopConfig) -> Self {
+        self.bar_builder = Arc::new(Mutex::new(BarBuilder::new(config.builder_timeframes(), MAX_BARS_PER_TIMEFRAME)));
         self.config = config;
         self
     }
 
+    /// Feed trades from the Polygon stream (`tick` events) into the bar builder and
+    /// the broker's limit order queue model
+    pub fn attach_tick_stream(&self) {
+        let bar_builder = self.bar_builder.clone();
+        let broker = self.broker.clone();
+        self.app_handle.listen_any("tick", move |event| {
+            let Ok(tick) = serde_json::from_str::<RealTimeTick>(event.payload()) else {
+                return;
+            };
+            let bar_builder = bar_builder.clone();
+            let broker = broker.clone();
+            tauri::async_runtime::spawn(async move {
+                bar_builder.lock().await.on_tick(&tick.symbol, tick.timestamp, tick.price, tick.size);
+                broker.lock().await.on_trade_print(&tick.symbol, tick.price, tick.size);
+            });
+        });
+    }
+
+    /// Seed the bar builder with enough history for every configured signal's lookback.
+    /// Bars come from the cache when present, otherwise from Polygon. Returns bars loaded.
+    pub async fn warm_up(&mut self, symbols: &[String]) -> Result<usize, String> {
+        if self.storage.is_none() {
+            self.storage = FileCache::new(&self.app_handle).ok();
+        }
+
+        let provider = PolygonProvider::new(self.app_handle.clone());
+        let end = Utc::now().date_naive();
+        let mut loaded = 0;
+        let mut errors = Vec::new();
+
+        for timeframe in self.config.required_timeframes() {
+            let start = end - chrono::Duration::days(self.config.history_days(timeframe));
+
+            for symbol in symbols {
+                let bars = match self.cached_bars(&provider, symbol, start, end, timeframe).await {
+                    Ok(bars) => bars,
+                    Err(e) => {
+                        errors.push(format!("{} {}: {}", symbol, timeframe.label(), e));
+                        continue;
+                    }
+                };
+
+                loaded += bars.len();
+                self.bar_builder.lock().await.seed(symbol, timeframe, &bars);
+            }
+        }
+
+        if !errors.is_empty() {
+            self.log(
+                LogLevel::Warning,
+                "warmup",
+                &format!("Warm-up incomplete: {}", errors.join("; ")),
+                None,
+                None,
+                None,
+            ).await;
+        }
+
+        Ok(loaded)
+    }
+
+    /// Bars for one symbol and timeframe from the cache, fetching from Polygon on a miss
+    async fn cached_bars(
+        &mut self,
+        provider: &PolygonProvider,
+        symbol: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+        timeframe: Timeframe,
+    ) -> Result<Vec<Candle>, String> {
+        let key = cache::cache_key_for_ohlc(symbol, &start.to_string(), &end.to_string(), timeframe.polygon_timeframe());
+        let cached: Option<Vec<Candle>> = match self.storage.as_mut() {
+            Some(storage) => storage.get(&key).unwrap_or(None),
+            None => None,
+        };
+        if let Some(bars) = cached {
+            return Ok(bars);
+        }
+
+        let bars = provider.fetch_ohlc(symbol, start, end, timeframe.polygon_timeframe()).await?;
+        if let Some(storage) = self.storage.as_mut() {
+            let _ = storage.set(&key, bars.clone(), Some(3600));
+        }
+        Ok(bars)
+    }
+
+    /// News halt rules and halts; shared with the news and halt commands
+    pub fn news_halts(&self) -> NewsHaltMonitor {
+        self.news_halts.clone()
+    }
+
+    /// The managed watchlist; shared with the watchlist, stream and maintenance commands
+    pub fn watchlists(&self) -> WatchlistService {
+        self.watchlists.clone()
+    }
+
+    /// Run a scan over daily bars for `symbols`, or scan-flagged watchlist symbols when None. Symbols whose
+    /// bars cannot be loaded are reported with the insufficient-history ones.
+    pub async fn run_scan(&mut self, filter: &ScanFilter, symbols: Option<Vec<String>>) -> Result<ScanResult, String> {
+        filter.validate()?;
+        if self.storage.is_none() {
+            self.storage = FileCache::new(&self.app_handle).ok();
+        }
+
+        let symbols = symbols.unwrap_or_else(|| self.watchlists.symbols_with(SymbolFlag::Scan));
+        if symbols.is_empty() {
+            return Err("No symbols to scan; add symbols to the watchlist or pass a list".to_string());
+        }
+
+        // Calendar days covering the required trading days, with slack for holidays
+        let days = (filter.required_bars() as i64 * 7 / 5) + 10;
+        let provider = PolygonProvider::new(self.app_handle.clone());
+        let end = Utc::now().date_naive();
+        let start = end - chrono::Duration::days(days);
+
+        let mut bars_by_symbol = BTreeMap::new();
+        for symbol in symbols {
+            let symbol = normalize_symbol(&symbol)?;
+            let bars = self.cached_bars(&provider, &symbol, start, end, Timeframe::OneDay).await.unwrap_or_default();
+            bars_by_symbol.insert(symbol, bars);
+        }
+        scanner::run_scan(filter, &bars_by_symbol)
+    }
+
     pub async fn start(&mut self) -> Result<(), String> {
         if self.loop_handle.is_some() {
             return Err("Strategy loop already running".to_string());
         }
+        self.watchdog.reset(Utc::now().timestamp());
+        self.launch().await
+    }
 
+    /// Warm up and spawn the loop task; shared by manual starts and watchdog restarts
+    async fn launch(&mut self) -> Result<(), String> {
         if !self.config.enabled {
             return Err("Strategy loop is disabled in config".to_string());
         }
 
+        // Warm up bar history for trade-flagged watchlist symbols and pair legs
+        let mut symbols = self.watchlists.symbols_with(SymbolFlag::Trade);
+        symbols.extend(self.config.pairs.iter().flat_map(|p| [p.first.clone(), p.second.clone()]));
+        symbols.sort();
+        symbols.dedup();
+        let warmed = self.warm_up(&symbols).await?;
+        self.log(
+            LogLevel::Info,
+            "warmup",
+            &format!("Loaded {} historical bars for {} symbols ({} days max lookback)", warmed, symbols.len(), self.config.warmup_days()),
+            None,
+            None,
+            None,
+        ).await;
+
         // Update state
         {
             let mut state = self.state.lock().await;
@@ -179,10 +1051,15 @@ impl StrategyLoop {
         let config = self.config.clone();
         let state = self.state.clone();
         let broker = self.broker.clone();
+        let bar_builder = self.bar_builder.clone();
         let app_handle = self.app_handle.clone();
+        let news_halts = self.news_halts.clone();
+        let watchlists = self.watchlists.shared();
+        let heartbeat = self.watchdog.heartbeat();
+        heartbeat.store(Utc::now().timestamp(), Ordering::Relaxed);
 
         let handle = tokio::spawn(async move {
-            Self::run_strategy_loop(config, state, broker, app_handle).await;
+            Self::run_strategy_loop(config, state, broker, bar_builder, app_handle, news_halts, watchlists, heartbeat).await;
         });
 
         self.loop_handle = Some(handle);
@@ -194,7 +1071,8 @@ impl StrategyLoop {
     pub async fn stop(&mut self) -> Result<(), String> {
         if let Some(handle) = self.loop_handle.take() {
             handle.abort();
-            
+            self.watchdog.reset(Utc::now().timestamp());
+
             // Update state
             {
                 let mut state = self.state.lock().await;
@@ -207,11 +1085,27 @@ impl StrategyLoop {
         Ok(())
     }
 
+    /// Mark the dry-run ledger with the latest quotes and take today's equity point
+    async fn sample_shadow(state: &Arc<Mutex<LoopState>>, broker: &PaperBroker, app_handle: &AppHandle, current_time: i64) {
+        let mut loop_state = state.lock().await;
+        for (symbol, data) in &broker.market_data {
+            loop_state.shadow.mark(symbol, data.last_price);
+        }
+        loop_state.shadow.sample(session_date(current_time), broker.get_portfolio().equity);
+        if let Err(e) = FileCache::new(app_handle).and_then(|mut cache| cache.set(SHADOW_LEDGER_KEY, &loop_state.shadow, None)) {
+            eprintln!("Failed to save the shadow ledger: {}", e);
+        }
+    }
+
     async fn run_strategy_loop(
         config: StrategyLoopConfig,
         state: Arc<Mutex<LoopState>>,
         broker: Arc<Mutex<PaperBroker>>,
+        bar_builder: Arc<Mutex<BarBuilder>>,
         app_handle: AppHandle,
+        news_halts: NewsHaltMonitor,
+        watchlists: SharedWatchlists,
+        heartbeat: Arc<AtomicI64>,
     ) {
         let mut interval = tokio::time::interval(Duration::from_secs(config.cadence_minutes * 60));
         interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
@@ -221,6 +1115,7 @@ impl StrategyLoop {
 
             let execution_start = Instant::now();
             let current_time = Utc::now().timestamp();
+            heartbeat.store(current_time, Ordering::Relaxed);
 
             // Update execution count
             {
@@ -229,30 +1124,163 @@ impl StrategyLoop {
                 loop_state.last_execution = current_time;
             }
 
-            // Get current market data and positions
+            // Get current market data and positions, generating synthetic quotes when offline,
+            // booking fills whose simulated latency has elapsed and re-aging the marks
             let (market_data, positions) = {
-                let broker_guard = broker.lock().await;
-                (broker_guard.market_data.clone(), broker_guard.positions.clone())
+                let mut broker_guard = broker.lock().await;
+                if broker_guard.needs_simulated_data() {
+                    if let Err(e) = broker_guard.tick_simulation() {
+                        eprintln!("Market data simulation failed: {}", e);
+                    }
+                }
+                broker_guard.process_matured_fills();
+                broker_guard.expire_orders();
+                broker_guard.check_freshness();
+                broker_guard.check_badges();
+                if config.dry_run {
+                    Self::sample_shadow(&state, &broker_guard, &app_handle, current_time).await;
+                }
+                let positions = match &config.allocation {
+                    Some(name) => broker_guard.allocation_positions(name).unwrap_or_else(|e| {
+                        eprintln!("Strategy loop allocation: {}", e);
+                        HashMap::new()
+                    }),
+                    None => broker_guard.positions.clone(),
+                };
+                (broker_guard.market_data.clone(), positions)
             };
 
-            // Process each symbol with market data
-            for (symbol, data) in market_data.iter() {
-                if let Err(e) = Self::process_symbol_bar(
-                    &symbol,
-                    data,
-                    &positions,
+            // Settle expired options after the close and warn about upcoming expirations
+            Self::check_option_expirations(&config, &state, &broker, &app_handle).await;
+
+            // Lift lapsed news halts and check held and watchlist symbols' news when due
+            Self::check_news_halts(&config, &news_halts, &watchlists, &positions, current_time).await;
+
+            // Write outcomes for decisions whose positions have closed
+            Self::settle_decision_outcomes(&broker, &app_handle).await;
+
+            // Feed the latest quotes into every timeframe's forming bar
+            {
+                let mut builder = bar_builder.lock().await;
+                for (symbol, data) in market_data.iter() {
+                    builder.on_tick(symbol, data.timestamp_secs(), data.last_price, 0);
+                }
+            }
+
+            // Retry previously failed bars before new symbols
+            let retries = state.lock().await.take_retries();
+            let mut retried_bars = HashSet::new();
+            for (symbol, bar_key, attempts) in retries {
+                // Quarantined symbols keep their retries until they are evaluated again
+                {
+                    let mut loop_state = state.lock().await;
+                    if loop_state.is_symbol_blocked(&symbol, current_time) {
+                        loop_state.retry_queue.push_back((symbol, bar_key.clone(), attempts));
+                        retried_bars.insert(bar_key);
+                        continue;
+                    }
+                }
+
+                let pair = config.pairs.iter().find(|p| p.label() == symbol);
+                if pair.is_none() && !Self::is_traded(&symbol, &config, &watchlists) {
+                    continue;
+                }
+                let result = match (pair, market_data.get(&symbol), Self::parse_bar_key(&bar_key)) {
+                    (Some(pair), _, Some(bar_timestamp)) => Self::process_pair_bar(
+                        pair,
+                        &market_data,
+                        &positions,
+                        &config,
+                        &state,
+                        &broker,
+                        &bar_builder,
+                        &app_handle,
+                        &news_halts,
+                        current_time,
+                        bar_timestamp,
+                    ).await,
+                    (None, Some(data), Some(bar_timestamp)) => Self::process_symbol_bar(
+                        &symbol,
+                        data,
+                        &positions,
+                        &config,
+                        &state,
+                        &broker,
+                        &bar_builder,
+                        &app_handle,
+                        &news_halts,
+                        current_time,
+                        bar_timestamp,
+                    ).await,
+                    (_, _, None) => Err(BarError::new(ErrorClass::Internal, format!("Invalid bar key {}", bar_key))),
+                    (None, None, _) => Err(BarError::new(ErrorClass::Data, format!("No market data for {}", symbol))),
+                };
+
+                match result {
+                    Ok(()) => {
+                        state.lock().await.record_retry_success(&bar_key);
+                        Self::handle_symbol_success(&symbol, &state, &app_handle, current_time).await;
+                    }
+                    Err(e) => {
+                        Self::handle_bar_error(&symbol, &bar_key, &e, attempts + 1, &config, &state, &app_handle, current_time).await;
+                    }
+                }
+                retried_bars.insert(bar_key);
+            }
+
+            // Process each trade-flagged symbol with market data
+            let bar_timestamp = Self::get_bar_timestamp(current_time, config.cadence_minutes);
+            for (symbol, data) in market_data.iter() {
+                let bar_key = format!("{}:{}", symbol, bar_timestamp);
+                if !Self::is_traded(symbol, &config, &watchlists)
+                    || retried_bars.contains(&bar_key)
+                    || state.lock().await.is_symbol_blocked(symbol, current_time)
+                {
+                    continue;
+                }
+
+                match Self::process_symbol_bar(
+                    &symbol,
+                    data,
+                    &positions,
                     &config,
                     &state,
                     &broker,
+                    &bar_builder,
                     &app_handle,
+                    &news_halts,
                     current_time,
+                    bar_timestamp,
                 ).await {
+                    Ok(()) => Self::handle_symbol_success(symbol, &state, &app_handle, current_time).await,
                     // Log error and continue with other symbols
-                    let mut loop_state = state.lock().await;
-                    loop_state.error_count += 1;
-                    loop_state.last_error = Some(e.clone());
-                    
-                    let _ = app_handle.emit("strategy_error", &format!("Error processing {}: {}", symbol, e));
+                    Err(e) => Self::handle_bar_error(symbol, &bar_key, &e, 1, &config, &state, &app_handle, current_time).await,
+                }
+            }
+
+            // Each pair is one watchlist entry: both legs trade together or not at all
+            for pair in &config.pairs {
+                let label = pair.label();
+                let bar_key = format!("{}:{}", label, bar_timestamp);
+                if retried_bars.contains(&bar_key) || state.lock().await.is_symbol_blocked(&label, current_time) {
+                    continue;
+                }
+
+                match Self::process_pair_bar(
+                    pair,
+                    &market_data,
+                    &positions,
+                    &config,
+                    &state,
+                    &broker,
+                    &bar_builder,
+                    &app_handle,
+                    &news_halts,
+                    current_time,
+                    bar_timestamp,
+                ).await {
+                    Ok(()) => Self::handle_symbol_success(&label, &state, &app_handle, current_time).await,
+                    Err(e) => Self::handle_bar_error(&label, &bar_key, &e, 1, &config, &state, &app_handle, current_time).await,
                 }
             }
 
@@ -275,6 +1303,215 @@ impl StrategyLoop {
         }
     }
 
+    async fn check_option_expirations(
+        config: &StrategyLoopConfig,
+        state: &Arc<Mutex<LoopState>>,
+        broker: &Arc<Mutex<PaperBroker>>,
+        app_handle: &AppHandle,
+    ) {
+        let (report, upcoming, now) = {
+            let mut broker_guard = broker.lock().await;
+            let now = DateTime::from_timestamp(broker_guard.now(), 0).unwrap_or_default().with_timezone(&New_York);
+
+            let (due, alert_due) = {
+                let loop_state = state.lock().await;
+                (
+                    loop_state.expiry_check_due(now.date_naive(), now.time(), broker_guard.is_market_open()),
+                    loop_state.last_expiry_alert_date != Some(now.date_naive()),
+                )
+            };
+            let report = due.then(|| broker_guard.process_option_expirations());
+            let upcoming = if alert_due { broker_guard.check_for_upcoming_expirations(config.expiry_alert_days) } else { Vec::new() };
+            (report, upcoming, now)
+        };
+
+        {
+            let mut loop_state = state.lock().await;
+            if report.is_some() {
+                loop_state.last_expiry_check_date = Some(now.date_naive());
+            }
+            if !upcoming.is_empty() {
+                loop_state.last_expiry_alert_date = Some(now.date_naive());
+            }
+        }
+
+        if let Some(report) = report {
+            if !report.expired.is_empty() {
+                let _ = app_handle.emit("strategy_log", &StrategyLog {
+                    timestamp: now.timestamp(),
+                    level: LogLevel::Info,
+                    category: "expiration".to_string(),
+                    message: format!(
+                        "Settled {} expired option positions ({} exercised or assigned)",
+                        report.expired.len(),
+                        report.assigned.len()
+                    ),
+                    data: Some(serde_json::to_value(&report).unwrap_or_default()),
+                    symbol: None,
+                    bar_timestamp: None,
+                });
+            }
+            let _ = app_handle.emit("option_expiration_processed", &report);
+        }
+        if !upcoming.is_empty() {
+            let _ = app_handle.emit("upcoming_expiration_alert", &serde_json::json!({
+                "days_ahead": config.expiry_alert_days,
+                "options": upcoming,
+            }));
+        }
+    }
+
+    /// Record a decision that places orders in the decision journal
+    fn journal_decision(evaluation: &SignalEvaluation, app_handle: &AppHandle) {
+        if evaluation.decision.orders.is_empty() {
+            return;
+        }
+        let result = DecisionJournal::open(app_handle).and_then(|journal| journal.record(DecisionRecord::from_evaluation(evaluation)));
+        if let Err(e) = result {
+            eprintln!("Failed to journal the decision for {}: {}", evaluation.symbol, e);
+        }
+    }
+
+    /// Write the outcome of each journaled decision whose opened position has closed
+    async fn settle_decision_outcomes(broker: &Arc<Mutex<PaperBroker>>, app_handle: &AppHandle) {
+        let journal = match DecisionJournal::open(app_handle) {
+            Ok(journal) => journal,
+            Err(e) => return eprintln!("Failed to open the decision journal: {}", e),
+        };
+        let unsettled: Vec<DecisionRecord> = match journal.load() {
+            Ok(records) => records.into_iter().filter(|r| r.outcome.is_none()).collect(),
+            Err(e) => return eprintln!("{}", e),
+        };
+        if unsettled.is_empty() {
+            return;
+        }
+        let reports = {
+            let broker_guard = broker.lock().await;
+            settle_decisions(&unsettled, &broker_guard.trades, &broker_guard.get_applied_splits(), &broker_guard.daily_summaries)
+        };
+        let settled: Vec<&DecisionReport> = reports.iter().filter(|r| r.record.outcome.is_some()).collect();
+        let outcomes: Vec<DecisionOutcome> = settled.iter().filter_map(|r| r.record.outcome.clone()).collect();
+        if let Err(e) = journal.write_outcomes(&outcomes) {
+            return eprintln!("Failed to write decision outcomes: {}", e);
+        }
+        for report in settled {
+            let _ = app_handle.emit("strategy_log", &StrategyLog {
+                timestamp: Utc::now().timestamp(),
+                level: LogLevel::Info,
+                category: "decision_outcome".to_string(),
+                message: format!(
+                    "Symbol: {} | {:?} decision at {} closed for {:.2}",
+                    report.record.symbol,
+                    report.record.action,
+                    Self::format_timestamp(report.record.bar_timestamp),
+                    report.realized_pnl
+                ),
+                data: Some(serde_json::to_value(report).unwrap_or_default()),
+                symbol: Some(report.record.symbol.clone()),
+                bar_timestamp: Some(report.record.bar_timestamp),
+            });
+        }
+    }
+
+    /// Whether the loop evaluates `symbol` on its own: trade-flagged and not a pair leg.
+    /// Read per symbol, so clearing the flag takes effect within the cycle.
+    fn is_traded(symbol: &str, config: &StrategyLoopConfig, watchlists: &SharedWatchlists) -> bool {
+        !config.is_pair_leg(symbol) && watchlists.lock().is_ok_and(|w| w.is_flagged(symbol, SymbolFlag::Trade))
+    }
+
+    /// Fetch news for held and trade-flagged watchlist symbols when the monitor's interval is up
+    async fn check_news_halts(
+        config: &StrategyLoopConfig,
+        news_halts: &NewsHaltMonitor,
+        watchlists: &SharedWatchlists,
+        positions: &HashMap<String, Position>,
+        current_time: i64,
+    ) {
+        news_halts.expire(current_time);
+        if !news_halts.check_due(current_time) {
+            return;
+        }
+        let days = news_halts.config().map(|c| c.fetch_days()).unwrap_or(1);
+
+        let mut symbols: Vec<String> = watchlists.lock().map(|w| w.symbols_with(SymbolFlag::Trade)).unwrap_or_default();
+        symbols.extend(config.pairs.iter().flat_map(|p| [p.first.clone(), p.second.clone()]));
+        symbols.extend(
+            positions
+                .values()
+                .filter(|p| p.quantity != 0)
+                .map(|p| OptionSymbolParser::parse(&p.symbol).map_or_else(|| p.symbol.clone(), |d| d.underlying)),
+        );
+        symbols.sort();
+        symbols.dedup();
+
+        for symbol in symbols {
+            match poly::fetch_news(news_halts.app_handle(), symbol.clone(), days).await {
+                Ok((_, articles)) => {
+                    news_halts.observe(&symbol, &articles, current_time);
+                }
+                Err(e) => eprintln!("News check for {} failed: {}", symbol, e),
+            }
+        }
+    }
+
+    /// Entries on a symbol under a news halt become skips; exits and holds go through
+    fn block_entries_for_news(decision: StrategyDecision, halts: &[NewsHalt]) -> StrategyDecision {
+        let Some(halt) = halts.first() else {
+            return decision;
+        };
+        if !matches!(decision.action, DecisionAction::Buy | DecisionAction::Sell) {
+            return decision;
+        }
+        StrategyDecision {
+            action: DecisionAction::Skip,
+            reason: format!("Entry blocked by the news halt on {} until {}: {}", halt.symbol, halt.expires_at, halt.reason),
+            orders: Vec::new(),
+            ..decision
+        }
+    }
+
+    /// Entries on a symbol whose orders the risk checks rejected `risk_rejections` times
+    /// this session become skips once the back-off threshold is reached
+    fn back_off_after_rejections(decision: StrategyDecision, risk_rejections: u32, backoff: &RejectionBackoff) -> StrategyDecision {
+        if !backoff.enabled || risk_rejections < backoff.max_risk_rejections {
+            return decision;
+        }
+        if !matches!(decision.action, DecisionAction::Buy | DecisionAction::Sell) {
+            return decision;
+        }
+        StrategyDecision {
+            action: DecisionAction::Skip,
+            reason: format!("repeated rejections: {} risk check rejections this session", risk_rejections),
+            orders: Vec::new(),
+            ..decision
+        }
+    }
+
+    /// Put a decision's orders in allocation `name` and cut its buys to the `room` the
+    /// bucket has left at `price`; an entry with no room left becomes a skip
+    fn size_to_allocation(decision: StrategyDecision, name: &str, room: f64, price: f64) -> StrategyDecision {
+        let had_orders = !decision.orders.is_empty();
+        let mut orders = Vec::new();
+        for mut order in decision.orders.clone() {
+            order.allocation = Some(name.to_string());
+            if order.side == OrderSide::Buy && price > 0.0 {
+                order.quantity = order.quantity.min((room / price).floor() as i64);
+            }
+            if order.quantity > 0 {
+                orders.push(order);
+            }
+        }
+        if had_orders && orders.is_empty() {
+            return StrategyDecision {
+                action: DecisionAction::Skip,
+                reason: format!("allocation {} has ${:.2} of room left", name, room),
+                orders,
+                ..decision
+            };
+        }
+        StrategyDecision { orders, ..decision }
+    }
+
     async fn process_symbol_bar(
         symbol: &str,
         market_data: &MarketData,
@@ -282,10 +1519,12 @@ impl StrategyLoop {
         config: &StrategyLoopConfig,
         state: &Arc<Mutex<LoopState>>,
         broker: &Arc<Mutex<PaperBroker>>,
+        bar_builder: &Arc<Mutex<BarBuilder>>,
         app_handle: &AppHandle,
+        news_halts: &NewsHaltMonitor,
         current_time: i64,
-    ) -> Result<(), String> {
-        let bar_timestamp = Self::get_bar_timestamp(current_time, config.cadence_minutes);
+        bar_timestamp: i64,
+    ) -> Result<(), BarError> {
         let bar_key = format!("{}:{}", symbol, bar_timestamp);
 
         // Check if we've already processed this bar (prevent double-firing)
@@ -306,134 +1545,725 @@ impl StrategyLoop {
             }
         }
 
-        let evaluation_start = Instant::now();
+        let evaluation_start = Instant::now();
+
+        // Bars for every required timeframe, including the forming bar, and the signals
+        // on them through the builder's indicators
+        let (bars, signals) = {
+            let mut builder = bar_builder.lock().await;
+            let (bars, minute_bars) = (builder.snapshot(symbol), builder.bars(symbol, Timeframe::OneMinute));
+            let signals = Self::evaluate_signals(symbol, &bars, &minute_bars, config, builder.indicators_mut())
+                .await
+                .map_err(|e| BarError::new(ErrorClass::Internal, e))?;
+            (bars, signals)
+        };
+
+        // Drop signals the market regime rules out
+        let daily_bars = bars.get(&Timeframe::OneDay).map(Vec::as_slice).unwrap_or(&[]);
+        let (signals, filtered) = Self::apply_regime_filter(signals, daily_bars, &config.regime_filter);
+        if !filtered.is_empty() {
+            let _ = app_handle.emit("strategy_log", &StrategyLog {
+                timestamp: current_time,
+                level: LogLevel::Info,
+                category: "regime_filter".to_string(),
+                message: format!(
+                    "Symbol: {} | Bar: {} | Filtered: {}",
+                    symbol,
+                    Self::format_timestamp(bar_timestamp),
+                    filtered.iter().map(|f| format!("{} ({})", f.signal, f.reason)).collect::<Vec<_>>().join(", ")
+                ),
+                data: Some(serde_json::json!({ "filtered": filtered })),
+                symbol: Some(symbol.to_string()),
+                bar_timestamp: Some(bar_timestamp),
+            });
+        }
+
+        // Make strategy decision
+        let rule = config.rule_for(symbol);
+        let decision = Self::make_strategy_decision(symbol, &signals, positions, market_data, rule)
+            .await
+            .map_err(|e| BarError::new(ErrorClass::Internal, e))?;
+        let halts: Vec<NewsHalt> = news_halts.active_halt(symbol, current_time).into_iter().collect();
+        let decision = Self::block_entries_for_news(decision, &halts);
+        let (risk_rejections, room) = {
+            let broker_guard = broker.lock().await;
+            let room = config.allocation.as_deref().map(|name| broker_guard.allocation_available(name).unwrap_or(0.0));
+            (broker_guard.risk_rejections_today(symbol, RejectionSource::Strategy), room)
+        };
+        let decision = Self::back_off_after_rejections(decision, risk_rejections, &config.rejection_backoff);
+        let decision = match (&config.allocation, room) {
+            (Some(name), Some(room)) => Self::size_to_allocation(decision, name, room, market_data.ask.unwrap_or(market_data.last_price)),
+            _ => decision,
+        };
+
+        let evaluation_time = evaluation_start.elapsed().as_millis() as u64;
+
+        // Create evaluation record
+        let evaluation = SignalEvaluation {
+            id: Uuid::new_v4().to_string(),
+            symbol: symbol.to_string(),
+            timestamp: current_time,
+            bar_timestamp,
+            signals: signals.clone(),
+            decision: decision.clone(),
+            execution_time_ms: evaluation_time,
+        };
+
+        // Log the evaluation
+        Self::log_evaluation(&evaluation, config, app_handle).await;
+
+        // Execute decision if not in dry run mode; in dry run, book it in the shadow ledger
+        if config.dry_run && decision.risk_assessment.approved {
+            state.lock().await.shadow.record(&decision.orders, |_| Some(market_data.last_price));
+        }
+        if !config.dry_run && decision.risk_assessment.approved {
+            Self::journal_decision(&evaluation, app_handle);
+            Self::execute_decision(symbol, &evaluation.id, &decision, broker, app_handle)
+                .await
+                .map_err(|e| BarError::new(ErrorClass::OrderPlacement, e))?;
+
+            // Update cooldown
+            {
+                let mut loop_state = state.lock().await;
+                loop_state.signal_cooldowns.insert(symbol.to_string(), current_time);
+            }
+        }
+
+        // Mark bar as processed
+        {
+            let mut loop_state = state.lock().await;
+            loop_state.processed_bars.insert(bar_key);
+        }
+
+        // Emit evaluation event
+        let _ = app_handle.emit("signal_evaluation", &evaluation);
+
+        Ok(())
+    }
+
+    /// Evaluate a pair on the z-score of its daily spread, the last bar forming, and
+    /// place both legs or neither
+    async fn process_pair_bar(
+        pair: &PairConfig,
+        market_data: &HashMap<String, MarketData>,
+        positions: &HashMap<String, Position>,
+        config: &StrategyLoopConfig,
+        state: &Arc<Mutex<LoopState>>,
+        broker: &Arc<Mutex<PaperBroker>>,
+        bar_builder: &Arc<Mutex<BarBuilder>>,
+        app_handle: &AppHandle,
+        news_halts: &NewsHaltMonitor,
+        current_time: i64,
+        bar_timestamp: i64,
+    ) -> Result<(), BarError> {
+        let label = pair.label();
+        let bar_key = format!("{}:{}", label, bar_timestamp);
+        {
+            let loop_state = state.lock().await;
+            if loop_state.processed_bars.contains(&bar_key) {
+                return Ok(());
+            }
+            if let Some(&last_signal_time) = loop_state.signal_cooldowns.get(&label) {
+                if current_time - last_signal_time < config.cooldown_seconds as i64 {
+                    return Ok(());
+                }
+            }
+        }
+
+        let evaluation_start = Instant::now();
+        let mut prices = [0.0; 2];
+        for (price, leg) in prices.iter_mut().zip([&pair.first, &pair.second]) {
+            *price = market_data
+                .get(leg)
+                .map(|data| data.last_price)
+                .ok_or_else(|| BarError::new(ErrorClass::Data, format!("No market data for {}", leg)))?;
+        }
+
+        let closes = {
+            let builder = bar_builder.lock().await;
+            let daily = |symbol: &str| -> Vec<(chrono::NaiveDate, f64)> {
+                builder.bars(symbol, Timeframe::OneDay).iter().map(|c| (c.date(), c.close)).collect()
+            };
+            align_closes(&daily(&pair.first), &daily(&pair.second))
+        };
+        let allow_short_selling = broker.lock().await.config.allow_short_selling;
+        let (mut decision, signal) = Self::make_pair_decision(pair, &closes, positions, prices, allow_short_selling);
+        for order in decision.orders.iter_mut() {
+            order.allocation = config.allocation.clone();
+        }
+        let halts: Vec<NewsHalt> = [&pair.first, &pair.second].iter().filter_map(|leg| news_halts.active_halt(leg, current_time)).collect();
+        let decision = Self::block_entries_for_news(decision, &halts);
+
+        let evaluation = SignalEvaluation {
+            id: Uuid::new_v4().to_string(),
+            symbol: label.clone(),
+            timestamp: current_time,
+            bar_timestamp,
+            signals: signal.into_iter().collect(),
+            decision: decision.clone(),
+            execution_time_ms: evaluation_start.elapsed().as_millis() as u64,
+        };
+        Self::log_evaluation(&evaluation, config, app_handle).await;
+
+        if config.dry_run && decision.risk_assessment.approved {
+            state.lock().await.shadow.record(&decision.orders, |leg| market_data.get(leg).map(|d| d.last_price));
+        }
+        if !config.dry_run && decision.risk_assessment.approved && !decision.orders.is_empty() {
+            Self::journal_decision(&evaluation, app_handle);
+            Self::execute_pair_decision(&label, &evaluation.id, &decision, broker, app_handle)
+                .await
+                .map_err(|e| BarError::new(ErrorClass::OrderPlacement, e))?;
+            state.lock().await.signal_cooldowns.insert(label.clone(), current_time);
+        }
+
+        state.lock().await.processed_bars.insert(bar_key);
+        let _ = app_handle.emit("signal_evaluation", &evaluation);
+        Ok(())
+    }
+
+    /// Open, close or hold a pair from the latest spread z-score and the legs held.
+    /// Legs held out of balance (one flat, or both on the same side) are left alone.
+    fn make_pair_decision(
+        pair: &PairConfig,
+        closes: &[AlignedClose],
+        positions: &HashMap<String, Position>,
+        prices: [f64; 2],
+        allow_short_selling: bool,
+    ) -> (StrategyDecision, Option<SignalResult>) {
+        let held = [&pair.first, &pair.second].map(|leg| positions.get(leg).map(|p| p.quantity).unwrap_or(0));
+        let open = match (held[0].signum(), held[1].signum()) {
+            (0, 0) => Ok(None),
+            (1, -1) => Ok(Some(PairDirection::LongSpread)),
+            (-1, 1) => Ok(Some(PairDirection::ShortSpread)),
+            _ => Err(format!("{} legs are out of balance ({} / {}); trade them by hand", pair.label(), held[0], held[1])),
+        };
+        let zscore = spread_zscores(pair, closes).last().copied().flatten();
+        let signal = zscore.map(|z| SignalResult {
+            name: "PairZScore".to_string(),
+            direction: match z {
+                z if z <= -pair.entry_z => SignalDirection::Long,
+                z if z >= pair.entry_z => SignalDirection::Short,
+                _ => SignalDirection::Neutral,
+            },
+            confidence: (z.abs() / pair.entry_z).min(1.0),
+            metadata: HashMap::from([
+                ("zscore".to_string(), serde_json::json!(z)),
+                ("aligned_bars".to_string(), serde_json::json!(closes.len())),
+            ]),
+            timeframe: Timeframe::OneDay,
+            weight: default_signal_weight(),
+        });
+
+        let order = |symbol: &str, quantity: i64| OrderRequest {
+            symbol: symbol.to_string(),
+            side: if quantity > 0 { OrderSide::Buy } else { OrderSide::Sell },
+            order_type: OrderType::Market,
+            quantity: quantity.abs(),
+            price: None,
+            stop_price: None,
+            time_in_force: TimeInForce::Day,
+            client_order_id: Some(format!("strategy_{}_{}", Utc::now().timestamp(), symbol)),
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            preferred_venue: VenueType::Smart,
+            expire_at: None,
+            auto_round: true,
+            override_token: None,
+            allocation: None,
+            residual_policy: None,
+        };
+        let mut warnings = Vec::new();
+        let (action, reason, orders) = match (open, zscore) {
+            (Err(e), _) => {
+                warnings.push(e.clone());
+                (DecisionAction::Skip, e, vec![])
+            }
+            (Ok(_), None) => (DecisionAction::Skip, format!("Not enough aligned daily history for {}", pair.label()), vec![]),
+            (Ok(open), Some(z)) => match pair.next_action(z, open) {
+                Some((PairAction::Open(_), _)) if !allow_short_selling => {
+                    (DecisionAction::Skip, "Short selling is disabled in the broker config".to_string(), vec![])
+                }
+                Some((PairAction::Open(direction), reason)) => {
+                    let hedge_ratio = pair.hedge_ratio(closes, closes.len() - 1);
+                    match leg_quantities(pair.leg_notional, hedge_ratio, prices[0], prices[1]) {
+                        (0, _) | (_, 0) => (DecisionAction::Skip, format!("{} notional buys no shares of a leg", pair.label()), vec![]),
+                        (a, b) => {
+                            let (action, signs) = match direction {
+                                PairDirection::LongSpread => (DecisionAction::Buy, [1, -1]),
+                                PairDirection::ShortSpread => (DecisionAction::Sell, [-1, 1]),
+                            };
+                            let orders = vec![order(&pair.first, signs[0] * a), order(&pair.second, signs[1] * b)];
+                            (action, format!("{} at z {:.2}, hedge ratio {:.2}", reason, z, hedge_ratio), orders)
+                        }
+                    }
+                }
+                Some((PairAction::Close, reason)) => {
+                    let orders = vec![order(&pair.first, -held[0]), order(&pair.second, -held[1])];
+                    (DecisionAction::Close, format!("{} at z {:.2}", reason, z), orders)
+                }
+                None if open.is_some() => (DecisionAction::Hold, format!("Spread z {:.2} has not reverted", z), vec![]),
+                None => (DecisionAction::Skip, format!("Spread z {:.2} within entry band", z), vec![]),
+            },
+        };
+
+        let gross = orders.iter().zip(prices).map(|(o, p)| o.quantity as f64 * p).sum::<f64>();
+        let decision = StrategyDecision {
+            action,
+            reason,
+            risk_assessment: RiskAssessment {
+                position_size: gross,
+                risk_per_trade: gross * 0.02,
+                portfolio_heat: 0.05,
+                max_drawdown_risk: 0.10,
+                approved: warnings.is_empty(),
+                warnings,
+            },
+            orders,
+            contributing_signals: signal.iter().map(|s| s.label()).collect(),
+        };
+        (decision, signal)
+    }
+
+    /// Place both legs; when the second is rejected the first is traded back out so
+    /// the pair is never left legged
+    async fn execute_pair_decision(
+        label: &str,
+        evaluation_id: &str,
+        decision: &StrategyDecision,
+        broker: &Arc<Mutex<PaperBroker>>,
+        app_handle: &AppHandle,
+    ) -> Result<(), String> {
+        let mut broker_guard = broker.lock().await;
+        let mut placed: Vec<&OrderRequest> = Vec::new();
+        for order in &decision.orders {
+            match broker_guard.place_evaluated_order(order.clone(), evaluation_id) {
+                Ok(execution) => {
+                    placed.push(order);
+                    let _ = app_handle.emit("strategy_order_placed", &serde_json::json!({
+                        "symbol": label,
+                        "action": decision.action,
+                        "order": order,
+                        "execution": execution
+                    }));
+                }
+                Err(e) => {
+                    for leg in placed {
+                        let unwind = OrderRequest {
+                            side: if leg.side == OrderSide::Buy { OrderSide::Sell } else { OrderSide::Buy },
+                            client_order_id: leg.client_order_id.as_ref().map(|id| format!("{}_unwind", id)),
+                            ..leg.clone()
+                        };
+                        if let Err(unwind_error) = broker_guard.place_evaluated_order(unwind, evaluation_id) {
+                            eprintln!("Unwinding {} leg of {} failed: {}", leg.symbol, label, unwind_error);
+                        }
+                    }
+                    let _ = app_handle.emit("strategy_order_failed", &serde_json::json!({
+                        "symbol": label,
+                        "action": decision.action,
+                        "order": order,
+                        "error": e
+                    }));
+                    return Err(format!("Failed to place {} leg of {}: {}", order.symbol, label, e));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_bar_error(
+        symbol: &str,
+        bar_key: &str,
+        error: &BarError,
+        attempts: u32,
+        config: &StrategyLoopConfig,
+        state: &Arc<Mutex<LoopState>>,
+        app_handle: &AppHandle,
+        current_time: i64,
+    ) {
+        let (dead_letter, escalation, health) = {
+            let mut loop_state = state.lock().await;
+            let dead_letter = loop_state.record_failure(symbol, bar_key, error, attempts, config, current_time);
+            let escalation = loop_state.record_symbol_error(symbol, error, config, current_time);
+            (dead_letter, escalation, loop_state.symbol_health.get(symbol).cloned())
+        };
+
+        let _ = app_handle.emit("strategy_error", &format!("Error processing {} (attempt {}): {}", symbol, attempts, error.message));
+        let _ = app_handle.emit("strategy_log", &StrategyLog {
+            timestamp: current_time,
+            level: LogLevel::Error,
+            category: error.class.category().to_string(),
+            message: format!("Symbol: {} | Bar: {} | Attempt: {} | {}", symbol, bar_key, attempts, error.message),
+            data: Some(serde_json::json!({ "error_class": error.class, "health": health })),
+            symbol: Some(symbol.to_string()),
+            bar_timestamp: Self::parse_bar_key(bar_key),
+        });
+
+        if let Some(entry) = dead_letter {
+            let _ = app_handle.emit("strategy_dead_letter", &entry);
+        }
+
+        if let Some(status) = escalation {
+            let _ = app_handle.emit("symbol_quarantined", &serde_json::json!({
+                "symbol": symbol,
+                "status": status,
+                "health": health
+            }));
+        }
+    }
+
+    async fn handle_symbol_success(
+        symbol: &str,
+        state: &Arc<Mutex<LoopState>>,
+        app_handle: &AppHandle,
+        current_time: i64,
+    ) {
+        if state.lock().await.record_symbol_success(symbol) {
+            let _ = app_handle.emit("symbol_unquarantined", &serde_json::json!({
+                "symbol": symbol,
+                "timestamp": current_time
+            }));
+        }
+    }
+
+    async fn evaluate_signals(
+        symbol: &str,
+        bars: &HashMap<Timeframe, Vec<Candle>>,
+        minute_bars: &[Candle],
+        config: &StrategyLoopConfig,
+        indicators: &mut IndicatorCache,
+    ) -> Result<Vec<SignalResult>, String> {
+        let mut signals: Vec<SignalResult> = config.signals
+            .iter()
+            .filter_map(|signal| {
+                let series = bars.get(&signal.timeframe)?;
+                Self::compute_signal_cached(signal, series, indicators, symbol, &indicator_series_key(signal.timeframe, false))
+            })
+            .collect();
+
+        // Signals evaluated on 1-minute bars downsampled to their timeframe
+        let mut timeframes: Vec<&u32> = config.multi_timeframe_signals.keys().collect();
+        timeframes.sort();
+        for minutes in timeframes {
+            let Some(timeframe) = Timeframe::from_minutes(*minutes) else {
+                continue;
+            };
+            let series = analytics::downsample_bars(minute_bars, *minutes);
+
+            for name in &config.multi_timeframe_signals[minutes] {
+                let signal = SignalConfig {
+                    name: name.clone(),
+                    timeframe,
+                    lookback: default_lookback(name),
+                    weight: default_signal_weight(),
+                };
+                signals.extend(Self::compute_signal_cached(&signal, &series, indicators, symbol, &indicator_series_key(timeframe, true)));
+            }
+        }
+
+        let composites: Vec<SignalResult> = config.composite_signals
+            .iter()
+            .filter(|composite| !composite.signals.is_empty())
+            .map(|composite| compute_composite_signal(&signals, composite))
+            .collect();
+        signals.extend(composites);
+
+        Ok(signals)
+    }
+
+    /// Apply the regime filter to evaluated signals using daily bars (the last one is the
+    /// current, possibly forming, day). Range and volume failures drop every signal; the
+    /// trend check drops only signals against the EMA50/EMA200 trend.
+    fn apply_regime_filter(
+        signals: Vec<SignalResult>,
+        daily_bars: &[Candle],
+        filter: &MarketRegimeFilter,
+    ) -> (Vec<SignalResult>, Vec<FilteredSignal>) {
+        if !filter.enabled || signals.is_empty() {
+            return (signals, Vec::new());
+        }
+        let reject_all = |reason: String| (Vec::new(), vec![FilteredSignal { signal: "*".to_string(), reason }]);
+
+        let Some(current) = daily_bars.last() else {
+            return reject_all("no daily bars".to_string());
+        };
+
+        if current.low <= 0.0 {
+            return reject_all("invalid daily low".to_string());
+        }
+        let range_pct = (current.high - current.low) / current.low * 100.0;
+        if range_pct < filter.min_adr_pct {
+            return reject_all(format!("range {:.2}% below {:.2}%", range_pct, filter.min_adr_pct));
+        }
+        if range_pct > filter.max_adr_pct {
+            return reject_all(format!("range {:.2}% above {:.2}%", range_pct, filter.max_adr_pct));
+        }
+
+        let prior = &daily_bars[..daily_bars.len() - 1];
+        let prior = &prior[prior.len().saturating_sub(REGIME_VOLUME_DAYS)..];
+        let avg_volume = Self::mean(&prior.iter().map(|b| b.volume as f64).collect::<Vec<_>>());
+        if prior.is_empty() || avg_volume <= 0.0 {
+            return reject_all("no volume history".to_string());
+        }
+        let volume_ratio = current.volume as f64 / avg_volume;
+        if volume_ratio < filter.min_volume_ratio {
+            return reject_all(format!("volume ratio {:.2} below {:.2}", volume_ratio, filter.min_volume_ratio));
+        }
+
+        if !filter.require_trend {
+            return (signals, Vec::new());
+        }
+        if daily_bars.len() < REGIME_SLOW_EMA {
+            return reject_all(format!("{} daily bars, trend needs {}", daily_bars.len(), REGIME_SLOW_EMA));
+        }
+
+        let closes: Vec<f64> = daily_bars.iter().map(|b| b.close).collect();
+        let fast = *Self::ema_series(&closes, REGIME_FAST_EMA).last().unwrap_or(&0.0);
+        let slow = *Self::ema_series(&closes, REGIME_SLOW_EMA).last().unwrap_or(&0.0);
+        let uptrend = fast > slow;
+
+        let (kept, against): (Vec<_>, Vec<_>) = signals.into_iter().partition(|s| match s.direction {
+            SignalDirection::Long => uptrend,
+            SignalDirection::Short => !uptrend,
+            SignalDirection::Neutral => true,
+        });
+        let trend = if uptrend { "uptrend" } else { "downtrend" };
+        let filtered = against
+            .iter()
+            .map(|s| FilteredSignal {
+                signal: s.label(),
+                reason: format!("{:?} against {} (EMA50 {:.2}, EMA200 {:.2})", s.direction, trend, fast, slow),
+            })
+            .collect();
+
+        (kept, filtered)
+    }
+
+    /// Indicators a signal reads; signals declaring the same one share its values
+    pub(crate) fn indicator_dependencies(signal: &SignalConfig) -> Vec<IndicatorSpec> {
+        let lookback = signal.lookback.max(2);
+        match signal.name.as_str() {
+            "SMA_Crossover" => vec![IndicatorSpec::new(IndicatorKind::Sma, (lookback / 4).max(2)), IndicatorSpec::new(IndicatorKind::Sma, lookback)],
+            "RSI" => vec![IndicatorSpec::new(IndicatorKind::Rsi, lookback - 1)],
+            "Trend" => vec![IndicatorSpec::new(IndicatorKind::Sma, lookback)],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Evaluate one configured signal on its timeframe's bars, computing its indicators
+    /// over all of them. Returns None when there is not enough history or the signal has
+    /// nothing to report.
+    #[cfg(test)]
+    pub(crate) fn compute_signal(signal: &SignalConfig, bars: &[Candle]) -> Option<SignalResult> {
+        let values = IndicatorValues::compute(&Self::indicator_dependencies(signal), bars);
+        Self::evaluate_signal(signal, bars, &values)
+    }
 
-        // Create synthetic OHLC bar from market data
-        let bar = OhlcBar {
-            symbol: symbol.to_string(),
-            timestamp: bar_timestamp,
-            open: market_data.last_price,
-            high: market_data.last_price,
-            low: market_data.last_price,
-            close: market_data.last_price,
-            volume: 0,
-        };
+    /// `compute_signal` with its indicators brought up to date in `indicators`, where
+    /// they are kept under `symbol` and `series` for the next bar
+    pub(crate) fn compute_signal_cached(
+        signal: &SignalConfig,
+        bars: &[Candle],
+        indicators: &mut IndicatorCache,
+        symbol: &str,
+        series: &str,
+    ) -> Option<SignalResult> {
+        let values = indicators.resolve(symbol, series, bars, &Self::indicator_dependencies(signal));
+        Self::evaluate_signal(signal, bars, &values)
+    }
 
-        // Evaluate signals for this symbol
-        let signals = Self::evaluate_signals(symbol, &bar, market_data, positions).await?;
+    fn evaluate_signal(signal: &SignalConfig, bars: &[Candle], values: &IndicatorValues) -> Option<SignalResult> {
+        let lookback = signal.lookback.max(2);
+        if bars.len() < lookback {
+            return None;
+        }
 
-        // Make strategy decision
-        let decision = Self::make_strategy_decision(symbol, &signals, positions, market_data).await?;
+        let window = &bars[bars.len() - lookback..];
+        let closes: Vec<f64> = window.iter().map(|b| b.close).collect();
+        let last_close = *closes.last()?;
+        let mut metadata = HashMap::new();
+        metadata.insert("timeframe".to_string(), serde_json::json!(signal.timeframe.label()));
+
+        let (direction, confidence) = match signal.name.as_str() {
+            "SMA_Crossover" => {
+                let sma_short = values.get(IndicatorKind::Sma, (lookback / 4).max(2))?;
+                let sma_long = values.get(IndicatorKind::Sma, lookback)?;
+                metadata.insert("sma_short".to_string(), serde_json::json!(sma_short));
+                metadata.insert("sma_long".to_string(), serde_json::json!(sma_long));
+
+                if sma_short > sma_long {
+                    (SignalDirection::Long, 0.7)
+                } else if sma_short < sma_long {
+                    (SignalDirection::Short, 0.7)
+                } else {
+                    return None;
+                }
+            }
+            "RSI" => {
+                let rsi = values.get(IndicatorKind::Rsi, lookback - 1)?;
+                metadata.insert("rsi".to_string(), serde_json::json!(rsi));
+
+                if rsi < 30.0 {
+                    (SignalDirection::Long, 0.8)
+                } else if rsi > 70.0 {
+                    (SignalDirection::Short, 0.8)
+                } else {
+                    (SignalDirection::Neutral, 0.5)
+                }
+            }
+            "Volume_Spike" => {
+                let volumes: Vec<f64> = window.iter().map(|b| b.volume as f64).collect();
+                let avg_volume = Self::mean(&volumes[..volumes.len() - 1]);
+                let current_volume = volumes[volumes.len() - 1];
+                if avg_volume <= 0.0 || current_volume <= avg_volume * 1.5 {
+                    return None;
+                }
+                metadata.insert("volume".to_string(), serde_json::json!(current_volume));
+                metadata.insert("avg_volume".to_string(), serde_json::json!(avg_volume));
+                (SignalDirection::Neutral, 0.6)
+            }
+            "MACD" => {
+                let (macd, signal_line) = Self::macd(&closes);
+                let histogram = macd - signal_line;
+                metadata.insert("macd".to_string(), serde_json::json!(macd));
+                metadata.insert("signal".to_string(), serde_json::json!(signal_line));
+                metadata.insert("histogram".to_string(), serde_json::json!(histogram));
+
+                if histogram > 0.0 {
+                    (SignalDirection::Long, 0.65)
+                } else if histogram < 0.0 {
+                    (SignalDirection::Short, 0.65)
+                } else {
+                    (SignalDirection::Neutral, 0.5)
+                }
+            }
+            "Trend" => {
+                let sma = values.get(IndicatorKind::Sma, lookback)?;
+                let deviation = (last_close - sma) / sma;
+                metadata.insert("sma".to_string(), serde_json::json!(sma));
+                metadata.insert("deviation".to_string(), serde_json::json!(deviation));
+
+                let confidence = 0.6 + (deviation.abs() * 10.0).min(0.3);
+                if deviation > 0.0 {
+                    (SignalDirection::Long, confidence)
+                } else if deviation < 0.0 {
+                    (SignalDirection::Short, confidence)
+                } else {
+                    (SignalDirection::Neutral, 0.5)
+                }
+            }
+            _ => return None,
+        };
 
-        let evaluation_time = evaluation_start.elapsed().as_millis() as u64;
+        Some(SignalResult {
+            name: signal.name.clone(),
+            direction,
+            confidence,
+            metadata,
+            timeframe: signal.timeframe,
+            weight: signal.weight,
+        })
+    }
 
-        // Create evaluation record
-        let evaluation = SignalEvaluation {
-            symbol: symbol.to_string(),
-            timestamp: current_time,
-            bar_timestamp,
-            signals: signals.clone(),
-            decision: decision.clone(),
-            execution_time_ms: evaluation_time,
-        };
+    pub(crate) fn mean(values: &[f64]) -> f64 {
+        if values.is_empty() {
+            return 0.0;
+        }
+        values.iter().sum::<f64>() / values.len() as f64
+    }
 
-        // Log the evaluation
-        Self::log_evaluation(&evaluation, config, app_handle).await;
+    pub(crate) fn ema_series(values: &[f64], period: usize) -> Vec<f64> {
+        let alpha = 2.0 / (period as f64 + 1.0);
+        let mut ema = Vec::with_capacity(values.len());
+        for (i, value) in values.iter().enumerate() {
+            let next = if i == 0 { *value } else { alpha * value + (1.0 - alpha) * ema[i - 1] };
+            ema.push(next);
+        }
+        ema
+    }
 
-        // Execute decision if not in dry run mode
-        if !config.dry_run && decision.risk_assessment.approved {
-            Self::execute_decision(symbol, &decision, broker, app_handle).await?;
+    /// MACD(12, 26) line and its 9-period signal line at the last close
+    pub(crate) fn macd(closes: &[f64]) -> (f64, f64) {
+        let fast = Self::ema_series(closes, 12);
+        let slow = Self::ema_series(closes, 26);
+        let line: Vec<f64> = fast.iter().zip(&slow).map(|(f, s)| f - s).collect();
+        let signal = Self::ema_series(&line, 9);
+        (*line.last().unwrap_or(&0.0), *signal.last().unwrap_or(&0.0))
+    }
 
-            // Update cooldown
-            {
-                let mut loop_state = state.lock().await;
-                loop_state.signal_cooldowns.insert(symbol.to_string(), current_time);
+    pub(crate) fn rsi(closes: &[f64]) -> f64 {
+        let (gains, losses) = closes.windows(2).fold((0.0, 0.0), |(gains, losses), w| {
+            let change = w[1] - w[0];
+            if change > 0.0 {
+                (gains + change, losses)
+            } else {
+                (gains, losses - change)
             }
-        }
+        });
 
-        // Mark bar as processed
-        {
-            let mut loop_state = state.lock().await;
-            loop_state.processed_bars.insert(bar_key);
+        if losses == 0.0 {
+            return if gains == 0.0 { 50.0 } else { 100.0 };
         }
+        100.0 - 100.0 / (1.0 + gains / losses)
+    }
 
-        // Emit evaluation event
-        let _ = app_handle.emit("signal_evaluation", &evaluation);
+    /// Combine signals under a rule into a direction, confidence and the labels
+    /// of the signals that agreed with it
+    pub(crate) fn combine_signals(signals: &[SignalResult], rule: &CombinationRule) -> (SignalDirection, f64, Vec<String>) {
+        let agreeing = |direction: &SignalDirection| -> Vec<String> {
+            signals.iter().filter(|s| &s.direction == direction).map(|s| s.label()).collect()
+        };
 
-        Ok(())
-    }
+        match rule {
+            CombinationRule::AllMustAgree => {
+                let directional: Vec<&SignalResult> = signals
+                    .iter()
+                    .filter(|s| s.direction != SignalDirection::Neutral)
+                    .collect();
+                let Some(first) = directional.first() else {
+                    return (SignalDirection::Neutral, 0.0, Vec::new());
+                };
+                if directional.iter().any(|s| s.direction != first.direction) {
+                    return (SignalDirection::Neutral, 0.0, Vec::new());
+                }
 
-    async fn evaluate_signals(
-        _symbol: &str,
-        bar: &OhlcBar,
-        market_data: &MarketData,
-        _positions: &HashMap<String, Position>,
-    ) -> Result<Vec<SignalResult>, String> {
-        let mut signals = Vec::new();
-
-        // Simple moving average crossover signal (mock implementation)
-        let price = bar.close;
-        let sma_short = price; // In real implementation, calculate from historical data
-        let sma_long = price * 0.99; // Mock longer MA slightly below current price
-
-        if sma_short > sma_long {
-            signals.push(SignalResult {
-                name: "SMA_Crossover".to_string(),
-                direction: SignalDirection::Long,
-                confidence: 0.7,
-                metadata: {
-                    let mut meta = HashMap::new();
-                    meta.insert("sma_short".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(sma_short).unwrap()));
-                    meta.insert("sma_long".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(sma_long).unwrap()));
-                    meta
-                },
-            });
-        }
+                // Every timeframe that produced a signal must confirm the direction
+                let confirmed = signals
+                    .iter()
+                    .all(|s| directional.iter().any(|d| d.timeframe == s.timeframe));
+                if !confirmed {
+                    return (SignalDirection::Neutral, 0.0, Vec::new());
+                }
 
-        // RSI signal (mock implementation)
-        let rsi = 45.0; // Mock RSI value
-        if rsi < 30.0 {
-            signals.push(SignalResult {
-                name: "RSI_Oversold".to_string(),
-                direction: SignalDirection::Long,
-                confidence: 0.8,
-                metadata: {
-                    let mut meta = HashMap::new();
-                    meta.insert("rsi".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(rsi).unwrap()));
-                    meta
-                },
-            });
-        } else if rsi > 70.0 {
-            signals.push(SignalResult {
-                name: "RSI_Overbought".to_string(),
-                direction: SignalDirection::Short,
-                confidence: 0.8,
-                metadata: {
-                    let mut meta = HashMap::new();
-                    meta.insert("rsi".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(rsi).unwrap()));
-                    meta
-                },
-            });
-        }
+                let confidence = directional.iter().map(|s| s.confidence).sum::<f64>() / directional.len() as f64;
+                (first.direction.clone(), confidence, agreeing(&first.direction))
+            }
+            CombinationRule::WeightedVote { threshold } => {
+                let total_weight: f64 = signals.iter().map(|s| s.weight).sum();
+                if total_weight <= 0.0 {
+                    return (SignalDirection::Neutral, 0.0, Vec::new());
+                }
 
-        // Volume signal (mock implementation)
-        let avg_volume = 1000000.0; // Mock average volume
-        let current_volume = market_data.volume.unwrap_or(0) as f64;
-        if current_volume > avg_volume * 1.5 {
-            signals.push(SignalResult {
-                name: "Volume_Spike".to_string(),
-                direction: SignalDirection::Neutral,
-                confidence: 0.6,
-                metadata: {
-                    let mut meta = HashMap::new();
-                    meta.insert("volume".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(current_volume).unwrap()));
-                    meta.insert("avg_volume".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(avg_volume).unwrap()));
-                    meta
-                },
-            });
+                let score = signals
+                    .iter()
+                    .map(|s| {
+                        let sign = match s.direction {
+                            SignalDirection::Long => 1.0,
+                            SignalDirection::Short => -1.0,
+                            SignalDirection::Neutral => 0.0,
+                        };
+                        sign * s.weight * s.confidence
+                    })
+                    .sum::<f64>() / total_weight;
+
+                if score >= *threshold {
+                    (SignalDirection::Long, score, agreeing(&SignalDirection::Long))
+                } else if score <= -*threshold {
+                    (SignalDirection::Short, -score, agreeing(&SignalDirection::Short))
+                } else {
+                    (SignalDirection::Neutral, score.abs(), Vec::new())
+                }
+            }
         }
-
-        Ok(signals)
     }
 
     async fn make_strategy_decision(
@@ -441,17 +2271,19 @@ impl StrategyLoop {
         signals: &[SignalResult],
         positions: &HashMap<String, Position>,
         market_data: &MarketData,
+        rule: &CombinationRule,
     ) -> Result<StrategyDecision, String> {
         let current_position = positions.get(symbol);
         let price = market_data.last_price;
 
-        // Count signal directions
-        let long_signals: Vec<_> = signals.iter().filter(|s| s.direction == SignalDirection::Long).collect();
-        let short_signals: Vec<_> = signals.iter().filter(|s| s.direction == SignalDirection::Short).collect();
-
-        // Calculate average confidence
-        let long_confidence: f64 = long_signals.iter().map(|s| s.confidence).sum::<f64>() / long_signals.len().max(1) as f64;
-        let short_confidence: f64 = short_signals.iter().map(|s| s.confidence).sum::<f64>() / short_signals.len().max(1) as f64;
+        let (direction, confidence, contributing_signals) = Self::combine_signals(signals, rule);
+        let consensus = format!(
+            "{:?} consensus ({:?}) with confidence {:.2}: {}",
+            direction,
+            rule,
+            confidence,
+            contributing_signals.join(", ")
+        );
 
         // Risk assessment
         let position_size = 100.0; // Mock position size
@@ -469,50 +2301,64 @@ impl StrategyLoop {
         };
 
         // Decision logic
-        let (action, reason, orders) = if long_signals.len() > short_signals.len() && long_confidence > 0.6 {
-            if current_position.is_none() {
-                // Open long position
-                let order = OrderRequest {
-                    symbol: symbol.to_string(),
-                    side: OrderSide::Buy,
-                    order_type: OrderType::Market,
-                    quantity: position_size as i64,
-                    price: None,
-                    stop_price: None,
-                    time_in_force: TimeInForce::Day,
-                    client_order_id: Some(format!("strategy_{}", Utc::now().timestamp())),
-                    instrument_type: InstrumentType::Stock,
-                    option_details: None,
-                };
-                (DecisionAction::Buy, format!("Long signals: {} with confidence {:.2}", long_signals.len(), long_confidence), vec![order])
-            } else {
-                (DecisionAction::Hold, "Already have position".to_string(), vec![])
-            }
-        } else if short_signals.len() > long_signals.len() && short_confidence > 0.6 {
-            if let Some(pos) = current_position {
-                if pos.quantity > 0 {
-                    // Close long position
+        let (action, reason, orders) = match direction {
+            SignalDirection::Long => {
+                if current_position.is_none() {
+                    // Open long position
                     let order = OrderRequest {
                         symbol: symbol.to_string(),
-                        side: OrderSide::Sell,
+                        side: OrderSide::Buy,
                         order_type: OrderType::Market,
-                        quantity: pos.quantity,
+                        quantity: position_size as i64,
                         price: None,
                         stop_price: None,
                         time_in_force: TimeInForce::Day,
                         client_order_id: Some(format!("strategy_{}", Utc::now().timestamp())),
                         instrument_type: InstrumentType::Stock,
                         option_details: None,
+                        preferred_venue: VenueType::Smart,
+                        expire_at: None,
+                        auto_round: true,
+                        override_token: None,
+                        allocation: None,
+                        residual_policy: None,
                     };
-                    (DecisionAction::Close, format!("Short signals: {} with confidence {:.2}", short_signals.len(), short_confidence), vec![order])
+                    (DecisionAction::Buy, consensus, vec![order])
                 } else {
-                    (DecisionAction::Hold, "Already short".to_string(), vec![])
+                    (DecisionAction::Hold, "Already have position".to_string(), vec![])
                 }
-            } else {
-                (DecisionAction::Skip, "No position to close".to_string(), vec![])
             }
-        } else {
-            (DecisionAction::Skip, "No clear signal consensus".to_string(), vec![])
+            SignalDirection::Short => {
+                if let Some(pos) = current_position {
+                    if pos.quantity > 0 {
+                        // Close long position
+                        let order = OrderRequest {
+                            symbol: symbol.to_string(),
+                            side: OrderSide::Sell,
+                            order_type: OrderType::Market,
+                            quantity: pos.quantity,
+                            price: None,
+                            stop_price: None,
+                            time_in_force: TimeInForce::Day,
+                            client_order_id: Some(format!("strategy_{}", Utc::now().timestamp())),
+                            instrument_type: InstrumentType::Stock,
+                            option_details: None,
+                            preferred_venue: VenueType::Smart,
+                            expire_at: None,
+                            auto_round: true,
+                            override_token: None,
+                            allocation: None,
+                            residual_policy: None,
+                        };
+                        (DecisionAction::Close, consensus, vec![order])
+                    } else {
+                        (DecisionAction::Hold, "Already short".to_string(), vec![])
+                    }
+                } else {
+                    (DecisionAction::Skip, "No position to close".to_string(), vec![])
+                }
+            }
+            SignalDirection::Neutral => (DecisionAction::Skip, "No clear signal consensus".to_string(), vec![]),
         };
 
         Ok(StrategyDecision {
@@ -520,11 +2366,13 @@ impl StrategyLoop {
             reason,
             orders,
             risk_assessment,
+            contributing_signals,
         })
     }
 
     async fn execute_decision(
         symbol: &str,
+        evaluation_id: &str,
         decision: &StrategyDecision,
         broker: &Arc<Mutex<PaperBroker>>,
         app_handle: &AppHandle,
@@ -532,7 +2380,7 @@ impl StrategyLoop {
         let mut broker_guard = broker.lock().await;
 
         for order in &decision.orders {
-            match broker_guard.place_order(order.clone()) {
+            match broker_guard.place_evaluated_order(order.clone(), evaluation_id) {
                 Ok(execution) => {
                     let _ = app_handle.emit("strategy_order_placed", &serde_json::json!({
                         "symbol": symbol,
@@ -566,10 +2414,10 @@ impl StrategyLoop {
             level: LogLevel::Info,
             category: "evaluation".to_string(),
             message: format!(
-                "Symbol: {} | Bar: {} | Signals: {} | Action: {:?} | Reason: {}",
+                "Symbol: {} | Bar: {} | Signals: [{}] | Action: {:?} | Reason: {}",
                 evaluation.symbol,
                 Self::format_timestamp(evaluation.bar_timestamp),
-                evaluation.signals.len(),
+                evaluation.signals.iter().map(|s| s.label()).collect::<Vec<_>>().join(", "),
                 evaluation.decision.action,
                 evaluation.decision.reason
             ),
@@ -620,6 +2468,10 @@ impl StrategyLoop {
         (current_time / cadence_seconds as i64) * cadence_seconds as i64
     }
 
+    fn parse_bar_key(bar_key: &str) -> Option<i64> {
+        bar_key.rsplit(':').next()?.parse::<i64>().ok()
+    }
+
     fn format_timestamp(timestamp: i64) -> String {
         DateTime::from_timestamp(timestamp, 0)
             .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
@@ -638,22 +2490,261 @@ impl StrategyLoop {
         });
     }
 
+    /// Whether the loop task is running, without waiting on its state
+    pub fn is_running(&self) -> bool {
+        self.loop_handle.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
     pub async fn get_state(&self) -> LoopState {
-        self.state.lock().await.clone()
+        let mut state = self.state.lock().await.clone();
+        state.last_heartbeat = self.watchdog.last_heartbeat();
+        state.healthy = !self.watchdog.is_stalled();
+        state
+    }
+
+    /// Compare the loop's heartbeat against the cadence, flag a stall and restart the
+    /// loop when the watchdog policy allows. Called periodically by the watchdog thread.
+    pub async fn check_liveness(&mut self) -> LivenessCheck {
+        let Some(handle) = self.loop_handle.as_ref().filter(|_| self.config.watchdog.enabled) else {
+            return LivenessCheck::Idle;
+        };
+        let now = Utc::now().timestamp();
+        let check = self.watchdog.check(&self.config.watchdog, self.config.cadence_minutes, handle.is_finished(), now);
+
+        match check {
+            LivenessCheck::Recovered => {
+                self.log(LogLevel::Info, "watchdog", "Strategy loop heartbeat resumed", None, None, None).await;
+            }
+            LivenessCheck::Stalled { silent_seconds, newly_stalled, restart } => {
+                // Abort first: a task deadlocked while holding the state lock releases it when dropped
+                if restart {
+                    if let Some(handle) = self.loop_handle.take() {
+                        handle.abort();
+                    }
+                }
+                if newly_stalled {
+                    let _ = self.app_handle.emit("strategy_loop_stalled", &serde_json::json!({
+                        "timestamp": now,
+                        "last_heartbeat": self.watchdog.last_heartbeat(),
+                        "silent_seconds": silent_seconds,
+                        "restarts": self.watchdog.restarts(),
+                        "restarting": restart
+                    }));
+                    self.log(
+                        LogLevel::Warning,
+                        "watchdog",
+                        &format!("No strategy loop heartbeat for {}s", silent_seconds),
+                        None,
+                        None,
+                        None,
+                    ).await;
+                }
+                if restart {
+                    self.restart(now, format!("No heartbeat for {}s", silent_seconds)).await;
+                }
+            }
+            LivenessCheck::Idle | LivenessCheck::Healthy => {}
+        }
+        check
+    }
+
+    async fn restart(&mut self, now: i64, reason: String) {
+        self.watchdog.record_restart(now);
+        let attempt = self.watchdog.restarts();
+        let error = self.launch().await.err();
+
+        {
+            let mut state = self.state.lock().await;
+            state.restart_count += 1;
+            state.restarts.push(LoopRestart { timestamp: now, attempt, reason: reason.clone(), error: error.clone() });
+            if error.is_some() {
+                state.running = false;
+            }
+        }
+
+        let (level, message) = match &error {
+            None => (LogLevel::Warning, format!("Restarted strategy loop (attempt {}): {}", attempt, reason)),
+            Some(e) => (LogLevel::Error, format!("Strategy loop restart {} failed: {}", attempt, e)),
+        };
+        self.log(level, "watchdog", &message, None, None, None).await;
+    }
+
+    /// `spec` on `symbol`'s `timeframe` bars as the signals read it, or on 1-minute bars
+    /// downsampled to it with `downsampled`. One no signal reads is computed over the
+    /// bars held now and kept up to date from then on.
+    pub async fn indicator_series(
+        &self,
+        symbol: &str,
+        timeframe: Timeframe,
+        downsampled: bool,
+        spec: IndicatorSpec,
+        range: (Option<i64>, Option<i64>),
+    ) -> Result<IndicatorSeries, String> {
+        spec.validate()?;
+        let mut builder = self.bar_builder.lock().await;
+        let bars = match downsampled {
+            true => analytics::downsample_bars(&builder.bars(symbol, Timeframe::OneMinute), (timeframe.seconds() / 60) as u32),
+            false => builder.bars(symbol, timeframe),
+        };
+        let key = indicator_series_key(timeframe, downsampled);
+        if bars.is_empty() {
+            return Err(format!("No {} bars for {}", key, symbol));
+        }
+        let indicators = builder.indicators_mut();
+        indicators.resolve(symbol, &key, &bars, &[spec]);
+        indicators
+            .series(symbol, &key, spec, range.0, range.1)
+            .ok_or_else(|| format!("No {} bars for {}", key, symbol))
+    }
+
+    /// 1-minute bars from the bar cache aggregated to `timeframe_minutes`
+    pub async fn get_bars_at_timeframe(&self, symbol: &str, timeframe_minutes: u32) -> Vec<Candle> {
+        let builder = self.bar_builder.lock().await;
+        analytics::downsample_bars(&builder.bars(symbol, Timeframe::OneMinute), timeframe_minutes)
+    }
+
+    pub async fn get_dead_letter_queue(&self) -> Vec<DeadLetterEntry> {
+        self.state.lock().await.dead_letter_queue.clone()
+    }
+
+    pub async fn clear_dead_letter_queue(&self) -> u32 {
+        self.state.lock().await.clear_dead_letter_queue()
+    }
+
+    /// Lift a quarantine or suspension so the symbol is evaluated on the next cycle
+    pub async fn resume_symbol(&self, symbol: &str) -> Result<(), String> {
+        self.state.lock().await.resume_symbol(symbol)
+    }
+
+    /// Journaled decisions made on sessions `from` through `to`, joined with their outcomes
+    /// or how much of their position is still open
+    pub async fn get_decision_outcomes(&self, from: NaiveDate, to: NaiveDate, symbol: Option<&str>) -> Result<Vec<DecisionReport>, String> {
+        let records = DecisionJournal::open(&self.app_handle)?.load()?;
+        let broker = self.broker.lock().await;
+        let reports = settle_decisions(&records, &broker.trades, &broker.get_applied_splits(), &broker.daily_summaries);
+        Ok(filter_decisions(reports, from, to, symbol))
     }
 
     pub async fn get_config(&self) -> StrategyLoopConfig {
         self.config.clone()
     }
 
-    pub async fn update_config(&mut self, config: StrategyLoopConfig) -> Result<(), String> {
+    pub async fn update_config(&mut self, mut config: StrategyLoopConfig) -> Result<(), String> {
         if self.loop_handle.is_some() {
             return Err("Cannot update config while loop is running".to_string());
         }
+        config.normalize_watchlist()?;
+        let imported = self.watchlists.import_loop_config(&config)?;
+        if !imported.is_empty() {
+            self.log(LogLevel::Info, "watchlist", &format!("Added {} to the {} watchlist", imported.join(", "), super::watchlist::DEFAULT_WATCHLIST), None, None, None).await;
+        }
+        if self.config.dry_run && !config.dry_run {
+            // Only confirm_live_promotion turns dry run off
+            config.dry_run = true;
+            self.log(LogLevel::Warning, "promotion", "Config update left dry_run on; use request_live_promotion to go live", None, None, None).await;
+        }
+        self.bar_builder.lock().await.set_timeframes(config.builder_timeframes());
         self.config = config;
         Ok(())
     }
 
+    /// Run the live promotion checklist against the shadow ledger and `facts`. When it is
+    /// ready the checklist carries a token for confirm_live_promotion.
+    pub async fn request_live_promotion(&mut self, facts: PromotionFacts, waivers: &BTreeMap<PromotionCheck, String>) -> Result<PromotionChecklist, String> {
+        if !self.config.dry_run {
+            return Err("The strategy loop is already placing orders".to_string());
+        }
+        let checklist = {
+            let state = self.state.lock().await;
+            promotion::run_checklist(&self.config.promotion, &state.shadow, &facts, waivers, Utc::now().timestamp())?
+        };
+        self.pending_promotion = Some(checklist.clone());
+        Ok(checklist)
+    }
+
+    /// Turn dry run off with the token of a ready checklist. The running loop restarts to
+    /// pick it up; the promotion and its checklist are journaled, the shadow ledger starts
+    /// over and "strategy_promotion" is emitted.
+    pub async fn confirm_live_promotion(&mut self, token: &str) -> Result<PromotionRecord, String> {
+        if !self.config.dry_run {
+            return Err("The strategy loop is already placing orders".to_string());
+        }
+        let record = promotion::confirm(self.pending_promotion.as_ref(), token, Utc::now().timestamp())?;
+        self.pending_promotion = None;
+        self.switch_mode(false).await?;
+        self.state.lock().await.shadow = ShadowLedger::default();
+        let _ = FileCache::new(&self.app_handle).and_then(|mut cache| cache.set(SHADOW_LEDGER_KEY, ShadowLedger::default(), None));
+        let waived = record.checklist.iter().flat_map(|c| &c.items).filter(|i| i.waiver.is_some()).count();
+        self.journal_promotion(&record, &format!("Promoted to live trading ({} checklist items waived)", waived)).await?;
+        Ok(record)
+    }
+
+    /// Back to dry run at once, journaled with `reason`
+    pub async fn demote_to_dry_run(&mut self, reason: &str) -> Result<PromotionRecord, String> {
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err("A reason is required to demote to dry run".to_string());
+        }
+        if self.config.dry_run {
+            return Err("The strategy loop is already in dry run".to_string());
+        }
+        self.switch_mode(true).await?;
+        self.pending_promotion = None;
+        let record = PromotionRecord {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now().timestamp(),
+            action: PromotionAction::Demoted,
+            reason: Some(reason.to_string()),
+            checklist: None,
+        };
+        self.journal_promotion(&record, &format!("Demoted to dry run: {}", reason)).await?;
+        Ok(record)
+    }
+
+    /// Promotions and demotions, oldest first
+    pub fn promotion_history(&self) -> Result<Vec<PromotionRecord>, String> {
+        FileCache::new(&self.app_handle)?.get(PROMOTION_LOG_KEY).map(Option::unwrap_or_default)
+    }
+
+    /// Set dry_run, restarting a running loop so its task sees the change
+    async fn switch_mode(&mut self, dry_run: bool) -> Result<(), String> {
+        let running = self.loop_handle.is_some();
+        self.stop().await?;
+        self.config.dry_run = dry_run;
+        if running {
+            self.launch().await?;
+        }
+        Ok(())
+    }
+
+    async fn journal_promotion(&self, record: &PromotionRecord, message: &str) -> Result<(), String> {
+        let mut log = self.promotion_history()?;
+        promotion::append(&mut log, record.clone());
+        FileCache::new(&self.app_handle)?.set(PROMOTION_LOG_KEY, log, None)?;
+        let _ = self.app_handle.emit("strategy_promotion", record);
+        self.log(LogLevel::Warning, "promotion", message, serde_json::to_value(record).ok(), None, None).await;
+        Ok(())
+    }
+
+    /// Stop the loop if it is running and switch to `config`, logging the field changes
+    /// under `source` (a preset name). Returns the changes.
+    pub async fn apply_config(&mut self, config: StrategyLoopConfig, source: &str) -> Result<Vec<ConfigChange>, String> {
+        let before = serde_json::to_value(&self.config).map_err(|e| e.to_string())?;
+        self.stop().await?;
+        self.update_config(config).await?;
+        let after = serde_json::to_value(&self.config).map_err(|e| e.to_string())?;
+        let changes = config_bundle::diff_section(config_bundle::SECTION_STRATEGY_LOOP, &before, &after).changes;
+        self.log(
+            LogLevel::Info,
+            "config",
+            &format!("Applied strategy preset {} ({} changes)", source, changes.len()),
+            Some(serde_json::json!({ "preset": source, "changes": changes })),
+            None,
+            None,
+        ).await;
+        Ok(changes)
+    }
+
     pub async fn reset_state(&mut self) -> Result<(), String> {
         if self.loop_handle.is_some() {
             return Err("Cannot reset state while loop is running".to_string());
@@ -665,7 +2756,709 @@ impl StrategyLoop {
         state.execution_count = 0;
         state.error_count = 0;
         state.last_error = None;
+        state.retry_queue.clear();
+        state.first_failed_at.clear();
+        state.symbol_health.clear();
+        state.restart_count = 0;
+        state.restarts.clear();
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_state() -> LoopState {
+        LoopState {
+            running: false,
+            last_execution: 0,
+            processed_bars: HashSet::new(),
+            signal_cooldowns: HashMap::new(),
+            execution_count: 0,
+            error_count: 0,
+            last_error: None,
+            retry_queue: VecDeque::new(),
+            dead_letter_queue: Vec::new(),
+            first_failed_at: HashMap::new(),
+            symbol_health: HashMap::new(),
+            last_expiry_check_date: None,
+            last_expiry_alert_date: None,
+            last_heartbeat: 0,
+            healthy: true,
+            restart_count: 0,
+            restarts: Vec::new(),
+            shadow: ShadowLedger::default(),
+        }
+    }
+
+    #[test]
+    fn test_failed_bar_is_retried_until_dead_lettered() {
+        let config = StrategyLoopConfig::default();
+        let mut state = empty_state();
+        let bar_key = "AAPL:1704207600";
+        let timeout = BarError::new(ErrorClass::Data, "timeout");
+
+        // First failure and the retries below max_retries stay in the retry queue
+        let mut attempts = 1;
+        assert!(state.record_failure("AAPL", bar_key, &timeout, attempts, &config, 1000).is_none());
+        for now in [1300, 1600] {
+            let retries = state.take_retries();
+            assert_eq!(retries, vec![("AAPL".to_string(), bar_key.to_string(), attempts)]);
+            attempts += 1;
+            let entry = state.record_failure("AAPL", bar_key, &timeout, attempts, &config, now);
+            if attempts < config.max_retries {
+                assert!(entry.is_none());
+            } else {
+                let entry = entry.unwrap();
+                assert_eq!(entry.attempts, 3);
+                assert_eq!(entry.first_failed_at, 1000);
+                assert_eq!(entry.error_class, ErrorClass::Data);
+            }
+        }
+
+        assert!(state.retry_queue.is_empty());
+        assert_eq!(state.dead_letter_queue.len(), 1);
+        assert_eq!(state.error_count, 3);
+        assert!(state.first_failed_at.is_empty());
+
+        assert_eq!(state.clear_dead_letter_queue(), 1);
+        assert!(state.dead_letter_queue.is_empty());
+    }
+
+    #[test]
+    fn test_retry_success_and_disabled_retries() {
+        let mut config = StrategyLoopConfig::default();
+        let mut state = empty_state();
+        let no_data = BarError::new(ErrorClass::Data, "no data");
+
+        state.record_failure("MSFT", "MSFT:300", &no_data, 1, &config, 500);
+        let retries = state.take_retries();
+        assert_eq!(retries.len(), 1);
+        state.record_retry_success(&retries[0].1);
+        assert!(state.first_failed_at.is_empty());
+        assert!(state.dead_letter_queue.is_empty());
+
+        // With retries disabled, errors are only logged
+        config.retry_on_error = false;
+        assert!(state.record_failure("MSFT", "MSFT:600", &no_data, 1, &config, 800).is_none());
+        assert!(state.retry_queue.is_empty());
+        assert!(state.dead_letter_queue.is_empty());
+        assert_eq!(state.last_error.as_deref(), Some("no data"));
+    }
+
+    #[test]
+    fn test_symbol_quarantine_escalation() {
+        let config = StrategyLoopConfig {
+            quarantine_after_errors: 2,
+            quarantine_minutes: 10,
+            max_quarantines: 2,
+            ..StrategyLoopConfig::default()
+        };
+        let mut state = empty_state();
+        let order_error = BarError::new(ErrorClass::OrderPlacement, "risk limit");
+
+        assert!(state.record_symbol_error("AAPL", &order_error, &config, 0).is_none());
+        assert_eq!(state.symbol_health["AAPL"].status(0), SymbolStatus::Erroring);
+        assert_eq!(state.record_symbol_error("AAPL", &order_error, &config, 60), Some(SymbolStatus::Quarantined));
+        assert!(state.is_symbol_blocked("AAPL", 60 + 599));
+        assert!(!state.is_symbol_blocked("MSFT", 60));
+
+        // Cool-off elapsed: the symbol is evaluated on probation and one success lifts it
+        assert!(!state.is_symbol_blocked("AAPL", 660));
+        assert_eq!(state.symbol_health["AAPL"].status(660), SymbolStatus::Probation);
+        assert!(state.record_symbol_success("AAPL"));
+        assert_eq!(state.symbol_health["AAPL"].status(700), SymbolStatus::Healthy);
+        assert!(!state.record_symbol_success("AAPL"));
+
+        // A second quarantine hits max_quarantines and needs a manual resume
+        state.record_symbol_error("AAPL", &order_error, &config, 1000);
+        assert_eq!(state.record_symbol_error("AAPL", &order_error, &config, 1060), Some(SymbolStatus::Suspended));
+        assert!(state.is_symbol_blocked("AAPL", 1_000_000));
+        assert_eq!(state.symbol_health["AAPL"].last_error_class, Some(ErrorClass::OrderPlacement));
+
+        state.resume_symbol("AAPL").unwrap();
+        assert!(!state.is_symbol_blocked("AAPL", 1060));
+        assert!(state.resume_symbol("AAPL").is_err());
+    }
+
+    #[test]
+    fn test_failure_on_probation_requarantines() {
+        let config = StrategyLoopConfig {
+            quarantine_after_errors: 3,
+            quarantine_minutes: 5,
+            max_quarantines: 3,
+            ..StrategyLoopConfig::default()
+        };
+        let mut state = empty_state();
+        let data_error = BarError::new(ErrorClass::Data, "no bars");
+
+        for now in 0..3 {
+            state.record_symbol_error("SPY", &data_error, &config, now);
+        }
+        assert_eq!(state.symbol_health["SPY"].quarantine_count, 1);
+
+        // One more failure after the cool-off goes straight back to quarantine
+        assert_eq!(state.record_symbol_error("SPY", &data_error, &config, 400), Some(SymbolStatus::Quarantined));
+        assert_eq!(state.symbol_health["SPY"].quarantined_until, Some(700));
+        assert_eq!(state.symbol_health["SPY"].quarantine_count, 2);
+    }
+
+    fn signal(name: &str, timeframe: Timeframe, direction: SignalDirection, weight: f64) -> SignalResult {
+        SignalResult {
+            name: name.to_string(),
+            direction,
+            confidence: 0.8,
+            metadata: HashMap::new(),
+            timeframe,
+            weight,
+        }
+    }
+
+    #[test]
+    fn test_composite_signal_weights_components() {
+        let signals = vec![
+            signal("SMA_Crossover", Timeframe::FiveMinute, SignalDirection::Long, 1.0),
+            signal("RSI", Timeframe::FiveMinute, SignalDirection::Short, 1.0),
+            signal("Trend", Timeframe::OneDay, SignalDirection::Long, 1.0),
+        ];
+        let composite = CompositeSignal {
+            signals: vec![("SMA_Crossover".into(), 2.0), ("RSI".into(), 1.0), ("Trend".into(), 1.0)],
+            threshold: 0.3,
+        };
+
+        // (2 * 0.8 - 0.8 + 0.8) / 4 = 0.4
+        let result = compute_composite_signal(&signals, &composite);
+        assert_eq!(result.name, "Composite(SMA_Crossover+RSI+Trend)");
+        assert_eq!(result.direction, SignalDirection::Long);
+        assert!((result.confidence - 0.4).abs() < 1e-9);
+        assert_eq!(result.timeframe, Timeframe::FiveMinute);
+
+        // Doubling the short leg's weight pulls the score to 0.8 / 5 = 0.16
+        let composite = CompositeSignal {
+            signals: vec![("SMA_Crossover".into(), 2.0), ("RSI".into(), 2.0), ("Trend".into(), 1.0)],
+            threshold: 0.3,
+        };
+        let result = compute_composite_signal(&signals, &composite);
+        assert_eq!(result.direction, SignalDirection::Neutral);
+        assert!((result.metadata["score"].as_f64().unwrap() - 0.16).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_composite_signal_labels_and_missing_components() {
+        let signals = vec![
+            signal("RSI", Timeframe::FiveMinute, SignalDirection::Short, 1.0),
+            signal("Trend", Timeframe::OneDay, SignalDirection::Short, 1.0),
+        ];
+        let composite = CompositeSignal {
+            signals: vec![("RSI@5m".into(), 1.0), ("Trend".into(), 1.0)],
+            threshold: 0.5,
+        };
+        let result = compute_composite_signal(&signals, &composite);
+        assert_eq!(result.direction, SignalDirection::Short);
+        assert!((result.confidence - 0.8).abs() < 1e-9);
+
+        // A missing component dilutes the score but not the confidence of what is present
+        let composite = CompositeSignal {
+            signals: vec![("RSI".into(), 1.0), ("MACD".into(), 3.0)],
+            threshold: 0.3,
+        };
+        let result = compute_composite_signal(&signals, &composite);
+        assert_eq!(result.direction, SignalDirection::Neutral);
+        assert!((result.metadata["score"].as_f64().unwrap() + 0.2).abs() < 1e-9);
+        assert!((result.confidence - 0.8).abs() < 1e-9);
+
+        let empty = compute_composite_signal(&[], &composite);
+        assert_eq!((empty.direction, empty.confidence), (SignalDirection::Neutral, 0.0));
+    }
+
+    #[test]
+    fn test_all_must_agree_requires_every_timeframe() {
+        let rule = CombinationRule::AllMustAgree;
+
+        // 5-minute entry confirmed by the daily trend
+        let signals = vec![
+            signal("SMA_Crossover", Timeframe::FiveMinute, SignalDirection::Long, 1.0),
+            signal("Trend", Timeframe::OneDay, SignalDirection::Long, 1.5),
+        ];
+        let (direction, _, contributing) = StrategyLoop::combine_signals(&signals, &rule);
+        assert_eq!(direction, SignalDirection::Long);
+        assert_eq!(contributing, vec!["SMA_Crossover@5m", "Trend@1d"]);
+
+        // Daily trend disagrees
+        let signals = vec![
+            signal("SMA_Crossover", Timeframe::FiveMinute, SignalDirection::Long, 1.0),
+            signal("Trend", Timeframe::OneDay, SignalDirection::Short, 1.5),
+        ];
+        let (direction, _, _) = StrategyLoop::combine_signals(&signals, &rule);
+        assert_eq!(direction, SignalDirection::Neutral);
+
+        // Daily timeframe only neutral, so the entry is unconfirmed
+        let signals = vec![
+            signal("SMA_Crossover", Timeframe::FiveMinute, SignalDirection::Long, 1.0),
+            signal("Trend", Timeframe::OneDay, SignalDirection::Neutral, 1.5),
+        ];
+        let (direction, _, _) = StrategyLoop::combine_signals(&signals, &rule);
+        assert_eq!(direction, SignalDirection::Neutral);
+    }
+
+    #[test]
+    fn test_weighted_vote() {
+        let rule = CombinationRule::WeightedVote { threshold: 0.2 };
+        let signals = vec![
+            signal("SMA_Crossover", Timeframe::FiveMinute, SignalDirection::Short, 1.0),
+            signal("Trend", Timeframe::OneDay, SignalDirection::Long, 2.0),
+        ];
+
+        // (2.0 * 0.8 - 1.0 * 0.8) / 3.0 = 0.267
+        let (direction, confidence, contributing) = StrategyLoop::combine_signals(&signals, &rule);
+        assert_eq!(direction, SignalDirection::Long);
+        assert!((confidence - 0.8 / 3.0).abs() < 1e-9);
+        assert_eq!(contributing, vec!["Trend@1d"]);
+
+        let strict = CombinationRule::WeightedVote { threshold: 0.5 };
+        let (direction, _, _) = StrategyLoop::combine_signals(&signals, &strict);
+        assert_eq!(direction, SignalDirection::Neutral);
+    }
+
+    #[test]
+    fn test_signals_record_timeframe_and_need_history() {
+        let config = StrategyLoopConfig::default();
+        let trend = config.signals.iter().find(|s| s.name == "Trend").unwrap();
+        let bars: Vec<Candle> = (0..trend.lookback)
+            .map(|i| {
+                let price = 100.0 + i as f64;
+                Candle::new(i as i64 * 86400, price, price, price, price, 1000).with_symbol("AAPL")
+            })
+            .collect();
+
+        let result = StrategyLoop::compute_signal(trend, &bars).unwrap();
+        assert_eq!(result.direction, SignalDirection::Long);
+        assert_eq!(result.timeframe, Timeframe::OneDay);
+        assert_eq!(result.metadata["timeframe"], "1d");
+
+        assert!(StrategyLoop::compute_signal(trend, &bars[1..]).is_none());
+    }
+
+    #[test]
+    fn test_signals_share_cached_indicators() {
+        let signal = |name: &str, lookback: usize| SignalConfig { name: name.to_string(), timeframe: Timeframe::OneDay, lookback, weight: 1.0 };
+        let signals = [signal("SMA_Crossover", 20), signal("Trend", 20), signal("RSI", 15)];
+        let bars: Vec<Candle> = (0..120)
+            .map(|i| {
+                let price = 100.0 + (i as f64 * 0.3).sin() * 4.0;
+                Candle::new(i as i64 * 86400, price, price + 1.0, price - 1.0, price, 1000).with_symbol("AAPL")
+            })
+            .collect();
+
+        // Advanced bar by bar, the cache gives what a fresh computation gives
+        let mut indicators = IndicatorCache::default();
+        for end in 1..=bars.len() {
+            for signal in &signals {
+                let cached = StrategyLoop::compute_signal_cached(signal, &bars[..end], &mut indicators, "AAPL", "1d");
+                let fresh = StrategyLoop::compute_signal(signal, &bars[..end]);
+                assert_eq!(cached.map(|r| (r.direction, r.metadata)), fresh.map(|r| (r.direction, r.metadata)));
+            }
+        }
+        // SMA(5), SMA(20) and RSI(14): the crossover and trend signals share SMA(20)
+        assert_eq!(indicators.tracked("AAPL", "1d"), 3);
+    }
+
+    #[test]
+    fn test_macd_on_downsampled_hourly_bars() {
+        // 40 hours of steadily rising 1-minute bars
+        let minute_bars: Vec<Candle> = (0..2400)
+            .map(|i| {
+                let price = 100.0 + i as f64 * 0.01;
+                Candle::new(1704207600 + i as i64 * 60, price, price, price, price, 10).with_symbol("AAPL")
+            })
+            .collect();
+        let hourly = analytics::downsample_bars(&minute_bars, 60);
+        assert_eq!(hourly.len(), 40);
+
+        let macd = SignalConfig {
+            name: "MACD".to_string(),
+            timeframe: Timeframe::OneHour,
+            lookback: default_lookback("MACD"),
+            weight: 1.0,
+        };
+        let result = StrategyLoop::compute_signal(&macd, &hourly).unwrap();
+        assert_eq!(result.direction, SignalDirection::Long);
+        assert_eq!(result.label(), "MACD@1h");
+    }
+
+    #[test]
+    fn test_warmup_covers_largest_lookback() {
+        let mut config = StrategyLoopConfig::default();
+        assert_eq!(config.required_timeframes(), vec![Timeframe::FiveMinute, Timeframe::OneDay]);
+
+        // 20 daily bars -> 20 sessions -> 28 calendar days + buffer
+        assert_eq!(config.history_days(Timeframe::OneDay), 31);
+        assert_eq!(config.warmup_days(), 31);
+
+        config.signals.push(SignalConfig {
+            name: "Trend".to_string(),
+            timeframe: Timeframe::OneDay,
+            lookback: 50,
+            weight: 1.0,
+        });
+        assert_eq!(config.warmup_days(), 73);
+    }
+
+    fn daily_bars(closes: &[f64], range_pct: f64, volume: u64, last_volume: u64) -> Vec<Candle> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, close)| {
+                let volume = if i == closes.len() - 1 { last_volume } else { volume };
+                Candle::new(i as i64 * 86400, *close, close * (1.0 + range_pct / 100.0), *close, *close, volume)
+                    .with_symbol("AAPL")
+            })
+            .collect()
+    }
+
+    fn long_and_short() -> Vec<SignalResult> {
+        vec![
+            signal("SMA_Crossover", Timeframe::FiveMinute, SignalDirection::Long, 1.0),
+            signal("RSI", Timeframe::FiveMinute, SignalDirection::Short, 1.0),
+        ]
+    }
+
+    #[test]
+    fn test_regime_filter_daily_range() {
+        let filter = MarketRegimeFilter { enabled: true, min_adr_pct: 1.0, max_adr_pct: 5.0, min_volume_ratio: 0.0, require_trend: false };
+
+        let (kept, filtered) = StrategyLoop::apply_regime_filter(long_and_short(), &daily_bars(&[100.0; 21], 2.0, 1000, 1000), &filter);
+        assert_eq!(kept.len(), 2);
+        assert!(filtered.is_empty());
+
+        let (kept, filtered) = StrategyLoop::apply_regime_filter(long_and_short(), &daily_bars(&[100.0; 21], 0.5, 1000, 1000), &filter);
+        assert!(kept.is_empty());
+        assert!(filtered[0].reason.contains("below"));
+
+        let (kept, filtered) = StrategyLoop::apply_regime_filter(long_and_short(), &daily_bars(&[100.0; 21], 8.0, 1000, 1000), &filter);
+        assert!(kept.is_empty());
+        assert!(filtered[0].reason.contains("above"));
+
+        // Disabled filter passes everything through
+        let disabled = MarketRegimeFilter { enabled: false, ..filter };
+        let (kept, _) = StrategyLoop::apply_regime_filter(long_and_short(), &[], &disabled);
+        assert_eq!(kept.len(), 2);
+    }
+
+    #[test]
+    fn test_regime_filter_volume_ratio() {
+        let filter = MarketRegimeFilter { enabled: true, min_adr_pct: 0.0, max_adr_pct: 100.0, min_volume_ratio: 0.8, require_trend: false };
+
+        // 700 / 1000 = 0.7
+        let (kept, filtered) = StrategyLoop::apply_regime_filter(long_and_short(), &daily_bars(&[100.0; 21], 2.0, 1000, 700), &filter);
+        assert!(kept.is_empty());
+        assert_eq!(filtered[0].signal, "*");
+        assert!(filtered[0].reason.contains("volume ratio 0.70"));
+
+        let (kept, _) = StrategyLoop::apply_regime_filter(long_and_short(), &daily_bars(&[100.0; 21], 2.0, 1000, 900), &filter);
+        assert_eq!(kept.len(), 2);
+    }
+
+    #[test]
+    fn test_regime_filter_trend_direction() {
+        let filter = MarketRegimeFilter { enabled: true, min_adr_pct: 0.0, max_adr_pct: 100.0, min_volume_ratio: 0.0, require_trend: true };
+        let rising: Vec<f64> = (0..250).map(|i| 100.0 + i as f64).collect();
+        let falling: Vec<f64> = rising.iter().rev().cloned().collect();
+
+        let (kept, filtered) = StrategyLoop::apply_regime_filter(long_and_short(), &daily_bars(&rising, 2.0, 1000, 1000), &filter);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].direction, SignalDirection::Long);
+        assert_eq!(filtered[0].signal, "RSI@5m");
+        assert!(filtered[0].reason.contains("uptrend"));
+
+        let (kept, _) = StrategyLoop::apply_regime_filter(long_and_short(), &daily_bars(&falling, 2.0, 1000, 1000), &filter);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].direction, SignalDirection::Short);
+
+        // Not enough history for the 200 EMA
+        let (kept, _) = StrategyLoop::apply_regime_filter(long_and_short(), &daily_bars(&rising[..100], 2.0, 1000, 1000), &filter);
+        assert!(kept.is_empty());
+
+        // Enabling the filter adds daily bars to the warm-up
+        let config = StrategyLoopConfig { regime_filter: filter, signals: Vec::new(), ..StrategyLoopConfig::default() };
+        assert_eq!(config.required_timeframes(), vec![Timeframe::OneDay]);
+        assert_eq!(config.history_days(Timeframe::OneDay), 283);
+    }
+
+    #[test]
+    fn test_pair_is_opened_and_closed_as_one_entry() {
+        use crate::engine::pairs::{PairSizing, SpreadDefinition};
+
+        let pair = PairConfig {
+            first: "xle".into(),
+            second: "xop".into(),
+            spread: SpreadDefinition::Ratio,
+            lookback: 10,
+            entry_z: 2.0,
+            exit_z: 0.0,
+            sizing: PairSizing::DollarNeutral,
+            leg_notional: 10_000.0,
+        };
+        let mut config = StrategyLoopConfig { pairs: vec![pair.clone(), pair.clone()], ..Default::default() };
+        assert!(config.normalize_watchlist().unwrap_err().contains("XLE/XOP"));
+        config.pairs.pop();
+        config.normalize_watchlist().unwrap();
+        let pair = config.pairs[0].clone();
+        assert!(config.is_pair_leg("XOP") && config.required_timeframes().contains(&Timeframe::OneDay));
+        assert!(config.history_days(Timeframe::OneDay) >= 10);
+
+        // XLE/XOP at 2.0 +/- 0.01, the forming bar dipping to 1.9
+        let closes = |last: f64| -> Vec<AlignedClose> {
+            (0..12)
+                .map(|i| {
+                    let ratio = if i == 11 { last } else if i % 2 == 0 { 2.01 } else { 1.99 };
+                    AlignedClose { date: chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap() + chrono::Duration::days(i), first: 50.0 * ratio, second: 50.0 }
+                })
+                .collect()
+        };
+        let position = |symbol: &str, quantity: i64| {
+            let mut position = Position::new(symbol.to_string());
+            position.quantity = quantity;
+            (symbol.to_string(), position)
+        };
+        let flat = HashMap::new();
+
+        let (decision, signal) = StrategyLoop::make_pair_decision(&pair, &closes(1.9), &flat, [95.0, 50.0], true);
+        assert_eq!(decision.action, DecisionAction::Buy);
+        let legs: Vec<(&str, &OrderSide, i64)> = decision.orders.iter().map(|o| (o.symbol.as_str(), &o.side, o.quantity)).collect();
+        assert_eq!(legs, vec![("XLE", &OrderSide::Buy, 105), ("XOP", &OrderSide::Sell, 199)]);
+        assert_eq!(signal.unwrap().direction, SignalDirection::Long);
+
+        // Without short selling neither leg is placed
+        let (decision, _) = StrategyLoop::make_pair_decision(&pair, &closes(1.9), &flat, [95.0, 50.0], false);
+        assert_eq!((decision.action, decision.orders.len()), (DecisionAction::Skip, 0));
+
+        // Reverted: both legs are closed together
+        let held: HashMap<String, Position> = [position("XLE", 105), position("XOP", -199)].into_iter().collect();
+        let (decision, _) = StrategyLoop::make_pair_decision(&pair, &closes(2.01), &held, [100.5, 50.0], true);
+        assert_eq!(decision.action, DecisionAction::Close);
+        let legs: Vec<(&str, &OrderSide, i64)> = decision.orders.iter().map(|o| (o.symbol.as_str(), &o.side, o.quantity)).collect();
+        assert_eq!(legs, vec![("XLE", &OrderSide::Sell, 105), ("XOP", &OrderSide::Buy, 199)]);
+        let (decision, _) = StrategyLoop::make_pair_decision(&pair, &closes(1.9), &held, [95.0, 50.0], true);
+        assert_eq!((decision.action, decision.orders.len()), (DecisionAction::Hold, 0));
+
+        // One leg alone is never traded by the pair
+        let legged: HashMap<String, Position> = [position("XLE", 105)].into_iter().collect();
+        let (decision, _) = StrategyLoop::make_pair_decision(&pair, &closes(2.01), &legged, [100.5, 50.0], true);
+        assert_eq!(decision.action, DecisionAction::Skip);
+        assert!(decision.orders.is_empty() && !decision.risk_assessment.approved);
+    }
+
+    #[test]
+    fn test_expiry_check_runs_once_after_close() {
+        let mut state = empty_state();
+        let session = NaiveDate::from_ymd_opt(2024, 1, 19).unwrap();
+        let at = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+
+        assert!(!state.expiry_check_due(session, at(15, 55), false)); // Before the close
+        assert!(!state.expiry_check_due(session, at(16, 5), true));   // Extended session still open
+        assert!(state.expiry_check_due(session, at(16, 5), false));
+
+        state.last_expiry_check_date = Some(session);
+        assert!(!state.expiry_check_due(session, at(17, 0), false));
+        assert!(state.expiry_check_due(session.succ_opt().unwrap(), at(16, 0), false));
+    }
+
+    #[tokio::test]
+    async fn test_watchdog_detects_stalled_cycle_and_restarts_once() {
+        let policy = WatchdogConfig { max_restarts: 1, ..WatchdogConfig::default() };
+        let cadence = 5;
+        let start = 1704207600;
+        let mut watchdog = LoopWatchdog::default();
+        watchdog.reset(start);
+
+        // A cycle that beats once and then hangs
+        let heartbeat = watchdog.heartbeat();
+        let stalled = tokio::spawn(async move {
+            heartbeat.store(start, Ordering::Relaxed);
+            sleep(Duration::from_secs(3600)).await;
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(watchdog.check(&policy, cadence, stalled.is_finished(), start + 300), LivenessCheck::Healthy);
+
+        let late = start + 300 + 61;
+        assert_eq!(
+            watchdog.check(&policy, cadence, stalled.is_finished(), late),
+            LivenessCheck::Stalled { silent_seconds: 361, newly_stalled: true, restart: true }
+        );
+        stalled.abort();
+        watchdog.record_restart(late);
+
+        // The restarted loop beats again
+        let heartbeat = watchdog.heartbeat();
+        let restarted = tokio::spawn(async move {
+            heartbeat.store(late + 1, Ordering::Relaxed);
+            sleep(Duration::from_secs(3600)).await;
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(watchdog.check(&policy, cadence, restarted.is_finished(), late + 60), LivenessCheck::Recovered);
+        assert!(!watchdog.is_stalled());
+
+        // A second stall is reported but the restart budget is spent
+        assert_eq!(
+            watchdog.check(&policy, cadence, restarted.is_finished(), late + 1000),
+            LivenessCheck::Stalled { silent_seconds: 999, newly_stalled: true, restart: false }
+        );
+        assert_eq!(watchdog.restarts(), 1);
+        restarted.abort();
+
+        // A task that died counts as stalled even with a fresh heartbeat
+        let mut watchdog = LoopWatchdog::default();
+        watchdog.reset(start);
+        assert!(matches!(
+            watchdog.check(&policy, cadence, true, start + 1),
+            LivenessCheck::Stalled { restart: true, .. }
+        ));
+    }
+
+    #[test]
+    fn test_news_halt_blocks_entries_but_not_exits() {
+        let decision = |action: DecisionAction| StrategyDecision {
+            action,
+            reason: "signal".to_string(),
+            orders: vec![OrderRequest {
+                symbol: "ACME".to_string(),
+                side: OrderSide::Buy,
+                order_type: OrderType::Market,
+                quantity: 10,
+                price: None,
+                stop_price: None,
+                time_in_force: TimeInForce::Day,
+                client_order_id: None,
+                instrument_type: InstrumentType::Stock,
+                option_details: None,
+                preferred_venue: VenueType::Smart,
+                expire_at: None,
+                auto_round: false,
+                override_token: None,
+                allocation: None,
+                residual_policy: None,
+            }],
+            risk_assessment: RiskAssessment {
+                position_size: 0.0,
+                risk_per_trade: 0.0,
+                portfolio_heat: 0.0,
+                max_drawdown_risk: 0.0,
+                approved: true,
+                warnings: Vec::new(),
+            },
+            contributing_signals: Vec::new(),
+        };
+        let halt = NewsHalt {
+            symbol: "ACME".to_string(),
+            reason: "Headline matched fraud".to_string(),
+            triggered_at: 1_704_812_400,
+            expires_at: 1_704_826_800,
+            sentiment: None,
+            matched_keywords: vec!["fraud".to_string()],
+            articles: Vec::new(),
+            expired: false,
+        };
+
+        let blocked = StrategyLoop::block_entries_for_news(decision(DecisionAction::Buy), std::slice::from_ref(&halt));
+        assert_eq!(blocked.action, DecisionAction::Skip);
+        assert!(blocked.orders.is_empty());
+        assert!(blocked.reason.contains("fraud"), "{}", blocked.reason);
+        assert_eq!(StrategyLoop::block_entries_for_news(decision(DecisionAction::Sell), std::slice::from_ref(&halt)).action, DecisionAction::Skip);
+
+        let exit = StrategyLoop::block_entries_for_news(decision(DecisionAction::Close), std::slice::from_ref(&halt));
+        assert_eq!((exit.action, exit.orders.len()), (DecisionAction::Close, 1));
+        assert_eq!(StrategyLoop::block_entries_for_news(decision(DecisionAction::Buy), &[]).orders.len(), 1);
+    }
+
+    #[test]
+    fn test_repeated_risk_rejections_back_off_entries() {
+        let mut broker = PaperBroker::new(100_000.0);
+        broker.risk_engine.limits.max_trade_size = 1_000.0;
+        let order = |side: OrderSide| OrderRequest {
+            symbol: "ACME".to_string(),
+            side,
+            order_type: OrderType::Limit,
+            quantity: 100,
+            price: Some(50.0),
+            stop_price: None,
+            time_in_force: TimeInForce::GTC,
+            client_order_id: None,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            preferred_venue: VenueType::Smart,
+            expire_at: None,
+            auto_round: false,
+            override_token: None,
+            allocation: None,
+            residual_policy: None,
+        };
+        for _ in 0..2 {
+            assert!(broker.place_evaluated_order(order(OrderSide::Buy), "eval").unwrap_err().starts_with("Risk check needs confirmation"));
+        }
+        assert!(broker.place_order(order(OrderSide::Buy)).is_err()); // Manual orders do not count
+        assert_eq!(broker.risk_rejections_today("ACME", RejectionSource::Strategy), 2);
+        let logged = broker.recent_rejections.back().unwrap();
+        assert_eq!((logged.source, logged.requested_notional), (RejectionSource::Manual, 5_000.0));
+        assert!(logged.risk_check.as_ref().is_some_and(|c| !c.allowed));
+
+        let decision = |action: DecisionAction| StrategyDecision {
+            action,
+            reason: "signal".to_string(),
+            orders: vec![order(OrderSide::Buy)],
+            risk_assessment: RiskAssessment {
+                position_size: 0.0,
+                risk_per_trade: 0.0,
+                portfolio_heat: 0.0,
+                max_drawdown_risk: 0.0,
+                approved: true,
+                warnings: Vec::new(),
+            },
+            contributing_signals: Vec::new(),
+        };
+        let backoff = RejectionBackoff::default();
+        let count = broker.risk_rejections_today("ACME", RejectionSource::Strategy);
+        assert_eq!(StrategyLoop::back_off_after_rejections(decision(DecisionAction::Buy), count, &backoff).action, DecisionAction::Buy);
+
+        assert!(broker.place_evaluated_order(order(OrderSide::Buy), "eval").is_err());
+        let count = broker.risk_rejections_today("ACME", RejectionSource::Strategy);
+        let skipped = StrategyLoop::back_off_after_rejections(decision(DecisionAction::Buy), count, &backoff);
+        assert_eq!((skipped.action, skipped.orders.len()), (DecisionAction::Skip, 0));
+        assert!(skipped.reason.starts_with("repeated rejections"), "{}", skipped.reason);
+        assert_eq!(StrategyLoop::back_off_after_rejections(decision(DecisionAction::Close), count, &backoff).action, DecisionAction::Close);
+        let disabled = RejectionBackoff { enabled: false, ..backoff };
+        assert_eq!(StrategyLoop::back_off_after_rejections(decision(DecisionAction::Sell), count, &disabled).action, DecisionAction::Sell);
+        assert_eq!(broker.risk_rejections_today("XYZ", RejectionSource::Strategy), 0);
+    }
+
+    #[test]
+    fn test_clearing_the_trade_flag_stops_evaluation_immediately() {
+        use crate::engine::watchlist::{SymbolFlags, Watchlists, DEFAULT_WATCHLIST};
+
+        let mut lists = Watchlists::default();
+        lists.add(DEFAULT_WATCHLIST, "AAPL", SymbolFlags::default()).unwrap();
+        lists.add(DEFAULT_WATCHLIST, "XLE", SymbolFlags::default()).unwrap();
+        let watchlists: SharedWatchlists = Arc::new(std::sync::Mutex::new(lists));
+        let config = serde_json::from_value::<PairConfig>(serde_json::json!({"first": "XLE", "second": "XOP"}))
+            .map(|pair| StrategyLoopConfig { pairs: vec![pair], ..Default::default() })
+            .unwrap();
+
+        assert!(StrategyLoop::is_traded("AAPL", &config, &watchlists));
+        assert!(!StrategyLoop::is_traded("XLE", &config, &watchlists), "pair legs trade with their pair");
+        assert!(!StrategyLoop::is_traded("MSFT", &config, &watchlists), "not on a list");
+
+        // The loop task holds the same lists the commands change
+        let task_view = watchlists.clone();
+        let untraded = SymbolFlags { trade: false, ..SymbolFlags::default() };
+        watchlists.lock().unwrap().set_flags(DEFAULT_WATCHLIST, "AAPL", untraded).unwrap();
+        assert!(!StrategyLoop::is_traded("AAPL", &config, &task_view));
+    }
+
+    #[test]
+    fn test_parse_bar_key() {
+        assert_eq!(StrategyLoop::parse_bar_key("AAPL:1704207600"), Some(1704207600));
+        assert_eq!(StrategyLoop::parse_bar_key("AAPL"), None);
+    }
+}
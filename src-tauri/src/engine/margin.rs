@@ -0,0 +1,373 @@
+// src-tauri/src/engine/margin.rs
+// Buying power and margin requirements for cash and Reg-T margin accounts. A cash
+// account buys with its cash and nothing else. A margin account carries an initial and
+// a maintenance requirement per position: a share of stock value, short options at the
+// standard 20%-of-underlying rule, verticals at their maximum loss and calls covered by
+// held shares at nothing beyond the stock's own requirement. Premiums and option values
+// are taken as the broker books them (market_value); underlying exposure is per share
+// times the contract multiplier.
+
+use super::mtm::{is_option_symbol, parse_option_symbol};
+use super::types::{OptionType, Position};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountType {
+    #[default]
+    Cash,
+    RegT,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MarginRates {
+    pub stock_initial_pct: f64,           // Of stock value, long or short
+    pub long_stock_maintenance_pct: f64,
+    pub short_stock_maintenance_pct: f64,
+    pub short_option_underlying_pct: f64, // Of the underlying, less the out-of-the-money amount
+    pub short_option_minimum_pct: f64,    // Of the underlying for calls, of the strike for puts
+}
+
+impl Default for MarginRates {
+    fn default() -> Self {
+        Self {
+            stock_initial_pct: 0.50,
+            long_stock_maintenance_pct: 0.25,
+            short_stock_maintenance_pct: 0.30,
+            short_option_underlying_pct: 0.20,
+            short_option_minimum_pct: 0.10,
+        }
+    }
+}
+
+impl MarginRates {
+    pub fn validate(&self) -> Result<(), String> {
+        let rates = [
+            ("stock_initial_pct", self.stock_initial_pct),
+            ("long_stock_maintenance_pct", self.long_stock_maintenance_pct),
+            ("short_stock_maintenance_pct", self.short_stock_maintenance_pct),
+            ("short_option_underlying_pct", self.short_option_underlying_pct),
+            ("short_option_minimum_pct", self.short_option_minimum_pct),
+        ];
+        for (name, rate) in rates {
+            if !(rate > 0.0 && rate <= 1.0) {
+                return Err(format!("{} must be in (0, 1], got {}", name, rate));
+            }
+        }
+        if self.long_stock_maintenance_pct > self.stock_initial_pct || self.short_stock_maintenance_pct > self.stock_initial_pct {
+            return Err("Maintenance rates cannot exceed the initial rate".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequirementKind {
+    LongStock,
+    ShortStock,
+    LongOption,  // Paid in full
+    NakedCall,
+    NakedPut,
+    CoveredCall, // Nothing beyond the shares' own requirement
+    Spread,      // Short and long leg of one vertical
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PositionRequirement {
+    pub kind: RequirementKind,
+    pub legs: Vec<String>, // Short leg first for spreads and covered calls
+    pub quantity: i64,     // Shares, or contracts
+    pub initial: f64,
+    pub maintenance: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MarginSummary {
+    pub account_type: AccountType,
+    pub equity: f64,
+    pub buying_power: f64,            // Stock that could still be bought
+    pub margin_used: f64,             // Initial requirement of the open positions
+    pub maintenance_requirement: f64,
+    pub maintenance_excess: f64,      // Equity over the maintenance requirement; negative in a call
+    pub requirements: Vec<PositionRequirement>,
+}
+
+/// An unmet maintenance requirement; risk-increasing orders are refused until it clears
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MarginCall {
+    pub since: i64,
+    pub deficit: f64, // Maintenance requirement less equity at the last check
+    pub last_notified: i64,
+}
+
+/// `account_type`'s figures for `positions`. `underlying_price` prices option
+/// underlyings; contracts whose underlying has no price are taken at the strike.
+pub fn summarize(
+    account_type: AccountType,
+    rates: &MarginRates,
+    cash: f64,
+    positions: &HashMap<String, Position>,
+    underlying_price: &dyn Fn(&str) -> Option<f64>,
+) -> MarginSummary {
+    let equity = cash + positions.values().map(|p| p.market_value).sum::<f64>();
+    if account_type == AccountType::Cash {
+        return MarginSummary {
+            account_type,
+            equity,
+            buying_power: cash.max(0.0),
+            margin_used: 0.0,
+            maintenance_requirement: 0.0,
+            maintenance_excess: equity,
+            requirements: Vec::new(),
+        };
+    }
+
+    let requirements = requirements(rates, positions, underlying_price);
+    let margin_used: f64 = requirements.iter().map(|r| r.initial).sum();
+    let maintenance_requirement: f64 = requirements.iter().map(|r| r.maintenance).sum();
+    MarginSummary {
+        account_type,
+        equity,
+        buying_power: ((equity - margin_used) / rates.stock_initial_pct).max(0.0),
+        margin_used,
+        maintenance_requirement,
+        maintenance_excess: equity - maintenance_requirement,
+        requirements,
+    }
+}
+
+struct Contract {
+    symbol: String,
+    option_type: OptionType,
+    strike: f64,
+    multiplier: f64,
+    premium: f64, // Booked value of one contract, >= 0
+}
+
+type LongsAndShorts = (Vec<(Contract, i64)>, Vec<(Contract, i64)>); // With the contracts still unpaired
+
+/// Reg-T requirements of every open position
+pub fn requirements(
+    rates: &MarginRates,
+    positions: &HashMap<String, Position>,
+    underlying_price: &dyn Fn(&str) -> Option<f64>,
+) -> Vec<PositionRequirement> {
+    let mut requirements = Vec::new();
+    let mut shares: BTreeMap<String, i64> = BTreeMap::new();
+    // Longs and shorts by (underlying, expiry, type)
+    let mut groups: BTreeMap<(String, chrono::NaiveDate, String), LongsAndShorts> = BTreeMap::new();
+
+    for (symbol, position) in positions.iter().filter(|(_, p)| p.quantity != 0) {
+        let details = parse_option_symbol(symbol).filter(|_| is_option_symbol(symbol));
+        let Some(details) = details else {
+            shares.insert(symbol.clone(), position.quantity);
+            let value = position.market_value.abs();
+            let (kind, maintenance_pct) = if position.quantity > 0 {
+                (RequirementKind::LongStock, rates.long_stock_maintenance_pct)
+            } else {
+                (RequirementKind::ShortStock, rates.short_stock_maintenance_pct)
+            };
+            requirements.push(PositionRequirement {
+                kind,
+                legs: vec![symbol.clone()],
+                quantity: position.quantity.abs(),
+                initial: value * rates.stock_initial_pct,
+                maintenance: value * maintenance_pct,
+            });
+            continue;
+        };
+        let contract = Contract {
+            symbol: symbol.clone(),
+            option_type: details.option_type.clone(),
+            strike: details.strike,
+            multiplier: details.multiplier as f64,
+            premium: position.market_value.abs() / position.quantity.abs() as f64,
+        };
+        let group = groups.entry((details.underlying, details.expiry, format!("{:?}", details.option_type))).or_default();
+        if position.quantity > 0 {
+            group.0.push((contract, position.quantity));
+        } else {
+            group.1.push((contract, -position.quantity));
+        }
+    }
+
+    for ((underlying, _, _), (mut longs, mut shorts)) in groups {
+        // Pair each short with the long that protects it best: the lowest call strike
+        // or the highest put strike
+        longs.sort_by(|a, b| a.0.strike.total_cmp(&b.0.strike));
+        shorts.sort_by(|a, b| a.0.strike.total_cmp(&b.0.strike));
+        if longs.first().is_some_and(|(c, _)| c.option_type == OptionType::Put) {
+            longs.reverse();
+        }
+        for (short, short_open) in shorts.iter_mut() {
+            for (long, long_open) in longs.iter_mut() {
+                let paired = (*short_open).min(*long_open);
+                if paired == 0 {
+                    continue;
+                }
+                *short_open -= paired;
+                *long_open -= paired;
+                // Most the pair can lose at expiry; a debit spread costs only its value
+                let width = match short.option_type {
+                    OptionType::Call => long.strike - short.strike,
+                    OptionType::Put => short.strike - long.strike,
+                };
+                let requirement = if width > 0.0 {
+                    width * short.multiplier * paired as f64
+                } else {
+                    ((long.premium - short.premium) * paired as f64).max(0.0)
+                };
+                requirements.push(PositionRequirement {
+                    kind: RequirementKind::Spread,
+                    legs: vec![short.symbol.clone(), long.symbol.clone()],
+                    quantity: paired,
+                    initial: requirement,
+                    maintenance: requirement,
+                });
+            }
+        }
+
+        let price = underlying_price(&underlying);
+        for (short, open) in shorts.into_iter().filter(|(_, open)| *open > 0) {
+            let mut naked = open;
+            if short.option_type == OptionType::Call {
+                // Calls are covered by shares not already covering another call
+                let held = shares.entry(underlying.clone()).or_insert(0);
+                let covered = naked.min((*held).max(0) / short.multiplier as i64);
+                if covered > 0 {
+                    *held -= covered * short.multiplier as i64;
+                    naked -= covered;
+                    requirements.push(PositionRequirement {
+                        kind: RequirementKind::CoveredCall,
+                        legs: vec![short.symbol.clone(), underlying.clone()],
+                        quantity: covered,
+                        initial: 0.0,
+                        maintenance: 0.0,
+                    });
+                }
+            }
+            if naked > 0 {
+                let requirement = naked_requirement(rates, &short, price.unwrap_or(short.strike)) * naked as f64;
+                let kind = if short.option_type == OptionType::Call { RequirementKind::NakedCall } else { RequirementKind::NakedPut };
+                requirements.push(PositionRequirement {
+                    kind,
+                    legs: vec![short.symbol.clone()],
+                    quantity: naked,
+                    initial: requirement,
+                    maintenance: requirement,
+                });
+            }
+        }
+        for (long, open) in longs.into_iter().filter(|(_, open)| *open > 0) {
+            let value = long.premium * open as f64;
+            requirements.push(PositionRequirement {
+                kind: RequirementKind::LongOption,
+                legs: vec![long.symbol],
+                quantity: open,
+                initial: value,
+                maintenance: value,
+            });
+        }
+    }
+
+    requirements.sort_by(|a, b| a.legs.cmp(&b.legs));
+    requirements
+}
+
+/// Premium plus the larger of 20% of the underlying less the amount out of the money
+/// and the minimum, per contract
+fn naked_requirement(rates: &MarginRates, contract: &Contract, underlying: f64) -> f64 {
+    let (out_of_the_money, minimum_base) = match contract.option_type {
+        OptionType::Call => ((contract.strike - underlying).max(0.0), underlying),
+        OptionType::Put => ((underlying - contract.strike).max(0.0), contract.strike),
+    };
+    let per_share = (rates.short_option_underlying_pct * underlying - out_of_the_money)
+        .max(rates.short_option_minimum_pct * minimum_base);
+    contract.premium + per_share * contract.multiplier
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(symbol: &str, quantity: i64, price: f64) -> Position {
+        let mut position = Position::new(symbol.to_string());
+        position.quantity = quantity;
+        position.avg_cost = price;
+        position.update_market_data(price);
+        position
+    }
+
+    fn book(positions: &[(&str, i64, f64)]) -> HashMap<String, Position> {
+        positions.iter().map(|(s, q, p)| (s.to_string(), position(s, *q, *p))).collect()
+    }
+
+    fn priced(symbol: &str) -> Option<f64> {
+        (symbol == "XYZ").then_some(50.0)
+    }
+
+    #[test]
+    fn test_requirements_by_position_kind() {
+        let rates = MarginRates::default();
+
+        // Covered call: the call adds nothing to the stock's own requirement
+        let covered = book(&[("XYZ", 200, 50.0), ("XYZ240621C00055000", -2, 1.5)]);
+        let summary = summarize(AccountType::RegT, &rates, 10_000.0, &covered, &priced);
+        let stock_only = summarize(AccountType::RegT, &rates, 10_000.0, &book(&[("XYZ", 200, 50.0)]), &priced);
+        assert_eq!(summary.margin_used, stock_only.margin_used);
+        assert_eq!(summary.margin_used, 5_000.0);
+        assert_eq!(summary.maintenance_requirement, 2_500.0);
+        let call = summary.requirements.iter().find(|r| r.kind == RequirementKind::CoveredCall).unwrap();
+        assert_eq!((call.quantity, call.initial), (2, 0.0));
+
+        // One contract covered, one naked: 20% of $50 less $5 out of the money, also the 10% minimum
+        let half = book(&[("XYZ", 100, 50.0), ("XYZ240621C00055000", -2, 1.5)]);
+        let naked_call = summarize(AccountType::RegT, &rates, 10_000.0, &half, &priced)
+            .requirements
+            .into_iter()
+            .find(|r| r.kind == RequirementKind::NakedCall)
+            .unwrap();
+        assert_eq!(naked_call.quantity, 1);
+        assert!((naked_call.initial - (1.5 + 5.0 * 100.0)).abs() < 1e-9);
+
+        // Naked put 45 strike, $5 OTM: max(0.2 * 50 - 5, 0.1 * 45) = 5 per share, plus premium
+        let naked_put = book(&[("XYZ240621P00045000", -3, 0.8)]);
+        let summary = summarize(AccountType::RegT, &rates, 10_000.0, &naked_put, &priced);
+        assert_eq!(summary.requirements.len(), 1);
+        assert_eq!(summary.requirements[0].kind, RequirementKind::NakedPut);
+        assert!((summary.margin_used - 3.0 * (0.8 + 500.0)).abs() < 1e-9);
+        // In the money at 55: 20% of 50 is the larger
+        let itm_put = book(&[("XYZ240621P00055000", -1, 5.5)]);
+        let summary = summarize(AccountType::RegT, &rates, 10_000.0, &itm_put, &priced);
+        assert!((summary.margin_used - (5.5 + 1_000.0)).abs() < 1e-9);
+
+        // Put credit spread 50/45: the requirement is its maximum loss
+        let spread = book(&[("XYZ240621P00050000", -4, 2.0), ("XYZ240621P00045000", 4, 0.7)]);
+        let summary = summarize(AccountType::RegT, &rates, 10_000.0, &spread, &priced);
+        assert_eq!(summary.requirements.len(), 1);
+        let vertical = &summary.requirements[0];
+        assert_eq!(vertical.kind, RequirementKind::Spread);
+        assert_eq!(vertical.legs, vec!["XYZ240621P00050000", "XYZ240621P00045000"]);
+        assert_eq!(vertical.quantity, 4);
+        assert!((vertical.initial - 5.0 * 100.0 * 4.0).abs() < 1e-9);
+        assert_eq!(vertical.maintenance, vertical.initial);
+
+        // A debit spread only holds its value; a leftover long is paid in full
+        let debit = book(&[("XYZ240621C00050000", 3, 2.5), ("XYZ240621C00055000", -2, 1.0)]);
+        let summary = summarize(AccountType::RegT, &rates, 10_000.0, &debit, &priced);
+        let vertical = summary.requirements.iter().find(|r| r.kind == RequirementKind::Spread).unwrap();
+        assert!((vertical.initial - 2.0 * (2.5 - 1.0)).abs() < 1e-9);
+        let long = summary.requirements.iter().find(|r| r.kind == RequirementKind::LongOption).unwrap();
+        assert_eq!(long.quantity, 1);
+        assert!((long.initial - 2.5).abs() < 1e-9);
+
+        // Short stock and cash accounts
+        let short = summarize(AccountType::RegT, &rates, 20_000.0, &book(&[("XYZ", -100, 50.0)]), &priced);
+        assert_eq!((short.margin_used, short.maintenance_requirement), (2_500.0, 1_500.0));
+        assert_eq!(short.buying_power, (15_000.0 - 2_500.0) / 0.5);
+        let cash = summarize(AccountType::Cash, &rates, 20_000.0, &book(&[("XYZ", 100, 50.0)]), &priced);
+        assert_eq!((cash.buying_power, cash.margin_used, cash.maintenance_excess), (20_000.0, 0.0, 25_000.0));
+    }
+}
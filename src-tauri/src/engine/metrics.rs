@@ -0,0 +1,253 @@
+// src-tauri/src/engine/metrics.rs
+// Performance metrics over equity series
+
+use serde::{Deserialize, Serialize};
+
+pub const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+/// Which equity a drawdown (or rolling statistic) is measured on
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawdownBasis {
+    #[default]
+    Total,    // Cash plus open positions at their marks
+    Realized, // Cash plus open positions at cost, so only closed trades move it
+}
+
+/// Drawdown series and deepest drawdown on both bases
+#[derive(Debug, Clone, PartialEq)]
+pub struct DualDrawdown {
+    pub total: Vec<f64>,
+    pub max_total: f64,
+    pub realized: Vec<f64>,
+    pub max_realized: f64,
+}
+
+/// Drawdowns of total equity and of realized-only equity sampled at the same points
+pub fn dual_drawdown(total: &[f64], realized: &[f64]) -> DualDrawdown {
+    let (total, max_total) = calc_drawdown_series(total);
+    let (realized, max_realized) = calc_drawdown_series(realized);
+    DualDrawdown { total, max_total, realized, max_realized }
+}
+
+/// Drawdown from the running peak at each point (<= 0) and the deepest drawdown
+pub fn calc_drawdown_series(eqs: &[f64]) -> (Vec<f64>, f64) {
+    let mut max_run = if eqs.is_empty() { 0.0 } else { eqs[0] };
+    let mut dds = Vec::with_capacity(eqs.len());
+    let mut min_dd = 0.0;
+    for &e in eqs {
+        if e > max_run {
+            max_run = e;
+        }
+        let dd = if max_run > 0.0 { (e - max_run) / max_run } else { 0.0 };
+        if dd < min_dd {
+            min_dd = dd;
+        }
+        dds.push(dd);
+    }
+    (dds, min_dd)
+}
+
+/// Deepest drawdown (<= 0) of a series of (open, high, low) buckets. A bucket's open
+/// is its first value and its high and low fall somewhere after it, so each low is
+/// measured from the highest point before the bucket or its open. Peaks and troughs
+/// inside one bucket are not seen; see `ohlc_drawdown_bound`.
+pub fn ohlc_max_drawdown(buckets: &[(f64, f64, f64)]) -> f64 {
+    let mut peak = buckets.first().map_or(0.0, |b| b.0);
+    let mut min_dd: f64 = 0.0;
+    for &(open, high, low) in buckets {
+        peak = peak.max(open);
+        if peak > 0.0 {
+            min_dd = min_dd.min((low - peak) / peak);
+        }
+        peak = peak.max(high);
+    }
+    min_dd
+}
+
+/// How much deeper the drawdown of the values inside `buckets` can be than
+/// `ohlc_max_drawdown` of the buckets: the widest (high - low) / high of any bucket
+pub fn ohlc_drawdown_bound(buckets: &[(f64, f64, f64)]) -> f64 {
+    buckets
+        .iter()
+        .filter(|b| b.1 > 0.0)
+        .map(|&(_, high, low)| (high - low) / high)
+        .fold(0.0, f64::max)
+}
+
+/// Annualized volatility of (timestamp, value) points spaced however they happen to be:
+/// squared log returns summed over the calendar time they cover, so a stretch counts by
+/// its length and not by how many points it was sampled at
+pub fn time_weighted_volatility(points: &[(i64, f64)]) -> f64 {
+    let (mut variance, mut seconds) = (0.0, 0.0);
+    for w in points.windows(2) {
+        let ((t0, v0), (t1, v1)) = (w[0], w[1]);
+        if t1 > t0 && v0 > 0.0 && v1 > 0.0 {
+            variance += (v1 / v0).ln().powi(2);
+            seconds += (t1 - t0) as f64;
+        }
+    }
+    if seconds > 0.0 {
+        (variance / seconds * 365.25 * 86_400.0).sqrt()
+    } else {
+        0.0
+    }
+}
+
+/// Compound annual growth over `days` calendar days
+pub fn annualized_cagr(first: f64, last: f64, days: usize) -> f64 {
+    if first <= 0.0 || last <= 0.0 || days == 0 {
+        return 0.0;
+    }
+    let years = (days as f64) / 365.25;
+    if years <= 0.0 {
+        0.0
+    } else {
+        (last / first).powf(1.0 / years) - 1.0
+    }
+}
+
+pub fn period_returns(equity_curve: &[f64]) -> Vec<f64> {
+    equity_curve
+        .windows(2)
+        .map(|w| if w[0] != 0.0 { w[1] / w[0] - 1.0 } else { w[1] - w[0] })
+        .collect()
+}
+
+fn mean(xs: &[f64]) -> f64 {
+    if xs.is_empty() {
+        0.0
+    } else {
+        xs.iter().sum::<f64>() / xs.len() as f64
+    }
+}
+
+/// Annualized Sharpe of daily returns at a zero risk-free rate; 0.0 without volatility
+pub fn sharpe_ratio(eqs: &[f64]) -> f64 {
+    let returns = period_returns(eqs);
+    if returns.len() < 2 {
+        return 0.0;
+    }
+    let m = mean(&returns);
+    let std = (returns.iter().map(|r| (r - m).powi(2)).sum::<f64>() / (returns.len() - 1) as f64).sqrt();
+    if std > 1e-12 {
+        m / std * TRADING_DAYS_PER_YEAR.sqrt()
+    } else {
+        0.0
+    }
+}
+
+/// Beta of `strategy`'s period returns against `benchmark`'s (covariance over benchmark
+/// variance) and the correlation of the two. Series are aligned from the start; either
+/// is 0.0 when undefined.
+pub fn beta_and_correlation(strategy: &[f64], benchmark: &[f64]) -> (f64, f64) {
+    let n = strategy.len().min(benchmark.len());
+    let (s, b) = (period_returns(&strategy[..n]), period_returns(&benchmark[..n]));
+    if s.len() < 2 {
+        return (0.0, 0.0);
+    }
+
+    let (mean_s, mean_b) = (mean(&s), mean(&b));
+    let (mut covariance, mut var_s, mut var_b) = (0.0, 0.0, 0.0);
+    for (rs, rb) in s.iter().zip(&b) {
+        covariance += (rs - mean_s) * (rb - mean_b);
+        var_s += (rs - mean_s).powi(2);
+        var_b += (rb - mean_b).powi(2);
+    }
+
+    let beta = if var_b > 0.0 { covariance / var_b } else { 0.0 };
+    let correlation = if var_s > 0.0 && var_b > 0.0 { covariance / (var_s * var_b).sqrt() } else { 0.0 };
+    (beta, correlation)
+}
+
+/// Pearson correlation of two equally long samples; 0.0 when either has no variance
+pub fn pearson_correlation(xs: &[f64], ys: &[f64]) -> f64 {
+    let n = xs.len().min(ys.len());
+    if n < 2 {
+        return 0.0;
+    }
+    let (mean_x, mean_y) = (mean(&xs[..n]), mean(&ys[..n]));
+    let (mut covariance, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in xs[..n].iter().zip(&ys[..n]) {
+        covariance += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+    if var_x > 0.0 && var_y > 0.0 {
+        covariance / (var_x * var_y).sqrt()
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drawdown_and_cagr() {
+        let (dds, max_dd) = calc_drawdown_series(&[100.0, 120.0, 90.0, 130.0]);
+        assert_eq!(dds, vec![0.0, 0.0, -0.25, 0.0]);
+        assert_eq!(max_dd, -0.25);
+        assert_eq!(calc_drawdown_series(&[]), (vec![], 0.0));
+
+        assert!((annualized_cagr(100.0, 110.0, 365) - 0.1).abs() < 1e-3);
+        assert_eq!(annualized_cagr(0.0, 110.0, 365), 0.0);
+        assert_eq!(annualized_cagr(100.0, 110.0, 0), 0.0);
+    }
+
+    #[test]
+    fn test_realized_drawdown_can_hide_behind_open_gains() {
+        // A 30,000 realized loss while an open position gains 28,000: total equity dips
+        // 2% but the closed trades alone are down 30%
+        let total = [100_000.0, 100_000.0, 98_000.0, 99_000.0];
+        let realized = [100_000.0, 100_000.0, 70_000.0, 70_000.0];
+        let drawdowns = dual_drawdown(&total, &realized);
+        assert!((drawdowns.max_total + 0.02).abs() < 1e-12);
+        assert!((drawdowns.max_realized + 0.30).abs() < 1e-12);
+        assert_eq!(drawdowns.realized, vec![0.0, 0.0, -0.3, -0.3]);
+    }
+
+    fn compound(start: f64, returns: &[f64]) -> Vec<f64> {
+        returns.iter().fold(vec![start], |mut curve, r| {
+            curve.push(curve.last().unwrap() * (1.0 + r));
+            curve
+        })
+    }
+
+    #[test]
+    fn test_time_weighted_volatility_ignores_sampling_density() {
+        // A 10% jump over a day, then nine flat days sampled daily or every 15 minutes
+        let jump = [(0, 100.0), (86_400, 110.0)];
+        let daily: Vec<(i64, f64)> = jump.into_iter().chain((2..=10).map(|d| (d * 86_400, 110.0))).collect();
+        let dense: Vec<(i64, f64)> = jump.into_iter().chain((97..=960).map(|q| (q * 900, 110.0))).collect();
+        let vol = time_weighted_volatility(&daily);
+        assert!(vol > 0.0);
+        assert!((time_weighted_volatility(&dense) - vol).abs() < 1e-12);
+        assert!((vol - (1.1f64.ln().powi(2) / 10.0 * 365.25).sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_beta_and_correlation_with_known_returns() {
+        let market = [0.01, -0.02, 0.015, 0.005, -0.01, 0.02];
+        let benchmark = compound(100.0, &market);
+
+        let levered = compound(100.0, &market.map(|r| 2.0 * r));
+        let (beta, correlation) = beta_and_correlation(&levered, &benchmark);
+        assert!((beta - 2.0).abs() < 1e-12 && (correlation - 1.0).abs() < 1e-12);
+
+        let inverse = compound(100.0, &market.map(|r| 0.001 - 0.5 * r));
+        let (beta, correlation) = beta_and_correlation(&inverse, &benchmark);
+        assert!((beta + 0.5).abs() < 1e-12 && (correlation + 1.0).abs() < 1e-12);
+
+        // Returns uncorrelated with the market: +1%/-1% against a symmetric pattern
+        let market = [0.01, 0.01, -0.01, -0.01];
+        let other = [0.01, -0.01, 0.01, -0.01];
+        let (beta, correlation) = beta_and_correlation(&compound(1.0, &other), &compound(1.0, &market));
+        assert!(beta.abs() < 1e-3 && correlation.abs() < 1e-3);
+
+        assert_eq!(beta_and_correlation(&[100.0, 101.0, 102.0], &[50.0, 50.0, 50.0]), (0.0, 0.0));
+        assert_eq!(sharpe_ratio(&compound(100.0, &[0.001; 10])), 0.0);
+        assert!(sharpe_ratio(&levered) > 0.0);
+    }
+}
@@ -2,11 +2,13 @@
 // Mark-to-market engine with Greeks calculation
 
 use super::types::*;
+use super::option_symbol::OptionSymbolParser;
+use super::rates::{Carry, DividendYields, RateCurve};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use chrono::{DateTime, Utc, NaiveDate};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PortfolioGreeks {
     pub delta: f64,      // Portfolio delta (price sensitivity)
     pub gamma: f64,      // Portfolio gamma (delta sensitivity)
@@ -42,9 +44,21 @@ pub struct MtMSnapshot {
     pub position_greeks: Vec<PositionGreeks>,
 }
 
+/// Odds and payoff of buying one option, per share at expiry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionProbabilities {
+    pub prob_itm: f64,
+    pub prob_otm: f64,
+    pub expected_value: f64, // Expected payoff at expiry
+    pub breakeven: f64,      // Underlying price where the payoff covers the premium
+    pub max_profit: Option<f64>, // None when unlimited (calls)
+    pub max_loss: Option<f64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct MtMEngine {
-    pub risk_free_rate: f64,
+    pub rate_curve: RateCurve,           // Risk-free rate by time to expiry
+    pub dividend_yields: DividendYields, // Carry of each underlying
     pub default_volatility: f64,
     pub volatility_cache: HashMap<String, f64>,
 }
@@ -52,7 +66,8 @@ pub struct MtMEngine {
 impl Default for MtMEngine {
     fn default() -> Self {
         Self {
-            risk_free_rate: 0.05,      // 5% risk-free rate
+            rate_curve: RateCurve::default(), // Flat 5%
+            dividend_yields: DividendYields::default(),
             default_volatility: 0.25,  // 25% default volatility
             volatility_cache: HashMap::new(),
         }
@@ -65,10 +80,18 @@ impl MtMEngine {
     }
 
     pub fn with_risk_free_rate(mut self, rate: f64) -> Self {
-        self.risk_free_rate = rate;
+        self.rate_curve = RateCurve::flat(rate);
         self
     }
 
+    /// Rate and dividend yield for an option on `underlying` at `spot`, `tte` years from expiry
+    pub fn carry_for(&self, underlying: &str, spot: f64, tte: f64) -> Carry {
+        Carry {
+            rate: self.rate_curve.rate_at(tte),
+            dividend_yield: self.dividend_yields.yield_for(underlying, spot, Utc::now().date_naive()),
+        }
+    }
+
     pub fn calculate_portfolio_mtm(
         &self,
         positions: &HashMap<String, Position>,
@@ -165,7 +188,7 @@ impl MtMEngine {
         }
     }
 
-    fn get_mid_price(&self, market_data: &MarketData) -> f64 {
+    pub fn get_mid_price(&self, market_data: &MarketData) -> f64 {
         match (market_data.bid, market_data.ask) {
             (Some(bid), Some(ask)) => (bid + ask) / 2.0,
             (Some(bid), None) => bid,
@@ -175,76 +198,21 @@ impl MtMEngine {
     }
 
     fn is_option_symbol(&self, symbol: &str) -> bool {
-        // Simple heuristic: options symbols typically contain expiry dates
-        // Format: AAPL240315C00150000 (AAPL, March 15 2024, Call, $150 strike)
-        symbol.len() > 10 && (symbol.contains('C') || symbol.contains('P'))
+        is_option_symbol(symbol)
     }
 
     fn parse_option_symbol(&self, symbol: &str) -> Option<OptionDetails> {
-        // Parse option symbol format: AAPL240315C00150000
-        // This is a simplified parser - in production you'd use a more robust parser
-        if symbol.len() < 15 {
-            return None;
-        }
-
-        // Find the underlying symbol (everything before the date)
-        let mut underlying_end = 0;
-        for (i, c) in symbol.chars().enumerate() {
-            if c.is_ascii_digit() {
-                underlying_end = i;
-                break;
-            }
-        }
-
-        if underlying_end == 0 {
-            return None;
-        }
-
-        let underlying = symbol[..underlying_end].to_string();
-        let rest = &symbol[underlying_end..];
-
-        if rest.len() < 15 {
-            return None;
-        }
-
-        // Parse date (YYMMDD format)
-        let year_str = &rest[0..2];
-        let month_str = &rest[2..4];
-        let day_str = &rest[4..6];
-
-        // Parse option type (C or P)
-        let option_type_char = rest.chars().nth(6)?;
-        let option_type = match option_type_char {
-            'C' => OptionType::Call,
-            'P' => OptionType::Put,
-            _ => return None,
-        };
-
-        // Parse strike price (8 digits, last 3 are decimals)
-        let strike_str = &rest[7..15];
-        let strike = strike_str.parse::<i64>().ok()? as f64 / 1000.0;
-
-        // Format expiry date as MM/DD/YYYY
-        let year = format!("20{}", year_str);
-        let expiry = format!("{}/{}/{}", month_str, day_str, year);
-
-        Some(OptionDetails {
-            underlying,
-            option_type,
-            strike,
-            expiry,
-            multiplier: 100,
-        })
+        parse_option_symbol(symbol)
     }
 
-    fn calculate_option_greeks(
+    pub fn calculate_option_greeks(
         &self,
         option_details: &OptionDetails,
         underlying_price: f64,
         quantity: i64,
     ) -> PositionGreeks {
         // Get time to expiration in years
-        let tte = self.calculate_time_to_expiry(&option_details.expiry);
+        let tte = self.calculate_time_to_expiry(option_details.expiry);
         
         // Get volatility (use cached or default)
         let volatility = self.volatility_cache
@@ -252,12 +220,12 @@ impl MtMEngine {
             .copied()
             .unwrap_or(self.default_volatility);
 
-        // Calculate Black-Scholes Greeks
+        // Calculate Black-Scholes Greeks at the rate and yield for this expiry
         let greeks = self.black_scholes_greeks(
             underlying_price,
             option_details.strike,
             tte,
-            self.risk_free_rate,
+            self.carry_for(&option_details.underlying, underlying_price, tte),
             volatility,
             &option_details.option_type,
         );
@@ -278,22 +246,7 @@ impl MtMEngine {
         }
     }
 
-    fn calculate_time_to_expiry(&self, expiry: &str) -> f64 {
-        // Parse MM/DD/YYYY format
-        let parts: Vec<&str> = expiry.split('/').collect();
-        if parts.len() != 3 {
-            return 0.0;
-        }
-
-        let month = parts[0].parse::<u32>().unwrap_or(1);
-        let day = parts[1].parse::<u32>().unwrap_or(1);
-        let year = parts[2].parse::<i32>().unwrap_or(2024);
-
-        let expiry_date = match NaiveDate::from_ymd_opt(year, month, day) {
-            Some(date) => date,
-            None => return 0.0,
-        };
-
+    pub fn calculate_time_to_expiry(&self, expiry_date: NaiveDate) -> f64 {
         let now = Utc::now().date_naive();
         let days_to_expiry = (expiry_date - now).num_days();
         
@@ -306,42 +259,45 @@ impl MtMEngine {
         s: f64,    // Underlying price
         k: f64,    // Strike price
         t: f64,    // Time to expiry (years)
-        r: f64,    // Risk-free rate
+        carry: Carry, // Risk-free rate and continuous dividend yield
         v: f64,    // Volatility
         option_type: &OptionType,
     ) -> (f64, f64, f64, f64, f64) {
-        if t <= 0.0 {
+        // No quote, strike or volatility to price from gives no sensitivity rather than NaN
+        if !(t > 0.0 && s > 0.0 && k > 0.0 && v > 0.0) || ![s, k, t, v].iter().all(|x| x.is_finite()) {
             return (0.0, 0.0, 0.0, 0.0, 0.0);
         }
+        let (r, q) = (carry.rate, carry.dividend_yield);
 
         let sqrt_t = t.sqrt();
-        let d1 = (s.ln() - k.ln() + (r + 0.5 * v * v) * t) / (v * sqrt_t);
+        let d1 = (s.ln() - k.ln() + (r - q + 0.5 * v * v) * t) / (v * sqrt_t);
         let d2 = d1 - v * sqrt_t;
 
-        let n_d1 = self.normal_cdf(d1);
-        let n_d2 = self.normal_cdf(d2);
+        let n_d1 = normal_cdf(d1);
+        let n_d2 = normal_cdf(d2);
         let n_prime_d1 = self.normal_pdf(d1);
+        let held = (-q * t).exp(); // The stock's dividends are not the option holder's
 
         let (delta, rho) = match option_type {
             OptionType::Call => {
-                let delta = n_d1;
+                let delta = held * n_d1;
                 let rho = k * t * (-r * t).exp() * n_d2;
                 (delta, rho)
             }
             OptionType::Put => {
-                let delta = n_d1 - 1.0;
+                let delta = held * (n_d1 - 1.0);
                 let rho = -k * t * (-r * t).exp() * (1.0 - n_d2);
                 (delta, rho)
             }
         };
 
-        let gamma = n_prime_d1 / (s * v * sqrt_t);
-        let theta = -(s * n_prime_d1 * v) / (2.0 * sqrt_t) - r * k * (-r * t).exp() * 
-            match option_type {
-                OptionType::Call => n_d2,
-                OptionType::Put => 1.0 - n_d2,
+        let gamma = held * n_prime_d1 / (s * v * sqrt_t);
+        let theta = -(s * held * n_prime_d1 * v) / (2.0 * sqrt_t)
+            + match option_type {
+                OptionType::Call => q * s * held * n_d1 - r * k * (-r * t).exp() * n_d2,
+                OptionType::Put => r * k * (-r * t).exp() * (1.0 - n_d2) - q * s * held * (1.0 - n_d1),
             };
-        let vega = s * n_prime_d1 * sqrt_t;
+        let vega = s * held * n_prime_d1 * sqrt_t;
 
         // Convert theta to per-day (divide by 365)
         let theta_per_day = theta / 365.0;
@@ -352,32 +308,61 @@ impl MtMEngine {
         (delta, gamma, theta_per_day, vega_per_percent, rho)
     }
 
-    fn normal_cdf(&self, x: f64) -> f64 {
-        // Approximation of the cumulative distribution function for standard normal
-        0.5 * (1.0 + self.erf(x / 2.0_f64.sqrt()))
+    /// Risk-neutral probability of finishing in the money: N(d2) for calls, N(-d2) for puts.
+    /// The underlying drifts at the rate less its dividend yield.
+    pub fn probability_itm(&self, underlying: f64, strike: f64, tte: f64, carry: Carry, vol: f64, option_type: &OptionType) -> f64 {
+        if tte <= 0.0 || vol <= 0.0 {
+            let itm = match option_type {
+                OptionType::Call => underlying > strike,
+                OptionType::Put => underlying < strike,
+            };
+            return if itm { 1.0 } else { 0.0 };
+        }
+        let d2 = d2(underlying, strike, tte, carry.drift(), vol);
+        match option_type {
+            OptionType::Call => normal_cdf(d2),
+            OptionType::Put => normal_cdf(-d2),
+        }
     }
 
-    fn normal_pdf(&self, x: f64) -> f64 {
-        // Probability density function for standard normal
-        (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt()
+    /// Probability the underlying finishes above `breakeven` with no drift; bearish
+    /// trades profit with the complement
+    pub fn probability_of_profit(&self, underlying: f64, breakeven: f64, tte: f64, vol: f64) -> f64 {
+        if breakeven <= 0.0 {
+            return 1.0;
+        }
+        if tte <= 0.0 || vol <= 0.0 {
+            return if underlying > breakeven { 1.0 } else { 0.0 };
+        }
+        normal_cdf(d2(underlying, breakeven, tte, 0.0, vol))
     }
 
-    fn erf(&self, x: f64) -> f64 {
-        // Approximation of the error function
-        let a1 = 0.254829592;
-        let a2 = -0.284496736;
-        let a3 = 1.421413741;
-        let a4 = -1.453152027;
-        let a5 = 1.061405429;
-        let p = 0.3275911;
-
-        let sign = if x < 0.0 { -1.0 } else { 1.0 };
-        let x = x.abs();
+    /// Expected payoff at expiry: probability_itm times the mean payout when in the
+    /// money. Undiscounted, so it is the Black-Scholes price grown at the rate.
+    pub fn expected_value(&self, underlying: f64, strike: f64, tte: f64, carry: Carry, vol: f64, option_type: &OptionType) -> f64 {
+        if tte <= 0.0 || vol <= 0.0 {
+            return match option_type {
+                OptionType::Call => (underlying - strike).max(0.0),
+                OptionType::Put => (strike - underlying).max(0.0),
+            };
+        }
+        let d2 = d2(underlying, strike, tte, carry.drift(), vol);
+        let d1 = d2 + vol * tte.sqrt();
+        let forward = underlying * (carry.drift() * tte).exp();
+        match option_type {
+            OptionType::Call => forward * normal_cdf(d1) - strike * normal_cdf(d2),
+            OptionType::Put => strike * normal_cdf(-d2) - forward * normal_cdf(-d1),
+        }
+    }
 
-        let t = 1.0 / (1.0 + p * x);
-        let y = 1.0 - (((((a5 * t + a4) * t) + a3) * t + a2) * t + a1) * t * (-x * x).exp();
+    /// Black-Scholes-Merton price: the expected payoff discounted at the rate
+    pub fn option_price(&self, underlying: f64, strike: f64, tte: f64, carry: Carry, vol: f64, option_type: &OptionType) -> f64 {
+        self.expected_value(underlying, strike, tte, carry, vol, option_type) * (-carry.rate * tte).exp()
+    }
 
-        sign * y
+    fn normal_pdf(&self, x: f64) -> f64 {
+        // Probability density function for standard normal
+        (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt()
     }
 
     pub fn update_volatility(&mut self, symbol: &str, volatility: f64) {
@@ -391,3 +376,100 @@ impl MtMEngine {
             .unwrap_or(self.default_volatility)
     }
 }
+
+fn d2(s: f64, k: f64, t: f64, r: f64, v: f64) -> f64 {
+    ((s / k).ln() + (r - 0.5 * v * v) * t) / (v * t.sqrt())
+}
+
+/// Standard normal cumulative distribution function
+pub fn normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / 2.0_f64.sqrt()))
+}
+
+/// Abramowitz and Stegun 7.1.26 approximation of the error function (max error 1.5e-7)
+fn erf(x: f64) -> f64 {
+    let a1 = 0.254829592;
+    let a2 = -0.284496736;
+    let a3 = 1.421413741;
+    let a4 = -1.453152027;
+    let a5 = 1.061405429;
+    let p = 0.3275911;
+
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+
+    let t = 1.0 / (1.0 + p * x);
+    let y = 1.0 - (((((a5 * t + a4) * t) + a3) * t + a2) * t + a1) * t * (-x * x).exp();
+
+    sign * y
+}
+
+/// Heuristic option symbol check shared by the MtM and risk engines
+pub fn is_option_symbol(symbol: &str) -> bool {
+    // Simple heuristic: options symbols typically contain expiry dates
+    // Format: AAPL240315C00150000 (AAPL, March 15 2024, Call, $150 strike)
+    symbol.len() > 10 && (symbol.contains('C') || symbol.contains('P'))
+}
+
+/// Parse an option symbol in any supported format (compact, OCC or Bloomberg)
+pub fn parse_option_symbol(symbol: &str) -> Option<OptionDetails> {
+    OptionSymbolParser::parse(symbol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::rates::RatePoint;
+
+    #[test]
+    fn test_probabilities_match_black_scholes() {
+        // S = K = 100, one year, 5% rate, 20% vol: d1 = 0.35, d2 = 0.15
+        let engine = MtMEngine::new();
+        let call = engine.probability_itm(100.0, 100.0, 1.0, Carry::rate(0.05), 0.2, &OptionType::Call);
+        let put = engine.probability_itm(100.0, 100.0, 1.0, Carry::rate(0.05), 0.2, &OptionType::Put);
+        assert!((call - 0.559618).abs() < 1e-6);
+        assert!((call + put - 1.0).abs() < 1e-12);
+
+        // Discounted, the expected payoffs are the textbook prices 10.4506 and 5.5735
+        let discount = (-0.05f64).exp();
+        let call_ev = engine.expected_value(100.0, 100.0, 1.0, Carry::rate(0.05), 0.2, &OptionType::Call);
+        let put_ev = engine.expected_value(100.0, 100.0, 1.0, Carry::rate(0.05), 0.2, &OptionType::Put);
+        assert!((call_ev * discount - 10.4506).abs() < 1e-4);
+        assert!((put_ev * discount - 5.5735).abs() < 1e-4);
+        assert_eq!(engine.option_price(100.0, 100.0, 1.0, Carry::rate(0.05), 0.2, &OptionType::Call), call_ev * discount);
+
+        // 30% vol, six months, breakeven 10% above spot
+        assert!((engine.probability_of_profit(100.0, 110.0, 0.5, 0.3) - 0.289323).abs() < 1e-6);
+
+        // At expiry only intrinsic value is left
+        assert_eq!(engine.probability_itm(105.0, 100.0, 0.0, Carry::rate(0.05), 0.2, &OptionType::Call), 1.0);
+        assert_eq!(engine.expected_value(95.0, 100.0, 0.0, Carry::rate(0.05), 0.2, &OptionType::Put), 5.0);
+    }
+
+    #[test]
+    fn test_dividend_yield_lowers_a_long_dated_calls_delta_and_rho() {
+        // Two-year at-the-money call, 25% vol; the curve gives 5% at two years
+        let curve = RateCurve::new(
+            vec![RatePoint { tenor_years: 1.0, rate: 0.04 }, RatePoint { tenor_years: 3.0, rate: 0.06 }],
+            None,
+        )
+        .unwrap();
+        let details = OptionDetails {
+            underlying: "XOM".to_string(),
+            option_type: OptionType::Call,
+            strike: 100.0,
+            expiry: Utc::now().date_naive() + chrono::Duration::days(730),
+            multiplier: 100,
+        };
+        let plain = MtMEngine { rate_curve: curve.clone(), ..MtMEngine::new() };
+        let mut paying = MtMEngine { rate_curve: curve, ..MtMEngine::new() };
+        paying.dividend_yields.set_override("XOM", Some(0.04)).unwrap();
+
+        let without = plain.calculate_option_greeks(&details, 100.0, 1);
+        let with = paying.calculate_option_greeks(&details, 100.0, 1);
+        assert!((without.delta - 67.7105).abs() < 1e-3, "{}", without.delta);
+        assert!((without.rho - 9812.69).abs() < 0.1, "{}", without.rho);
+        assert!((with.delta - 54.6719).abs() < 1e-3, "{}", with.delta);
+        assert!((with.rho - 8182.61).abs() < 0.1, "{}", with.rho);
+    }
+}
@@ -0,0 +1,476 @@
+// src-tauri/src/engine/news_halt.rs
+// Soft trading halts on held and watchlist symbols when their news turns sharply
+// negative: the strategy loop stops opening positions in the symbol but still exits,
+// until a cool-off passes without newer articles tripping the rule again.
+
+use super::r#loop::{LogLevel, StrategyLog};
+use crate::provider::polygon::NewsItem;
+use crate::storage::cache::FileCache;
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter};
+
+const CONFIG_KEY: &str = "news_halt_config";
+const HALTS_KEY: &str = "news_halts";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NewsHaltRule {
+    pub enabled: bool,
+    pub window_hours: u32,        // Articles published this recently count
+    pub min_articles: u32,        // Scored articles needed before their average can halt
+    pub sentiment_threshold: f64, // Average sentiment at or below this halts (-1..1)
+    pub keywords: Vec<String>,    // A headline containing any of these halts; case-insensitive
+    pub cool_off_minutes: u64,    // A halt lifts this long after its latest trigger
+    pub alert: bool,              // Emit "news_halt_alert" when the halt starts or is extended
+}
+
+impl Default for NewsHaltRule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_hours: 24,
+            min_articles: 2,
+            sentiment_threshold: -0.5,
+            keywords: ["bankruptcy", "investigation", "fraud", "delisting", "going concern"].map(String::from).to_vec(),
+            cool_off_minutes: 240,
+            alert: true,
+        }
+    }
+}
+
+impl NewsHaltRule {
+    fn validated(mut self) -> Result<Self, String> {
+        if self.window_hours == 0 || self.cool_off_minutes == 0 || self.min_articles == 0 {
+            return Err("window_hours, min_articles and cool_off_minutes must be at least 1".to_string());
+        }
+        if !(-1.0..=1.0).contains(&self.sentiment_threshold) {
+            return Err(format!("sentiment_threshold {} is outside -1..1", self.sentiment_threshold));
+        }
+        self.keywords = self.keywords.iter().map(|k| k.trim().to_lowercase()).filter(|k| !k.is_empty()).collect();
+        self.keywords.sort();
+        self.keywords.dedup();
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NewsHaltConfig {
+    pub enabled: bool,
+    pub check_interval_minutes: u64, // How often the strategy loop fetches news itself
+    pub default_rule: NewsHaltRule,
+    #[serde(default)]
+    pub symbol_rules: BTreeMap<String, NewsHaltRule>,
+}
+
+impl Default for NewsHaltConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false, // Each check is a news request per symbol
+            check_interval_minutes: 30,
+            default_rule: NewsHaltRule::default(),
+            symbol_rules: BTreeMap::new(),
+        }
+    }
+}
+
+impl NewsHaltConfig {
+    pub fn rule_for(&self, symbol: &str) -> &NewsHaltRule {
+        self.symbol_rules.get(symbol).unwrap_or(&self.default_rule)
+    }
+
+    /// Days of news a check fetches to cover the widest window
+    pub fn fetch_days(&self) -> u32 {
+        let widest = self.symbol_rules.values().chain([&self.default_rule]).map(|r| r.window_hours).max().unwrap_or(24);
+        widest.div_ceil(24).max(1)
+    }
+}
+
+/// A symbol's soft halt and the articles behind it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NewsHalt {
+    pub symbol: String,
+    pub reason: String,
+    pub triggered_at: i64,
+    pub expires_at: i64,
+    pub sentiment: Option<f64>,       // Window average at the latest trigger, when scored
+    pub matched_keywords: Vec<String>,
+    pub articles: Vec<NewsItem>,      // Every article that triggered it, oldest trigger first
+    #[serde(default)]
+    pub expired: bool,                // Set once the expiry has been reported
+}
+
+impl NewsHalt {
+    pub fn is_active(&self, now: i64) -> bool {
+        now < self.expires_at
+    }
+}
+
+/// What tripped a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewsTrigger {
+    pub reason: String,
+    pub sentiment: Option<f64>,
+    pub matched_keywords: Vec<String>,
+    pub articles: Vec<NewsItem>, // Keyword matches and negative articles in the window
+}
+
+/// Check the articles published in the rule's window before `now`
+pub fn evaluate_news(rule: &NewsHaltRule, articles: &[NewsItem], now: i64) -> Option<NewsTrigger> {
+    if !rule.enabled {
+        return None;
+    }
+    let since = now - rule.window_hours as i64 * 3600;
+    let recent: Vec<&NewsItem> = articles
+        .iter()
+        .filter(|a| DateTime::parse_from_rfc3339(&a.published_utc).is_ok_and(|t| (since..=now).contains(&t.timestamp())))
+        .collect();
+
+    let mut matched_keywords = Vec::new();
+    let mut triggering: Vec<NewsItem> = Vec::new();
+    for article in &recent {
+        let title = article.title.to_lowercase();
+        let hits: Vec<&String> = rule.keywords.iter().filter(|k| title.contains(k.as_str())).collect();
+        if !hits.is_empty() {
+            matched_keywords.extend(hits.into_iter().cloned());
+            triggering.push((*article).clone());
+        }
+    }
+    matched_keywords.sort();
+    matched_keywords.dedup();
+
+    let scores: Vec<f64> = recent.iter().filter_map(|a| a.sentiment.filter(|s| s.is_finite())).collect();
+    let sentiment = (!scores.is_empty()).then(|| scores.iter().sum::<f64>() / scores.len() as f64);
+    let sentiment_tripped = scores.len() >= rule.min_articles as usize && sentiment.is_some_and(|s| s <= rule.sentiment_threshold);
+    if sentiment_tripped {
+        for article in recent.iter().filter(|a| a.sentiment.is_some_and(|s| s < 0.0)) {
+            if !triggering.iter().any(|t| t.article_url == article.article_url) {
+                triggering.push((*article).clone());
+            }
+        }
+    }
+
+    let reason = match (sentiment_tripped, matched_keywords.is_empty()) {
+        (false, true) => return None,
+        (true, true) => format!(
+            "Sentiment {:.2} over {} articles in {}h is at or below {:.2}",
+            sentiment.unwrap_or_default(), scores.len(), rule.window_hours, rule.sentiment_threshold
+        ),
+        (false, false) => format!("Headline matched {}", matched_keywords.join(", ")),
+        (true, false) => format!(
+            "Headline matched {} and sentiment {:.2} is at or below {:.2}",
+            matched_keywords.join(", "), sentiment.unwrap_or_default(), rule.sentiment_threshold
+        ),
+    };
+    Some(NewsTrigger { reason, sentiment, matched_keywords, articles: triggering })
+}
+
+/// Latest halt per symbol. Halts stay after they expire so the articles behind them
+/// cannot start another one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct NewsHaltBook {
+    #[serde(default)]
+    pub halts: BTreeMap<String, NewsHalt>,
+}
+
+impl NewsHaltBook {
+    pub fn active(&self, symbol: &str, now: i64) -> Option<&NewsHalt> {
+        self.halts.get(symbol).filter(|h| h.is_active(now))
+    }
+
+    pub fn active_halts(&self, now: i64) -> Vec<NewsHalt> {
+        self.halts.values().filter(|h| h.is_active(now)).cloned().collect()
+    }
+
+    /// Halt `symbol` when `articles` trip `rule`, or extend its halt. Only articles the
+    /// symbol's last halt has not seen can trigger; returns the halt when it changed.
+    pub fn check(&mut self, symbol: &str, articles: &[NewsItem], rule: &NewsHaltRule, now: i64) -> Option<NewsHalt> {
+        let mut trigger = evaluate_news(rule, articles, now)?;
+        let seen: HashSet<&str> = self
+            .halts
+            .get(symbol)
+            .map(|h| h.articles.iter().map(|a| a.article_url.as_str()).collect())
+            .unwrap_or_default();
+        trigger.articles.retain(|a| !seen.contains(a.article_url.as_str()));
+        if trigger.articles.is_empty() {
+            return None;
+        }
+
+        let expires_at = now + rule.cool_off_minutes as i64 * 60;
+        let halt = match self.halts.remove(symbol).filter(|h| h.is_active(now)) {
+            Some(mut halt) => {
+                halt.reason = trigger.reason;
+                halt.expires_at = halt.expires_at.max(expires_at);
+                halt.sentiment = trigger.sentiment;
+                halt.matched_keywords.extend(trigger.matched_keywords);
+                halt.matched_keywords.sort();
+                halt.matched_keywords.dedup();
+                halt.articles.extend(trigger.articles);
+                halt
+            }
+            None => NewsHalt {
+                symbol: symbol.to_string(),
+                reason: trigger.reason,
+                triggered_at: now,
+                expires_at,
+                sentiment: trigger.sentiment,
+                matched_keywords: trigger.matched_keywords,
+                articles: trigger.articles,
+                expired: false,
+            },
+        };
+        self.halts.insert(symbol.to_string(), halt.clone());
+        Some(halt)
+    }
+
+    /// End `symbol`'s halt now; its articles still cannot trigger another
+    pub fn clear(&mut self, symbol: &str, now: i64) -> Result<NewsHalt, String> {
+        let halt = self
+            .halts
+            .get_mut(symbol)
+            .filter(|h| h.is_active(now))
+            .ok_or_else(|| format!("{} has no active news halt", symbol))?;
+        halt.expires_at = now;
+        halt.expired = true;
+        Ok(halt.clone())
+    }
+
+    /// Halts that ran out since the last call
+    pub fn take_expired(&mut self, now: i64) -> Vec<NewsHalt> {
+        self.halts
+            .values_mut()
+            .filter(|h| !h.expired && !h.is_active(now))
+            .map(|h| {
+                h.expired = true;
+                h.clone()
+            })
+            .collect()
+    }
+}
+
+/// The configured rules and the halt book, shared by the strategy loop, the news
+/// commands and the halt commands
+#[derive(Clone)]
+pub struct NewsHaltMonitor {
+    config: Arc<Mutex<NewsHaltConfig>>,
+    book: Arc<Mutex<NewsHaltBook>>,
+    last_check: Arc<Mutex<i64>>,
+    app_handle: AppHandle,
+}
+
+impl NewsHaltMonitor {
+    pub fn new(app_handle: AppHandle) -> Self {
+        let mut cache = FileCache::new(&app_handle).ok();
+        let config = cache.as_mut().and_then(|c| c.get(CONFIG_KEY).ok().flatten()).unwrap_or_default();
+        let book = cache.as_mut().and_then(|c| c.get(HALTS_KEY).ok().flatten()).unwrap_or_default();
+        Self {
+            config: Arc::new(Mutex::new(config)),
+            book: Arc::new(Mutex::new(book)),
+            last_check: Arc::new(Mutex::new(0)),
+            app_handle,
+        }
+    }
+
+    pub fn app_handle(&self) -> &AppHandle {
+        &self.app_handle
+    }
+
+    pub fn config(&self) -> Result<NewsHaltConfig, String> {
+        Ok(self.config.lock().map_err(|e| e.to_string())?.clone())
+    }
+
+    pub fn configure(&self, mut config: NewsHaltConfig) -> Result<NewsHaltConfig, String> {
+        if config.check_interval_minutes == 0 {
+            return Err("check_interval_minutes must be at least 1".to_string());
+        }
+        config.default_rule = config.default_rule.validated()?;
+        config.symbol_rules = config
+            .symbol_rules
+            .into_iter()
+            .map(|(symbol, rule)| Ok((super::symbols::normalize_symbol(&symbol)?, rule.validated()?)))
+            .collect::<Result<_, String>>()?;
+        FileCache::new(&self.app_handle).and_then(|mut cache| cache.set(CONFIG_KEY, config.clone(), None))?;
+        *self.config.lock().map_err(|e| e.to_string())? = config.clone();
+        Ok(config)
+    }
+
+    /// Set the rule for `symbol`, or the default rule when None. A symbol with no rule
+    /// goes back to the default.
+    pub fn set_rule(&self, symbol: Option<&str>, rule: Option<NewsHaltRule>) -> Result<NewsHaltConfig, String> {
+        let mut config = self.config()?;
+        match (symbol, rule) {
+            (None, Some(rule)) => config.default_rule = rule,
+            (None, None) => return Err("The default rule cannot be removed".to_string()),
+            (Some(symbol), Some(rule)) => {
+                config.symbol_rules.insert(super::symbols::normalize_symbol(symbol)?, rule);
+            }
+            (Some(symbol), None) => {
+                config.symbol_rules.remove(&super::symbols::normalize_symbol(symbol)?);
+            }
+        }
+        self.configure(config)
+    }
+
+    pub fn active_halt(&self, symbol: &str, now: i64) -> Option<NewsHalt> {
+        self.book.lock().ok()?.active(symbol, now).cloned()
+    }
+
+    pub fn active_halts(&self, now: i64) -> Vec<NewsHalt> {
+        self.book.lock().map(|book| book.active_halts(now)).unwrap_or_default()
+    }
+
+    /// Whether the strategy loop should fetch news this cycle; claims the check if so
+    pub fn check_due(&self, now: i64) -> bool {
+        let Ok(config) = self.config() else {
+            return false;
+        };
+        let Ok(mut last_check) = self.last_check.lock() else {
+            return false;
+        };
+        if !config.enabled || now - *last_check < config.check_interval_minutes as i64 * 60 {
+            return false;
+        }
+        *last_check = now;
+        true
+    }
+
+    /// Check fetched `articles` for `symbol`; a new or extended halt is saved, recorded
+    /// in the strategy log with its articles, and alerted when the rule asks
+    pub fn observe(&self, symbol: &str, articles: &[NewsItem], now: i64) -> Option<NewsHalt> {
+        let config = self.config().ok().filter(|c| c.enabled)?;
+        let rule = config.rule_for(symbol);
+        let halt = self.book.lock().ok()?.check(symbol, articles, rule, now)?;
+        self.save();
+
+        let _ = self.app_handle.emit("strategy_log", &StrategyLog {
+            timestamp: now,
+            level: LogLevel::Warning,
+            category: "news_halt".to_string(),
+            message: format!("Entries in {} halted until {}: {}", symbol, halt.expires_at, halt.reason),
+            data: Some(serde_json::json!({ "halt": halt })),
+            symbol: Some(symbol.to_string()),
+            bar_timestamp: None,
+        });
+        if rule.alert {
+            let _ = self.app_handle.emit("news_halt_alert", &halt);
+        }
+        Some(halt)
+    }
+
+    pub fn clear(&self, symbol: &str, now: i64) -> Result<NewsHalt, String> {
+        let halt = self.book.lock().map_err(|e| e.to_string())?.clear(symbol, now)?;
+        self.save();
+        self.log_lifted(&halt, "cleared");
+        Ok(halt)
+    }
+
+    /// Report halts whose cool-off ran out
+    pub fn expire(&self, now: i64) -> Vec<NewsHalt> {
+        let expired = self.book.lock().map(|mut book| book.take_expired(now)).unwrap_or_default();
+        if !expired.is_empty() {
+            self.save();
+        }
+        for halt in &expired {
+            self.log_lifted(halt, "expired");
+        }
+        expired
+    }
+
+    fn log_lifted(&self, halt: &NewsHalt, how: &str) {
+        let _ = self.app_handle.emit("strategy_log", &StrategyLog {
+            timestamp: halt.expires_at,
+            level: LogLevel::Info,
+            category: "news_halt".to_string(),
+            message: format!("News halt on {} {}", halt.symbol, how),
+            data: None,
+            symbol: Some(halt.symbol.clone()),
+            bar_timestamp: None,
+        });
+        let _ = self.app_handle.emit("news_halt_lifted", halt);
+    }
+
+    fn save(&self) {
+        let Ok(book) = self.book.lock().map(|book| book.clone()) else {
+            return;
+        };
+        if let Err(e) = FileCache::new(&self.app_handle).and_then(|mut cache| cache.set(HALTS_KEY, book, None)) {
+            eprintln!("Failed to save news halts: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_704_812_400; // 2024-01-09 15:00 UTC
+
+    fn article(url: &str, title: &str, hours_ago: i64, sentiment: Option<f64>) -> NewsItem {
+        NewsItem {
+            title: title.to_string(),
+            article_url: format!("https://news.example/{}", url),
+            published_utc: DateTime::from_timestamp(NOW - hours_ago * 3600, 0).unwrap().to_rfc3339(),
+            tickers: Some(vec!["ACME".to_string()]),
+            sentiment,
+        }
+    }
+
+    #[test]
+    fn test_sentiment_and_keyword_triggers() {
+        let rule = NewsHaltRule::default();
+        let mixed = [article("a", "ACME beats estimates", 2, Some(0.6)), article("b", "ACME guidance cut", 3, Some(-0.9))];
+        assert!(evaluate_news(&rule, &mixed, NOW).is_none());
+
+        // One scored article is below min_articles; articles outside the window don't count
+        let thin = [article("b", "ACME guidance cut", 3, Some(-0.9)), article("old", "ACME recall", 30, Some(-1.0))];
+        assert!(evaluate_news(&rule, &thin, NOW).is_none());
+
+        let bad = [article("b", "ACME guidance cut", 3, Some(-0.9)), article("c", "ACME CFO resigns", 1, Some(-0.4))];
+        let trigger = evaluate_news(&rule, &bad, NOW).unwrap();
+        assert!((trigger.sentiment.unwrap() + 0.65).abs() < 1e-12);
+        assert_eq!(trigger.articles.len(), 2);
+
+        let keyword = [article("d", "SEC opens INVESTIGATION into ACME", 1, None)];
+        let trigger = evaluate_news(&rule, &keyword, NOW).unwrap();
+        assert_eq!(trigger.matched_keywords, vec!["investigation"]);
+        assert_eq!(trigger.sentiment, None);
+        assert!(evaluate_news(&NewsHaltRule { enabled: false, ..rule }, &keyword, NOW).is_none());
+    }
+
+    #[test]
+    fn test_halt_lifecycle_trigger_extend_and_expire() {
+        let rule = NewsHaltRule { cool_off_minutes: 60, ..NewsHaltRule::default() };
+        let mut book = NewsHaltBook::default();
+        let first = [article("d", "ACME files for bankruptcy protection", 1, None)];
+
+        let halt = book.check("ACME", &first, &rule, NOW).unwrap();
+        assert_eq!((halt.triggered_at, halt.expires_at), (NOW, NOW + 3600));
+        assert!(book.active("ACME", NOW + 1800).is_some());
+        assert!(book.active("OTHER", NOW).is_none());
+        // The same article again is not a new trigger
+        assert!(book.check("ACME", &first, &rule, NOW + 600).is_none());
+
+        // A new article re-triggers and pushes the expiry out
+        let more = [first[0].clone(), article("e", "ACME bankruptcy judge sets hearing", 0, None)];
+        let extended = book.check("ACME", &more, &rule, NOW + 1800).unwrap();
+        assert_eq!((extended.triggered_at, extended.expires_at), (NOW, NOW + 1800 + 3600));
+        assert_eq!(extended.articles.len(), 2);
+        assert!(book.take_expired(NOW + 3600).is_empty());
+
+        let expired = book.take_expired(NOW + 5400);
+        assert_eq!(expired.len(), 1);
+        assert!(book.active("ACME", NOW + 5400).is_none());
+        assert!(book.take_expired(NOW + 6000).is_empty());
+        // Expired halts remember their articles
+        assert!(book.check("ACME", &more, &rule, NOW + 6000).is_none());
+
+        let halt = book.check("ACME", &[article("f", "ACME fraud charges", 0, None)], &rule, NOW + 7200).unwrap();
+        assert_eq!(halt.triggered_at, NOW + 7200);
+        assert_eq!(halt.articles.len(), 1);
+        assert_eq!(book.active_halts(NOW + 7200).len(), 1);
+        assert!(book.clear("ACME", NOW + 7300).is_ok());
+        assert!(book.active("ACME", NOW + 7300).is_none());
+        assert!(book.clear("ACME", NOW + 7300).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+// src-tauri/src/engine/news_impact.rs
+// How a symbol's price moved in the trading days after each news article, and how
+// well article sentiment lines up with those moves
+
+use super::metrics::pearson_correlation;
+use crate::market_data::types::Candle;
+use crate::provider::polygon::NewsItem;
+use chrono::{DateTime, NaiveTime};
+use chrono_tz::America::New_York;
+use serde::{Deserialize, Serialize};
+
+pub const NEWS_IMPACT_CACHE_SECONDS: i64 = 3600;
+const RETURN_HORIZONS: [usize; 3] = [1, 3, 5]; // Trading days after publication
+const MOST_IMPACTFUL_COUNT: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewsImpactAnalysis {
+    pub symbol: String,
+    pub articles_analyzed: u32, // Articles with a 1-day return
+    pub avg_return_1d: f64,
+    pub avg_return_3d: f64,
+    pub avg_return_5d: f64,
+    pub positive_news_1d_return: f64, // Mean 1-day return after articles with sentiment > 0
+    pub negative_news_1d_return: f64, // Mean 1-day return after articles with sentiment < 0
+    pub sentiment_return_correlation: f64, // Pearson, sentiment vs 1-day return
+    pub most_impactful_articles: Vec<(NewsItem, f64)>, // (article, 1-day return), largest moves first
+}
+
+/// Forward returns of one article from the last close before it was published
+#[derive(Debug, Clone, PartialEq)]
+struct ArticleReturns {
+    returns: [Option<f64>; 3], // By RETURN_HORIZONS
+}
+
+/// Index of the last daily bar that closed (16:00 New York) before `published_utc`
+fn base_bar(candles: &[Candle], published_utc: &str) -> Option<usize> {
+    let published = DateTime::parse_from_rfc3339(published_utc).ok()?.with_timezone(&New_York);
+    let date = published.date_naive();
+    let after_close = published.time() >= NaiveTime::from_hms_opt(16, 0, 0)?;
+    candles.iter().rposition(|c| if after_close { c.date() <= date } else { c.date() < date })
+}
+
+fn article_returns(candles: &[Candle], article: &NewsItem) -> Option<ArticleReturns> {
+    let base = base_bar(candles, &article.published_utc)?;
+    let base_close = candles[base].close;
+    if base_close <= 0.0 {
+        return None;
+    }
+    Some(ArticleReturns {
+        returns: RETURN_HORIZONS.map(|days| candles.get(base + days).map(|c| c.close / base_close - 1.0)),
+    })
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+/// Analyze `news` against daily `candles` (oldest first). Articles published before the
+/// first bar, or too recently to have a 1-day return, are left out.
+pub fn analyze_news_impact(symbol: &str, news: &[NewsItem], candles: &[Candle]) -> NewsImpactAnalysis {
+    let measured: Vec<(&NewsItem, ArticleReturns)> = news
+        .iter()
+        .filter_map(|article| article_returns(candles, article).map(|r| (article, r)))
+        .filter(|(_, r)| r.returns[0].is_some())
+        .collect();
+    let horizon_mean = |h: usize| mean(&measured.iter().filter_map(|(_, r)| r.returns[h]).collect::<Vec<_>>());
+    let one_day = |(article, r): &(&NewsItem, ArticleReturns)| (article.sentiment, r.returns[0].unwrap_or(0.0));
+
+    let scored: Vec<(f64, f64)> = measured
+        .iter()
+        .map(one_day)
+        .filter_map(|(sentiment, ret)| sentiment.filter(|s| s.is_finite()).map(|s| (s, ret)))
+        .collect();
+    let (sentiments, returns): (Vec<f64>, Vec<f64>) = scored.iter().copied().unzip();
+    let mean_where = |keep: fn(f64) -> bool| mean(&scored.iter().filter(|(s, _)| keep(*s)).map(|(_, r)| *r).collect::<Vec<_>>());
+
+    let mut most_impactful: Vec<(NewsItem, f64)> =
+        measured.iter().map(|m| ((*m.0).clone(), one_day(m).1)).collect();
+    most_impactful.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+    most_impactful.truncate(MOST_IMPACTFUL_COUNT);
+
+    NewsImpactAnalysis {
+        symbol: symbol.to_string(),
+        articles_analyzed: measured.len() as u32,
+        avg_return_1d: horizon_mean(0),
+        avg_return_3d: horizon_mean(1),
+        avg_return_5d: horizon_mean(2),
+        positive_news_1d_return: mean_where(|s| s > 0.0),
+        negative_news_1d_return: mean_where(|s| s < 0.0),
+        sentiment_return_correlation: pearson_correlation(&sentiments, &returns),
+        most_impactful_articles: most_impactful,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Daily bars from Monday 2024-01-08, stamped at midnight New York like Polygon's
+    fn daily(closes: &[f64]) -> Vec<Candle> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, c)| Candle::new(1704690000 + i as i64 * 86400, *c, *c, *c, *c, 1_000_000))
+            .collect()
+    }
+
+    fn article(title: &str, published_utc: &str, sentiment: Option<f64>) -> NewsItem {
+        NewsItem {
+            title: title.to_string(),
+            article_url: format!("https://example.com/{}", title),
+            published_utc: published_utc.to_string(),
+            tickers: Some(vec!["AAPL".to_string()]),
+            sentiment,
+        }
+    }
+
+    #[test]
+    fn test_forward_returns_and_sentiment_correlation() {
+        //                 Jan 8  9      10     11     12     13     14     15
+        let candles = daily(&[100.0, 110.0, 99.0, 99.0, 108.9, 108.9, 108.9, 100.0]);
+        let news = vec![
+            // After the Jan 8 close: measured from Jan 8, +10% the next day
+            article("beat", "2024-01-08T21:30:00Z", Some(0.8)),
+            // After the Jan 9 close: measured from Jan 9, -10%
+            article("miss", "2024-01-09T22:00:00Z", Some(-0.6)),
+            // Pre-market Jan 11: measured from Jan 10, +0%
+            article("neutral", "2024-01-11T12:00:00Z", Some(0.1)),
+            // No sentiment: counted in returns, not in the correlation
+            article("unscored", "2024-01-11T21:30:00Z", None),
+            // Before the Jan 8 close, with no earlier bar to measure from
+            article("early", "2024-01-08T15:00:00Z", Some(0.5)),
+            // No bar closed after it
+            article("latest", "2024-01-15T22:00:00Z", Some(0.9)),
+            article("bad date", "yesterday", Some(0.5)),
+        ];
+
+        let analysis = analyze_news_impact("AAPL", &news, &candles);
+        assert_eq!(analysis.articles_analyzed, 4);
+        // 1-day: +10%, -10%, 0%, +10% (Jan 11 -> Jan 12)
+        assert!((analysis.avg_return_1d - 0.025).abs() < 1e-12);
+        // 3-day from Jan 8, 9, 10, 11: -1%, -1%, +10%, +10%
+        assert!((analysis.avg_return_3d - 0.045).abs() < 1e-12);
+        assert!((analysis.positive_news_1d_return - 0.05).abs() < 1e-12);
+        assert!((analysis.negative_news_1d_return + 0.1).abs() < 1e-12);
+
+        // Pearson over (0.8, 0.1), (-0.6, -0.1), (0.1, 0.0)
+        let (s, r) = ([0.8, -0.6, 0.1], [0.1, -0.1, 0.0]);
+        let (ms, mr) = (0.1, 0.0);
+        let cov: f64 = (0..3).map(|i| (s[i] - ms) * (r[i] - mr)).sum();
+        let var_s: f64 = s.iter().map(|x| (x - ms).powi(2)).sum();
+        let var_r: f64 = r.iter().map(|x| (x - mr).powi(2)).sum();
+        let expected = cov / (var_s * var_r).sqrt();
+        assert!((analysis.sentiment_return_correlation - expected).abs() < 1e-12);
+        assert!(analysis.sentiment_return_correlation > 0.9);
+
+        // Largest absolute 1-day moves first
+        let impact: Vec<(&str, f64)> = analysis.most_impactful_articles.iter().map(|(a, r)| (a.title.as_str(), *r)).collect();
+        assert_eq!(impact.len(), 4);
+        assert!(impact[..3].iter().all(|(_, r)| (r.abs() - 0.1).abs() < 1e-12));
+        assert_eq!(impact[3], ("neutral", 0.0));
+    }
+
+    #[test]
+    fn test_no_measurable_articles() {
+        let analysis = analyze_news_impact("AAPL", &[article("old", "2023-12-01T15:00:00Z", Some(1.0))], &daily(&[100.0, 101.0]));
+        assert_eq!(analysis.articles_analyzed, 0);
+        assert_eq!((analysis.avg_return_1d, analysis.sentiment_return_correlation), (0.0, 0.0));
+        assert!(analysis.most_impactful_articles.is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+// src-tauri/src/engine/numeric.rs
+// Numeric hygiene for broker state. One NaN or infinity in cash or a position spreads
+// to equity, P&L, drawdown and every risk check after it, and is then persisted. Fills
+// are checked before they reach cash and positions, the money fields after each fill
+// and before every save, and restored state is swept once on load: derived fields are
+// recomputed and values that cannot be recomputed are reset. Each finding is kept as a
+// NumericFault.
+
+use super::drawdown::DrawdownTracker;
+use super::mtm::PortfolioGreeks;
+use super::types::{Fill, OrderSide, Portfolio, Position};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NumericAction {
+    Repaired, // Recomputed from other fields that were intact
+    Reset,    // Nothing to recompute from; set to a neutral value
+    Rejected, // The update that would have produced it was refused
+}
+
+/// A non-finite value found in, or kept out of, broker state
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NumericFault {
+    pub timestamp: i64,
+    pub location: String, // e.g. "cash", "position AAPL avg_cost", "fill 1a2b"
+    pub value: String,    // As printed; JSON has no NaN or infinity
+    pub action: NumericAction,
+    pub message: String,
+}
+
+impl NumericFault {
+    pub fn new(timestamp: i64, location: impl Into<String>, value: f64, action: NumericAction, message: impl Into<String>) -> Self {
+        Self { timestamp, location: location.into(), value: value.to_string(), action, message: message.into() }
+    }
+}
+
+/// `(field, value)` pairs that are NaN or infinite
+pub fn non_finite<'a>(fields: impl IntoIterator<Item = (&'a str, f64)>) -> Vec<(&'a str, f64)> {
+    fields.into_iter().filter(|(_, v)| !v.is_finite()).collect()
+}
+
+pub fn position_fields(position: &Position) -> [(&'static str, f64); 9] {
+    [
+        ("avg_cost", position.avg_cost),
+        ("last_price", position.last_price),
+        ("market_value", position.market_value),
+        ("cost_basis", position.cost_basis),
+        ("unrealized_pnl", position.unrealized_pnl),
+        ("unrealized_pnl_pct", position.unrealized_pnl_pct),
+        ("realized_pnl", position.realized_pnl),
+        ("prev_close", position.prev_close),
+        ("day_pnl", position.day_pnl),
+    ]
+}
+
+/// Non-finite money fields of a portfolio and its Greeks, named for the fault log
+pub fn portfolio_faults(portfolio: &Portfolio, greeks: &PortfolioGreeks) -> Vec<(String, f64)> {
+    let mut faults: Vec<(String, f64)> = non_finite([
+        ("cash", portfolio.cash),
+        ("equity", portfolio.equity),
+        ("day_pnl", portfolio.day_pnl),
+        ("total_pnl", portfolio.total_pnl),
+        ("greeks delta", greeks.delta),
+        ("greeks gamma", greeks.gamma),
+        ("greeks theta", greeks.theta),
+        ("greeks vega", greeks.vega),
+        ("greeks rho", greeks.rho),
+    ])
+    .into_iter()
+    .map(|(field, value)| (field.to_string(), value))
+    .collect();
+    let mut symbols: Vec<&String> = portfolio.positions.keys().collect();
+    symbols.sort();
+    for symbol in symbols {
+        for (field, value) in non_finite(position_fields(&portfolio.positions[symbol])) {
+            faults.push((format!("position {} {}", symbol, field), value));
+        }
+    }
+    faults
+}
+
+/// Err unless `fill` can be booked against a position of `position_quantity` shares
+/// and `cash` without overflowing either
+pub fn check_fill(fill: &Fill, position_quantity: i64, cash: f64) -> Result<(), String> {
+    if fill.quantity <= 0 {
+        return Err(format!("Fill quantity {} must be positive", fill.quantity));
+    }
+    if !(fill.price.is_finite() && fill.price > 0.0) {
+        return Err(format!("Fill price {} must be positive and finite", fill.price));
+    }
+    if !(fill.commission.is_finite() && fill.commission >= 0.0) {
+        return Err(format!("Fill commission {} must be finite and not negative", fill.commission));
+    }
+    let signed = match fill.side {
+        OrderSide::Buy => fill.quantity,
+        OrderSide::Sell => -fill.quantity,
+    };
+    position_quantity
+        .checked_add(signed)
+        .ok_or_else(|| format!("Fill of {} would overflow the {} share position", fill.quantity, position_quantity))?;
+    let notional = fill.price * fill.quantity as f64;
+    if !notional.is_finite() || !(cash - notional - fill.commission).is_finite() || !(cash + notional).is_finite() {
+        return Err(format!("Fill value {} at {} is not representable", fill.quantity, fill.price));
+    }
+    Ok(())
+}
+
+/// Repair a restored position's non-finite fields. Derived fields are recomputed from
+/// quantity, average cost and the mark; a lost mark falls back to the average cost and
+/// a lost average cost to the mark, which restarts its unrealized P&L from zero.
+/// Non-finite exit levels are dropped.
+pub fn sanitize_position(position: &mut Position, timestamp: i64) -> Vec<NumericFault> {
+    for level in [&mut position.stop_loss_price, &mut position.take_profit_price, &mut position.trailing_stop_price] {
+        if level.is_some_and(|p| !p.is_finite()) {
+            *level = None;
+        }
+    }
+    let bad = non_finite(position_fields(position));
+    if bad.is_empty() {
+        return Vec::new();
+    }
+    let symbol = position.symbol.clone();
+    let location = |field: &str| format!("position {} {}", symbol, field);
+    let mut faults = Vec::new();
+
+    let cost_ok = position.avg_cost.is_finite();
+    let mark_ok = position.last_price.is_finite();
+    match (cost_ok, mark_ok) {
+        (true, true) => {}
+        (true, false) => {
+            faults.push(NumericFault::new(timestamp, location("last_price"), position.last_price, NumericAction::Repaired, "Marked at the average cost until the next quote"));
+            position.last_price = position.avg_cost;
+        }
+        (false, true) => {
+            faults.push(NumericFault::new(timestamp, location("avg_cost"), position.avg_cost, NumericAction::Reset, "Average cost lost; reset to the mark, so unrealized P&L restarts at zero"));
+            position.avg_cost = position.last_price;
+        }
+        (false, false) => {
+            faults.push(NumericFault::new(timestamp, location("avg_cost"), position.avg_cost, NumericAction::Reset, "Average cost and mark lost; both reset to zero until the next quote"));
+            position.avg_cost = 0.0;
+            position.last_price = 0.0;
+        }
+    }
+    if !position.realized_pnl.is_finite() {
+        faults.push(NumericFault::new(timestamp, location("realized_pnl"), position.realized_pnl, NumericAction::Reset, "Realized P&L lost; reset to zero"));
+        position.realized_pnl = 0.0;
+    }
+    if !position.prev_close.is_finite() {
+        faults.push(NumericFault::new(timestamp, location("prev_close"), position.prev_close, NumericAction::Repaired, "Set to the mark; day P&L restarts at zero"));
+        position.prev_close = position.last_price;
+    }
+    for (field, value) in bad.iter().filter(|(f, _)| !matches!(*f, "avg_cost" | "last_price" | "realized_pnl" | "prev_close")) {
+        faults.push(NumericFault::new(timestamp, location(field), *value, NumericAction::Repaired, "Recomputed from quantity, average cost and mark"));
+    }
+
+    let updated_at = position.updated_at;
+    position.update_market_data(position.last_price);
+    position.updated_at = updated_at;
+    faults
+}
+
+/// Reset high-water marks that are not finite; the next equity sample sets them again
+pub fn sanitize_drawdown(tracker: &mut DrawdownTracker, timestamp: i64) -> Vec<NumericFault> {
+    let mut faults = Vec::new();
+    for (field, value) in [("all_time_high", &mut tracker.all_time_high), ("ytd_high", &mut tracker.ytd_high), ("equity", &mut tracker.equity)] {
+        if !value.is_finite() {
+            faults.push(NumericFault::new(timestamp, format!("drawdown {}", field), *value, NumericAction::Reset, "Reset; the next equity sample sets it again"));
+            *value = 0.0;
+        }
+    }
+    faults
+}
@@ -0,0 +1,201 @@
+// src-tauri/src/engine/option_symbol.rs
+// Option symbol formats: compact (AAPL240315C00150000, Polygon adds an "O:" prefix),
+// OCC 21-character with a space-padded root, and Bloomberg tickers
+
+use super::types::{OptionDetails, OptionType};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+const OCC_ROOT_WIDTH: usize = 6;
+const OCC_TAIL_LEN: usize = 15; // YYMMDD + C/P + 8-digit strike in thousandths
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OptionSymbolFormat {
+    Compact,   // AAPL240315C00150000
+    Occ,       // "AAPL  240315C00150000"
+    Bloomberg, // "AAPL US 03/15/24 C150 Equity"
+}
+
+impl OptionSymbolFormat {
+    /// Guess the format from the symbol's shape; None when it cannot be an option symbol
+    pub fn detect(symbol: &str) -> Option<Self> {
+        let symbol = symbol.trim();
+        let symbol = symbol.strip_prefix("O:").unwrap_or(symbol);
+        if symbol.contains('/') && symbol.split_whitespace().count() >= 4 {
+            return Some(OptionSymbolFormat::Bloomberg);
+        }
+        if symbol.len() <= OCC_TAIL_LEN || !symbol.is_ascii() {
+            return None;
+        }
+
+        let (root, tail) = symbol.split_at(symbol.len() - OCC_TAIL_LEN);
+        if !matches!(tail.as_bytes()[6], b'C' | b'P') {
+            return None;
+        }
+        if symbol.len() == OCC_ROOT_WIDTH + OCC_TAIL_LEN && root.ends_with(' ') {
+            Some(OptionSymbolFormat::Occ)
+        } else if !root.contains(' ') {
+            Some(OptionSymbolFormat::Compact)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct OptionSymbolParser;
+
+impl OptionSymbolParser {
+    /// Parse any supported format into contract details
+    pub fn parse(symbol: &str) -> Option<OptionDetails> {
+        let symbol = symbol.trim();
+        match OptionSymbolFormat::detect(symbol)? {
+            OptionSymbolFormat::Bloomberg => Self::parse_bloomberg(symbol),
+            OptionSymbolFormat::Compact | OptionSymbolFormat::Occ => {
+                Self::parse_occ(symbol.strip_prefix("O:").unwrap_or(symbol))
+            }
+        }
+    }
+
+    /// Root (padded or not) followed by YYMMDD, C/P and the strike in thousandths
+    fn parse_occ(symbol: &str) -> Option<OptionDetails> {
+        let (root, tail) = symbol.split_at(symbol.len().checked_sub(OCC_TAIL_LEN)?);
+        let underlying = valid_root(root.trim_end())?;
+        let expiry = NaiveDate::parse_from_str(&tail[..6], "%y%m%d").ok()?;
+        let option_type = option_type(&tail[6..7])?;
+        if !tail[7..].bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let strike = tail[7..].parse::<i64>().ok()? as f64 / 1000.0;
+        Some(details(underlying, option_type, strike, expiry))
+    }
+
+    /// ROOT EXCH MM/DD/YY C150 Equity (or Index)
+    fn parse_bloomberg(symbol: &str) -> Option<OptionDetails> {
+        let parts: Vec<&str> = symbol.split_whitespace().collect();
+        let [root, _exchange, date, contract, rest @ ..] = parts.as_slice() else {
+            return None;
+        };
+        if rest.len() > 1 || rest.first().is_some_and(|key| !key.eq_ignore_ascii_case("equity") && !key.eq_ignore_ascii_case("index")) {
+            return None;
+        }
+
+        let underlying = valid_root(root)?;
+        let expiry = NaiveDate::parse_from_str(date, "%m/%d/%y").ok()?;
+        let option_type = option_type(contract.get(..1)?)?;
+        let strike: f64 = contract[1..].parse().ok()?;
+        if !strike.is_finite() || strike <= 0.0 {
+            return None;
+        }
+        Some(details(underlying, option_type, strike, expiry))
+    }
+}
+
+/// Write contract details in `format`
+pub fn format_option_symbol(details: &OptionDetails, format: OptionSymbolFormat) -> Result<String, String> {
+    let expiry = details.expiry;
+    let underlying = valid_root(&details.underlying).ok_or_else(|| format!("Invalid option root '{}'", details.underlying))?;
+    let side = match details.option_type {
+        OptionType::Call => 'C',
+        OptionType::Put => 'P',
+    };
+    let thousandths = (details.strike * 1000.0).round() as i64;
+    if !(1..=99_999_999).contains(&thousandths) {
+        return Err(format!("Strike {} does not fit an OCC symbol", details.strike));
+    }
+
+    Ok(match format {
+        OptionSymbolFormat::Compact => format!("{}{}{}{:08}", underlying, expiry.format("%y%m%d"), side, thousandths),
+        OptionSymbolFormat::Occ => format!("{:<6}{}{}{:08}", underlying, expiry.format("%y%m%d"), side, thousandths),
+        // Bloomberg drops trailing zeros from the strike: C150, P152.5
+        OptionSymbolFormat::Bloomberg => {
+            format!("{} US {} {}{} Equity", underlying, expiry.format("%m/%d/%y"), side, thousandths as f64 / 1000.0)
+        }
+    })
+}
+
+/// Convert an option symbol in any supported format to `target_format`
+pub fn normalize_option_symbol(symbol: &str, target_format: OptionSymbolFormat) -> Result<String, String> {
+    let details = OptionSymbolParser::parse(symbol).ok_or_else(|| format!("Unrecognized option symbol '{}'", symbol))?;
+    format_option_symbol(&details, target_format)
+}
+
+fn valid_root(root: &str) -> Option<String> {
+    let valid = !root.is_empty()
+        && root.len() <= OCC_ROOT_WIDTH
+        && root.chars().all(|c| c.is_ascii_alphanumeric() || c == '.')
+        && root.starts_with(|c: char| c.is_ascii_alphabetic());
+    valid.then(|| root.to_uppercase())
+}
+
+fn option_type(code: &str) -> Option<OptionType> {
+    match code {
+        "C" | "c" => Some(OptionType::Call),
+        "P" | "p" => Some(OptionType::Put),
+        _ => None,
+    }
+}
+
+fn details(underlying: String, option_type: OptionType, strike: f64, expiry: NaiveDate) -> OptionDetails {
+    OptionDetails {
+        underlying,
+        option_type,
+        strike,
+        expiry,
+        multiplier: 100,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aapl_march_150_call() -> OptionDetails {
+        OptionDetails {
+            underlying: "AAPL".to_string(),
+            option_type: OptionType::Call,
+            strike: 150.0,
+            expiry: NaiveDate::from_ymd_opt(2024, 3, 15).unwrap(),
+            multiplier: 100,
+        }
+    }
+
+    #[test]
+    fn test_all_formats_parse_to_the_same_contract() {
+        for (symbol, format) in [
+            ("AAPL240315C00150000", OptionSymbolFormat::Compact),
+            ("O:AAPL240315C00150000", OptionSymbolFormat::Compact),
+            ("AAPL  240315C00150000", OptionSymbolFormat::Occ),
+            ("AAPL US 03/15/24 C150 Equity", OptionSymbolFormat::Bloomberg),
+        ] {
+            assert_eq!(OptionSymbolFormat::detect(symbol), Some(format), "{}", symbol);
+            assert_eq!(OptionSymbolParser::parse(symbol), Some(aapl_march_150_call()), "{}", symbol);
+        }
+    }
+
+    #[test]
+    fn test_normalize_between_formats() {
+        let contract = aapl_march_150_call();
+        assert_eq!(format_option_symbol(&contract, OptionSymbolFormat::Occ).unwrap(), "AAPL  240315C00150000");
+        assert_eq!(format_option_symbol(&contract, OptionSymbolFormat::Occ).unwrap().len(), 21);
+        assert_eq!(
+            normalize_option_symbol("AAPL  240315C00150000", OptionSymbolFormat::Bloomberg).unwrap(),
+            "AAPL US 03/15/24 C150 Equity"
+        );
+        assert_eq!(
+            normalize_option_symbol("AAPL US 03/15/24 C150 Equity", OptionSymbolFormat::Compact).unwrap(),
+            "AAPL240315C00150000"
+        );
+        assert_eq!(
+            normalize_option_symbol("SPY US 06/21/24 P512.5 Equity", OptionSymbolFormat::Occ).unwrap(),
+            "SPY   240621P00512500"
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_option_symbols() {
+        for symbol in ["AAPL", "BRK.B", "AAPL241315C00150000", "AAPL240315X00150000", "AAPL US 03/15/24 X150 Equity", "TOOLONGROOT240315C00150000"] {
+            assert_eq!(OptionSymbolParser::parse(symbol), None, "{}", symbol);
+        }
+        assert!(normalize_option_symbol("AAPL", OptionSymbolFormat::Occ).is_err());
+    }
+}
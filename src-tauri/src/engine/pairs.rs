@@ -0,0 +1,279 @@
+// src-tauri/src/engine/pairs.rs
+// Two-leg relative-value strategy: trade the z-score of the spread between two symbols'
+// daily closes, opening both legs together when it stretches and closing them together
+// when it reverts. The backtest engine and the strategy loop both decide through here.
+
+use super::metrics::beta_and_correlation;
+use super::symbols::normalize_symbol;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+fn default_pair_lookback() -> usize {
+    20
+}
+
+fn default_entry_z() -> f64 {
+    2.0
+}
+
+fn default_leg_notional() -> f64 {
+    10_000.0
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum SpreadDefinition {
+    #[default]
+    Ratio,         // first / second
+    LogDifference, // ln(first) - ln(second)
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum PairSizing {
+    #[default]
+    DollarNeutral, // Equal notional in both legs
+    BetaNeutral,   // Second leg notional scaled by the first's beta to it over the lookback
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PairConfig {
+    pub first: String, // Bought when the spread is cheap, sold short when it is rich
+    pub second: String,
+    #[serde(default)]
+    pub spread: SpreadDefinition,
+    #[serde(default = "default_pair_lookback")]
+    pub lookback: usize, // Bars in the z-score window
+    #[serde(default = "default_entry_z")]
+    pub entry_z: f64,
+    #[serde(default)]
+    pub exit_z: f64, // Close once the z-score reverts to within this of the mean
+    #[serde(default)]
+    pub sizing: PairSizing,
+    #[serde(default = "default_leg_notional")]
+    pub leg_notional: f64, // Dollars in the first leg when the loop opens the pair; backtests size from equity
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum PairDirection {
+    LongSpread,  // Long first, short second
+    ShortSpread, // Short first, long second
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum PairAction {
+    Open(PairDirection),
+    Close,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairSignal {
+    pub index: usize, // Aligned bar whose close both legs fill at
+    pub action: PairAction,
+    pub zscore: f64,
+    pub reason: &'static str,
+}
+
+/// Closes of both legs on one date
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlignedClose {
+    pub date: NaiveDate,
+    pub first: f64,
+    pub second: f64,
+}
+
+impl PairConfig {
+    /// Watchlist label, e.g. "XLE/XOP"
+    pub fn label(&self) -> String {
+        format!("{}/{}", self.first, self.second)
+    }
+
+    /// Canonical leg symbols and validated thresholds
+    pub fn normalized(&self) -> Result<Self, String> {
+        let (first, second) = (normalize_symbol(&self.first)?, normalize_symbol(&self.second)?);
+        if first == second {
+            return Err(format!("Pair legs must be different symbols, got {} twice", first));
+        }
+        if self.lookback < 2 {
+            return Err(format!("Pair lookback must be at least 2 bars, got {}", self.lookback));
+        }
+        if !(self.exit_z >= 0.0 && self.entry_z > self.exit_z) {
+            return Err(format!("Pair entry z-score {} must exceed its exit z-score {} >= 0", self.entry_z, self.exit_z));
+        }
+        if !self.leg_notional.is_finite() || self.leg_notional <= 0.0 {
+            return Err(format!("Pair leg notional must be positive, got {}", self.leg_notional));
+        }
+        Ok(Self { first, second, ..self.clone() })
+    }
+
+    /// What to do at z-score `zscore` with `open` the pair's current direction, if any
+    pub fn next_action(&self, zscore: f64, open: Option<PairDirection>) -> Option<(PairAction, &'static str)> {
+        match open {
+            None if zscore <= -self.entry_z => Some((PairAction::Open(PairDirection::LongSpread), "spread_below_entry")),
+            None if zscore >= self.entry_z => Some((PairAction::Open(PairDirection::ShortSpread), "spread_above_entry")),
+            Some(PairDirection::LongSpread) if zscore >= -self.exit_z => Some((PairAction::Close, "spread_reverted")),
+            Some(PairDirection::ShortSpread) if zscore <= self.exit_z => Some((PairAction::Close, "spread_reverted")),
+            _ => None,
+        }
+    }
+
+    /// Second leg notional per dollar of the first at aligned bar `index`: 1 when dollar
+    /// neutral, else the first leg's beta to the second over the lookback (1 when that
+    /// window has no positive beta)
+    pub fn hedge_ratio(&self, closes: &[AlignedClose], index: usize) -> f64 {
+        if self.sizing == PairSizing::DollarNeutral {
+            return 1.0;
+        }
+        let window = &closes[(index + 1).saturating_sub(self.lookback)..=index];
+        let first: Vec<f64> = window.iter().map(|c| c.first).collect();
+        let second: Vec<f64> = window.iter().map(|c| c.second).collect();
+        let (beta, _) = beta_and_correlation(&first, &second);
+        if beta.is_finite() && beta > 0.0 {
+            beta
+        } else {
+            1.0
+        }
+    }
+}
+
+impl SpreadDefinition {
+    pub fn value(&self, first: f64, second: f64) -> f64 {
+        match self {
+            SpreadDefinition::Ratio => first / second,
+            SpreadDefinition::LogDifference => first.ln() - second.ln(),
+        }
+    }
+}
+
+/// Dates both legs have a positive close, in the first leg's order
+pub fn align_closes(first: &[(NaiveDate, f64)], second: &[(NaiveDate, f64)]) -> Vec<AlignedClose> {
+    let second: HashMap<NaiveDate, f64> = second.iter().copied().collect();
+    first
+        .iter()
+        .filter_map(|&(date, close)| {
+            let other = *second.get(&date)?;
+            (close > 0.0 && other > 0.0).then_some(AlignedClose { date, first: close, second: other })
+        })
+        .collect()
+}
+
+/// Z-score of each bar's spread against the trailing `lookback` spreads, itself
+/// included; None until the window is full or while it has no variance
+pub fn spread_zscores(config: &PairConfig, closes: &[AlignedClose]) -> Vec<Option<f64>> {
+    let spreads: Vec<f64> = closes.iter().map(|c| config.spread.value(c.first, c.second)).collect();
+    (0..spreads.len())
+        .map(|i| {
+            if i + 1 < config.lookback {
+                return None;
+            }
+            let window = &spreads[i + 1 - config.lookback..=i];
+            let n = window.len() as f64;
+            let mean = window.iter().sum::<f64>() / n;
+            let std = (window.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n).sqrt();
+            (std > 1e-12).then(|| (spreads[i] - mean) / std)
+        })
+        .collect()
+}
+
+/// Entries and exits over the aligned series, oldest first
+pub fn pair_signals(config: &PairConfig, closes: &[AlignedClose]) -> Vec<PairSignal> {
+    let mut signals = Vec::new();
+    let mut open = None;
+    for (index, zscore) in spread_zscores(config, closes).into_iter().enumerate() {
+        let Some(zscore) = zscore else {
+            continue;
+        };
+        if let Some((action, reason)) = config.next_action(zscore, open) {
+            open = match action {
+                PairAction::Open(direction) => Some(direction),
+                PairAction::Close => None,
+            };
+            signals.push(PairSignal { index, action, zscore, reason });
+        }
+    }
+    signals
+}
+
+/// Whole shares (first, second) for `first_notional` dollars in the first leg and
+/// `hedge_ratio` times the first leg's actual notional in the second
+pub fn leg_quantities(first_notional: f64, hedge_ratio: f64, first_price: f64, second_price: f64) -> (i64, i64) {
+    if first_price <= 0.0 || second_price <= 0.0 {
+        return (0, 0);
+    }
+    let first = (first_notional / first_price).floor().max(0.0) as i64;
+    let second = (hedge_ratio * first as f64 * first_price / second_price).floor().max(0.0) as i64;
+    (first, second)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(i: usize) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + chrono::Duration::days(i as i64)
+    }
+
+    fn config(spread: SpreadDefinition) -> PairConfig {
+        PairConfig {
+            first: "xle".into(),
+            second: "XOP".into(),
+            spread,
+            lookback: 10,
+            entry_z: 1.5,
+            exit_z: 0.0,
+            sizing: PairSizing::DollarNeutral,
+            leg_notional: 10_000.0,
+        }
+    }
+
+    #[test]
+    fn test_zscore_signals_on_a_stretched_ratio() {
+        // The ratio sits at 2.0 +/- 0.01, drops to 1.9 (long the spread), recovers,
+        // then jumps to 2.1 (short the spread) and recovers
+        let mut ratios: Vec<f64> = (0..12).map(|i| if i % 2 == 0 { 2.01 } else { 1.99 }).collect();
+        ratios.extend([1.9, 1.95, 2.01, 1.99, 2.01, 1.99, 2.1, 2.05, 1.99]);
+        let second: Vec<(NaiveDate, f64)> = (0..ratios.len()).map(|i| (day(i), 50.0)).collect();
+        let first: Vec<(NaiveDate, f64)> = ratios.iter().enumerate().map(|(i, r)| (day(i), r * 50.0)).collect();
+        let closes = align_closes(&first, &second);
+
+        let config = config(SpreadDefinition::Ratio).normalized().unwrap();
+        let signals = pair_signals(&config, &closes);
+        let actions: Vec<(usize, PairAction)> = signals.iter().map(|s| (s.index, s.action)).collect();
+        assert_eq!(
+            actions,
+            vec![
+                (12, PairAction::Open(PairDirection::LongSpread)),
+                (14, PairAction::Close),
+                (18, PairAction::Open(PairDirection::ShortSpread)),
+                (20, PairAction::Close),
+            ]
+        );
+        assert!(signals[0].zscore < -1.5 && signals[2].zscore > 1.5);
+        assert_eq!(config.label(), "XLE/XOP");
+
+        // A date missing from one leg is dropped from both
+        let aligned = align_closes(&first, &second[1..]);
+        assert_eq!((aligned.len(), aligned[0].date), (ratios.len() - 1, day(1)));
+    }
+
+    #[test]
+    fn test_spread_definitions_and_sizing() {
+        assert!((SpreadDefinition::Ratio.value(100.0, 50.0) - 2.0).abs() < 1e-12);
+        assert!((SpreadDefinition::LogDifference.value(100.0, 50.0) - 2f64.ln()).abs() < 1e-12);
+
+        // Dollar neutral: $10,000 in each leg
+        assert_eq!(leg_quantities(10_000.0, 1.0, 80.0, 125.0), (125, 80));
+        // Beta neutral: the first leg moves twice as much as the second
+        let closes: Vec<AlignedClose> = (0..10)
+            .map(|i| {
+                let r = if i % 2 == 0 { 0.01 } else { -0.01 };
+                AlignedClose { date: day(i), first: 100.0 * (1.0 + 2.0 * r), second: 50.0 * (1.0 + r) }
+            })
+            .collect();
+        let beta_neutral = PairConfig { sizing: PairSizing::BetaNeutral, ..config(SpreadDefinition::LogDifference) };
+        assert!((beta_neutral.hedge_ratio(&closes, 9) - 2.0).abs() < 0.05);
+        assert_eq!(config(SpreadDefinition::Ratio).hedge_ratio(&closes, 9), 1.0);
+
+        assert!(PairConfig { second: "XLE".into(), ..config(SpreadDefinition::Ratio) }.normalized().is_err());
+        assert!(PairConfig { exit_z: 2.0, ..config(SpreadDefinition::Ratio) }.normalized().is_err());
+    }
+}
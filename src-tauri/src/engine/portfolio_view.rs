@@ -0,0 +1,184 @@
+// src-tauri/src/engine/portfolio_view.rs
+// Scoped reads of the enhanced portfolio. A query picks the parts a view needs (totals,
+// positions, Greeks, badge counts), narrows the per-position parts to some symbols and
+// pages the positions once there are more than a page of them. Every response carries
+// the broker's portfolio version, bumped on each fill, mark, cash adjustment and badge or
+// freshness change; a query naming the current version gets "not modified" back. The
+// portfolio events carry the same version.
+
+use super::badges::BadgeSummary;
+use super::drawdown::DrawdownStatus;
+use super::entry_stats::PositionStats;
+use super::freshness::PortfolioFreshness;
+use super::mtm::{PortfolioGreeks, PositionGreeks};
+use super::symbols::normalize_symbol;
+use super::types::Position;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+pub const DEFAULT_PORTFOLIO_PAGE_SIZE: usize = 50;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PortfolioField {
+    Totals,    // Cash, equity, P&L, drawdown and freshness
+    Positions, // With their since-entry stats
+    Greeks,
+    Badges,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortfolioQuery {
+    pub fields: Option<BTreeSet<PortfolioField>>, // None for every field
+    pub symbols: Option<Vec<String>>,             // Scopes positions and position Greeks
+    pub page: usize,                              // 0-based
+    pub page_size: Option<usize>,                 // None for the broker's portfolio_page_size
+    pub if_none_match: Option<u64>,               // The state_version the caller already has
+}
+
+impl PortfolioQuery {
+    pub fn wants(&self, field: PortfolioField) -> bool {
+        self.fields.as_ref().is_none_or(|fields| fields.contains(&field))
+    }
+
+    /// "Not modified" when the caller already has `state_version`
+    pub fn not_modified(&self, state_version: u64) -> Option<PortfolioResponse> {
+        (self.if_none_match == Some(state_version)).then_some(PortfolioResponse::NotModified { state_version })
+    }
+
+    /// The page of held symbols in scope, in symbol order
+    pub fn scope<'a>(&self, held: impl Iterator<Item = &'a String>, default_page_size: usize) -> Result<Page<String>, String> {
+        let page_size = self.page_size.unwrap_or(default_page_size);
+        if page_size == 0 {
+            return Err("page_size must be at least 1".to_string());
+        }
+        let wanted = match &self.symbols {
+            Some(symbols) => Some(symbols.iter().map(|s| normalize_symbol(s)).collect::<Result<BTreeSet<_>, _>>()?),
+            None => None,
+        };
+        let selected: BTreeSet<String> = held.filter(|s| wanted.as_ref().is_none_or(|w| w.contains(*s))).cloned().collect();
+        paginate(selected.into_iter().collect(), self.page, page_size)
+    }
+}
+
+/// One page of an ordered collection
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: usize,
+    pub page_size: usize,
+    pub total: usize, // Items across every page
+    pub pages: usize,
+}
+
+impl<T> Page<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page { items: self.items.into_iter().map(f).collect(), page: self.page, page_size: self.page_size, total: self.total, pages: self.pages }
+    }
+}
+
+/// Page `page` of `items`; a collection of at most `page_size` is a single page
+pub fn paginate<T>(mut items: Vec<T>, page: usize, page_size: usize) -> Result<Page<T>, String> {
+    let total = items.len();
+    let pages = total.div_ceil(page_size).max(1);
+    if page >= pages {
+        return Err(format!("Page {} is past the last page ({})", page, pages - 1));
+    }
+    let start = page * page_size;
+    items.truncate((start + page_size).min(total));
+    items.drain(..start);
+    Ok(Page { items, page, page_size, total, pages })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioTotals {
+    pub cash: f64,
+    pub equity: f64,
+    pub buying_power: f64,
+    pub margin_used: f64,
+    pub maintenance_excess: f64,
+    pub day_pnl: f64,
+    pub total_pnl: f64,
+    pub stock_value: f64,
+    pub option_value: f64,
+    pub unrealized_pnl: f64,
+    pub realized_pnl: f64,
+    pub drawdown: DrawdownStatus,
+    pub freshness: PortfolioFreshness,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GreeksView {
+    pub portfolio: PortfolioGreeks,     // Account-wide
+    pub positions: Vec<PositionGreeks>, // The page's positions
+}
+
+/// The requested parts of the enhanced portfolio; parts not asked for are left out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioView {
+    pub state_version: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub totals: Option<PortfolioTotals>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub positions: Option<Page<Position>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position_stats: Option<HashMap<String, PositionStats>>, // The page's positions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub greeks: Option<GreeksView>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub badges: Option<BadgeSummary>, // Account-wide counts
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PortfolioResponse {
+    NotModified { state_version: u64 },
+    Modified(PortfolioView),
+}
+
+/// An event payload stamped with the portfolio version it was taken at
+#[derive(Debug, Clone, Serialize)]
+pub struct Versioned<T> {
+    pub state_version: u64,
+    #[serde(flatten)]
+    pub payload: T,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_filters_and_pages_in_symbol_order() {
+        let held: Vec<String> = ["MSFT", "AAPL", "TSLA", "AMD", "NVDA"].iter().map(|s| s.to_string()).collect();
+        let query = |symbols: Option<&[&str]>, page, page_size| PortfolioQuery {
+            symbols: symbols.map(|s| s.iter().map(|s| s.to_string()).collect()),
+            page,
+            page_size,
+            ..PortfolioQuery::default()
+        };
+
+        // At or under the page size it is one page
+        let all = query(None, 0, None).scope(held.iter(), 5).unwrap();
+        assert_eq!((all.items.len(), all.pages), (5, 1));
+        assert_eq!(all.items[0], "AAPL");
+
+        let second = query(None, 1, Some(2)).scope(held.iter(), 5).unwrap();
+        assert_eq!(second.items, vec!["MSFT".to_string(), "NVDA".to_string()]);
+        assert_eq!((second.total, second.pages), (5, 3));
+        assert_eq!(query(None, 2, Some(2)).scope(held.iter(), 5).unwrap().items, vec!["TSLA".to_string()]);
+        assert!(query(None, 3, Some(2)).scope(held.iter(), 5).is_err());
+        assert!(query(None, 0, Some(0)).scope(held.iter(), 5).is_err());
+
+        // Symbols are normalized; ones not held are ignored, and an empty scope is an empty page
+        let some = query(Some(&["tsla", "amd", "GME"]), 0, None).scope(held.iter(), 5).unwrap();
+        assert_eq!((some.items, some.total), (vec!["AMD".to_string(), "TSLA".to_string()], 2));
+        assert_eq!(query(Some(&[]), 0, None).scope(held.iter(), 5).unwrap().pages, 1);
+
+        let only_totals = PortfolioQuery { fields: Some(BTreeSet::from([PortfolioField::Totals])), ..PortfolioQuery::default() };
+        assert!(only_totals.wants(PortfolioField::Totals) && !only_totals.wants(PortfolioField::Greeks));
+        assert!(PortfolioQuery::default().wants(PortfolioField::Greeks));
+    }
+}
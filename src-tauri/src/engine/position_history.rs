@@ -0,0 +1,268 @@
+// src-tauri/src/engine/position_history.rs
+// Positions rebuilt from the trade journal with splits applied on their execution
+// dates. Statements and the position history API both replay through here so their
+// quantities and cost bases agree. Marks come from the session closes recorded at each
+// daily roll, which are as traded; provider bars are split-adjusted.
+
+use super::types::{DailySummary, Fill, OrderSide, Position, StockSplit, Trade};
+use chrono::{DateTime, NaiveDate};
+use chrono_tz::America::New_York;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Price basis for historical quantities and prices
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BasisMode {
+    #[default]
+    AsTraded, // As they were on each date
+    Adjusted, // Restated by every later split, comparable with today's prices
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PositionHistoryPoint {
+    pub date: NaiveDate,
+    pub quantity: f64, // Whole shares as traded; restating by a reverse split can leave a fraction
+    pub avg_cost: f64,
+    pub cost_basis: f64,
+    pub mark: Option<f64>, // Session close, when the broker recorded one
+    pub market_value: Option<f64>,
+    pub unrealized_pnl: Option<f64>,
+    pub split_factor: f64, // Combined ratio of the later splits applied; 1 as traded
+}
+
+/// Session date (New York) of a timestamp in seconds
+pub fn session_date(timestamp: i64) -> NaiveDate {
+    DateTime::from_timestamp(timestamp, 0)
+        .unwrap_or_default()
+        .with_timezone(&New_York)
+        .date_naive()
+}
+
+/// A journal trade and the P&L it realized when replayed
+pub struct ReplayedTrade<'a> {
+    pub trade: &'a Trade,
+    pub realized_pnl: f64,
+    pub closes: bool, // Reduced or closed an existing position
+}
+
+/// Replays the journal in a fixed order, applying each split at the start of its
+/// execution date before that session's trades
+pub struct PositionReplay<'a> {
+    trades: Vec<&'a Trade>,
+    splits: Vec<&'a StockSplit>,
+    next_trade: usize,
+    next_split: usize,
+    holdings: BTreeMap<String, Position>,
+}
+
+impl<'a> PositionReplay<'a> {
+    pub fn new(trades: &'a [Trade], splits: &'a [StockSplit]) -> Self {
+        let mut trades: Vec<&Trade> = trades.iter().collect();
+        trades.sort_by(|a, b| (a.timestamp, &a.id).cmp(&(b.timestamp, &b.id)));
+        let mut splits: Vec<&StockSplit> = splits.iter().collect();
+        splits.sort_by(|a, b| (a.execution_date, &a.symbol).cmp(&(b.execution_date, &b.symbol)));
+        Self { trades, splits, next_trade: 0, next_split: 0, holdings: BTreeMap::new() }
+    }
+
+    /// Apply everything effective through the end of session `date`; returns the
+    /// trades applied, in order
+    pub fn advance_through(&mut self, date: NaiveDate) -> Vec<ReplayedTrade<'a>> {
+        let mut applied = Vec::new();
+        loop {
+            let split = self.splits.get(self.next_split).copied().filter(|s| s.execution_date <= date);
+            let trade = self.trades.get(self.next_trade).copied().filter(|t| session_date(t.timestamp) <= date);
+            match (split, trade) {
+                (Some(split), trade) if trade.is_none_or(|t| split.execution_date <= session_date(t.timestamp)) => {
+                    self.next_split += 1;
+                    if let Some(position) = self.holdings.get_mut(&split.symbol) {
+                        position.apply_split(split);
+                        if position.quantity == 0 {
+                            self.holdings.remove(&split.symbol);
+                        }
+                    }
+                }
+                (_, Some(trade)) => {
+                    self.next_trade += 1;
+                    applied.push(self.apply_trade(trade));
+                }
+                _ => break,
+            }
+        }
+        applied
+    }
+
+    fn apply_trade(&mut self, trade: &'a Trade) -> ReplayedTrade<'a> {
+        let position = self.holdings.entry(trade.symbol.clone()).or_insert_with(|| Position::new(trade.symbol.clone()));
+        let closes = match trade.side {
+            OrderSide::Buy => position.quantity < 0,
+            OrderSide::Sell => position.quantity > 0,
+        };
+        let realized_pnl = position.apply_fill(&Fill::from(trade));
+        if position.quantity == 0 {
+            self.holdings.remove(&trade.symbol);
+        }
+        ReplayedTrade { trade, realized_pnl, closes }
+    }
+
+    pub fn holdings(&self) -> &BTreeMap<String, Position> {
+        &self.holdings
+    }
+
+    pub fn into_holdings(self) -> BTreeMap<String, Position> {
+        self.holdings
+    }
+}
+
+/// Daily position in `symbol` from `from` through `to`: one point per recorded session
+/// and per day the symbol traded, skipping days it was not held
+pub fn position_history(
+    symbol: &str,
+    trades: &[Trade],
+    splits: &[StockSplit],
+    summaries: &[DailySummary],
+    from: NaiveDate,
+    to: NaiveDate,
+    mode: BasisMode,
+) -> Result<Vec<PositionHistoryPoint>, String> {
+    if from > to {
+        return Err(format!("History start {} is after its end {}", from, to));
+    }
+    let in_range = |date: &NaiveDate| *date >= from && *date <= to;
+    let dates: BTreeSet<NaiveDate> = summaries
+        .iter()
+        .map(|s| s.date)
+        .chain(trades.iter().filter(|t| t.symbol == symbol).map(|t| session_date(t.timestamp)))
+        .filter(in_range)
+        .collect();
+    let marks: BTreeMap<NaiveDate, f64> = summaries
+        .iter()
+        .filter_map(|s| s.marks.get(symbol).map(|mark| (s.date, *mark)))
+        .collect();
+    let symbol_splits: Vec<&StockSplit> = splits.iter().filter(|s| s.symbol == symbol).collect();
+
+    let mut replay = PositionReplay::new(trades, splits);
+    let mut points = Vec::new();
+    for date in dates {
+        let traded = replay.advance_through(date).iter().any(|r| r.trade.symbol == symbol);
+        let (quantity, avg_cost) = match replay.holdings().get(symbol) {
+            Some(position) => (position.quantity, position.avg_cost),
+            None if traded => (0, 0.0),
+            None => continue,
+        };
+        let split_factor = match mode {
+            BasisMode::AsTraded => 1.0,
+            BasisMode::Adjusted => symbol_splits.iter().filter(|s| s.execution_date > date).map(|s| s.ratio()).product(),
+        };
+
+        let quantity = quantity as f64 * split_factor;
+        let avg_cost = avg_cost / split_factor;
+        let mark = marks.get(&date).map(|m| m / split_factor);
+        points.push(PositionHistoryPoint {
+            date,
+            quantity,
+            avg_cost,
+            cost_basis: quantity * avg_cost,
+            mark,
+            market_value: mark.map(|m| m * quantity),
+            unrealized_pnl: mark.map(|m| (m - avg_cost) * quantity),
+            split_factor,
+        });
+    }
+    Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::statements::build_statement;
+    use crate::engine::types::InstrumentType;
+
+    fn day(month: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, d).unwrap()
+    }
+
+    // 10:00 New York
+    fn trade(id: &str, side: OrderSide, quantity: i64, price: f64, date: NaiveDate) -> Trade {
+        Trade {
+            id: id.to_string(),
+            symbol: "NVDA".to_string(),
+            side,
+            quantity,
+            price,
+            timestamp: date.and_hms_opt(15, 0, 0).unwrap().and_utc().timestamp(),
+            order_id: format!("order-{}", id),
+            commission: 0.0,
+            net_amount: price * quantity as f64,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            leg_number: None,
+            assignment_id: None,
+            tag: None,
+            evaluation_id: None,
+        }
+    }
+
+    fn session(date: NaiveDate, mark: f64) -> DailySummary {
+        DailySummary {
+            date,
+            starting_equity: 0.0,
+            ending_equity: 0.0,
+            cash: 0.0,
+            unrealized_pnl: 0.0,
+            marks: [("NVDA".to_string(), mark)].into_iter().collect(),
+            open_auction_fills: 0,
+            close_auction_fills: 0,
+            realized_equity: None,
+            equity_range: None,
+            benchmark_day_pnl: None,
+        }
+    }
+
+    #[test]
+    fn test_history_through_a_split_in_both_bases() {
+        // 10-for-1 split effective June 10; 15 shares bought before, 20 after
+        let trades = vec![
+            trade("t1", OrderSide::Buy, 10, 900.0, day(6, 5)),
+            trade("t2", OrderSide::Buy, 5, 1000.0, day(6, 6)),
+            trade("t3", OrderSide::Buy, 20, 120.0, day(6, 11)),
+        ];
+        let splits = vec![StockSplit { symbol: "NVDA".to_string(), execution_date: day(6, 10), split_from: 1.0, split_to: 10.0 }];
+        let summaries = vec![
+            session(day(6, 5), 950.0),
+            session(day(6, 6), 1000.0),
+            session(day(6, 7), 1200.0),
+            session(day(6, 10), 121.0),
+            session(day(6, 11), 125.0),
+        ];
+
+        let as_traded = position_history("NVDA", &trades, &splits, &summaries, day(6, 1), day(6, 30), BasisMode::AsTraded).unwrap();
+        let adjusted = position_history("NVDA", &trades, &splits, &summaries, day(6, 1), day(6, 30), BasisMode::Adjusted).unwrap();
+        assert_eq!(as_traded.iter().map(|p| p.date).collect::<Vec<_>>(), summaries.iter().map(|s| s.date).collect::<Vec<_>>());
+
+        // Before the split: 15 shares at $1200 as traded, 150 at $120 restated
+        let (before, restated) = (&as_traded[2], &adjusted[2]);
+        assert_eq!((before.quantity, before.mark, before.split_factor), (15.0, Some(1200.0), 1.0));
+        assert_eq!((restated.quantity, restated.mark, restated.split_factor), (150.0, Some(120.0), 10.0));
+        assert!((before.avg_cost - 2800.0 / 3.0).abs() < 1e-9);
+        assert!((restated.avg_cost - 280.0 / 3.0).abs() < 1e-9);
+
+        // From the split on both bases match
+        assert_eq!(as_traded[3], adjusted[3]);
+        assert_eq!(as_traded[4].quantity, 170.0);
+
+        for (a, b) in as_traded.iter().zip(&adjusted) {
+            assert!((a.market_value.unwrap() - b.market_value.unwrap()).abs() < 1e-6, "{}", a.date);
+            assert!((a.cost_basis - b.cost_basis).abs() < 1e-6);
+            assert!((a.unrealized_pnl.unwrap() - b.unrealized_pnl.unwrap()).abs() < 1e-6);
+        }
+        assert_eq!(as_traded[4].market_value, Some(170.0 * 125.0));
+
+        // Statements hold the same month-end position
+        let statement = build_statement(2024, 6, &trades, &summaries, &[], &splits).unwrap();
+        assert_eq!(statement.positions[0].quantity, 170);
+        assert_eq!(statement.positions[0].market_value, as_traded[4].market_value);
+        assert!((statement.positions[0].cost_basis - as_traded[4].cost_basis).abs() < 1e-6);
+
+        assert!(position_history("NVDA", &trades, &splits, &summaries, day(6, 30), day(6, 1), BasisMode::AsTraded).is_err());
+    }
+}
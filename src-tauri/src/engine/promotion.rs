@@ -0,0 +1,265 @@
+// src-tauri/src/engine/promotion.rs
+// Promotion of the strategy loop from dry run to placing orders. A request runs the
+// safety checklist and, when every item passes or is waived with a reason, hands back
+// a short-lived token; confirming with the token turns dry_run off and journals the
+// checklist as it stood. Demotion back to dry run needs only a reason. Config updates
+// and presets never turn dry_run off themselves.
+
+use super::shadow_ledger::ShadowLedger;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+pub const PROMOTION_LOG_KEY: &str = "promotion_log";
+pub const PROMOTION_TOKEN_TTL_SECONDS: i64 = 600;
+const MAX_PROMOTION_RECORDS: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PromotionConfig {
+    pub min_dry_run_days: usize,
+    pub min_sharpe: f64,
+    pub max_drawdown_pct: f64, // Of the dry-run equity, from its peak
+    pub max_backup_age_days: i64,
+}
+
+impl Default for PromotionConfig {
+    fn default() -> Self {
+        Self { min_dry_run_days: 10, min_sharpe: 0.5, max_drawdown_pct: 0.15, max_backup_age_days: 7 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PromotionCheck {
+    DryRunDays,
+    DryRunPerformance,
+    RiskLimits,     // Changed from the defaults, or the defaults acknowledged
+    ProviderHealth, // The live stream is up and connected
+    RecentBackup,
+    CircuitBreaker, // Not tripped; cannot be waived
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChecklistItem {
+    pub check: PromotionCheck,
+    pub passed: bool,
+    pub detail: String,
+    pub waiver: Option<String>, // Reason a failing item was let through
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PromotionChecklist {
+    pub items: Vec<ChecklistItem>,
+    pub ready: bool,           // Every item passed or was waived
+    pub token: Option<String>, // For confirm_live_promotion, when ready
+    pub checked_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+/// What the checklist is run against
+#[derive(Debug, Clone, Default)]
+pub struct PromotionFacts {
+    pub limits_customized: bool,
+    pub defaults_acknowledged: bool,
+    pub stream_connected: bool,
+    pub stream_detail: String,
+    pub backup_age_days: Option<i64>, // Newest configuration backup; None without one
+    pub circuit_breaker_active: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PromotionAction {
+    Promoted,
+    Demoted,
+}
+
+/// A journaled switch between dry run and live
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PromotionRecord {
+    pub id: String,
+    pub timestamp: i64,
+    pub action: PromotionAction,
+    pub reason: Option<String>,                 // Given on demotion
+    pub checklist: Option<PromotionChecklist>,  // As confirmed, on promotion
+}
+
+fn item(check: PromotionCheck, passed: bool, detail: String) -> ChecklistItem {
+    ChecklistItem { check, passed, detail, waiver: None }
+}
+
+/// Run the checklist over the dry-run `ledger` and `facts`. A waiver lets a failing item
+/// through; waivers need a reason and cannot cover the circuit breaker.
+pub fn run_checklist(
+    config: &PromotionConfig,
+    ledger: &ShadowLedger,
+    facts: &PromotionFacts,
+    waivers: &BTreeMap<PromotionCheck, String>,
+    now: i64,
+) -> Result<PromotionChecklist, String> {
+    if waivers.contains_key(&PromotionCheck::CircuitBreaker) {
+        return Err("The circuit breaker check cannot be waived".to_string());
+    }
+    if waivers.values().any(|reason| reason.trim().is_empty()) {
+        return Err("Every waiver needs a reason".to_string());
+    }
+
+    let days = ledger.days_run();
+    let drawdown = ledger.max_drawdown();
+    let performance = match ledger.sharpe() {
+        Some(sharpe) => item(
+            PromotionCheck::DryRunPerformance,
+            sharpe >= config.min_sharpe && drawdown <= config.max_drawdown_pct,
+            format!("Sharpe {:.2} (at least {:.2}), max drawdown {:.1}% (at most {:.1}%)", sharpe, config.min_sharpe, drawdown * 100.0, config.max_drawdown_pct * 100.0),
+        ),
+        None => item(PromotionCheck::DryRunPerformance, false, "Fewer than two dry-run days to measure".to_string()),
+    };
+    let mut items = vec![
+        item(PromotionCheck::DryRunDays, days >= config.min_dry_run_days, format!("{} dry-run days of {} required", days, config.min_dry_run_days)),
+        performance,
+        item(
+            PromotionCheck::RiskLimits,
+            facts.limits_customized || facts.defaults_acknowledged,
+            match (facts.limits_customized, facts.defaults_acknowledged) {
+                (true, _) => "Risk limits configured".to_string(),
+                (false, true) => "Default risk limits acknowledged".to_string(),
+                (false, false) => "Risk limits are the defaults and not acknowledged".to_string(),
+            },
+        ),
+        item(PromotionCheck::ProviderHealth, facts.stream_connected, facts.stream_detail.clone()),
+        item(
+            PromotionCheck::RecentBackup,
+            facts.backup_age_days.is_some_and(|age| age <= config.max_backup_age_days),
+            match facts.backup_age_days {
+                Some(age) => format!("Newest backup is {} days old (at most {})", age, config.max_backup_age_days),
+                None => "No configuration backup found".to_string(),
+            },
+        ),
+        item(
+            PromotionCheck::CircuitBreaker,
+            !facts.circuit_breaker_active,
+            if facts.circuit_breaker_active { "Circuit breaker is active".to_string() } else { "Circuit breaker is not active".to_string() },
+        ),
+    ];
+    for item in items.iter_mut().filter(|i| !i.passed) {
+        item.waiver = waivers.get(&item.check).map(|reason| reason.trim().to_string());
+    }
+
+    let ready = items.iter().all(|i| i.passed || i.waiver.is_some());
+    Ok(PromotionChecklist {
+        items,
+        ready,
+        token: ready.then(|| Uuid::new_v4().to_string()),
+        checked_at: now,
+        expires_at: ready.then_some(now + PROMOTION_TOKEN_TTL_SECONDS),
+    })
+}
+
+/// The promotion record for `token` against the checklist still pending, if it matches
+/// and has not expired
+pub fn confirm(pending: Option<&PromotionChecklist>, token: &str, now: i64) -> Result<PromotionRecord, String> {
+    let checklist = pending
+        .filter(|c| c.ready && c.token.as_deref() == Some(token))
+        .ok_or_else(|| "Unknown promotion token; request_live_promotion again".to_string())?;
+    if checklist.expires_at.is_some_and(|at| now > at) {
+        return Err("Promotion token has expired; request_live_promotion again".to_string());
+    }
+    Ok(PromotionRecord {
+        id: Uuid::new_v4().to_string(),
+        timestamp: now,
+        action: PromotionAction::Promoted,
+        reason: None,
+        checklist: Some(checklist.clone()),
+    })
+}
+
+/// Append `record` to the journal, dropping the oldest past MAX_PROMOTION_RECORDS
+pub fn append(log: &mut Vec<PromotionRecord>, record: PromotionRecord) {
+    log.push(record);
+    log.drain(..log.len().saturating_sub(MAX_PROMOTION_RECORDS));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::types::{InstrumentType, OrderRequest, OrderSide, OrderType, TimeInForce, VenueType};
+    use chrono::NaiveDate;
+
+    fn ledger(equities: &[f64]) -> ShadowLedger {
+        let mut ledger = ShadowLedger::default();
+        let start = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        ledger.sample(start, 100_000.0);
+        let buy = OrderRequest {
+            symbol: "AAPL".to_string(),
+            side: OrderSide::Buy,
+            order_type: OrderType::Market,
+            quantity: 100,
+            price: None,
+            stop_price: None,
+            time_in_force: TimeInForce::Day,
+            client_order_id: None,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            preferred_venue: VenueType::default(),
+            expire_at: None,
+            auto_round: false,
+            override_token: None,
+            allocation: None,
+            residual_policy: None,
+        };
+        ledger.record(&[buy], |_| Some(100.0));
+        for (i, equity) in equities.iter().enumerate() {
+            // Marks that move the ledger's equity to each target
+            ledger.mark("AAPL", 100.0 + (equity - 100_000.0) / 100.0);
+            ledger.sample(start + chrono::Duration::days(i as i64 + 1), 100_000.0);
+        }
+        ledger
+    }
+
+    #[test]
+    fn test_failing_item_blocks_and_waivers_are_journaled() {
+        let config = PromotionConfig { min_dry_run_days: 5, ..PromotionConfig::default() };
+        let steady = ledger(&[100_200.0, 100_350.0, 100_300.0, 100_600.0, 100_800.0]);
+        assert_eq!(steady.days_run(), 6);
+        assert_eq!(steady.decisions, 1);
+        let facts = PromotionFacts {
+            limits_customized: true,
+            stream_connected: true,
+            stream_detail: "Connected".to_string(),
+            backup_age_days: Some(1),
+            ..PromotionFacts::default()
+        };
+        let none = BTreeMap::new();
+        let checklist = run_checklist(&config, &steady, &facts, &none, 1_000).unwrap();
+        assert!(checklist.ready, "{:?}", checklist.items);
+
+        // A missing backup blocks promotion: no token, and nothing to confirm
+        let no_backup = PromotionFacts { backup_age_days: None, ..facts.clone() };
+        let blocked = run_checklist(&config, &steady, &no_backup, &none, 1_000).unwrap();
+        assert!(!blocked.ready && blocked.token.is_none());
+        assert_eq!(blocked.items.iter().filter(|i| !i.passed).map(|i| i.check).collect::<Vec<_>>(), vec![PromotionCheck::RecentBackup]);
+        assert!(confirm(Some(&blocked), "anything", 1_001).is_err());
+
+        // So does a deep dry-run drawdown, and the circuit breaker cannot be waived
+        let slump = ledger(&[100_500.0, 88_000.0, 90_000.0, 91_000.0, 92_000.0]);
+        assert!(!run_checklist(&config, &slump, &facts, &none, 1_000).unwrap().ready);
+        let tripped = PromotionFacts { circuit_breaker_active: true, ..facts.clone() };
+        let waive_breaker = BTreeMap::from([(PromotionCheck::CircuitBreaker, "urgent".to_string())]);
+        assert!(run_checklist(&config, &steady, &tripped, &waive_breaker, 1_000).is_err());
+
+        // Waived with a reason, the item is let through and the reason is journaled
+        let waivers = BTreeMap::from([(PromotionCheck::RecentBackup, "Backup drive offline; exported by hand".to_string())]);
+        assert!(run_checklist(&config, &steady, &no_backup, &BTreeMap::from([(PromotionCheck::RecentBackup, " ".to_string())]), 1_000).is_err());
+        let waived = run_checklist(&config, &steady, &no_backup, &waivers, 1_000).unwrap();
+        let token = waived.token.clone().unwrap();
+        assert!(confirm(Some(&waived), &token, 1_000 + PROMOTION_TOKEN_TTL_SECONDS + 1).is_err());
+        let record = confirm(Some(&waived), &token, 1_010).unwrap();
+        let mut log = Vec::new();
+        append(&mut log, record);
+        let journaled = serde_json::to_value(&log).unwrap();
+        assert_eq!(journaled[0]["action"], "promoted");
+        let backup = journaled[0]["checklist"]["items"].as_array().unwrap().iter().find(|i| i["check"] == "recent_backup").unwrap();
+        assert_eq!((backup["passed"].as_bool(), backup["waiver"].as_str()), (Some(false), Some("Backup drive offline; exported by hand")));
+    }
+}
@@ -0,0 +1,212 @@
+// src-tauri/src/engine/rates.rs
+// Term-dependent carry inputs for option pricing. The risk-free rate comes from a
+// curve of a few tenor points, linearly interpolated between them and held flat past
+// either end. A symbol's continuous dividend yield is an explicit override when one is
+// set, else its trailing twelve months of per-share dividends over the spot price. The
+// Greeks, the probability pricer and the hedge finder look both up for each option's
+// time to expiry.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const DEFAULT_RISK_FREE_RATE: f64 = 0.05;
+const MAX_RATE: f64 = 0.5; // Curve points and yield overrides are annual fractions, not percents
+const TRAILING_DAYS: i64 = 365;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct RatePoint {
+    pub tenor_years: f64,
+    pub rate: f64, // Continuously compounded, e.g. 0.045
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RateCurve {
+    pub points: Vec<RatePoint>, // Ascending tenors
+    #[serde(default)]
+    pub as_of: Option<NaiveDate>, // Session of the fetched yields; None when set by hand
+}
+
+impl Default for RateCurve {
+    fn default() -> Self {
+        Self::flat(DEFAULT_RISK_FREE_RATE)
+    }
+}
+
+impl RateCurve {
+    /// A curve through `points`, sorted by tenor
+    pub fn new(mut points: Vec<RatePoint>, as_of: Option<NaiveDate>) -> Result<Self, String> {
+        if points.is_empty() {
+            return Err("A rate curve needs at least one point".to_string());
+        }
+        for point in &points {
+            if !point.tenor_years.is_finite() || point.tenor_years <= 0.0 {
+                return Err(format!("Tenor {} must be a positive number of years", point.tenor_years));
+            }
+            if !point.rate.is_finite() || point.rate.abs() > MAX_RATE {
+                return Err(format!("Rate {} at {}y is not an annual fraction", point.rate, point.tenor_years));
+            }
+        }
+        points.sort_by(|a, b| a.tenor_years.total_cmp(&b.tenor_years));
+        if points.windows(2).any(|w| w[0].tenor_years == w[1].tenor_years) {
+            return Err("Each tenor can appear only once".to_string());
+        }
+        Ok(Self { points, as_of })
+    }
+
+    /// One rate for every tenor
+    pub fn flat(rate: f64) -> Self {
+        Self { points: vec![RatePoint { tenor_years: 1.0, rate }], as_of: None }
+    }
+
+    /// Rate for `tenor_years`: linear between the neighbouring points, the nearest end's
+    /// rate outside them
+    pub fn rate_at(&self, tenor_years: f64) -> f64 {
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+            return DEFAULT_RISK_FREE_RATE;
+        };
+        if tenor_years <= first.tenor_years {
+            return first.rate;
+        }
+        if tenor_years >= last.tenor_years {
+            return last.rate;
+        }
+        let upper = self.points.partition_point(|p| p.tenor_years < tenor_years);
+        let (a, b) = (self.points[upper - 1], self.points[upper]);
+        let weight = (tenor_years - a.tenor_years) / (b.tenor_years - a.tenor_years);
+        a.rate + weight * (b.rate - a.rate)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DividendPayment {
+    pub ex_date: NaiveDate,
+    pub amount: f64, // Per share
+}
+
+/// Dividend schedules from dividend fetches and hand-set yields, by symbol
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DividendYields {
+    #[serde(default)]
+    pub schedules: HashMap<String, Vec<DividendPayment>>, // Ex-date ascending
+    #[serde(default)]
+    pub overrides: HashMap<String, f64>,
+}
+
+impl DividendYields {
+    /// Replace `symbol`'s schedule with `payments`
+    pub fn record_schedule(&mut self, symbol: &str, mut payments: Vec<DividendPayment>) {
+        payments.retain(|p| p.amount.is_finite() && p.amount > 0.0);
+        payments.sort_by_key(|p| p.ex_date);
+        self.schedules.insert(symbol.to_string(), payments);
+    }
+
+    /// Pin `symbol`'s yield, or go back to the one read from its schedule with None
+    pub fn set_override(&mut self, symbol: &str, dividend_yield: Option<f64>) -> Result<(), String> {
+        match dividend_yield {
+            Some(y) if !(y.is_finite() && (0.0..=MAX_RATE).contains(&y)) => {
+                Err(format!("Dividend yield {} is not an annual fraction", y))
+            }
+            Some(y) => {
+                self.overrides.insert(symbol.to_string(), y);
+                Ok(())
+            }
+            None => {
+                self.overrides.remove(symbol);
+                Ok(())
+            }
+        }
+    }
+
+    /// Per-share dividends of `symbol` that went ex in the year up to `today`
+    pub fn trailing_dividends(&self, symbol: &str, today: NaiveDate) -> f64 {
+        let since = today - chrono::Duration::days(TRAILING_DAYS);
+        self.schedules
+            .get(symbol)
+            .map_or(0.0, |payments| payments.iter().filter(|p| p.ex_date > since && p.ex_date <= today).map(|p| p.amount).sum())
+    }
+
+    /// Continuous yield of `symbol` at `spot`; 0 without an override or a schedule
+    pub fn yield_for(&self, symbol: &str, spot: f64, today: NaiveDate) -> f64 {
+        if let Some(y) = self.overrides.get(symbol) {
+            return *y;
+        }
+        if !spot.is_finite() || spot <= 0.0 {
+            return 0.0;
+        }
+        (self.trailing_dividends(symbol, today) / spot).min(MAX_RATE)
+    }
+}
+
+/// Rate and dividend yield for one option's time to expiry
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct Carry {
+    pub rate: f64,
+    pub dividend_yield: f64,
+}
+
+impl Carry {
+    /// A non-paying underlying at `rate`
+    pub fn rate(rate: f64) -> Self {
+        Self { rate, dividend_yield: 0.0 }
+    }
+
+    /// The underlying's risk-neutral drift
+    pub fn drift(&self) -> f64 {
+        self.rate - self.dividend_yield
+    }
+}
+
+/// Carry inputs as shown to the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateInputs {
+    pub curve: RateCurve,
+    pub dividend_yields: HashMap<String, f64>, // Held and scheduled underlyings at their current price
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_curve_interpolates_between_tenors_and_holds_flat_outside() {
+        let point = |tenor_years, rate| RatePoint { tenor_years, rate };
+        let curve = RateCurve::new(vec![point(10.0, 0.042), point(0.25, 0.052), point(2.0, 0.046)], None).unwrap();
+        assert_eq!(curve.points[0].tenor_years, 0.25);
+
+        // Halfway between 2y and 10y is halfway between their rates
+        assert!((curve.rate_at(6.0) - 0.044).abs() < 1e-12);
+        assert!((curve.rate_at(1.125) - 0.049).abs() < 1e-12);
+        assert_eq!(curve.rate_at(2.0), 0.046);
+        assert_eq!(curve.rate_at(0.01), 0.052);
+        assert_eq!(curve.rate_at(30.0), 0.042);
+        assert_eq!(RateCurve::default().rate_at(7.0), DEFAULT_RISK_FREE_RATE);
+
+        assert!(RateCurve::new(vec![], None).is_err());
+        assert!(RateCurve::new(vec![point(0.0, 0.05)], None).is_err());
+        assert!(RateCurve::new(vec![point(1.0, 5.0)], None).is_err(), "percent, not a fraction");
+        assert!(RateCurve::new(vec![point(1.0, 0.05), point(1.0, 0.04)], None).is_err());
+    }
+
+    #[test]
+    fn test_yield_reads_the_trailing_year_unless_overridden() {
+        let mut yields = DividendYields::default();
+        let quarterly = |m, d| DividendPayment { ex_date: date(2024, m, d), amount: 0.75 };
+        yields.record_schedule("XOM", vec![quarterly(8, 15), quarterly(2, 13), quarterly(5, 14), quarterly(11, 14)]);
+
+        // Three of the four went ex by September; $2.25 on $100
+        assert!((yields.yield_for("XOM", 100.0, date(2024, 9, 1)) - 0.0225).abs() < 1e-12);
+        assert!((yields.yield_for("XOM", 100.0, date(2025, 1, 2)) - 0.03).abs() < 1e-12);
+        assert_eq!(yields.yield_for("AAPL", 100.0, date(2024, 9, 1)), 0.0);
+
+        yields.set_override("XOM", Some(0.035)).unwrap();
+        assert_eq!(yields.yield_for("XOM", 100.0, date(2024, 9, 1)), 0.035);
+        yields.set_override("XOM", None).unwrap();
+        assert!(yields.set_override("XOM", Some(3.5)).is_err());
+        assert!((yields.yield_for("XOM", 100.0, date(2024, 9, 1)) - 0.0225).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,243 @@
+// src-tauri/src/engine/rejections.rs
+// Orders the broker turned away before they reached the book. Every rejection is logged
+// with why it happened, who placed the order and the session it was placed in, and the
+// log is aggregated into a report for working out why orders keep failing. Orders placed
+// past soft risk limits with an override token are logged and reported alongside.
+
+use super::calendar::MarketSession;
+use super::risk::{RiskCheckResult, RiskViolation, RiskViolationType};
+use super::types::{OrderRequest, OrderSide};
+use crate::storage::config_bundle;
+use chrono::{NaiveDate, Timelike};
+use chrono_tz::America::New_York;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionReason {
+    Validation,         // Malformed request, tick size, expiry
+    StaleQuote,         // Limit order on a stale quote
+    RiskCheck,          // Blocked by a hard risk limit
+    RiskConfirmation,   // Only soft risk limits; an override token came back with the rejection
+    Routing,            // No venue would take it
+    BuyingPower,
+    InsufficientShares, // Sell larger than the position, short selling off
+    Session,            // Not valid in the current session, e.g. on-open after the open
+    Allocation,         // Over its sub-account's exposure or cash
+    MarginCall,         // Would add to the requirement while a maintenance call is open
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectionSource {
+    Manual,
+    Strategy, // Placed by the strategy loop for an evaluation
+    System,   // Tagged orders such as de-risking and hedges
+}
+
+/// Why `evaluate_order` turned an order away
+#[derive(Debug, Clone)]
+pub struct Rejection {
+    pub reason: RejectionReason,
+    pub message: String,
+    pub risk_check: Option<Box<RiskCheckResult>>, // For risk check failures
+    pub override_token: Option<String>,           // Issued for soft risk check failures
+}
+
+impl Rejection {
+    pub fn new(reason: RejectionReason, message: impl Into<String>) -> Self {
+        Self { reason, message: message.into(), risk_check: None, override_token: None }
+    }
+}
+
+/// How long an override token can be redeemed for
+pub const OVERRIDE_TOKEN_TTL_SECONDS: i64 = 120;
+
+/// One-time pass past the soft risk violations an order was rejected for
+#[derive(Debug, Clone)]
+pub struct OverrideToken {
+    pub request_hash: String,
+    pub violations: Vec<RiskViolationType>,
+    pub expires_at: i64,
+}
+
+impl OverrideToken {
+    /// Whether the token lets `request_hash` past `violations` at `now`; the reason not otherwise
+    pub fn check(&self, request_hash: &str, violations: &[RiskViolationType], now: i64) -> Result<(), String> {
+        if now >= self.expires_at {
+            return Err("the override token has expired".to_string());
+        }
+        if request_hash != self.request_hash {
+            return Err("the order differs from the one the override token was issued for".to_string());
+        }
+        if let Some(new) = violations.iter().find(|v| !self.violations.contains(v)) {
+            return Err(format!("the override token does not cover {:?}", new));
+        }
+        Ok(())
+    }
+}
+
+/// Hash of `request` as submitted, leaving out its override token
+pub fn request_hash(request: &OrderRequest) -> String {
+    let mut request = request.clone();
+    request.override_token = None;
+    config_bundle::checksum(&serde_json::to_value(&request).unwrap_or_default())
+}
+
+impl From<Rejection> for String {
+    fn from(rejection: Rejection) -> Self {
+        rejection.message
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderRejection {
+    pub id: String,
+    pub timestamp: i64,
+    pub session_date: NaiveDate,
+    pub session: MarketSession,
+    pub source: RejectionSource,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub quantity: i64,
+    pub requested_notional: f64, // Quantity at the order's price, or the last price; 0 without either
+    pub reason: RejectionReason,
+    pub message: String,
+    #[serde(default)]
+    pub risk_check: Option<RiskCheckResult>,
+    #[serde(default)]
+    pub evaluation_id: Option<String>,
+    #[serde(default)]
+    pub override_token: Option<String>,
+}
+
+/// An order placed past soft risk violations with an override token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskOverride {
+    pub id: String,
+    pub timestamp: i64,
+    pub session_date: NaiveDate,
+    pub source: RejectionSource,
+    pub order_id: String,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub quantity: i64,
+    pub override_token: String,
+    pub bypassed: Vec<RiskViolation>,
+    #[serde(default)]
+    pub evaluation_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectionReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub total: usize,
+    pub by_reason: BTreeMap<RejectionReason, usize>,
+    pub by_symbol: BTreeMap<String, usize>,
+    pub by_time_of_day: BTreeMap<String, usize>, // "HH:MM" start of each Eastern time bucket
+    pub by_source: BTreeMap<RejectionSource, usize>,
+    pub recent: Vec<OrderRejection>, // Newest first
+    pub overrides: usize,
+    pub overrides_by_violation: BTreeMap<RiskViolationType, usize>,
+    pub recent_overrides: Vec<RiskOverride>, // Newest first
+}
+
+/// Start of the `bucket_minutes` Eastern time bucket `timestamp` falls in, as "HH:MM"
+pub fn time_of_day_bucket(timestamp: i64, bucket_minutes: u32) -> String {
+    let local = chrono::DateTime::from_timestamp(timestamp, 0).unwrap_or_default().with_timezone(&New_York);
+    let minute = local.hour() * 60 + local.minute();
+    let start = minute - minute % bucket_minutes.clamp(1, 24 * 60);
+    format!("{:02}:{:02}", start / 60, start % 60)
+}
+
+/// Aggregate the rejections and overrides from sessions `from` through `to`, keeping the
+/// `recent_limit` newest of each in full
+pub fn build_report(
+    rejections: &[OrderRejection],
+    overrides: &[RiskOverride],
+    from: NaiveDate,
+    to: NaiveDate,
+    bucket_minutes: u32,
+    recent_limit: usize,
+) -> RejectionReport {
+    let mut in_range: Vec<&OrderRejection> = rejections.iter().filter(|r| r.session_date >= from && r.session_date <= to).collect();
+    in_range.sort_by_key(|r| std::cmp::Reverse(r.timestamp));
+    let mut overrides: Vec<&RiskOverride> = overrides.iter().filter(|o| o.session_date >= from && o.session_date <= to).collect();
+    overrides.sort_by_key(|o| std::cmp::Reverse(o.timestamp));
+
+    let mut report = RejectionReport {
+        from,
+        to,
+        total: in_range.len(),
+        by_reason: BTreeMap::new(),
+        by_symbol: BTreeMap::new(),
+        by_time_of_day: BTreeMap::new(),
+        by_source: BTreeMap::new(),
+        recent: in_range.iter().take(recent_limit).map(|r| (*r).clone()).collect(),
+        overrides: overrides.len(),
+        overrides_by_violation: BTreeMap::new(),
+        recent_overrides: overrides.iter().take(recent_limit).map(|o| (*o).clone()).collect(),
+    };
+    for rejection in in_range {
+        *report.by_reason.entry(rejection.reason).or_default() += 1;
+        *report.by_symbol.entry(rejection.symbol.clone()).or_default() += 1;
+        *report.by_time_of_day.entry(time_of_day_bucket(rejection.timestamp, bucket_minutes)).or_default() += 1;
+        *report.by_source.entry(rejection.source).or_default() += 1;
+    }
+    for risk_override in overrides {
+        for violation in &risk_override.bypassed {
+            *report.overrides_by_violation.entry(violation.violation_type.clone()).or_default() += 1;
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejection(timestamp: i64, symbol: &str, reason: RejectionReason, source: RejectionSource) -> OrderRejection {
+        OrderRejection {
+            id: timestamp.to_string(),
+            timestamp,
+            session_date: crate::engine::position_history::session_date(timestamp),
+            session: MarketSession::Regular,
+            source,
+            symbol: symbol.to_string(),
+            side: OrderSide::Buy,
+            quantity: 10,
+            requested_notional: 1_000.0,
+            reason,
+            message: "rejected".to_string(),
+            risk_check: None,
+            evaluation_id: None,
+            override_token: None,
+        }
+    }
+
+    #[test]
+    fn test_report_counts_and_buckets() {
+        // 2024-01-09 09:35 and 09:50 ET, 2024-01-10 15:59 ET and 2024-01-12 10:00 ET
+        let rejections = vec![
+            rejection(1_704_810_900, "ACME", RejectionReason::RiskCheck, RejectionSource::Strategy),
+            rejection(1_704_811_800, "ACME", RejectionReason::BuyingPower, RejectionSource::Manual),
+            rejection(1_704_920_340, "XYZ", RejectionReason::RiskCheck, RejectionSource::Strategy),
+            rejection(1_705_071_600, "XYZ", RejectionReason::Validation, RejectionSource::Manual),
+        ];
+        let from = NaiveDate::from_ymd_opt(2024, 1, 9).unwrap();
+        let to = NaiveDate::from_ymd_opt(2024, 1, 10).unwrap();
+        let report = build_report(&rejections, &[], from, to, 30, 2);
+
+        assert_eq!(report.total, 3);
+        assert_eq!(report.by_reason[&RejectionReason::RiskCheck], 2);
+        assert_eq!(report.by_reason[&RejectionReason::BuyingPower], 1);
+        assert!(!report.by_reason.contains_key(&RejectionReason::Validation));
+        assert_eq!((report.by_symbol["ACME"], report.by_symbol["XYZ"]), (2, 1));
+        assert_eq!(report.by_time_of_day["09:30"], 2);
+        assert_eq!(report.by_time_of_day["15:30"], 1);
+        assert_eq!(report.by_source[&RejectionSource::Strategy], 2);
+        assert_eq!(report.recent.iter().map(|r| r.timestamp).collect::<Vec<_>>(), vec![1_704_920_340, 1_704_811_800]);
+    }
+}
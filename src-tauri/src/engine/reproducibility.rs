@@ -0,0 +1,185 @@
+// src-tauri/src/engine/reproducibility.rs
+// Reproducibility manifests for saved backtests: the exact bars a run used (by content
+// hash, embedded or pinned), its parameters and seed, the engine build and fill timing,
+// and a hash of the result. Re-running from a manifest and comparing hashes shows
+// whether the result reproduces bit for bit.
+
+use crate::commands::backtest::{BacktestParams, BacktestSummary};
+use crate::market_data::types::Candle;
+use crate::storage::config_bundle::checksum;
+use serde::{Deserialize, Serialize};
+
+/// 2: results hash with native dates; hashes in older manifests were taken over MM/DD/YYYY strings
+pub const MANIFEST_SCHEMA_VERSION: u32 = 2;
+
+/// Cargo features that change how the engine runs, hashed into the manifest
+const ENGINE_FEATURES: [(&str, bool); 1] = [("sqlite", cfg!(feature = "sqlite"))];
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FillTiming {
+    #[default]
+    BarClose, // Signals fill at the close of the bar that raised them
+}
+
+/// Where a manifest's bars are kept
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum BarSource {
+    Embedded { bars: Vec<Candle> },
+    Pinned, // In the backtest store's pins, by content hash
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BarSeriesRecord {
+    pub symbol: String,
+    pub count: usize,
+    pub first_timestamp: Option<i64>,
+    pub last_timestamp: Option<i64>,
+    pub content_hash: String,
+    pub source: BarSource,
+}
+
+impl BarSeriesRecord {
+    pub fn new(symbol: &str, bars: &[Candle], embed: bool) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            count: bars.len(),
+            first_timestamp: bars.first().map(|b| b.timestamp),
+            last_timestamp: bars.last().map(|b| b.timestamp),
+            content_hash: bars_hash(bars),
+            source: if embed { BarSource::Embedded { bars: bars.to_vec() } } else { BarSource::Pinned },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestManifest {
+    pub schema_version: u32,
+    pub run_id: String,
+    pub created_at: i64,
+    pub engine_version: String,
+    pub feature_hash: String,
+    #[serde(default)]
+    pub fill_timing: FillTiming,
+    pub params: BacktestParams, // Strategy, costs and seed
+    pub series: Vec<BarSeriesRecord>, // The ticker's bars, or each leg's for a pair
+    pub result_hash: String,
+}
+
+impl BacktestManifest {
+    pub fn new(summary: &BacktestSummary, params: &BacktestParams, series: Vec<BarSeriesRecord>) -> Self {
+        Self {
+            schema_version: MANIFEST_SCHEMA_VERSION,
+            run_id: summary.id.clone().unwrap_or_default(),
+            created_at: chrono::Utc::now().timestamp(),
+            engine_version: env!("CARGO_PKG_VERSION").to_string(),
+            feature_hash: engine_feature_hash(),
+            fill_timing: FillTiming::BarClose,
+            params: params.clone(),
+            series,
+            result_hash: result_hash(summary),
+        }
+    }
+}
+
+/// Hash of the prices, volumes and times of `bars`; symbol and interval labels are left out
+pub fn bars_hash(bars: &[Candle]) -> String {
+    let rows: Vec<(i64, f64, f64, f64, f64, u64)> = bars.iter().map(|b| (b.timestamp, b.open, b.high, b.low, b.close, b.volume)).collect();
+    checksum(&serde_json::to_value(rows).unwrap_or_default())
+}
+
+/// Hash of everything a run produced; the saved id is not part of it
+pub fn result_hash(summary: &BacktestSummary) -> String {
+    let mut summary = summary.clone();
+    summary.id = None;
+    checksum(&serde_json::to_value(&summary).unwrap_or_default())
+}
+
+pub fn engine_feature_hash() -> String {
+    checksum(&serde_json::to_value(ENGINE_FEATURES).unwrap_or_default())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MetricDiff {
+    pub metric: String,
+    pub original: f64,
+    pub reproduced: f64,
+}
+
+/// Headline metrics that differ between two runs
+pub fn diff_metrics(original: &BacktestSummary, reproduced: &BacktestSummary) -> Vec<MetricDiff> {
+    let metrics = |s: &BacktestSummary| {
+        [
+            ("cagr", s.cagr),
+            ("trades", s.trades as f64),
+            ("win_rate", s.win_rate),
+            ("max_dd", s.max_dd),
+            ("max_dd_realized", s.max_dd_realized),
+            ("missing_trading_days", s.missing_trading_days as f64),
+            ("total_transaction_costs", s.total_transaction_costs),
+            ("gross_pnl", s.gross_pnl),
+            ("net_pnl", s.net_pnl),
+            ("equity_points", s.equity_curve.len() as f64),
+            ("final_equity", s.equity_curve.last().map_or(0.0, |p| p.equity)),
+        ]
+    };
+    metrics(original)
+        .into_iter()
+        .zip(metrics(reproduced))
+        .filter(|((_, a), (_, b))| a.to_bits() != b.to_bits())
+        .map(|((metric, original), (_, reproduced))| MetricDiff { metric: metric.to_string(), original, reproduced })
+        .collect()
+}
+
+/// Outcome of re-running a saved backtest from its manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReproductionReport {
+    pub id: String,
+    pub matches: bool, // The new result hashes the same as the original
+    pub original_hash: String,
+    pub reproduced_hash: String,
+    pub diffs: Vec<MetricDiff>,
+    pub restated_series: Vec<String>, // Symbols whose stored bars were lost and refetched with different data
+    pub warnings: Vec<String>,
+    pub reproduced: BacktestSummary, // Without its trade log
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::backtest::summarize_backtest;
+
+    #[test]
+    fn test_manifest_hashes_reproduce_and_detect_restatements() {
+        let candles: Vec<Candle> = (0..300)
+            .map(|i| {
+                let close = 100.0 + (i as f64 * 0.1).sin() * 10.0 + i as f64 * 0.05;
+                Candle::new(1_704_205_800 + i * 86_400, close, close + 1.0, close - 1.0, close, 1_000)
+            })
+            .collect();
+        let params: BacktestParams = serde_json::from_value(serde_json::json!({
+            "ticker": "ABC", "start_date": "01/02/2024", "end_date": "10/27/2024",
+            "strategy": "MeanReversion", "initial_capital": 100000.0, "seed": 7
+        }))
+        .unwrap();
+        let mut original = summarize_backtest(&params, &candles);
+        original.id = Some("run".to_string());
+        let manifest = BacktestManifest::new(&original, &params, vec![BarSeriesRecord::new("ABC", &candles, true)]);
+
+        // The same inputs give the same hash, whatever the saved id
+        let BarSource::Embedded { bars } = &manifest.series[0].source else { panic!("bars were embedded") };
+        assert_eq!(bars_hash(bars), manifest.series[0].content_hash);
+        let reproduced = summarize_backtest(&manifest.params, bars);
+        assert_eq!(result_hash(&reproduced), manifest.result_hash);
+        assert!(diff_metrics(&original, &reproduced).is_empty());
+
+        // A restated bar changes the series hash and the metrics it feeds
+        let mut restated = candles.clone();
+        restated[150].close *= 1.5;
+        assert_ne!(bars_hash(&restated), manifest.series[0].content_hash);
+        let rerun = summarize_backtest(&params, &restated);
+        assert_ne!(result_hash(&rerun), manifest.result_hash);
+        assert!(diff_metrics(&original, &rerun).iter().any(|d| d.metric == "final_equity"));
+    }
+}
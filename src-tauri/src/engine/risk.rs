@@ -2,12 +2,15 @@
 // Risk management engine with circuit breakers and position limits
 
 use super::types::*;
-use super::mtm::PortfolioGreeks;
+use super::mtm::{is_option_symbol, PortfolioGreeks};
+use super::drawdown::DrawdownStatus;
+use super::margin::MarginSummary;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use super::position_history::session_date;
+use chrono::{NaiveDate, Utc};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct RiskLimits {
     // Daily limits
     pub max_daily_loss: f64,           // Maximum daily loss allowed
@@ -24,11 +27,47 @@ pub struct RiskLimits {
     pub max_option_gamma: f64,         // Maximum portfolio gamma
     pub max_option_vega: f64,          // Maximum portfolio vega
     pub max_contracts_per_trade: i64,  // Maximum option contracts per trade
+
+    // Portfolio-level exposure limits
+    pub max_total_option_exposure: f64, // Maximum total option notional ($)
+    pub max_total_stock_exposure: f64,  // Maximum total stock notional as fraction of equity
+    pub max_option_portfolio_pct: f64,  // Maximum option notional as fraction of equity
     
     // Circuit breaker settings
     pub circuit_breaker_loss_pct: f64, // Trigger circuit breaker at this loss %
     pub circuit_breaker_duration_minutes: i64, // How long to halt trading
     pub max_consecutive_losses: i32,    // Max consecutive losing trades
+
+    // Which violations block outright and which an override token can bypass
+    #[serde(default = "default_violation_classes")]
+    pub violation_classes: BTreeMap<RiskViolationType, ViolationClass>, // Types not listed are hard
+}
+
+/// Hard violations always reject the order. Soft ones reject it with a one-time override
+/// token, and resubmitting the same order with the token places it anyway.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationClass {
+    Hard,
+    Soft,
+}
+
+fn default_violation_classes() -> BTreeMap<RiskViolationType, ViolationClass> {
+    [
+        (RiskViolationType::TradeSizeLimit, ViolationClass::Soft),
+        (RiskViolationType::PositionSizeLimit, ViolationClass::Soft),
+        (RiskViolationType::ConcentrationLimit, ViolationClass::Soft),
+        (RiskViolationType::DailyLossLimit, ViolationClass::Hard),
+        (RiskViolationType::CircuitBreaker, ViolationClass::Hard),
+    ]
+    .into_iter()
+    .collect()
+}
+
+impl RiskLimits {
+    pub fn violation_class(&self, violation_type: &RiskViolationType) -> ViolationClass {
+        self.violation_classes.get(violation_type).copied().unwrap_or(ViolationClass::Hard)
+    }
 }
 
 impl Default for RiskLimits {
@@ -49,11 +88,18 @@ impl Default for RiskLimits {
             max_option_gamma: 100.0,        // 100 gamma max
             max_option_vega: 1000.0,        // $1000 vega max
             max_contracts_per_trade: 50,    // 50 contracts max per trade
+
+            // Portfolio exposure limits
+            max_total_option_exposure: 50000.0, // $50k option notional max
+            max_total_stock_exposure: 1.0,      // 100% of equity in stock
+            max_option_portfolio_pct: 0.20,     // 20% of equity in options
             
             // Circuit breakers
             circuit_breaker_loss_pct: 0.10, // 10% portfolio loss
             circuit_breaker_duration_minutes: 60, // 1 hour halt
             max_consecutive_losses: 5,       // 5 consecutive losses
+
+            violation_classes: default_violation_classes(),
         }
     }
 }
@@ -68,12 +114,20 @@ pub struct RiskMetrics {
     pub portfolio_delta: f64,
     pub portfolio_gamma: f64,
     pub portfolio_vega: f64,
+    pub total_option_notional: f64,
+    pub option_pct_of_portfolio: f64,
     pub circuit_breaker_active: bool,
     pub circuit_breaker_until: Option<i64>,
+    #[serde(default)]
+    pub circuit_breaker_fired: bool, // Tripped at any point today, even if since expired
     pub last_updated: i64,
+    #[serde(default)]
+    pub drawdown: Option<DrawdownStatus>, // The account's high-water marks; filled in by the broker
+    #[serde(default)]
+    pub margin: Option<MarginSummary>,    // Buying power and requirements; filled in by the broker
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum RiskViolationType {
     DailyLossLimit,
     DailyTradeLimit,
@@ -85,6 +139,7 @@ pub enum RiskViolationType {
     GammaLimit,
     VegaLimit,
     ContractLimit,
+    OptionExposureLimit,
     CircuitBreaker,
     ConsecutiveLossLimit,
 }
@@ -111,6 +166,42 @@ pub struct RiskCheckResult {
     pub allowed: bool,
     pub violations: Vec<RiskViolation>,
     pub warnings: Vec<RiskViolation>,
+    #[serde(default)]
+    pub overridden: Vec<RiskViolation>, // Soft violations bypassed with an override token
+}
+
+impl RiskCheckResult {
+    /// The violations `limits` class as soft, or None if any is hard
+    pub fn soft_violations(&self, limits: &RiskLimits) -> Option<Vec<RiskViolationType>> {
+        let mut soft = Vec::new();
+        for violation in &self.violations {
+            if limits.violation_class(&violation.violation_type) == ViolationClass::Hard {
+                return None;
+            }
+            if !soft.contains(&violation.violation_type) {
+                soft.push(violation.violation_type.clone());
+            }
+        }
+        Some(soft)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExposureBreakdown {
+    pub equity: f64,
+    pub stock_notional: f64,
+    pub option_notional: f64,
+    pub total_notional: f64,
+    pub stock_pct_of_portfolio: f64,
+    pub option_pct_of_portfolio: f64,
+    pub stock_position_count: usize,
+    pub option_position_count: usize,
+    pub max_total_option_exposure: f64,
+    pub max_total_stock_exposure: f64, // In dollars, derived from equity
+    pub max_option_portfolio_pct: f64,
+    pub stock_within_limit: bool,
+    pub option_within_limit: bool,
+    pub timestamp: i64,
 }
 
 #[derive(Debug, Clone)]
@@ -119,6 +210,7 @@ pub struct RiskEngine {
     pub metrics: RiskMetrics,
     pub daily_trades: Vec<String>, // Trade IDs for today
     pub recent_trades: Vec<(i64, f64)>, // (timestamp, pnl) for consecutive loss tracking
+    pub closed_day: Option<RiskMetrics>, // Metrics at the last daily reset, until the broker records them
 }
 
 impl Default for RiskEngine {
@@ -140,17 +232,23 @@ impl RiskEngine {
                 portfolio_delta: 0.0,
                 portfolio_gamma: 0.0,
                 portfolio_vega: 0.0,
+                total_option_notional: 0.0,
+                option_pct_of_portfolio: 0.0,
                 circuit_breaker_active: false,
                 circuit_breaker_until: None,
+                circuit_breaker_fired: false,
                 last_updated: Utc::now().timestamp(),
+                drawdown: None,
+                margin: None,
             },
             daily_trades: Vec::new(),
             recent_trades: Vec::new(),
+            closed_day: None,
         }
     }
 
     pub fn check_order_risk(
-        &mut self,
+        &self,
         order: &OrderRequest,
         portfolio_equity: f64,
         positions: &HashMap<String, Position>,
@@ -173,6 +271,7 @@ impl RiskEngine {
                 allowed: false,
                 violations,
                 warnings,
+                overridden: Vec::new(),
             };
         }
 
@@ -265,6 +364,35 @@ impl RiskEngine {
                 });
             }
 
+            // Check portfolio option exposure
+            let (_, option_notional) = Self::exposure_notional(positions);
+            let new_option_notional = Self::notional_after_order(order, option_notional, positions, estimated_price);
+            if new_option_notional > option_notional {
+                if new_option_notional > self.limits.max_total_option_exposure {
+                    violations.push(RiskViolation {
+                        violation_type: RiskViolationType::OptionExposureLimit,
+                        message: format!("Option notional ${:.2} would exceed limit ${:.2}", new_option_notional, self.limits.max_total_option_exposure),
+                        current_value: new_option_notional,
+                        limit_value: self.limits.max_total_option_exposure,
+                        timestamp: Utc::now().timestamp(),
+                        severity: RiskSeverity::Error,
+                    });
+                }
+
+                let option_pct = if portfolio_equity > 0.0 { new_option_notional / portfolio_equity } else { f64::INFINITY };
+                if option_pct > self.limits.max_option_portfolio_pct {
+                    violations.push(RiskViolation {
+                        violation_type: RiskViolationType::OptionExposureLimit,
+                        message: format!("Option exposure {:.1}% of portfolio would exceed limit {:.1}%",
+                            option_pct * 100.0, self.limits.max_option_portfolio_pct * 100.0),
+                        current_value: option_pct,
+                        limit_value: self.limits.max_option_portfolio_pct,
+                        timestamp: Utc::now().timestamp(),
+                        severity: RiskSeverity::Error,
+                    });
+                }
+            }
+
             // Check Greeks limits if available
             if let Some(greeks) = portfolio_greeks {
                 if greeks.delta.abs() > self.limits.max_option_delta {
@@ -342,6 +470,7 @@ impl RiskEngine {
             allowed: violations.is_empty(),
             violations,
             warnings,
+            overridden: Vec::new(),
         }
     }
 
@@ -373,20 +502,88 @@ impl RiskEngine {
             self.metrics.portfolio_vega = greeks.vega;
         }
 
-        // Reset daily counters if it's a new day
-        let today = Utc::now().date_naive();
-        let last_update_date = DateTime::from_timestamp(self.metrics.last_updated, 0)
-            .map(|dt| dt.date_naive())
-            .unwrap_or(today);
-
-        if today != last_update_date {
+        // Reset daily counters on a new session date, as the broker rolls its day
+        let today = session_date(Utc::now().timestamp());
+        if today != session_date(self.metrics.last_updated) {
             self.reset_daily_counters();
         }
 
         self.metrics.last_updated = Utc::now().timestamp();
     }
 
-    fn is_circuit_breaker_active(&self) -> bool {
+    pub fn update_exposure_metrics(&mut self, positions: &HashMap<String, Position>, portfolio_equity: f64) {
+        let (_, option_notional) = Self::exposure_notional(positions);
+        self.metrics.total_option_notional = option_notional;
+        self.metrics.option_pct_of_portfolio = if portfolio_equity > 0.0 {
+            option_notional / portfolio_equity
+        } else {
+            0.0
+        };
+    }
+
+    pub fn get_exposure_breakdown(&self, positions: &HashMap<String, Position>, portfolio_equity: f64) -> ExposureBreakdown {
+        let (stock_notional, option_notional) = Self::exposure_notional(positions);
+        let option_position_count = positions.keys().filter(|s| is_option_symbol(s)).count();
+        let pct = |notional: f64| if portfolio_equity > 0.0 { notional / portfolio_equity } else { 0.0 };
+        let max_stock_exposure = self.limits.max_total_stock_exposure * portfolio_equity;
+
+        ExposureBreakdown {
+            equity: portfolio_equity,
+            stock_notional,
+            option_notional,
+            total_notional: stock_notional + option_notional,
+            stock_pct_of_portfolio: pct(stock_notional),
+            option_pct_of_portfolio: pct(option_notional),
+            stock_position_count: positions.len() - option_position_count,
+            option_position_count,
+            max_total_option_exposure: self.limits.max_total_option_exposure,
+            max_total_stock_exposure: max_stock_exposure,
+            max_option_portfolio_pct: self.limits.max_option_portfolio_pct,
+            stock_within_limit: stock_notional <= max_stock_exposure,
+            option_within_limit: option_notional <= self.limits.max_total_option_exposure
+                && pct(option_notional) <= self.limits.max_option_portfolio_pct,
+            timestamp: Utc::now().timestamp(),
+        }
+    }
+
+    /// Sum of |position value| split into (stock, option) notional
+    fn exposure_notional(positions: &HashMap<String, Position>) -> (f64, f64) {
+        let mut stock_notional = 0.0;
+        let mut option_notional = 0.0;
+
+        for (symbol, position) in positions {
+            if is_option_symbol(symbol) {
+                option_notional += position.market_value.abs();
+            } else {
+                stock_notional += position.market_value.abs();
+            }
+        }
+
+        (stock_notional, option_notional)
+    }
+
+    /// Notional of the order's instrument bucket once the order is applied
+    fn notional_after_order(
+        order: &OrderRequest,
+        current_notional: f64,
+        positions: &HashMap<String, Position>,
+        estimated_price: f64,
+    ) -> f64 {
+        let signed_quantity = match order.side {
+            OrderSide::Buy => order.quantity,
+            OrderSide::Sell => -order.quantity,
+        };
+
+        let (current_quantity, current_value) = positions
+            .get(&order.symbol)
+            .map(|p| (p.quantity, p.market_value.abs()))
+            .unwrap_or((0, 0.0));
+
+        let new_value = ((current_quantity + signed_quantity) as f64 * estimated_price).abs();
+        current_notional - current_value + new_value
+    }
+
+    pub fn is_circuit_breaker_active(&self) -> bool {
         if !self.metrics.circuit_breaker_active {
             return false;
         }
@@ -400,6 +597,7 @@ impl RiskEngine {
 
     fn trigger_circuit_breaker(&mut self) {
         self.metrics.circuit_breaker_active = true;
+        self.metrics.circuit_breaker_fired = true;
         self.metrics.circuit_breaker_until = Some(
             Utc::now().timestamp() + (self.limits.circuit_breaker_duration_minutes * 60)
         );
@@ -423,12 +621,27 @@ impl RiskEngine {
         self.metrics.consecutive_losses = consecutive;
     }
 
+    /// The metrics `date` closed with, resetting the daily counters unless
+    /// `update_daily_metrics` already did on the new date
+    pub fn close_day(&mut self, date: NaiveDate) -> RiskMetrics {
+        if let Some(closed) = self.closed_day.take().filter(|m| session_date(m.last_updated) == date) {
+            return closed;
+        }
+        let closing = self.metrics.clone();
+        self.reset_daily_counters();
+        self.closed_day = None;
+        self.metrics.last_updated = Utc::now().timestamp();
+        closing
+    }
+
     fn reset_daily_counters(&mut self) {
+        self.closed_day = Some(self.metrics.clone());
         self.metrics.daily_trades = 0;
         self.metrics.daily_volume = 0.0;
         self.daily_trades.clear();
         self.metrics.circuit_breaker_active = false;
         self.metrics.circuit_breaker_until = None;
+        self.metrics.circuit_breaker_fired = false;
     }
 
     pub fn get_risk_status(&self) -> RiskMetrics {
@@ -457,3 +670,101 @@ impl RiskEngine {
         summary
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTION_SYMBOL: &str = "AAPL240315C00150000";
+
+    fn create_position(symbol: &str, quantity: i64, price: f64) -> Position {
+        let mut position = Position::new(symbol.to_string());
+        position.quantity = quantity;
+        position.avg_cost = price;
+        position.update_market_data(price);
+        position
+    }
+
+    fn create_option_order(symbol: &str, quantity: i64, price: f64) -> OrderRequest {
+        OrderRequest {
+            symbol: symbol.to_string(),
+            side: OrderSide::Buy,
+            order_type: OrderType::Limit,
+            quantity,
+            price: Some(price),
+            stop_price: None,
+            time_in_force: TimeInForce::Day,
+            client_order_id: None,
+            instrument_type: InstrumentType::Option,
+            option_details: None,
+            preferred_venue: VenueType::Smart,
+            expire_at: None,
+            auto_round: false,
+            override_token: None,
+            allocation: None,
+            residual_policy: None,
+        }
+    }
+
+    fn has_violation(result: &RiskCheckResult, violation_type: RiskViolationType) -> bool {
+        result.violations.iter().any(|v| v.violation_type == violation_type)
+    }
+
+    #[test]
+    fn test_option_exposure_dollar_limit() {
+        let engine = RiskEngine::default();
+        let mut positions = HashMap::new();
+        positions.insert(OPTION_SYMBOL.to_string(), create_position(OPTION_SYMBOL, 10, 4500.0));
+
+        // $45k existing + $6k order = $51k > $50k limit
+        let order = create_option_order("AAPL240315P00140000", 10, 600.0);
+        let result = engine.check_order_risk(&order, 1_000_000.0, &positions, None);
+        assert!(!result.allowed);
+        assert!(has_violation(&result, RiskViolationType::OptionExposureLimit));
+
+        // $45k existing + $4k order = $49k stays under the limit
+        let order = create_option_order("AAPL240315P00140000", 10, 400.0);
+        let result = engine.check_order_risk(&order, 1_000_000.0, &positions, None);
+        assert!(!has_violation(&result, RiskViolationType::OptionExposureLimit));
+    }
+
+    #[test]
+    fn test_option_exposure_portfolio_pct_limit() {
+        let engine = RiskEngine::default();
+        let mut positions = HashMap::new();
+        positions.insert(OPTION_SYMBOL.to_string(), create_position(OPTION_SYMBOL, 10, 1500.0));
+
+        // $15k existing + $6k order = 21% of $100k equity > 20%
+        let order = create_option_order("AAPL240315P00140000", 10, 600.0);
+        let result = engine.check_order_risk(&order, 100_000.0, &positions, None);
+        assert!(has_violation(&result, RiskViolationType::OptionExposureLimit));
+
+        // Selling to reduce the existing position never trips the exposure cap
+        let mut order = create_option_order(OPTION_SYMBOL, 5, 1500.0);
+        order.side = OrderSide::Sell;
+        let result = engine.check_order_risk(&order, 100_000.0, &positions, None);
+        assert!(!has_violation(&result, RiskViolationType::OptionExposureLimit));
+    }
+
+    #[test]
+    fn test_exposure_breakdown() {
+        let mut engine = RiskEngine::default();
+        let mut positions = HashMap::new();
+        positions.insert("MSFT".to_string(), create_position("MSFT", 100, 300.0));
+        positions.insert(OPTION_SYMBOL.to_string(), create_position(OPTION_SYMBOL, -5, 2000.0));
+
+        let breakdown = engine.get_exposure_breakdown(&positions, 100_000.0);
+        assert_eq!(breakdown.stock_notional, 30_000.0);
+        assert_eq!(breakdown.option_notional, 10_000.0);
+        assert_eq!(breakdown.total_notional, 40_000.0);
+        assert_eq!(breakdown.stock_position_count, 1);
+        assert_eq!(breakdown.option_position_count, 1);
+        assert!((breakdown.option_pct_of_portfolio - 0.10).abs() < 1e-9);
+        assert!(breakdown.stock_within_limit);
+        assert!(breakdown.option_within_limit);
+
+        engine.update_exposure_metrics(&positions, 100_000.0);
+        assert_eq!(engine.metrics.total_option_notional, 10_000.0);
+        assert!((engine.metrics.option_pct_of_portfolio - 0.10).abs() < 1e-9);
+    }
+}
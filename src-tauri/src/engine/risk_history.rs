@@ -0,0 +1,207 @@
+// src-tauri/src/engine/risk_history.rs
+// Daily risk snapshots: the risk engine's metrics as each session closed, with the
+// limits they were held to. Days before snapshots were recorded are reconstructed from
+// the trade journal and daily summaries; those carry `reconstructed` since intraday
+// Greeks, exposure and circuit-breaker timing cannot be recovered.
+
+use super::position_history::{session_date, PositionReplay};
+use super::risk::{RiskLimits, RiskMetrics, RiskViolationType};
+use super::types::{DailySummary, StockSplit, Trade};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+// The live engine measures circuit-breaker losses against this when it has no equity
+const ASSUMED_ACCOUNT_SIZE: f64 = 100_000.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskSnapshot {
+    pub date: NaiveDate,
+    pub metrics: RiskMetrics,
+    pub limits: RiskLimits,               // In force when the snapshot was taken
+    pub breaches: Vec<RiskViolationType>, // Daily limits the day's metrics went past
+    pub circuit_breaker_fired: bool,
+    #[serde(default)]
+    pub reconstructed: bool, // Derived from the journal rather than recorded at the roll
+}
+
+impl RiskSnapshot {
+    pub fn new(date: NaiveDate, metrics: RiskMetrics, limits: &RiskLimits, reconstructed: bool) -> Self {
+        Self {
+            date,
+            breaches: breaches(&metrics, limits),
+            circuit_breaker_fired: metrics.circuit_breaker_fired,
+            metrics,
+            limits: limits.clone(),
+            reconstructed,
+        }
+    }
+}
+
+/// Limits `metrics` went past. Trade and consecutive-loss counts breach on reaching
+/// their limit, as the engine stops orders there.
+pub fn breaches(metrics: &RiskMetrics, limits: &RiskLimits) -> Vec<RiskViolationType> {
+    let checks = [
+        (metrics.daily_pnl < -limits.max_daily_loss, RiskViolationType::DailyLossLimit),
+        (metrics.daily_trades >= limits.max_daily_trades, RiskViolationType::DailyTradeLimit),
+        (metrics.daily_volume > limits.max_daily_volume, RiskViolationType::DailyVolumeLimit),
+        (metrics.consecutive_losses >= limits.max_consecutive_losses, RiskViolationType::ConsecutiveLossLimit),
+        (metrics.portfolio_delta.abs() > limits.max_option_delta, RiskViolationType::DeltaLimit),
+        (metrics.portfolio_gamma.abs() > limits.max_option_gamma, RiskViolationType::GammaLimit),
+        (metrics.portfolio_vega.abs() > limits.max_option_vega, RiskViolationType::VegaLimit),
+        (metrics.total_option_notional > limits.max_total_option_exposure, RiskViolationType::OptionExposureLimit),
+        (metrics.circuit_breaker_fired, RiskViolationType::CircuitBreaker),
+    ];
+    checks.into_iter().filter(|(breached, _)| *breached).map(|(_, kind)| kind).collect()
+}
+
+/// Approximate snapshots for the sessions `from` through `to` that traded or have a
+/// daily summary, skipping `recorded` dates. Trade counts, volume and realized P&L come
+/// from the journal; day P&L from the summary where there is one. Consecutive losses
+/// are losing closes in a row, carried across days. The circuit breaker counts as fired
+/// when the day lost more than its threshold of starting equity.
+pub fn reconstruct_snapshots(
+    trades: &[Trade],
+    splits: &[StockSplit],
+    summaries: &[DailySummary],
+    limits: &RiskLimits,
+    from: NaiveDate,
+    to: NaiveDate,
+    recorded: &BTreeSet<NaiveDate>,
+) -> Vec<RiskSnapshot> {
+    let summaries: BTreeMap<NaiveDate, &DailySummary> = summaries.iter().map(|s| (s.date, s)).collect();
+    let dates: BTreeSet<NaiveDate> = trades
+        .iter()
+        .map(|t| session_date(t.timestamp))
+        .chain(summaries.keys().copied())
+        .filter(|date| *date <= to)
+        .collect();
+
+    let mut replay = PositionReplay::new(trades, splits);
+    let mut consecutive_losses = 0;
+    let mut snapshots = Vec::new();
+    for date in dates {
+        let applied = replay.advance_through(date);
+        for closed in applied.iter().filter(|t| t.closes) {
+            consecutive_losses = if closed.realized_pnl < 0.0 { consecutive_losses + 1 } else { 0 };
+        }
+        if date < from || recorded.contains(&date) {
+            continue;
+        }
+
+        let realized_pnl: f64 = applied.iter().map(|t| t.realized_pnl).sum();
+        let summary = summaries.get(&date);
+        let daily_pnl = summary.map(|s| s.ending_equity - s.starting_equity).unwrap_or(realized_pnl);
+        let starting_equity = summary.map(|s| s.starting_equity).filter(|e| *e > 0.0).unwrap_or(ASSUMED_ACCOUNT_SIZE);
+        let metrics = RiskMetrics {
+            daily_pnl,
+            daily_trades: applied.len() as i32,
+            daily_volume: applied.iter().map(|t| t.trade.net_amount.abs()).sum(),
+            consecutive_losses,
+            largest_position_pct: 0.0,
+            portfolio_delta: 0.0,
+            portfolio_gamma: 0.0,
+            portfolio_vega: 0.0,
+            total_option_notional: 0.0,
+            option_pct_of_portfolio: 0.0,
+            circuit_breaker_active: false,
+            circuit_breaker_until: None,
+            circuit_breaker_fired: daily_pnl / starting_equity < -limits.circuit_breaker_loss_pct,
+            last_updated: applied.iter().map(|t| t.trade.timestamp).max().unwrap_or(0),
+            drawdown: None,
+            margin: None,
+        };
+        snapshots.push(RiskSnapshot::new(date, metrics, limits, true));
+    }
+    snapshots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::types::{InstrumentType, OrderSide};
+    use chrono::TimeZone;
+    use chrono_tz::America::New_York;
+
+    fn trade(id: &str, side: OrderSide, quantity: i64, price: f64, day: u32, hour: u32) -> Trade {
+        Trade {
+            id: id.to_string(),
+            symbol: "ABC".to_string(),
+            side,
+            quantity,
+            price,
+            timestamp: New_York.with_ymd_and_hms(2024, 3, day, hour, 0, 0).unwrap().timestamp(),
+            order_id: format!("order-{}", id),
+            commission: 0.0,
+            net_amount: price * quantity as f64,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            leg_number: None,
+            assignment_id: None,
+            tag: None,
+            evaluation_id: None,
+        }
+    }
+
+    fn summary(day: u32, starting_equity: f64, ending_equity: f64) -> DailySummary {
+        DailySummary {
+            date: NaiveDate::from_ymd_opt(2024, 3, day).unwrap(),
+            starting_equity,
+            ending_equity,
+            cash: ending_equity,
+            unrealized_pnl: 0.0,
+            marks: BTreeMap::new(),
+            open_auction_fills: 0,
+            close_auction_fills: 0,
+            realized_equity: None,
+            equity_range: None,
+            benchmark_day_pnl: None,
+        }
+    }
+
+    #[test]
+    fn test_reconstructed_counts_match_journal() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        // Two losing round trips on the 4th, one losing on the 5th, a winner on the 6th
+        let trades = vec![
+            trade("1", OrderSide::Buy, 100, 50.0, 4, 10),
+            trade("2", OrderSide::Sell, 100, 49.0, 4, 11),
+            trade("3", OrderSide::Buy, 200, 50.0, 4, 12),
+            trade("4", OrderSide::Sell, 200, 48.0, 4, 15),
+            trade("5", OrderSide::Buy, 100, 40.0, 5, 10),
+            trade("6", OrderSide::Sell, 100, 39.0, 5, 15),
+            trade("7", OrderSide::Buy, 10, 30.0, 6, 10),
+            trade("8", OrderSide::Sell, 10, 31.0, 6, 15),
+        ];
+        let summaries = vec![summary(4, 100_000.0, 89_000.0), summary(5, 89_000.0, 88_900.0)];
+        let limits = RiskLimits { max_daily_trades: 4, max_consecutive_losses: 3, ..RiskLimits::default() };
+
+        let snapshots = reconstruct_snapshots(&trades, &[], &summaries, &limits, date(4), date(6), &BTreeSet::new());
+        assert_eq!(snapshots.len(), 3);
+        assert!(snapshots.iter().all(|s| s.reconstructed));
+
+        let day4 = &snapshots[0];
+        assert_eq!(day4.metrics.daily_trades, 4);
+        assert!((day4.metrics.daily_volume - (5_000.0 + 4_900.0 + 10_000.0 + 9_600.0)).abs() < 1e-9);
+        assert_eq!(day4.metrics.consecutive_losses, 2);
+        assert_eq!(day4.metrics.daily_pnl, -11_000.0); // From the summary, not the -500 realized
+        assert!(day4.circuit_breaker_fired);
+        assert!(day4.breaches.contains(&RiskViolationType::DailyLossLimit));
+        assert!(day4.breaches.contains(&RiskViolationType::DailyTradeLimit));
+
+        // The losing streak carries into the next day; no summary falls back to realized P&L
+        let (day5, day6) = (&snapshots[1], &snapshots[2]);
+        assert_eq!((day5.metrics.daily_trades, day5.metrics.consecutive_losses), (2, 3));
+        assert_eq!(day5.breaches, vec![RiskViolationType::ConsecutiveLossLimit]);
+        assert_eq!((day6.metrics.consecutive_losses, day6.metrics.daily_pnl), (0, 10.0));
+        assert!(day6.breaches.is_empty());
+
+        // Recorded days and days outside the range are left out
+        let recorded = BTreeSet::from([date(5)]);
+        let dates: Vec<NaiveDate> = reconstruct_snapshots(&trades, &[], &summaries, &limits, date(5), date(6), &recorded)
+            .iter()
+            .map(|s| s.date)
+            .collect();
+        assert_eq!(dates, vec![date(6)]);
+    }
+}
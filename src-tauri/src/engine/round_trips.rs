@@ -0,0 +1,291 @@
+// src-tauri/src/engine/round_trips.rs
+// Round trips matched out of a trade list, first in first out, and the statistics built
+// on them. The paper broker's journal and backtest fills go through the same matching
+// so live and backtest statistics compare like for like.
+
+use super::position_history::session_date;
+use super::types::{InstrumentType, OrderSide, StockSplit, Trade};
+use crate::market_data::types::Candle;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RoundTrip {
+    pub symbol: String,
+    pub quantity: i64, // Negative for shorts
+    pub entry_timestamp: i64,
+    pub entry_date: NaiveDate, // Session date (New York)
+    pub entry_price: f64,      // Split-adjusted
+    pub exit_timestamp: i64,
+    pub exit_date: NaiveDate,
+    pub exit_price: f64,
+    pub commission: f64, // The entry and exit trades' share
+    pub pnl: f64,        // Net of commission
+    pub return_pct: f64, // pnl / entry notional
+    pub holding_days: i64,
+    pub entry_reason: Option<String>, // Tag of the opening trade: a signal, rule or strategy
+    pub exit_reason: Option<String>,
+    #[serde(default)]
+    pub mae: Option<f64>, // Worst move against the entry while held, as a fraction of entry price; <= 0
+    #[serde(default)]
+    pub mfe: Option<f64>, // Best move in its favor; >= 0. Both None without bars
+    #[serde(default)]
+    pub entry_trade_id: String,
+    #[serde(default)]
+    pub exit_trade_id: String,
+    #[serde(default)]
+    pub entry_evaluation_id: Option<String>, // Strategy evaluations that placed the two trades
+    #[serde(default)]
+    pub exit_evaluation_id: Option<String>,
+    #[serde(default)]
+    pub intended_entry_price: Option<f64>, // Backtests: the price the entry signal asked for, before slippage
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TradeStatistics {
+    pub round_trips: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub win_rate: f64, // 0..1
+    pub total_pnl: f64,
+    pub avg_win: f64,
+    pub avg_loss: f64, // <= 0
+    pub largest_win: f64,
+    pub largest_loss: f64,
+    pub profit_factor: Option<f64>, // Gross wins / gross losses; None without losses
+    pub expectancy: f64,            // Mean P&L per round trip
+    pub avg_return_pct: f64,
+    pub avg_holding_days: f64,
+    pub avg_mae: Option<f64>, // Over round trips with excursions
+    pub avg_mfe: Option<f64>,
+}
+
+struct OpenLot<'a> {
+    trade: &'a Trade,
+    quantity: i64, // Signed; shorts are negative
+    price: f64,
+    commission_per_unit: f64,
+}
+
+impl RoundTrip {
+    /// Fill `mae` and `mfe` from the highs and lows of `bars` after the entry bar through
+    /// the exit bar
+    pub fn track_excursions(&mut self, bars: &[Candle]) {
+        if self.entry_price <= 0.0 {
+            return;
+        }
+        let direction = self.quantity.signum() as f64;
+        let (mut mae, mut mfe) = (0.0f64, 0.0f64);
+        for bar in bars.iter().filter(|b| b.timestamp > self.entry_timestamp && b.timestamp <= self.exit_timestamp) {
+            let (worst, best) = if direction > 0.0 { (bar.low, bar.high) } else { (bar.high, bar.low) };
+            mae = mae.min((worst - self.entry_price) / self.entry_price * direction);
+            mfe = mfe.max((best - self.entry_price) / self.entry_price * direction);
+        }
+        self.mae = Some(mae);
+        self.mfe = Some(mfe);
+    }
+}
+
+/// Round trips in `trades`, oldest exit first. Each trade closes the symbol's oldest
+/// opposite lots before opening its own; commissions are split by quantity. Stock
+/// splits restate open lots on their execution dates.
+pub fn round_trips(trades: &[Trade], splits: &[StockSplit]) -> Vec<RoundTrip> {
+    let mut trades: Vec<&Trade> = trades.iter().collect();
+    trades.sort_by(|a, b| (a.timestamp, &a.id).cmp(&(b.timestamp, &b.id)));
+    let mut splits: Vec<&StockSplit> = splits.iter().collect();
+    splits.sort_by_key(|s| s.execution_date);
+    let mut splits = splits.into_iter().peekable();
+
+    let mut open: BTreeMap<&str, VecDeque<OpenLot>> = BTreeMap::new();
+    let mut trips = Vec::new();
+    for trade in trades {
+        if trade.quantity <= 0 {
+            continue;
+        }
+        while let Some(split) = splits.next_if(|s| s.execution_date <= session_date(trade.timestamp)) {
+            if let Some(lots) = open.get_mut(split.symbol.as_str()) {
+                for lot in lots.iter_mut().filter(|l| l.trade.instrument_type == InstrumentType::Stock) {
+                    lot.quantity = split.shares_after(lot.quantity).0;
+                    lot.price /= split.ratio();
+                    lot.commission_per_unit /= split.ratio();
+                }
+                lots.retain(|l| l.quantity != 0);
+            }
+        }
+
+        let multiplier = trade.option_details.as_ref().map(|d| d.multiplier).unwrap_or(1) as f64;
+        let commission_per_unit = trade.commission / trade.quantity as f64;
+        let mut remaining = match trade.side {
+            OrderSide::Buy => trade.quantity,
+            OrderSide::Sell => -trade.quantity,
+        };
+        let lots = open.entry(trade.symbol.as_str()).or_default();
+        while let Some(lot) = lots.front_mut().filter(|l| l.quantity.signum() == -remaining.signum()) {
+            let closed = remaining.abs().min(lot.quantity.abs());
+            let quantity = closed * lot.quantity.signum();
+            let commission = (lot.commission_per_unit + commission_per_unit) * closed as f64;
+            let pnl = (trade.price - lot.price) * quantity as f64 * multiplier - commission;
+            let entry_notional = lot.price * closed as f64 * multiplier;
+            let (entry_date, exit_date) = (session_date(lot.trade.timestamp), session_date(trade.timestamp));
+            trips.push(RoundTrip {
+                symbol: trade.symbol.clone(),
+                quantity,
+                entry_timestamp: lot.trade.timestamp,
+                entry_date,
+                entry_price: lot.price,
+                exit_timestamp: trade.timestamp,
+                exit_date,
+                exit_price: trade.price,
+                commission,
+                pnl,
+                return_pct: if entry_notional > 0.0 { pnl / entry_notional } else { 0.0 },
+                holding_days: (exit_date - entry_date).num_days(),
+                entry_reason: lot.trade.tag.clone(),
+                exit_reason: trade.tag.clone(),
+                mae: None,
+                mfe: None,
+                entry_trade_id: lot.trade.id.clone(),
+                exit_trade_id: trade.id.clone(),
+                entry_evaluation_id: lot.trade.evaluation_id.clone(),
+                exit_evaluation_id: trade.evaluation_id.clone(),
+                intended_entry_price: None,
+            });
+            lot.quantity -= quantity;
+            remaining += quantity;
+            if lot.quantity == 0 {
+                lots.pop_front();
+            }
+        }
+        if remaining != 0 {
+            lots.push_back(OpenLot { trade, quantity: remaining, price: trade.price, commission_per_unit });
+        }
+    }
+    trips
+}
+
+pub fn trade_statistics(trips: &[RoundTrip]) -> TradeStatistics {
+    if trips.is_empty() {
+        return TradeStatistics::default();
+    }
+    let n = trips.len() as f64;
+    let wins: Vec<f64> = trips.iter().map(|t| t.pnl).filter(|p| *p > 0.0).collect();
+    let losses: Vec<f64> = trips.iter().map(|t| t.pnl).filter(|p| *p < 0.0).collect();
+    let (gross_win, gross_loss) = (wins.iter().sum::<f64>(), losses.iter().sum::<f64>());
+    let mean = |values: Vec<f64>| (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64);
+    let total_pnl: f64 = trips.iter().map(|t| t.pnl).sum();
+    TradeStatistics {
+        round_trips: trips.len(),
+        wins: wins.len(),
+        losses: losses.len(),
+        win_rate: wins.len() as f64 / n,
+        total_pnl,
+        avg_win: mean(wins.clone()).unwrap_or(0.0),
+        avg_loss: mean(losses.clone()).unwrap_or(0.0),
+        largest_win: wins.iter().copied().fold(0.0, f64::max),
+        largest_loss: losses.iter().copied().fold(0.0, f64::min),
+        profit_factor: (gross_loss < 0.0).then(|| gross_win / -gross_loss),
+        expectancy: total_pnl / n,
+        avg_return_pct: trips.iter().map(|t| t.return_pct).sum::<f64>() / n,
+        avg_holding_days: trips.iter().map(|t| t.holding_days as f64).sum::<f64>() / n,
+        avg_mae: mean(trips.iter().filter_map(|t| t.mae).collect()),
+        avg_mfe: mean(trips.iter().filter_map(|t| t.mfe).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86400;
+    const START: i64 = 1704207600; // 01/02/2024 10:00 ET
+
+    fn trade(id: &str, side: OrderSide, quantity: i64, price: f64, day: i64, tag: &str) -> Trade {
+        Trade {
+            id: id.to_string(),
+            symbol: "ABC".to_string(),
+            side,
+            quantity,
+            price,
+            timestamp: START + day * DAY,
+            order_id: format!("order-{}", id),
+            commission: quantity as f64 * 0.01,
+            net_amount: 0.0,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            leg_number: None,
+            assignment_id: None,
+            tag: Some(tag.to_string()),
+            evaluation_id: None,
+        }
+    }
+
+    #[test]
+    fn test_round_trips_match_fifo_through_splits_and_shorts() {
+        let trades = vec![
+            trade("1", OrderSide::Buy, 100, 10.0, 0, "breakout"),
+            trade("2", OrderSide::Buy, 100, 12.0, 1, "add"),
+            trade("3", OrderSide::Sell, 150, 13.0, 3, "target"),
+            // 2-for-1 on day 5 turns the 50 shares left at 12 into 100 at 6
+            trade("4", OrderSide::Sell, 300, 7.0, 6, "reverse"),
+            trade("5", OrderSide::Buy, 200, 6.5, 8, "cover"),
+        ];
+        let split = StockSplit {
+            symbol: "ABC".into(),
+            execution_date: session_date(START + 5 * DAY),
+            split_from: 1.0,
+            split_to: 2.0,
+        };
+        let trips = round_trips(&trades, &[split]);
+        let summary: Vec<_> = trips
+            .iter()
+            .map(|t| (t.quantity, t.entry_price, t.exit_price, t.entry_reason.as_deref().unwrap(), t.exit_reason.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (100, 10.0, 13.0, "breakout", "target"),
+                (50, 12.0, 13.0, "add", "target"),
+                (100, 6.0, 7.0, "add", "reverse"),
+                (-200, 7.0, 6.5, "reverse", "cover"),
+            ]
+        );
+
+        // 100 shares in at $0.01 and out at the sell's $0.01 per share
+        assert!((trips[0].pnl - (300.0 - 2.0)).abs() < 1e-9);
+        assert!((trips[0].return_pct - trips[0].pnl / 1000.0).abs() < 1e-12);
+        assert_eq!((trips[0].holding_days, trips[2].holding_days), (3, 5));
+        // The split halves the per-share commission along with the price
+        assert!((trips[2].commission - (50.0 * 0.01 + 100.0 * 0.01)).abs() < 1e-9);
+        assert!((trips[3].pnl - (100.0 - 4.0)).abs() < 1e-9);
+        assert!(trips.iter().all(|t| t.mae.is_none()));
+    }
+
+    #[test]
+    fn test_statistics_and_excursions() {
+        let trades = vec![
+            trade("1", OrderSide::Buy, 100, 10.0, 0, "entry"),
+            trade("2", OrderSide::Sell, 100, 11.0, 2, "exit"),
+            trade("3", OrderSide::Sell, 100, 11.0, 3, "entry"),
+            trade("4", OrderSide::Buy, 100, 11.5, 4, "stop"),
+        ];
+        let mut trips = round_trips(&trades, &[]);
+        let bar = |day: i64, low: f64, high: f64| Candle::new(START + day * DAY, low, high, low, high, 1000);
+        let bars = vec![bar(0, 8.0, 20.0), bar(1, 9.0, 10.5), bar(2, 10.0, 11.5), bar(3, 10.5, 11.2), bar(4, 10.8, 12.1)];
+        for trip in &mut trips {
+            trip.track_excursions(&bars);
+        }
+        // The entry bar's range happened before the fill
+        assert!((trips[0].mae.unwrap() + 0.1).abs() < 1e-12 && (trips[0].mfe.unwrap() - 0.15).abs() < 1e-12);
+        assert!((trips[1].mae.unwrap() + 1.1 / 11.0).abs() < 1e-12 && (trips[1].mfe.unwrap() - 0.2 / 11.0).abs() < 1e-12);
+
+        let stats = trade_statistics(&trips);
+        assert_eq!((stats.round_trips, stats.wins, stats.losses, stats.win_rate), (2, 1, 1, 0.5));
+        assert!((stats.avg_win - 98.0).abs() < 1e-9 && (stats.avg_loss + 52.0).abs() < 1e-9);
+        assert!((stats.profit_factor.unwrap() - 98.0 / 52.0).abs() < 1e-12);
+        assert!((stats.expectancy - 23.0).abs() < 1e-9);
+        assert_eq!(stats.avg_holding_days, 1.5);
+        assert!(stats.avg_mae.unwrap() < 0.0 && stats.avg_mfe.unwrap() > 0.0);
+        assert_eq!(trade_statistics(&[]), TradeStatistics::default());
+    }
+}
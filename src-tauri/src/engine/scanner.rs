@@ -0,0 +1,314 @@
+// src-tauri/src/engine/scanner.rs
+// Watchlist scanner: indicator filters evaluated over daily bars, using the same
+// indicator math as the strategy loop's signals
+
+use super::r#loop::StrategyLoop;
+use crate::market_data::types::Candle;
+use crate::storage::cache::FileCache;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const SAVED_SCANS_KEY: &str = "saved_scans";
+const MACD_MIN_BARS: usize = 35; // Slow EMA (26) plus signal line (9)
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ScanIndicator {
+    Close,
+    Volume,
+    SMA,
+    EMA,
+    RSI,
+    MACD, // Histogram: MACD(12, 26) line minus its 9-period signal
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum Comparator {
+    #[serde(rename = ">")]
+    Above,
+    #[serde(rename = ">=")]
+    AtLeast,
+    #[serde(rename = "<")]
+    Below,
+    #[serde(rename = "<=")]
+    AtMost,
+}
+
+impl Comparator {
+    fn holds(&self, left: f64, right: f64) -> bool {
+        match self {
+            Comparator::Above => left > right,
+            Comparator::AtLeast => left >= right,
+            Comparator::Below => left < right,
+            Comparator::AtMost => left <= right,
+        }
+    }
+}
+
+/// Right-hand side of a condition: a constant or another indicator
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum ScanOperand {
+    Value(f64),
+    Indicator {
+        indicator: ScanIndicator,
+        #[serde(default = "default_period")]
+        period: usize,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ScanFilter {
+    Condition {
+        indicator: ScanIndicator,
+        #[serde(default = "default_period")]
+        period: usize,
+        comparator: Comparator,
+        value: ScanOperand,
+    },
+    And { filters: Vec<ScanFilter> },
+    Or { filters: Vec<ScanFilter> },
+}
+
+fn default_period() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SavedScan {
+    pub name: String,
+    pub filter: ScanFilter,
+    #[serde(default)]
+    pub symbols: Option<Vec<String>>, // None scans the watchlist
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScanMatch {
+    pub symbol: String,
+    pub as_of: NaiveDate,              // Date of the last bar used
+    pub values: BTreeMap<String, f64>, // Every indicator the filter references, e.g. "RSI(14)"
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InsufficientHistory {
+    pub symbol: String,
+    pub bars: usize,
+    pub required: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ScanResult {
+    pub matches: Vec<ScanMatch>,
+    pub insufficient_history: Vec<InsufficientHistory>,
+    pub scanned: usize,
+}
+
+fn label(indicator: ScanIndicator, period: usize) -> String {
+    match indicator {
+        ScanIndicator::Close | ScanIndicator::Volume | ScanIndicator::MACD => format!("{:?}", indicator).to_uppercase(),
+        _ => format!("{:?}({})", indicator, period),
+    }
+}
+
+fn bars_needed(indicator: ScanIndicator, period: usize) -> usize {
+    match indicator {
+        ScanIndicator::Close | ScanIndicator::Volume => 1,
+        ScanIndicator::SMA | ScanIndicator::EMA => period,
+        ScanIndicator::RSI => period + 1,
+        ScanIndicator::MACD => MACD_MIN_BARS,
+    }
+}
+
+/// Indicator value at the last bar. Callers check `required_bars` first.
+fn indicator_value(indicator: ScanIndicator, period: usize, bars: &[Candle]) -> f64 {
+    let closes: Vec<f64> = bars.iter().map(|b| b.close).collect();
+    let n = closes.len();
+    match indicator {
+        ScanIndicator::Close => closes[n - 1],
+        ScanIndicator::Volume => bars[n - 1].volume as f64,
+        ScanIndicator::SMA => StrategyLoop::mean(&closes[n - period..]),
+        ScanIndicator::EMA => *StrategyLoop::ema_series(&closes, period).last().unwrap_or(&0.0),
+        ScanIndicator::RSI => StrategyLoop::rsi(&closes[n - period - 1..]),
+        ScanIndicator::MACD => {
+            let (line, signal) = StrategyLoop::macd(&closes);
+            line - signal
+        }
+    }
+}
+
+impl ScanFilter {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            ScanFilter::Condition { period, value, .. } => {
+                let value_period = match value {
+                    ScanOperand::Indicator { period, .. } => *period,
+                    ScanOperand::Value(v) if !v.is_finite() => return Err("Scan values must be finite".to_string()),
+                    ScanOperand::Value(_) => 1,
+                };
+                if *period == 0 || value_period == 0 {
+                    return Err("Indicator periods must be at least 1".to_string());
+                }
+                Ok(())
+            }
+            ScanFilter::And { filters } | ScanFilter::Or { filters } => {
+                if filters.is_empty() {
+                    return Err("AND/OR groups need at least one filter".to_string());
+                }
+                filters.iter().try_for_each(|f| f.validate())
+            }
+        }
+    }
+
+    /// Daily bars needed to evaluate every indicator in the filter
+    pub fn required_bars(&self) -> usize {
+        match self {
+            ScanFilter::Condition { indicator, period, value, .. } => {
+                let right = match value {
+                    ScanOperand::Indicator { indicator, period } => bars_needed(*indicator, *period),
+                    ScanOperand::Value(_) => 1,
+                };
+                bars_needed(*indicator, *period).max(right)
+            }
+            ScanFilter::And { filters } | ScanFilter::Or { filters } => {
+                filters.iter().map(|f| f.required_bars()).max().unwrap_or(1)
+            }
+        }
+    }
+
+    /// Whether the filter holds at the last bar. Every indicator referenced is
+    /// recorded in `values`, including those in branches that did not decide the result.
+    fn evaluate(&self, bars: &[Candle], values: &mut BTreeMap<String, f64>) -> bool {
+        match self {
+            ScanFilter::Condition { indicator, period, comparator, value } => {
+                let left = indicator_value(*indicator, *period, bars);
+                values.insert(label(*indicator, *period), left);
+                let right = match value {
+                    ScanOperand::Value(v) => *v,
+                    ScanOperand::Indicator { indicator, period } => {
+                        let right = indicator_value(*indicator, *period, bars);
+                        values.insert(label(*indicator, *period), right);
+                        right
+                    }
+                };
+                comparator.holds(left, right)
+            }
+            ScanFilter::And { filters } => filters.iter().fold(true, |all, f| f.evaluate(bars, values) && all),
+            ScanFilter::Or { filters } => filters.iter().fold(false, |any, f| f.evaluate(bars, values) || any),
+        }
+    }
+}
+
+/// Evaluate `filter` against each symbol's daily bars (oldest first)
+pub fn run_scan(filter: &ScanFilter, bars_by_symbol: &BTreeMap<String, Vec<Candle>>) -> Result<ScanResult, String> {
+    filter.validate()?;
+    let required = filter.required_bars();
+    let mut result = ScanResult { scanned: bars_by_symbol.len(), ..Default::default() };
+
+    for (symbol, bars) in bars_by_symbol {
+        let Some(last) = bars.last().filter(|_| bars.len() >= required) else {
+            result.insufficient_history.push(InsufficientHistory { symbol: symbol.clone(), bars: bars.len(), required });
+            continue;
+        };
+        let mut values = BTreeMap::new();
+        if filter.evaluate(bars, &mut values) {
+            result.matches.push(ScanMatch { symbol: symbol.clone(), as_of: last.date(), values });
+        }
+    }
+    Ok(result)
+}
+
+pub fn load_saved_scans(cache: &mut FileCache) -> Result<Vec<SavedScan>, String> {
+    Ok(cache.get::<Vec<SavedScan>>(SAVED_SCANS_KEY)?.unwrap_or_default())
+}
+
+/// Save a scan, replacing any scan with the same name
+pub fn save_scan(cache: &mut FileCache, scan: SavedScan) -> Result<(), String> {
+    scan.filter.validate()?;
+    if scan.name.trim().is_empty() {
+        return Err("Scan name cannot be empty".to_string());
+    }
+    let mut scans = load_saved_scans(cache)?;
+    scans.retain(|s| s.name != scan.name);
+    scans.push(scan);
+    scans.sort_by(|a, b| a.name.cmp(&b.name));
+    cache.set(SAVED_SCANS_KEY, scans, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Daily closes starting 2024-01-02; volume is flat except the last bar
+    fn daily(closes: &[f64], last_volume: u64) -> Vec<Candle> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, close)| {
+                let volume = if i + 1 == closes.len() { last_volume } else { 1_000_000 };
+                Candle::new(1704153600 + i as i64 * 86400, *close, close + 1.0, close - 1.0, *close, volume)
+            })
+            .collect()
+    }
+
+    fn uptrend_then(drop_per_day: f64) -> Vec<f64> {
+        let mut closes: Vec<f64> = (0..200).map(|i| 100.0 + i as f64 * 0.5).collect();
+        let top = *closes.last().unwrap();
+        closes.extend((1..=15).map(|i| top - i as f64 * drop_per_day));
+        closes
+    }
+
+    #[test]
+    fn test_compound_scan_on_fixture() {
+        // (RSI(14) < 30 AND close > SMA(200)) OR volume > 5M
+        let filter: ScanFilter = serde_json::from_value(serde_json::json!({
+            "op": "or",
+            "filters": [
+                { "op": "and", "filters": [
+                    { "op": "condition", "indicator": "RSI", "period": 14, "comparator": "<", "value": 30.0 },
+                    { "op": "condition", "indicator": "Close", "comparator": ">", "value": { "indicator": "SMA", "period": 200 } }
+                ]},
+                { "op": "condition", "indicator": "Volume", "comparator": ">", "value": 5_000_000.0 }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(filter.required_bars(), 200);
+
+        let bars: BTreeMap<String, Vec<Candle>> = [
+            ("DIP", daily(&uptrend_then(1.0), 1_000_000)),   // Oversold pullback above the 200-day
+            ("CRASH", daily(&uptrend_then(6.0), 1_000_000)), // Oversold below the 200-day
+            ("SURGE", daily(&uptrend_then(-1.0), 8_000_000)), // Overbought on heavy volume
+            ("FLAT", daily(&[150.0; 215], 1_000_000)),
+            ("IPO", daily(&[20.0; 50], 9_000_000)),
+        ]
+        .into_iter()
+        .map(|(s, b)| (s.to_string(), b))
+        .collect();
+
+        let result = run_scan(&filter, &bars).unwrap();
+        assert_eq!(result.scanned, 5);
+        assert_eq!(result.matches.iter().map(|m| m.symbol.as_str()).collect::<Vec<_>>(), vec!["DIP", "SURGE"]);
+        assert_eq!(result.insufficient_history, vec![InsufficientHistory { symbol: "IPO".to_string(), bars: 50, required: 200 }]);
+
+        let dip = &result.matches[0];
+        assert_eq!(dip.as_of, NaiveDate::from_ymd_opt(2024, 8, 3).unwrap());
+        assert_eq!(dip.values["RSI(14)"], 0.0);
+        assert_eq!(dip.values["CLOSE"], 184.5);
+        assert!(dip.values["SMA(200)"] < 184.5);
+        assert_eq!(dip.values["VOLUME"], 1_000_000.0);
+    }
+
+    #[test]
+    fn test_scan_filter_validation() {
+        let empty = ScanFilter::And { filters: vec![] };
+        assert!(run_scan(&empty, &BTreeMap::new()).is_err());
+
+        let zero_period = ScanFilter::Condition {
+            indicator: ScanIndicator::SMA,
+            period: 0,
+            comparator: Comparator::Above,
+            value: ScanOperand::Value(1.0),
+        };
+        assert!(zero_period.validate().is_err());
+    }
+}
@@ -0,0 +1,107 @@
+// src-tauri/src/engine/shadow_ledger.rs
+// What the strategy loop would have done while in dry run. Approved decisions that are
+// logged instead of placed are booked here as filled at the market, marked with every
+// loop iteration, and sampled once per session date. The daily equity points are the
+// dry-run track record the live promotion checklist reads. The ledger starts with the
+// paper account's equity at its first sample and starts over on promotion.
+
+use super::metrics;
+use super::types::{OrderRequest, OrderSide};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ShadowPosition {
+    pub quantity: i64, // Positive = long, negative = short
+    pub avg_price: f64,
+    pub mark: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ShadowDay {
+    pub date: NaiveDate,
+    pub equity: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ShadowLedger {
+    pub starting_equity: f64,
+    pub cash: f64,
+    pub positions: BTreeMap<String, ShadowPosition>,
+    pub days: Vec<ShadowDay>, // One per session date, oldest first
+    pub decisions: u64,       // Decisions booked
+}
+
+impl ShadowLedger {
+    pub fn is_started(&self) -> bool {
+        !self.days.is_empty()
+    }
+
+    /// Book a dry-run decision's orders as filled at `price` of their symbol; orders for
+    /// symbols without a price are skipped
+    pub fn record(&mut self, orders: &[OrderRequest], price: impl Fn(&str) -> Option<f64>) {
+        let mut booked = false;
+        for order in orders.iter().filter(|o| o.quantity > 0) {
+            let Some(price) = price(&order.symbol).filter(|p| *p > 0.0) else { continue };
+            let signed = if order.side == OrderSide::Buy { order.quantity } else { -order.quantity };
+            let position = self.positions.entry(order.symbol.clone()).or_default();
+            let quantity = position.quantity + signed;
+            if position.quantity == 0 || position.quantity.signum() == signed.signum() {
+                position.avg_price = (position.avg_price * position.quantity.abs() as f64 + price * signed.abs() as f64) / quantity.abs() as f64;
+            } else if quantity.signum() == signed.signum() {
+                position.avg_price = price; // Flipped through flat
+            }
+            position.quantity = quantity;
+            position.mark = price;
+            self.cash -= signed as f64 * price;
+            booked = true;
+        }
+        self.positions.retain(|_, p| p.quantity != 0);
+        self.decisions += u64::from(booked);
+    }
+
+    pub fn mark(&mut self, symbol: &str, price: f64) {
+        if let Some(position) = self.positions.get_mut(symbol).filter(|_| price > 0.0) {
+            position.mark = price;
+        }
+    }
+
+    pub fn equity(&self) -> f64 {
+        self.cash + self.positions.values().map(|p| p.quantity as f64 * p.mark).sum::<f64>()
+    }
+
+    /// Take the equity point for session `date`, replacing one already taken that day.
+    /// The first sample starts the ledger with `account_equity`.
+    pub fn sample(&mut self, date: NaiveDate, account_equity: f64) {
+        if !self.is_started() {
+            self.starting_equity = account_equity;
+            self.cash = account_equity;
+        }
+        let equity = self.equity();
+        match self.days.last_mut() {
+            Some(day) if day.date == date => day.equity = equity,
+            _ => self.days.push(ShadowDay { date, equity }),
+        }
+    }
+
+    /// Session dates the loop has run in dry run
+    pub fn days_run(&self) -> usize {
+        self.days.len()
+    }
+
+    fn curve(&self) -> Vec<f64> {
+        std::iter::once(self.starting_equity).chain(self.days.iter().map(|d| d.equity)).collect()
+    }
+
+    /// Annualized Sharpe of the daily equity points; None before two days
+    pub fn sharpe(&self) -> Option<f64> {
+        (self.days.len() >= 2).then(|| metrics::sharpe_ratio(&self.curve()))
+    }
+
+    /// Deepest drawdown from the running peak, as a positive fraction
+    pub fn max_drawdown(&self) -> f64 {
+        -metrics::calc_drawdown_series(&self.curve()).1
+    }
+}
@@ -0,0 +1,503 @@
+// src-tauri/src/engine/signal_optimizer.rs
+// Parameter search over the strategy loop's signals. Candidates are scored by replaying
+// daily bars through the loop's own signal evaluation and combine_signals, so a tuned config
+// behaves in the loop as it did here. Each symbol's bars are split: the first 75% ranks
+// the candidates and the rest validates that the winner was not fit to noise.
+
+use super::indicators::IndicatorCache;
+use super::metrics::{annualized_cagr, calc_drawdown_series, sharpe_ratio};
+use super::r#loop::{CombinationRule, SignalConfig, SignalDirection, StrategyLoop, StrategyLoopConfig};
+use super::simulation::SimRng;
+use crate::market_data::dates;
+use crate::market_data::types::Candle;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+pub const IN_SAMPLE_FRACTION: f64 = 0.75;
+pub const MAX_CANDIDATES: usize = 5000;
+const PROFIT_FACTOR_CAP: f64 = 10.0; // Reported for a segment without losing trades
+const MIN_DRAWDOWN: f64 = 0.01;      // Floor under the drawdown CAGR is divided by
+
+/// Values from `min` to `max` in steps of `step`; a zero step is the single value `min`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ParamRange {
+    pub min: f64,
+    pub max: f64,
+    #[serde(default)]
+    pub step: f64,
+}
+
+impl ParamRange {
+    fn values(&self, what: &str) -> Result<Vec<f64>, String> {
+        if !self.min.is_finite() || !self.max.is_finite() || self.min > self.max {
+            return Err(format!("The {} range {} - {} is invalid", what, self.min, self.max));
+        }
+        if self.step <= 0.0 || self.min == self.max {
+            return Ok(vec![self.min]);
+        }
+        let count = ((self.max - self.min) / self.step + 1e-9).floor() as usize + 1;
+        if count > MAX_CANDIDATES {
+            return Err(format!("The {} range has {} steps; use a larger step", what, count));
+        }
+        Ok((0..count).map(|i| self.min + i as f64 * self.step).collect())
+    }
+}
+
+/// Ranges for one of the base config's signals, named as "RSI" or "RSI@5m"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SignalRanges {
+    pub signal: String,
+    #[serde(default)]
+    pub lookback: Option<ParamRange>,
+    #[serde(default)]
+    pub weight: Option<ParamRange>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchSpace {
+    pub signals: Vec<SignalRanges>,
+    #[serde(default)]
+    pub threshold: Option<ParamRange>, // Weighted-vote threshold of the default rule
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OptimizationObjective {
+    #[default]
+    Sharpe,
+    ProfitFactor,
+    CagrToDrawdown, // CAGR over the deepest drawdown
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum SearchMethod {
+    #[default]
+    Grid,
+    Random { samples: usize, seed: u64 },
+}
+
+/// Everything an optimization run depends on, stored with its result to re-run it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OptimizationSpec {
+    pub symbols: Vec<String>,
+    #[serde(rename = "start_on", alias = "start_date", deserialize_with = "dates::lenient")]
+    pub start_date: NaiveDate,
+    #[serde(rename = "end_on", alias = "end_date", deserialize_with = "dates::lenient")]
+    pub end_date: NaiveDate,
+    pub base_config: StrategyLoopConfig,
+    pub search_space: SearchSpace,
+    #[serde(default)]
+    pub objective: OptimizationObjective,
+    #[serde(default)]
+    pub method: SearchMethod,
+}
+
+impl OptimizationSpec {
+    /// Check the search space against the base config; returns how many candidates it
+    /// will evaluate
+    pub fn validate(&self) -> Result<usize, String> {
+        if self.symbols.is_empty() {
+            return Err("An optimization needs at least one symbol".to_string());
+        }
+        candidate_choices(&axes(&self.base_config, &self.search_space)?, self.method).map(|c| c.len())
+    }
+}
+
+/// A candidate's performance over one stretch, averaged across the symbols
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SegmentMetrics {
+    pub sharpe: f64,
+    pub profit_factor: f64,
+    pub cagr: f64,
+    pub max_drawdown: f64, // <= 0
+    pub trades: f64,       // Round trips per symbol
+    pub score: f64,        // On the run's objective
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RankedCandidate {
+    pub rank: usize,            // 1 is best in-sample
+    pub validation_rank: usize, // 1 is best over the validation stretch
+    pub signals: Vec<SignalConfig>,
+    pub rule: CombinationRule,
+    pub in_sample: SegmentMetrics,
+    pub validation: SegmentMetrics,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignalOptimization {
+    pub id: Option<String>,
+    pub created_at: i64,
+    pub spec: OptimizationSpec,
+    pub candidates: Vec<RankedCandidate>, // By in-sample rank
+    pub recommended: Option<StrategyLoopConfig>, // The base config with the winner's parameters
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+// One tunable parameter and the values it takes
+enum Axis {
+    Lookback(usize, Vec<f64>),
+    Weight(usize, Vec<f64>),
+    Threshold(Vec<f64>),
+}
+
+impl Axis {
+    fn len(&self) -> usize {
+        match self {
+            Axis::Lookback(_, v) | Axis::Weight(_, v) | Axis::Threshold(v) => v.len(),
+        }
+    }
+}
+
+fn axes(base: &StrategyLoopConfig, space: &SearchSpace) -> Result<Vec<Axis>, String> {
+    let mut axes = Vec::new();
+    for ranges in &space.signals {
+        let index = base
+            .signals
+            .iter()
+            .position(|s| s.name == ranges.signal || format!("{}@{}", s.name, s.timeframe.label()) == ranges.signal)
+            .ok_or_else(|| format!("{} is not one of the base config's signals", ranges.signal))?;
+        if let Some(range) = &ranges.lookback {
+            let lookbacks = range.values(&format!("{} lookback", ranges.signal))?;
+            axes.push(Axis::Lookback(index, lookbacks));
+        }
+        if let Some(range) = &ranges.weight {
+            axes.push(Axis::Weight(index, range.values(&format!("{} weight", ranges.signal))?));
+        }
+    }
+    if let Some(range) = &space.threshold {
+        if !matches!(base.default_rule, CombinationRule::WeightedVote { .. }) {
+            return Err("A threshold range needs a weighted-vote default rule".to_string());
+        }
+        axes.push(Axis::Threshold(range.values("threshold")?));
+    }
+    if axes.is_empty() {
+        return Err("The search space declares no ranges".to_string());
+    }
+    Ok(axes)
+}
+
+/// Value indexes into each axis for every candidate the method visits
+fn candidate_choices(axes: &[Axis], method: SearchMethod) -> Result<Vec<Vec<usize>>, String> {
+    let total = axes.iter().try_fold(1usize, |n, a| n.checked_mul(a.len())).unwrap_or(usize::MAX);
+    match method {
+        SearchMethod::Grid => {
+            if total > MAX_CANDIDATES {
+                return Err(format!(
+                    "The grid has {} candidates (at most {}); narrow the ranges or use a random search",
+                    total, MAX_CANDIDATES
+                ));
+            }
+            Ok((0..total)
+                .map(|mut n| {
+                    axes.iter()
+                        .map(|a| {
+                            let i = n % a.len();
+                            n /= a.len();
+                            i
+                        })
+                        .collect()
+                })
+                .collect())
+        }
+        SearchMethod::Random { samples, seed } => {
+            if samples == 0 || samples > MAX_CANDIDATES {
+                return Err(format!("A random search takes 1 to {} samples", MAX_CANDIDATES));
+            }
+            let mut rng = SimRng::new(seed);
+            let mut seen = HashSet::new();
+            let wanted = samples.min(total);
+            // Repeats are dropped, so allow some extra draws before settling for fewer
+            for _ in 0..wanted * 10 {
+                if seen.len() == wanted {
+                    break;
+                }
+                seen.insert(axes.iter().map(|a| rng.next_u15() as usize % a.len()).collect::<Vec<_>>());
+            }
+            let mut choices: Vec<Vec<usize>> = seen.into_iter().collect();
+            choices.sort();
+            Ok(choices)
+        }
+    }
+}
+
+fn apply_choice(base: &StrategyLoopConfig, axes: &[Axis], choice: &[usize]) -> (Vec<SignalConfig>, CombinationRule) {
+    let mut signals = base.signals.clone();
+    let mut rule = base.default_rule.clone();
+    for (axis, &i) in axes.iter().zip(choice) {
+        match axis {
+            Axis::Lookback(s, values) => signals[*s].lookback = (values[i].round() as usize).max(2),
+            Axis::Weight(s, values) => signals[*s].weight = values[i],
+            Axis::Threshold(values) => rule = CombinationRule::WeightedVote { threshold: values[i] },
+        }
+    }
+    (signals, rule)
+}
+
+/// Replay `bars[start..end]` as the loop would trade them: go long on a Long consensus
+/// when flat, go flat on a Short one when long, and hold otherwise. Signals see the bars
+/// before `start` as history. Returns the equity curve (from 1.0) and each round trip's
+/// return; a position still open at `end` is closed there.
+pub fn replay_signals(signals: &[SignalConfig], rule: &CombinationRule, bars: &[Candle], start: usize, end: usize) -> (Vec<f64>, Vec<f64>) {
+    let end = end.min(bars.len());
+    let mut equity = 1.0;
+    let mut entry_equity: Option<f64> = None;
+    let mut curve = Vec::with_capacity(end.saturating_sub(start));
+    let mut trades = Vec::new();
+    let mut indicators = IndicatorCache::default(); // Advanced a bar at a time, not recomputed per bar
+    for i in start..end {
+        if entry_equity.is_some() && i > 0 && bars[i - 1].close > 0.0 {
+            equity *= bars[i].close / bars[i - 1].close;
+        }
+        curve.push(equity);
+
+        let results: Vec<_> = signals
+            .iter()
+            .filter_map(|s| StrategyLoop::compute_signal_cached(s, &bars[..=i], &mut indicators, "", "replay"))
+            .collect();
+        let (direction, _, _) = StrategyLoop::combine_signals(&results, rule);
+        match (direction, entry_equity) {
+            (SignalDirection::Long, None) => entry_equity = Some(equity),
+            (SignalDirection::Short, Some(entry)) => {
+                trades.push(equity / entry - 1.0);
+                entry_equity = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(entry) = entry_equity {
+        trades.push(equity / entry - 1.0);
+    }
+    (curve, trades)
+}
+
+fn segment_metrics(bars: &[Candle], start: usize, end: usize, curve: &[f64], trades: &[f64]) -> SegmentMetrics {
+    let days = match (bars.get(start), bars.get(end.min(bars.len()).saturating_sub(1))) {
+        (Some(first), Some(last)) => ((last.timestamp - first.timestamp).max(0) / 86_400) as usize,
+        _ => 0,
+    };
+    let gross_win: f64 = trades.iter().filter(|r| **r > 0.0).sum();
+    let gross_loss: f64 = -trades.iter().filter(|r| **r < 0.0).sum::<f64>();
+    let profit_factor = if gross_loss > 0.0 {
+        (gross_win / gross_loss).min(PROFIT_FACTOR_CAP)
+    } else if gross_win > 0.0 {
+        PROFIT_FACTOR_CAP
+    } else {
+        0.0
+    };
+    SegmentMetrics {
+        sharpe: sharpe_ratio(curve),
+        profit_factor,
+        cagr: annualized_cagr(1.0, curve.last().copied().unwrap_or(1.0), days),
+        max_drawdown: calc_drawdown_series(curve).1,
+        trades: trades.len() as f64,
+        score: 0.0,
+    }
+}
+
+fn score(objective: OptimizationObjective, m: &SegmentMetrics) -> f64 {
+    match objective {
+        OptimizationObjective::Sharpe => m.sharpe,
+        OptimizationObjective::ProfitFactor => m.profit_factor,
+        OptimizationObjective::CagrToDrawdown => m.cagr / m.max_drawdown.abs().max(MIN_DRAWDOWN),
+    }
+}
+
+fn average(segments: &[SegmentMetrics], objective: OptimizationObjective) -> SegmentMetrics {
+    let n = segments.len().max(1) as f64;
+    let mean = |f: fn(&SegmentMetrics) -> f64| segments.iter().map(f).sum::<f64>() / n;
+    let mut m = SegmentMetrics {
+        sharpe: mean(|m| m.sharpe),
+        profit_factor: mean(|m| m.profit_factor),
+        cagr: mean(|m| m.cagr),
+        max_drawdown: mean(|m| m.max_drawdown),
+        trades: mean(|m| m.trades),
+        score: 0.0,
+    };
+    m.score = segments.iter().map(|s| score(objective, s)).sum::<f64>() / n;
+    m
+}
+
+/// The in-sample winner of ranked `candidates`, unless it falls outside the top quarter
+/// over the validation stretch
+fn validated_winner(candidates: &[RankedCandidate]) -> Result<Option<&RankedCandidate>, String> {
+    let Some(best) = candidates.iter().find(|c| c.rank == 1) else {
+        return Ok(None);
+    };
+    if best.validation_rank > candidates.len().div_ceil(4) {
+        return Err(format!(
+            "The best in-sample candidate ranks {} of {} on the validation stretch, outside the top quarter; it is likely overfit and is not recommended",
+            best.validation_rank,
+            candidates.len()
+        ));
+    }
+    Ok(Some(best))
+}
+
+/// Score every candidate on each symbol's in-sample and validation stretches and rank
+/// them. `progress` hears (evaluated, total) after each candidate and returns false to
+/// stop. The in-sample winner is recommended only when it also ranks in the top quarter
+/// over the validation stretch.
+pub fn optimize(
+    spec: &OptimizationSpec,
+    bars: &[(String, Vec<Candle>)],
+    progress: &mut dyn FnMut(usize, usize) -> bool,
+) -> Result<SignalOptimization, String> {
+    let axes = axes(&spec.base_config, &spec.search_space)?;
+    let choices = candidate_choices(&axes, spec.method)?;
+    let splits: Vec<(&[Candle], usize)> = bars
+        .iter()
+        .filter(|(_, b)| b.len() >= 4)
+        .map(|(_, b)| (b.as_slice(), (b.len() as f64 * IN_SAMPLE_FRACTION) as usize))
+        .collect();
+    if splits.is_empty() {
+        return Err("Not enough bars to split into in-sample and validation stretches".to_string());
+    }
+
+    let mut candidates = Vec::with_capacity(choices.len());
+    for (n, choice) in choices.iter().enumerate() {
+        let (signals, rule) = apply_choice(&spec.base_config, &axes, choice);
+        let (mut in_sample, mut validation) = (Vec::new(), Vec::new());
+        for &(b, split) in &splits {
+            let (curve, trades) = replay_signals(&signals, &rule, b, 0, split);
+            in_sample.push(segment_metrics(b, 0, split, &curve, &trades));
+            let (curve, trades) = replay_signals(&signals, &rule, b, split, b.len());
+            validation.push(segment_metrics(b, split, b.len(), &curve, &trades));
+        }
+        candidates.push(RankedCandidate {
+            rank: 0,
+            validation_rank: 0,
+            signals,
+            rule,
+            in_sample: average(&in_sample, spec.objective),
+            validation: average(&validation, spec.objective),
+        });
+        if !progress(n + 1, choices.len()) {
+            return Err("Optimization cancelled".to_string());
+        }
+    }
+
+    let mut by_validation: Vec<usize> = (0..candidates.len()).collect();
+    by_validation.sort_by(|a, b| candidates[*b].validation.score.total_cmp(&candidates[*a].validation.score));
+    for (rank, i) in by_validation.into_iter().enumerate() {
+        candidates[i].validation_rank = rank + 1;
+    }
+    candidates.sort_by(|a, b| b.in_sample.score.total_cmp(&a.in_sample.score).then(a.validation_rank.cmp(&b.validation_rank)));
+    for (rank, candidate) in candidates.iter_mut().enumerate() {
+        candidate.rank = rank + 1;
+    }
+
+    let mut warnings = Vec::new();
+    let recommended = match validated_winner(&candidates) {
+        Ok(best) => best.map(|best| StrategyLoopConfig {
+            signals: best.signals.clone(),
+            default_rule: best.rule.clone(),
+            ..spec.base_config.clone()
+        }),
+        Err(warning) => {
+            warnings.push(warning);
+            None
+        }
+    };
+    if splits.len() < bars.len() {
+        warnings.push("Symbols with fewer than four bars were left out".to_string());
+    }
+
+    Ok(SignalOptimization {
+        id: None,
+        created_at: chrono::Utc::now().timestamp(),
+        spec: spec.clone(),
+        candidates,
+        recommended,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::bars::Timeframe;
+
+    fn bars(closes: &[f64]) -> Vec<Candle> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, c)| Candle {
+                timestamp: 1_700_000_000 + i as i64 * 86_400,
+                open: *c,
+                high: *c,
+                low: *c,
+                close: *c,
+                volume: 1_000,
+                symbol: None,
+                interval: None,
+            })
+            .collect()
+    }
+
+    fn spec(method: SearchMethod) -> OptimizationSpec {
+        OptimizationSpec {
+            symbols: vec!["ABC".to_string()],
+            start_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+            base_config: StrategyLoopConfig {
+                signals: vec![SignalConfig { name: "Trend".to_string(), timeframe: Timeframe::OneDay, lookback: 10, weight: 1.0 }],
+                default_rule: CombinationRule::WeightedVote { threshold: 0.3 },
+                ..StrategyLoopConfig::default()
+            },
+            search_space: SearchSpace {
+                signals: vec![SignalRanges {
+                    signal: "Trend@1d".to_string(),
+                    lookback: Some(ParamRange { min: 5.0, max: 40.0, step: 5.0 }),
+                    weight: None,
+                }],
+                threshold: Some(ParamRange { min: 0.1, max: 0.5, step: 0.2 }),
+            },
+            objective: OptimizationObjective::Sharpe,
+            method,
+        }
+    }
+
+    #[test]
+    fn test_replay_matches_live_decisions_and_guards_overfitting() {
+        // A steady climb: the trend signal goes long once it has history and stays long
+        let rising: Vec<f64> = (0..60).map(|i| 100.0 + i as f64).collect();
+        let trend = &spec(SearchMethod::Grid).base_config;
+        let (curve, trades) = replay_signals(&trend.signals, &trend.default_rule, &bars(&rising), 0, 60);
+        assert_eq!(curve.len(), 60);
+        assert_eq!(trades.len(), 1);
+        assert!((curve[59] - 159.0 / 109.0).abs() < 1e-9); // In from the tenth bar's close
+
+        // Every candidate trades the climb alike, so the winner validates
+        let result = optimize(&spec(SearchMethod::Grid), &[("ABC".to_string(), bars(&rising))], &mut |_, _| true).unwrap();
+        assert_eq!(result.candidates.len(), 8 * 3);
+        assert!(result.recommended.is_some());
+        assert_eq!(result.candidates[0].rank, 1);
+
+        // An in-sample winner that ranks poorly out of sample is held back
+        let ranked = |rank, validation_rank| RankedCandidate {
+            rank,
+            validation_rank,
+            signals: trend.signals.clone(),
+            rule: trend.default_rule.clone(),
+            in_sample: SegmentMetrics::default(),
+            validation: SegmentMetrics::default(),
+        };
+        let overfit: Vec<_> = (1..=8).map(|rank| ranked(rank, 9 - rank)).collect();
+        assert!(validated_winner(&overfit).unwrap_err().contains("overfit"));
+        let robust: Vec<_> = (1..=8).map(|rank| ranked(rank, if rank <= 2 { 3 - rank } else { rank })).collect();
+        assert_eq!(validated_winner(&robust).unwrap().unwrap().validation_rank, 2);
+
+        // Random search is reproducible from its seed and stops when asked
+        let random = SearchMethod::Random { samples: 5, seed: 7 };
+        let first = optimize(&spec(random), &[("ABC".to_string(), bars(&rising))], &mut |_, _| true).unwrap();
+        let second = optimize(&spec(random), &[("ABC".to_string(), bars(&rising))], &mut |_, _| true).unwrap();
+        assert_eq!(first.candidates.len(), 5);
+        let lookbacks = |r: &SignalOptimization| r.candidates.iter().map(|c| (c.signals[0].lookback, c.rank)).collect::<Vec<_>>();
+        assert_eq!(lookbacks(&first), lookbacks(&second));
+        assert!(optimize(&spec(random), &[("ABC".to_string(), bars(&rising))], &mut |n, _| n < 2).is_err());
+    }
+}
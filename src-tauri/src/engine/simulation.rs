@@ -0,0 +1,124 @@
+// src-tauri/src/engine/simulation.rs
+// Synthetic market data for offline development: geometric Brownian motion driven by a seeded LCG
+
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Simulated ticks per trading day; each tick is one simulated minute
+pub const SIM_STEPS_PER_DAY: f64 = 390.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    pub enabled: bool,
+    pub start_price: f64, // First price for symbols without market data
+    pub daily_vol: f64,   // e.g. 0.02 = 2% daily standard deviation
+    pub drift: f64,       // Expected daily log return, e.g. 0.0005
+    pub seed: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start_price: 100.0,
+            daily_vol: 0.02,
+            drift: 0.0,
+            seed: 42,
+        }
+    }
+}
+
+/// Deterministic LCG shared by the synthetic equity curve and the price simulator
+#[derive(Debug, Clone)]
+pub struct SimRng {
+    state: u64,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        let mut hasher = DefaultHasher::new();
+        seed.hash(&mut hasher);
+        Self { state: hasher.finish() }
+    }
+
+    /// Next value in 0..32768
+    pub fn next_u15(&mut self) -> u64 {
+        self.state = self.state.wrapping_mul(1103515245).wrapping_add(12345);
+        (self.state / 65536) % 32768
+    }
+
+    /// Uniform in (0, 1), never exactly 0 or 1
+    pub fn next_uniform(&mut self) -> f64 {
+        (self.next_u15() as f64 + 0.5) / 32768.0
+    }
+
+    /// Standard normal via Box-Muller
+    pub fn next_normal(&mut self) -> f64 {
+        let u1 = self.next_uniform();
+        let u2 = self.next_uniform();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+/// One GBM step of `SIM_STEPS_PER_DAY`-th of a day
+pub fn gbm_step(price: f64, drift: f64, daily_vol: f64, rng: &mut SimRng) -> f64 {
+    let dt = 1.0 / SIM_STEPS_PER_DAY;
+    let z = rng.next_normal();
+    price * ((drift - 0.5 * daily_vol * daily_vol) * dt + daily_vol * dt.sqrt() * z).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gbm_is_deterministic_per_seed() {
+        let path = |seed| {
+            let mut rng = SimRng::new(seed);
+            let mut price = 100.0;
+            (0..50).map(|_| { price = gbm_step(price, 0.0005, 0.02, &mut rng); price }).collect::<Vec<f64>>()
+        };
+
+        assert_eq!(path(7), path(7));
+        assert_ne!(path(7), path(8));
+        assert!(path(7).iter().all(|p| *p > 0.0));
+    }
+
+    #[test]
+    fn test_gbm_log_returns_match_parameters() {
+        let (drift, daily_vol) = (0.001, 0.02);
+        let mut rng = SimRng::new(1);
+        let mut price = 100.0;
+        let n = 20_000;
+
+        let returns: Vec<f64> = (0..n)
+            .map(|_| {
+                let next = gbm_step(price, drift, daily_vol, &mut rng);
+                let r = (next / price).ln();
+                price = next;
+                r
+            })
+            .collect();
+
+        let mean = returns.iter().sum::<f64>() / n as f64;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1) as f64;
+        let dt = 1.0 / SIM_STEPS_PER_DAY;
+        let expected_sd = daily_vol * dt.sqrt();
+
+        // Per-step standard deviation within 5%; mean within 3 standard errors
+        assert!((variance.sqrt() / expected_sd - 1.0).abs() < 0.05);
+        let expected_mean = (drift - 0.5 * daily_vol * daily_vol) * dt;
+        assert!((mean - expected_mean).abs() < 3.0 * expected_sd / (n as f64).sqrt());
+    }
+
+    #[test]
+    fn test_zero_vol_follows_drift() {
+        let mut rng = SimRng::new(3);
+        let mut price = 50.0;
+        for _ in 0..SIM_STEPS_PER_DAY as usize {
+            price = gbm_step(price, 0.01, 0.0, &mut rng);
+        }
+        assert!((price - 50.0 * 0.01_f64.exp()).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,147 @@
+// src-tauri/src/engine/spread.rs
+// Bid/ask spread estimates for prices that come without a usable quote. Daily bars
+// carry no spread, so it is read from their highs and lows with the Corwin-Schultz
+// estimator; a streamed symbol's recent two-sided quotes give the realized spread
+// instead. Backtests under the EstimatedSpread fill model and order estimates for a
+// symbol with no bid or ask cross half the estimate per side.
+
+use super::types::MarketData;
+use crate::market_data::types::Candle;
+use serde::{Deserialize, Serialize};
+
+pub const SPREAD_LOOKBACK_BARS: usize = 60; // Consecutive-day pairs read for an estimate
+pub const MAX_QUOTE_SAMPLES: usize = 200;   // Quote spreads kept per streamed symbol
+const MIN_BAR_PAIRS: usize = 10;
+const MIN_QUOTE_SAMPLES: usize = 20;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpreadMethod {
+    CorwinSchultz,  // From daily highs and lows
+    RealizedQuotes, // Mean of recent streamed quotes' spreads
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SpreadEstimate {
+    pub symbol: String,
+    pub spread_bps: f64, // Full spread relative to the midpoint
+    pub method: SpreadMethod,
+    pub lookback: usize, // Day pairs or quotes read
+    pub confidence: f64, // 0..1
+    pub estimated_at: i64,
+}
+
+impl SpreadEstimate {
+    /// Half the spread as a fraction of price, what one side of a trade crosses
+    pub fn half_spread(&self) -> f64 {
+        self.spread_bps / 20_000.0
+    }
+}
+
+/// Corwin-Schultz spread of two consecutive days' (high, low), as a fraction of price.
+/// Negative when the days' volatility swamps the spread.
+pub fn corwin_schultz_pair(first: (f64, f64), second: (f64, f64)) -> f64 {
+    let k = 3.0 - 2.0 * std::f64::consts::SQRT_2;
+    let beta = (first.0 / first.1).ln().powi(2) + (second.0 / second.1).ln().powi(2);
+    let gamma = (first.0.max(second.0) / first.1.min(second.1)).ln().powi(2);
+    let alpha = ((2.0 * beta).sqrt() - beta.sqrt()) / k - (gamma / k).sqrt();
+    2.0 * (alpha.exp() - 1.0) / (1.0 + alpha.exp())
+}
+
+/// Estimate from the last SPREAD_LOOKBACK_BARS day pairs of daily `bars`. A day that
+/// opened clear of the prior close has its range shifted back to the close so the
+/// overnight move is not read as spread, and negative pair estimates count as zero.
+/// Confidence grows with the pairs read and the share of them with a positive estimate.
+pub fn from_bars(symbol: &str, bars: &[Candle], now: i64) -> Option<SpreadEstimate> {
+    let recent = &bars[bars.len().saturating_sub(SPREAD_LOOKBACK_BARS + 1)..];
+    let estimates: Vec<f64> = recent
+        .windows(2)
+        .filter(|w| w.iter().all(|c| c.low > 0.0 && c.high >= c.low))
+        .map(|w| {
+            let (prior, day) = (&w[0], &w[1]);
+            let gap = if day.low > prior.close {
+                prior.close - day.low
+            } else if day.high < prior.close {
+                prior.close - day.high
+            } else {
+                0.0
+            };
+            corwin_schultz_pair((prior.high, prior.low), (day.high + gap, day.low + gap))
+        })
+        .collect();
+    if estimates.len() < MIN_BAR_PAIRS {
+        return None;
+    }
+    let pairs = estimates.len() as f64;
+    let positive = estimates.iter().filter(|s| **s > 0.0).count() as f64;
+    Some(SpreadEstimate {
+        symbol: symbol.to_string(),
+        spread_bps: estimates.iter().map(|s| s.max(0.0)).sum::<f64>() / pairs * 10_000.0,
+        method: SpreadMethod::CorwinSchultz,
+        lookback: estimates.len(),
+        confidence: (pairs / SPREAD_LOOKBACK_BARS as f64).min(1.0) * positive / pairs,
+        estimated_at: now,
+    })
+}
+
+/// Estimate from streamed quotes' relative spreads, once there are enough of them
+pub fn from_quotes<'a>(symbol: &str, spreads: impl ExactSizeIterator<Item = &'a f64>, now: i64) -> Option<SpreadEstimate> {
+    let samples = spreads.len();
+    if samples < MIN_QUOTE_SAMPLES {
+        return None;
+    }
+    Some(SpreadEstimate {
+        symbol: symbol.to_string(),
+        spread_bps: spreads.sum::<f64>() / samples as f64 * 10_000.0,
+        method: SpreadMethod::RealizedQuotes,
+        lookback: samples,
+        confidence: (samples as f64 / MAX_QUOTE_SAMPLES as f64).min(1.0),
+        estimated_at: now,
+    })
+}
+
+/// Spread of a two-sided quote relative to its midpoint
+pub fn quote_spread(data: &MarketData) -> Option<f64> {
+    match (data.bid, data.ask) {
+        (Some(bid), Some(ask)) if bid > 0.0 && ask >= bid => Some((ask - bid) / ((ask + bid) / 2.0)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corwin_schultz_matches_worked_values() {
+        // With no move between the days the estimator returns the whole range:
+        // alpha = ln(H/L), so S = 2(H - L)/(H + L)
+        assert!((corwin_schultz_pair((101.0, 99.0), (101.0, 99.0)) - 0.02).abs() < 1e-12);
+        assert!((corwin_schultz_pair((50.6, 49.8), (50.7, 49.9)) - 0.011115951364012474).abs() < 1e-12);
+        assert!((corwin_schultz_pair((101.0, 99.0), (101.5, 99.3)) - 0.011417873682339002).abs() < 1e-12);
+        // A trending pair reads negative
+        assert!((corwin_schultz_pair((101.0, 99.0), (102.0, 100.0)) - -0.004122108538858288).abs() < 1e-12);
+
+        // A day opening clear of the prior close is shifted back to it before estimating
+        let day = |i: i64, high: f64, low: f64, close: f64| Candle::new(1704153600 + i * 86400, close, high, low, close, 1_000);
+        let bars: Vec<Candle> = (0..61)
+            .map(|i| match i % 2 {
+                0 => day(i, 101.0, 99.0, 99.2),
+                _ => day(i, 102.0, 100.0, 101.0), // Opens 0.8 over the close: read as 101.2-99.2
+            })
+            .collect();
+        let estimate = from_bars("THIN", &bars, 0).unwrap();
+        assert_eq!((estimate.method, estimate.lookback), (SpreadMethod::CorwinSchultz, SPREAD_LOOKBACK_BARS));
+        let up = corwin_schultz_pair((101.0, 99.0), (101.2, 99.2));
+        let down = corwin_schultz_pair((102.0, 100.0), (101.0, 99.0)); // Within the close; negative, counted as zero
+        assert!(up > 0.0 && down < 0.0 && corwin_schultz_pair((101.0, 99.0), (102.0, 100.0)) < 0.0);
+        assert!((estimate.spread_bps - up / 2.0 * 10_000.0).abs() < 1e-9, "{}", estimate.spread_bps);
+        assert!((estimate.confidence - 0.5).abs() < 1e-12);
+        assert!(from_bars("THIN", &bars[..5], 0).is_none());
+
+        let quotes = vec![0.001; 50];
+        let realized = from_quotes("LIQ", quotes.iter(), 0).unwrap();
+        assert!((realized.spread_bps - 10.0).abs() < 1e-9 && realized.confidence == 0.25);
+        assert!(from_quotes("LIQ", quotes[..5].iter(), 0).is_none());
+    }
+}
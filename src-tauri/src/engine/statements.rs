@@ -0,0 +1,396 @@
+// src-tauri/src/engine/statements.rs
+// Monthly account statements assembled from the trade journal, daily summaries, cash
+// adjustments and applied splits. Assembly is pure and ordered so a month regenerates
+// byte-for-byte.
+
+use super::benchmark::{BenchmarkAccount, BenchmarkComparison};
+use super::position_history::{session_date, PositionReplay, ReplayedTrade};
+use super::types::{AdjustmentKind, CashAdjustment, DailySummary, InstrumentType, StockSplit, Trade};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const FEE_STOCK_COMMISSIONS: &str = "stock_commissions";
+pub const FEE_OPTION_COMMISSIONS: &str = "option_commissions";
+pub const FEE_ASSIGNMENT: &str = "assignment_fees";
+pub const FEE_EXERCISE: &str = "exercise_fees";
+pub const FEE_OTHER: &str = "other_fees";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatementTrade {
+    pub trade_id: String,
+    pub symbol: String,
+    pub timestamp: i64,
+    pub quantity: i64,
+    pub price: f64,
+    pub realized_pnl: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatementPosition {
+    pub symbol: String,
+    pub quantity: i64,
+    pub avg_cost: f64,
+    pub cost_basis: f64,
+    pub mark: Option<f64>,         // Last session close in the month, when one was recorded
+    pub market_value: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatementEquityPoint {
+    pub date: NaiveDate,
+    pub equity: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Statement {
+    pub year: i32,
+    pub month: u32,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub starting_balance: f64,
+    pub ending_balance: f64,
+    pub deposits: f64,
+    pub withdrawals: f64,
+    pub adjustments: Vec<CashAdjustment>,
+    pub trade_count: u32,
+    pub volume: i64,          // Shares and contracts traded
+    pub notional_traded: f64,
+    pub realized_pnl: f64,
+    pub unrealized_pnl_change: f64,
+    pub fees: BTreeMap<String, f64>, // By category; every category is always present
+    pub total_fees: f64,
+    pub interest: f64,
+    pub dividends: f64,
+    pub best_trade: Option<StatementTrade>,  // Closing trades only
+    pub worst_trade: Option<StatementTrade>,
+    pub positions: Vec<StatementPosition>,   // Open at month end, by symbol
+    pub equity_curve: Vec<StatementEquityPoint>,
+    #[serde(default)]
+    pub benchmark: Option<BenchmarkComparison>, // Relative performance against the shadow benchmark account
+}
+
+impl Statement {
+    /// Fill in the relative performance section from the shadow benchmark account
+    pub fn compare_with(&mut self, benchmark: &BenchmarkAccount) {
+        let net_transfers = self.deposits - self.withdrawals;
+        self.benchmark = benchmark.compare(self.period_start, self.period_end, self.starting_balance, self.ending_balance, net_transfers);
+    }
+}
+
+/// Realized P&L and activity in one symbol over a report period
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PnlReportRow {
+    pub symbol: String,
+    pub realized_pnl: f64,
+    pub commissions: f64,
+    pub trade_count: u32,
+    pub volume: i64, // Shares and contracts traded
+}
+
+/// First and last calendar day of a month
+pub fn month_bounds(year: i32, month: u32) -> Result<(NaiveDate, NaiveDate), String> {
+    let start = NaiveDate::from_ymd_opt(year, month, 1).ok_or_else(|| format!("Invalid month {}-{:02}", year, month))?;
+    let next = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    };
+    let end = next.and_then(|d| d.pred_opt()).ok_or_else(|| format!("Invalid month {}-{:02}", year, month))?;
+    Ok((start, end))
+}
+
+fn fee_category(trade: &Trade) -> &'static str {
+    match (trade.tag.as_deref(), &trade.instrument_type) {
+        (Some("assignment"), _) if trade.assignment_id.is_some() => FEE_ASSIGNMENT,
+        (Some("exercise"), _) if trade.assignment_id.is_some() => FEE_EXERCISE,
+        (_, InstrumentType::Option) => FEE_OPTION_COMMISSIONS,
+        (_, InstrumentType::Stock) => FEE_STOCK_COMMISSIONS,
+    }
+}
+
+pub fn build_statement(
+    year: i32,
+    month: u32,
+    trades: &[Trade],
+    summaries: &[DailySummary],
+    adjustments: &[CashAdjustment],
+    splits: &[StockSplit],
+) -> Result<Statement, String> {
+    let (period_start, period_end) = month_bounds(year, month)?;
+    let in_period = |date: NaiveDate| date >= period_start && date <= period_end;
+
+    // Replay the journal to get realized P&L per trade and month-end holdings
+    let mut replay = PositionReplay::new(trades, splits);
+    let mut fees: BTreeMap<String, f64> = [FEE_STOCK_COMMISSIONS, FEE_OPTION_COMMISSIONS, FEE_ASSIGNMENT, FEE_EXERCISE, FEE_OTHER]
+        .iter()
+        .map(|c| (c.to_string(), 0.0))
+        .collect();
+    let (mut trade_count, mut volume, mut notional_traded, mut realized_pnl) = (0u32, 0i64, 0.0, 0.0);
+    let mut best_trade: Option<StatementTrade> = None;
+    let mut worst_trade: Option<StatementTrade> = None;
+
+    for ReplayedTrade { trade, realized_pnl: realized, closes } in replay.advance_through(period_end) {
+        if !in_period(session_date(trade.timestamp)) {
+            continue;
+        }
+        trade_count += 1;
+        volume += trade.quantity;
+        notional_traded += trade.price * trade.quantity as f64;
+        realized_pnl += realized;
+        *fees.get_mut(fee_category(trade)).unwrap() += trade.commission;
+
+        if closes {
+            let record = StatementTrade {
+                trade_id: trade.id.clone(),
+                symbol: trade.symbol.clone(),
+                timestamp: trade.timestamp,
+                quantity: trade.quantity,
+                price: trade.price,
+                realized_pnl: realized,
+            };
+            // Ties keep the earlier trade
+            if best_trade.as_ref().is_none_or(|best| record.realized_pnl > best.realized_pnl) {
+                best_trade = Some(record.clone());
+            }
+            if worst_trade.as_ref().is_none_or(|worst| record.realized_pnl < worst.realized_pnl) {
+                worst_trade = Some(record);
+            }
+        }
+    }
+
+    let mut month_adjustments: Vec<CashAdjustment> = adjustments
+        .iter()
+        .filter(|a| in_period(session_date(a.timestamp)))
+        .cloned()
+        .collect();
+    month_adjustments.sort_by(|a, b| (a.timestamp, &a.id).cmp(&(b.timestamp, &b.id)));
+    let total = |kind: AdjustmentKind| month_adjustments.iter().filter(|a| a.kind == kind).map(|a| a.amount).sum::<f64>();
+    *fees.get_mut(FEE_OTHER).unwrap() += total(AdjustmentKind::Fee);
+
+    let mut days: Vec<&DailySummary> = summaries.iter().filter(|s| s.date <= period_end).collect();
+    days.sort_by_key(|s| s.date);
+    let prior = days.iter().rev().find(|s| s.date < period_start);
+    let month_days: Vec<&DailySummary> = days.iter().filter(|s| in_period(s.date)).copied().collect();
+
+    let starting_balance = prior
+        .map(|s| s.ending_equity)
+        .or_else(|| month_days.first().map(|s| s.starting_equity))
+        .unwrap_or(0.0);
+    let ending_balance = month_days.last().map(|s| s.ending_equity).unwrap_or(starting_balance);
+    let unrealized_start = prior.map(|s| s.unrealized_pnl).unwrap_or(0.0);
+    let unrealized_end = month_days.last().or(prior).map(|s| s.unrealized_pnl).unwrap_or(0.0);
+    let marks = month_days.last().or(prior).map(|s| &s.marks);
+
+    let positions = replay
+        .into_holdings()
+        .into_values()
+        .map(|p| {
+            let mark = marks.and_then(|m| m.get(&p.symbol)).copied();
+            StatementPosition {
+                quantity: p.quantity,
+                avg_cost: p.avg_cost,
+                cost_basis: p.quantity as f64 * p.avg_cost,
+                mark,
+                market_value: mark.map(|m| m * p.quantity as f64),
+                symbol: p.symbol,
+            }
+        })
+        .collect();
+
+    Ok(Statement {
+        year: period_start.year(),
+        month: period_start.month(),
+        period_start,
+        period_end,
+        starting_balance,
+        ending_balance,
+        deposits: total(AdjustmentKind::Deposit),
+        withdrawals: total(AdjustmentKind::Withdrawal),
+        trade_count,
+        volume,
+        notional_traded,
+        realized_pnl,
+        unrealized_pnl_change: unrealized_end - unrealized_start,
+        total_fees: fees.values().sum(),
+        fees,
+        interest: total(AdjustmentKind::Interest),
+        dividends: total(AdjustmentKind::Dividend),
+        best_trade,
+        worst_trade,
+        positions,
+        equity_curve: month_days
+            .iter()
+            .map(|s| StatementEquityPoint { date: s.date, equity: s.ending_equity })
+            .collect(),
+        adjustments: month_adjustments,
+        benchmark: None,
+    })
+}
+
+/// P&L per symbol from the trades of sessions `from` through `to`. `trades` must hold
+/// every earlier trade in those symbols too: they set the basis the period's closes
+/// realize against.
+pub fn pnl_report(trades: &[Trade], splits: &[StockSplit], from: NaiveDate, to: NaiveDate) -> Result<Vec<PnlReportRow>, String> {
+    if from > to {
+        return Err(format!("Report start {} is after its end {}", from, to));
+    }
+    let mut replay = PositionReplay::new(trades, splits);
+    if let Some(before) = from.pred_opt() {
+        replay.advance_through(before);
+    }
+    let mut rows: BTreeMap<String, PnlReportRow> = BTreeMap::new();
+    for ReplayedTrade { trade, realized_pnl, .. } in replay.advance_through(to) {
+        let row = rows.entry(trade.symbol.clone()).or_insert_with(|| PnlReportRow {
+            symbol: trade.symbol.clone(),
+            realized_pnl: 0.0,
+            commissions: 0.0,
+            trade_count: 0,
+            volume: 0,
+        });
+        row.realized_pnl += realized_pnl;
+        row.commissions += trade.commission;
+        row.trade_count += 1;
+        row.volume += trade.quantity;
+    }
+    Ok(rows.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::types::OrderSide;
+
+    // 10:00 New York on the given 2024 date
+    fn ts(month: u32, day: u32) -> i64 {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap().and_hms_opt(15, 0, 0).unwrap().and_utc().timestamp()
+    }
+
+    fn trade(id: &str, symbol: &str, side: OrderSide, quantity: i64, price: f64, timestamp: i64) -> Trade {
+        Trade {
+            id: id.to_string(),
+            symbol: symbol.to_string(),
+            side,
+            quantity,
+            price,
+            timestamp,
+            order_id: format!("order-{}", id),
+            commission: 1.0,
+            net_amount: price * quantity as f64,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            leg_number: None,
+            assignment_id: None,
+            tag: None,
+            evaluation_id: None,
+        }
+    }
+
+    fn summary(day: (u32, u32), starting_equity: f64, ending_equity: f64, unrealized_pnl: f64, marks: &[(&str, f64)]) -> DailySummary {
+        DailySummary {
+            date: NaiveDate::from_ymd_opt(2024, day.0, day.1).unwrap(),
+            starting_equity,
+            ending_equity,
+            cash: 0.0,
+            unrealized_pnl,
+            marks: marks.iter().map(|(s, p)| (s.to_string(), *p)).collect(),
+            open_auction_fills: 0,
+            close_auction_fills: 0,
+            realized_equity: None,
+            equity_range: None,
+            benchmark_day_pnl: None,
+        }
+    }
+
+    fn adjustment(id: &str, kind: AdjustmentKind, amount: f64, timestamp: i64) -> CashAdjustment {
+        CashAdjustment { id: id.to_string(), timestamp, kind, amount, symbol: None, note: None }
+    }
+
+    #[test]
+    fn test_pnl_report_realizes_against_earlier_basis() {
+        let trades = vec![
+            trade("t1", "AAPL", OrderSide::Buy, 10, 100.0, ts(1, 10)),
+            trade("t2", "AAPL", OrderSide::Buy, 10, 110.0, ts(2, 5)),
+            trade("t3", "AAPL", OrderSide::Sell, 15, 120.0, ts(2, 20)),
+            trade("t4", "MSFT", OrderSide::Buy, 5, 400.0, ts(3, 1)),
+        ];
+        let (from, to) = month_bounds(2024, 2).unwrap();
+        let report = pnl_report(&trades, &[], from, to).unwrap();
+        assert_eq!(report.len(), 1);
+        let row = &report[0];
+        assert_eq!((row.symbol.as_str(), row.trade_count, row.volume), ("AAPL", 2, 25));
+        assert!((row.commissions - 2.0).abs() < 1e-9);
+        // Closed against the January lot too, as the statement does
+        let statement = build_statement(2024, 2, &trades, &[], &[], &[]).unwrap();
+        assert!(row.realized_pnl > 0.0);
+        assert!((row.realized_pnl - statement.realized_pnl).abs() < 1e-9);
+
+        assert!(pnl_report(&trades, &[], to, from).is_err());
+    }
+
+    #[test]
+    fn test_empty_month_statement() {
+        let statement = build_statement(2024, 2, &[], &[], &[], &[]).unwrap();
+        assert_eq!((statement.period_start, statement.period_end), (
+            NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(),
+        ));
+        assert_eq!((statement.starting_balance, statement.ending_balance, statement.trade_count), (0.0, 0.0, 0));
+        assert_eq!(statement.fees.len(), 5);
+        assert_eq!(statement.total_fees, 0.0);
+        assert!(statement.best_trade.is_none() && statement.positions.is_empty() && statement.equity_curve.is_empty());
+
+        assert!(build_statement(2024, 13, &[], &[], &[], &[]).is_err());
+    }
+
+    #[test]
+    fn test_statement_from_journal_and_summaries() {
+        let trades = vec![
+            trade("t1", "AAPL", OrderSide::Buy, 100, 100.0, ts(1, 31)),
+            trade("t4", "MSFT", OrderSide::Buy, 10, 400.0, ts(2, 7)),
+            trade("t2", "AAPL", OrderSide::Sell, 50, 110.0, ts(2, 5)),
+            trade("t3", "AAPL", OrderSide::Sell, 20, 95.0, ts(2, 6)),
+        ];
+        let summaries = vec![
+            summary((2, 29), 10700.0, 10800.0, 300.0, &[("AAPL", 105.0), ("MSFT", 410.0)]),
+            summary((1, 31), 9900.0, 10000.0, 0.0, &[("AAPL", 100.0)]),
+            summary((2, 5), 10000.0, 10500.0, 200.0, &[("AAPL", 110.0)]),
+        ];
+        let adjustments = vec![
+            adjustment("a1", AdjustmentKind::Deposit, 1000.0, ts(2, 10)),
+            adjustment("a2", AdjustmentKind::Fee, 5.0, ts(2, 12)),
+            CashAdjustment { symbol: Some("AAPL".to_string()), ..adjustment("a3", AdjustmentKind::Dividend, 12.0, ts(2, 15)) },
+            adjustment("a4", AdjustmentKind::Interest, 3.0, ts(3, 1)),
+        ];
+
+        let statement = build_statement(2024, 2, &trades, &summaries, &adjustments, &[]).unwrap();
+        assert_eq!((statement.starting_balance, statement.ending_balance), (10000.0, 10800.0));
+        assert_eq!((statement.trade_count, statement.volume), (3, 80));
+        assert_eq!(statement.notional_traded, 5500.0 + 1900.0 + 4000.0);
+        assert_eq!(statement.realized_pnl, 400.0);
+        assert_eq!(statement.unrealized_pnl_change, 300.0);
+        assert_eq!((statement.deposits, statement.withdrawals, statement.dividends, statement.interest), (1000.0, 0.0, 12.0, 0.0));
+        assert_eq!(statement.adjustments.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(), vec!["a1", "a2", "a3"]);
+
+        assert_eq!(statement.fees[FEE_STOCK_COMMISSIONS], 3.0);
+        assert_eq!(statement.fees[FEE_OTHER], 5.0);
+        assert_eq!(statement.total_fees, 8.0);
+
+        assert_eq!(statement.best_trade.as_ref().map(|t| t.trade_id.as_str()), Some("t2"));
+        assert_eq!(statement.worst_trade.as_ref().map(|t| (t.trade_id.as_str(), t.realized_pnl)), Some(("t3", -100.0)));
+
+        let held: Vec<(&str, i64, Option<f64>)> = statement
+            .positions
+            .iter()
+            .map(|p| (p.symbol.as_str(), p.quantity, p.market_value))
+            .collect();
+        assert_eq!(held, vec![("AAPL", 30, Some(3150.0)), ("MSFT", 10, Some(4100.0))]);
+        assert_eq!(statement.equity_curve.iter().map(|p| p.equity).collect::<Vec<_>>(), vec![10500.0, 10800.0]);
+
+        // Input order does not leak into the output
+        let mut shuffled = trades.clone();
+        shuffled.reverse();
+        let again = build_statement(2024, 2, &shuffled, &summaries, &adjustments, &[]).unwrap();
+        assert_eq!(serde_json::to_string(&again).unwrap(), serde_json::to_string(&statement).unwrap());
+    }
+}
@@ -0,0 +1,258 @@
+// src-tauri/src/engine/strategies.rs
+// Long/flat backtest strategies over daily closes: 12-1 month time-series momentum
+// and 20-day Bollinger Band mean reversion. Signals fill at the close of their bar,
+// unless the run prices its entries as limit or stop orders: those are placed after the
+// signal bar's close and fill on a later bar whose range reaches them.
+
+use super::types::OrderSide;
+use crate::market_data::types::Candle;
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+const MOMENTUM_LOOKBACK_MONTHS: usize = 12;
+const BOLLINGER_PERIOD: usize = 20;
+const BOLLINGER_WIDTH: f64 = 2.0; // Standard deviations
+const MEAN_REVERSION_MAX_HOLD_DAYS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BacktestStrategy {
+    TsMomentum,
+    MeanReversion,
+}
+
+impl BacktestStrategy {
+    /// The `BacktestParams::strategy` names these implement; other names keep the buy & hold path
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            n if n.eq_ignore_ascii_case("TsMomentum") => Some(BacktestStrategy::TsMomentum),
+            n if n.eq_ignore_ascii_case("MeanReversion") => Some(BacktestStrategy::MeanReversion),
+            _ => None,
+        }
+    }
+
+    /// Entries and exits over `closes`, as (session, close) oldest first
+    pub fn signals(&self, closes: &[(NaiveDate, f64)]) -> Vec<StrategySignal> {
+        match self {
+            BacktestStrategy::TsMomentum => ts_momentum_signals(closes),
+            BacktestStrategy::MeanReversion => mean_reversion_signals(closes),
+        }
+    }
+}
+
+/// How a run prices its strategy's buy signals; exits always fill at the close
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EntryOrder {
+    #[default]
+    AtClose,                   // The signal bar's close
+    LimitAtLow,                // A limit at the signal bar's low
+    Limit { offset_pct: f64 }, // A limit this far below the signal bar's close, e.g. 0.01
+    Stop { offset_pct: f64 },  // A stop this far above the signal bar's close
+}
+
+/// Whether a bar that reaches a limit or stop price exactly, without trading through
+/// it, fills the order
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TouchRule {
+    #[default]
+    Conservative, // No: the bar has to trade through the price
+    Optimistic,   // Yes
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct SignalOrderConfig {
+    pub entry: EntryOrder,
+    pub touch_rule: TouchRule,
+    pub expire_after_bars: usize, // Bars an unfilled entry works before it expires; at least 1
+}
+
+impl Default for SignalOrderConfig {
+    fn default() -> Self {
+        Self { entry: EntryOrder::AtClose, touch_rule: TouchRule::Conservative, expire_after_bars: 1 }
+    }
+}
+
+/// A buy order working from the bar after its signal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorkingEntry {
+    Limit(f64),
+    Stop(f64),
+}
+
+impl EntryOrder {
+    /// The order a buy signal on `bar` places; None when it fills at the close
+    pub fn working_entry(&self, bar: &Candle) -> Option<WorkingEntry> {
+        match *self {
+            EntryOrder::AtClose => None,
+            EntryOrder::LimitAtLow => Some(WorkingEntry::Limit(bar.low)),
+            EntryOrder::Limit { offset_pct } => Some(WorkingEntry::Limit(bar.close * (1.0 - offset_pct))),
+            EntryOrder::Stop { offset_pct } => Some(WorkingEntry::Stop(bar.close * (1.0 + offset_pct))),
+        }
+    }
+}
+
+impl WorkingEntry {
+    pub fn price(&self) -> f64 {
+        match *self {
+            WorkingEntry::Limit(price) | WorkingEntry::Stop(price) => price,
+        }
+    }
+
+    /// Where the order fills on `bar`, if it does: at the open when the bar gapped
+    /// through the price, else at the price once the range trades through it (or, under
+    /// the optimistic rule, touches it)
+    pub fn fill_price(&self, bar: &Candle, rule: TouchRule) -> Option<f64> {
+        let touches = |level: f64| rule == TouchRule::Optimistic && (level - self.price()).abs() < 1e-9;
+        match *self {
+            WorkingEntry::Limit(limit) if bar.open <= limit => Some(bar.open),
+            WorkingEntry::Limit(limit) if bar.low < limit || touches(bar.low) => Some(limit),
+            WorkingEntry::Stop(stop) if bar.open >= stop => Some(bar.open),
+            WorkingEntry::Stop(stop) if bar.high > stop || touches(bar.high) => Some(stop),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategySignal {
+    pub index: usize, // Bar whose close the trade fills at
+    pub side: OrderSide,
+    pub reason: &'static str,
+}
+
+impl StrategySignal {
+    fn new(index: usize, side: OrderSide, reason: &'static str) -> Self {
+        Self { index, side, reason }
+    }
+}
+
+/// Rebalance on the last trading day of each month: long while the 12-1 month return
+/// (month-end closes 12 months back to 1 month back) is positive, in cash otherwise.
+/// The final bar is not treated as a month end since the month may not be over.
+pub fn ts_momentum_signals(closes: &[(NaiveDate, f64)]) -> Vec<StrategySignal> {
+    let months: Vec<(i32, u32)> = closes.iter().map(|(date, _)| (date.year(), date.month())).collect();
+    let month_ends: Vec<usize> = (0..closes.len().saturating_sub(1)).filter(|&i| months[i] != months[i + 1]).collect();
+
+    let mut signals = Vec::new();
+    let mut long = false;
+    for k in MOMENTUM_LOOKBACK_MONTHS..month_ends.len() {
+        let from = closes[month_ends[k - MOMENTUM_LOOKBACK_MONTHS]].1;
+        let to = closes[month_ends[k - 1]].1;
+        let positive = from > 0.0 && to / from - 1.0 > 0.0;
+        if positive != long {
+            let (side, reason) = if positive {
+                (OrderSide::Buy, "momentum_positive")
+            } else {
+                (OrderSide::Sell, "momentum_negative")
+            };
+            signals.push(StrategySignal::new(month_ends[k], side, reason));
+            long = positive;
+        }
+    }
+    signals
+}
+
+/// Buy a close below the lower 20-day Bollinger Band; sell a close above the upper
+/// band or after 10 days in the trade, whichever comes first
+pub fn mean_reversion_signals(closes: &[(NaiveDate, f64)]) -> Vec<StrategySignal> {
+    let mut signals = Vec::new();
+    let mut entered_at: Option<usize> = None;
+    for i in BOLLINGER_PERIOD.saturating_sub(1)..closes.len() {
+        let (lower, upper) = bollinger_bands(&closes[i + 1 - BOLLINGER_PERIOD..=i]);
+        let close = closes[i].1;
+        match entered_at {
+            None if close < lower => {
+                signals.push(StrategySignal::new(i, OrderSide::Buy, "below_lower_band"));
+                entered_at = Some(i);
+            }
+            Some(_) if close > upper => {
+                signals.push(StrategySignal::new(i, OrderSide::Sell, "above_upper_band"));
+                entered_at = None;
+            }
+            Some(entry) if i - entry >= MEAN_REVERSION_MAX_HOLD_DAYS => {
+                signals.push(StrategySignal::new(i, OrderSide::Sell, "max_hold"));
+                entered_at = None;
+            }
+            _ => {}
+        }
+    }
+    signals
+}
+
+/// (lower, upper) bands: mean -/+ 2 population standard deviations of the window
+fn bollinger_bands(window: &[(NaiveDate, f64)]) -> (f64, f64) {
+    let n = window.len() as f64;
+    let mean = window.iter().map(|(_, c)| c).sum::<f64>() / n;
+    let std = (window.iter().map(|(_, c)| (c - mean).powi(2)).sum::<f64>() / n).sqrt();
+    (mean - BOLLINGER_WIDTH * std, mean + BOLLINGER_WIDTH * std)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn daily_closes(start: NaiveDate, closes: &[f64]) -> Vec<(NaiveDate, f64)> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (start + chrono::Duration::days(i as i64), *c))
+            .collect()
+    }
+
+    #[test]
+    fn test_ts_momentum_rebalances_at_month_end() {
+        // 15 months of calendar days: rising through June 2024, falling afterwards
+        let start = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+        let peak = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+        let days = (NaiveDate::from_ymd_opt(2024, 12, 15).unwrap() - start).num_days() as usize;
+        let closes: Vec<f64> = (0..days)
+            .map(|i| {
+                let d = (start + chrono::Duration::days(i as i64) - peak).num_days() as f64;
+                if d <= 0.0 { 200.0 + d * 0.1 } else { 200.0 - d * 0.5 }
+            })
+            .collect();
+        let closes = daily_closes(start, &closes);
+
+        let signals = ts_momentum_signals(&closes);
+        let dates: Vec<(NaiveDate, &OrderSide)> = signals.iter().map(|s| (closes[s.index].0, &s.side)).collect();
+        // First decision once 12 month-ends precede it; the 12-1 return turns negative
+        // once the month-end a month back is well below the one a year earlier
+        assert_eq!(dates[0], (NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(), &OrderSide::Buy));
+        assert_eq!(dates.len(), 2);
+        assert_eq!(dates[1].1, &OrderSide::Sell);
+        assert_eq!(signals[1].reason, "momentum_negative");
+        let exit = dates[1].0;
+        assert!(exit > peak && exit.succ_opt().unwrap().day() == 1);
+
+        // Less than 13 month-ends never trades
+        assert!(ts_momentum_signals(&closes[..365]).is_empty());
+    }
+
+    #[test]
+    fn test_mean_reversion_band_entries_and_exits() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        // Quiet range, a sharp drop (entry), then a rally through the upper band (exit)
+        let mut closes: Vec<f64> = (0..25).map(|i| 100.0 + if i % 2 == 0 { 0.5 } else { -0.5 }).collect();
+        closes.push(90.0); // 25: below the lower band
+        closes.extend([95.0, 100.0, 112.0]); // 28: above the upper band
+        // Another drop that never recovers: exited after 10 days
+        closes.extend((0..15).map(|i| 100.0 + if i % 2 == 0 { 0.5 } else { -0.5 }));
+        closes.push(80.0); // 44
+        closes.extend([80.0; 12]);
+        let closes = daily_closes(start, &closes);
+
+        let signals = mean_reversion_signals(&closes);
+        assert_eq!(
+            signals,
+            vec![
+                StrategySignal::new(25, OrderSide::Buy, "below_lower_band"),
+                StrategySignal::new(28, OrderSide::Sell, "above_upper_band"),
+                StrategySignal::new(44, OrderSide::Buy, "below_lower_band"),
+                StrategySignal::new(54, OrderSide::Sell, "max_hold"),
+            ]
+        );
+        assert_eq!(BacktestStrategy::parse("meanreversion"), Some(BacktestStrategy::MeanReversion));
+        assert_eq!(BacktestStrategy::parse("PMCC"), None);
+    }
+}
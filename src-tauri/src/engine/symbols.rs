@@ -0,0 +1,95 @@
+// src-tauri/src/engine/symbols.rs
+// Symbol metadata: one canonical key per instrument. Every entry point (orders, market
+// data, the watchlist, provider fetches) normalizes through here so "aapl " and "AAPL"
+// or BRK-B and BRK.B never become separate positions. Share classes are canonical with
+// a dot, as Polygon spells them; providers translate back to their own spelling.
+
+use super::option_symbol::OptionSymbolFormat;
+
+const MAX_SYMBOL_LEN: usize = 12;
+const SYMBOL_PUNCTUATION: [char; 5] = ['.', '-', '/', ':', '^']; // Share classes, X:/C: pairs, ^ indices
+
+// Spellings that cannot be told apart from a plain ticker by their shape
+const SYMBOL_ALIASES: [(&str, &str); 2] = [("BRKA", "BRK.A"), ("BRKB", "BRK.B")];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolProvider {
+    Polygon, // BRK.B
+    Yahoo,   // BRK-B
+}
+
+/// Trim, uppercase and validate `raw`, then resolve aliases to the canonical key.
+/// Option symbols are only trimmed and uppercased.
+pub fn normalize_symbol(raw: &str) -> Result<String, String> {
+    let symbol = raw.trim().to_uppercase();
+    if symbol.is_empty() {
+        return Err("Symbol cannot be empty".to_string());
+    }
+    if OptionSymbolFormat::detect(&symbol).is_some() {
+        return Ok(symbol);
+    }
+
+    let valid = symbol.len() <= MAX_SYMBOL_LEN
+        && symbol.chars().all(|c| c.is_ascii_alphanumeric() || SYMBOL_PUNCTUATION.contains(&c))
+        && symbol.chars().any(|c| c.is_ascii_alphanumeric());
+    if !valid {
+        return Err(format!(
+            "Symbol '{}' must be at most {} letters, digits, '.', '-', '/', ':' or '^'",
+            raw.trim(),
+            MAX_SYMBOL_LEN
+        ));
+    }
+
+    if let Some((_, canonical)) = SYMBOL_ALIASES.iter().find(|(alias, _)| *alias == symbol) {
+        return Ok(canonical.to_string());
+    }
+    Ok(match share_class(&symbol, &['-', '/']) {
+        Some((root, class)) => format!("{}.{}", root, class),
+        None => symbol,
+    })
+}
+
+/// `canonical` as `provider` expects it in API calls
+pub fn provider_symbol(canonical: &str, provider: SymbolProvider) -> String {
+    match (provider, share_class(canonical, &['.'])) {
+        (SymbolProvider::Yahoo, Some((root, class))) => format!("{}-{}", root, class),
+        _ => canonical.to_string(),
+    }
+}
+
+/// (root, class) of a share-class ticker such as BRK.B, split at one of `separators`.
+/// Longer suffixes (SHOP.TO, BTC-USD) are exchanges or pairs, not classes.
+fn share_class<'a>(symbol: &'a str, separators: &[char]) -> Option<(&'a str, &'a str)> {
+    let (root, class) = symbol.rsplit_once(separators)?;
+    let is_class = class.len() == 1
+        && class.chars().all(|c| c.is_ascii_alphabetic())
+        && !root.is_empty()
+        && root.chars().all(|c| c.is_ascii_alphabetic());
+    is_class.then_some((root, class))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_and_provider_spellings() {
+        for raw in ["brk.b", " BRK-B ", "BRK/B", "brkb"] {
+            assert_eq!(normalize_symbol(raw).unwrap(), "BRK.B", "{}", raw);
+        }
+        assert_eq!(normalize_symbol(" aapl\n").unwrap(), "AAPL");
+        assert_eq!(normalize_symbol("shop.to").unwrap(), "SHOP.TO");
+        assert_eq!(normalize_symbol("btc-usd").unwrap(), "BTC-USD");
+        assert_eq!(normalize_symbol("O:AAPL240315C00150000").unwrap(), "O:AAPL240315C00150000");
+
+        assert!(normalize_symbol("  ").is_err());
+        assert!(normalize_symbol("AA PL").is_err());
+        assert!(normalize_symbol("AAPL;DROP").is_err());
+        assert!(normalize_symbol("--").is_err());
+
+        assert_eq!(provider_symbol("BRK.B", SymbolProvider::Yahoo), "BRK-B");
+        assert_eq!(provider_symbol("BRK.B", SymbolProvider::Polygon), "BRK.B");
+        assert_eq!(provider_symbol("SHOP.TO", SymbolProvider::Yahoo), "SHOP.TO");
+        assert_eq!(normalize_symbol(&provider_symbol("BRK.B", SymbolProvider::Yahoo)).unwrap(), "BRK.B");
+    }
+}
@@ -0,0 +1,343 @@
+// src-tauri/src/engine/tax_lots.rs
+// Open stock tax lots rebuilt from the trade journal. Each buy opens a lot keyed by its
+// trade id; sells close lots first in, first out unless their order was placed against
+// specific lots. Splits restate lot quantities on their execution dates and keep the
+// basis, less any fractional share paid out in cash. Short sales open no lots.
+
+use super::position_history::session_date;
+use super::types::{InstrumentType, OrderSide, StockSplit, Trade};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+const LONG_TERM_HOLDING_DAYS: i64 = 365; // Held more than a year
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TaxLot {
+    pub id: String, // Id of the trade that opened the lot
+    pub symbol: String,
+    pub quantity: i64,            // Shares still open
+    pub cost_basis: f64,          // Of the open shares, buy commission included
+    pub acquired_date: NaiveDate, // Session date (New York)
+    #[serde(default)]
+    pub current_gain: Option<f64>, // gain_if_closed at the current mid, when quoted
+}
+
+impl TaxLot {
+    pub fn unit_cost(&self) -> f64 {
+        if self.quantity == 0 {
+            0.0
+        } else {
+            self.cost_basis / self.quantity as f64
+        }
+    }
+
+    /// Gain (negative for a loss) from selling the whole lot at `current_price`, before
+    /// the sale's commission
+    pub fn gain_if_closed(&self, current_price: f64) -> f64 {
+        current_price * self.quantity as f64 - self.cost_basis
+    }
+
+    pub fn is_long_term(&self, as_of: NaiveDate) -> bool {
+        (as_of - self.acquired_date).num_days() > LONG_TERM_HOLDING_DAYS
+    }
+
+    /// Take up to `quantity` shares out of the lot with their share of the basis
+    fn take(&mut self, quantity: i64) -> i64 {
+        let taken = quantity.min(self.quantity);
+        self.cost_basis -= self.unit_cost() * taken as f64;
+        self.quantity -= taken;
+        taken
+    }
+}
+
+/// How a sell order picks the lots it closes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum TaxMethod {
+    Fifo,
+    SpecId(Vec<(String, i64)>), // (lot id, shares), closed in this order
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum TaxOptimizationGoal {
+    MinimizeGain,     // Highest cost lots first
+    MaximizeGain,     // Lowest cost lots first
+    MaximizeLongTerm, // Lots held over a year first, highest cost first within each holding period
+}
+
+/// Open lots per symbol after replaying `trades` with `splits`. Sells close lots by their
+/// order's entry in `order_methods`, FIFO when it has none.
+pub fn open_lots(
+    trades: &[Trade],
+    splits: &[StockSplit],
+    order_methods: &HashMap<String, TaxMethod>,
+) -> BTreeMap<String, Vec<TaxLot>> {
+    let mut trades: Vec<&Trade> = trades.iter().filter(|t| t.instrument_type == InstrumentType::Stock).collect();
+    trades.sort_by(|a, b| (a.timestamp, &a.id).cmp(&(b.timestamp, &b.id)));
+    let mut splits: Vec<&StockSplit> = splits.iter().collect();
+    splits.sort_by_key(|s| s.execution_date);
+    let mut splits = splits.into_iter().peekable();
+
+    let mut lots: BTreeMap<String, Vec<TaxLot>> = BTreeMap::new();
+    let mut short_shares: HashMap<String, i64> = HashMap::new();
+    // Shares of each selection not yet closed, for orders that fill over several trades
+    let mut remaining_selections: HashMap<&str, Vec<(String, i64)>> = order_methods
+        .iter()
+        .filter_map(|(order_id, method)| match method {
+            TaxMethod::SpecId(selections) => Some((order_id.as_str(), selections.clone())),
+            TaxMethod::Fifo => None,
+        })
+        .collect();
+
+    for trade in trades {
+        let date = session_date(trade.timestamp);
+        while let Some(split) = splits.next_if(|s| s.execution_date <= date) {
+            apply_split(lots.get_mut(&split.symbol), split);
+            if let Some(short) = short_shares.get_mut(&split.symbol) {
+                *short = split.shares_after(*short).0;
+            }
+        }
+
+        let symbol_lots = lots.entry(trade.symbol.clone()).or_default();
+        let short = short_shares.entry(trade.symbol.clone()).or_insert(0);
+        match trade.side {
+            OrderSide::Buy => {
+                let covered = trade.quantity.min(*short);
+                *short -= covered;
+                let quantity = trade.quantity - covered;
+                if quantity > 0 {
+                    let cost_per_share = trade.price + trade.commission / trade.quantity as f64;
+                    symbol_lots.push(TaxLot {
+                        id: trade.id.clone(),
+                        symbol: trade.symbol.clone(),
+                        quantity,
+                        cost_basis: cost_per_share * quantity as f64,
+                        acquired_date: date,
+                        current_gain: None,
+                    });
+                }
+            }
+            OrderSide::Sell => {
+                let method = match remaining_selections.get_mut(trade.order_id.as_str()) {
+                    Some(selections) => TaxMethod::SpecId(std::mem::take(selections)),
+                    None => TaxMethod::Fifo,
+                };
+                let (unfilled, left_over) = close_lots(symbol_lots, trade.quantity, method);
+                if let Some(selections) = remaining_selections.get_mut(trade.order_id.as_str()) {
+                    *selections = left_over;
+                }
+                *short += unfilled;
+            }
+        }
+        symbol_lots.retain(|lot| lot.quantity > 0);
+    }
+    for split in splits {
+        apply_split(lots.get_mut(&split.symbol), split);
+    }
+
+    lots.retain(|_, symbol_lots| !symbol_lots.is_empty());
+    lots
+}
+
+/// Close `quantity` shares from `lots`; returns the shares no lot covered and the
+/// selections not yet used up
+fn close_lots(lots: &mut [TaxLot], mut quantity: i64, method: TaxMethod) -> (i64, Vec<(String, i64)>) {
+    let mut left_over = Vec::new();
+    if let TaxMethod::SpecId(selections) = method {
+        for (lot_id, selected) in selections {
+            let taken = match lots.iter_mut().find(|lot| lot.id == lot_id) {
+                Some(lot) if quantity > 0 => lot.take(selected.min(quantity)),
+                _ => 0,
+            };
+            quantity -= taken;
+            if selected > taken {
+                left_over.push((lot_id, selected - taken));
+            }
+        }
+    }
+    // FIFO for everything else, including a sell larger than its selections
+    for lot in lots.iter_mut() {
+        quantity -= lot.take(quantity);
+    }
+    (quantity, left_over)
+}
+
+fn apply_split(lots: Option<&mut Vec<TaxLot>>, split: &StockSplit) {
+    for lot in lots.into_iter().flatten() {
+        let (whole, fraction) = split.shares_after(lot.quantity);
+        let exact = whole as f64 + fraction;
+        if exact > 0.0 {
+            lot.cost_basis *= whole as f64 / exact;
+        }
+        lot.quantity = whole;
+    }
+}
+
+/// Check `selections` against a symbol's open lots: every lot exists and is selected
+/// once for a positive number of the shares it holds, and the total fits in the
+/// position. Returns the total shares selected.
+pub fn validate_lot_selections(lots: &[TaxLot], selections: &[(String, i64)], position_quantity: i64) -> Result<i64, String> {
+    if selections.is_empty() {
+        return Err("Select at least one lot to close".to_string());
+    }
+    let mut seen = HashSet::new();
+    for (lot_id, quantity) in selections {
+        let lot = lots.iter().find(|lot| &lot.id == lot_id).ok_or_else(|| format!("No open lot {}", lot_id))?;
+        if !seen.insert(lot_id) {
+            return Err(format!("Lot {} is selected more than once", lot_id));
+        }
+        if *quantity <= 0 || *quantity > lot.quantity {
+            return Err(format!("Lot {} holds {} shares; cannot close {}", lot_id, lot.quantity, quantity));
+        }
+    }
+    let total: i64 = selections.iter().map(|(_, quantity)| quantity).sum();
+    if total > position_quantity {
+        return Err(format!("Selected {} shares but the position holds {}", total, position_quantity));
+    }
+    Ok(total)
+}
+
+/// Lots and shares that close `target_quantity` shares toward `goal`
+pub fn optimize_lots(
+    lots: &[TaxLot],
+    target_quantity: i64,
+    goal: TaxOptimizationGoal,
+    as_of: NaiveDate,
+) -> Result<Vec<(String, i64)>, String> {
+    let available: i64 = lots.iter().map(|lot| lot.quantity).sum();
+    if target_quantity <= 0 || target_quantity > available {
+        return Err(format!("Target must be between 1 and the {} shares held in lots, got {}", available, target_quantity));
+    }
+
+    let mut ranked: Vec<&TaxLot> = lots.iter().collect();
+    // Ties go to the oldest lot
+    ranked.sort_by(|a, b| {
+        let by_goal = match goal {
+            TaxOptimizationGoal::MinimizeGain => b.unit_cost().total_cmp(&a.unit_cost()),
+            TaxOptimizationGoal::MaximizeGain => a.unit_cost().total_cmp(&b.unit_cost()),
+            TaxOptimizationGoal::MaximizeLongTerm => b
+                .is_long_term(as_of)
+                .cmp(&a.is_long_term(as_of))
+                .then(b.unit_cost().total_cmp(&a.unit_cost())),
+        };
+        by_goal.then(a.acquired_date.cmp(&b.acquired_date))
+    });
+
+    let mut remaining = target_quantity;
+    let mut selections = Vec::new();
+    for lot in ranked {
+        if remaining == 0 {
+            break;
+        }
+        let quantity = remaining.min(lot.quantity);
+        selections.push((lot.id.clone(), quantity));
+        remaining -= quantity;
+    }
+    Ok(selections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(year: i32, month: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, d).unwrap()
+    }
+
+    // 10:00 New York
+    fn trade(id: &str, side: OrderSide, quantity: i64, price: f64, date: NaiveDate) -> Trade {
+        Trade {
+            id: id.to_string(),
+            symbol: "AAPL".to_string(),
+            side,
+            quantity,
+            price,
+            timestamp: date.and_hms_opt(15, 0, 0).unwrap().and_utc().timestamp(),
+            order_id: format!("order-{}", id),
+            commission: 1.0,
+            net_amount: 0.0,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            leg_number: None,
+            assignment_id: None,
+            tag: None,
+            evaluation_id: None,
+        }
+    }
+
+    fn lots_held() -> Vec<TaxLot> {
+        let trades = vec![
+            trade("old-cheap", OrderSide::Buy, 100, 100.0, day(2022, 3, 1)),
+            trade("old-dear", OrderSide::Buy, 50, 180.0, day(2022, 9, 1)),
+            trade("new-dear", OrderSide::Buy, 100, 190.0, day(2024, 2, 1)),
+            trade("new-cheap", OrderSide::Buy, 100, 150.0, day(2024, 5, 1)),
+        ];
+        open_lots(&trades, &[], &HashMap::new()).remove("AAPL").unwrap()
+    }
+
+    #[test]
+    fn test_fifo_and_specific_identification() {
+        let mut trades = vec![
+            trade("b1", OrderSide::Buy, 100, 10.0, day(2024, 1, 2)),
+            trade("b2", OrderSide::Buy, 100, 20.0, day(2024, 2, 1)),
+            trade("b3", OrderSide::Buy, 100, 30.0, day(2024, 3, 1)),
+            // FIFO: 50 out of b1
+            trade("s1", OrderSide::Sell, 50, 25.0, day(2024, 4, 1)),
+        ];
+        let lots = open_lots(&trades, &[], &HashMap::new()).remove("AAPL").unwrap();
+        assert_eq!(lots.iter().map(|l| (l.id.as_str(), l.quantity)).collect::<Vec<_>>(), vec![("b1", 50), ("b2", 100), ("b3", 100)]);
+        assert!((lots[0].cost_basis - 500.5).abs() < 1e-9); // $10.01 a share with commission
+        assert!((lots[0].gain_if_closed(25.0) - 749.5).abs() < 1e-9);
+
+        // One order closing 80 of b3 and 20 of b2 fills in two trades
+        let selections = HashMap::from([(
+            "order-s2".to_string(),
+            TaxMethod::SpecId(vec![("b3".to_string(), 80), ("b2".to_string(), 20)]),
+        )]);
+        let mut first_fill = trade("s2a", OrderSide::Sell, 60, 25.0, day(2024, 5, 1));
+        let mut second_fill = trade("s2b", OrderSide::Sell, 40, 25.0, day(2024, 5, 1));
+        first_fill.order_id = "order-s2".to_string();
+        second_fill.order_id = "order-s2".to_string();
+        trades.extend([first_fill, second_fill]);
+        let lots = open_lots(&trades, &[], &selections).remove("AAPL").unwrap();
+        assert_eq!(lots.iter().map(|l| (l.id.as_str(), l.quantity)).collect::<Vec<_>>(), vec![("b1", 50), ("b2", 80), ("b3", 20)]);
+
+        // A 2-for-1 split doubles the shares and keeps the basis
+        let split = StockSplit { symbol: "AAPL".to_string(), execution_date: day(2024, 6, 3), split_from: 1.0, split_to: 2.0 };
+        let split_lots = open_lots(&trades, &[split], &selections).remove("AAPL").unwrap();
+        assert_eq!(split_lots[0].quantity, 100);
+        assert!((split_lots[0].cost_basis - lots[0].cost_basis).abs() < 1e-9);
+
+        assert!(validate_lot_selections(&lots, &[("b2".into(), 80), ("b3".into(), 20)], 150).is_ok());
+        assert!(validate_lot_selections(&lots, &[("b9".into(), 1)], 150).unwrap_err().contains("No open lot"));
+        assert!(validate_lot_selections(&lots, &[("b3".into(), 21)], 150).is_err());
+        assert!(validate_lot_selections(&lots, &[("b3".into(), 10), ("b3".into(), 10)], 150).is_err());
+        assert!(validate_lot_selections(&lots, &[("b2".into(), 80), ("b3".into(), 20)], 90).unwrap_err().contains("holds 90"));
+    }
+
+    #[test]
+    fn test_minimize_gain_takes_highest_cost_lots() {
+        let selections = optimize_lots(&lots_held(), 120, TaxOptimizationGoal::MinimizeGain, day(2024, 6, 3)).unwrap();
+        assert_eq!(selections, vec![("new-dear".to_string(), 100), ("old-dear".to_string(), 20)]);
+    }
+
+    #[test]
+    fn test_maximize_gain_takes_lowest_cost_lots() {
+        let selections = optimize_lots(&lots_held(), 150, TaxOptimizationGoal::MaximizeGain, day(2024, 6, 3)).unwrap();
+        assert_eq!(selections, vec![("old-cheap".to_string(), 100), ("new-cheap".to_string(), 50)]);
+    }
+
+    #[test]
+    fn test_maximize_long_term_takes_year_old_lots_first() {
+        let lots = lots_held();
+        let as_of = day(2024, 6, 3);
+        let selections = optimize_lots(&lots, 200, TaxOptimizationGoal::MaximizeLongTerm, as_of).unwrap();
+        assert_eq!(
+            selections,
+            vec![("old-dear".to_string(), 50), ("old-cheap".to_string(), 100), ("new-dear".to_string(), 50)]
+        );
+        assert!(lots.iter().filter(|l| l.id.starts_with("old")).all(|l| l.is_long_term(as_of)));
+        assert!(optimize_lots(&lots, 351, TaxOptimizationGoal::MaximizeLongTerm, as_of).is_err());
+        assert!(optimize_lots(&lots, 0, TaxOptimizationGoal::MinimizeGain, as_of).is_err());
+    }
+}
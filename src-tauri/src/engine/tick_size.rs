@@ -0,0 +1,181 @@
+// src-tauri/src/engine/tick_size.rs
+// Minimum price increments. Equities trade in pennies from $1.00 and in hundredths of a
+// cent below it; options in nickels below $3.00 and dimes from it. Order prices off the
+// tick are rejected, or rounded toward the passive side when the request asks for it.
+
+use super::types::{InstrumentType, OrderRequest, OrderSide, OrderType};
+use serde::{Deserialize, Serialize};
+
+const SUB_DOLLAR: f64 = 1.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TickSizeRules {
+    pub equity: f64,            // $1.00 and up
+    pub sub_dollar_equity: f64, // Below $1.00
+    pub option_threshold: f64,  // Premium where options move to the larger increment
+    pub option_below: f64,
+    pub option_above: f64,
+}
+
+impl Default for TickSizeRules {
+    fn default() -> Self {
+        Self { equity: 0.01, sub_dollar_equity: 0.0001, option_threshold: 3.0, option_below: 0.05, option_above: 0.10 }
+    }
+}
+
+impl TickSizeRules {
+    pub fn validate(&self) -> Result<(), String> {
+        let ticks = [self.equity, self.sub_dollar_equity, self.option_below, self.option_above];
+        if ticks.iter().any(|tick| !tick.is_finite() || *tick <= 0.0) {
+            return Err("Tick sizes must be positive".to_string());
+        }
+        if !self.option_threshold.is_finite() || self.option_threshold <= 0.0 {
+            return Err("The option tick threshold must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    /// The increment `price` trades in
+    pub fn tick_size(&self, instrument_type: &InstrumentType, price: f64) -> f64 {
+        match instrument_type {
+            InstrumentType::Stock if price < SUB_DOLLAR => self.sub_dollar_equity,
+            InstrumentType::Stock => self.equity,
+            InstrumentType::Option if price < self.option_threshold => self.option_below,
+            InstrumentType::Option => self.option_above,
+        }
+    }
+
+    /// The valid prices at or below and at or above `price`; both are `price` on the tick
+    pub fn nearest_ticks(&self, instrument_type: &InstrumentType, price: f64) -> (f64, f64) {
+        let tick = self.tick_size(instrument_type, price);
+        let steps = price / tick;
+        let nearest = steps.round();
+        if (steps - nearest).abs() < 1e-6 {
+            let on_tick = round_to_tick(nearest, tick);
+            return (on_tick, on_tick);
+        }
+        (round_to_tick(steps.floor(), tick), round_to_tick(steps.ceil(), tick))
+    }
+
+    /// `price` on the nearest tick, for fill prices
+    pub fn snap(&self, instrument_type: &InstrumentType, price: f64) -> f64 {
+        let (below, above) = self.nearest_ticks(instrument_type, price);
+        if price - below <= above - price { below } else { above }
+    }
+
+    /// Check the request's limit and stop prices are on the tick. With `auto_round` they
+    /// are moved to the passive side instead: limits away from the market (buys down,
+    /// sells up) and stops further from triggering (buy stops up, sell stops down).
+    pub fn apply(&self, request: &mut OrderRequest) -> Result<(), String> {
+        let instrument_type = request.instrument_type.clone();
+        let buy = request.side == OrderSide::Buy;
+        let limit = matches!(request.order_type, OrderType::Limit | OrderType::StopLimit).then_some(request.price).flatten();
+        let stop = matches!(request.order_type, OrderType::Stop | OrderType::StopLimit).then_some(request.stop_price).flatten();
+
+        for (label, price, round_up) in [("Limit", limit, !buy), ("Stop", stop, buy)] {
+            let Some(price) = price else {
+                continue;
+            };
+            let (below, above) = self.nearest_ticks(&instrument_type, price);
+            if below == above {
+                continue;
+            }
+            if !request.auto_round {
+                let tick = self.tick_size(&instrument_type, price);
+                let places = decimals(tick).max(2);
+                return Err(format!(
+                    "{} price {} for {} is off the ${:.*} tick; the nearest valid prices are {:.*} and {:.*} (or set auto_round)",
+                    label, price, request.symbol, places, tick, places, below, places, above
+                ));
+            }
+            let rounded = if round_up { above } else { below };
+            if label == "Limit" {
+                request.price = Some(rounded);
+            } else {
+                request.stop_price = Some(rounded);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `steps` ticks as a price, without the float noise of the multiplication
+fn round_to_tick(steps: f64, tick: f64) -> f64 {
+    (steps * tick * 1e8).round() / 1e8
+}
+
+/// Decimal places needed to show prices on `tick`
+fn decimals(tick: f64) -> usize {
+    (0..8).find(|d| ((tick * 10f64.powi(*d as i32)).round() - tick * 10f64.powi(*d as i32)).abs() < 1e-9).unwrap_or(8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::types::{TimeInForce, VenueType};
+
+    fn request(instrument_type: InstrumentType, side: OrderSide, order_type: OrderType, price: Option<f64>, stop_price: Option<f64>) -> OrderRequest {
+        OrderRequest {
+            symbol: "ABC".to_string(),
+            side,
+            order_type,
+            quantity: 1,
+            price,
+            stop_price,
+            time_in_force: TimeInForce::Day,
+            client_order_id: None,
+            instrument_type,
+            option_details: None,
+            preferred_venue: VenueType::default(),
+            expire_at: None,
+            auto_round: false,
+            override_token: None,
+            allocation: None,
+            residual_policy: None,
+        }
+    }
+
+    #[test]
+    fn test_sub_dollar_and_option_increment_boundaries() {
+        let rules = TickSizeRules::default();
+        let stock = InstrumentType::Stock;
+        let option = InstrumentType::Option;
+
+        assert_eq!(rules.tick_size(&stock, 0.9999), 0.0001);
+        assert_eq!(rules.tick_size(&stock, 1.0), 0.01);
+        assert_eq!(rules.nearest_ticks(&stock, 0.99995), (0.9999, 1.0));
+        assert_eq!(rules.nearest_ticks(&stock, 0.1234), (0.1234, 0.1234));
+        assert_eq!(rules.nearest_ticks(&stock, 1.005), (1.0, 1.01));
+        assert_eq!(rules.nearest_ticks(&stock, 150.123456), (150.12, 150.13));
+
+        assert_eq!(rules.tick_size(&option, 2.95), 0.05);
+        assert_eq!(rules.tick_size(&option, 3.0), 0.10);
+        assert_eq!(rules.nearest_ticks(&option, 2.97), (2.95, 3.0));
+        assert_eq!(rules.nearest_ticks(&option, 3.05), (3.0, 3.1));
+        assert_eq!(rules.nearest_ticks(&option, 2.85), (2.85, 2.85));
+        assert_eq!(rules.snap(&option, 3.04), 3.0);
+        assert_eq!(rules.snap(&stock, 150.126), 150.13);
+
+        // Off-tick prices are rejected with the valid ones either side
+        let mut limit = request(stock.clone(), OrderSide::Buy, OrderType::Limit, Some(150.123456), None);
+        let error = rules.apply(&mut limit).unwrap_err();
+        assert!(error.contains("150.12 and 150.13"), "{}", error);
+        let mut on_tick = request(option.clone(), OrderSide::Sell, OrderType::Limit, Some(3.2), None);
+        assert!(rules.apply(&mut on_tick).is_ok());
+        let mut nickel_above_three = request(option.clone(), OrderSide::Sell, OrderType::Limit, Some(3.05), None);
+        assert!(rules.apply(&mut nickel_above_three).unwrap_err().contains("3.00 and 3.10"));
+
+        // auto_round moves limits and stops to the passive side
+        let mut buy = request(stock.clone(), OrderSide::Buy, OrderType::StopLimit, Some(0.50005), Some(0.49995));
+        buy.auto_round = true;
+        rules.apply(&mut buy).unwrap();
+        assert_eq!((buy.price, buy.stop_price), (Some(0.5), Some(0.5)));
+        let mut sell = request(option, OrderSide::Sell, OrderType::StopLimit, Some(2.97), Some(3.05));
+        sell.auto_round = true;
+        rules.apply(&mut sell).unwrap();
+        assert_eq!((sell.price, sell.stop_price), (Some(3.0), Some(3.0)));
+
+        assert!(TickSizeRules { equity: 0.0, ..TickSizeRules::default() }.validate().is_err());
+    }
+}
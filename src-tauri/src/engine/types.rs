@@ -1,8 +1,14 @@
 // src-tauri/src/engine/types.rs
 // Trading engine types for paper broker
 
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::market_data::dates;
+use super::metrics::DrawdownBasis;
+use super::tick_size::TickSizeRules;
+use super::equity_history::EquityRetention;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum OrderType {
@@ -30,21 +36,64 @@ pub enum OptionType {
     Put,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(try_from = "OptionDetailsFields")]
 pub struct OptionDetails {
     pub underlying: String,
     pub option_type: OptionType,
     pub strike: f64,
-    pub expiry: String,  // MM/DD/YYYY format
+    #[serde(rename = "expiry_date")]
+    pub expiry: NaiveDate,
     pub multiplier: i64, // Usually 100 for equity options
 }
 
+/// OptionDetails as read. Older state and UI requests carry `expiry` as MM/DD/YYYY
+/// instead of `expiry_date`; presented responses carry both.
+#[derive(Deserialize)]
+struct OptionDetailsFields {
+    underlying: String,
+    option_type: OptionType,
+    strike: f64,
+    #[serde(default)]
+    expiry_date: Option<NaiveDate>,
+    #[serde(default)]
+    expiry: Option<String>,
+    multiplier: i64,
+}
+
+impl TryFrom<OptionDetailsFields> for OptionDetails {
+    type Error = String;
+
+    fn try_from(fields: OptionDetailsFields) -> Result<Self, String> {
+        let expiry = match (fields.expiry_date, fields.expiry) {
+            (Some(date), _) => date,
+            (None, Some(legacy)) => dates::parse(&legacy)?,
+            (None, None) => return Err("Option details need an expiry_date".to_string()),
+        };
+        Ok(Self {
+            underlying: fields.underlying,
+            option_type: fields.option_type,
+            strike: fields.strike,
+            expiry,
+            multiplier: fields.multiplier,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum TimeInForce {
     Day,      // Good for day
     GTC,      // Good till canceled
     IOC,      // Immediate or cancel
     FOK,      // Fill or kill
+    OnOpen,   // Opening auction: first regular-session print
+    OnClose,  // Closing auction: last regular-session print
+}
+
+impl TimeInForce {
+    pub fn is_auction(&self) -> bool {
+        matches!(self, TimeInForce::OnOpen | TimeInForce::OnClose)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -57,6 +106,130 @@ pub enum OrderStatus {
     Expired,
 }
 
+impl OrderStatus {
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, OrderStatus::Filled | OrderStatus::Canceled | OrderStatus::Rejected | OrderStatus::Expired)
+    }
+
+    /// Working orders fill, partially fill (again), or end canceled, rejected or expired;
+    /// nothing goes back to pending and terminal statuses never change
+    pub fn can_transition_to(&self, to: &OrderStatus) -> bool {
+        !self.is_terminal() && *to != OrderStatus::Pending
+    }
+}
+
+/// Why an order refused a status change or fill
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderStateError {
+    Terminal { order_id: String, status: OrderStatus, to: OrderStatus },
+    IllegalTransition { order_id: String, from: OrderStatus, to: OrderStatus },
+    Quantity { order_id: String, message: String },
+}
+
+impl std::fmt::Display for OrderStateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderStateError::Terminal { order_id, status, to } => {
+                write!(f, "Order {} is {:?} and cannot become {:?}", order_id, status, to)
+            }
+            OrderStateError::IllegalTransition { order_id, from, to } => {
+                write!(f, "Order {} cannot go from {:?} to {:?}", order_id, from, to)
+            }
+            OrderStateError::Quantity { order_id, message } => write!(f, "Order {}: {}", order_id, message),
+        }
+    }
+}
+
+impl From<OrderStateError> for String {
+    fn from(e: OrderStateError) -> Self {
+        e.to_string()
+    }
+}
+
+/// A restored order whose status and quantities do not add up
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderStateViolation {
+    pub order_id: String,
+    pub symbol: String,
+    pub status: OrderStatus,
+    pub message: String,
+}
+
+/// Where a stock order is sent; Smart picks the venue with the best estimated price
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum VenueType {
+    #[default]
+    Smart,
+    NYSE,
+    NASDAQ,
+    BATS,
+    IEX,
+    OTC,
+}
+
+impl VenueType {
+    pub const ROUTABLE: [VenueType; 5] = [VenueType::NYSE, VenueType::NASDAQ, VenueType::BATS, VenueType::IEX, VenueType::OTC];
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VenueCharacteristics {
+    pub name: String,
+    pub typical_spread_bps: f64,   // Quoted spread on the venue; wider than the NBBO costs half the difference
+    pub fill_rate: f64,            // Share of orders filled in full; breaks price ties when routing
+    pub avg_latency_ms: u64,
+    pub supports_odd_lots: bool,
+    #[serde(default)]
+    pub price_improvement_bps: f64, // Midpoint liquidity that fills inside the touch
+}
+
+impl VenueCharacteristics {
+    fn new(name: &str, typical_spread_bps: f64, fill_rate: f64, avg_latency_ms: u64, supports_odd_lots: bool, price_improvement_bps: f64) -> Self {
+        Self {
+            name: name.to_string(),
+            typical_spread_bps,
+            fill_rate,
+            avg_latency_ms,
+            supports_odd_lots,
+            price_improvement_bps,
+        }
+    }
+
+    /// Extra cost in basis points over the touch (negative for price improvement)
+    /// when the NBBO spread is `nbbo_spread_bps` wide
+    pub fn cost_bps(&self, nbbo_spread_bps: f64) -> f64 {
+        (self.typical_spread_bps - nbbo_spread_bps).max(0.0) / 2.0 - self.price_improvement_bps
+    }
+}
+
+pub fn default_venue_characteristics() -> HashMap<VenueType, VenueCharacteristics> {
+    HashMap::from([
+        (VenueType::NYSE, VenueCharacteristics::new("NYSE", 2.0, 0.97, 4, true, 0.0)),
+        (VenueType::NASDAQ, VenueCharacteristics::new("NASDAQ", 2.0, 0.97, 3, true, 0.0)),
+        (VenueType::BATS, VenueCharacteristics::new("BATS", 2.5, 0.95, 2, true, 0.0)),
+        (VenueType::IEX, VenueCharacteristics::new("IEX", 2.5, 0.90, 10, true, 0.5)), // 350us speed bump plus routing
+        (VenueType::OTC, VenueCharacteristics::new("OTC", 40.0, 0.80, 25, false, 0.0)),
+    ])
+}
+
+/// Routing decision, emitted as `order_routed`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OrderRouting {
+    pub order_id: String,
+    pub venue: VenueType,
+    pub estimated_fill_latency_ms: u64,
+}
+
+/// Routed orders and fills per venue
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct VenueStats {
+    pub orders_routed: u32,
+    pub fills: u32,
+    pub shares_filled: i64,
+    pub fill_rate: f64,             // Filled shares over routed shares
+    pub avg_fill_latency_ms: f64,
+    pub avg_price_improvement_bps: f64, // Against the arrival price; negative is slippage
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderRequest {
     pub symbol: String,
@@ -69,6 +242,87 @@ pub struct OrderRequest {
     pub client_order_id: Option<String>,
     pub instrument_type: InstrumentType,
     pub option_details: Option<OptionDetails>,
+    #[serde(default)]
+    pub preferred_venue: VenueType, // Stocks only
+    #[serde(default)]
+    pub expire_at: Option<i64>,     // Epoch seconds; the order expires then whatever its time in force
+    #[serde(default)]
+    pub auto_round: bool,           // Round off-tick prices to the passive side instead of rejecting
+    #[serde(default)]
+    pub override_token: Option<String>, // From a rejection on soft risk limits, to place the same order anyway
+    #[serde(default)]
+    pub allocation: Option<String>,     // Sub-account to book the order in; otherwise its client_order_id prefix decides
+    #[serde(default)]
+    pub residual_policy: Option<ResidualPolicy>, // Overrides BrokerConfig.residual_policy for this order
+}
+
+/// What happens to the unfilled rest of a partially filled order. A market residual is
+/// tried again on each quote until it fills, and canceled once it has used
+/// `max_attempts` or `timeout_seconds` has passed since the first fill. A limit
+/// residual keeps resting; with `escalate_after_minutes` it goes to market once it has
+/// rested that long.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResidualPolicy {
+    #[serde(default = "default_residual_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_residual_timeout_seconds")]
+    pub timeout_seconds: Option<u64>,
+    #[serde(default)]
+    pub escalate_after_minutes: Option<u32>,
+}
+
+impl Default for ResidualPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_residual_max_attempts(),
+            timeout_seconds: default_residual_timeout_seconds(),
+            escalate_after_minutes: None,
+        }
+    }
+}
+
+/// Bounds on what a single order may ask for, well past any real order but finite, so
+/// the cost, commission and position math cannot overflow into infinity
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct OrderLimits {
+    pub max_quantity: i64, // Shares or contracts
+    pub max_price: f64,    // Per share or contract, limit and stop alike
+    pub max_notional: f64, // Quantity times the order's (or its estimated) price
+}
+
+impl Default for OrderLimits {
+    fn default() -> Self {
+        Self { max_quantity: 100_000_000, max_price: 10_000_000.0, max_notional: 1e12 }
+    }
+}
+
+impl OrderLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_quantity <= 0 {
+            return Err("max_quantity must be positive".to_string());
+        }
+        if !(self.max_price.is_finite() && self.max_price > 0.0) || !(self.max_notional.is_finite() && self.max_notional > 0.0) {
+            return Err("max_price and max_notional must be positive and finite".to_string());
+        }
+        Ok(())
+    }
+
+    /// Err when `quantity` at `price` is past max_notional
+    pub fn check_notional(&self, quantity: i64, price: f64) -> Result<(), String> {
+        let notional = quantity as f64 * price;
+        if !notional.is_finite() || notional.abs() > self.max_notional {
+            return Err(format!("Order value {:.2} exceeds the {:.0} limit", notional, self.max_notional));
+        }
+        Ok(())
+    }
+}
+
+fn default_residual_max_attempts() -> u32 {
+    10
+}
+
+fn default_residual_timeout_seconds() -> Option<u64> {
+    Some(300)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,6 +344,80 @@ pub struct Order {
     pub fills: Vec<Fill>,
     pub instrument_type: InstrumentType,
     pub option_details: Option<OptionDetails>,
+    #[serde(default)]
+    pub pending_reason: Option<String>, // Why a fillable order is still waiting (e.g. quote stale)
+    #[serde(default)]
+    pub tag: Option<String>,            // Origin of the order, e.g. "derisk"; copied to its trades
+    #[serde(default)]
+    pub evaluation_id: Option<String>,  // Strategy evaluation that placed the order; copied to its trades
+    #[serde(default)]
+    pub estimated_queue_ahead: Option<i64>, // Shares ahead of a limit order that joined the touch
+    #[serde(default)]
+    pub auction_queued_at: Option<i64>, // Broker time an on-open/on-close order was queued
+    #[serde(default)]
+    pub arrival_price: Option<f64>,     // Mid quote when the order was placed
+    #[serde(default)]
+    pub twap_of_fills: f64,             // Fill price weighted by quantity and time since creation
+    #[serde(default)]
+    pub implementation_shortfall: Option<f64>, // Per share against the arrival price; positive is a cost
+    #[serde(default)]
+    pub venue: Option<VenueType>,       // Venue the order was routed to; None for options
+    #[serde(default)]
+    pub expire_at: Option<i64>,         // Good-till-date expiry, epoch seconds
+    #[serde(default)]
+    pub events: Vec<OrderEvent>,        // Status changes and residual attempts after placement, oldest first
+    #[serde(default)]
+    pub estimated_cost: Option<OrderCostEstimate>, // At placement, as preview_order reports it
+    #[serde(default)]
+    pub allocation: Option<String>,     // Sub-account the order's fills are booked in
+    #[serde(default)]
+    pub residual_policy: Option<ResidualPolicy>, // The order's own; the broker's when None
+    #[serde(default)]
+    pub residual_since: Option<i64>,    // Broker time of the first partial fill
+    #[serde(default)]
+    pub residual_attempts: u32,         // Times the remainder was tried again
+}
+
+/// A status change in an order's history and why it happened
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OrderEvent {
+    pub timestamp: i64,
+    pub status: OrderStatus,
+    pub reason: String,
+}
+
+/// Filter for `query_orders`; every set field must match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrderQuery {
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default)]
+    pub status: Option<OrderStatus>,
+    #[serde(default)]
+    pub open_only: bool,
+    #[serde(default)]
+    pub expiring_within_hours: Option<f64>, // Open orders with an expire_at this close to now
+    #[serde(default)]
+    pub side: Option<OrderSide>,
+    #[serde(default)]
+    pub order_type: Option<OrderType>,
+    #[serde(default)]
+    pub older_than_seconds: Option<i64>, // Orders placed at least this long ago
+}
+
+impl OrderQuery {
+    pub fn matches(&self, order: &Order, now: i64) -> bool {
+        let expiring = self.expiring_within_hours.is_none_or(|hours| {
+            order.can_fill() && order.expire_at.is_some_and(|at| at > now && ((at - now) as f64) <= hours * 3600.0)
+        });
+        self.symbol.as_ref().is_none_or(|s| &order.symbol == s)
+            && self.status.as_ref().is_none_or(|s| &order.status == s)
+            && self.side.as_ref().is_none_or(|s| &order.side == s)
+            && self.order_type.as_ref().is_none_or(|t| &order.order_type == t)
+            && self.older_than_seconds.is_none_or(|age| now - order.created_at >= age)
+            && (!self.open_only || order.can_fill())
+            && expiring
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,6 +433,40 @@ pub struct Fill {
     pub instrument_type: InstrumentType,
     pub option_details: Option<OptionDetails>,
     pub leg_number: Option<i32>, // For multi-leg strategies
+    #[serde(default)]
+    pub at_open: bool,  // Filled in the opening auction
+    #[serde(default)]
+    pub at_close: bool, // Filled in the closing auction
+    #[serde(default)]
+    pub entry_quality: Option<f64>, // 0..1 position in the intraday range; 1.0 is the best price of the day
+    #[serde(default)]
+    pub venue: Option<VenueType>,
+    #[serde(default)]
+    pub fill_latency_ms: u64,       // Simulated time from routing to execution
+}
+
+/// Journal trades replay through the same position math as live fills
+impl From<&Trade> for Fill {
+    fn from(trade: &Trade) -> Self {
+        Self {
+            id: trade.id.clone(),
+            order_id: trade.order_id.clone(),
+            symbol: trade.symbol.clone(),
+            side: trade.side.clone(),
+            quantity: trade.quantity,
+            price: trade.price,
+            timestamp: trade.timestamp,
+            commission: trade.commission,
+            instrument_type: trade.instrument_type.clone(),
+            option_details: trade.option_details.clone(),
+            leg_number: trade.leg_number,
+            at_open: false,
+            at_close: false,
+            entry_quality: None,
+            venue: None,
+            fill_latency_ms: 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -117,6 +479,57 @@ pub struct Position {
     pub realized_pnl: f64,      // Realized P&L from closed trades
     pub last_price: f64,        // Last known price
     pub updated_at: i64,
+
+    // Derived fields maintained by update_market_data/apply_fill
+    #[serde(default)]
+    pub cost_basis: f64,        // quantity * avg_cost; negative for shorts (proceeds received)
+    #[serde(default)]
+    pub unrealized_pnl_pct: f64, // unrealized_pnl / |cost_basis|, positive when the position is in profit
+    #[serde(default)]
+    pub weight_pct: f64,        // market_value / portfolio equity, set by get_portfolio
+    #[serde(default)]
+    pub prev_close: f64,        // Mark at the last daily roll (blended with same-day entries)
+    #[serde(default)]
+    pub day_pnl: f64,           // quantity * (last_price - prev_close)
+
+    // Exit levels shown on charts, set by PaperBroker::configure_position_exits
+    #[serde(default)]
+    pub stop_loss_price: Option<f64>,
+    #[serde(default)]
+    pub take_profit_price: Option<f64>,
+    #[serde(default)]
+    pub trailing_stop_active: bool,
+    #[serde(default)]
+    pub trailing_stop_price: Option<f64>, // Ratchets with the mark while trailing_stop_active
+
+    // Quote behind the mark; age and class are set by get_portfolio
+    #[serde(default)]
+    pub marked_at: i64,         // Timestamp of the market data last marked with, 0 before any
+    #[serde(default)]
+    pub mark_age_secs: i64,
+    #[serde(default)]
+    pub freshness: FreshnessClass,
+    #[serde(default)]
+    pub badges: Vec<RiskBadge>, // Set by get_portfolio
+}
+
+/// Exit levels configured for an open position
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PositionExit {
+    pub stop_loss_price: Option<f64>,
+    pub take_profit_price: Option<f64>,
+    pub trailing_stop_pct: Option<f64>, // e.g. 0.05 trails 5% behind the best mark
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionDetail {
+    #[serde(flatten)]
+    pub position: Position,
+    pub pending_orders: Vec<Order>,       // Open orders for the symbol
+    pub exit_config: Option<PositionExit>,
+    pub spread_memberships: Vec<String>,  // Spread IDs ("short_leg/long_leg") this position is a leg of
+    pub risk_per_share: f64,              // Entry to stop, positive when the stop is on the losing side; 0 without a stop
+    pub reward_per_share: f64,            // Entry to target, positive when the target is on the winning side; 0 without a target
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -128,6 +541,22 @@ pub struct Portfolio {
     pub day_pnl: f64,          // Day's P&L
     pub total_pnl: f64,        // Total P&L
     pub updated_at: i64,
+    #[serde(default)]
+    pub gross_exposure: f64,   // Sum of |market_value|
+    #[serde(default)]
+    pub net_exposure: f64,     // long_value - short_value
+    #[serde(default)]
+    pub long_value: f64,       // Market value of long positions
+    #[serde(default)]
+    pub short_value: f64,      // Absolute market value of short positions
+    #[serde(default)]
+    pub margin_used: f64,      // Initial requirement of the open positions; 0 in a cash account
+    #[serde(default)]
+    pub maintenance_excess: f64, // Equity over the maintenance requirement
+    #[serde(default)]
+    pub freshness: PortfolioFreshness, // How current the position marks are
+    #[serde(default)]
+    pub badges: BadgeSummary, // Risk badges across the positions
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -136,6 +565,10 @@ pub struct EnhancedPortfolio {
     pub cash: f64,
     pub equity: f64,
     pub buying_power: f64,
+    #[serde(default)]
+    pub margin_used: f64,
+    #[serde(default)]
+    pub maintenance_excess: f64,
     pub positions: HashMap<String, Position>,
     pub day_pnl: f64,
     pub total_pnl: f64,
@@ -148,10 +581,28 @@ pub struct EnhancedPortfolio {
     pub realized_pnl: f64,
     pub portfolio_greeks: PortfolioGreeks,
     pub position_greeks: Vec<PositionGreeks>,
+    #[serde(default)]
+    pub drawdown: DrawdownStatus,
+    #[serde(default)]
+    pub position_stats: HashMap<String, PositionStats>, // Since-entry figures by symbol
+    #[serde(default)]
+    pub freshness: PortfolioFreshness,
+    #[serde(default)]
+    pub badges: BadgeSummary,
+    #[serde(default)]
+    pub state_version: u64, // The broker's portfolio version this was read at
 }
 
 // Re-export from mtm module for convenience
 use super::mtm::{PortfolioGreeks, PositionGreeks};
+use super::drawdown::DrawdownStatus;
+use super::entry_stats::PositionStats;
+use super::margin::{AccountType, MarginRates};
+use super::freshness::{FreshnessClass, FreshnessConfig, PortfolioFreshness};
+use super::badges::{BadgeConfig, BadgeSummary, RiskBadge};
+use super::undo::DEFAULT_UNDO_DEPTH;
+use super::portfolio_view::DEFAULT_PORTFOLIO_PAGE_SIZE;
+use super::rates::RateCurve;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
@@ -168,6 +619,21 @@ pub struct Trade {
     pub option_details: Option<OptionDetails>,
     pub leg_number: Option<i32>,
     pub assignment_id: Option<String>, // For option assignments
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub evaluation_id: Option<String>, // SignalEvaluation whose order this filled
+}
+
+/// Per-symbol P&L across the trade journal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolPnl {
+    pub symbol: String,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub total_pnl: f64,
+    pub trade_count: u32,
+    pub avg_entry_quality: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -182,7 +648,19 @@ pub struct MarketData {
     pub timestamp: i64,
 }
 
+impl MarketData {
+    /// Quote timestamp in seconds (streamed ticks arrive in milliseconds)
+    pub fn timestamp_secs(&self) -> i64 {
+        if self.timestamp > 10_000_000_000 {
+            self.timestamp / 1000
+        } else {
+            self.timestamp
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)] // Settings missing from older save files fall back to defaults
 pub struct BrokerConfig {
     // Stock commissions
     pub commission_per_share: f64,
@@ -206,6 +684,96 @@ pub struct BrokerConfig {
     // Options expiration rules
     pub auto_close_dte_threshold: i32,  // Auto-close options at this DTE
     pub itm_assignment_threshold: f64,  // ITM threshold for assignment (e.g., 0.01 = $0.01)
+
+    // Stale quote guard
+    #[serde(default = "default_max_quote_age_seconds")]
+    pub max_quote_age_seconds: i64,     // Quotes older than this never produce fills
+    #[serde(default = "default_true")]
+    pub rest_limit_orders_on_stale_quote: bool, // Accept limit orders while the quote is stale
+
+    // Queue position model
+    #[serde(default)]
+    pub queue_position_model: bool, // Limit orders joining the bid/ask wait behind the displayed size
+
+    // Stock commission schedule; options always pay per contract
+    #[serde(default)]
+    pub commission_schedule: CommissionSchedule,
+
+    // Auctions
+    #[serde(default)]
+    pub market_orders_on_open_when_closed: bool, // Market orders placed while closed join the opening auction
+
+    // Short selling
+    #[serde(default)]
+    pub allow_short_selling: bool, // Stock sells beyond the shares held open a short position
+
+    // Order routing
+    #[serde(default = "default_venue_characteristics")]
+    pub venue_characteristics: HashMap<VenueType, VenueCharacteristics>,
+
+    // Order-to-fill latency; fills priced at routing book once it has elapsed
+    #[serde(default)]
+    pub simulated_latency_ms: u64,
+    #[serde(default)]
+    pub latency_jitter_ms: u64, // Up to this much more, drawn per fill
+
+    // Minimum price increments for order and fill prices
+    #[serde(default)]
+    pub tick_sizes: TickSizeRules,
+
+    // Sampled equity history: cadence and how long each resolution is kept
+    #[serde(default)]
+    pub equity_history: EquityRetention,
+
+    // Buying power: cash only, or Reg-T margin at these rates
+    #[serde(default)]
+    pub account_type: AccountType,
+    #[serde(default)]
+    pub margin_rates: MarginRates,
+
+    // Mark freshness classes; the regular session's stale line is max_quote_age_seconds
+    #[serde(default)]
+    pub freshness: FreshnessConfig,
+
+    // Position risk badge thresholds
+    #[serde(default)]
+    pub badges: BadgeConfig,
+
+    // Manual actions kept for undo_last_action
+    #[serde(default = "default_undo_depth")]
+    pub undo_depth: usize,
+
+    // Positions per page of get_portfolio_view once there are more
+    #[serde(default = "default_portfolio_page_size")]
+    pub portfolio_page_size: usize,
+
+    // Risk-free rate by tenor for option Greeks and theoretical prices
+    #[serde(default)]
+    pub rate_curve: RateCurve,
+
+    // Unfilled remainders of partially filled orders that do not set their own policy
+    #[serde(default)]
+    pub residual_policy: ResidualPolicy,
+
+    // Largest quantity, price and value a single order may ask for
+    #[serde(default)]
+    pub order_limits: OrderLimits,
+}
+
+fn default_undo_depth() -> usize {
+    DEFAULT_UNDO_DEPTH
+}
+
+fn default_portfolio_page_size() -> usize {
+    DEFAULT_PORTFOLIO_PAGE_SIZE
+}
+
+fn default_max_quote_age_seconds() -> i64 {
+    120
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl Default for BrokerConfig {
@@ -233,10 +801,166 @@ impl Default for BrokerConfig {
             // Options expiration rules
             auto_close_dte_threshold: 0,    // Auto-close on expiry day
             itm_assignment_threshold: 0.01, // $0.01 ITM triggers assignment
+
+            // Stale quote guard
+            max_quote_age_seconds: default_max_quote_age_seconds(), // 2 minutes
+            rest_limit_orders_on_stale_quote: true,
+
+            // Queue position model
+            queue_position_model: false,
+
+            commission_schedule: CommissionSchedule::PerShare,
+
+            // Auctions
+            market_orders_on_open_when_closed: false,
+
+            allow_short_selling: false,
+
+            venue_characteristics: default_venue_characteristics(),
+
+            // Latency: fills book immediately
+            simulated_latency_ms: 0,
+            latency_jitter_ms: 0,
+
+            tick_sizes: TickSizeRules::default(),
+
+            equity_history: EquityRetention::default(),
+
+            account_type: AccountType::Cash,
+            margin_rates: MarginRates::default(),
+
+            freshness: FreshnessConfig::default(),
+
+            badges: BadgeConfig::default(),
+
+            undo_depth: DEFAULT_UNDO_DEPTH,
+
+            portfolio_page_size: DEFAULT_PORTFOLIO_PAGE_SIZE,
+
+            rate_curve: RateCurve::default(),
+
+            residual_policy: ResidualPolicy::default(),
+
+            order_limits: OrderLimits::default(),
+        }
+    }
+}
+
+pub const FLAT_COMMISSION_PER_TRADE: f64 = 4.95;
+pub const TIERED_MIN_COMMISSION: f64 = 0.35;
+pub const TIERED_MAX_COMMISSION_PCT: f64 = 0.01;  // Of trade value
+pub const ZERO_COMMISSION_COST_BPS: f64 = 1.0;    // Price improvement given up to order flow
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum CommissionSchedule {
+    #[default]
+    PerShare, // commission_per_share + commission_per_trade, clamped to min/max
+    Flat,     // FLAT_COMMISSION_PER_TRADE per order
+    Tiered,   // Per-share rate falling with shares traded over the last 30 days
+    Zero,     // No commission, paid for through execution quality
+}
+
+impl CommissionSchedule {
+    pub const ALL: [CommissionSchedule; 4] = [Self::PerShare, Self::Flat, Self::Tiered, Self::Zero];
+
+    /// Cost of a stock trade under this schedule. `trailing_shares` is the share volume
+    /// traded over the previous 30 days, which sets the tiered rate.
+    pub fn stock_cost(&self, config: &BrokerConfig, quantity: i64, price: f64, trailing_shares: i64) -> f64 {
+        let shares = quantity as f64;
+        match self {
+            Self::PerShare => (shares * config.commission_per_share + config.commission_per_trade)
+                .max(config.min_commission)
+                .min(config.max_commission),
+            Self::Flat => FLAT_COMMISSION_PER_TRADE,
+            Self::Tiered => {
+                let rate = match trailing_shares {
+                    ..=300_000 => 0.0035,
+                    ..=3_000_000 => 0.002,
+                    _ => 0.0015,
+                };
+                (shares * rate)
+                    .max(TIERED_MIN_COMMISSION)
+                    .min(shares * price * TIERED_MAX_COMMISSION_PCT)
+            }
+            Self::Zero => shares * price * ZERO_COMMISSION_COST_BPS / 10_000.0,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PerShare => "PerShare",
+            Self::Flat => "Flat",
+            Self::Tiered => "Tiered",
+            Self::Zero => "Zero",
         }
     }
 }
 
+/// A trade whose commission was a large share of its value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommissionSuggestion {
+    pub trade_id: String,
+    pub symbol: String,
+    pub timestamp: i64,
+    pub commission: f64,
+    pub trade_value: f64,
+    pub commission_pct: f64, // 0..1
+    pub message: String,
+}
+
+/// Stock commissions over the last 30 days repriced under each schedule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommissionModelComparison {
+    pub current_cost: f64,
+    pub flat_cost: f64,
+    pub tiered_cost: f64,
+    pub zero_cost: f64,
+    pub recommended: String, // CommissionSchedule name
+}
+
+/// What an order is expected to cost at the current quote and fee schedule
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OrderCostEstimate {
+    pub reference_price: f64, // Quote side for market orders, else the limit or stop price
+    pub estimated_price: f64, // After slippage and venue cost; limit prices fill as given
+    pub quantity: i64,
+    pub gross_amount: f64,    // estimated_price * quantity
+    pub commission: f64,
+    pub slippage_cost: f64,   // Dollars paid over (or received under) the reference price
+    pub net_amount: f64,      // Cash change, negative for buys: gross plus commission paid, or less it received
+    pub placeholder_price: bool, // No quote to estimate from; reference_price is a stand-in
+}
+
+/// Cash and the order's position after a simulated full fill at the estimate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostTradeSnapshot {
+    pub cash: f64,
+    pub equity: f64,
+    pub position_quantity: i64,
+    pub position_value: f64,
+    pub position_weight: f64,           // Of post-trade equity
+    pub position_delta: Option<f64>,    // Options only
+    pub portfolio_delta: Option<f64>,   // Options only
+    #[serde(default)]
+    pub buying_power: f64,
+    #[serde(default)]
+    pub margin_used: f64,
+    #[serde(default)]
+    pub maintenance_excess: f64,
+}
+
+/// The result of every check `place_order` runs, without creating the order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderPreview {
+    pub request: OrderRequest,          // As it would be placed: symbol normalized
+    pub time_in_force: TimeInForce,     // After a closed-market order is moved to the open
+    pub venue: Option<VenueType>,
+    pub estimate: OrderCostEstimate,
+    pub buying_power_used_pct: f64,     // 0..1; 0 for sells
+    pub post_trade: PostTradeSnapshot,
+    pub warnings: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradeExecution {
     pub order_id: String,
@@ -251,7 +975,8 @@ pub struct OptionAssignment {
     pub symbol: String,
     pub option_type: OptionType,
     pub strike: f64,
-    pub expiry: String,
+    #[serde(rename = "expiry_date", alias = "expiry", deserialize_with = "dates::lenient")]
+    pub expiry: NaiveDate,
     pub quantity: i64,           // Number of contracts assigned
     pub underlying_quantity: i64, // Shares received/delivered (quantity * multiplier)
     pub assignment_price: f64,   // Strike price
@@ -267,7 +992,8 @@ pub struct OptionExpiration {
     pub symbol: String,
     pub option_type: OptionType,
     pub strike: f64,
-    pub expiry: String,
+    #[serde(rename = "expiry_date", alias = "expiry", deserialize_with = "dates::lenient")]
+    pub expiry: NaiveDate,
     pub quantity: i64,
     pub underlying_price: f64,   // Price at expiration
     pub intrinsic_value: f64,    // Max(0, underlying - strike) for calls
@@ -275,16 +1001,167 @@ pub struct OptionExpiration {
     pub action: ExpirationAction,
 }
 
+/// Result of one expiration run; `assigned` holds the share deliveries from ITM contracts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OptionExpirationReport {
+    pub expired: Vec<OptionExpiration>,
+    pub assigned: Vec<OptionAssignment>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ExpirationAction {
     Expired,      // Expired worthless
     AutoExercised, // Auto-exercised ITM
     AutoClosed,   // Auto-closed before expiry
+    ExercisedByInstruction, // Exercised under an Exercise instruction
+    ExpiredByInstruction,   // Left to expire under a DoNotExercise instruction
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ExerciseAction {
+    Exercise,
+    DoNotExercise,
+}
+
+/// Standing expiry instruction for a long option, used instead of the ITM threshold rule
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExerciseInstruction {
+    pub symbol: String,
+    pub action: ExerciseAction,
+    pub quantity: i64, // Contracts covered; any others follow the threshold rule
+    pub created_at: i64,
+}
+
+/// Cash movement that is not a trade
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AdjustmentKind {
+    Deposit,
+    Withdrawal,
+    Interest,
+    Dividend,
+    Fee,
+    CashInLieu, // Fractional shares left by a split, paid out at the prior close
+}
+
+impl AdjustmentKind {
+    /// +1 when the adjustment adds cash, -1 when it removes it
+    pub fn cash_sign(&self) -> f64 {
+        match self {
+            AdjustmentKind::Deposit
+            | AdjustmentKind::Interest
+            | AdjustmentKind::Dividend
+            | AdjustmentKind::CashInLieu => 1.0,
+            AdjustmentKind::Withdrawal | AdjustmentKind::Fee => -1.0,
+        }
+    }
+
+    /// Deposits and withdrawals move money in or out of the account rather than earning it
+    pub fn is_transfer(&self) -> bool {
+        matches!(self, AdjustmentKind::Deposit | AdjustmentKind::Withdrawal)
+    }
+
+    /// Kind of the adjustment that offsets this one; fees have none
+    pub fn offsetting(&self) -> Option<AdjustmentKind> {
+        match self {
+            AdjustmentKind::Deposit => Some(AdjustmentKind::Withdrawal),
+            AdjustmentKind::Withdrawal => Some(AdjustmentKind::Deposit),
+            AdjustmentKind::Interest | AdjustmentKind::Dividend | AdjustmentKind::CashInLieu => Some(AdjustmentKind::Fee),
+            AdjustmentKind::Fee => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CashAdjustment {
+    pub id: String,
+    pub timestamp: i64,
+    pub kind: AdjustmentKind,
+    pub amount: f64,            // Always positive; the kind gives the direction
+    pub symbol: Option<String>, // Paying security for dividends
+    pub note: Option<String>,
+}
+
+/// Stock split taking effect at the open of `execution_date`; `split_to` new shares
+/// for every `split_from` held (4-for-1 is 1 -> 4, a 1-for-10 reverse split is 10 -> 1)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StockSplit {
+    pub symbol: String,
+    pub execution_date: chrono::NaiveDate,
+    pub split_from: f64,
+    pub split_to: f64,
+}
+
+impl StockSplit {
+    /// New shares per old share
+    pub fn ratio(&self) -> f64 {
+        self.split_to / self.split_from
+    }
+
+    /// Whole post-split shares for `shares` and the fraction left over (signed like `shares`)
+    pub fn shares_after(&self, shares: i64) -> (i64, f64) {
+        let exact = shares as f64 * self.ratio();
+        // Share counts come out of float multiplication, e.g. 70 * 0.1 = 7.000000000000001
+        let whole = ((exact.abs() + 1e-9).floor() * exact.signum()) as i64;
+        (whole, exact - whole as f64)
+    }
+}
+
+/// Journal entry for a split applied to a held position
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CorporateActionAdjustment {
+    pub id: String,
+    pub split: StockSplit,
+    pub timestamp: i64,
+    pub quantity_before: i64,
+    pub quantity_after: i64,
+    pub avg_cost_before: f64,
+    pub avg_cost_after: f64,
+    pub cash_in_lieu: f64,                      // Signed; negative when a short pays for its fraction
+    pub cash_adjustment_id: Option<String>,     // Cash-in-lieu booking, if any
+    pub orders_adjusted: Vec<String>,
+    pub orders_cancelled: Vec<String>,          // Open orders that rounded to zero shares
+}
+
+/// Closing state of one session, recorded when the broker rolls to the next trading date
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DailySummary {
+    pub date: chrono::NaiveDate,
+    pub starting_equity: f64,
+    pub ending_equity: f64,
+    pub cash: f64,
+    pub unrealized_pnl: f64,
+    pub marks: BTreeMap<String, f64>, // Last price of each open position
+    #[serde(default)]
+    pub open_auction_fills: u32,
+    #[serde(default)]
+    pub close_auction_fills: u32,
+    #[serde(default)]
+    pub realized_equity: Option<f64>, // Ending equity with open positions at cost; None before it was recorded
+    #[serde(default)]
+    pub equity_range: Option<(f64, f64)>, // (peak, trough) equity during the session; None before it was recorded
+    #[serde(default)]
+    pub benchmark_day_pnl: Option<f64>, // The shadow benchmark account's, when one is set
+}
+
+impl DailySummary {
+    /// Ending equity on `basis`. Summaries recorded before realized equity was kept
+    /// take it as ending equity less unrealized P&L.
+    pub fn equity_on(&self, basis: DrawdownBasis) -> f64 {
+        match basis {
+            DrawdownBasis::Total => self.ending_equity,
+            DrawdownBasis::Realized => self.realized_equity.unwrap_or(self.ending_equity - self.unrealized_pnl),
+        }
+    }
 }
 
 // Helper functions for order validation
 impl OrderRequest {
     pub fn validate(&self) -> Result<(), String> {
+        self.validate_within(&OrderLimits::default())
+    }
+
+    /// Validate, holding quantity, prices and value to `limits`
+    pub fn validate_within(&self, limits: &OrderLimits) -> Result<(), String> {
         if self.symbol.is_empty() {
             return Err("Symbol cannot be empty".to_string());
         }
@@ -292,6 +1169,21 @@ impl OrderRequest {
         if self.quantity <= 0 {
             return Err("Quantity must be positive".to_string());
         }
+        if self.quantity > limits.max_quantity {
+            return Err(format!("Quantity {} exceeds the {} limit", self.quantity, limits.max_quantity));
+        }
+
+        // NaN would pass the positivity checks below
+        for (name, value) in [("Price", self.price), ("Stop price", self.stop_price)] {
+            match value {
+                Some(v) if !v.is_finite() => return Err(format!("{} must be a finite number", name)),
+                Some(v) if v > limits.max_price => return Err(format!("{} {} exceeds the {} limit", name, v, limits.max_price)),
+                _ => {}
+            }
+        }
+        if let Some(price) = self.price.into_iter().chain(self.stop_price).reduce(f64::max) {
+            limits.check_notional(self.quantity, price)?;
+        }
         
         match self.order_type {
             OrderType::Limit => {
@@ -328,6 +1220,10 @@ impl OrderRequest {
                 // Market orders don't need price validation
             }
         }
+
+        if self.time_in_force.is_auction() && !matches!(self.order_type, OrderType::Market | OrderType::Limit) {
+            return Err("On-open and on-close orders must be market or limit orders".to_string());
+        }
         
         Ok(())
     }
@@ -355,31 +1251,161 @@ impl Order {
             fills: Vec::new(),
             instrument_type: request.instrument_type,
             option_details: request.option_details,
+            pending_reason: None,
+            tag: None,
+            evaluation_id: None,
+            estimated_queue_ahead: None,
+            auction_queued_at: None,
+            arrival_price: None,
+            twap_of_fills: 0.0,
+            implementation_shortfall: None,
+            venue: None,
+            expire_at: request.expire_at,
+            events: Vec::new(),
+            estimated_cost: None,
+            allocation: request.allocation,
+            residual_policy: request.residual_policy,
+            residual_since: None,
+            residual_attempts: 0,
+        }
+    }
+
+    /// Move to `status` if the state machine allows it and the quantities agree with
+    /// it, recording why in the order's history
+    pub fn transition(&mut self, status: OrderStatus, reason: String, timestamp: i64) -> Result<(), OrderStateError> {
+        if self.status.is_terminal() {
+            return Err(OrderStateError::Terminal { order_id: self.id.clone(), status: self.status.clone(), to: status });
         }
+        if !self.status.can_transition_to(&status) {
+            return Err(OrderStateError::IllegalTransition { order_id: self.id.clone(), from: self.status.clone(), to: status });
+        }
+        self.check_quantities(&status)?;
+        self.status = status.clone();
+        self.updated_at = timestamp;
+        self.events.push(OrderEvent { timestamp, status, reason });
+        Ok(())
+    }
+
+    /// Record something that happened to the order without a status change
+    pub fn note(&mut self, reason: String, timestamp: i64) {
+        self.updated_at = timestamp;
+        self.events.push(OrderEvent { timestamp, status: self.status.clone(), reason });
+    }
+
+    /// filled + remaining == quantity, and the fills add up to what was filled as
+    /// `status` says: nothing while pending, some of it partially filled, all of it filled
+    fn check_quantities(&self, status: &OrderStatus) -> Result<(), OrderStateError> {
+        let fail = |message: String| Err(OrderStateError::Quantity { order_id: self.id.clone(), message });
+        if self.quantity <= 0 || self.filled_quantity < 0 || self.remaining_quantity < 0 {
+            return fail(format!(
+                "quantities must not be negative ({} filled, {} remaining of {})",
+                self.filled_quantity, self.remaining_quantity, self.quantity
+            ));
+        }
+        if self.filled_quantity + self.remaining_quantity != self.quantity {
+            return fail(format!(
+                "{} filled and {} remaining do not make up {}",
+                self.filled_quantity, self.remaining_quantity, self.quantity
+            ));
+        }
+        let fill_total: i64 = self.fills.iter().map(|f| f.quantity).sum();
+        if fill_total != self.filled_quantity {
+            return fail(format!("fills add up to {} but {} is filled", fill_total, self.filled_quantity));
+        }
+        let consistent = match status {
+            OrderStatus::Pending => self.filled_quantity == 0,
+            OrderStatus::PartiallyFilled => self.filled_quantity > 0 && self.remaining_quantity > 0,
+            OrderStatus::Filled => self.remaining_quantity == 0,
+            OrderStatus::Canceled | OrderStatus::Rejected | OrderStatus::Expired => true,
+        };
+        if !consistent {
+            return fail(format!("{:?} with {} of {} filled", status, self.filled_quantity, self.quantity));
+        }
+        Ok(())
+    }
+
+    /// Problems with a restored order's status and quantities
+    pub fn validate_state(&self) -> Result<(), OrderStateError> {
+        self.check_quantities(&self.status)
     }
     
     pub fn is_complete(&self) -> bool {
-        matches!(self.status, OrderStatus::Filled | OrderStatus::Canceled | OrderStatus::Rejected | OrderStatus::Expired)
+        self.status.is_terminal()
     }
     
     pub fn can_fill(&self) -> bool {
         matches!(self.status, OrderStatus::Pending | OrderStatus::PartiallyFilled) && self.remaining_quantity > 0
     }
     
-    pub fn add_fill(&mut self, fill: Fill) {
+    /// Book `fill` against the order, which must still be working and have at least the
+    /// fill's quantity left
+    pub fn add_fill(&mut self, fill: Fill) -> Result<(), OrderStateError> {
+        let to = if fill.quantity == self.remaining_quantity { OrderStatus::Filled } else { OrderStatus::PartiallyFilled };
+        if self.status.is_terminal() {
+            return Err(OrderStateError::Terminal { order_id: self.id.clone(), status: self.status.clone(), to });
+        }
+        if fill.quantity <= 0 || fill.quantity > self.remaining_quantity {
+            return Err(OrderStateError::Quantity {
+                order_id: self.id.clone(),
+                message: format!("a fill of {} does not fit the {} remaining", fill.quantity, self.remaining_quantity),
+            });
+        }
+        let reason = format!("{} filled at {:.4}", fill.quantity, fill.price);
+        let timestamp = fill.timestamp;
+        let previous = (self.filled_quantity, self.remaining_quantity, self.fills.len());
         self.filled_quantity += fill.quantity;
-        self.remaining_quantity = self.quantity - self.filled_quantity;
+        self.remaining_quantity -= fill.quantity;
         self.fills.push(fill);
-        self.updated_at = chrono::Utc::now().timestamp();
-        
-        if self.remaining_quantity == 0 {
-            self.status = OrderStatus::Filled;
-        } else if self.filled_quantity > 0 {
-            self.status = OrderStatus::PartiallyFilled;
+        if let Err(e) = self.transition(to, reason, timestamp) {
+            (self.filled_quantity, self.remaining_quantity) = (previous.0, previous.1);
+            self.fills.truncate(previous.2);
+            return Err(e);
+        }
+        self.pending_reason = None;
+        self.update_twap_of_fills();
+        Ok(())
+    }
+
+    /// Weight each fill by quantity and by how far into the order's life it came,
+    /// (fill time - created) / (latest fill - created). Fills at creation carry no
+    /// weight unless every fill did, in which case this is the plain VWAP.
+    fn update_twap_of_fills(&mut self) {
+        let latest = self.fills.iter().map(|f| f.timestamp).max().unwrap_or(self.created_at);
+        let span = (latest - self.created_at) as f64;
+        let (weighted, total_weight) = self.fills.iter().fold((0.0, 0.0), |(weighted, total), fill| {
+            let time_weight = if span > 0.0 { ((fill.timestamp - self.created_at) as f64 / span).max(0.0) } else { 1.0 };
+            let weight = fill.quantity as f64 * time_weight;
+            (weighted + fill.price * weight, total + weight)
+        });
+        if total_weight <= 0.0 {
+            return;
         }
+
+        self.twap_of_fills = weighted / total_weight;
+        self.implementation_shortfall = self.arrival_price.map(|arrival| match self.side {
+            OrderSide::Buy => self.twap_of_fills - arrival,
+            OrderSide::Sell => arrival - self.twap_of_fills,
+        });
     }
 }
 
+/// Execution quality of one order against the quote when it was placed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderShortfall {
+    pub order_id: String,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub status: OrderStatus,
+    pub filled_quantity: i64,
+    pub fill_count: usize,
+    pub limit_price: Option<f64>,
+    pub arrival_price: f64,
+    pub twap_of_fills: f64,
+    pub shortfall_per_share: f64,
+    pub shortfall_cost: f64, // Per-share shortfall times filled quantity and contract multiplier
+    pub created_at: i64,
+}
+
 impl Position {
     pub fn new(symbol: String) -> Self {
         Self {
@@ -391,15 +1417,114 @@ impl Position {
             realized_pnl: 0.0,
             last_price: 0.0,
             updated_at: chrono::Utc::now().timestamp(),
+            cost_basis: 0.0,
+            unrealized_pnl_pct: 0.0,
+            weight_pct: 0.0,
+            prev_close: 0.0,
+            day_pnl: 0.0,
+            stop_loss_price: None,
+            take_profit_price: None,
+            trailing_stop_active: false,
+            trailing_stop_price: None,
+            marked_at: 0,
+            mark_age_secs: 0,
+            freshness: FreshnessClass::default(),
+            badges: Vec::new(),
         }
     }
     
     pub fn update_market_data(&mut self, price: f64) {
         self.last_price = price;
         self.market_value = self.quantity as f64 * price;
-        self.unrealized_pnl = self.market_value - (self.quantity as f64 * self.avg_cost);
+        self.cost_basis = self.quantity as f64 * self.avg_cost;
+        self.unrealized_pnl = self.market_value - self.cost_basis;
+        self.unrealized_pnl_pct = if self.cost_basis != 0.0 {
+            self.unrealized_pnl / self.cost_basis.abs()
+        } else {
+            0.0
+        };
+        self.day_pnl = self.quantity as f64 * (price - self.prev_close);
         self.updated_at = chrono::Utc::now().timestamp();
     }
+
+    /// Copy configured exit levels onto the position; the trailing stop restarts from the current mark
+    pub fn apply_exit_levels(&mut self, exit: &PositionExit) {
+        self.stop_loss_price = exit.stop_loss_price;
+        self.take_profit_price = exit.take_profit_price;
+        self.trailing_stop_active = exit.trailing_stop_pct.is_some();
+        self.trailing_stop_price = None;
+        if let Some(pct) = exit.trailing_stop_pct {
+            self.trail_stop(pct);
+        }
+    }
+
+    /// Move the trailing stop toward the mark, never away from it
+    pub fn trail_stop(&mut self, pct: f64) {
+        if !self.trailing_stop_active || self.last_price <= 0.0 || self.quantity == 0 {
+            return;
+        }
+
+        let candidate = if self.quantity > 0 {
+            self.last_price * (1.0 - pct)
+        } else {
+            self.last_price * (1.0 + pct)
+        };
+        self.trailing_stop_price = Some(match self.trailing_stop_price {
+            Some(current) if self.quantity > 0 => current.max(candidate),
+            Some(current) => current.min(candidate),
+            None => candidate,
+        });
+    }
+
+    /// Capture the current mark as the prior close at the daily roll
+    pub fn roll_day(&mut self) {
+        self.prev_close = self.last_price;
+        self.day_pnl = 0.0;
+    }
+
+    /// Restate in post-split shares and prices. Whole shares are kept; the fraction is
+    /// realized at the prior close and returned for the caller to settle in cash.
+    pub fn apply_split(&mut self, split: &StockSplit) -> f64 {
+        let ratio = split.ratio();
+        let prior_close = self.last_price / ratio;
+        let (quantity_after, fraction) = split.shares_after(self.quantity);
+
+        self.quantity = quantity_after;
+        self.avg_cost /= ratio;
+        self.prev_close /= ratio;
+        self.stop_loss_price = self.stop_loss_price.map(|p| p / ratio);
+        self.take_profit_price = self.take_profit_price.map(|p| p / ratio);
+        self.trailing_stop_price = self.trailing_stop_price.map(|p| p / ratio);
+        self.realized_pnl += fraction * (prior_close - self.avg_cost);
+        self.update_market_data(prior_close);
+        fraction
+    }
+
+    /// Fold in a duplicate position in the same symbol: quantities and realized P&L add,
+    /// average cost and prior close are quantity-weighted, and the fresher mark wins
+    pub fn merge(&mut self, other: Position) {
+        let quantity = self.quantity + other.quantity;
+        let weighted = |a: f64, b: f64| {
+            if quantity == 0 {
+                0.0
+            } else {
+                (self.quantity as f64 * a + other.quantity as f64 * b) / quantity as f64
+            }
+        };
+        self.avg_cost = weighted(self.avg_cost, other.avg_cost);
+        self.prev_close = weighted(self.prev_close, other.prev_close);
+        self.quantity = quantity;
+        self.realized_pnl += other.realized_pnl;
+        if other.updated_at > self.updated_at {
+            self.last_price = other.last_price;
+            self.updated_at = other.updated_at;
+        }
+        self.stop_loss_price = self.stop_loss_price.or(other.stop_loss_price);
+        self.take_profit_price = self.take_profit_price.or(other.take_profit_price);
+        self.trailing_stop_active |= other.trailing_stop_active;
+        self.trailing_stop_price = self.trailing_stop_price.or(other.trailing_stop_price);
+        self.update_market_data(self.last_price);
+    }
     
     pub fn apply_fill(&mut self, fill: &Fill) -> f64 {
         let old_quantity = self.quantity;
@@ -415,11 +1540,14 @@ impl Position {
             // Opening position
             self.quantity = new_quantity;
             self.avg_cost = fill.price;
+            self.prev_close = fill.price;
         } else if (old_quantity > 0 && fill_quantity > 0) || (old_quantity < 0 && fill_quantity < 0) {
             // Adding to position
             let total_cost = (old_quantity as f64 * self.avg_cost) + (fill_quantity as f64 * fill.price);
+            let total_mark = (old_quantity as f64 * self.prev_close) + (fill_quantity as f64 * fill.price);
             self.quantity = new_quantity;
             self.avg_cost = total_cost / new_quantity as f64;
+            self.prev_close = total_mark / new_quantity as f64;
         } else {
             // Reducing or closing position
             let closed_quantity = fill_quantity.abs().min(old_quantity.abs());
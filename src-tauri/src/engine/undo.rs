@@ -0,0 +1,63 @@
+// src-tauri/src/engine/undo.rs
+// Undo of recent manual actions in the paper account. Each manual fill, cash adjustment
+// or exit rule change pushes the action that reverses it; undoing the newest runs that
+// action through the normal path. Trades are reversed at the current market and tagged
+// "undo", so the round trip's P&L shows instead of the action being erased. Strategy,
+// de-risk and hedge orders carry an evaluation id or tag and are never recorded.
+
+use super::types::{AdjustmentKind, CashAdjustment, InstrumentType, OptionDetails, OrderSide, Position, PositionExit, TradeExecution};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_UNDO_DEPTH: usize = 10;
+pub const UNDO_TAG: &str = "undo";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UndoAction {
+    /// Trade back what a manual fill traded, at market
+    Trade {
+        symbol: String,
+        side: OrderSide,
+        quantity: i64,
+        instrument_type: InstrumentType,
+        option_details: Option<OptionDetails>,
+        position_after: i64, // The symbol's position as the fill left it; the undo needs it unchanged
+    },
+    /// Offset a cash adjustment with one in the other direction
+    CashAdjustment {
+        adjustment_id: String,
+        kind: AdjustmentKind, // The offsetting kind
+        amount: f64,
+        symbol: Option<String>,
+    },
+    /// Put a position's exit levels back as they were
+    PositionExits {
+        symbol: String,
+        previous: Option<PositionExit>,
+        applied: PositionExit, // Must still be in place to be undone
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UndoEntry {
+    pub id: String,
+    pub recorded_at: i64,
+    pub description: String, // The action as taken
+    pub undo: String,         // What undoing it does
+    pub action: UndoAction,
+}
+
+/// What undo_last_action did; emitted as "action_undone"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoOutcome {
+    pub undone: UndoEntry,
+    pub execution: Option<TradeExecution>, // The reversing trade
+    pub adjustment: Option<CashAdjustment>, // The offsetting adjustment
+    pub position: Option<Position>,         // With its exit levels restored
+}
+
+/// Push `entry` onto `stack` (newest last), dropping the oldest past `depth`
+pub fn push(stack: &mut Vec<UndoEntry>, entry: UndoEntry, depth: usize) {
+    stack.push(entry);
+    stack.drain(..stack.len().saturating_sub(depth));
+}
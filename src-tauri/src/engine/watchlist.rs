@@ -0,0 +1,316 @@
+// src-tauri/src/engine/watchlist.rs
+// The one managed watchlist: named lists (default "main") of ordered symbols, each with
+// flags saying which parts of the app use it. The stream subscribes stream-flagged
+// symbols, the strategy loop evaluates trade-flagged ones, the scanner defaults to
+// scan-flagged ones and the daily refresh keeps warm_cache-flagged bars current. A
+// symbol on several lists is used when any of its entries sets the flag. Every change
+// is saved and emitted as "watchlist_changed" with the stream's subscribe/unsubscribe
+// delta.
+
+use super::r#loop::StrategyLoopConfig;
+use super::symbols::normalize_symbol;
+use crate::storage::cache::FileCache;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter};
+
+pub const DEFAULT_WATCHLIST: &str = "main";
+const WATCHLISTS_KEY: &str = "watchlists";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SymbolFlags {
+    pub stream: bool,     // Subscribe on the live stream
+    pub trade: bool,      // Evaluated by the strategy loop
+    pub scan: bool,       // Scanned when a scan names no symbols
+    pub warm_cache: bool, // Daily bars refreshed after each close
+}
+
+impl Default for SymbolFlags {
+    fn default() -> Self {
+        Self { stream: true, trade: true, scan: true, warm_cache: true }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolFlag {
+    Stream,
+    Trade,
+    Scan,
+    WarmCache,
+}
+
+impl SymbolFlags {
+    pub fn has(&self, flag: SymbolFlag) -> bool {
+        match flag {
+            SymbolFlag::Stream => self.stream,
+            SymbolFlag::Trade => self.trade,
+            SymbolFlag::Scan => self.scan,
+            SymbolFlag::WarmCache => self.warm_cache,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatchlistEntry {
+    pub symbol: String,
+    pub flags: SymbolFlags,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Watchlist {
+    pub name: String,
+    pub entries: Vec<WatchlistEntry>, // In the order they were added
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Watchlists {
+    pub lists: Vec<Watchlist>, // DEFAULT_WATCHLIST first
+}
+
+impl Default for Watchlists {
+    fn default() -> Self {
+        Self { lists: vec![Watchlist { name: DEFAULT_WATCHLIST.to_string(), entries: Vec::new() }] }
+    }
+}
+
+impl Watchlists {
+    pub fn create(&mut self, name: &str) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Watchlist name cannot be empty".to_string());
+        }
+        if self.lists.iter().any(|l| l.name.eq_ignore_ascii_case(name)) {
+            return Err(format!("A watchlist named '{}' already exists", name));
+        }
+        self.lists.push(Watchlist { name: name.to_string(), entries: Vec::new() });
+        Ok(())
+    }
+
+    fn list_mut(&mut self, name: &str) -> Result<&mut Watchlist, String> {
+        self.lists
+            .iter_mut()
+            .find(|l| l.name == name)
+            .ok_or_else(|| format!("No watchlist named '{}'", name))
+    }
+
+    /// Append `symbol` to `list`; returns the canonical symbol
+    pub fn add(&mut self, list: &str, symbol: &str, flags: SymbolFlags) -> Result<String, String> {
+        let symbol = normalize_symbol(symbol)?;
+        let list = self.list_mut(list)?;
+        if list.entries.iter().any(|e| e.symbol == symbol) {
+            return Err(format!("{} is already on '{}'", symbol, list.name));
+        }
+        list.entries.push(WatchlistEntry { symbol: symbol.clone(), flags });
+        Ok(symbol)
+    }
+
+    pub fn remove(&mut self, list: &str, symbol: &str) -> Result<(), String> {
+        let symbol = normalize_symbol(symbol)?;
+        let list = self.list_mut(list)?;
+        let before = list.entries.len();
+        list.entries.retain(|e| e.symbol != symbol);
+        if list.entries.len() == before {
+            return Err(format!("{} is not on '{}'", symbol, list.name));
+        }
+        Ok(())
+    }
+
+    pub fn set_flags(&mut self, list: &str, symbol: &str, flags: SymbolFlags) -> Result<(), String> {
+        let symbol = normalize_symbol(symbol)?;
+        let list = self.list_mut(list)?;
+        let name = list.name.clone();
+        let entry = list
+            .entries
+            .iter_mut()
+            .find(|e| e.symbol == symbol)
+            .ok_or_else(|| format!("{} is not on '{}'", symbol, name))?;
+        entry.flags = flags;
+        Ok(())
+    }
+
+    /// Whether any list's entry for `symbol` sets `flag`
+    pub fn is_flagged(&self, symbol: &str, flag: SymbolFlag) -> bool {
+        self.lists.iter().flat_map(|l| &l.entries).any(|e| e.symbol == symbol && e.flags.has(flag))
+    }
+
+    /// Symbols with `flag` set on any list, sorted
+    pub fn symbols_with(&self, flag: SymbolFlag) -> Vec<String> {
+        let symbols: BTreeSet<&String> =
+            self.lists.iter().flat_map(|l| &l.entries).filter(|e| e.flags.has(flag)).map(|e| &e.symbol).collect();
+        symbols.into_iter().cloned().collect()
+    }
+
+    /// Add the loop config's symbols that are on no list to DEFAULT_WATCHLIST, as the
+    /// watchlist used to be read from the config. Pair legs are added without the trade
+    /// flag, since the pair trades them. Returns the symbols added.
+    pub fn import_loop_config(&mut self, config: &StrategyLoopConfig) -> Result<Vec<String>, String> {
+        let mut rule_symbols: Vec<&String> = config.symbol_rules.keys().collect();
+        rule_symbols.sort();
+        let legs = config.pairs.iter().flat_map(|p| [&p.first, &p.second]);
+        let candidates: Vec<(&String, bool)> = rule_symbols.into_iter().map(|s| (s, true)).chain(legs.map(|s| (s, false))).collect();
+
+        let mut added = Vec::new();
+        for (symbol, trade) in candidates {
+            let symbol = normalize_symbol(symbol)?;
+            if self.lists.iter().flat_map(|l| &l.entries).any(|e| e.symbol == symbol) {
+                continue;
+            }
+            if self.lists.iter().all(|l| l.name != DEFAULT_WATCHLIST) {
+                self.lists.insert(0, Watchlist { name: DEFAULT_WATCHLIST.to_string(), entries: Vec::new() });
+            }
+            added.push(self.add(DEFAULT_WATCHLIST, &symbol, SymbolFlags { trade, ..SymbolFlags::default() })?);
+        }
+        Ok(added)
+    }
+}
+
+/// What the live stream has to subscribe and unsubscribe after a change
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct StreamDelta {
+    pub subscribe: Vec<String>,
+    pub unsubscribe: Vec<String>,
+}
+
+impl StreamDelta {
+    pub fn between(before: &Watchlists, after: &Watchlists) -> Self {
+        let before: BTreeSet<String> = before.symbols_with(SymbolFlag::Stream).into_iter().collect();
+        let after: BTreeSet<String> = after.symbols_with(SymbolFlag::Stream).into_iter().collect();
+        Self {
+            subscribe: after.difference(&before).cloned().collect(),
+            unsubscribe: before.difference(&after).cloned().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscribe.is_empty() && self.unsubscribe.is_empty()
+    }
+}
+
+/// Payload of "watchlist_changed"
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatchlistChange {
+    pub watchlists: Watchlists,
+    pub stream: StreamDelta,
+}
+
+/// The lists, shared with the strategy loop task so flag changes reach it mid-cycle
+pub type SharedWatchlists = Arc<Mutex<Watchlists>>;
+
+/// The managed lists; shared by the watchlist commands, the strategy loop and the
+/// stream and maintenance consumers
+#[derive(Clone)]
+pub struct WatchlistService {
+    lists: SharedWatchlists,
+    app_handle: AppHandle,
+}
+
+impl WatchlistService {
+    pub fn new(app_handle: AppHandle) -> Self {
+        let lists = FileCache::new(&app_handle)
+            .and_then(|mut cache| cache.get(WATCHLISTS_KEY))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        Self { lists: Arc::new(Mutex::new(lists)), app_handle }
+    }
+
+    pub fn shared(&self) -> SharedWatchlists {
+        self.lists.clone()
+    }
+
+    pub fn get(&self) -> Result<Watchlists, String> {
+        Ok(self.lists.lock().map_err(|e| e.to_string())?.clone())
+    }
+
+    /// Symbols with `flag` set on any list; none if the lists cannot be read
+    pub fn symbols_with(&self, flag: SymbolFlag) -> Vec<String> {
+        self.lists.lock().map(|l| l.symbols_with(flag)).unwrap_or_default()
+    }
+
+    pub fn create(&self, name: &str) -> Result<Watchlists, String> {
+        self.update(|lists| lists.create(name))
+    }
+
+    pub fn add(&self, list: &str, symbol: &str, flags: SymbolFlags) -> Result<Watchlists, String> {
+        self.update(|lists| lists.add(list, symbol, flags).map(|_| ()))
+    }
+
+    pub fn remove(&self, list: &str, symbol: &str) -> Result<Watchlists, String> {
+        self.update(|lists| lists.remove(list, symbol))
+    }
+
+    pub fn set_flags(&self, list: &str, symbol: &str, flags: SymbolFlags) -> Result<Watchlists, String> {
+        self.update(|lists| lists.set_flags(list, symbol, flags))
+    }
+
+    pub fn import_loop_config(&self, config: &StrategyLoopConfig) -> Result<Vec<String>, String> {
+        let mut added = Vec::new();
+        self.update(|lists| {
+            added = lists.import_loop_config(config)?;
+            Ok(())
+        })?;
+        Ok(added)
+    }
+
+    /// Apply `change` to a copy, save it, then swap it in and emit the change
+    fn update(&self, change: impl FnOnce(&mut Watchlists) -> Result<(), String>) -> Result<Watchlists, String> {
+        let mut lists = self.lists.lock().map_err(|e| e.to_string())?;
+        let mut updated = lists.clone();
+        change(&mut updated)?;
+        if updated == *lists {
+            return Ok(updated);
+        }
+        FileCache::new(&self.app_handle).and_then(|mut cache| cache.set(WATCHLISTS_KEY, &updated, None))?;
+        let stream = StreamDelta::between(&lists, &updated);
+        *lists = updated.clone();
+        drop(lists);
+        let _ = self.app_handle.emit("watchlist_changed", &WatchlistChange { watchlists: updated.clone(), stream });
+        Ok(updated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::pairs::PairConfig;
+
+    #[test]
+    fn test_clearing_the_stream_flag_unsubscribes_only_that_symbol() {
+        let mut lists = Watchlists::default();
+        lists.add(DEFAULT_WATCHLIST, "aapl", SymbolFlags::default()).unwrap();
+        lists.add(DEFAULT_WATCHLIST, "MSFT", SymbolFlags::default()).unwrap();
+        lists.create("earnings").unwrap();
+        lists.add("earnings", "NVDA", SymbolFlags { stream: false, ..SymbolFlags::default() }).unwrap();
+        assert!(lists.add(DEFAULT_WATCHLIST, "AAPL ", SymbolFlags::default()).unwrap_err().contains("already on 'main'"));
+        assert!(lists.create("Earnings").is_err());
+
+        let before = lists.clone();
+        lists.set_flags(DEFAULT_WATCHLIST, "AAPL", SymbolFlags { stream: false, ..SymbolFlags::default() }).unwrap();
+        let delta = StreamDelta::between(&before, &lists);
+        assert_eq!(delta, StreamDelta { subscribe: vec![], unsubscribe: vec!["AAPL".to_string()] });
+        assert_eq!(lists.symbols_with(SymbolFlag::Stream), vec!["MSFT"]);
+        assert_eq!(lists.symbols_with(SymbolFlag::Trade), vec!["AAPL", "MSFT", "NVDA"]);
+
+        // Still streamed while another list's entry wants it
+        lists.add("earnings", "MSFT", SymbolFlags::default()).unwrap();
+        let before = lists.clone();
+        lists.remove(DEFAULT_WATCHLIST, "MSFT").unwrap();
+        assert!(StreamDelta::between(&before, &lists).is_empty());
+    }
+
+    #[test]
+    fn test_import_adds_rule_symbols_and_untraded_pair_legs_once() {
+        let mut config = StrategyLoopConfig::default();
+        config.symbol_rules.insert("SPY".to_string(), Default::default());
+        config.symbol_rules.insert("AAPL".to_string(), Default::default());
+        config.pairs.push(serde_json::from_value::<PairConfig>(serde_json::json!({"first": "XLE", "second": "SPY"})).unwrap());
+
+        let mut lists = Watchlists::default();
+        assert_eq!(lists.import_loop_config(&config).unwrap(), vec!["AAPL", "SPY", "XLE"]);
+        assert!(lists.is_flagged("SPY", SymbolFlag::Trade));
+        assert!(!lists.is_flagged("XLE", SymbolFlag::Trade) && lists.is_flagged("XLE", SymbolFlag::Stream));
+        assert!(lists.import_loop_config(&config).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,487 @@
+// src-tauri/src/engine/what_if.rs
+// "What if" exits: the journal's actual entries replayed against daily bars under a
+// different exit rule. Entry fills are kept exactly as they happened and only the exit
+// side is simulated. Each entry trade's opening quantity is its own lot, simulated
+// independently of overlapping positions and of how the actual exits split it. Prices
+// and quantities are restated by later splits to match the split-adjusted bars.
+
+use super::indicators::IndicatorCache;
+use super::position_history::session_date;
+use super::r#loop::{CombinationRule, SignalConfig, SignalDirection, StrategyLoop};
+use super::round_trips::{round_trips, trade_statistics, RoundTrip, TradeStatistics};
+use super::types::{InstrumentType, OrderSide, StockSplit, Trade};
+use crate::market_data::types::Candle;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+fn default_atr_period() -> usize {
+    14
+}
+
+/// How the simulated exits are taken. Price levels are checked against each bar after
+/// the entry's session; a bar that opens through a level fills at its open.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExitRule {
+    /// Stop and/or target this far from the entry price, e.g. 0.05; a bar reaching both
+    /// is taken as stopped out
+    FixedStopTarget { stop_pct: Option<f64>, target_pct: Option<f64> },
+    /// Stop `multiple` ATRs behind the best high (low for shorts) since entry, only ever
+    /// tightening; the ATR is the mean true range of the `period` bars before each bar
+    TrailingAtr {
+        multiple: f64,
+        #[serde(default = "default_atr_period")]
+        period: usize,
+    },
+    /// Out at the close of the `bars`th session after the entry's
+    TimeBased { bars: usize },
+    /// Out at the close of the first bar whose combined signals turn against the lot
+    Signal { signals: Vec<SignalConfig>, rule: CombinationRule },
+}
+
+impl ExitRule {
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = |name: &str, value: f64| {
+            if value > 0.0 && value.is_finite() { Ok(()) } else { Err(format!("{} must be positive, got {}", name, value)) }
+        };
+        match self {
+            ExitRule::FixedStopTarget { stop_pct, target_pct } => {
+                if stop_pct.is_none() && target_pct.is_none() {
+                    return Err("A fixed exit needs a stop, a target or both".to_string());
+                }
+                if let Some(stop) = stop_pct {
+                    positive("stop_pct", *stop)?;
+                    if *stop >= 1.0 {
+                        return Err(format!("stop_pct must be below 1, got {}", stop));
+                    }
+                }
+                target_pct.map_or(Ok(()), |t| positive("target_pct", t))
+            }
+            ExitRule::TrailingAtr { multiple, period } => {
+                positive("multiple", *multiple)?;
+                if *period == 0 {
+                    return Err("The ATR period must be at least 1".to_string());
+                }
+                Ok(())
+            }
+            ExitRule::TimeBased { bars } if *bars == 0 => Err("A time exit must hold for at least 1 bar".to_string()),
+            ExitRule::TimeBased { .. } => Ok(()),
+            ExitRule::Signal { signals, .. } if signals.is_empty() => Err("A signal exit needs at least one signal".to_string()),
+            ExitRule::Signal { .. } => Ok(()),
+        }
+    }
+
+    /// Daily bars of history the rule reads before an entry's session
+    pub fn history_bars(&self) -> usize {
+        match self {
+            ExitRule::TrailingAtr { period, .. } => period + 1,
+            ExitRule::Signal { signals, .. } => signals.iter().map(|s| s.lookback).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SimulatedStatus {
+    Closed,
+    StillOpen, // The rule had not exited by the last bar
+    Skipped,   // No bars for the symbol, or too few before the entry for the rule
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SimulatedExit {
+    pub status: SimulatedStatus,
+    pub exit_timestamp: Option<i64>, // The exit bar's
+    pub exit_price: Option<f64>,
+    pub reason: Option<String>,      // "stop", "target", "trailing_stop", "time" or "signal"
+    pub bars_held: usize,            // Sessions after the entry's, through the exit or the last bar
+    pub pnl: f64,                    // Realized when closed, else marked at the last close; net of commission
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WhatIfLot {
+    pub entry_trade_id: String,
+    pub symbol: String,
+    pub quantity: i64,       // Signed; in today's shares
+    pub entry_timestamp: i64,
+    pub entry_price: f64,    // Split-adjusted like the bars
+    pub entry_reason: Option<String>,
+    pub actual_pnl: f64,     // Realized by the actual exits so far, net of commission
+    pub actual_open_quantity: i64, // Not yet exited, in today's shares
+    pub actual_exit_timestamp: Option<i64>, // Last actual exit
+    pub simulated: SimulatedExit,
+    pub pnl_difference: Option<f64>, // Simulated less actual, once both closed the whole lot
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct WhatIfSide {
+    pub realized_pnl: f64,
+    pub closed_lots: usize,
+    pub open_lots: usize,
+    pub statistics: TradeStatistics, // Over closed round trips
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WhatIfReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub exit_rule: ExitRule,
+    pub lots: Vec<WhatIfLot>, // Oldest entry first
+    pub actual: WhatIfSide,
+    pub simulated: WhatIfSide,
+    pub simulated_unrealized_pnl: f64, // Lots the rule still holds, at the last close
+    pub skipped_lots: usize,
+    pub realized_difference: f64,      // Simulated less actual realized P&L
+}
+
+/// Product of the ratios of `symbol`'s splits executed after `date`
+fn split_factor(symbol: &str, date: NaiveDate, splits: &[StockSplit]) -> f64 {
+    splits.iter().filter(|s| s.symbol == symbol && s.execution_date > date).map(|s| s.ratio()).product()
+}
+
+/// Replay the stock entries in `trades` whose sessions fall in `from..=to` (and, with a
+/// `tag`, whose entry trade carries it) under `rule`. `bars` holds each symbol's daily
+/// bars, oldest first. The simulated exit pays the same commission the entry did.
+pub fn what_if_exits(
+    trades: &[Trade],
+    splits: &[StockSplit],
+    bars: &HashMap<String, Vec<Candle>>,
+    from: NaiveDate,
+    to: NaiveDate,
+    tag: Option<&str>,
+    rule: &ExitRule,
+) -> Result<WhatIfReport, String> {
+    rule.validate()?;
+    if from > to {
+        return Err(format!("Range start {} is after its end {}", from, to));
+    }
+    let trips = round_trips(trades, splits);
+
+    let mut entries: Vec<&Trade> = trades
+        .iter()
+        .filter(|t| t.instrument_type == InstrumentType::Stock && t.quantity > 0)
+        .filter(|t| (from..=to).contains(&session_date(t.timestamp)))
+        .filter(|t| tag.is_none_or(|tag| t.tag.as_deref() == Some(tag)))
+        .collect();
+    entries.sort_by(|a, b| (a.timestamp, &a.id).cmp(&(b.timestamp, &b.id)));
+
+    let mut lots = Vec::new();
+    let mut actual_trips: Vec<RoundTrip> = Vec::new();
+    let mut simulated_trips: Vec<RoundTrip> = Vec::new();
+    for trade in entries {
+        // What the trade opened, after closing anything before it
+        let closed_by: i64 = trips.iter().filter(|t| t.exit_trade_id == trade.id).map(|t| t.quantity.abs()).sum();
+        let opened = trade.quantity - closed_by;
+        if opened <= 0 {
+            continue;
+        }
+        let direction = if trade.side == OrderSide::Buy { 1 } else { -1 };
+        let entry_date = session_date(trade.timestamp);
+        let factor = split_factor(&trade.symbol, entry_date, splits);
+        let quantity = (opened as f64 * factor).round() as i64 * direction;
+        let entry_price = trade.price / factor;
+        let commission = trade.commission * opened as f64 / trade.quantity as f64;
+
+        let exits: Vec<&RoundTrip> = trips.iter().filter(|t| t.entry_trade_id == trade.id).collect();
+        let exited: f64 = exits.iter().map(|t| t.quantity.abs() as f64 * split_factor(&t.symbol, t.exit_date, splits)).sum();
+        let actual_open_quantity = (quantity.abs() - exited.round() as i64).max(0) * direction;
+        actual_trips.extend(exits.iter().map(|t| (*t).clone()));
+
+        let simulated = simulate_exit(rule, bars.get(&trade.symbol).map(Vec::as_slice).unwrap_or(&[]), entry_date, quantity, entry_price, commission);
+        if simulated.status == SimulatedStatus::Closed {
+            let exit_timestamp = simulated.exit_timestamp.unwrap_or(trade.timestamp);
+            let exit_date = session_date(exit_timestamp);
+            let entry_notional = entry_price * quantity.abs() as f64;
+            simulated_trips.push(RoundTrip {
+                symbol: trade.symbol.clone(),
+                quantity,
+                entry_timestamp: trade.timestamp,
+                entry_date,
+                entry_price,
+                exit_timestamp,
+                exit_date,
+                exit_price: simulated.exit_price.unwrap_or(entry_price),
+                commission: commission * 2.0,
+                pnl: simulated.pnl,
+                return_pct: if entry_notional > 0.0 { simulated.pnl / entry_notional } else { 0.0 },
+                holding_days: (exit_date - entry_date).num_days(),
+                entry_reason: trade.tag.clone(),
+                exit_reason: simulated.reason.clone(),
+                mae: None,
+                mfe: None,
+                entry_trade_id: trade.id.clone(),
+                exit_trade_id: String::new(),
+                entry_evaluation_id: trade.evaluation_id.clone(),
+                exit_evaluation_id: None,
+                intended_entry_price: None,
+            });
+        }
+
+        let actual_pnl: f64 = exits.iter().map(|t| t.pnl).sum();
+        let both_closed = actual_open_quantity == 0 && simulated.status == SimulatedStatus::Closed;
+        lots.push(WhatIfLot {
+            entry_trade_id: trade.id.clone(),
+            symbol: trade.symbol.clone(),
+            quantity,
+            entry_timestamp: trade.timestamp,
+            entry_price,
+            entry_reason: trade.tag.clone(),
+            actual_pnl,
+            actual_open_quantity,
+            actual_exit_timestamp: exits.iter().map(|t| t.exit_timestamp).max(),
+            pnl_difference: both_closed.then_some(simulated.pnl - actual_pnl),
+            simulated,
+        });
+    }
+
+    let actual = WhatIfSide {
+        realized_pnl: actual_trips.iter().map(|t| t.pnl).sum(),
+        closed_lots: lots.iter().filter(|l| l.actual_open_quantity == 0).count(),
+        open_lots: lots.iter().filter(|l| l.actual_open_quantity != 0).count(),
+        statistics: trade_statistics(&actual_trips),
+    };
+    let simulated = WhatIfSide {
+        realized_pnl: simulated_trips.iter().map(|t| t.pnl).sum(),
+        closed_lots: simulated_trips.len(),
+        open_lots: lots.iter().filter(|l| l.simulated.status == SimulatedStatus::StillOpen).count(),
+        statistics: trade_statistics(&simulated_trips),
+    };
+    Ok(WhatIfReport {
+        from,
+        to,
+        exit_rule: rule.clone(),
+        simulated_unrealized_pnl: lots.iter().filter(|l| l.simulated.status == SimulatedStatus::StillOpen).map(|l| l.simulated.pnl).sum(),
+        skipped_lots: lots.iter().filter(|l| l.simulated.status == SimulatedStatus::Skipped).count(),
+        realized_difference: simulated.realized_pnl - actual.realized_pnl,
+        lots,
+        actual,
+        simulated,
+    })
+}
+
+/// Walk the bars after the entry's session until `rule` exits the lot
+fn simulate_exit(rule: &ExitRule, bars: &[Candle], entry_date: NaiveDate, quantity: i64, entry_price: f64, commission: f64) -> SimulatedExit {
+    let direction = quantity.signum() as f64;
+    let start = bars.partition_point(|b| session_date(b.timestamp) <= entry_date);
+    let pnl = |price: f64| (price - entry_price) * quantity as f64 - commission;
+    let skipped = |note: String| SimulatedExit {
+        status: SimulatedStatus::Skipped,
+        exit_timestamp: None,
+        exit_price: None,
+        reason: None,
+        bars_held: 0,
+        pnl: 0.0,
+        note: Some(note),
+    };
+    if bars.is_empty() {
+        return skipped("No daily bars for the symbol".to_string());
+    }
+    if start < rule.history_bars() {
+        return skipped(format!("{} bars before the entry are needed, {} available", rule.history_bars(), start));
+    }
+
+    // A level crossed on a bar: at the open when it gapped through, else at the level
+    let crossed = |bar: &Candle, level: f64, adverse: bool| -> Option<f64> {
+        let against = if adverse { -direction } else { direction };
+        let (open, extreme) = if against > 0.0 { (bar.open, bar.high) } else { (bar.open, bar.low) };
+        if (open - level) * against >= 0.0 {
+            Some(open)
+        } else if (extreme - level) * against >= 0.0 {
+            Some(level)
+        } else {
+            None
+        }
+    };
+    let atr = |end: usize, period: usize| {
+        let window = &bars[end - period - 1..end];
+        window.windows(2).map(|w| (w[1].high - w[1].low).max((w[1].high - w[0].close).abs()).max((w[1].low - w[0].close).abs())).sum::<f64>() / period as f64
+    };
+
+    let mut trailing: Option<(f64, f64)> = None; // Best extreme and stop
+    let mut indicators = IndicatorCache::default();
+    for (held, i) in (start..bars.len()).enumerate() {
+        let bar = &bars[i];
+        let exit = match rule {
+            ExitRule::FixedStopTarget { stop_pct, target_pct } => {
+                let stop = stop_pct.and_then(|p| crossed(bar, entry_price * (1.0 - p * direction), true)).map(|p| (p, "stop"));
+                stop.or_else(|| target_pct.and_then(|p| crossed(bar, entry_price * (1.0 + p * direction), false)).map(|p| (p, "target")))
+            }
+            ExitRule::TrailingAtr { multiple, period } => {
+                let (best, stop) = *trailing.get_or_insert_with(|| (entry_price, entry_price - direction * multiple * atr(i, *period)));
+                let exit = crossed(bar, stop, true).map(|p| (p, "trailing_stop"));
+                let best = if direction > 0.0 { best.max(bar.high) } else { best.min(bar.low) };
+                let next = best - direction * multiple * atr(i + 1, *period);
+                trailing = Some((best, if direction > 0.0 { stop.max(next) } else { stop.min(next) }));
+                exit
+            }
+            ExitRule::TimeBased { bars: hold } => (held + 1 >= *hold).then_some((bar.close, "time")),
+            ExitRule::Signal { signals, rule } => {
+                let results: Vec<_> = signals
+                    .iter()
+                    .filter_map(|s| StrategyLoop::compute_signal_cached(s, &bars[..=i], &mut indicators, "", "replay"))
+                    .collect();
+                let against = if direction > 0.0 { SignalDirection::Short } else { SignalDirection::Long };
+                (StrategyLoop::combine_signals(&results, rule).0 == against).then_some((bar.close, "signal"))
+            }
+        };
+        if let Some((price, reason)) = exit {
+            return SimulatedExit {
+                status: SimulatedStatus::Closed,
+                exit_timestamp: Some(bar.timestamp),
+                exit_price: Some(price),
+                reason: Some(reason.to_string()),
+                bars_held: held + 1,
+                pnl: pnl(price) - commission,
+                note: None,
+            };
+        }
+    }
+
+    let last = bars.last().map(|b| b.close).unwrap_or(entry_price);
+    SimulatedExit {
+        status: SimulatedStatus::StillOpen,
+        exit_timestamp: None,
+        exit_price: None,
+        reason: None,
+        bars_held: bars.len() - start,
+        pnl: pnl(last),
+        note: Some(format!("Still held under the rule at the last bar's close of {}", last)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::bars::Timeframe;
+
+    const DAY: i64 = 86400;
+    const START: i64 = 1704207600; // 01/02/2024 10:00 ET
+
+    fn trade(id: &str, side: OrderSide, quantity: i64, price: f64, day: i64, tag: &str) -> Trade {
+        Trade {
+            id: id.to_string(),
+            symbol: "ABC".to_string(),
+            side,
+            quantity,
+            price,
+            timestamp: START + day * DAY + 3600,
+            order_id: format!("order-{}", id),
+            commission: quantity as f64 * 0.01,
+            net_amount: 0.0,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            leg_number: None,
+            assignment_id: None,
+            tag: Some(tag.to_string()),
+            evaluation_id: None,
+        }
+    }
+
+    fn journal() -> Vec<Trade> {
+        vec![
+            trade("1", OrderSide::Buy, 100, 10.0, 0, "breakout"),
+            trade("2", OrderSide::Buy, 50, 11.0, 1, "add"),
+            // Closes all of lot 1 and 20 of lot 2
+            trade("3", OrderSide::Sell, 120, 12.0, 4, "target"),
+            // Closes the last 30 of lot 2 and opens a 40 share short
+            trade("4", OrderSide::Sell, 70, 9.0, 6, "reverse"),
+        ]
+    }
+
+    fn bars() -> HashMap<String, Vec<Candle>> {
+        let bars = [
+            (-3, 9.0, 9.4, 8.8, 9.2),
+            (-2, 9.2, 9.6, 9.0, 9.5),
+            (-1, 9.5, 9.9, 9.4, 9.8),
+            (0, 10.0, 10.2, 9.8, 10.0),
+            (1, 10.5, 11.2, 10.4, 11.0),
+            (2, 11.0, 11.4, 10.8, 11.2),
+            (3, 11.2, 12.3, 11.0, 11.9),
+            (4, 11.9, 12.2, 11.7, 12.0),
+            (5, 11.5, 11.6, 10.2, 10.4),
+            (6, 10.0, 10.1, 8.9, 9.0),
+            (7, 9.6, 9.8, 9.5, 9.7),
+        ];
+        let series = bars.iter().map(|&(day, o, h, l, c)| Candle::new(START + day * DAY, o, h, l, c, 1000)).collect();
+        HashMap::from([("ABC".to_string(), series)])
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_fixed_and_time_exits_replay_each_entry_lot() {
+        let (from, to) = (session_date(START), session_date(START + 6 * DAY));
+        let rule = ExitRule::FixedStopTarget { stop_pct: Some(0.05), target_pct: Some(0.10) };
+        let report = what_if_exits(&journal(), &[], &bars(), from, to, None, &rule).unwrap();
+        let ids: Vec<&str> = report.lots.iter().map(|l| l.entry_trade_id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "2", "4"]);
+        let [lot1, lot2, short] = &report.lots[..] else { unreachable!() };
+
+        // Actual: lot 1 out at 12 in one exit; lot 2 split across two; the short never closed
+        assert!(close(lot1.actual_pnl, 200.0 - 2.0));
+        assert!(close(lot2.actual_pnl, (20.0 - 0.4) + (-60.0 - 0.6)));
+        assert_eq!((short.quantity, short.actual_open_quantity, short.actual_pnl), (-40, -40, 0.0));
+
+        // Lot 1's 11.00 target is reached the next day; lot 2's 12.10 two days after it
+        assert_eq!((lot1.simulated.reason.as_deref(), lot1.simulated.exit_price, lot1.simulated.bars_held), (Some("target"), Some(11.0), 1));
+        assert!(close(lot1.simulated.pnl, 100.0 - 2.0));
+        assert_eq!(lot2.simulated.exit_timestamp, Some(START + 3 * DAY));
+        assert!(close(lot2.simulated.pnl, 55.0 - 1.0));
+        assert!(close(lot2.pnl_difference.unwrap(), 54.0 - lot2.actual_pnl));
+        // The short's 9.45 stop is gapped through at the 9.60 open
+        assert_eq!((short.simulated.reason.as_deref(), short.simulated.exit_price), (Some("stop"), Some(9.6)));
+        assert!(close(short.simulated.pnl, -24.0 - 0.8));
+        assert_eq!(short.pnl_difference, None);
+
+        assert!(close(report.actual.realized_pnl, 198.0 - 41.0));
+        assert_eq!((report.actual.closed_lots, report.actual.open_lots), (2, 1));
+        assert!(close(report.simulated.realized_pnl, 98.0 + 54.0 - 24.8));
+        assert_eq!((report.simulated.closed_lots, report.simulated.statistics.round_trips), (3, 3));
+        assert!(close(report.realized_difference, 127.2 - 157.0));
+
+        // Held three sessions; the short runs out of bars and stays open, marked at the last close
+        let timed = what_if_exits(&journal(), &[], &bars(), from, to, None, &ExitRule::TimeBased { bars: 3 }).unwrap();
+        assert_eq!(timed.lots[0].simulated.exit_price, Some(11.9));
+        assert_eq!(timed.lots[1].simulated.exit_price, Some(12.0));
+        let open = &timed.lots[2].simulated;
+        assert_eq!((open.status, open.bars_held, open.exit_price), (SimulatedStatus::StillOpen, 1, None));
+        assert!(close(open.pnl, -28.0 - 0.4) && close(timed.simulated_unrealized_pnl, open.pnl));
+        assert_eq!((timed.simulated.closed_lots, timed.simulated.open_lots), (2, 1));
+
+        // A tag keeps only the entries it placed
+        let tagged = what_if_exits(&journal(), &[], &bars(), from, to, Some("add"), &rule).unwrap();
+        assert_eq!(tagged.lots.len(), 1);
+        assert!(close(tagged.actual.realized_pnl, -41.0));
+        assert!(what_if_exits(&journal(), &[], &bars(), from, to, None, &ExitRule::TimeBased { bars: 0 }).is_err());
+    }
+
+    #[test]
+    fn test_trailing_atr_and_signal_exits() {
+        let day0 = session_date(START);
+        let trailing = ExitRule::TrailingAtr { multiple: 1.0, period: 2 };
+        let report = what_if_exits(&journal(), &[], &bars(), day0, day0, None, &trailing).unwrap();
+        // ATR 0.45 puts the first stop at 9.55; it trails up to 11.40 by day 4 and is hit on day 5
+        let exit = &report.lots[0].simulated;
+        assert_eq!((exit.reason.as_deref(), exit.exit_timestamp, exit.bars_held), (Some("trailing_stop"), Some(START + 5 * DAY), 5));
+        assert!(close(exit.exit_price.unwrap(), 11.4));
+        assert!(close(exit.pnl, 140.0 - 2.0));
+
+        // Long until the close drops under its 3-day mean on day 5
+        let trend = SignalConfig { name: "Trend".to_string(), timeframe: Timeframe::OneDay, lookback: 3, weight: 1.0 };
+        let signal = ExitRule::Signal { signals: vec![trend], rule: CombinationRule::AllMustAgree };
+        let exit = &what_if_exits(&journal(), &[], &bars(), day0, day0, None, &signal).unwrap().lots[0].simulated;
+        assert_eq!((exit.reason.as_deref(), exit.exit_price), (Some("signal"), Some(10.4)));
+        assert!(close(exit.pnl, 40.0 - 2.0));
+
+        // Too little history before the entry for a longer ATR
+        let long_atr = ExitRule::TrailingAtr { multiple: 2.0, period: 14 };
+        let report = what_if_exits(&journal(), &[], &bars(), day0, day0, None, &long_atr).unwrap();
+        assert_eq!((report.lots[0].simulated.status, report.skipped_lots), (SimulatedStatus::Skipped, 1));
+        assert_eq!(report.simulated, WhatIfSide::default());
+    }
+}
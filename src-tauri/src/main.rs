@@ -3,765 +3,129 @@
 mod provider {
     pub mod polygon;
     pub mod yahoo;
+    pub mod alphavantage;
+    pub mod diagnostics;
+    pub mod entitlements;
 }
 
 mod providers {
     pub mod polygon;
+    pub mod microstructure;
+    pub mod tick_filter;
+    pub mod quote_consensus;
+}
+
+mod market_data {
+    pub mod types;
+    pub mod dates;
 }
 
 mod storage {
     pub mod cache;
+    pub mod downloads;
+    pub mod daily_refresh;
+    pub mod config_bundle;
+    pub mod chain_snapshots;
+    pub mod statements;
+    pub mod backtests;
+    pub mod bar_pins;
+    pub mod backtest_history;
+    pub mod decision_journal;
+    pub mod risk_history;
+    pub mod rejections;
+    pub mod bulk_actions;
+    pub mod equity_history;
+    pub mod api_budget;
+    pub mod ui_state;
+    pub mod strategy_presets;
+    pub mod journal_store;
+    pub mod journal_writer;
+    pub mod bar_file;
+    pub mod export;
+    #[cfg(feature = "sqlite")]
+    pub mod sqlite;
 }
 
 mod engine {
     pub mod types;
     pub mod broker;
     pub mod mtm;
+    pub mod numeric;
+    pub mod option_symbol;
+    pub mod symbols;
     pub mod risk;
+    pub mod risk_history;
+    pub mod rejections;
+    pub mod reproducibility;
     pub mod calendar;
+    pub mod bars;
+    pub mod allocations;
+    pub mod analytics;
+    pub mod basket;
+    pub mod benchmark;
+    pub mod decision_outcomes;
+    pub mod derisk;
+    pub mod drawdown;
+    pub mod entry_stats;
+    pub mod equity_history;
+    pub mod hedge;
+    pub mod indicators;
+    pub mod margin;
+    pub mod freshness;
+    pub mod badges;
+    pub mod metrics;
+    pub mod news_impact;
+    pub mod news_halt;
+    pub mod pairs;
+    pub mod promotion;
+    pub mod shadow_ledger;
+    pub mod simulation;
+    pub mod signal_optimizer;
+    pub mod spread;
     pub mod r#loop;
-}
-
-use provider::polygon as poly;
-use provider::yahoo as yfin;
-use providers::polygon::{PolygonProvider, OhlcBar};
-use engine::broker::PaperBroker;
-use engine::types::{OrderRequest, TradeExecution, Portfolio, Trade, MarketData, EnhancedPortfolio};
-use engine::risk::RiskMetrics;
-use engine::calendar::TradingSession;
-use engine::r#loop::{StrategyLoop, StrategyLoopConfig, LoopState, SignalEvaluation};
-use storage::cache::JournalStats;
-
-use serde::{Deserialize, Serialize};
-use std::{fs, time::Instant};
-use tauri::{Manager, Emitter};
-
-//
-// ---------- Types shared with frontend ----------
-//
-
-#[tauri::command]
-async fn get_sample_backtest_result() -> BacktestSummary {
-    // TODO: return your existing sample, or synthesize a small curve
-    // minimal safe stub:
-    BacktestSummary {
-        strategy: "PMCC".into(),
-        symbol: "SPY".into(),
-        start: "01/01/2023".into(),
-        end: "12/31/2023".into(),
-        capital: 100_000.0,
-        cagr: 0.12,
-        trades: 40,
-        win_rate: 0.55,
-        max_dd: -0.15,
-        equity_curve: (0..252).scan((100000.0f64, 100000.0f64), |state, i|{
-          let r = 0.0006f64;
-          state.0 *= 1.0 + r;
-          state.1 = state.1.max(state.0);
-          Some(EquityPoint{
-            t: format!("{:02}/{:02}/2023", (i % 12) + 1, (i % 28) + 1),
-            equity: state.0,
-            drawdown: (state.0 - state.1) / state.1
-          })
-        }).collect()
-    }
-}
-
-
-
-#[tauri::command]
-async fn suggest_and_analyze(_params: serde_json::Value) -> serde_json::Value {
-    serde_json::json!({
-      "ok": true,
-      "notes": ["stub"],
-      "recommendation": { "strategy": "PMCC", "confidence": 0.6 }
-    })
-}
-
-#[tauri::command]
-async fn fetch_news_sentiment(symbol: String) -> serde_json::Value {
-    serde_json::json!({ "symbol": symbol, "stories": [], "sentiment": 0.0 })
-}
-
-#[tauri::command]
-async fn fetch_polygon_bars(
-    symbol: String,
-    from: String,
-    to: String,
-    apikey: String
-) -> serde_json::Value {
-    // Construct Polygon API URL
-    let url = format!(
-        "https://api.polygon.io/v2/aggs/ticker/{}/range/1/day/{}/{}?adjusted=true&sort=asc&apikey={}",
-        symbol.to_uppercase(),
-        from,
-        to,
-        apikey
-    );
-
-    // Make HTTP request
-    match reqwest::get(&url).await {
-        Ok(response) => {
-            match response.json::<serde_json::Value>().await {
-                Ok(data) => data,
-                Err(e) => {
-                    eprintln!("Failed to parse Polygon response: {}", e);
-                    serde_json::json!({
-                        "status": "ERROR",
-                        "error": "Failed to parse response"
-                    })
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("Failed to fetch from Polygon: {}", e);
-            serde_json::json!({
-                "status": "ERROR",
-                "error": format!("HTTP request failed: {}", e)
-            })
-        }
-    }
-}
-
-
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct EquityPoint {
-    pub t: String,     // MM/DD/YYYY
-    pub equity: f64,   // portfolio equity
-    pub drawdown: f64, // <= 0
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct BacktestParams {
-    pub ticker: String,
-    pub start_date: String,   // MM/DD/YYYY
-    pub end_date: String,     // MM/DD/YYYY
-    pub strategy: String,     // e.g. "BuyHold" / "PMCC"
-    pub initial_capital: f64, // e.g. 100000
-    pub seed: Option<u32>,
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct BacktestSummary {
-    pub strategy: String,
-    pub symbol: String,
-    pub start: String,
-    pub end: String,
-    pub capital: f64,
-    pub cagr: f64,
-    pub trades: u32,
-    pub win_rate: f64, // 0..1
-    pub max_dd: f64,   // <= 0
-    pub equity_curve: Vec<EquityPoint>,
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct PingResponse {
-    ok: bool,
-    ts: u64,
-}
-
-//
-// ---------- Helper math ----------
-//
-
-fn calc_drawdown_series(eqs: &[f64]) -> (Vec<f64>, f64) {
-    let mut max_run = if eqs.is_empty() { 0.0 } else { eqs[0] };
-    let mut dds = Vec::with_capacity(eqs.len());
-    let mut min_dd = 0.0;
-    for &e in eqs {
-        if e > max_run {
-            max_run = e;
-        }
-        let dd = if max_run > 0.0 { (e - max_run) / max_run } else { 0.0 };
-        if dd < min_dd {
-            min_dd = dd;
-        }
-        dds.push(dd);
-    }
-    (dds, min_dd)
-}
-
-fn annualized_cagr(first: f64, last: f64, days: usize) -> f64 {
-    if first <= 0.0 || last <= 0.0 || days == 0 {
-        return 0.0;
-    }
-    let years = (days as f64) / 365.25;
-    if years <= 0.0 {
-        0.0
-    } else {
-        (last / first).powf(1.0 / years) - 1.0
-    }
-}
-
-//
-// ---------- Commands: utilities ----------
-//
-
-#[tauri::command]
-async fn ping() -> PingResponse {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let ts = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_millis() as u64;
-    PingResponse { ok: true, ts }
-}
-
-fn prefs_path(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
-    let p = app.path().app_config_dir().map_err(|e| e.to_string())?;
-    Ok(p.join("trading-app").join("config.json"))
-}
-
-#[tauri::command]
-async fn load_preferences(app: tauri::AppHandle) -> Result<Option<BacktestParams>, String> {
-    let path = prefs_path(&app)?;
-    if !path.exists() {
-        return Ok(None);
-    }
-    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
-    let v: serde_json::Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
-    let p: BacktestParams = serde_json::from_value(v).map_err(|e| e.to_string())?;
-    Ok(Some(p))
-}
-
-#[tauri::command]
-async fn save_preferences(app: tauri::AppHandle, preferences: BacktestParams) -> Result<(), String> {
-    let path = prefs_path(&app)?;
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    }
-    let v = serde_json::json!({
-        "ticker": preferences.ticker,
-        "start_date": preferences.start_date,
-        "end_date": preferences.end_date,
-        "strategy": preferences.strategy,
-        "initial_capital": preferences.initial_capital,
-        "seed": preferences.seed
-    });
-    fs::write(path, serde_json::to_string_pretty(&v).unwrap()).map_err(|e| e.to_string())
-}
-
-//
-// ---------- Commands: data providers ----------
-//
-
-#[tauri::command]
-async fn save_api_key(app: tauri::AppHandle, key: String) -> Result<(), String> {
-    poly::save_polygon_key(&app, key).await
-}
-
-#[tauri::command]
-async fn fetch_history(
-    app: tauri::AppHandle,
-    symbol: String,
-    start: String,
-    end: String,
-    interval: Option<String>,
-) -> Result<Vec<poly::Bar>, String> {
-    poly::fetch_history(&app, symbol, start, end, interval).await
-}
-
-#[tauri::command]
-async fn fetch_history_yahoo(symbol: String, start: String, end: String) -> Result<Vec<yfin::YBar>, String> {
-    yfin::yahoo_history(symbol, start, end).await
-}
-
-#[tauri::command]
-async fn fetch_news(app: tauri::AppHandle, symbol: String, days: u32) -> Result<(f64, Vec<poly::NewsItem>), String> {
-    poly::fetch_news(&app, symbol, days).await
-}
-
-// Additional command stubs to prevent "command not found" errors
-#[tauri::command]
-async fn adaptive_run(_mode: String) -> serde_json::Value {
-    serde_json::json!({
-        "status": "stub",
-        "message": "Adaptive run not implemented yet"
-    })
-}
-
-#[tauri::command]
-async fn fetch_option_chain(_symbol: String, _expiry: String) -> serde_json::Value {
-    serde_json::json!({
-        "status": "stub",
-        "chains": []
-    })
-}
-
-#[tauri::command]
-async fn fetch_option_quotes(_symbols: Vec<String>) -> serde_json::Value {
-    serde_json::json!({
-        "status": "stub",
-        "quotes": []
-    })
-}
-
-#[tauri::command]
-async fn store_api_key(app: tauri::AppHandle, key: String) -> Result<(), String> {
-    // Alias for save_api_key for backward compatibility
-    save_api_key(app, key).await
-}
-
-#[tauri::command]
-async fn test_api_connection() -> Result<String, String> {
-    Ok("Connection test not implemented".to_string())
-}
-
-//
-// ---------- Commands: Realtime Data & Streaming ----------
-//
-
-#[tauri::command]
-async fn fetch_ohlc(
-    app: tauri::AppHandle,
-    symbol: String,
-    start: String,
-    end: String,
-    tf: String,
-) -> Result<Vec<OhlcBar>, String> {
-    let provider = PolygonProvider::new(app);
-    provider.fetch_ohlc(&symbol, &start, &end, &tf).await
-}
-
-#[tauri::command]
-async fn start_stream(
-    app: tauri::AppHandle,
-    symbols: Vec<String>,
-) -> Result<(), String> {
-    // Store provider in app state - for now we'll create a new one each time
-    // In production, you'd want to manage this as persistent state
-    let mut provider = PolygonProvider::new(app);
-    provider.start_stream(symbols).await
-}
-
-#[tauri::command]
-async fn stop_stream(app: tauri::AppHandle) -> Result<(), String> {
-    // For now, we'll emit a stop signal
-    // In production, you'd access the stored provider state
-    let _ = app.emit("stream_stop_requested", ());
-    Ok(())
-}
-
-//
-// ---------- Commands: Paper Broker ----------
-//
-
-#[tauri::command]
-async fn paper_order(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-    req: OrderRequest,
-) -> Result<TradeExecution, String> {
-    let mut broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    broker.place_order(req)
-}
-
-#[tauri::command]
-async fn portfolio(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-) -> Result<Portfolio, String> {
-    let broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    Ok(broker.get_portfolio())
-}
-
-#[tauri::command]
-async fn trades(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-) -> Result<Vec<Trade>, String> {
-    let broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    Ok(broker.get_trades())
-}
-
-#[tauri::command]
-async fn cancel_order(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-    order_id: String,
-) -> Result<(), String> {
-    let mut broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    broker.cancel_order(&order_id)
-}
-
-#[tauri::command]
-async fn close_position(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-    symbol: String,
-) -> Result<TradeExecution, String> {
-    let mut broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    broker.close_position(&symbol)
-}
-
-#[tauri::command]
-async fn update_market_data(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-    data: MarketData,
-) -> Result<(), String> {
-    let mut broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    broker.update_market_data(data);
-    Ok(())
-}
-
-#[tauri::command]
-async fn enhanced_portfolio(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-) -> Result<EnhancedPortfolio, String> {
-    let broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    Ok(broker.get_enhanced_portfolio())
-}
-
-#[tauri::command]
-async fn risk_status(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-) -> Result<RiskMetrics, String> {
-    let broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    Ok(broker.get_risk_status())
-}
-
-#[tauri::command]
-async fn risk_violations(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-) -> Result<Vec<String>, String> {
-    let broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    Ok(broker.get_risk_violations())
-}
-
-#[tauri::command]
-async fn update_risk_metrics(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-) -> Result<(), String> {
-    let mut broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    broker.update_risk_metrics();
-    Ok(())
-}
-
-//
-// ---------- Commands: Broker Persistence ----------
-//
-
-#[tauri::command]
-async fn save_broker_state(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-) -> Result<(), String> {
-    let mut broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    broker.save_state()
-}
-
-#[tauri::command]
-async fn get_journal_stats(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-) -> Result<JournalStats, String> {
-    let broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    broker.get_journal_stats()
-}
-
-#[tauri::command]
-async fn backup_journal(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-    backup_suffix: String,
-) -> Result<String, String> {
-    let broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    let backup_path = broker.backup_journal(&backup_suffix)?;
-    Ok(backup_path.to_string_lossy().to_string())
-}
-
-#[tauri::command]
-async fn set_auto_save(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-    enabled: bool,
-) -> Result<(), String> {
-    let mut broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    broker.set_auto_save(enabled);
-    Ok(())
-}
-
-//
-// ---------- Commands: Market Calendar ----------
-//
-
-#[tauri::command]
-async fn get_current_session(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-) -> Result<TradingSession, String> {
-    let broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    Ok(broker.get_current_session())
-}
-
-#[tauri::command]
-async fn is_market_open(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-) -> Result<bool, String> {
-    let broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    Ok(broker.is_market_open())
-}
-
-#[tauri::command]
-async fn get_next_session_start(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-) -> Result<Option<i64>, String> {
-    let broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    Ok(broker.get_next_session_start())
-}
-
-#[tauri::command]
-async fn configure_extended_hours(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-    premarket: bool,
-    afterhours: bool,
-) -> Result<(), String> {
-    let mut broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    broker.configure_extended_hours(premarket, afterhours);
-    Ok(())
-}
-
-#[tauri::command]
-async fn set_holiday_trading(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-    enabled: bool,
-) -> Result<(), String> {
-    let mut broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-    broker.set_holiday_trading(enabled);
-    Ok(())
-}
-
-#[tauri::command]
-async fn add_custom_holiday(
-    broker: tauri::State<'_, std::sync::Mutex<PaperBroker>>,
-    date: String, // MM/DD/YYYY format
-    name: String,
-    is_early_close: bool,
-) -> Result<(), String> {
-    let mut broker = broker.lock().map_err(|e| format!("Lock error: {}", e))?;
-
-    // Parse MM/DD/YYYY date format
-    let date_parts: Vec<&str> = date.split('/').collect();
-    if date_parts.len() != 3 {
-        return Err("Date must be in MM/DD/YYYY format".to_string());
-    }
-
-    let month: u32 = date_parts[0].parse()
-        .map_err(|_| "Invalid month".to_string())?;
-    let day: u32 = date_parts[1].parse()
-        .map_err(|_| "Invalid day".to_string())?;
-    let year: i32 = date_parts[2].parse()
-        .map_err(|_| "Invalid year".to_string())?;
-
-    let naive_date = chrono::NaiveDate::from_ymd_opt(year, month, day)
-        .ok_or("Invalid date".to_string())?;
-
-    broker.add_custom_holiday(naive_date, name, is_early_close);
-    Ok(())
-}
-
-//
-// ---------- Commands: Strategy Loop ----------
-//
-
-#[tauri::command]
-fn start_strategy_loop(
-    strategy_loop: tauri::State<'_, std::sync::Mutex<StrategyLoop>>,
-) -> Result<(), String> {
-    let mut loop_guard = strategy_loop.lock().map_err(|e| format!("Lock error: {}", e))?;
-    // Use blocking version or spawn the async operation
-    tokio::task::block_in_place(|| {
-        tokio::runtime::Handle::current().block_on(loop_guard.start())
-    })
-}
-
-#[tauri::command]
-fn stop_strategy_loop(
-    strategy_loop: tauri::State<'_, std::sync::Mutex<StrategyLoop>>,
-) -> Result<(), String> {
-    let mut loop_guard = strategy_loop.lock().map_err(|e| format!("Lock error: {}", e))?;
-    tokio::task::block_in_place(|| {
-        tokio::runtime::Handle::current().block_on(loop_guard.stop())
-    })
-}
-
-#[tauri::command]
-fn get_strategy_loop_state(
-    strategy_loop: tauri::State<'_, std::sync::Mutex<StrategyLoop>>,
-) -> Result<LoopState, String> {
-    let loop_guard = strategy_loop.lock().map_err(|e| format!("Lock error: {}", e))?;
-    Ok(tokio::task::block_in_place(|| {
-        tokio::runtime::Handle::current().block_on(loop_guard.get_state())
-    }))
-}
-
-#[tauri::command]
-fn get_strategy_loop_config(
-    strategy_loop: tauri::State<'_, std::sync::Mutex<StrategyLoop>>,
-) -> Result<StrategyLoopConfig, String> {
-    let loop_guard = strategy_loop.lock().map_err(|e| format!("Lock error: {}", e))?;
-    Ok(tokio::task::block_in_place(|| {
-        tokio::runtime::Handle::current().block_on(loop_guard.get_config())
-    }))
-}
-
-#[tauri::command]
-fn update_strategy_loop_config(
-    strategy_loop: tauri::State<'_, std::sync::Mutex<StrategyLoop>>,
-    config: StrategyLoopConfig,
-) -> Result<(), String> {
-    let mut loop_guard = strategy_loop.lock().map_err(|e| format!("Lock error: {}", e))?;
-    tokio::task::block_in_place(|| {
-        tokio::runtime::Handle::current().block_on(loop_guard.update_config(config))
-    })
-}
-
-#[tauri::command]
-fn reset_strategy_loop_state(
-    strategy_loop: tauri::State<'_, std::sync::Mutex<StrategyLoop>>,
-) -> Result<(), String> {
-    let mut loop_guard = strategy_loop.lock().map_err(|e| format!("Lock error: {}", e))?;
-    tokio::task::block_in_place(|| {
-        tokio::runtime::Handle::current().block_on(loop_guard.reset_state())
-    })
-}
-
-//
-// ---------- Command: run_backtest (uses Polygon, falls back to Yahoo) ----------
-//
-
-#[tauri::command]
-async fn run_backtest(app: tauri::AppHandle, params: BacktestParams) -> Result<BacktestSummary, String> {
-    let t0 = Instant::now();
-
-    // Try Polygon first
-    let bars_res = fetch_history(
-        app.clone(),
-        params.ticker.clone(),
-        params.start_date.clone(),
-        params.end_date.clone(),
-        Some("1day".into()),
-    )
-    .await
-    .map(|v| {
-        v.into_iter()
-            .map(|b| (b.date, b.c))
-            .collect::<Vec<(String, f64)>>()
-    });
-
-    // Fallback to Yahoo if Polygon fails
-    let closes: Vec<(String, f64)> = match bars_res {
-        Ok(v) if !v.is_empty() => v,
-        _ => fetch_history_yahoo(params.ticker.clone(), params.start_date.clone(), params.end_date.clone())
-            .await
-            .map_err(|e| format!("Both providers failed: {e}"))?
-            .into_iter()
-            .map(|b| (b.date, b.c))
-            .collect(),
-    };
-
-    // If we have insufficient data, return empty result (frontend will handle with synthetic data)
-    if closes.len() < 2 {
-        return Ok(BacktestSummary {
-            strategy: params.strategy.clone(),
-            symbol: params.ticker.clone(),
-            start: params.start_date.clone(),
-            end: params.end_date.clone(),
-            capital: params.initial_capital,
-            cagr: 0.0,
-            trades: 0,
-            win_rate: 0.0,
-            max_dd: 0.0,
-            equity_curve: vec![], // Empty curve - frontend will detect and use synthetic data
-        });
-    }
-
-    // Simple buy & hold example backtest; replace with your strategy later.
-    let mut equity_curve = Vec::with_capacity(closes.len());
-    let mut equities = Vec::with_capacity(closes.len());
-
-    let start_close = closes[0].1.max(1e-9);
-    let mut equity = params.initial_capital;
-
-    for (i, (d, c)) in closes.iter().enumerate() {
-        // scale equity proportional to close/first_close
-        equity = params.initial_capital * (*c / start_close);
-        equities.push(equity);
-        // drawdown computed later
-        equity_curve.push(EquityPoint {
-            t: d.clone(),
-            equity,
-            drawdown: 0.0,
-        });
-    }
-
-    let (dd_series, max_dd) = calc_drawdown_series(&equities);
-    for (i, dd) in dd_series.into_iter().enumerate() {
-        equity_curve[i].drawdown = dd;
-    }
-
-    // Daily positive return as a proxy for "win"
-    let mut wins = 0u32;
-    let mut trades = 0u32;
-    for i in 1..closes.len() {
-        let r = (closes[i].1 / closes[i - 1].1) - 1.0;
-        trades += 1;
-        if r > 0.0 {
-            wins += 1;
-        }
-    }
-    let win_rate = (wins as f64) / (trades as f64);
-
-    let cagr = annualized_cagr(equity_curve[0].equity, equity_curve.last().unwrap().equity, closes.len());
-
-    let out = BacktestSummary {
-        strategy: params.strategy.clone(),
-        symbol: params.ticker.clone(),
-        start: params.start_date.clone(),
-        end: params.end_date.clone(),
-        capital: params.initial_capital,
-        cagr,
-        trades,
-        win_rate,
-        max_dd,
-        equity_curve,
-    };
-
-    let _elapsed_ms = t0.elapsed().as_millis();
-    Ok(out)
-}
-
-// Helper function to generate synthetic equity curve
-fn generate_deterministic_equity_curve(days: usize, start_equity: f64, seed: u64) -> Vec<EquityPoint> {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
-
-    let mut hasher = DefaultHasher::new();
-    seed.hash(&mut hasher);
-    let mut rng_state = hasher.finish();
-
-    // Simple LCG for deterministic random numbers
-    let mut next_random = move || {
-        rng_state = rng_state.wrapping_mul(1103515245).wrapping_add(12345);
-        (rng_state / 65536) % 32768
-    };
-
-    let mut equity = start_equity;
-    let mut max_equity = start_equity;
-    let mut curve = Vec::with_capacity(days);
-
-    for i in 0..days {
-        // Generate deterministic return
-        let rand_val = next_random() as f64 / 32767.0; // 0 to 1
-        let daily_return = 0.0006 + (rand_val - 0.5) * 0.02; // ~0.06% avg with volatility
-
-        equity *= 1.0 + daily_return;
-        max_equity = max_equity.max(equity);
-        let drawdown = (equity - max_equity) / max_equity;
-
-        curve.push(EquityPoint {
-            t: format!("{:02}/{:02}/2023", (i % 12) + 1, (i % 28) + 1),
-            equity,
-            drawdown,
-        });
-    }
+    pub mod statements;
+    pub mod position_history;
+    pub mod scanner;
+    pub mod strategies;
+    pub mod tax_lots;
+    pub mod tick_size;
+    pub mod undo;
+    pub mod portfolio_view;
+    pub mod rates;
+    pub mod bulk;
+    pub mod round_trips;
+    pub mod what_if;
+    pub mod watchlist;
+}
+
+mod commands {
+    pub mod state;
+    pub mod backtest;
+    pub mod broker;
+    pub mod calendar;
+    pub mod data;
+    pub mod prefs;
+    pub mod strategy;
+    pub mod supervisor;
+    pub mod display;
 
-    curve
+    #[cfg(test)]
+    mod tests;
 }
 
+use commands::state::{BrokerHandle, JobRegistry, ProviderRegistry, StrategyLoopHandle};
+use commands::{backtest, broker, calendar, data, prefs, strategy, supervisor};
+use commands::supervisor::{
+    MaintenanceSubsystem, StorageSubsystem, StrategyLoopSubsystem, StreamSubsystem, Supervisor, WatchdogSubsystem,
+};
+use engine::broker::PaperBroker;
+use engine::r#loop::StrategyLoop;
+use storage::chain_snapshots::ChainRecorder;
+use storage::daily_refresh::DailyRefresher;
+use storage::downloads::DownloadManager;
 
+use std::sync::Arc;
+use tauri::Manager;
 
 //
 // ---------- App bootstrap ----------
@@ -783,6 +147,7 @@ fn main() {
 
             // Initialize strategy loop
             let strategy_loop = StrategyLoop::new(broker_arc.clone(), app.handle().clone());
+            strategy_loop.attach_tick_stream();
 
             // Convert Arc<tokio::Mutex<PaperBroker>> back to PaperBroker for std::sync::Mutex
             // This is a workaround for the different mutex types
@@ -790,70 +155,290 @@ fn main() {
                 let broker_guard = broker_arc.blocking_lock();
                 broker_guard.clone()
             };
+            // Both copies share the journal and its storage worker
+            let journal_writer = paper_broker_for_tauri.journal_writer();
+
+            // Manage the broker, strategy loop and data providers
+            app.manage(BrokerHandle::new(paper_broker_for_tauri));
+            broker::attach_tick_stream(app.handle());
+            broker::start_order_expiry_timer(app.handle());
+            app.manage(strategy_loop.news_halts());
+            app.manage(strategy_loop.watchlists());
+            app.manage(StrategyLoopHandle::new(strategy_loop));
+            let providers = ProviderRegistry::new(app.handle())?;
+            prefs::restore_display_format(&providers);
+            app.manage(providers);
+            data::attach_watchlist_stream(app.handle());
+            app.manage(JobRegistry::new(app.handle()));
+
+            // Persisted history downloads, resumed by the maintenance subsystem
+            let download_manager = DownloadManager::new(app.handle().clone());
+
+            // Keep watchlist and held symbols' daily bars current after each close
+            let daily_refresher = DailyRefresher::new(app.handle().clone(), download_manager.clone(), Arc::new(data::tracked_daily_symbols));
+            app.manage(daily_refresher);
+            app.manage(download_manager);
+
+            // Daily option chain recorder
+            match ChainRecorder::new(app.handle().clone()) {
+                Ok(recorder) => {
+                    app.manage(recorder);
+                }
+                Err(e) => eprintln!("Failed to initialize option chain recorder: {}", e),
+            }
 
-            // Manage the broker state and strategy loop
-            app.manage(std::sync::Mutex::new(paper_broker_for_tauri));
-            app.manage(std::sync::Mutex::new(strategy_loop));
+            // Background subsystems; the stream and strategy loop start on request
+            let supervisor = Supervisor::default();
+            let now = chrono::Utc::now().timestamp();
+            match journal_writer {
+                Ok(writer) => supervisor.register(Arc::new(StorageSubsystem::new(writer)), true, now)?,
+                Err(e) => eprintln!("Journal storage worker unavailable: {}", e),
+            }
+            supervisor.register(Arc::new(StreamSubsystem::new(app.handle())), false, now)?;
+            supervisor.register(Arc::new(StrategyLoopSubsystem::new(app.handle())), false, now)?;
+            supervisor.register(Arc::new(WatchdogSubsystem::new(app.handle())), true, now)?;
+            supervisor.register(Arc::new(MaintenanceSubsystem::new(app.handle())), true, now)?;
+            app.manage(supervisor);
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             // utils / prefs
-            ping,
-            load_preferences,
-            save_preferences,
+            prefs::ping,
+            prefs::load_preferences,
+            prefs::get_display_format,
+            prefs::set_display_format,
+            prefs::save_preferences,
+            prefs::set_ui_state,
+            prefs::get_ui_state,
+            prefs::get_ui_namespace,
+            prefs::clear_ui_state,
+            prefs::get_storage_backend,
+            prefs::set_storage_backend,
+            prefs::migrate_journal_to_sqlite,
             // data
-            save_api_key,
-            store_api_key,
-            test_api_connection,
-            fetch_history,
-            fetch_history_yahoo,
-            fetch_news,
-            fetch_polygon_bars,
-            fetch_option_chain,
-            fetch_option_quotes,
+            data::save_api_key,
+            data::store_api_key,
+            data::test_api_connection,
+            data::test_provider_key,
+            data::get_provider_status,
+            data::fetch_history,
+            data::estimate_history_request,
+            data::get_api_usage,
+            data::get_provider_parse_errors,
+            data::verify_quotes,
+            data::get_quote_consensus_config,
+            data::set_quote_consensus_config,
+            data::get_quote_diagnostics,
+            data::set_api_limits,
+            data::query_cached_bars,
+            data::export_cached_bars_json,
+            data::fetch_history_yahoo,
+            data::fetch_news,
+            data::analyze_news_impact,
+            data::fetch_polygon_bars,
+            data::fetch_option_chain,
+            data::fetch_option_quotes,
+            data::normalize_option_symbol,
+            data::save_alphavantage_key,
+            data::fetch_historical_option_chain,
+            data::fetch_earnings_calendar,
+            data::fetch_rate_curve,
+            data::fetch_dividends,
             // realtime data
-            fetch_ohlc,
-            start_stream,
-            stop_stream,
+            data::fetch_ohlc,
+            data::start_stream,
+            data::subscribe_symbols_tiered,
+            data::stop_stream,
+            data::get_microstructure_stats,
+            data::get_tick_filter_config,
+            data::set_tick_filter_config,
             // paper broker
-            paper_order,
-            portfolio,
-            trades,
-            cancel_order,
-            close_position,
-            update_market_data,
+            broker::paper_order,
+            broker::preview_order,
+            broker::process_matured_fills,
+            broker::query_orders,
+            broker::get_order_state_diagnostics,
+            broker::get_numeric_faults,
+            broker::portfolio,
+            broker::trades,
+            broker::get_symbol_pnl,
+            broker::get_trade_statistics,
+            broker::what_if_exits,
+            broker::get_implementation_shortfall_report,
+            broker::cancel_order,
+            broker::cancel_all_orders,
+            broker::close_all_positions,
+            broker::go_flat,
+            broker::get_bulk_actions,
+            broker::close_position,
+            broker::get_position_detail,
+            broker::get_open_lots,
+            broker::close_specific_lots,
+            broker::optimize_lots_for_tax,
+            broker::calculate_option_probability,
+            broker::configure_position_exits,
+            broker::process_option_expirations,
+            broker::get_upcoming_expirations,
+            broker::exercise_option,
+            broker::set_exercise_instruction,
+            broker::clear_exercise_instruction,
+            broker::get_exercise_instructions,
+            broker::schedule_stock_split,
+            broker::get_pending_splits,
+            broker::get_corporate_actions,
+            broker::get_position_history,
+            broker::get_venue_statistics,
+            broker::update_market_data,
+            broker::configure_simulation,
+            broker::tick_simulation,
             // enhanced portfolio & risk
-            enhanced_portfolio,
-            risk_status,
-            risk_violations,
-            update_risk_metrics,
+            broker::enhanced_portfolio,
+            broker::get_portfolio_view,
+            broker::risk_status,
+            broker::risk_violations,
+            broker::get_exposure_breakdown,
+            broker::get_derisk_plan,
+            broker::execute_derisk_plan,
+            broker::get_hedge_suggestion,
+            broker::execute_hedge,
+            broker::update_risk_metrics,
+            broker::get_risk_history,
+            broker::get_rejection_report,
+            broker::set_drawdown_alerts,
+            broker::set_freshness_config,
+            broker::set_badge_config,
+            broker::get_rate_inputs,
+            broker::set_rate_curve,
+            broker::set_dividend_yield,
+            broker::get_spread_estimate,
+            broker::undo_last_action,
+            broker::get_undo_stack,
+            broker::set_undo_depth,
             // broker persistence
-            save_broker_state,
-            get_journal_stats,
-            backup_journal,
-            set_auto_save,
+            broker::save_broker_state,
+            broker::get_journal_stats,
+            broker::backup_journal,
+            broker::query_trades,
+            broker::pnl_report,
+            broker::get_rolling_metrics,
+            broker::get_equity_history,
+            broker::set_equity_retention,
+            broker::set_benchmark_account,
+            broker::clear_benchmark_account,
+            broker::create_allocation,
+            broker::remove_allocation,
+            broker::rebalance_allocations,
+            broker::get_allocation_report,
+            broker::set_auto_save,
+            broker::get_tick_size_rules,
+            broker::set_tick_size_rules,
+            broker::get_margin_summary,
+            broker::set_account_type,
+            broker::set_broker_lock_timeout,
+            broker::get_commission_suggestions,
+            broker::find_optimal_commission_model,
+            // account statements
+            broker::record_cash_adjustment,
+            broker::generate_statement,
+            broker::start_statement_generation,
+            broker::start_export,
+            broker::get_export_status,
+            broker::cancel_export,
+            broker::list_statements,
+            broker::get_statement,
             // market calendar
-            get_current_session,
-            is_market_open,
-            get_next_session_start,
-            configure_extended_hours,
-            set_holiday_trading,
-            add_custom_holiday,
+            calendar::get_current_session,
+            calendar::is_market_open,
+            calendar::get_next_session_start,
+            calendar::configure_extended_hours,
+            calendar::set_holiday_trading,
+            calendar::add_custom_holiday,
             // strategy loop
-            start_strategy_loop,
-            stop_strategy_loop,
-            get_strategy_loop_state,
-            get_strategy_loop_config,
-            update_strategy_loop_config,
-            reset_strategy_loop_state,
+            strategy::start_strategy_loop,
+            strategy::stop_strategy_loop,
+            strategy::get_strategy_loop_state,
+            strategy::get_strategy_loop_config,
+            strategy::update_strategy_loop_config,
+            strategy::reset_strategy_loop_state,
+            strategy::get_strategy_bars,
+            strategy::get_indicator_series,
+            strategy::get_dead_letter_queue,
+            strategy::clear_dead_letter_queue,
+            strategy::resume_symbol,
+            strategy::get_news_halt_config,
+            strategy::configure_news_halts,
+            strategy::set_news_halt_rule,
+            strategy::get_news_halts,
+            strategy::clear_news_halt,
+            strategy::get_decision_outcomes,
+            strategy::get_signal_performance,
+            // strategy presets
+            strategy::save_strategy_preset,
+            strategy::list_strategy_presets,
+            strategy::load_strategy_preset,
+            strategy::apply_strategy_preset,
+            strategy::delete_strategy_preset,
+            strategy::request_live_promotion,
+            strategy::confirm_live_promotion,
+            strategy::demote_to_dry_run,
+            strategy::get_promotion_history,
+            // watchlist
+            strategy::get_watchlists,
+            strategy::create_watchlist,
+            strategy::add_to_watchlist,
+            strategy::remove_from_watchlist,
+            strategy::set_symbol_flags,
+            // scanner
+            strategy::run_scan,
+            strategy::save_scan,
+            strategy::list_scans,
+            strategy::run_saved_scan,
+            // background subsystems
+            supervisor::get_subsystems,
+            supervisor::restart_subsystem,
+            // history downloads
+            data::queue_history_download,
+            data::get_download_jobs,
+            data::pause_download_job,
+            data::resume_download_job,
+            data::cancel_download_job,
+            data::refresh_daily_data,
+            data::get_daily_refresh_config,
+            data::configure_daily_refresh,
+            data::get_volatility_profiles,
+            // option chain snapshots
+            data::snapshot_option_chain,
+            data::list_chain_snapshots,
+            data::get_chain_snapshot,
+            data::get_chain_coverage,
+            data::get_chain_recorder_config,
+            data::configure_chain_recorder,
+            // configuration bundles
+            prefs::export_configuration,
+            prefs::import_configuration,
             // backtest
-            run_backtest,
-            get_sample_backtest_result,
-            suggest_and_analyze,
-            fetch_news_sentiment,
+            backtest::run_backtest,
+            backtest::preflight_backtest,
+            backtest::get_backtest,
+            backtest::get_backtest_statistics,
+            backtest::reproduce_backtest,
+            backtest::load_backtest_history,
+            backtest::compare_backtest_runs,
+            backtest::delete_backtest_run,
+            backtest::run_cost_sensitivity,
+            backtest::run_basket_backtest,
+            backtest::list_basket_backtests,
+            backtest::get_basket_backtest,
+            backtest::delete_basket_backtest,
+            backtest::optimize_signal_config,
+            backtest::list_signal_optimizations,
+            backtest::get_signal_optimization,
+            backtest::get_sample_backtest_result,
+            backtest::suggest_and_analyze,
+            backtest::fetch_news_sentiment,
             // adaptive
-            adaptive_run,
+            backtest::adaptive_run,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,44 @@
+// src-tauri/src/market_data/dates.rs
+// Calendar dates as the engine keeps them: NaiveDate in memory and YYYY-MM-DD when
+// persisted. State, journals and caches written before the switch, and requests from
+// older UI builds, carry MM/DD/YYYY strings instead; `parse` is the one place that still
+// reads them. Display formats belong to commands::display.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer};
+
+const LEGACY_FORMAT: &str = "%m/%d/%Y";
+
+/// A YYYY-MM-DD date, or an MM/DD/YYYY one from before the switch
+pub fn parse(date: &str) -> Result<NaiveDate, String> {
+    let date = date.trim();
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(date, LEGACY_FORMAT))
+        .map_err(|_| format!("Invalid date {} (expected YYYY-MM-DD)", date))
+}
+
+/// A date field that may still hold the old format
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lenient(pub NaiveDate);
+
+impl<'de> Deserialize<'de> for Lenient {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        parse(&text).map(Lenient).map_err(serde::de::Error::custom)
+    }
+}
+
+/// `deserialize_with` for a NaiveDate field written in either format
+pub fn lenient<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+    Lenient::deserialize(deserializer).map(|d| d.0)
+}
+
+/// `deserialize_with` for a list of dates written in either format
+pub fn lenient_vec<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<NaiveDate>, D::Error> {
+    Vec::<Lenient>::deserialize(deserializer).map(|dates| dates.into_iter().map(|d| d.0).collect())
+}
+
+/// `deserialize_with` for (first, last) date ranges written in either format
+pub fn lenient_ranges<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(NaiveDate, NaiveDate)>, D::Error> {
+    Vec::<(Lenient, Lenient)>::deserialize(deserializer).map(|ranges| ranges.into_iter().map(|(a, b)| (a.0, b.0)).collect())
+}
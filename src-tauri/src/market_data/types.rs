@@ -0,0 +1,101 @@
+// src-tauri/src/market_data/types.rs
+// Canonical OHLCV candle. Providers convert their wire formats into it at the serde
+// boundary; the bar cache, backtests and the strategy loop only see candles.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub timestamp: i64, // Epoch seconds (UTC) at the start of the interval
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: u64,
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default)]
+    pub interval: Option<String>, // "1m", "5m", "1h", "1d"
+}
+
+impl Candle {
+    pub fn new(timestamp: i64, open: f64, high: f64, low: f64, close: f64, volume: u64) -> Self {
+        Self { timestamp, open, high, low, close, volume, symbol: None, interval: None }
+    }
+
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = Some(symbol.into());
+        self
+    }
+
+    pub fn with_interval(mut self, interval: impl Into<String>) -> Self {
+        self.interval = Some(interval.into());
+        self
+    }
+
+    pub fn timestamp_millis(&self) -> i64 {
+        self.timestamp * 1000
+    }
+
+    /// UTC calendar date the candle opens on
+    pub fn date(&self) -> NaiveDate {
+        DateTime::<Utc>::from_timestamp(self.timestamp, 0)
+            .unwrap_or_default()
+            .date_naive()
+    }
+}
+
+/// Epoch seconds from a provider timestamp in seconds or milliseconds
+pub fn epoch_seconds(timestamp: i64) -> i64 {
+    if timestamp > 10_000_000_000 {
+        timestamp / 1000
+    } else {
+        timestamp
+    }
+}
+
+/// Epoch seconds at midnight UTC for a YYYY-MM-DD date
+pub fn iso_date_to_epoch(date: &str) -> Result<i64, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp())
+        .map_err(|e| format!("Invalid date {}: {}", date, e))
+}
+
+/// Volume reported as a float on the wire; negative or non-finite values become 0
+pub fn volume_from_f64(volume: f64) -> u64 {
+    if volume.is_finite() && volume > 0.0 {
+        volume.round() as u64
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candle_dates_and_timestamps() {
+        let candle = Candle::new(1704153600, 1.0, 2.0, 0.5, 1.5, 10).with_symbol("SPY").with_interval("1d");
+        assert_eq!(candle.date(), NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert_eq!(candle.timestamp_millis(), 1704153600_000);
+        assert_eq!(iso_date_to_epoch("2024-01-02").unwrap(), candle.timestamp);
+        assert!(iso_date_to_epoch("01/02/2024").is_err());
+
+        assert_eq!(epoch_seconds(1704153600_000), 1704153600);
+        assert_eq!(epoch_seconds(1704153600), 1704153600);
+        assert_eq!((volume_from_f64(1234.6), volume_from_f64(-5.0), volume_from_f64(f64::NAN)), (1235, 0, 0));
+    }
+
+    #[test]
+    fn test_candle_reads_legacy_cached_bars() {
+        // Bars cached before candles existed carry a plain symbol and signed volume
+        let legacy = serde_json::json!({
+            "symbol": "AAPL", "timestamp": 1704207600, "open": 1.0, "high": 2.0, "low": 0.5, "close": 1.5, "volume": 300
+        });
+        let candle: Candle = serde_json::from_value(legacy).unwrap();
+        assert_eq!(candle.symbol.as_deref(), Some("AAPL"));
+        assert_eq!((candle.volume, candle.interval), (300, None));
+    }
+}
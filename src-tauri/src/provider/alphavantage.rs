@@ -0,0 +1,528 @@
+// src-tauri/src/provider/alphavantage.rs
+// Alpha Vantage historical options, earnings calendar and Treasury yields (CSV endpoints)
+// and dividend history (JSON)
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::Manager; // brings .path() into scope for AppHandle
+use crate::market_data::dates;
+use crate::storage::cache::{
+    cache_key_for_dividends, cache_key_for_earnings, cache_key_for_option_chain, cache_key_for_treasury_yield, FileCache,
+};
+
+const BASE_URL: &str = "https://www.alphavantage.co/query";
+const CACHE_TTL_SECONDS: i64 = 86400; // 24 hours
+
+/// Treasury maturities read for the risk-free curve, with their tenors in years
+pub const TREASURY_MATURITIES: [(&str, f64); 5] = [("3month", 0.25), ("2year", 2.0), ("5year", 5.0), ("10year", 10.0), ("30year", 30.0)];
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OptionContract {
+    pub symbol: String,
+    pub strike: f64,
+    #[serde(rename = "expiry_date", alias = "expiry", deserialize_with = "dates::lenient")]
+    pub expiry: NaiveDate,
+    pub option_type: String, // "call" or "put"
+    pub last_price: Option<f64>,
+    pub mark: Option<f64>,
+    pub bid: Option<f64>,
+    pub ask: Option<f64>,
+    pub volume: Option<i64>,
+    pub open_interest: Option<i64>,
+    pub implied_volatility: Option<f64>,
+    pub delta: Option<f64>,
+    pub gamma: Option<f64>,
+    pub theta: Option<f64>,
+    pub vega: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OptionChain {
+    pub underlying_symbol: String,
+    #[serde(alias = "as_of_date", deserialize_with = "dates::lenient")]
+    pub as_of: NaiveDate,
+    #[serde(alias = "expiry_dates", deserialize_with = "dates::lenient_vec")]
+    pub expiries: Vec<NaiveDate>, // Ascending
+    pub strikes: Vec<f64>,
+    pub contracts: HashMap<String, OptionContract>, // key: contract symbol
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EarningsEvent {
+    pub symbol: String,
+    pub name: String,
+    #[serde(alias = "report_date", deserialize_with = "dates::lenient")]
+    pub report_on: NaiveDate,
+    #[serde(alias = "fiscal_date_ending", deserialize_with = "dates::lenient")]
+    pub fiscal_period_end: NaiveDate,
+    pub estimate: Option<f64>,
+    pub currency: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DividendEvent {
+    pub symbol: String,
+    #[serde(deserialize_with = "dates::lenient")]
+    pub ex_date: NaiveDate,
+    pub amount: f64, // Per share
+    #[serde(default)]
+    pub payment_date: Option<NaiveDate>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TreasuryYield {
+    pub maturity: String, // e.g. "10year"
+    pub tenor_years: f64,
+    pub yield_pct: f64, // As quoted, e.g. 4.25
+    #[serde(deserialize_with = "dates::lenient")]
+    pub as_of: NaiveDate,
+}
+
+fn app_cache_dir(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    Ok(app
+        .path()
+        .app_cache_dir()
+        .map_err(|e| e.to_string())?
+        .join("trading-app"))
+}
+
+async fn read_key(app: &tauri::AppHandle) -> Result<String, String> {
+    if let Ok(k) = std::env::var("ALPHAVANTAGE_API_KEY") {
+        if !k.is_empty() {
+            return Ok(k);
+        }
+    }
+    let secrets = app_cache_dir(app)?.join("secrets.json");
+    if secrets.exists() {
+        let text = std::fs::read_to_string(&secrets).map_err(|e| e.to_string())?;
+        let v: serde_json::Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        if let Some(k) = v.get("alphavantage").and_then(|x| x.as_str()) {
+            return Ok(k.to_string());
+        }
+    }
+    Err("Alpha Vantage API key not set. Save it in settings or set ALPHAVANTAGE_API_KEY".into())
+}
+
+pub async fn save_alphavantage_key(app: &tauri::AppHandle, key: String) -> Result<(), String> {
+    let dir = app_cache_dir(app)?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join("secrets.json");
+    let mut obj = if path.exists() {
+        serde_json::from_str::<serde_json::Value>(&std::fs::read_to_string(&path).map_err(|e| e.to_string())?)
+            .unwrap_or(serde_json::json!({}))
+    } else {
+        serde_json::json!({})
+    };
+    obj["alphavantage"] = serde_json::Value::String(key);
+    std::fs::write(path, serde_json::to_string_pretty(&obj).unwrap()).map_err(|e| e.to_string())
+}
+
+/// Alpha Vantage reports missing numbers as "N/A", "None", "-" or an empty cell
+fn parse_av_f64(s: &str) -> Option<f64> {
+    match s.trim() {
+        "" | "N/A" | "NA" | "None" | "null" | "-" => None,
+        v => v.parse::<f64>().ok().filter(|x| x.is_finite()),
+    }
+}
+
+fn parse_av_i64(s: &str) -> Option<i64> {
+    parse_av_f64(s).map(|v| v as i64)
+}
+
+fn api_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+/// AV returns HTTP 200 with a JSON note when throttled or the key is invalid
+fn check_av_error(text: &str) -> Result<(), String> {
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('{') {
+        let v: serde_json::Value = serde_json::from_str(trimmed).map_err(|e| e.to_string())?;
+        for field in ["Error Message", "Note", "Information"] {
+            if let Some(msg) = v.get(field).and_then(|x| x.as_str()) {
+                return Err(format!("Alpha Vantage error: {}", msg));
+            }
+        }
+        return Err("Alpha Vantage returned JSON where CSV was expected".into());
+    }
+    Ok(())
+}
+
+fn column_index(headers: &csv::StringRecord) -> HashMap<String, usize> {
+    headers
+        .iter()
+        .enumerate()
+        .map(|(i, h)| (h.trim().to_string(), i))
+        .collect()
+}
+
+/// Chain for the session `as_of`; the latest session, read from the rows, when None
+pub fn parse_option_chain_csv(symbol: &str, as_of: Option<NaiveDate>, text: &str) -> Result<OptionChain, String> {
+    check_av_error(text)?;
+
+    let mut rdr = csv::Reader::from_reader(text.as_bytes());
+    let headers = rdr.headers().map_err(|e| e.to_string())?.clone();
+    let cols = column_index(&headers);
+    for required in ["contractID", "expiration", "strike", "type"] {
+        if !cols.contains_key(required) {
+            return Err(format!("Alpha Vantage options CSV missing column: {}", required));
+        }
+    }
+
+    let field = |r: &csv::StringRecord, name: &str| -> String {
+        cols.get(name).and_then(|&i| r.get(i)).unwrap_or("").to_string()
+    };
+
+    let mut contracts = HashMap::new();
+    let mut expiries: Vec<NaiveDate> = Vec::new();
+    let mut strikes: Vec<f64> = Vec::new();
+    let mut session: Option<NaiveDate> = None;
+
+    for rec in rdr.records() {
+        let r = rec.map_err(|e| e.to_string())?;
+        let contract_id = field(&r, "contractID");
+        let strike = match parse_av_f64(&field(&r, "strike")) {
+            Some(k) => k,
+            None => continue, // A contract without a strike is unusable
+        };
+        let expiry = match dates::parse(&field(&r, "expiration")) {
+            Ok(d) => d,
+            Err(_) => continue,
+        };
+        session = session.or_else(|| dates::parse(&field(&r, "date")).ok());
+
+        if !expiries.contains(&expiry) {
+            expiries.push(expiry);
+        }
+        if !strikes.iter().any(|k| (k - strike).abs() < 1e-9) {
+            strikes.push(strike);
+        }
+
+        contracts.insert(contract_id.clone(), OptionContract {
+            symbol: contract_id,
+            strike,
+            expiry,
+            option_type: field(&r, "type").to_lowercase(),
+            last_price: parse_av_f64(&field(&r, "last")),
+            mark: parse_av_f64(&field(&r, "mark")),
+            bid: parse_av_f64(&field(&r, "bid")),
+            ask: parse_av_f64(&field(&r, "ask")),
+            volume: parse_av_i64(&field(&r, "volume")),
+            open_interest: parse_av_i64(&field(&r, "open_interest")),
+            implied_volatility: parse_av_f64(&field(&r, "implied_volatility")),
+            delta: parse_av_f64(&field(&r, "delta")),
+            gamma: parse_av_f64(&field(&r, "gamma")),
+            theta: parse_av_f64(&field(&r, "theta")),
+            vega: parse_av_f64(&field(&r, "vega")),
+        });
+    }
+
+    expiries.sort();
+    strikes.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    Ok(OptionChain {
+        underlying_symbol: symbol.to_uppercase(),
+        as_of: as_of.or(session).unwrap_or_else(|| chrono::Utc::now().date_naive()),
+        expiries,
+        strikes,
+        contracts,
+    })
+}
+
+pub fn parse_earnings_csv(text: &str) -> Result<Vec<EarningsEvent>, String> {
+    check_av_error(text)?;
+
+    let mut rdr = csv::Reader::from_reader(text.as_bytes());
+    let headers = rdr.headers().map_err(|e| e.to_string())?.clone();
+    let cols = column_index(&headers);
+    if !cols.contains_key("symbol") || !cols.contains_key("reportDate") {
+        return Err("Alpha Vantage earnings CSV missing symbol/reportDate columns".into());
+    }
+
+    let field = |r: &csv::StringRecord, name: &str| -> String {
+        cols.get(name).and_then(|&i| r.get(i)).unwrap_or("").trim().to_string()
+    };
+
+    let mut out = vec![];
+    for rec in rdr.records() {
+        let r = rec.map_err(|e| e.to_string())?;
+        let (Ok(report_on), Ok(fiscal_period_end)) = (dates::parse(&field(&r, "reportDate")), dates::parse(&field(&r, "fiscalDateEnding"))) else {
+            continue; // An event without its dates cannot be placed
+        };
+        out.push(EarningsEvent {
+            symbol: field(&r, "symbol"),
+            name: field(&r, "name"),
+            report_on,
+            fiscal_period_end,
+            estimate: parse_av_f64(&field(&r, "estimate")),
+            currency: field(&r, "currency"),
+        });
+    }
+    Ok(out)
+}
+
+/// Latest quoted yield of a TREASURY_YIELD CSV (newest row first); holidays are blank
+pub fn parse_treasury_csv(maturity: &str, tenor_years: f64, text: &str) -> Result<TreasuryYield, String> {
+    check_av_error(text)?;
+
+    let mut rdr = csv::Reader::from_reader(text.as_bytes());
+    let headers = rdr.headers().map_err(|e| e.to_string())?.clone();
+    let cols = column_index(&headers);
+    let (Some(&date_col), Some(&value_col)) = (cols.get("timestamp"), cols.get("value")) else {
+        return Err("Alpha Vantage Treasury CSV missing timestamp/value columns".into());
+    };
+
+    for rec in rdr.records() {
+        let r = rec.map_err(|e| e.to_string())?;
+        let as_of = dates::parse(r.get(date_col).unwrap_or(""));
+        let value = parse_av_f64(r.get(value_col).unwrap_or(""));
+        if let (Ok(as_of), Some(yield_pct)) = (as_of, value) {
+            return Ok(TreasuryYield { maturity: maturity.to_string(), tenor_years, yield_pct, as_of });
+        }
+    }
+    Err(format!("No {} Treasury yield in the Alpha Vantage response", maturity))
+}
+
+/// Dividends of a DIVIDENDS response, oldest first. Rows without an ex-date or amount are dropped.
+pub fn parse_dividends_json(symbol: &str, text: &str) -> Result<Vec<DividendEvent>, String> {
+    let v: serde_json::Value = serde_json::from_str(text.trim()).map_err(|e| format!("Alpha Vantage dividends: {}", e))?;
+    for field in ["Error Message", "Note", "Information"] {
+        if let Some(msg) = v.get(field).and_then(|x| x.as_str()) {
+            return Err(format!("Alpha Vantage error: {}", msg));
+        }
+    }
+    let rows = v.get("data").and_then(|d| d.as_array()).ok_or("Alpha Vantage dividends response has no data")?;
+
+    let text_of = |row: &serde_json::Value, name: &str| row.get(name).and_then(|x| x.as_str()).unwrap_or("").to_string();
+    let mut out: Vec<DividendEvent> = rows
+        .iter()
+        .filter_map(|row| {
+            Some(DividendEvent {
+                symbol: symbol.to_uppercase(),
+                ex_date: dates::parse(&text_of(row, "ex_dividend_date")).ok()?,
+                amount: parse_av_f64(&text_of(row, "amount")).filter(|a| *a > 0.0)?,
+                payment_date: dates::parse(&text_of(row, "payment_date")).ok(),
+            })
+        })
+        .collect();
+    out.sort_by_key(|d| d.ex_date);
+    Ok(out)
+}
+
+async fn fetch_csv(url: &str) -> Result<String, String> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let resp = client.get(url).send().await.map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("Alpha Vantage error: {}", resp.status()));
+    }
+    resp.text().await.map_err(|e| e.to_string())
+}
+
+pub async fn fetch_option_chain(
+    app: &tauri::AppHandle,
+    symbol: String,
+    date: Option<NaiveDate>, // Latest session when omitted
+) -> Result<OptionChain, String> {
+    let symbol = symbol.to_uppercase();
+    let cache_key = cache_key_for_option_chain(&symbol, &date.map(api_date).unwrap_or_default());
+
+    let mut cache = FileCache::new(app)?;
+    if let Ok(Some(chain)) = cache.get::<OptionChain>(&cache_key) {
+        return Ok(chain);
+    }
+
+    let key = read_key(app).await?;
+    let mut url = format!(
+        "{}?function=HISTORICAL_OPTIONS&symbol={}&datatype=csv&apikey={}",
+        BASE_URL, symbol, key
+    );
+    if let Some(d) = date {
+        url.push_str(&format!("&date={}", api_date(d)));
+    }
+
+    let text = fetch_csv(&url).await?;
+    let chain = parse_option_chain_csv(&symbol, date, &text)?;
+
+    if let Err(e) = cache.set(&cache_key, &chain, Some(CACHE_TTL_SECONDS)) {
+        eprintln!("Failed to cache option chain: {}", e);
+    }
+    Ok(chain)
+}
+
+pub async fn fetch_earnings_calendar(
+    app: &tauri::AppHandle,
+    symbol: Option<String>,
+    horizon: Option<String>, // "3month" | "6month" | "12month"
+) -> Result<Vec<EarningsEvent>, String> {
+    let symbol = symbol.map(|s| s.to_uppercase());
+    let horizon = horizon.unwrap_or_else(|| "3month".to_string());
+    let cache_key = cache_key_for_earnings(symbol.as_deref().unwrap_or("ALL"), &horizon);
+
+    let mut cache = FileCache::new(app)?;
+    if let Ok(Some(events)) = cache.get::<Vec<EarningsEvent>>(&cache_key) {
+        return Ok(events);
+    }
+
+    let key = read_key(app).await?;
+    let mut url = format!("{}?function=EARNINGS_CALENDAR&horizon={}&apikey={}", BASE_URL, horizon, key);
+    if let Some(s) = symbol.as_deref() {
+        url.push_str(&format!("&symbol={}", s));
+    }
+
+    let text = fetch_csv(&url).await?;
+    let events = parse_earnings_csv(&text)?;
+
+    if let Err(e) = cache.set(&cache_key, &events, Some(CACHE_TTL_SECONDS)) {
+        eprintln!("Failed to cache earnings calendar: {}", e);
+    }
+    Ok(events)
+}
+
+/// Latest yield of each of TREASURY_MATURITIES, shortest first
+pub async fn fetch_treasury_yields(app: &tauri::AppHandle) -> Result<Vec<TreasuryYield>, String> {
+    let mut cache = FileCache::new(app)?;
+    let mut key = None;
+    let mut yields = Vec::with_capacity(TREASURY_MATURITIES.len());
+    for (maturity, tenor_years) in TREASURY_MATURITIES {
+        let cache_key = cache_key_for_treasury_yield(maturity);
+        if let Ok(Some(cached)) = cache.get::<TreasuryYield>(&cache_key) {
+            yields.push(cached);
+            continue;
+        }
+
+        if key.is_none() {
+            key = Some(read_key(app).await?);
+        }
+        let url = format!(
+            "{}?function=TREASURY_YIELD&interval=daily&maturity={}&datatype=csv&apikey={}",
+            BASE_URL,
+            maturity,
+            key.as_deref().unwrap_or_default()
+        );
+        let text = fetch_csv(&url).await?;
+        let latest = parse_treasury_csv(maturity, tenor_years, &text)?;
+
+        if let Err(e) = cache.set(&cache_key, &latest, Some(CACHE_TTL_SECONDS)) {
+            eprintln!("Failed to cache Treasury yield: {}", e);
+        }
+        yields.push(latest);
+    }
+    Ok(yields)
+}
+
+pub async fn fetch_dividends(app: &tauri::AppHandle, symbol: String) -> Result<Vec<DividendEvent>, String> {
+    let symbol = symbol.to_uppercase();
+    let cache_key = cache_key_for_dividends(&symbol);
+
+    let mut cache = FileCache::new(app)?;
+    if let Ok(Some(events)) = cache.get::<Vec<DividendEvent>>(&cache_key) {
+        return Ok(events);
+    }
+
+    let key = read_key(app).await?;
+    let url = format!("{}?function=DIVIDENDS&symbol={}&apikey={}", BASE_URL, symbol, key);
+
+    let text = fetch_csv(&url).await?;
+    let events = parse_dividends_json(&symbol, &text)?;
+
+    if let Err(e) = cache.set(&cache_key, &events, Some(CACHE_TTL_SECONDS)) {
+        eprintln!("Failed to cache dividends: {}", e);
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    const OPTIONS_CSV: &str = "\
+contractID,symbol,expiration,strike,type,last,mark,bid,bid_size,ask,ask_size,volume,open_interest,date,implied_volatility,delta,gamma,theta,vega,rho
+AAPL240315C00150000,AAPL,2024-03-15,150.00,call,21.50,21.45,21.30,10,21.60,12,1520,8800,2024-02-01,0.2712,0.8123,0.0124,-0.0451,0.1210,0.0833
+AAPL240315P00150000,AAPL,2024-03-15,150.00,put,N/A,0.85,0.80,5,0.90,7,None,4100,2024-02-01,0.2950,-0.1877,0.0124,-0.0398,0.1180,-0.0210
+AAPL240419C00160000,AAPL,2024-04-19,160.00,call,,12.10,-,0,12.30,3,0,0,2024-02-01,N/A,N/A,N/A,N/A,N/A,N/A
+";
+
+    #[test]
+    fn test_parse_option_chain_csv() {
+        let chain = parse_option_chain_csv("aapl", None, OPTIONS_CSV).unwrap();
+
+        assert_eq!(chain.underlying_symbol, "AAPL");
+        assert_eq!(chain.as_of, date(2024, 2, 1));
+        assert_eq!(chain.contracts.len(), 3);
+        assert_eq!(chain.expiries, vec![date(2024, 3, 15), date(2024, 4, 19)]);
+        assert_eq!(chain.strikes, vec![150.0, 160.0]);
+
+        let call = &chain.contracts["AAPL240315C00150000"];
+        assert_eq!(call.option_type, "call");
+        assert_eq!(call.expiry, date(2024, 3, 15));
+        assert_eq!(call.last_price, Some(21.50));
+        assert_eq!(call.volume, Some(1520));
+        assert_eq!(call.implied_volatility, Some(0.2712));
+    }
+
+    #[test]
+    fn test_parse_missing_values() {
+        let chain = parse_option_chain_csv("AAPL", Some(date(2024, 2, 1)), OPTIONS_CSV).unwrap();
+
+        let put = &chain.contracts["AAPL240315P00150000"];
+        assert_eq!(put.last_price, None);
+        assert_eq!(put.volume, None);
+        assert_eq!(put.open_interest, Some(4100));
+        assert_eq!(put.delta, Some(-0.1877));
+
+        let far_call = &chain.contracts["AAPL240419C00160000"];
+        assert_eq!(far_call.last_price, None);
+        assert_eq!(far_call.bid, None);
+        assert_eq!(far_call.mark, Some(12.10));
+        assert_eq!(far_call.implied_volatility, None);
+        assert_eq!(far_call.vega, None);
+    }
+
+    #[test]
+    fn test_parse_earnings_csv() {
+        let csv = "\
+symbol,name,reportDate,fiscalDateEnding,estimate,currency
+AAPL,Apple Inc,2024-05-02,2024-03-31,1.50,USD
+XYZ,Example Corp,2024-05-09,2024-03-31,,USD
+";
+        let events = parse_earnings_csv(csv).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].report_on, date(2024, 5, 2));
+        assert_eq!(events[0].fiscal_period_end, date(2024, 3, 31));
+        assert_eq!(events[0].estimate, Some(1.50));
+        assert_eq!(events[1].estimate, None);
+    }
+
+    #[test]
+    fn test_parse_treasury_and_dividends() {
+        let csv = "timestamp,value\n2024-07-04,.\n2024-07-03,4.36\n2024-07-02,4.43\n";
+        let ten_year = parse_treasury_csv("10year", 10.0, csv).unwrap();
+        assert_eq!((ten_year.as_of, ten_year.yield_pct), (date(2024, 7, 3), 4.36));
+
+        let json = r#"{"symbol": "XOM", "data": [
+            {"ex_dividend_date": "2024-05-14", "declaration_date": "2024-04-26", "record_date": "2024-05-15", "payment_date": "2024-06-10", "amount": "0.95"},
+            {"ex_dividend_date": "2024-02-13", "declaration_date": "2024-02-02", "record_date": "2024-02-14", "payment_date": "None", "amount": "0.95"},
+            {"ex_dividend_date": "None", "declaration_date": "None", "record_date": "None", "payment_date": "None", "amount": "0.91"}
+        ]}"#;
+        let dividends = parse_dividends_json("xom", json).unwrap();
+        assert_eq!(dividends.len(), 2);
+        assert_eq!((dividends[0].ex_date, dividends[0].payment_date), (date(2024, 2, 13), None));
+        assert_eq!((dividends[1].symbol.as_str(), dividends[1].amount), ("XOM", 0.95));
+        assert!(parse_dividends_json("XOM", r#"{"Information": "premium endpoint"}"#).unwrap_err().contains("premium"));
+    }
+
+    #[test]
+    fn test_av_error_payload() {
+        let throttled = r#"{"Note": "Thank you for using Alpha Vantage! Our standard API rate limit is 25 requests per day."}"#;
+        let err = parse_option_chain_csv("AAPL", None, throttled).unwrap_err();
+        assert!(err.contains("rate limit"));
+    }
+}
@@ -0,0 +1,215 @@
+// src-tauri/src/provider/diagnostics.rs
+// Parsing of provider responses that reports where a response stopped matching the
+// expected schema. Failures keep the start of the raw response in provider_errors/ in
+// the cache dir; top-level keys nobody expected are noted in unknown_fields.json there.
+
+use super::entitlements::EntitlementProfile;
+use crate::storage::cache::FileCache;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const PARSE_ERRORS_DIR: &str = "provider_errors";
+const UNKNOWN_FIELDS_FILE: &str = "unknown_fields.json";
+const CAPTURE_BYTES: usize = 16 * 1024;
+const MAX_CAPTURES: usize = 50;
+
+/// Where a parsed response broke an invariant, as a JSON path ("results[3].t")
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseFailure {
+    pub path: String,
+    pub message: String,
+}
+
+impl ParseFailure {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { path: path.into(), message: message.into() }
+    }
+}
+
+/// A provider response body with the top-level keys the provider is known to send and
+/// the invariants a well-formed response keeps
+pub trait ProviderResponse: DeserializeOwned {
+    const KNOWN_FIELDS: &'static [&'static str];
+
+    fn validate(&self) -> Result<(), ParseFailure> {
+        Ok(())
+    }
+}
+
+/// A response that did not parse, or parsed but failed validation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ParseCapture {
+    pub id: String, // File stem in provider_errors/
+    pub provider: String,
+    pub endpoint: String,
+    pub captured_at: i64,
+    pub path: String,
+    pub error: String,
+    pub response_bytes: usize,
+    pub raw: String, // At most the first 16 KB
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ProviderDiagnostics {
+    pub captures: Vec<ParseCapture>,                       // Newest first
+    pub unknown_fields: BTreeMap<String, BTreeSet<String>>, // "provider/endpoint" -> keys
+    #[serde(default)]
+    pub entitlements: Option<EntitlementProfile>, // What the Polygon key's plan covers, once probed
+}
+
+/// provider_errors/ in the app cache dir
+pub fn capture_dir(app: &tauri::AppHandle) -> Option<PathBuf> {
+    FileCache::new(app).ok().map(|cache| cache.cache_dir().join(PARSE_ERRORS_DIR))
+}
+
+/// Parse `text` as `T`, then validate it. On failure the raw response is captured in
+/// `capture_dir` (when there is one) and the error names the JSON path that broke.
+pub fn parse_response<T: ProviderResponse>(
+    provider: &str,
+    endpoint: &str,
+    text: &str,
+    capture_dir: Option<&Path>,
+) -> Result<T, String> {
+    let parsed = serde_path_to_error::deserialize::<_, T>(&mut serde_json::Deserializer::from_str(text))
+        .map_err(|e| ParseFailure::new(e.path().to_string(), e.inner().to_string()))
+        .and_then(|parsed| parsed.validate().map(|_| parsed));
+
+    match parsed {
+        Ok(parsed) => {
+            if let Some(dir) = capture_dir {
+                note_unknown_fields::<T>(dir, provider, endpoint, text);
+            }
+            Ok(parsed)
+        }
+        Err(failure) => Err(report_failure(provider, endpoint, text, &failure, capture_dir)),
+    }
+}
+
+/// Capture `text` and describe `failure` for the caller
+pub fn report_failure(provider: &str, endpoint: &str, text: &str, failure: &ParseFailure, capture_dir: Option<&Path>) -> String {
+    let mut error = format!("{} {} response changed shape at `{}`: {}", provider, endpoint, failure.path, failure.message);
+    if let Some(dir) = capture_dir {
+        match capture(dir, provider, endpoint, text, failure, chrono::Utc::now().timestamp()) {
+            Ok(capture) => error.push_str(&format!(" (response saved as {}/{}.json)", PARSE_ERRORS_DIR, capture.id)),
+            Err(e) => eprintln!("Failed to save unparseable {} response: {}", provider, e),
+        }
+    }
+    error
+}
+
+/// Top-level keys of `text` that `T` does not know about
+pub fn unknown_fields<T: ProviderResponse>(text: &str) -> Vec<String> {
+    serde_json::from_str::<BTreeMap<String, IgnoredAny>>(text)
+        .map(|keys| keys.into_keys().filter(|k| !T::KNOWN_FIELDS.contains(&k.as_str())).collect())
+        .unwrap_or_default()
+}
+
+fn note_unknown_fields<T: ProviderResponse>(dir: &Path, provider: &str, endpoint: &str, text: &str) {
+    let unknown = unknown_fields::<T>(text);
+    if unknown.is_empty() {
+        return;
+    }
+    let path = dir.join(UNKNOWN_FIELDS_FILE);
+    let mut seen: BTreeMap<String, BTreeSet<String>> =
+        fs::read_to_string(&path).ok().and_then(|text| serde_json::from_str(&text).ok()).unwrap_or_default();
+    let keys = seen.entry(format!("{}/{}", provider, endpoint)).or_default();
+    let before = keys.len();
+    keys.extend(unknown);
+    if keys.len() == before {
+        return;
+    }
+    eprintln!("{} {} response has new fields: {:?}", provider, endpoint, keys);
+    let result = fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&path, serde_json::to_string(&seen).unwrap_or_default()));
+    if let Err(e) = result {
+        eprintln!("Failed to record unknown {} fields: {}", provider, e);
+    }
+}
+
+/// Save the start of an unparseable response, dropping the oldest captures beyond
+/// MAX_CAPTURES
+pub fn capture(dir: &Path, provider: &str, endpoint: &str, text: &str, failure: &ParseFailure, now: i64) -> Result<ParseCapture, String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let mut end = text.len().min(CAPTURE_BYTES);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let id = format!("{}_{}_{}_{}", now, provider, endpoint, &uuid::Uuid::new_v4().simple().to_string()[..8]);
+    let capture = ParseCapture {
+        id: id.clone(),
+        provider: provider.to_string(),
+        endpoint: endpoint.to_string(),
+        captured_at: now,
+        path: failure.path.clone(),
+        error: failure.message.clone(),
+        response_bytes: text.len(),
+        raw: text[..end].to_string(),
+    };
+    let json = serde_json::to_string_pretty(&capture).map_err(|e| e.to_string())?;
+    fs::write(dir.join(format!("{}.json", id)), json).map_err(|e| e.to_string())?;
+
+    for old in list_captures(dir)?.into_iter().skip(MAX_CAPTURES) {
+        fs::remove_file(dir.join(format!("{}.json", old.id))).ok();
+    }
+    Ok(capture)
+}
+
+/// Captures in `dir`, newest first
+pub fn list_captures(dir: &Path) -> Result<Vec<ParseCapture>, String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let mut captures: Vec<ParseCapture> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.file_name().and_then(|n| n.to_str()) != Some(UNKNOWN_FIELDS_FILE))
+        .filter_map(|p| fs::read_to_string(p).ok())
+        .filter_map(|text| serde_json::from_str(&text).ok())
+        .collect();
+    captures.sort_by(|a: &ParseCapture, b| (b.captured_at, &b.id).cmp(&(a.captured_at, &a.id)));
+    Ok(captures)
+}
+
+pub fn load_diagnostics(dir: &Path) -> Result<ProviderDiagnostics, String> {
+    let unknown_fields = fs::read_to_string(dir.join(UNKNOWN_FIELDS_FILE))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default();
+    Ok(ProviderDiagnostics { captures: list_captures(dir)?, unknown_fields, entitlements: None })
+}
+
+/// `status` must be one of `recognized`; an error status carries the provider's message
+pub fn check_status(status: Option<&str>, recognized: &[&str], message: Option<&str>) -> Result<(), ParseFailure> {
+    match status {
+        Some(status) if !recognized.contains(&status) => Err(ParseFailure::new(
+            "status",
+            format!("unrecognized status {:?}{}", status, message.map(|m| format!(": {}", m)).unwrap_or_default()),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// A reported count must match the array it counts
+pub fn check_count(field: &str, reported: Option<u64>, actual: usize) -> Result<(), ParseFailure> {
+    match reported {
+        Some(reported) if reported != actual as u64 => {
+            Err(ParseFailure::new(field, format!("reports {} results but {} were sent", reported, actual)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Timestamps must strictly increase; `path` formats the offending element's path
+pub fn check_monotonic(timestamps: impl Iterator<Item = i64>, path: impl Fn(usize) -> String) -> Result<(), ParseFailure> {
+    let mut last: Option<i64> = None;
+    for (i, t) in timestamps.enumerate() {
+        if let Some(previous) = last.filter(|previous| t <= *previous) {
+            return Err(ParseFailure::new(path(i), format!("timestamp {} does not follow {}", t, previous)));
+        }
+        last = Some(t);
+    }
+    Ok(())
+}
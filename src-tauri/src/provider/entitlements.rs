@@ -0,0 +1,268 @@
+// src-tauri/src/provider/entitlements.rs
+// What the configured Polygon key's plan covers, read from a handful of probe requests
+// when the key is saved or tested. Free and lower-tier keys answer endpoints outside
+// their plan with 403s or empty results; the profile lets the stream, option chain and
+// backtest paths say so up front instead. It is cached under the key's last four
+// characters, so a different key is treated as unprobed.
+
+use crate::storage::cache::FileCache;
+use chrono::{Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+pub const ENTITLEMENTS_CACHE_KEY: &str = "polygon_entitlements";
+pub const PROBE_SYMBOL: &str = "AAPL";
+pub const STOCKS_PROBE_YEARS: u32 = 1; // The stocks probe asks for bars this far back
+pub const HISTORY_PROBE_YEARS: [u32; 4] = [2, 5, 10, 20]; // Deeper daily windows, shallowest first
+
+pub const PROBE_STOCKS: &str = "stocks";
+pub const PROBE_LAST_TRADE: &str = "last_trade";
+pub const PROBE_OPTIONS: &str = "options";
+pub const PROBE_FOREX: &str = "forex";
+pub const PROBE_CRYPTO: &str = "crypto";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeOutcome {
+    Allowed,
+    Delayed,      // Answered, with status "DELAYED"
+    Denied,       // 401/403 or NOT_AUTHORIZED
+    Empty,        // Answered without results
+    Inconclusive, // Rate limited, network or server error; says nothing about the plan
+}
+
+impl ProbeOutcome {
+    /// Whether the plan covers the probed data; None when the probe could not tell
+    fn access(self) -> Option<bool> {
+        match self {
+            ProbeOutcome::Allowed | ProbeOutcome::Delayed => Some(true),
+            ProbeOutcome::Denied | ProbeOutcome::Empty => Some(false),
+            ProbeOutcome::Inconclusive => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Probe {
+    pub name: String, // PROBE_* or "history_{years}y"
+    pub outcome: ProbeOutcome,
+    pub detail: String,
+}
+
+/// Name of the daily history probe `years` back
+pub fn history_probe(years: u32) -> String {
+    format!("history_{}y", years)
+}
+
+/// Read a probe's HTTP status and body
+pub fn classify_response(status: u16, body: &str) -> (ProbeOutcome, String) {
+    let json: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+    let message = json
+        .get("message")
+        .or_else(|| json.get("error"))
+        .and_then(|m| m.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("HTTP {}", status));
+    match status {
+        401 | 403 => return (ProbeOutcome::Denied, message),
+        429 => return (ProbeOutcome::Inconclusive, "Rate limited; probe again in a minute".to_string()),
+        200..=299 => {}
+        _ => return (ProbeOutcome::Inconclusive, message),
+    }
+    match json.get("status").and_then(|s| s.as_str()) {
+        Some("NOT_AUTHORIZED") => return (ProbeOutcome::Denied, message),
+        Some("ERROR") => return (ProbeOutcome::Inconclusive, message),
+        _ => {}
+    }
+    let results = match json.get("results") {
+        Some(serde_json::Value::Array(results)) => results.len(),
+        Some(serde_json::Value::Object(_)) => 1,
+        _ => 0,
+    };
+    if results == 0 {
+        return (ProbeOutcome::Empty, "No results".to_string());
+    }
+    match json.get("status").and_then(|s| s.as_str()) {
+        Some("DELAYED") => (ProbeOutcome::Delayed, "Delayed data".to_string()),
+        _ => (ProbeOutcome::Allowed, "OK".to_string()),
+    }
+}
+
+/// Data a feature needs from the plan
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Entitlement {
+    RealtimeStream,
+    Stocks,
+    Options,
+    Forex,
+    Crypto,
+}
+
+/// What a key's plan covers. Capabilities are None when their probe was inconclusive;
+/// only a definite false blocks a feature.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EntitlementProfile {
+    pub key_suffix: String, // Last four characters of the probed key
+    pub probed_at: i64,
+    pub realtime: Option<bool>,
+    pub stocks: Option<bool>,
+    pub options: Option<bool>,
+    pub forex: Option<bool>,
+    pub crypto: Option<bool>,
+    pub history_years: Option<u32>, // Deepest daily lookback that returned bars
+    pub history_capped: bool,       // A deeper window was refused, so history_years is the plan's limit
+    pub probes: Vec<Probe>,
+}
+
+impl EntitlementProfile {
+    pub fn from_probes(key: &str, probed_at: i64, probes: Vec<Probe>) -> Self {
+        let outcome = |name: &str| probes.iter().find(|p| p.name == name).map(|p| p.outcome);
+        let access = |name: &str| outcome(name).and_then(ProbeOutcome::access);
+
+        let stocks = access(PROBE_STOCKS);
+        // Delayed plans answer bars with status "DELAYED" and refuse the last trade
+        let realtime = match (outcome(PROBE_STOCKS), outcome(PROBE_LAST_TRADE)) {
+            (Some(ProbeOutcome::Delayed), _) => Some(false),
+            (_, Some(ProbeOutcome::Allowed)) => Some(true),
+            (_, Some(ProbeOutcome::Inconclusive)) | (_, None) => None,
+            (_, Some(_)) => Some(false),
+        };
+
+        let mut history_years = (stocks == Some(true)).then_some(STOCKS_PROBE_YEARS);
+        let mut history_capped = stocks == Some(false);
+        for years in HISTORY_PROBE_YEARS {
+            match access(&history_probe(years)) {
+                Some(true) => history_years = Some(years),
+                Some(false) => {
+                    history_capped = true;
+                    break;
+                }
+                None => break,
+            }
+        }
+
+        Self {
+            key_suffix: key_suffix(key),
+            probed_at,
+            realtime,
+            stocks,
+            options: access(PROBE_OPTIONS),
+            forex: access(PROBE_FOREX),
+            crypto: access(PROBE_CRYPTO),
+            history_years,
+            history_capped,
+            probes,
+        }
+    }
+
+    /// Whether this profile was probed with `key`
+    pub fn is_for(&self, key: &str) -> bool {
+        self.key_suffix == key_suffix(key)
+    }
+
+    /// Err with what the plan lacks when it definitely does not cover `entitlement`
+    pub fn check(&self, entitlement: Entitlement) -> Result<(), String> {
+        let (covered, missing) = match entitlement {
+            Entitlement::RealtimeStream => (self.realtime, "Your Polygon key does not include realtime websockets (delayed data only)"),
+            Entitlement::Stocks => (self.stocks, "Stock data is not included in your Polygon plan"),
+            Entitlement::Options => (self.options, "Options data is not included in your Polygon plan"),
+            Entitlement::Forex => (self.forex, "Forex data is not included in your Polygon plan"),
+            Entitlement::Crypto => (self.crypto, "Crypto data is not included in your Polygon plan"),
+        };
+        if covered == Some(false) {
+            return Err(missing.to_string());
+        }
+        Ok(())
+    }
+
+    /// Warning when daily history from `start` reaches past the plan's lookback
+    pub fn history_warning(&self, start: NaiveDate, today: NaiveDate) -> Option<String> {
+        let years = self.history_years.filter(|_| self.history_capped)?;
+        let earliest = today.checked_sub_months(Months::new(12 * years))?;
+        (start < earliest).then(|| {
+            format!(
+                "Your Polygon plan covers about {} year{} of history (from {}); bars before that will come from Yahoo or be missing",
+                years,
+                if years == 1 { "" } else { "s" },
+                earliest
+            )
+        })
+    }
+}
+
+fn key_suffix(key: &str) -> String {
+    let chars: Vec<char> = key.trim().chars().collect();
+    chars[chars.len().saturating_sub(4)..].iter().collect()
+}
+
+/// The cached profile, whichever key it was probed with
+pub fn load(app: &tauri::AppHandle) -> Option<EntitlementProfile> {
+    FileCache::new(app).and_then(|mut cache| cache.get(ENTITLEMENTS_CACHE_KEY)).ok().flatten()
+}
+
+pub fn save(app: &tauri::AppHandle, profile: &EntitlementProfile) -> Result<(), String> {
+    FileCache::new(app).and_then(|mut cache| cache.set(ENTITLEMENTS_CACHE_KEY, profile, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(name: &str, status: u16, body: &str) -> Probe {
+        let (outcome, detail) = classify_response(status, body);
+        Probe { name: name.to_string(), outcome, detail }
+    }
+
+    const BARS: &str = r#"{"status":"OK","resultsCount":1,"results":[{"t":1,"o":1,"h":1,"l":1,"c":1,"v":1}]}"#;
+    const DENIED: &str = r#"{"status":"NOT_AUTHORIZED","message":"You are not entitled to this data. Please upgrade your plan."}"#;
+
+    #[test]
+    fn test_free_key_is_delayed_stocks_only_with_two_years() {
+        let probes = vec![
+            probe(PROBE_STOCKS, 200, BARS),
+            probe(PROBE_LAST_TRADE, 403, DENIED),
+            probe(PROBE_OPTIONS, 403, DENIED),
+            probe(&history_probe(2), 200, BARS),
+            probe(&history_probe(5), 200, r#"{"status":"OK","resultsCount":0}"#),
+            probe(PROBE_FOREX, 429, r#"{"status":"ERROR","error":"You've exceeded the maximum requests per minute"}"#),
+        ];
+        let profile = EntitlementProfile::from_probes("abcd1234WXYZ", 0, probes);
+
+        assert_eq!((profile.stocks, profile.realtime, profile.options), (Some(true), Some(false), Some(false)));
+        assert_eq!(profile.forex, None, "rate limited, not refused");
+        assert_eq!((profile.history_years, profile.history_capped), (Some(2), true));
+        assert_eq!(profile.key_suffix, "WXYZ");
+        assert!(profile.is_for("  other-WXYZ") && !profile.is_for("abcd1234"));
+
+        let stream = profile.check(Entitlement::RealtimeStream).unwrap_err();
+        assert!(stream.contains("realtime websockets (delayed data only)"), "{}", stream);
+        assert!(profile.check(Entitlement::Options).unwrap_err().contains("Options data is not included"));
+        assert!(profile.check(Entitlement::Forex).is_ok());
+
+        let today = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let warning = profile.history_warning(NaiveDate::from_ymd_opt(2020, 1, 2).unwrap(), today).unwrap();
+        assert!(warning.contains("2 years") && warning.contains("2022-06-03"), "{}", warning);
+        assert_eq!(profile.history_warning(NaiveDate::from_ymd_opt(2023, 1, 3).unwrap(), today), None);
+    }
+
+    #[test]
+    fn test_realtime_key_and_inconclusive_depths_warn_about_nothing() {
+        let probes = vec![
+            probe(PROBE_STOCKS, 200, BARS),
+            probe(PROBE_LAST_TRADE, 200, r#"{"status":"OK","results":{"p":190.1,"t":1}}"#),
+            probe(PROBE_OPTIONS, 200, r#"{"status":"OK","results":[{}]}"#),
+            probe(&history_probe(2), 200, BARS),
+            probe(&history_probe(5), 502, "Bad Gateway"),
+        ];
+        let profile = EntitlementProfile::from_probes("key", 0, probes);
+        assert_eq!((profile.realtime, profile.options), (Some(true), Some(true)));
+        assert_eq!((profile.history_years, profile.history_capped), (Some(2), false));
+        assert!(profile.check(Entitlement::RealtimeStream).is_ok());
+        let today = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        assert_eq!(profile.history_warning(NaiveDate::from_ymd_opt(2004, 1, 2).unwrap(), today), None);
+
+        // Bars marked DELAYED mean a delayed plan even if the last trade answered
+        let delayed = EntitlementProfile::from_probes("key", 0, vec![probe(PROBE_STOCKS, 200, &BARS.replace("OK", "DELAYED"))]);
+        assert_eq!((delayed.stocks, delayed.realtime), (Some(true), Some(false)));
+    }
+}
@@ -2,17 +2,9 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use async_trait::async_trait;
 
-pub mod polygon;
+use crate::market_data::types::Candle;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HistoryPoint {
-    pub date: String,  // MM/DD/YYYY format
-    pub open: f64,
-    pub high: f64,
-    pub low: f64,
-    pub close: f64,
-    pub volume: i64,
-}
+pub mod polygon;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptionContract {
@@ -95,7 +87,7 @@ pub trait Provider: Send + Sync {
         start_date: &str,  // MM/DD/YYYY
         end_date: &str,    // MM/DD/YYYY
         interval: &str,    // "1day", "1hour", etc.
-    ) -> ProviderResult<Vec<HistoryPoint>>;
+    ) -> ProviderResult<Vec<Candle>>;
 
     /// Fetch option chain for a symbol
     async fn fetch_option_chain(
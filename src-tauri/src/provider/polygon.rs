@@ -1,10 +1,22 @@
-use chrono::{NaiveDateTime, Utc};
+use super::alphavantage::{OptionChain, OptionContract};
+use super::diagnostics::{self, ParseFailure, ProviderResponse};
+use super::entitlements::{self, Entitlement, EntitlementProfile, Probe, ProbeOutcome};
+use crate::engine::calendar::MarketCalendar;
+use crate::engine::symbols::{normalize_symbol, provider_symbol, SymbolProvider};
+use crate::market_data::dates;
+use crate::market_data::types::{epoch_seconds, volume_from_f64, Candle};
+use crate::storage::api_budget::{self, ApiBudget, RequestEstimate};
+use crate::storage::cache::FileCache;
+use crate::storage::journal_store;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use tauri::Manager; // brings .path() into scope for AppHandle
+use std::collections::HashMap;
+use tauri::{Emitter, Manager}; // Manager brings .path() into scope for AppHandle
 
+/// History bar as the frontend receives it
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Bar {
-    pub date: String, // MM/DD/YYYY
+    pub timestamp: i64, // Epoch seconds
     pub o: f64,
     pub h: f64,
     pub l: f64,
@@ -12,11 +24,34 @@ pub struct Bar {
     pub v: f64,
 }
 
-#[derive(Deserialize)]
+impl From<&Candle> for Bar {
+    fn from(candle: &Candle) -> Self {
+        Self {
+            timestamp: candle.timestamp,
+            o: candle.open,
+            h: candle.high,
+            l: candle.low,
+            c: candle.close,
+            v: candle.volume as f64,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
 struct AggsResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+    #[serde(default, rename = "resultsCount", skip_serializing_if = "Option::is_none")]
+    results_count: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
     results: Option<Vec<AggBar>>,
+    // Gaps a refetch returned no bars for (halts, holidays the calendar does not list),
+    // kept in the cache file so they are not requested again
+    #[serde(default, skip_serializing_if = "Vec::is_empty", deserialize_with = "dates::lenient_ranges")]
+    unfillable_gaps: Vec<(NaiveDate, NaiveDate)>,
 }
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 struct AggBar {
     t: i64,
     o: f64,
@@ -26,7 +61,22 @@ struct AggBar {
     v: f64,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl From<AggBar> for Candle {
+    fn from(bar: AggBar) -> Self {
+        Candle::new(epoch_seconds(bar.t), bar.o, bar.h, bar.l, bar.c, volume_from_f64(bar.v))
+    }
+}
+
+/// Payload of the "history_gaps_filled" event, sent when a cached daily series was
+/// missing trading days and they were refetched
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GapFillResult {
+    pub symbol: String,
+    pub gaps_found: u32,
+    pub bars_added: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct NewsItem {
     pub title: String,
     pub article_url: String,
@@ -38,15 +88,119 @@ pub struct NewsItem {
 
 #[derive(Deserialize)]
 struct NewsResponse {
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    count: Option<u64>,
     results: Option<Vec<NewsItem>>,
 }
 
-fn to_mmddyyyy(ms: i64) -> String {
-    let dt = NaiveDateTime::from_timestamp_millis(ms)
-        .unwrap_or_else(|| NaiveDateTime::from_timestamp_opt(0, 0).unwrap());
-    dt.format("%m/%d/%Y").to_string()
+#[derive(Deserialize)]
+struct OptionSnapshotResponse {
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+    results: Option<Vec<OptionSnapshot>>,
+    next_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LastTradeResponse {
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+    results: Option<LastTrade>,
+}
+#[derive(Deserialize)]
+struct LastTrade {
+    p: f64,
+    t: i64, // SIP timestamp, nanoseconds
+}
+
+const POLYGON_STATUSES: &[&str] = &["OK", "DELAYED"];
+
+impl ProviderResponse for AggsResponse {
+    const KNOWN_FIELDS: &'static [&'static str] = &[
+        "ticker", "queryCount", "resultsCount", "adjusted", "results", "status", "request_id", "count", "next_url",
+        "error", "message", "unfillable_gaps",
+    ];
+
+    fn validate(&self) -> Result<(), ParseFailure> {
+        diagnostics::check_status(self.status.as_deref(), POLYGON_STATUSES, self.error.as_deref())?;
+        let results = self.results.as_deref().unwrap_or_default();
+        diagnostics::check_count("resultsCount", self.results_count, results.len())?;
+        diagnostics::check_monotonic(results.iter().map(|bar| bar.t), |i| format!("results[{}].t", i))
+    }
+}
+
+impl ProviderResponse for NewsResponse {
+    const KNOWN_FIELDS: &'static [&'static str] = &["results", "status", "request_id", "count", "next_url"];
+
+    fn validate(&self) -> Result<(), ParseFailure> {
+        diagnostics::check_status(self.status.as_deref(), POLYGON_STATUSES, None)?;
+        diagnostics::check_count("count", self.count, self.results.as_ref().map_or(0, Vec::len))
+    }
 }
 
+impl ProviderResponse for LastTradeResponse {
+    const KNOWN_FIELDS: &'static [&'static str] = &["results", "status", "request_id", "error", "message"];
+
+    fn validate(&self) -> Result<(), ParseFailure> {
+        diagnostics::check_status(self.status.as_deref(), POLYGON_STATUSES, self.error.as_deref())?;
+        match &self.results {
+            Some(trade) if trade.p > 0.0 => Ok(()),
+            Some(_) => Err(ParseFailure::new("results.p", "price is not positive")),
+            None => Err(ParseFailure::new("results", "missing")),
+        }
+    }
+}
+
+impl ProviderResponse for OptionSnapshotResponse {
+    const KNOWN_FIELDS: &'static [&'static str] = &["results", "status", "request_id", "next_url", "error", "message"];
+
+    fn validate(&self) -> Result<(), ParseFailure> {
+        diagnostics::check_status(self.status.as_deref(), POLYGON_STATUSES, self.error.as_deref())
+    }
+}
+#[derive(Deserialize)]
+struct OptionSnapshot {
+    details: OptionDetails,
+    day: Option<OptionDay>,
+    last_quote: Option<OptionLastQuote>,
+    open_interest: Option<i64>,
+    implied_volatility: Option<f64>,
+    greeks: Option<OptionGreeks>,
+}
+#[derive(Deserialize)]
+struct OptionDetails {
+    ticker: String,
+    contract_type: String,   // "call" | "put"
+    expiration_date: String, // YYYY-MM-DD
+    strike_price: f64,
+}
+#[derive(Deserialize)]
+struct OptionDay {
+    close: Option<f64>,
+    volume: Option<f64>,
+}
+#[derive(Deserialize)]
+struct OptionLastQuote {
+    bid: Option<f64>,
+    ask: Option<f64>,
+    midpoint: Option<f64>,
+}
+#[derive(Deserialize)]
+struct OptionGreeks {
+    delta: Option<f64>,
+    gamma: Option<f64>,
+    theta: Option<f64>,
+    vega: Option<f64>,
+}
+
+const MAX_CHAIN_PAGES: usize = 40; // 250 contracts per page
+
 fn app_cache_dir(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
     Ok(app
         .path()
@@ -72,6 +226,16 @@ async fn read_key(app: &tauri::AppHandle) -> Result<String, String> {
     Err("Polygon API key not set. Save it in settings or set POLYGON_API_KEY".into())
 }
 
+/// Provider keys saved in the app's secrets file (environment variables are not included)
+pub fn read_stored_keys(app: &tauri::AppHandle) -> Result<serde_json::Value, String> {
+    let path = app_cache_dir(app)?.join("secrets.json");
+    if !path.exists() {
+        return Ok(serde_json::json!({}));
+    }
+    let text = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&text).map_err(|e| e.to_string())
+}
+
 pub async fn save_polygon_key(app: &tauri::AppHandle, key: String) -> Result<(), String> {
     let dir = app_cache_dir(app)?;
     std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
@@ -86,89 +250,313 @@ pub async fn save_polygon_key(app: &tauri::AppHandle, key: String) -> Result<(),
     std::fs::write(path, serde_json::to_string_pretty(&obj).unwrap()).map_err(|e| e.to_string())
 }
 
-pub async fn fetch_history(
-    app: &tauri::AppHandle,
-    symbol: String,
-    start: String,           // MM/DD/YYYY
-    end: String,             // MM/DD/YYYY
-    interval: Option<String> // "1day" | "1hour"
-) -> Result<Vec<Bar>, String> {
-    let key = read_key(app).await?;
-    let cache_dir = app_cache_dir(app)?;
-    std::fs::create_dir_all(&cache_dir).ok();
+fn api_date(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
 
-    let ts = |s: &str| -> String {
-        let parts: Vec<&str> = s.split('/').collect();
-        if parts.len() == 3 {
-            format!("{}-{}-{}", parts[2], parts[0], parts[1])
-        } else {
-            s.to_string()
-        }
-    };
-    let (mult, span) = match interval.as_deref() {
+fn interval_params(interval: Option<&str>) -> (&'static str, &'static str) {
+    match interval {
         Some("1hour") => ("1", "hour"),
         _ => ("1", "day"),
+    }
+}
+
+/// (first, last) missing trading days between consecutive bars that are more than
+/// `expected_interval_days` trading days apart. Bars must be oldest first.
+pub fn detect_bar_gaps(bars: &[Bar], calendar: &MarketCalendar, expected_interval_days: u32) -> Vec<(NaiveDate, NaiveDate)> {
+    let dates: Vec<NaiveDate> = bars
+        .iter()
+        .filter_map(|b| DateTime::from_timestamp(b.timestamp, 0).map(|t| t.date_naive()))
+        .collect();
+    let expected_between = expected_interval_days.max(1) as usize - 1;
+    dates
+        .windows(2)
+        .filter_map(|pair| {
+            let (from, to) = (pair[0].succ_opt()?, pair[1].pred_opt()?);
+            if from > to {
+                return None;
+            }
+            let missing = calendar.get_trading_days(from, to);
+            if missing.len() <= expected_between {
+                return None;
+            }
+            Some((missing[0], missing[missing.len() - 1]))
+        })
+        .collect()
+}
+
+/// Gaps in a cached daily series that have not already been refetched without result
+fn pending_gaps(parsed: &AggsResponse, calendar: &MarketCalendar) -> Vec<(NaiveDate, NaiveDate)> {
+    let bars: Vec<Bar> = parsed
+        .results
+        .iter()
+        .flatten()
+        .map(|r| Bar::from(&Candle::from(r.clone())))
+        .collect();
+    detect_bar_gaps(&bars, calendar, 1)
+        .into_iter()
+        .filter(|gap| !parsed.unfillable_gaps.contains(gap))
+        .collect()
+}
+
+/// Merge bars refetched for `gap` into the series, oldest first; returns how many were new
+fn merge_gap_bars(parsed: &mut AggsResponse, gap: &(NaiveDate, NaiveDate), fetched: Vec<AggBar>) -> u32 {
+    let results = parsed.results.get_or_insert_with(Vec::new);
+    let before = results.len();
+    for bar in fetched {
+        if !results.iter().any(|r| r.t == bar.t) {
+            results.push(bar);
+        }
+    }
+    results.sort_by_key(|r| r.t);
+    let added = results.len() - before;
+    parsed.results_count = Some(results.len() as u64);
+    if added == 0 {
+        parsed.unfillable_gaps.push(*gap);
+    }
+    added as u32
+}
+
+async fn fill_history_gaps(
+    budget: &ApiBudget,
+    capture_dir: Option<&std::path::Path>,
+    symbol: &str,
+    parsed: &mut AggsResponse,
+    gaps: &[(NaiveDate, NaiveDate)],
+    key: &str,
+) -> Result<GapFillResult, String> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let calendar = MarketCalendar::new();
+    let mut bars_added = 0;
+    for gap in gaps {
+        // Gap refetches are small; they only need to fit the quota
+        let estimate = estimate_request(symbol, gap.0, gap.1, Some("1day"), false, &calendar);
+        budget.check(&estimate, true, api_budget::usage_day())?;
+        let url = aggregates_url(symbol, gap.0, gap.1, Some("1day"), key);
+        let resp = client.get(&url).send().await.map_err(|e| e.to_string())?;
+        budget.record(&estimate, 1, api_budget::usage_day())?;
+        if !resp.status().is_success() {
+            return Err(status_error("history", resp.status()));
+        }
+        let text = resp.text().await.map_err(|e| e.to_string())?;
+        let fetched: AggsResponse = diagnostics::parse_response("polygon", "aggregates", &text, capture_dir)?;
+        bars_added += merge_gap_bars(parsed, gap, fetched.results.unwrap_or_default());
+    }
+    Ok(GapFillResult { symbol: symbol.to_string(), gaps_found: gaps.len() as u32, bars_added })
+}
+
+fn to_candles(parsed: AggsResponse, symbol: &str, interval: Option<&str>) -> Vec<Candle> {
+    let label = match interval_params(interval) {
+        (_, "hour") => "1h",
+        _ => "1d",
     };
+    parsed
+        .results
+        .unwrap_or_default()
+        .into_iter()
+        .map(|r| Candle::from(r).with_symbol(symbol.to_uppercase()).with_interval(label))
+        .collect()
+}
 
-    let url = format!(
+fn aggregates_url(symbol: &str, start: NaiveDate, end: NaiveDate, interval: Option<&str>, key: &str) -> String {
+    let (mult, span) = interval_params(interval);
+    format!(
         "https://api.polygon.io/v2/aggs/ticker/{}/range/{}/{}/{}/{}?adjusted=true&sort=asc&limit=50000&apiKey={}",
-        symbol.to_uppercase(),
+        provider_symbol(symbol, SymbolProvider::Polygon),
         mult,
         span,
-        ts(&start),
-        ts(&end),
+        api_date(start),
+        api_date(end),
         key
-    );
+    )
+}
+
+fn history_cache_file(
+    app: &tauri::AppHandle,
+    symbol: &str,
+    start: NaiveDate,
+    end: NaiveDate,
+    interval: Option<&str>,
+) -> Result<std::path::PathBuf, String> {
+    let (mult, _) = interval_params(interval);
+    let cache_key = format!("aggs_{}_{}_{}_{}.json", symbol.to_uppercase(), mult, api_date(start), api_date(end));
+    Ok(app_cache_dir(app)?.join(cache_key))
+}
+
+/// Calls and bars a Polygon history request for this range will cost
+pub fn estimate_request(
+    symbol: &str,
+    start: NaiveDate,
+    end: NaiveDate,
+    interval: Option<&str>,
+    extended_hours: bool,
+    calendar: &MarketCalendar,
+) -> RequestEstimate {
+    let interval = match interval_params(interval) {
+        (_, "hour") => "1hour",
+        _ => "1day",
+    };
+    api_budget::estimate_history_request(api_budget::POLYGON, symbol, interval, start, end, extended_hours, calendar)
+}
+
+/// Intraday bars overlapping the regular session, or the extended one with
+/// `extended_hours`; daily bars pass through
+fn session_candles(candles: Vec<Candle>, interval: Option<&str>, extended_hours: bool, calendar: &MarketCalendar) -> Vec<Candle> {
+    let seconds = match interval_params(interval) {
+        (_, "hour") => 3600,
+        _ => return candles,
+    };
+    candles.into_iter().filter(|c| calendar.bar_in_session(c.timestamp, seconds, extended_hours)).collect()
+}
+
+/// Whether `fetch_history` would be served from disk for this range, or needs no
+/// request because none of it trades
+pub fn is_history_cached(app: &tauri::AppHandle, symbol: &str, start: NaiveDate, end: NaiveDate, interval: Option<&str>) -> bool {
+    match MarketCalendar::new().trading_range(start, end) {
+        Some((start, end)) => normalize_symbol(symbol)
+            .and_then(|symbol| history_cache_file(app, &symbol, start, end, interval))
+            .map(|path| path.exists())
+            .unwrap_or(false),
+        None => true,
+    }
+}
+
+pub async fn fetch_history(
+    app: &tauri::AppHandle,
+    symbol: String,
+    start: NaiveDate,
+    end: NaiveDate,
+    interval: Option<String> // "1day" | "1hour"
+) -> Result<Vec<Candle>, String> {
+    fetch_history_budgeted(app, symbol, start, end, interval, false, false).await
+}
 
-    let cache_key = format!("aggs_{}_{}_{}_{}.json", symbol.to_uppercase(), mult, ts(&start), ts(&end));
-    let cache_file = cache_dir.join(cache_key);
+/// `fetch_history`, letting a request over the large request thresholds through when
+/// `acknowledge_large_request` is set. Every call counts against the daily quota. The
+/// range is trimmed to trading days, and hourly bars outside the regular session are
+/// dropped unless `extended_hours` is set.
+pub async fn fetch_history_budgeted(
+    app: &tauri::AppHandle,
+    symbol: String,
+    start: NaiveDate,
+    end: NaiveDate,
+    interval: Option<String>,
+    acknowledge_large_request: bool,
+    extended_hours: bool,
+) -> Result<Vec<Candle>, String> {
+    let calendar = MarketCalendar::new();
+    // Trimmed to the first and last trading days; nothing to request when none trade
+    let Some((start, end)) = calendar.trading_range(start, end) else {
+        return Ok(Vec::new());
+    };
+    let key = read_key(app).await?;
+    let budget = ApiBudget::open(app)?;
+    let capture_dir = diagnostics::capture_dir(app);
+    let cache_dir = app_cache_dir(app)?;
+    std::fs::create_dir_all(&cache_dir).ok();
+
+    let symbol = normalize_symbol(&symbol)?;
+    let url = aggregates_url(&symbol, start, end, interval.as_deref(), &key);
+
+    let cache_file = history_cache_file(app, &symbol, start, end, interval.as_deref())?;
     if cache_file.exists() {
         if let Ok(text) = std::fs::read_to_string(&cache_file) {
-            if let Ok(parsed) = serde_json::from_str::<AggsResponse>(&text) {
-                let out = parsed
-                    .results
-                    .unwrap_or_default()
-                    .into_iter()
-                    .map(|r| Bar {
-                        date: to_mmddyyyy(r.t),
-                        o: r.o,
-                        h: r.h,
-                        l: r.l,
-                        c: r.c,
-                        v: r.v,
-                    })
-                    .collect();
-                return Ok(out);
+            if let Ok(mut parsed) = serde_json::from_str::<AggsResponse>(&text) {
+                let gaps = match interval_params(interval.as_deref()) {
+                    (_, "day") => pending_gaps(&parsed, &calendar),
+                    _ => Vec::new(),
+                };
+                if !gaps.is_empty() {
+                    match fill_history_gaps(&budget, capture_dir.as_deref(), &symbol, &mut parsed, &gaps, &key).await {
+                        Ok(result) => {
+                            if let Ok(json) = serde_json::to_string(&parsed) {
+                                std::fs::write(&cache_file, json).ok();
+                            }
+                            println!("Refetched {} history gaps for {}: {} bars added", result.gaps_found, symbol, result.bars_added);
+                            let _ = app.emit("history_gaps_filled", &result);
+                        }
+                        Err(e) => eprintln!("Refetching history gaps for {} failed: {}", symbol, e),
+                    }
+                }
+                let candles = session_candles(to_candles(parsed, &symbol, interval.as_deref()), interval.as_deref(), extended_hours, &calendar);
+                store_history_bars(app, &symbol, &candles);
+                return Ok(candles);
             }
         }
     }
 
+    let estimate = estimate_request(&symbol, start, end, interval.as_deref(), extended_hours, &calendar);
+    budget.check(&estimate, acknowledge_large_request, api_budget::usage_day())?;
+
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(15))
         .build()
         .map_err(|e| e.to_string())?;
     let resp = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    budget.record(&estimate, 1, api_budget::usage_day())?;
     if !resp.status().is_success() {
-        return Err(format!("Polygon error: {}", resp.status()));
+        return Err(status_error("history", resp.status()));
     }
     let text = resp.text().await.map_err(|e| e.to_string())?;
+    let parsed: AggsResponse = diagnostics::parse_response("polygon", "aggregates", &text, capture_dir.as_deref())?;
+    // Only responses that parsed are cached
     std::fs::write(&cache_file, &text).ok();
+    let candles = session_candles(to_candles(parsed, &symbol, interval.as_deref()), interval.as_deref(), extended_hours, &calendar);
+    store_history_bars(app, &symbol, &candles);
+    Ok(candles)
+}
 
-    let parsed: AggsResponse = serde_json::from_str(&text).map_err(|e| e.to_string())?;
-    let bars = parsed
-        .results
-        .unwrap_or_default()
-        .into_iter()
-        .map(|r| Bar {
-            date: to_mmddyyyy(r.t),
-            o: r.o,
-            h: r.h,
-            l: r.l,
-            c: r.c,
-            v: r.v,
-        })
-        .collect();
-    Ok(bars)
+/// Keep history bars in the journal store's bar cache, which answers range queries
+/// across fetches
+fn store_history_bars(app: &tauri::AppHandle, symbol: &str, candles: &[Candle]) {
+    let Some(interval) = candles.first().and_then(|c| c.interval.clone()) else {
+        return;
+    };
+    let result = FileCache::new(app).and_then(|cache| {
+        let backend = journal_store::configured_backend(&cache);
+        journal_store::open_store(cache, backend).save_bars(symbol, &interval, candles)
+    });
+    if let Err(e) = result {
+        eprintln!("Failed to store {} history bars: {}", symbol, e);
+    }
+}
+
+/// Every cached history response as (symbol, interval, bars). Cache file names do not
+/// record the interval, so hourly series are told apart by their bar spacing.
+pub fn cached_history_series(app: &tauri::AppHandle) -> Result<Vec<(String, String, Vec<Candle>)>, String> {
+    let dir = app_cache_dir(app)?;
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Ok(Vec::new());
+    };
+    let mut series = Vec::new();
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let Some(symbol) = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix("aggs_"))
+            .and_then(|n| n.split('_').next())
+            .map(str::to_string)
+        else {
+            continue;
+        };
+        let parsed: AggsResponse = match std::fs::read_to_string(&path).map(|text| serde_json::from_str(&text)) {
+            Ok(Ok(parsed)) => parsed,
+            _ => {
+                eprintln!("Skipping unreadable history cache file {:?}", path);
+                continue;
+            }
+        };
+        let timestamps: Vec<i64> = parsed.results.iter().flatten().map(|bar| epoch_seconds(bar.t)).collect();
+        let hourly = timestamps.windows(2).any(|w| w[1] - w[0] > 0 && w[1] - w[0] < 86400 / 2);
+        let candles = to_candles(parsed, &symbol, Some(if hourly { "1hour" } else { "1day" }));
+        if let Some(interval) = candles.first().and_then(|c| c.interval.clone()) {
+            series.push((symbol, interval, candles));
+        }
+    }
+    Ok(series)
 }
 
 pub async fn fetch_news(
@@ -177,11 +565,12 @@ pub async fn fetch_news(
     days: u32,
 ) -> Result<(f64, Vec<NewsItem>), String> {
     let key = read_key(app).await?;
+    let symbol = normalize_symbol(&symbol)?;
     let now = Utc::now();
     let from = now - chrono::Duration::days(days as i64);
     let url = format!(
         "https://api.polygon.io/v2/reference/news?ticker={}&published_utc.gte={}&order=desc&limit=25&apiKey={}",
-        symbol.to_uppercase(),
+        provider_symbol(&symbol, SymbolProvider::Polygon),
         from.format("%Y-%m-%d"),
         key
     );
@@ -189,10 +578,10 @@ pub async fn fetch_news(
     let client = reqwest::Client::new();
     let resp = client.get(url).send().await.map_err(|e| e.to_string())?;
     if !resp.status().is_success() {
-        return Err(format!("Polygon news error: {}", resp.status()));
+        return Err(status_error("news", resp.status()));
     }
     let text = resp.text().await.map_err(|e| e.to_string())?;
-    let parsed: NewsResponse = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    let parsed: NewsResponse = diagnostics::parse_response("polygon", "news", &text, diagnostics::capture_dir(app).as_deref())?;
     let items = parsed.results.unwrap_or_default();
 
     let mut n = 0u32;
@@ -206,3 +595,347 @@ pub async fn fetch_news(
     let avg = if n > 0 { sum / (n as f64) } else { 0.0 };
     Ok((avg, items))
 }
+
+/// Last trade price and its time in epoch seconds. Counts one call against the daily quota.
+pub async fn fetch_last_trade(app: &tauri::AppHandle, symbol: &str) -> Result<(f64, i64), String> {
+    let key = read_key(app).await?;
+    let symbol = normalize_symbol(symbol)?;
+    let budget = ApiBudget::open(app)?;
+    let today = api_budget::usage_day();
+    let estimate = RequestEstimate {
+        provider: api_budget::POLYGON.to_string(),
+        symbol: symbol.clone(),
+        interval: "last_trade".to_string(),
+        start: today,
+        end: today,
+        trading_days: 0,
+        bars: 0,
+        api_calls: 1,
+        extended_hours: false,
+    };
+    budget.check(&estimate, false, today)?;
+
+    let url = format!(
+        "https://api.polygon.io/v2/last/trade/{}?apiKey={}",
+        provider_symbol(&symbol, SymbolProvider::Polygon),
+        key
+    );
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let resp = client.get(url).send().await.map_err(|e| e.to_string())?;
+    budget.record(&estimate, 1, today)?;
+    if !resp.status().is_success() {
+        return Err(status_error("last trade", resp.status()));
+    }
+    let text = resp.text().await.map_err(|e| e.to_string())?;
+    let parsed: LastTradeResponse = diagnostics::parse_response("polygon", "last_trade", &text, diagnostics::capture_dir(app).as_deref())?;
+    let trade = parsed.results.expect("validated");
+    Ok((trade.p, trade.t / 1_000_000_000))
+}
+
+/// Full option chain for an underlying from the options snapshot endpoint.
+/// Needs an options-entitled key; quotes reflect the latest session.
+pub async fn fetch_option_chain_snapshot(app: &tauri::AppHandle, symbol: &str) -> Result<OptionChain, String> {
+    require_entitlement(app, Entitlement::Options).await?;
+    let key = read_key(app).await?;
+    let symbol = normalize_symbol(symbol)?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut url = format!(
+        "https://api.polygon.io/v3/snapshot/options/{}?limit=250&apiKey={}",
+        provider_symbol(&symbol, SymbolProvider::Polygon),
+        key
+    );
+    let mut contracts = HashMap::new();
+    let capture_dir = diagnostics::capture_dir(app);
+
+    for _ in 0..MAX_CHAIN_PAGES {
+        let resp = client.get(&url).send().await.map_err(|e| e.to_string())?;
+        if !resp.status().is_success() {
+            return Err(status_error("options", resp.status()));
+        }
+        let text = resp.text().await.map_err(|e| e.to_string())?;
+        let parsed: OptionSnapshotResponse = diagnostics::parse_response("polygon", "options_snapshot", &text, capture_dir.as_deref())?;
+
+        for snap in parsed.results.unwrap_or_default() {
+            let quote = snap.last_quote.as_ref();
+            let greeks = snap.greeks.as_ref();
+            let Ok(expiry) = dates::parse(&snap.details.expiration_date) else {
+                continue;
+            };
+            let contract = OptionContract {
+                symbol: snap.details.ticker.clone(),
+                strike: snap.details.strike_price,
+                expiry,
+                option_type: snap.details.contract_type.to_lowercase(),
+                last_price: snap.day.as_ref().and_then(|d| d.close),
+                mark: quote.and_then(|q| q.midpoint),
+                bid: quote.and_then(|q| q.bid),
+                ask: quote.and_then(|q| q.ask),
+                volume: snap.day.as_ref().and_then(|d| d.volume).map(|v| v as i64),
+                open_interest: snap.open_interest,
+                implied_volatility: snap.implied_volatility,
+                delta: greeks.and_then(|g| g.delta),
+                gamma: greeks.and_then(|g| g.gamma),
+                theta: greeks.and_then(|g| g.theta),
+                vega: greeks.and_then(|g| g.vega),
+            };
+            contracts.insert(snap.details.ticker, contract);
+        }
+
+        match parsed.next_url {
+            Some(next) => url = format!("{}&apiKey={}", next, key),
+            None => break,
+        }
+    }
+
+    if contracts.is_empty() {
+        return Err(format!("No option contracts returned for {}", symbol));
+    }
+
+    let mut expiries: Vec<NaiveDate> = contracts.values().map(|c| c.expiry).collect();
+    expiries.sort();
+    expiries.dedup();
+    let mut strikes: Vec<f64> = contracts.values().map(|c| c.strike).collect();
+    strikes.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    strikes.dedup();
+
+    Ok(OptionChain {
+        underlying_symbol: symbol,
+        as_of: Utc::now().date_naive(),
+        expiries,
+        strikes,
+        contracts,
+    })
+}
+
+/// Error for a non-success response; a refusal says the plan does not cover the data
+fn status_error(what: &str, status: reqwest::StatusCode) -> String {
+    match status {
+        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => {
+            format!("Polygon {} data is not included in your plan ({})", what, status)
+        }
+        _ => format!("Polygon {} error: {}", what, status),
+    }
+}
+
+/// Err when the cached profile for the configured key says its plan lacks
+/// `entitlement`; passes when the key has not been probed
+pub async fn require_entitlement(app: &tauri::AppHandle, entitlement: Entitlement) -> Result<(), String> {
+    let Ok(key) = read_key(app).await else {
+        return Ok(()); // The feature reports the missing key itself
+    };
+    match entitlements::load(app) {
+        Some(profile) if profile.is_for(&key) => profile.check(entitlement),
+        _ => Ok(()),
+    }
+}
+
+/// The cached profile, if it was probed with the configured key
+pub async fn current_entitlements(app: &tauri::AppHandle) -> Option<EntitlementProfile> {
+    let key = read_key(app).await.ok()?;
+    entitlements::load(app).filter(|profile| profile.is_for(&key))
+}
+
+/// Probe what the configured key's plan covers and cache the profile. The probes run
+/// one at a time, cheapest-to-refuse first, and count against the daily quota; history
+/// windows stop at the first one refused.
+pub async fn probe_entitlements(app: &tauri::AppHandle) -> Result<EntitlementProfile, String> {
+    let key = read_key(app).await?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let today = Utc::now().date_naive();
+    let years_back = |years: u32| today.checked_sub_months(chrono::Months::new(12 * years)).unwrap_or(today);
+    let week_from = |years: u32| {
+        let start = years_back(years);
+        aggregates_url(entitlements::PROBE_SYMBOL, start, start + chrono::Duration::days(7), Some("1day"), &key)
+    };
+    let probe = |name: String, url: String| {
+        let client = client.clone();
+        async move {
+            let (outcome, detail) = match client.get(&url).send().await {
+                Ok(resp) => {
+                    let status = resp.status().as_u16();
+                    entitlements::classify_response(status, &resp.text().await.unwrap_or_default())
+                }
+                Err(e) => (ProbeOutcome::Inconclusive, e.to_string()),
+            };
+            Probe { name, outcome, detail }
+        }
+    };
+
+    let mut probes = vec![
+        probe(entitlements::PROBE_STOCKS.to_string(), week_from(entitlements::STOCKS_PROBE_YEARS)).await,
+        probe(
+            entitlements::PROBE_LAST_TRADE.to_string(),
+            format!("https://api.polygon.io/v2/last/trade/{}?apiKey={}", entitlements::PROBE_SYMBOL, key),
+        )
+        .await,
+        probe(
+            entitlements::PROBE_OPTIONS.to_string(),
+            format!("https://api.polygon.io/v3/snapshot/options/{}?limit=1&apiKey={}", entitlements::PROBE_SYMBOL, key),
+        )
+        .await,
+    ];
+    for years in entitlements::HISTORY_PROBE_YEARS {
+        let result = probe(entitlements::history_probe(years), week_from(years)).await;
+        let deeper = matches!(result.outcome, ProbeOutcome::Allowed | ProbeOutcome::Delayed);
+        probes.push(result);
+        if !deeper {
+            break;
+        }
+    }
+    for (name, ticker) in [(entitlements::PROBE_FOREX, "C:EURUSD"), (entitlements::PROBE_CRYPTO, "X:BTCUSD")] {
+        let url = format!("https://api.polygon.io/v2/aggs/ticker/{}/prev?apiKey={}", ticker, key);
+        probes.push(probe(name.to_string(), url).await);
+    }
+
+    let estimate = RequestEstimate {
+        provider: api_budget::POLYGON.to_string(),
+        symbol: entitlements::PROBE_SYMBOL.to_string(),
+        interval: "entitlement_probe".to_string(),
+        start: today,
+        end: today,
+        trading_days: 0,
+        bars: 0,
+        api_calls: probes.len() as u32,
+        extended_hours: false,
+    };
+    if let Err(e) = ApiBudget::open(app).and_then(|budget| budget.record(&estimate, probes.len() as u32, api_budget::usage_day())) {
+        eprintln!("Failed to count entitlement probes: {}", e);
+    }
+
+    let profile = EntitlementProfile::from_probes(&key, Utc::now().timestamp(), probes);
+    entitlements::save(app, &profile)?;
+    let _ = app.emit("provider_entitlements_changed", &profile);
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregates_round_trip_to_history_bars() {
+        let raw = r#"{"results":[{"t":1704171600000,"o":470.1,"h":473.0,"l":469.5,"c":472.65,"v":123456.0}]}"#;
+        let candles = to_candles(serde_json::from_str(raw).unwrap(), "spy", Some("1day"));
+
+        assert_eq!(candles[0].timestamp, 1704171600);
+        assert_eq!((candles[0].symbol.as_deref(), candles[0].interval.as_deref()), (Some("SPY"), Some("1d")));
+        assert_eq!(
+            serde_json::to_value(Bar::from(&candles[0])).unwrap(),
+            serde_json::json!({ "timestamp": 1704171600, "o": 470.1, "h": 473.0, "l": 469.5, "c": 472.65, "v": 123456.0 })
+        );
+    }
+    #[test]
+    fn test_share_class_alias_round_trips_through_aggregates() {
+        let symbol = normalize_symbol("brk-b").unwrap();
+        let url = aggregates_url(&symbol, jan(2), jan(31), Some("1day"), "KEY");
+        assert!(url.starts_with("https://api.polygon.io/v2/aggs/ticker/BRK.B/range/1/day/2024-01-02/2024-01-31?"));
+
+        let raw = r#"{"results":[{"t":1704171600000,"o":362.0,"h":364.1,"l":360.3,"c":363.2,"v":3100000.0}]}"#;
+        let candles = to_candles(serde_json::from_str(raw).unwrap(), &symbol, Some("1day"));
+        assert_eq!(candles[0].symbol.as_deref(), Some("BRK.B"));
+    }
+
+    #[test]
+    fn test_aggregates_schema_drift_names_the_path_and_keeps_the_response() {
+        let dir = std::env::temp_dir().join(format!("provider-errors-test-{}", uuid::Uuid::new_v4()));
+        let good = r#"{"status":"OK","resultsCount":2,"results":[
+            {"t":1704171600000,"o":1.0,"h":1.0,"l":1.0,"c":1.0,"v":1.0},
+            {"t":1704258000000,"o":1.0,"h":1.0,"l":1.0,"c":1.0,"v":1.0}]}"#;
+        let parse = |text: &str| diagnostics::parse_response::<AggsResponse>("polygon", "aggregates", text, Some(&dir)).map(|_| ());
+        let parsed: AggsResponse = diagnostics::parse_response("polygon", "aggregates", good, Some(&dir)).unwrap();
+        assert_eq!(parsed.results.unwrap().len(), 2);
+
+        // The second bar's "t" renamed to "timestamp"
+        let renamed = good.replacen(r#"{"t":1704258000000"#, r#"{"timestamp":1704258000000"#, 1);
+        let error = parse(&renamed).unwrap_err();
+        assert!(error.contains("`results[1]`") && error.contains("missing field `t`"), "{}", error);
+        assert!(error.contains("provider_errors/"), "{}", error);
+
+        let failures = [
+            (good.replace(r#""OK""#, r#""NOT_AUTHORIZED""#), "status"),
+            (good.replace(r#""resultsCount":2"#, r#""resultsCount":3"#), "resultsCount"),
+            (good.replace("1704258000000", "1704171600000"), "results[1].t"),
+        ];
+        for (text, path) in &failures {
+            let error = parse(text).unwrap_err();
+            assert!(error.contains(&format!("`{}`", path)), "{}", error);
+        }
+
+        // New top-level keys parse but are noted
+        let extra = good.replacen(r#""status":"OK""#, r#""status":"OK","cursor":"abc""#, 1);
+        parse(&extra).unwrap();
+
+        let report = diagnostics::load_diagnostics(&dir).unwrap();
+        assert_eq!(report.captures.len(), 4);
+        assert!(report.captures.iter().any(|c| c.path == "results[1]" && c.raw == renamed));
+        assert_eq!(report.unknown_fields["polygon/aggregates"].iter().collect::<Vec<_>>(), vec!["cursor"]);
+    }
+
+    fn jan(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    // Daily bar stamped at midnight New York on `day` of January 2024
+    fn jan_bar(day: i64) -> AggBar {
+        let t = (1704085200 + (day - 1) * 86400) * 1000;
+        AggBar { t, o: 100.0, h: 101.0, l: 99.0, c: 100.0 + day as f64, v: 1000.0 }
+    }
+
+    #[test]
+    fn test_detects_and_fills_gaps_in_cached_daily_bars() {
+        let calendar = MarketCalendar::new();
+        // Jan 2-5, then Jan 11-12, then Jan 16: Jan 8-10 are missing; the weekend and
+        // MLK Day (Jan 15) are not trading days
+        let mut parsed = AggsResponse {
+            results: Some([2, 3, 4, 5, 11, 12, 16].into_iter().map(jan_bar).collect()),
+            ..Default::default()
+        };
+        let gaps = pending_gaps(&parsed, &calendar);
+        assert_eq!(gaps, vec![(jan(8), jan(10))]);
+
+        // The refetch overlaps a bar already cached
+        let added = merge_gap_bars(&mut parsed, &gaps[0], [8, 9, 10, 11].into_iter().map(jan_bar).collect());
+        assert_eq!(added, 3);
+        let days: Vec<NaiveDate> = to_candles(serde_json::from_str(&serde_json::to_string(&parsed).unwrap()).unwrap(), "SPY", None)
+            .iter()
+            .map(Candle::date)
+            .collect();
+        assert_eq!(days.len(), 10);
+        assert_eq!((days[4], days[9]), (jan(8), jan(16)));
+        assert!(pending_gaps(&parsed, &calendar).is_empty());
+
+        // A gap the API has nothing for is remembered and not requested again
+        parsed.results.as_mut().unwrap().retain(|r| r.t != jan_bar(12).t);
+        let gaps = pending_gaps(&parsed, &calendar);
+        assert_eq!(gaps, vec![(jan(12), jan(12))]);
+        assert_eq!(merge_gap_bars(&mut parsed, &gaps[0], Vec::new()), 0);
+        let reloaded: AggsResponse = serde_json::from_str(&serde_json::to_string(&parsed).unwrap()).unwrap();
+        assert!(pending_gaps(&reloaded, &calendar).is_empty());
+        // Cache files written before the switch kept their gaps as MM/DD/YYYY
+        let legacy = r#"{"results":[],"unfillable_gaps":[["01/12/2024","01/12/2024"]]}"#;
+        assert_eq!(serde_json::from_str::<AggsResponse>(legacy).unwrap().unfillable_gaps, vec![(jan(12), jan(12))]);
+    }
+
+    #[test]
+    fn test_gap_detection_respects_the_bar_interval() {
+        let calendar = MarketCalendar::new();
+        let bars: Vec<Bar> = [2, 4, 8, 9]
+            .into_iter()
+            .map(|day| Bar::from(&Candle::from(jan_bar(day))))
+            .collect();
+        assert_eq!(detect_bar_gaps(&bars, &calendar, 1), vec![(jan(3), jan(3)), (jan(5), jan(5))]);
+        // Every other trading day is the expected spacing
+        assert!(detect_bar_gaps(&bars, &calendar, 2).is_empty());
+        assert!(detect_bar_gaps(&bars[..1], &calendar, 1).is_empty());
+    }
+}
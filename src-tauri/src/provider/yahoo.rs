@@ -1,9 +1,16 @@
-use chrono::{NaiveDate, NaiveDateTime};
-use serde::Serialize;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
 
+use super::diagnostics::{self, ParseFailure, ProviderResponse};
+
+use crate::engine::symbols::{normalize_symbol, provider_symbol, SymbolProvider};
+use crate::market_data::types::{iso_date_to_epoch, volume_from_f64, Candle};
+
+/// History bar as the frontend receives it
 #[derive(Serialize, Clone)]
 pub struct YBar {
-    pub date: String, // MM/DD/YYYY
+    pub timestamp: i64, // Epoch seconds
     pub o: f64,
     pub h: f64,
     pub l: f64,
@@ -11,23 +18,83 @@ pub struct YBar {
     pub v: f64,
 }
 
-fn to_epoch(d: &str) -> i64 {
-    let parts: Vec<&str> = d.split('/').collect();
-    let (m, d2, y) = (
-        parts[0].parse::<u32>().unwrap(),
-        parts[1].parse::<u32>().unwrap(),
-        parts[2].parse::<i32>().unwrap(),
-    );
-    let nd = NaiveDate::from_ymd_opt(y, m, d2).unwrap();
-    NaiveDateTime::new(nd, chrono::NaiveTime::from_hms_opt(0, 0, 0).unwrap())
-        .and_utc()
-        .timestamp()
+impl From<&Candle> for YBar {
+    fn from(candle: &Candle) -> Self {
+        Self {
+            timestamp: candle.timestamp,
+            o: candle.open,
+            h: candle.high,
+            l: candle.low,
+            c: candle.close,
+            v: candle.volume as f64,
+        }
+    }
+}
+
+/// One row of Yahoo's daily history CSV: Date,Open,High,Low,Close,Adj Close,Volume
+impl TryFrom<&csv::StringRecord> for Candle {
+    type Error = String;
+
+    fn try_from(r: &csv::StringRecord) -> Result<Self, String> {
+        let field = |i: usize| r.get(i).unwrap_or("");
+        let timestamp = iso_date_to_epoch(field(0))?;
+        let o: f64 = field(1).parse().unwrap_or(0.0);
+        let h: f64 = field(2).parse().unwrap_or(0.0);
+        let l: f64 = field(3).parse().unwrap_or(0.0);
+        let c: f64 = field(5).parse().unwrap_or_else(|_| field(4).parse().unwrap_or(0.0)); // AdjClose or Close
+        let v: f64 = field(6).parse::<f64>().unwrap_or(0.0);
+        Ok(Candle::new(timestamp, o, h, l, c, volume_from_f64(v)).with_interval("1d"))
+    }
+}
+
+fn to_epoch(date: NaiveDate) -> i64 {
+    date.and_time(chrono::NaiveTime::MIN).and_utc().timestamp()
+}
+
+fn download_url(symbol: &str, start: NaiveDate, end: NaiveDate) -> String {
+    let p1 = to_epoch(start);
+    let p2 = to_epoch(end) + 86400; // inclusive end
+    format!(
+        "https://query1.finance.yahoo.com/v7/finance/download/{}?period1={}&period2={}&interval=1d&events=history&includeAdjustedClose=true",
+        provider_symbol(symbol, SymbolProvider::Yahoo),
+        p1,
+        p2
+    )
 }
 
-pub async fn yahoo_history(symbol: String, start: String, end: String) -> Result<Vec<YBar>, String> {
-    let p1 = to_epoch(&start);
-    let p2 = to_epoch(&end) + 86400; // inclusive end
-    let url = format!("https://query1.finance.yahoo.com/v7/finance/download/{}?period1={}&period2={}&interval=1d&events=history&includeAdjustedClose=true", symbol, p1, p2);
+const HISTORY_COLUMNS: [&str; 7] = ["Date", "Open", "High", "Low", "Close", "Adj Close", "Volume"];
+
+/// Candles from a history CSV, tagged with the canonical `symbol`. Failures name the
+/// header column or data row (1-based) that broke.
+fn parse_history(text: &str, symbol: &str) -> Result<Vec<Candle>, ParseFailure> {
+    let mut rdr = csv::Reader::from_reader(text.as_bytes());
+    let headers = rdr.headers().map_err(|e| ParseFailure::new("header", e.to_string()))?;
+    for (i, expected) in HISTORY_COLUMNS.iter().enumerate() {
+        if headers.get(i).map(str::trim) != Some(*expected) {
+            return Err(ParseFailure::new(
+                format!("header[{}]", i),
+                format!("expected column {:?}, found {:?}", expected, headers.get(i).unwrap_or("")),
+            ));
+        }
+    }
+
+    let mut out: Vec<Candle> = vec![];
+    for (i, rec) in rdr.records().enumerate() {
+        let row = format!("row {}", i + 1);
+        let r = rec.map_err(|e| ParseFailure::new(row.clone(), e.to_string()))?;
+        let candle = Candle::try_from(&r).map_err(|e| ParseFailure::new(row.clone(), e))?;
+        if out.last().is_some_and(|previous| candle.timestamp <= previous.timestamp) {
+            return Err(ParseFailure::new(row, format!("date {} is not after the previous row", &r[0])));
+        }
+        out.push(candle.with_symbol(symbol.to_string()));
+    }
+    Ok(out)
+}
+
+/// Daily history from Yahoo; an unparseable CSV is saved to `capture_dir` when given
+pub async fn yahoo_history(symbol: String, start: NaiveDate, end: NaiveDate, capture_dir: Option<&Path>) -> Result<Vec<Candle>, String> {
+    let symbol = normalize_symbol(&symbol)?;
+    let url = download_url(&symbol, start, end);
 
     let text = reqwest::Client::new()
         .get(url)
@@ -37,30 +104,83 @@ pub async fn yahoo_history(symbol: String, start: String, end: String) -> Result
         .text()
         .await
         .map_err(|e| e.to_string())?;
+    parse_history(&text, &symbol).map_err(|failure| diagnostics::report_failure("yahoo", "history", &text, &failure, capture_dir))
+}
 
-    let mut rdr = csv::Reader::from_reader(text.as_bytes());
-    let mut out = vec![];
-    for rec in rdr.records() {
-        let r = rec.map_err(|e| e.to_string())?;
-        if &r[0] == "Date" {
-            continue;
+#[derive(Deserialize)]
+struct ChartResponse {
+    chart: Chart,
+}
+#[derive(Deserialize)]
+struct Chart {
+    result: Option<Vec<ChartResult>>,
+}
+#[derive(Deserialize)]
+struct ChartResult {
+    meta: ChartMeta,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChartMeta {
+    regular_market_price: Option<f64>,
+    regular_market_time: Option<i64>, // Epoch seconds
+}
+
+impl ProviderResponse for ChartResponse {
+    const KNOWN_FIELDS: &'static [&'static str] = &["chart"];
+
+    fn validate(&self) -> Result<(), ParseFailure> {
+        let meta = self.chart.result.as_ref().and_then(|r| r.first()).map(|r| &r.meta);
+        match meta {
+            None => Err(ParseFailure::new("chart.result", "no results")),
+            Some(meta) if !meta.regular_market_price.is_some_and(|p| p > 0.0) => {
+                Err(ParseFailure::new("chart.result[0].meta.regularMarketPrice", "missing or not positive"))
+            }
+            Some(meta) if meta.regular_market_time.is_none() => Err(ParseFailure::new("chart.result[0].meta.regularMarketTime", "missing")),
+            Some(_) => Ok(()),
         }
-        // YYYY-MM-DD → MM/DD/YYYY
-        let parts: Vec<&str> = r[0].split('-').collect();
-        let mmddyyyy = format!("{}/{}/{}", parts[1], parts[2], parts[0]);
-        let o: f64 = r[1].parse().unwrap_or(0.0);
-        let h: f64 = r[2].parse().unwrap_or(0.0);
-        let l: f64 = r[3].parse().unwrap_or(0.0);
-        let c: f64 = r[5].parse().unwrap_or_else(|_| r[4].parse().unwrap_or(0.0)); // AdjClose or Close
-        let v: f64 = r[6].parse::<f64>().unwrap_or(0.0);
-        out.push(YBar {
-            date: mmddyyyy,
-            o,
-            h,
-            l,
-            c,
-            v,
-        });
     }
-    Ok(out)
+}
+
+/// Last regular-market price and its time in epoch seconds from Yahoo's chart endpoint
+pub async fn yahoo_last_price(symbol: &str, capture_dir: Option<&Path>) -> Result<(f64, i64), String> {
+    let symbol = normalize_symbol(symbol)?;
+    let url = format!(
+        "https://query1.finance.yahoo.com/v8/finance/chart/{}?interval=1m&range=1d",
+        provider_symbol(&symbol, SymbolProvider::Yahoo)
+    );
+    let text = reqwest::Client::new()
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .text()
+        .await
+        .map_err(|e| e.to_string())?;
+    let parsed: ChartResponse = diagnostics::parse_response("yahoo", "chart", &text, capture_dir)?;
+    let meta = &parsed.chart.result.as_ref().and_then(|r| r.first()).expect("validated").meta;
+    Ok((meta.regular_market_price.unwrap_or_default(), meta.regular_market_time.unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_class_alias_round_trips_through_download() {
+        let symbol = normalize_symbol("BRK.B").unwrap();
+        let url = download_url(&symbol, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(), NaiveDate::from_ymd_opt(2024, 1, 31).unwrap());
+        assert!(url.starts_with("https://query1.finance.yahoo.com/v7/finance/download/BRK-B?period1=1704153600&"));
+
+        let csv = "Date,Open,High,Low,Close,Adj Close,Volume\n2024-01-02,362.0,364.1,360.3,363.2,363.2,3100000\n";
+        let candles = parse_history(csv, &symbol).unwrap();
+        assert_eq!(candles.len(), 1);
+        assert_eq!((candles[0].symbol.as_deref(), candles[0].close), (Some("BRK.B"), 363.2));
+
+        // A renamed column or out-of-order rows name where the CSV broke
+        let renamed = csv.replace("Adj Close", "Adjusted Close");
+        assert_eq!(parse_history(&renamed, &symbol).unwrap_err().path, "header[5]");
+        let unordered = format!("{}2024-01-01,1,1,1,1,1,1\n", csv);
+        assert_eq!(parse_history(&unordered, &symbol).unwrap_err().path, "row 2");
+    }
 }
@@ -0,0 +1,223 @@
+// src-tauri/src/providers/microstructure.rs
+// Streaming trade/quote microstructure statistics over a rolling 1-minute window
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub const MICROSTRUCTURE_WINDOW_MS: i64 = 60_000;
+
+/// Per-symbol trackers shared between the stream task and the command layer
+pub type MicrostructureStore = Arc<Mutex<HashMap<String, MicrostructureTracker>>>;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MicrostructureStats {
+    pub symbol: String,
+    pub window_seconds: u64,
+    pub trade_count: u64,
+    pub avg_trade_size: f64,
+    pub trade_size_std: f64,
+    pub tick_direction_runs: u32,           // Current run of same-direction price moves
+    pub bid_ask_spread_avg: f64,            // Quoted spread prevailing at each trade
+    pub effective_spread_bps: f64,          // 2 * |price - mid| / mid * 10000
+    pub price_impact_per_1000_shares: f64,  // Mean |price move| per trade, scaled by mean trade size
+    pub updated_at: i64,                    // Timestamp (ms) of the last trade
+}
+
+/// Welford mean/variance that also supports removing a previously added value
+#[derive(Debug, Clone, Default)]
+struct RollingWelford {
+    count: u64,
+    mean: f64,
+    m2: f64,
+}
+
+impl RollingWelford {
+    fn push(&mut self, x: f64) {
+        self.count += 1;
+        let delta = x - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn remove(&mut self, x: f64) {
+        if self.count <= 1 {
+            *self = Self::default();
+            return;
+        }
+        let n = self.count as f64;
+        let mean = (n * self.mean - x) / (n - 1.0);
+        self.m2 = (self.m2 - (x - self.mean) * (x - mean)).max(0.0);
+        self.mean = mean;
+        self.count -= 1;
+    }
+
+    fn mean(&self) -> f64 {
+        if self.count == 0 { 0.0 } else { self.mean }
+    }
+
+    fn std(&self) -> f64 {
+        if self.count < 2 { 0.0 } else { (self.m2 / (self.count - 1) as f64).sqrt() }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TradeSample {
+    timestamp: i64,
+    size: f64,
+    abs_price_change: Option<f64>,
+    quoted_spread: Option<f64>,
+    effective_spread_bps: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MicrostructureTracker {
+    window: VecDeque<TradeSample>,
+    trade_size: RollingWelford,
+    abs_price_change: RollingWelford,
+    quoted_spread: RollingWelford,
+    effective_spread_bps: RollingWelford,
+    last_quote: Option<(f64, f64)>,
+    last_price: Option<f64>,
+    last_direction: i8,
+    run_length: u32,
+    last_trade_at: i64,
+}
+
+impl MicrostructureTracker {
+    pub fn record_quote(&mut self, bid: f64, ask: f64) {
+        if bid > 0.0 && ask >= bid {
+            self.last_quote = Some((bid, ask));
+        }
+    }
+
+    pub fn record_trade(&mut self, price: f64, size: i64, timestamp: i64) {
+        if price <= 0.0 {
+            return;
+        }
+
+        let abs_price_change = self.last_price.map(|last| (price - last).abs());
+        if let Some(last) = self.last_price {
+            let direction = if price > last { 1 } else if price < last { -1 } else { 0 };
+            // Zero ticks neither extend nor break a run
+            if direction != 0 {
+                self.run_length = if direction == self.last_direction { self.run_length + 1 } else { 1 };
+                self.last_direction = direction;
+            }
+        }
+        self.last_price = Some(price);
+
+        let (quoted_spread, effective_spread_bps) = match self.last_quote {
+            Some((bid, ask)) => {
+                let mid = (bid + ask) / 2.0;
+                (Some(ask - bid), Some(2.0 * (price - mid).abs() / mid * 10_000.0))
+            }
+            None => (None, None),
+        };
+
+        let sample = TradeSample { timestamp, size: size.max(0) as f64, abs_price_change, quoted_spread, effective_spread_bps };
+        self.trade_size.push(sample.size);
+        if let Some(x) = sample.abs_price_change { self.abs_price_change.push(x); }
+        if let Some(x) = sample.quoted_spread { self.quoted_spread.push(x); }
+        if let Some(x) = sample.effective_spread_bps { self.effective_spread_bps.push(x); }
+        self.window.push_back(sample);
+        self.last_trade_at = self.last_trade_at.max(timestamp);
+
+        self.evict(timestamp);
+    }
+
+    /// Drop trades older than the window ending at `now` (ms)
+    pub fn evict(&mut self, now: i64) {
+        while let Some(oldest) = self.window.front() {
+            if oldest.timestamp > now - MICROSTRUCTURE_WINDOW_MS {
+                break;
+            }
+            let sample = self.window.pop_front().unwrap();
+            self.trade_size.remove(sample.size);
+            if let Some(x) = sample.abs_price_change { self.abs_price_change.remove(x); }
+            if let Some(x) = sample.quoted_spread { self.quoted_spread.remove(x); }
+            if let Some(x) = sample.effective_spread_bps { self.effective_spread_bps.remove(x); }
+        }
+    }
+
+    pub fn stats(&self, symbol: &str) -> MicrostructureStats {
+        let avg_trade_size = self.trade_size.mean();
+        MicrostructureStats {
+            symbol: symbol.to_string(),
+            window_seconds: (MICROSTRUCTURE_WINDOW_MS / 1000) as u64,
+            trade_count: self.window.len() as u64,
+            avg_trade_size,
+            trade_size_std: self.trade_size.std(),
+            tick_direction_runs: self.run_length,
+            bid_ask_spread_avg: self.quoted_spread.mean(),
+            effective_spread_bps: self.effective_spread_bps.mean(),
+            price_impact_per_1000_shares: if avg_trade_size > 0.0 {
+                self.abs_price_change.mean() / avg_trade_size * 1000.0
+            } else {
+                0.0
+            },
+            updated_at: self.last_trade_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_spread_and_quoted_spread() {
+        let mut tracker = MicrostructureTracker::default();
+        tracker.record_quote(99.95, 100.05); // mid 100, spread 0.10
+
+        // Trades at the ask and bid are 5 bps from mid: effective spread 10 bps
+        tracker.record_trade(100.05, 100, 1_000);
+        tracker.record_trade(99.95, 300, 2_000);
+        // A trade at mid has zero effective spread
+        tracker.record_trade(100.0, 200, 3_000);
+
+        let stats = tracker.stats("XYZ");
+        assert_eq!(stats.trade_count, 3);
+        assert!((stats.effective_spread_bps - 20.0 / 3.0).abs() < 1e-9, "{}", stats.effective_spread_bps);
+        assert!((stats.bid_ask_spread_avg - 0.10).abs() < 1e-9);
+        assert!((stats.avg_trade_size - 200.0).abs() < 1e-9);
+        assert!((stats.trade_size_std - 100.0).abs() < 1e-9);
+        // |moves| 0.10 and 0.05 per 200-share average trade
+        assert!((stats.price_impact_per_1000_shares - 0.075 / 200.0 * 1000.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_direction_runs_ignore_zero_ticks() {
+        let mut tracker = MicrostructureTracker::default();
+        for (i, price) in [10.0, 10.01, 10.02, 10.02, 10.03, 10.01, 10.00].iter().enumerate() {
+            tracker.record_trade(*price, 100, i as i64 * 1000);
+            if i == 4 {
+                assert_eq!(tracker.stats("XYZ").tick_direction_runs, 3);
+            }
+        }
+        assert_eq!(tracker.stats("XYZ").tick_direction_runs, 2);
+    }
+
+    #[test]
+    fn test_rolling_window_matches_fresh_computation() {
+        let mut tracker = MicrostructureTracker::default();
+        tracker.record_quote(49.90, 50.10);
+        tracker.record_trade(50.10, 1_000, 0);
+        tracker.record_trade(50.10, 1_000, 10_000);
+        tracker.record_quote(49.99, 50.01);
+
+        // The first two trades age out; only the tight-quote trades remain
+        tracker.record_trade(50.01, 100, 65_000);
+        tracker.record_trade(49.99, 300, 70_001);
+        let stats = tracker.stats("XYZ");
+        assert_eq!(stats.trade_count, 2);
+        assert!((stats.avg_trade_size - 200.0).abs() < 1e-9);
+        assert!((stats.bid_ask_spread_avg - 0.02).abs() < 1e-9);
+        assert!((stats.effective_spread_bps - 4.0).abs() < 1e-6, "{}", stats.effective_spread_bps);
+
+        tracker.evict(200_000);
+        let empty = tracker.stats("XYZ");
+        assert_eq!((empty.trade_count, empty.avg_trade_size, empty.effective_spread_bps), (0, 0.0, 0.0));
+    }
+}
@@ -7,13 +7,112 @@ use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 use futures_util::{SinkExt, StreamExt};
 use reqwest;
 use tauri::{AppHandle, Emitter, Manager};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use chrono::{DateTime, Utc, NaiveDateTime};
+use chrono::{DateTime, NaiveDate, Utc, NaiveDateTime};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::time::{sleep, Instant};
 
+use super::microstructure::{MicrostructureStats, MicrostructureStore};
+use super::tick_filter::{TickFilterHandle, TickVerdict};
+use crate::provider::diagnostics::{self, ParseFailure, ProviderResponse};
+use crate::engine::calendar::MarketCalendar;
+use crate::engine::position_history::session_date;
+use crate::storage::cache::FileCache;
+use crate::storage::journal_store::{self, JournalStore};
+use crate::engine::symbols::{normalize_symbol, provider_symbol, SymbolProvider};
+use crate::market_data::types::{epoch_seconds, volume_from_f64, Candle};
+
+const MICROSTRUCTURE_EMIT_SECONDS: u64 = 30;
+const TIER_STATS_EMIT_SECONDS: u64 = 60;
+
+/// Regular-session seconds since `last_tick_time`, so a symbol quiet overnight, over a
+/// weekend or on a holiday is not stale while the market is closed
+fn session_seconds_since(calendar: &MarketCalendar, last_tick_time: i64, now: i64) -> i64 {
+    calendar.session_seconds_between(last_tick_time, now, false)
+}
+
+/// How closely a streamed symbol is watched: open positions, then the watchlist, then
+/// everything else
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum SubscriptionTier {
+    Priority,
+    Standard,
+    Background,
+}
+
+impl SubscriptionTier {
+    pub const ALL: [SubscriptionTier; 3] = [SubscriptionTier::Priority, SubscriptionTier::Standard, SubscriptionTier::Background];
+
+    /// Seconds without a tick before the symbol counts as stale
+    pub fn stale_threshold_seconds(self) -> u64 {
+        match self {
+            SubscriptionTier::Priority => 5,
+            SubscriptionTier::Standard => 30,
+            SubscriptionTier::Background => 60,
+        }
+    }
+
+    /// "priority" | "standard" | "background"; "auto" or "" leaves the tier to `assign`
+    pub fn parse(name: &str) -> Result<Option<Self>, String> {
+        match name.trim().to_lowercase().as_str() {
+            "priority" => Ok(Some(SubscriptionTier::Priority)),
+            "standard" => Ok(Some(SubscriptionTier::Standard)),
+            "background" => Ok(Some(SubscriptionTier::Background)),
+            "auto" | "" => Ok(None),
+            other => Err(format!("Unknown subscription tier {} (expected priority, standard, background or auto)", other)),
+        }
+    }
+
+    /// Symbols with an open position are always Priority; otherwise the requested tier,
+    /// or Standard for watchlist symbols and Background for the rest
+    pub fn assign(symbol: &str, requested: Option<Self>, open_positions: &HashSet<String>, watchlist: &HashSet<String>) -> Self {
+        if open_positions.contains(symbol) {
+            SubscriptionTier::Priority
+        } else if let Some(tier) = requested {
+            tier
+        } else if watchlist.contains(symbol) {
+            SubscriptionTier::Standard
+        } else {
+            SubscriptionTier::Background
+        }
+    }
+}
+
+/// Tier per streamed symbol, shared between the stream task and the command layer.
+/// Symbols without an entry are treated as Standard.
+pub type SubscriptionTiers = Arc<Mutex<HashMap<String, SubscriptionTier>>>;
+
+fn tier_of(tiers: &HashMap<String, SubscriptionTier>, symbol: &str) -> SubscriptionTier {
+    tiers.get(symbol).copied().unwrap_or(SubscriptionTier::Standard)
+}
+
+/// Payload of the "subscription_tier_stats" event: stream messages per tier over the
+/// last `window_seconds`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SubscriptionTierStats {
+    pub window_seconds: u64,
+    pub messages: BTreeMap<SubscriptionTier, u64>,
+    pub symbols: BTreeMap<SubscriptionTier, usize>,
+    pub share_pct: BTreeMap<SubscriptionTier, f64>,
+}
+
+impl SubscriptionTierStats {
+    fn new(window_seconds: u64, messages: &HashMap<SubscriptionTier, u64>, tiers: &HashMap<String, SubscriptionTier>, symbols: &[String]) -> Self {
+        let total: u64 = messages.values().sum();
+        let mut stats = Self { window_seconds, ..Default::default() };
+        for tier in SubscriptionTier::ALL {
+            let count = messages.get(&tier).copied().unwrap_or(0);
+            stats.messages.insert(tier, count);
+            stats.symbols.insert(tier, symbols.iter().filter(|s| tier_of(tiers, s) == tier).count());
+            stats.share_pct.insert(tier, if total > 0 { count as f64 / total as f64 * 100.0 } else { 0.0 });
+        }
+        stats
+    }
+}
+
+/// Bar as the frontend receives it from commands and events
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OhlcBar {
     pub symbol: String,
@@ -25,6 +124,27 @@ pub struct OhlcBar {
     pub volume: i64,
 }
 
+impl From<&Candle> for OhlcBar {
+    fn from(candle: &Candle) -> Self {
+        Self {
+            symbol: candle.symbol.clone().unwrap_or_default(),
+            timestamp: candle.timestamp,
+            open: candle.open,
+            high: candle.high,
+            low: candle.low,
+            close: candle.close,
+            volume: candle.volume as i64,
+        }
+    }
+}
+
+impl OhlcBar {
+    /// REST aggregates have always reached the frontend with Polygon's millisecond timestamps
+    pub fn aggregate(candle: &Candle) -> Self {
+        Self { timestamp: candle.timestamp_millis(), ..Self::from(candle) }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RealTimeTick {
     pub symbol: String,
@@ -52,6 +172,37 @@ pub struct DataQuality {
     pub tick_count: u64,
     pub gap_detected: bool,
     pub last_backfill: Option<i64>,
+    // Microstructure over the last minute of trades
+    #[serde(default)]
+    pub avg_trade_size: f64,
+    #[serde(default)]
+    pub tick_direction_runs: u32,
+    #[serde(default)]
+    pub bid_ask_spread_avg: f64,
+    #[serde(default)]
+    pub price_impact_per_1000_shares: f64,
+    #[serde(default)]
+    pub quarantined_ticks: u64, // Prints held back by the tick filter
+}
+
+impl DataQuality {
+    /// Tracking for a newly subscribed symbol, fresh as of now
+    fn tracking(symbol: &str, tier: SubscriptionTier) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            last_tick_time: Utc::now().timestamp(),
+            is_stale: false,
+            stale_threshold_seconds: tier.stale_threshold_seconds(),
+            tick_count: 0,
+            gap_detected: false,
+            last_backfill: None,
+            avg_trade_size: 0.0,
+            tick_direction_runs: 0,
+            bid_ask_spread_avg: 0.0,
+            price_impact_per_1000_shares: 0.0,
+            quarantined_ticks: 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,7 +218,23 @@ pub struct BackfillRequest {
 struct PolygonOhlcResponse {
     results: Option<Vec<PolygonOhlcResult>>,
     status: String,
-    count: Option<i32>,
+    count: Option<u64>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl ProviderResponse for PolygonOhlcResponse {
+    const KNOWN_FIELDS: &'static [&'static str] = &[
+        "ticker", "queryCount", "resultsCount", "adjusted", "results", "status", "request_id", "count", "next_url",
+        "error", "message",
+    ];
+
+    fn validate(&self) -> Result<(), ParseFailure> {
+        diagnostics::check_status(Some(&self.status), &["OK", "DELAYED"], self.error.as_deref())?;
+        let results = self.results.as_deref().unwrap_or_default();
+        diagnostics::check_count("count", self.count, results.len())?;
+        diagnostics::check_monotonic(results.iter().map(|r| r.timestamp), |i| format!("results[{}].t", i))
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -88,6 +255,13 @@ struct PolygonOhlcResult {
     volume: f64,
 }
 
+impl From<PolygonOhlcResult> for Candle {
+    fn from(r: PolygonOhlcResult) -> Self {
+        Candle::new(epoch_seconds(r.timestamp), r.open, r.high, r.low, r.close, volume_from_f64(r.volume))
+            .with_symbol(r.symbol)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct PolygonTickMessage {
     #[serde(rename = "ev")]
@@ -102,6 +276,51 @@ struct PolygonTickMessage {
     timestamp: Option<i64>,
     #[serde(rename = "c")]
     conditions: Option<Vec<i32>>,
+    #[serde(rename = "bp")]
+    bid_price: Option<f64>, // Quote ("Q") events
+    #[serde(rename = "ap")]
+    ask_price: Option<f64>,
+}
+
+/// A change to a running stream's subscriptions, sent over the open socket rather than
+/// by reconnecting
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscriptionChange {
+    Subscribe(Vec<String>),
+    Unsubscribe(Vec<String>),
+}
+
+impl SubscriptionChange {
+    /// Socket message covering trades and quotes of every symbol in the change
+    fn message(&self) -> String {
+        let (action, symbols) = match self {
+            SubscriptionChange::Subscribe(symbols) => ("subscribe", symbols),
+            SubscriptionChange::Unsubscribe(symbols) => ("unsubscribe", symbols),
+        };
+        let params: Vec<String> = symbols.iter().map(|s| format!("T.{0},Q.{0}", s)).collect();
+        format!(r#"{{"action":"{}","params":"{}"}}"#, action, params.join(","))
+    }
+
+    /// Apply the change to a connection's symbol list
+    fn apply(&self, subscribed: &mut Vec<String>) {
+        match self {
+            SubscriptionChange::Subscribe(symbols) => {
+                let new: Vec<String> = symbols.iter().filter(|s| !subscribed.contains(s)).cloned().collect();
+                subscribed.extend(new);
+            }
+            SubscriptionChange::Unsubscribe(symbols) => subscribed.retain(|s| !symbols.contains(s)),
+        }
+    }
+}
+
+/// Handles the stream task shares with the provider and, through it, the command layer
+#[derive(Clone)]
+struct StreamShared {
+    connection_state: Arc<Mutex<ConnectionState>>,
+    data_quality: Arc<Mutex<HashMap<String, DataQuality>>>,
+    microstructure: MicrostructureStore,
+    tiers: SubscriptionTiers,
+    tick_filter: TickFilterHandle,
 }
 
 pub struct PolygonProvider {
@@ -112,7 +331,11 @@ pub struct PolygonProvider {
     stream_handle: Option<tokio::task::JoinHandle<()>>,
     connection_state: Arc<Mutex<ConnectionState>>,
     data_quality: Arc<Mutex<HashMap<String, DataQuality>>>,
+    microstructure: MicrostructureStore,
     subscribed_symbols: Arc<Mutex<Vec<String>>>,
+    subscription_changes: Option<mpsc::UnboundedSender<SubscriptionChange>>, // To the running stream task
+    tiers: SubscriptionTiers,
+    tick_filter: TickFilterHandle,
 }
 
 impl PolygonProvider {
@@ -135,27 +358,48 @@ impl PolygonProvider {
                 backoff_duration: 1, // Start with 1 second
             })),
             data_quality: Arc::new(Mutex::new(HashMap::new())),
+            microstructure: Arc::new(Mutex::new(HashMap::new())),
             subscribed_symbols: Arc::new(Mutex::new(Vec::new())),
+            subscription_changes: None,
+            tiers: Arc::new(Mutex::new(HashMap::new())),
+            tick_filter: TickFilterHandle::default(),
         }
     }
 
+    /// Read subscription tiers from a map that outlives this provider
+    pub fn with_subscription_tiers(mut self, tiers: SubscriptionTiers) -> Self {
+        self.tiers = tiers;
+        self
+    }
+
+    /// Record microstructure into a store that outlives this provider
+    pub fn with_microstructure(mut self, store: MicrostructureStore) -> Self {
+        self.microstructure = store;
+        self
+    }
+
+    /// Screen prints with a filter that outlives this provider
+    pub fn with_tick_filter(mut self, tick_filter: TickFilterHandle) -> Self {
+        self.tick_filter = tick_filter;
+        self
+    }
+
     pub async fn fetch_ohlc(
         &self,
         symbol: &str,
-        start_date: &str,
-        end_date: &str,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
         timeframe: &str,
-    ) -> Result<Vec<OhlcBar>, String> {
+    ) -> Result<Vec<Candle>, String> {
         let client = reqwest::Client::new();
-        
-        // Convert MM/DD/YYYY to YYYY-MM-DD
-        let start = self.convert_date_format(start_date)?;
-        let end = self.convert_date_format(end_date)?;
+        let start = start_date.format("%Y-%m-%d").to_string();
+        let end = end_date.format("%Y-%m-%d").to_string();
         
         let multiplier = match timeframe {
             "1D" => "1",
             "1H" => "1",
             "5M" => "5",
+            "1M" => "1",
             _ => "1",
         };
         
@@ -163,12 +407,13 @@ impl PolygonProvider {
             "1D" => "day",
             "1H" => "hour", 
             "5M" => "minute",
+            "1M" => "minute",
             _ => "day",
         };
         
         let url = format!(
             "{}/v2/aggs/ticker/{}/range/{}/{}/{}/{}?adjusted=true&sort=asc&apikey={}",
-            self.base_url, symbol, multiplier, timespan, start, end, self.api_key
+            self.base_url, provider_symbol(&normalize_symbol(symbol)?, SymbolProvider::Polygon), multiplier, timespan, start, end, self.api_key
         );
         
         println!("Fetching OHLC data from: {}", url.replace(&self.api_key, "***"));
@@ -183,27 +428,21 @@ impl PolygonProvider {
             return Err(format!("HTTP error: {}", response.status()));
         }
         
-        let polygon_response: PolygonOhlcResponse = response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse JSON: {}", e))?;
-            
-        if polygon_response.status != "OK" {
-            return Err(format!("Polygon API error: {}", polygon_response.status));
-        }
+        let text = response.text().await.map_err(|e| format!("HTTP request failed: {}", e))?;
+        let capture_dir = diagnostics::capture_dir(&self.app_handle);
+        let polygon_response: PolygonOhlcResponse = diagnostics::parse_response("polygon", "ohlc", &text, capture_dir.as_deref())?;
         
-        let results = polygon_response.results.unwrap_or_default();
-        let bars: Vec<OhlcBar> = results
+        let interval = match timeframe {
+            "1H" => "1h",
+            "5M" => "5m",
+            "1M" => "1m",
+            _ => "1d",
+        };
+        let bars: Vec<Candle> = polygon_response
+            .results
+            .unwrap_or_default()
             .into_iter()
-            .map(|r| OhlcBar {
-                symbol: r.symbol,
-                timestamp: r.timestamp,
-                open: r.open,
-                high: r.high,
-                low: r.low,
-                close: r.close,
-                volume: r.volume as i64,
-            })
+            .map(|r| Candle::from(r).with_interval(interval))
             .collect();
             
         println!("Fetched {} bars for {}", bars.len(), symbol);
@@ -214,18 +453,18 @@ impl PolygonProvider {
         &self,
         symbol: &str,
         minutes_back: i64,
-    ) -> Result<Vec<OhlcBar>, String> {
+    ) -> Result<Vec<Candle>, String> {
         let now = Utc::now();
         let start_time = now - chrono::Duration::minutes(minutes_back);
 
-        let start_date = start_time.format("%Y-%m-%d").to_string();
-        let end_date = now.format("%Y-%m-%d").to_string();
+        let start_date = start_time.date_naive();
+        let end_date = now.date_naive();
 
         println!("Backfilling {} from {} to {} ({} minutes)",
             symbol, start_date, end_date, minutes_back);
 
         // Fetch minute bars for backfill
-        let bars = self.fetch_ohlc(symbol, &start_date, &end_date, "1/minute").await?;
+        let bars = self.fetch_ohlc(symbol, start_date, end_date, "1/minute").await?;
 
         // Update data quality tracking
         {
@@ -237,7 +476,8 @@ impl PolygonProvider {
         }
 
         // Emit backfill data to frontend
-        if let Err(e) = self.app_handle.emit("backfill_data", &bars) {
+        let payload: Vec<OhlcBar> = bars.iter().map(OhlcBar::aggregate).collect();
+        if let Err(e) = self.app_handle.emit("backfill_data", &payload) {
             eprintln!("Failed to emit backfill data: {}", e);
         }
 
@@ -247,11 +487,12 @@ impl PolygonProvider {
     pub async fn check_data_staleness(&self) -> Vec<String> {
         let mut stale_symbols = Vec::new();
         let now = Utc::now().timestamp();
+        let calendar = MarketCalendar::new();
 
         {
             let mut quality_map = self.data_quality.lock().await;
             for (symbol, quality) in quality_map.iter_mut() {
-                let time_since_last_tick = now - quality.last_tick_time;
+                let time_since_last_tick = session_seconds_since(&calendar, quality.last_tick_time, now);
                 quality.is_stale = time_since_last_tick > quality.stale_threshold_seconds as i64;
 
                 if quality.is_stale {
@@ -272,10 +513,56 @@ impl PolygonProvider {
         stale_symbols
     }
 
+    /// Like `check_data_staleness`, but each symbol is stale after its tier's threshold.
+    /// Returns the stale symbols by tier.
+    pub async fn check_data_staleness_by_tier(&self) -> BTreeMap<SubscriptionTier, Vec<String>> {
+        let tiers = self.tiers.lock().await.clone();
+        let now = Utc::now().timestamp();
+        let calendar = MarketCalendar::new();
+        let mut stale: BTreeMap<SubscriptionTier, Vec<String>> = BTreeMap::new();
+
+        {
+            let mut quality_map = self.data_quality.lock().await;
+            for (symbol, quality) in quality_map.iter_mut() {
+                let tier = tier_of(&tiers, symbol);
+                quality.stale_threshold_seconds = tier.stale_threshold_seconds();
+                quality.is_stale = session_seconds_since(&calendar, quality.last_tick_time, now) > quality.stale_threshold_seconds as i64;
+                if quality.is_stale {
+                    stale.entry(tier).or_default().push(symbol.clone());
+                }
+            }
+        }
+        for symbols in stale.values_mut() {
+            symbols.sort();
+        }
+
+        if !stale.is_empty() {
+            let stale_symbols: Vec<&String> = stale.values().flatten().collect();
+            if let Err(e) = self.app_handle.emit("stale_data_alert", &stale_symbols) {
+                eprintln!("Failed to emit stale data alert: {}", e);
+            }
+        }
+
+        stale
+    }
+
+    /// Start streaming `symbols` at their tiers; the tiers replace any set before
+    pub async fn start_stream_tiered(&mut self, symbols: HashMap<String, SubscriptionTier>) -> Result<(), String> {
+        let mut tiered = HashMap::new();
+        for (symbol, tier) in symbols {
+            tiered.insert(normalize_symbol(&symbol)?, tier);
+        }
+        let mut names: Vec<String> = tiered.keys().cloned().collect();
+        names.sort();
+        *self.tiers.lock().await = tiered;
+        self.start_stream(names).await
+    }
+
     pub async fn start_stream(&mut self, symbols: Vec<String>) -> Result<(), String> {
         if self.stream_handle.is_some() {
             return Err("Stream already running".to_string());
         }
+        let symbols = symbols.iter().map(|s| normalize_symbol(s)).collect::<Result<Vec<_>, _>>()?;
 
         // Store subscribed symbols for reconnection
         {
@@ -285,42 +572,89 @@ impl PolygonProvider {
 
         // Initialize data quality tracking for symbols
         {
+            let tiers = self.tiers.lock().await;
             let mut quality_map = self.data_quality.lock().await;
             for symbol in &symbols {
-                quality_map.insert(symbol.clone(), DataQuality {
-                    symbol: symbol.clone(),
-                    last_tick_time: Utc::now().timestamp(),
-                    is_stale: false,
-                    stale_threshold_seconds: 30, // 30 seconds stale threshold
-                    tick_count: 0,
-                    gap_detected: false,
-                    last_backfill: None,
-                });
+                quality_map.insert(symbol.clone(), DataQuality::tracking(symbol, tier_of(&tiers, symbol)));
             }
         }
 
         let ws_url = format!("{}?apikey={}", self.ws_url, self.api_key);
         let app_handle = self.app_handle.clone();
-        let connection_state = self.connection_state.clone();
-        let data_quality = self.data_quality.clone();
+        let shared = StreamShared {
+            connection_state: self.connection_state.clone(),
+            data_quality: self.data_quality.clone(),
+            microstructure: self.microstructure.clone(),
+            tiers: self.tiers.clone(),
+            tick_filter: self.tick_filter.clone(),
+        };
         let subscribed_symbols = self.subscribed_symbols.clone();
+        let (changes_tx, changes) = mpsc::unbounded_channel();
 
         let handle = tokio::spawn(async move {
             Self::run_websocket_with_reconnect(
                 ws_url,
-                symbols,
                 app_handle,
-                connection_state,
-                data_quality,
+                shared,
                 subscribed_symbols,
+                changes,
             ).await;
         });
 
         self.stream_handle = Some(handle);
+        self.subscription_changes = Some(changes_tx);
+        Ok(())
+    }
+
+    /// Subscribe `subscribe` at their tiers and drop `unsubscribe` on the running stream,
+    /// over its open socket. Symbols already in the wanted state are left alone.
+    pub async fn update_subscriptions(
+        &mut self,
+        subscribe: HashMap<String, SubscriptionTier>,
+        unsubscribe: Vec<String>,
+    ) -> Result<(), String> {
+        let Some(changes) = self.subscription_changes.clone().filter(|_| self.is_streaming()) else {
+            return Err("Stream is not running".to_string());
+        };
+        let mut subscribed = self.subscribed_symbols.lock().await;
+        let mut tiers = self.tiers.lock().await;
+        let mut quality_map = self.data_quality.lock().await;
+
+        let mut added = Vec::new();
+        for (symbol, tier) in subscribe {
+            let symbol = normalize_symbol(&symbol)?;
+            tiers.insert(symbol.clone(), tier);
+            if !subscribed.contains(&symbol) && !added.contains(&symbol) {
+                quality_map.insert(symbol.clone(), DataQuality::tracking(&symbol, tier));
+                added.push(symbol);
+            }
+        }
+        let mut removed = Vec::new();
+        for symbol in unsubscribe {
+            let symbol = normalize_symbol(&symbol)?;
+            if subscribed.contains(&symbol) && !removed.contains(&symbol) {
+                tiers.remove(&symbol);
+                quality_map.remove(&symbol);
+                removed.push(symbol);
+            }
+        }
+
+        added.sort();
+        removed.sort();
+        let pending = [
+            (!added.is_empty()).then_some(SubscriptionChange::Subscribe(added)),
+            (!removed.is_empty()).then_some(SubscriptionChange::Unsubscribe(removed)),
+        ];
+        for change in pending.into_iter().flatten() {
+            // Reconnects resubscribe from this list
+            change.apply(&mut subscribed);
+            changes.send(change).map_err(|_| "Stream task has stopped".to_string())?;
+        }
         Ok(())
     }
 
     pub async fn stop_stream(&mut self) -> Result<(), String> {
+        self.subscription_changes = None;
         if let Some(handle) = self.stream_handle.take() {
             handle.abort();
             println!("Stream stopped");
@@ -335,21 +669,23 @@ impl PolygonProvider {
         Ok(())
     }
 
+    /// Stream the subscribed symbols, reconnecting with backoff
     async fn run_websocket_with_reconnect(
         ws_url: String,
-        symbols: Vec<String>,
         app_handle: AppHandle,
-        connection_state: Arc<Mutex<ConnectionState>>,
-        data_quality: Arc<Mutex<HashMap<String, DataQuality>>>,
+        shared: StreamShared,
         subscribed_symbols: Arc<Mutex<Vec<String>>>,
+        mut changes: mpsc::UnboundedReceiver<SubscriptionChange>,
     ) {
+        let connection_state = shared.connection_state.clone();
         loop {
+            let symbols = subscribed_symbols.lock().await.clone();
             let result = Self::run_websocket_connection(
                 &ws_url,
-                &symbols,
+                symbols,
                 &app_handle,
-                connection_state.clone(),
-                data_quality.clone(),
+                shared.clone(),
+                &mut changes,
             ).await;
 
             // Update connection state
@@ -415,11 +751,12 @@ impl PolygonProvider {
 
     async fn run_websocket_connection(
         ws_url: &str,
-        symbols: &[String],
+        mut symbols: Vec<String>,
         app_handle: &AppHandle,
-        connection_state: Arc<Mutex<ConnectionState>>,
-        data_quality: Arc<Mutex<HashMap<String, DataQuality>>>,
+        shared: StreamShared,
+        changes: &mut mpsc::UnboundedReceiver<SubscriptionChange>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let StreamShared { connection_state, data_quality, microstructure, tiers, tick_filter } = shared;
         println!("Connecting to WebSocket: {}", ws_url.replace("apikey=", "apikey=***"));
         
         let (ws_stream, _) = connect_async(ws_url).await?;
@@ -435,25 +772,82 @@ impl PolygonProvider {
         }
 
         // Subscribe to symbols
-        for symbol in symbols {
-            let subscribe_msg = format!(r#"{{"action":"subscribe","params":"T.{}"}}"#, symbol);
+        for symbol in &symbols {
+            // Trades, plus quotes for spread statistics
+            let subscribe_msg = format!(r#"{{"action":"subscribe","params":"T.{0},Q.{0}"}}"#, symbol);
             ws_sender.send(Message::Text(subscribe_msg)).await?;
             println!("Subscribed to {}", symbol);
         }
 
         // Emit connection status
         let _ = app_handle.emit("stream_connected", &symbols);
+
+        // Daily closes for judging the first print after a gap
+        let journal = FileCache::new(app_handle).ok().map(|storage| {
+            let backend = journal_store::configured_backend(&storage);
+            journal_store::open_store(storage, backend)
+        });
         
-        // Process incoming messages
-        while let Some(msg) = ws_receiver.next().await {
+        // Process incoming messages; publish microstructure stats on a timer
+        let mut stats_timer = tokio::time::interval(Duration::from_secs(MICROSTRUCTURE_EMIT_SECONDS));
+        stats_timer.tick().await; // The first tick completes immediately
+        let mut tier_timer = tokio::time::interval(Duration::from_secs(TIER_STATS_EMIT_SECONDS));
+        tier_timer.tick().await;
+        let mut tier_messages: HashMap<SubscriptionTier, u64> = HashMap::new();
+
+        loop {
+            let msg = tokio::select! {
+                msg = ws_receiver.next() => msg,
+                _ = stats_timer.tick() => {
+                    Self::emit_microstructure(app_handle, &microstructure).await;
+                    continue;
+                }
+                _ = tier_timer.tick() => {
+                    let stats = SubscriptionTierStats::new(TIER_STATS_EMIT_SECONDS, &tier_messages, &*tiers.lock().await, &symbols);
+                    let _ = app_handle.emit("subscription_tier_stats", &stats);
+                    tier_messages.clear();
+                    continue;
+                }
+                Some(change) = changes.recv() => {
+                    change.apply(&mut symbols);
+                    ws_sender.send(Message::Text(change.message())).await?;
+                    let _ = app_handle.emit("stream_subscriptions_changed", &symbols);
+                    continue;
+                }
+            };
+            let Some(msg) = msg else { break };
+
             match msg? {
                 Message::Text(text) => {
                     if let Ok(tick_msgs) = serde_json::from_str::<Vec<PolygonTickMessage>>(&text) {
+                        {
+                            let tiers = tiers.lock().await;
+                            for symbol in tick_msgs.iter().filter_map(|m| m.symbol.as_deref()) {
+                                *tier_messages.entry(tier_of(&tiers, symbol)).or_insert(0) += 1;
+                            }
+                        }
                         for tick_msg in tick_msgs {
-                            if tick_msg.event_type == "T" {
+                            if tick_msg.event_type == "Q" {
+                                if let (Some(symbol), Some(bid), Some(ask)) =
+                                    (tick_msg.symbol, tick_msg.bid_price, tick_msg.ask_price) {
+                                    microstructure.lock().await.entry(symbol).or_default().record_quote(bid, ask);
+                                }
+                            } else if tick_msg.event_type == "T" {
                                 if let (Some(symbol), Some(price), Some(timestamp)) = 
                                     (tick_msg.symbol, tick_msg.price, tick_msg.timestamp) {
-                                    
+
+                                    // Bad prints stop here, before they reach microstructure, bars or the broker
+                                    let verdict = tick_filter.lock().await.check(&symbol, price, timestamp, || {
+                                        journal.as_deref().and_then(|j| last_daily_close(j, &symbol, timestamp / 1000))
+                                    });
+                                    if let TickVerdict::Quarantined(suspect) = verdict {
+                                        if let Some(quality) = data_quality.lock().await.get_mut(&symbol) {
+                                            quality.quarantined_ticks += 1;
+                                        }
+                                        let _ = app_handle.emit("suspect_tick", &suspect);
+                                        continue;
+                                    }
+
                                     let tick = RealTimeTick {
                                         symbol: symbol.clone(),
                                         price,
@@ -462,14 +856,21 @@ impl PolygonProvider {
                                         conditions: tick_msg.conditions.unwrap_or_default(),
                                     };
 
+                                    let stats = {
+                                        let mut trackers = microstructure.lock().await;
+                                        let tracker = trackers.entry(symbol.clone()).or_default();
+                                        tracker.record_trade(price, tick.size, timestamp);
+                                        tracker.stats(&symbol)
+                                    };
+
                                     // Update data quality tracking
                                     {
                                         let mut quality_map = data_quality.lock().await;
                                         if let Some(quality) = quality_map.get_mut(&symbol) {
                                             let now = Utc::now().timestamp();
 
-                                            // Check for gaps (more than 2x the stale threshold)
-                                            let time_since_last = now - quality.last_tick_time;
+                                            // Check for gaps (more than 2x the stale threshold of session time)
+                                            let time_since_last = session_seconds_since(&MarketCalendar::new(), quality.last_tick_time, now);
                                             if time_since_last > (quality.stale_threshold_seconds * 2) as i64 {
                                                 quality.gap_detected = true;
                                                 println!("Data gap detected for {}: {} seconds", symbol, time_since_last);
@@ -478,6 +879,10 @@ impl PolygonProvider {
                                             quality.last_tick_time = now;
                                             quality.tick_count += 1;
                                             quality.is_stale = false;
+                                            quality.avg_trade_size = stats.avg_trade_size;
+                                            quality.tick_direction_runs = stats.tick_direction_runs;
+                                            quality.bid_ask_spread_avg = stats.bid_ask_spread_avg;
+                                            quality.price_impact_per_1000_shares = stats.price_impact_per_1000_shares;
                                         }
                                     }
 
@@ -506,18 +911,25 @@ impl PolygonProvider {
         Ok(())
     }
 
-    fn convert_date_format(&self, date: &str) -> Result<String, String> {
-        // Convert MM/DD/YYYY to YYYY-MM-DD
-        let parts: Vec<&str> = date.split('/').collect();
-        if parts.len() != 3 {
-            return Err(format!("Invalid date format: {}", date));
+    async fn emit_microstructure(app_handle: &AppHandle, microstructure: &MicrostructureStore) {
+        let now = Utc::now().timestamp_millis();
+        let mut trackers = microstructure.lock().await;
+        for (symbol, tracker) in trackers.iter_mut() {
+            tracker.evict(now);
+            let _ = app_handle.emit("microstructure_update", &serde_json::json!({
+                "symbol": symbol,
+                "stats": tracker.stats(symbol),
+            }));
         }
-        
-        let month = parts[0].parse::<u32>().map_err(|_| "Invalid month")?;
-        let day = parts[1].parse::<u32>().map_err(|_| "Invalid day")?;
-        let year = parts[2].parse::<u32>().map_err(|_| "Invalid year")?;
-        
-        Ok(format!("{:04}-{:02}-{:02}", year, month, day))
+    }
+
+    /// Whether the stream task started by `start_stream` is still running
+    pub fn is_streaming(&self) -> bool {
+        self.stream_handle.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
+    pub async fn subscribed_symbols(&self) -> Vec<String> {
+        self.subscribed_symbols.lock().await.clone()
     }
 
     pub async fn get_connection_status(&self) -> ConnectionState {
@@ -533,7 +945,7 @@ impl PolygonProvider {
             let quality_map = self.data_quality.lock().await;
             if let Some(quality) = quality_map.get(symbol) {
                 let now = Utc::now().timestamp();
-                let time_since_last = now - quality.last_tick_time;
+                let time_since_last = session_seconds_since(&MarketCalendar::new(), quality.last_tick_time, now);
                 return time_since_last > quality.stale_threshold_seconds as i64;
             }
         }
@@ -559,6 +971,21 @@ impl PolygonProvider {
     }
 }
 
+/// Latest 1-minute stats for `symbol`, if it has streamed any trades or quotes
+/// Close of the last daily bar cached for `symbol` from a session before `timestamp`'s
+fn last_daily_close(journal: &dyn JournalStore, symbol: &str, timestamp: i64) -> Option<f64> {
+    let session_start = journal_store::session_start(session_date(timestamp))?;
+    let bars = journal.query_bars(symbol, "1d", session_start - 10 * 86_400, session_start - 1).ok()?;
+    bars.last().map(|b| b.close)
+}
+
+pub async fn microstructure_stats(store: &MicrostructureStore, symbol: &str) -> Option<MicrostructureStats> {
+    let mut trackers = store.lock().await;
+    let tracker = trackers.get_mut(symbol)?;
+    tracker.evict(Utc::now().timestamp_millis());
+    Some(tracker.stats(symbol))
+}
+
 // Helper function to get app config directory
 pub fn get_config_path(app_handle: &AppHandle) -> Result<std::path::PathBuf, String> {
     app_handle
@@ -566,3 +993,65 @@ pub fn get_config_path(app_handle: &AppHandle) -> Result<std::path::PathBuf, Str
         .app_config_dir()
         .map_err(|e| format!("Failed to get app config directory: {}", e))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clearing_a_stream_flag_unsubscribes_on_the_open_socket() {
+        use crate::engine::watchlist::{StreamDelta, SymbolFlags, Watchlists, DEFAULT_WATCHLIST};
+
+        let mut lists = Watchlists::default();
+        lists.add(DEFAULT_WATCHLIST, "AAPL", SymbolFlags::default()).unwrap();
+        lists.add(DEFAULT_WATCHLIST, "MSFT", SymbolFlags::default()).unwrap();
+        let before = lists.clone();
+        lists.set_flags(DEFAULT_WATCHLIST, "AAPL", SymbolFlags { stream: false, ..SymbolFlags::default() }).unwrap();
+        let delta = StreamDelta::between(&before, &lists);
+        assert!(delta.subscribe.is_empty());
+
+        // The connection's task applies the change in place and tells the socket; the
+        // connection itself, and MSFT's subscription, carry on
+        let mut connection_symbols = vec!["AAPL".to_string(), "MSFT".to_string()];
+        let change = SubscriptionChange::Unsubscribe(delta.unsubscribe);
+        change.apply(&mut connection_symbols);
+        assert_eq!(connection_symbols, vec!["MSFT"]);
+        assert_eq!(change.message(), r#"{"action":"unsubscribe","params":"T.AAPL,Q.AAPL"}"#);
+
+        let change = SubscriptionChange::Subscribe(vec!["MSFT".to_string(), "NVDA".to_string()]);
+        change.apply(&mut connection_symbols);
+        assert_eq!(connection_symbols, vec!["MSFT", "NVDA"]);
+        assert_eq!(change.message(), r#"{"action":"subscribe","params":"T.MSFT,Q.MSFT,T.NVDA,Q.NVDA"}"#);
+    }
+
+    #[test]
+    fn test_tier_assignment_and_stale_thresholds() {
+        let positions: HashSet<String> = ["AAPL".to_string()].into();
+        let watchlist: HashSet<String> = ["AAPL".to_string(), "MSFT".to_string()].into();
+        let assign = |symbol: &str, requested| SubscriptionTier::assign(symbol, requested, &positions, &watchlist);
+
+        assert_eq!(assign("AAPL", None), SubscriptionTier::Priority);
+        // An open position outranks the requested tier
+        assert_eq!(assign("AAPL", Some(SubscriptionTier::Background)), SubscriptionTier::Priority);
+        assert_eq!(assign("MSFT", None), SubscriptionTier::Standard);
+        assert_eq!(assign("MSFT", Some(SubscriptionTier::Background)), SubscriptionTier::Background);
+        assert_eq!(assign("SPY", None), SubscriptionTier::Background);
+
+        assert_eq!(SubscriptionTier::parse(" Priority ").unwrap(), Some(SubscriptionTier::Priority));
+        assert_eq!(SubscriptionTier::parse("auto").unwrap(), None);
+        assert!(SubscriptionTier::parse("urgent").is_err());
+
+        let thresholds: Vec<u64> = SubscriptionTier::ALL.iter().map(|t| t.stale_threshold_seconds()).collect();
+        assert_eq!(thresholds, vec![5, 30, 60]);
+
+        let tiers: HashMap<String, SubscriptionTier> =
+            [("AAPL".to_string(), SubscriptionTier::Priority), ("SPY".to_string(), SubscriptionTier::Background)].into();
+        assert_eq!(tier_of(&tiers, "QQQ"), SubscriptionTier::Standard);
+        let messages: HashMap<SubscriptionTier, u64> = [(SubscriptionTier::Priority, 30), (SubscriptionTier::Background, 10)].into();
+        let symbols = vec!["AAPL".to_string(), "SPY".to_string(), "QQQ".to_string()];
+        let stats = SubscriptionTierStats::new(60, &messages, &tiers, &symbols);
+        assert_eq!(stats.messages[&SubscriptionTier::Standard], 0);
+        assert_eq!(stats.symbols[&SubscriptionTier::Standard], 1);
+        assert_eq!(stats.share_pct[&SubscriptionTier::Priority], 75.0);
+    }
+}
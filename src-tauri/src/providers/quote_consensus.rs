@@ -0,0 +1,390 @@
+// src-tauri/src/providers/quote_consensus.rs
+// Cross-check of the primary quote provider against a second one. The same symbols are
+// fetched from both and their last prices compared; a gap beyond the threshold is a
+// discrepancy, logged with both quotes and their ages and sent as a "quote_discrepancy"
+// event. When the primary keeps being the staler side it is marked degraded, and history
+// requests try the fallback provider first until the two agree again.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, OpenOptions};
+use std::future::Future;
+use std::io::Write;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::engine::symbols::normalize_symbol;
+
+pub const DISCREPANCY_LOG_FILE: &str = "quote_discrepancies.jsonl";
+const RECENT_DISCREPANCIES_KEPT: usize = 100;
+
+/// Shared by the verify_quotes command and the periodic check
+pub type QuoteConsensusHandle = Arc<Mutex<QuoteConsensus>>;
+
+/// A provider's last price for a symbol
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProviderQuote {
+    pub provider: String,
+    pub price: f64,
+    pub timestamp: i64, // Epoch seconds of the trade
+}
+
+pub type QuoteFuture<'a> = Pin<Box<dyn Future<Output = Result<ProviderQuote, String>> + Send + 'a>>;
+
+pub trait QuoteSource: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn last_quote<'a>(&'a self, symbol: &'a str) -> QuoteFuture<'a>;
+}
+
+/// Polygon's last trade; each quote is one call against the daily budget
+pub struct PolygonQuotes(pub tauri::AppHandle);
+
+impl QuoteSource for PolygonQuotes {
+    fn name(&self) -> &'static str {
+        "polygon"
+    }
+
+    fn last_quote<'a>(&'a self, symbol: &'a str) -> QuoteFuture<'a> {
+        Box::pin(async move {
+            let (price, timestamp) = crate::provider::polygon::fetch_last_trade(&self.0, symbol).await?;
+            Ok(ProviderQuote { provider: self.name().to_string(), price, timestamp })
+        })
+    }
+}
+
+/// Yahoo's last regular-market price
+pub struct YahooQuotes(pub Option<PathBuf>); // Capture dir for unparseable responses
+
+impl QuoteSource for YahooQuotes {
+    fn name(&self) -> &'static str {
+        "yahoo"
+    }
+
+    fn last_quote<'a>(&'a self, symbol: &'a str) -> QuoteFuture<'a> {
+        Box::pin(async move {
+            let (price, timestamp) = crate::provider::yahoo::yahoo_last_price(symbol, self.0.as_deref()).await?;
+            Ok(ProviderQuote { provider: self.name().to_string(), price, timestamp })
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct QuoteConsensusConfig {
+    pub enabled: bool,            // Run the periodic check
+    pub interval_minutes: u64,
+    pub symbols: Vec<String>,
+    pub threshold_pct: f64,       // Relative gap between the last prices that counts as a discrepancy
+    pub stale_lag_seconds: i64,   // The side whose quote is this much older is the stale one
+    pub degrade_after: u32,       // Consecutive discrepancies with the primary stale before it is marked degraded
+    pub mark_degraded: bool,
+    pub reserved_calls: u32,      // Daily primary calls the periodic check leaves for everything else
+}
+
+impl Default for QuoteConsensusConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_minutes: 15,
+            symbols: Vec::new(),
+            threshold_pct: 0.005,
+            stale_lag_seconds: 60,
+            degrade_after: 3,
+            mark_degraded: true,
+            reserved_calls: 25,
+        }
+    }
+}
+
+impl QuoteConsensusConfig {
+    /// Check the settings and normalize the symbols
+    pub fn validated(mut self) -> Result<Self, String> {
+        if self.interval_minutes == 0 {
+            return Err("interval_minutes must be at least 1".to_string());
+        }
+        if self.threshold_pct.is_nan() || self.threshold_pct <= 0.0 {
+            return Err("threshold_pct must be positive".to_string());
+        }
+        if self.stale_lag_seconds < 0 || self.degrade_after == 0 {
+            return Err("stale_lag_seconds must not be negative and degrade_after must be at least 1".to_string());
+        }
+        self.symbols = self.symbols.iter().filter(|s| !s.trim().is_empty()).map(|s| normalize_symbol(s)).collect::<Result<_, _>>()?;
+        self.symbols.dedup();
+        Ok(self)
+    }
+}
+
+/// One symbol compared across the two providers
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuoteCheck {
+    pub symbol: String,
+    pub checked_at: i64,
+    pub primary: Option<ProviderQuote>,
+    pub secondary: Option<ProviderQuote>,
+    pub primary_age_seconds: Option<i64>,
+    pub secondary_age_seconds: Option<i64>,
+    pub divergence_pct: Option<f64>, // |primary - secondary| / secondary
+    pub discrepancy: bool,
+    pub stale_provider: Option<String>, // On a discrepancy, the side with the clearly older quote
+    pub error: Option<String>,          // Either provider failed; nothing was compared
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DegradedProvider {
+    pub provider: String,
+    pub since: i64,
+    pub reason: String,
+}
+
+/// What the verify_quotes command returns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteVerification {
+    pub checks: Vec<QuoteCheck>,
+    pub degraded: Option<DegradedProvider>, // Marked degraded by this run
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteDiagnostics {
+    pub config: QuoteConsensusConfig,
+    pub degraded: Vec<DegradedProvider>,
+    pub stale_streaks: BTreeMap<String, u32>, // Consecutive discrepancies each provider was the stale side of
+    pub recent_discrepancies: Vec<QuoteCheck>, // Newest first
+    pub last_run: Option<i64>,
+}
+
+/// Compare the two providers' quotes for `symbol` at `now`
+pub fn compare(
+    symbol: &str,
+    primary: Result<ProviderQuote, String>,
+    secondary: Result<ProviderQuote, String>,
+    now: i64,
+    config: &QuoteConsensusConfig,
+) -> QuoteCheck {
+    let mut check = QuoteCheck {
+        symbol: symbol.to_string(),
+        checked_at: now,
+        primary: None,
+        secondary: None,
+        primary_age_seconds: None,
+        secondary_age_seconds: None,
+        divergence_pct: None,
+        discrepancy: false,
+        stale_provider: None,
+        error: None,
+    };
+    let (primary, secondary) = match (primary, secondary) {
+        (Ok(primary), Ok(secondary)) => (primary, secondary),
+        (primary, secondary) => {
+            check.error = Some([primary.err(), secondary.err()].into_iter().flatten().collect::<Vec<_>>().join("; "));
+            return check;
+        }
+    };
+    let divergence = (primary.price - secondary.price).abs() / secondary.price;
+    check.discrepancy = divergence > config.threshold_pct;
+    if check.discrepancy {
+        let lag = secondary.timestamp - primary.timestamp;
+        if lag > config.stale_lag_seconds {
+            check.stale_provider = Some(primary.provider.clone());
+        } else if -lag > config.stale_lag_seconds {
+            check.stale_provider = Some(secondary.provider.clone());
+        }
+    }
+    check.divergence_pct = Some(divergence);
+    check.primary_age_seconds = Some(now - primary.timestamp);
+    check.secondary_age_seconds = Some(now - secondary.timestamp);
+    check.primary = Some(primary);
+    check.secondary = Some(secondary);
+    check
+}
+
+pub struct QuoteConsensus {
+    config: QuoteConsensusConfig,
+    stale_streaks: BTreeMap<String, u32>,
+    degraded: BTreeMap<String, DegradedProvider>,
+    recent: VecDeque<QuoteCheck>, // Discrepancies, newest last
+    last_run: Option<i64>,
+    log_path: PathBuf,
+}
+
+impl QuoteConsensus {
+    pub fn new(config: QuoteConsensusConfig, log_path: PathBuf) -> Self {
+        Self {
+            config,
+            stale_streaks: BTreeMap::new(),
+            degraded: BTreeMap::new(),
+            recent: VecDeque::new(),
+            last_run: None,
+            log_path,
+        }
+    }
+
+    pub fn config(&self) -> &QuoteConsensusConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: QuoteConsensusConfig) -> Result<(), String> {
+        self.config = config.validated()?;
+        Ok(())
+    }
+
+    pub fn is_degraded(&self, provider: &str) -> bool {
+        self.degraded.contains_key(provider)
+    }
+
+    /// Track a comparison against `primary`: a discrepancy is logged, and the primary is
+    /// marked degraded once it has been the stale side `degrade_after` times running.
+    /// A check where the two agree clears the streak and the degraded mark. Returns the
+    /// provider newly marked degraded.
+    pub fn record(&mut self, check: &QuoteCheck, primary: &str) -> Option<DegradedProvider> {
+        self.last_run = Some(check.checked_at);
+        if check.error.is_some() {
+            return None;
+        }
+        if !check.discrepancy {
+            self.stale_streaks.remove(primary);
+            self.degraded.remove(primary);
+            return None;
+        }
+
+        if let Err(e) = self.append_log(check) {
+            eprintln!("Failed to log quote discrepancy: {}", e);
+        }
+        self.recent.push_back(check.clone());
+        while self.recent.len() > RECENT_DISCREPANCIES_KEPT {
+            self.recent.pop_front();
+        }
+
+        if check.stale_provider.as_deref() != Some(primary) {
+            self.stale_streaks.remove(primary);
+            return None;
+        }
+        let streak = self.stale_streaks.entry(primary.to_string()).or_insert(0);
+        *streak += 1;
+        if !self.config.mark_degraded || *streak < self.config.degrade_after || self.degraded.contains_key(primary) {
+            return None;
+        }
+        let degraded = DegradedProvider {
+            provider: primary.to_string(),
+            since: check.checked_at,
+            reason: format!("the stale side of {} quote discrepancies in a row, the last on {}", streak, check.symbol),
+        };
+        self.degraded.insert(primary.to_string(), degraded.clone());
+        Some(degraded)
+    }
+
+    fn append_log(&self, check: &QuoteCheck) -> Result<(), String> {
+        if let Some(dir) = self.log_path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let line = serde_json::to_string(check).map_err(|e| e.to_string())?;
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.log_path).map_err(|e| e.to_string())?;
+        writeln!(file, "{}", line).map_err(|e| e.to_string())
+    }
+
+    pub fn diagnostics(&self) -> QuoteDiagnostics {
+        QuoteDiagnostics {
+            config: self.config.clone(),
+            degraded: self.degraded.values().cloned().collect(),
+            stale_streaks: self.stale_streaks.clone(),
+            recent_discrepancies: self.recent.iter().rev().cloned().collect(),
+            last_run: self.last_run,
+        }
+    }
+}
+
+/// Fetch `symbols` from both providers and record each comparison
+pub async fn verify(
+    consensus: &QuoteConsensusHandle,
+    primary: &dyn QuoteSource,
+    secondary: &dyn QuoteSource,
+    symbols: &[String],
+    now: i64,
+) -> QuoteVerification {
+    let mut verification = QuoteVerification { checks: Vec::new(), degraded: None };
+    for symbol in symbols {
+        let (primary_quote, secondary_quote) = tokio::join!(primary.last_quote(symbol), secondary.last_quote(symbol));
+        let mut consensus = consensus.lock().await;
+        let check = compare(symbol, primary_quote, secondary_quote, now, consensus.config());
+        if let Some(degraded) = consensus.record(&check, primary.name()) {
+            verification.degraded = Some(degraded);
+        }
+        verification.checks.push(check);
+    }
+    verification
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Serves fixed quotes, one per call, repeating the last
+    struct MockSource {
+        name: &'static str,
+        quotes: std::sync::Mutex<VecDeque<(f64, i64)>>,
+    }
+
+    impl MockSource {
+        fn new(name: &'static str, quotes: &[(f64, i64)]) -> Self {
+            Self { name, quotes: std::sync::Mutex::new(quotes.iter().copied().collect()) }
+        }
+    }
+
+    impl QuoteSource for MockSource {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn last_quote<'a>(&'a self, _symbol: &'a str) -> QuoteFuture<'a> {
+            let mut quotes = self.quotes.lock().unwrap();
+            let quote = if quotes.len() > 1 { quotes.pop_front() } else { quotes.front().copied() }
+                .map(|(price, timestamp)| ProviderQuote { provider: self.name.to_string(), price, timestamp })
+                .ok_or_else(|| "no quote".to_string());
+            Box::pin(async move { quote })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_a_stuck_primary_is_marked_degraded_and_recovers() {
+        let dir = std::env::temp_dir().join(format!("quote_consensus_{}", uuid::Uuid::new_v4()));
+        let log_path = dir.join(DISCREPANCY_LOG_FILE);
+        let config = QuoteConsensusConfig { degrade_after: 2, ..QuoteConsensusConfig::default() };
+        let consensus: QuoteConsensusHandle = Arc::new(Mutex::new(QuoteConsensus::new(config, log_path.clone())));
+        let now = 1_704_207_600;
+        let symbols = vec!["AAPL".to_string()];
+
+        // The primary is stuck at a price five minutes old while the secondary moves on
+        let primary = MockSource::new("polygon", &[(150.0, now - 300), (150.0, now - 300), (150.0, now - 300), (152.0, now)]);
+        let secondary = MockSource::new("yahoo", &[(151.5, now), (152.0, now), (152.5, now), (152.05, now)]);
+
+        let first = verify(&consensus, &primary, &secondary, &symbols, now).await;
+        let check = &first.checks[0];
+        assert!(check.discrepancy && first.degraded.is_none());
+        assert_eq!((check.stale_provider.as_deref(), check.primary_age_seconds), (Some("polygon"), Some(300)));
+        assert!((check.divergence_pct.unwrap() - 1.5 / 151.5).abs() < 1e-12);
+
+        let second = verify(&consensus, &primary, &secondary, &symbols, now).await;
+        assert_eq!(second.degraded.unwrap().provider, "polygon");
+        assert!(consensus.lock().await.is_degraded("polygon"));
+
+        // Already degraded: not reported again
+        assert!(verify(&consensus, &primary, &secondary, &symbols, now).await.degraded.is_none());
+        let diagnostics = consensus.lock().await.diagnostics();
+        assert_eq!((diagnostics.recent_discrepancies.len(), diagnostics.stale_streaks["polygon"]), (3, 3));
+        assert_eq!(fs::read_to_string(&log_path).unwrap().lines().count(), 3);
+
+        // Agreement clears the mark
+        let agreed = verify(&consensus, &primary, &secondary, &symbols, now).await;
+        assert!(!agreed.checks[0].discrepancy);
+        assert!(!consensus.lock().await.is_degraded("polygon"));
+
+        // A failed fetch compares nothing; a fresh primary that disagrees is not the stale side
+        let failing = MockSource::new("polygon", &[]);
+        assert!(verify(&consensus, &failing, &secondary, &symbols, now).await.checks[0].error.is_some());
+        let fresh = compare("AAPL", Ok(ProviderQuote { provider: "polygon".into(), price: 160.0, timestamp: now }), Ok(ProviderQuote { provider: "yahoo".into(), price: 150.0, timestamp: now - 10 }), now, &QuoteConsensusConfig::default());
+        assert!(fresh.discrepancy && fresh.stale_provider.is_none());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
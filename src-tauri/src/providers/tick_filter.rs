@@ -0,0 +1,276 @@
+// src-tauri/src/providers/tick_filter.rs
+// Sanity filter for streamed trade prints. A print further from the last accepted price
+// than a multiple of recent realized volatility, or outside a hard percentage band, is
+// quarantined: it is not published, so it can neither fill orders nor mark positions.
+// A second print near it within a short window confirms it as a real move. The first
+// print after a long gap is judged against the last daily close instead.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// The filter shared by every stream the registry starts, so its state survives reconnects
+pub type TickFilterHandle = Arc<Mutex<TickFilter>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TickFilterConfig {
+    pub enabled: bool,
+    pub volatility_multiple: f64,   // Allowed move as a multiple of the std dev of recent print-to-print returns
+    pub volatility_window: usize,   // Returns kept for that std dev
+    pub min_samples: usize,         // Fewer returns than this and only the hard band applies
+    pub min_deviation_pct: f64,     // Moves within this are never suspect, however quiet the tape
+    pub max_deviation_pct: f64,     // Hard band: moves beyond this are always suspect
+    pub confirm_window_ms: i64,     // A suspect print is confirmed by another within this
+    pub confirm_tolerance_pct: f64, // and this close to it
+    pub gap_seconds: i64,           // After this long without an accepted print, judge against the daily close
+}
+
+impl Default for TickFilterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            volatility_multiple: 12.0,
+            volatility_window: 100,
+            min_samples: 20,
+            min_deviation_pct: 0.01,
+            max_deviation_pct: 0.10,
+            confirm_window_ms: 5_000,
+            confirm_tolerance_pct: 0.005,
+            gap_seconds: 15 * 60,
+        }
+    }
+}
+
+impl TickFilterConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.volatility_multiple.is_nan() || self.volatility_multiple <= 0.0 {
+            return Err("volatility_multiple must be positive".to_string());
+        }
+        if self.volatility_window < 2 || self.min_samples < 2 || self.min_samples > self.volatility_window {
+            return Err("min_samples must be at least 2 and no more than volatility_window".to_string());
+        }
+        if self.min_deviation_pct.is_nan() || self.min_deviation_pct < 0.0 || self.min_deviation_pct > self.max_deviation_pct {
+            return Err("min_deviation_pct must be between 0 and max_deviation_pct".to_string());
+        }
+        if self.confirm_window_ms <= 0 || self.gap_seconds <= 0 {
+            return Err("confirm_window_ms and gap_seconds must be positive".to_string());
+        }
+        if self.confirm_tolerance_pct.is_nan() || self.confirm_tolerance_pct <= 0.0 {
+            return Err("confirm_tolerance_pct must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceKind {
+    LastPrice,
+    DailyClose, // First print, or the first after a gap
+    None,       // Unusable price and nothing to compare it with
+}
+
+/// A quarantined print; the payload of `suspect_tick` events
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SuspectTick {
+    pub symbol: String,
+    pub price: f64,
+    pub timestamp: i64, // Milliseconds, as streamed
+    pub reference_price: f64,
+    pub reference: ReferenceKind,
+    pub deviation_pct: f64,
+    pub limit_pct: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TickVerdict {
+    Accepted,
+    Confirmed(SuspectTick), // Accepted, along with the earlier print it confirms
+    Quarantined(SuspectTick),
+}
+
+#[derive(Debug, Clone, Default)]
+struct SymbolState {
+    last_price: Option<f64>,
+    last_at: i64,
+    returns: VecDeque<f64>,
+    suspect: Option<SuspectTick>,
+}
+
+impl SymbolState {
+    fn volatility(&self) -> f64 {
+        let n = self.returns.len() as f64;
+        let mean = self.returns.iter().sum::<f64>() / n;
+        (self.returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+    }
+
+    fn accept(&mut self, price: f64, timestamp: i64, window: usize, record_return: bool) {
+        if let (Some(last), true) = (self.last_price, record_return) {
+            self.returns.push_back(price / last - 1.0);
+            while self.returns.len() > window {
+                self.returns.pop_front();
+            }
+        }
+        self.last_price = Some(price);
+        self.last_at = timestamp;
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TickFilter {
+    config: TickFilterConfig,
+    symbols: HashMap<String, SymbolState>,
+}
+
+impl TickFilter {
+    pub fn config(&self) -> &TickFilterConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: TickFilterConfig) -> Result<(), String> {
+        config.validate()?;
+        self.config = config;
+        Ok(())
+    }
+
+    /// Judge a print at `timestamp` (ms). `daily_close` is asked for only when the symbol
+    /// has had no accepted print within `gap_seconds`.
+    pub fn check(&mut self, symbol: &str, price: f64, timestamp: i64, daily_close: impl FnOnce() -> Option<f64>) -> TickVerdict {
+        let config = &self.config;
+        let state = self.symbols.entry(symbol.to_string()).or_default();
+        let usable = price.is_finite() && price > 0.0;
+        if !config.enabled && usable {
+            state.accept(price, timestamp, config.volatility_window, true);
+            return TickVerdict::Accepted;
+        }
+
+        // A second print near an unconfirmed suspect one makes both real
+        if let Some(suspect) = state.suspect.take() {
+            let near = usable && (price / suspect.price - 1.0).abs() <= config.confirm_tolerance_pct;
+            if near && timestamp - suspect.timestamp <= config.confirm_window_ms {
+                state.accept(price, timestamp, config.volatility_window, false);
+                return TickVerdict::Confirmed(suspect);
+            }
+            if timestamp - suspect.timestamp <= config.confirm_window_ms {
+                state.suspect = Some(suspect);
+            }
+        }
+
+        let after_gap = state.last_price.is_none() || timestamp - state.last_at > config.gap_seconds * 1000;
+        let (reference, reference_price) = match (after_gap.then(daily_close).flatten(), state.last_price) {
+            (Some(close), _) => (ReferenceKind::DailyClose, close),
+            (None, Some(last)) => (ReferenceKind::LastPrice, last),
+            (None, None) if usable => {
+                state.accept(price, timestamp, config.volatility_window, false);
+                return TickVerdict::Accepted;
+            }
+            (None, None) => (ReferenceKind::None, 0.0),
+        };
+        // Overnight and after a gap the tape's recent volatility says little about the move
+        let limit_pct = if after_gap || state.returns.len() < config.min_samples {
+            config.max_deviation_pct
+        } else {
+            (config.volatility_multiple * state.volatility()).clamp(config.min_deviation_pct, config.max_deviation_pct)
+        };
+        let deviation_pct = if usable && reference_price > 0.0 { (price / reference_price - 1.0).abs() } else { 1.0 };
+
+        if usable && deviation_pct <= limit_pct {
+            state.accept(price, timestamp, config.volatility_window, !after_gap);
+            return TickVerdict::Accepted;
+        }
+        let suspect = SuspectTick {
+            symbol: symbol.to_string(),
+            price,
+            timestamp,
+            reference_price,
+            reference,
+            deviation_pct,
+            limit_pct,
+        };
+        if usable {
+            state.suspect = Some(suspect.clone());
+        }
+        TickVerdict::Quarantined(suspect)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `(offset ms, price)` prints after `start` and collect the verdicts
+    fn replay(filter: &mut TickFilter, start: i64, prints: &[(i64, f64)], daily_close: Option<f64>) -> Vec<TickVerdict> {
+        prints.iter().map(|(offset, price)| filter.check("ACME", *price, start + offset, || daily_close)).collect()
+    }
+
+    fn quiet_tape(filter: &mut TickFilter, start: i64) {
+        // Thirty prints a second apart wobbling a few cents around $100
+        let prints: Vec<(i64, f64)> = (0..30).map(|i| (i * 1_000, 100.0 + [0.0, 0.02, -0.01, 0.03][i as usize % 4])).collect();
+        assert!(replay(filter, start, &prints, None).iter().all(|v| *v == TickVerdict::Accepted));
+    }
+
+    #[test]
+    fn test_fat_finger_print_quarantined_but_real_gap_confirmed() {
+        let start = 1_704_207_600_000;
+        let mut filter = TickFilter::default();
+        quiet_tape(&mut filter, start);
+
+        // A print 90% away, then the tape carries on where it was
+        let verdicts = replay(&mut filter, start, &[(30_000, 10.0), (30_500, 100.01), (31_000, 10.0 * 1.001)], None);
+        let TickVerdict::Quarantined(suspect) = &verdicts[0] else { panic!("{:?}", verdicts[0]) };
+        assert_eq!((suspect.reference, suspect.reference_price), (ReferenceKind::LastPrice, 100.02));
+        assert!(suspect.deviation_pct > 0.89 && suspect.limit_pct == 0.01);
+        assert_eq!(verdicts[1], TickVerdict::Accepted);
+        // Another print down there inside the window confirms it; that is what a real
+        // crash looks like, so it goes through
+        assert!(matches!(&verdicts[2], TickVerdict::Confirmed(s) if s.price == 10.0));
+
+        // A 3% move is past the quiet tape's volatility, and a lone one is not confirmed
+        // by a print outside the window
+        let mut filter = TickFilter::default();
+        quiet_tape(&mut filter, start);
+        let verdicts = replay(&mut filter, start, &[(30_000, 103.0), (40_000, 103.0), (41_000, 103.1), (42_000, 103.05)], None);
+        assert!(matches!(verdicts[0], TickVerdict::Quarantined(_)));
+        assert!(matches!(verdicts[1], TickVerdict::Quarantined(_))); // A new suspect, not a confirmation
+        assert!(matches!(&verdicts[2], TickVerdict::Confirmed(s) if s.timestamp == start + 40_000));
+        assert_eq!(verdicts[3], TickVerdict::Accepted); // Judged against the confirmed level
+
+        // Unusable prices are never accepted or confirmed
+        assert!(matches!(filter.check("ACME", 0.0, start + 43_000, || None), TickVerdict::Quarantined(_)));
+        assert!(matches!(filter.check("ACME", 0.0, start + 43_500, || None), TickVerdict::Quarantined(_)));
+        assert!(matches!(filter.check("NEW", f64::NAN, start, || None), TickVerdict::Quarantined(s) if s.reference == ReferenceKind::None));
+
+        // Disabled, everything usable goes through
+        filter.set_config(TickFilterConfig { enabled: false, ..TickFilterConfig::default() }).unwrap();
+        assert_eq!(filter.check("ACME", 10.0, start + 44_000, || None), TickVerdict::Accepted);
+        assert!(filter.set_config(TickFilterConfig { min_samples: 1, ..TickFilterConfig::default() }).is_err());
+    }
+
+    #[test]
+    fn test_first_print_after_a_gap_judged_against_the_daily_close() {
+        let start = 1_704_207_600_000;
+        let mut filter = TickFilter::default();
+        quiet_tape(&mut filter, start);
+
+        // Two days later the stock closed at $140 in sessions that were not streamed; the
+        // stale $100 would quarantine the real price, the close does not
+        let later = start + 2 * 86_400_000;
+        let verdicts = replay(&mut filter, later, &[(0, 141.0), (1_000, 141.1)], Some(140.0));
+        assert_eq!(verdicts, vec![TickVerdict::Accepted, TickVerdict::Accepted]);
+
+        // While a bad first print is caught against the close, and the next is judged
+        // against the close too until a print is accepted
+        let mut filter = TickFilter::default();
+        let verdicts = replay(&mut filter, later, &[(0, 14.0), (1_000, 139.5), (2_000, 150.0)], Some(140.0));
+        let TickVerdict::Quarantined(suspect) = &verdicts[0] else { panic!("{:?}", verdicts[0]) };
+        assert_eq!((suspect.reference, suspect.reference_price, suspect.limit_pct), (ReferenceKind::DailyClose, 140.0, 0.10));
+        assert_eq!(verdicts[1], TickVerdict::Accepted);
+        // Without enough returns only the hard band applies: 7.5% is let through
+        assert_eq!(verdicts[2], TickVerdict::Accepted);
+
+        // With no close cached the first print sets the level
+        let mut filter = TickFilter::default();
+        assert_eq!(replay(&mut filter, later, &[(0, 14.0), (1_000, 14.02)], None), vec![TickVerdict::Accepted; 2]);
+    }
+}
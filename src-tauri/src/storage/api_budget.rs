@@ -0,0 +1,286 @@
+// src-tauri/src/storage/api_budget.rs
+// History request budgeting: estimate the calls and bars a request will cost before it
+// runs, hold large requests for acknowledgement, and count calls per provider per day
+// in api_usage.json in the cache dir so the daily quota is shared by every caller.
+
+use super::cache::FileCache;
+use crate::engine::calendar::MarketCalendar;
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+const USAGE_FILE: &str = "api_usage.json";
+pub const POLYGON: &str = "polygon";
+pub const AGGREGATES_PAGE_SIZE: u64 = 50_000; // `limit` on Polygon aggregate requests
+const LARGEST_REQUESTS_KEPT: usize = 10;
+const LARGEST_REQUESTS_DAYS: i64 = 7;
+
+// Calls are counted with a read-modify-write of the usage file
+static USAGE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApiLimits {
+    pub daily_calls: u32,         // Per provider; Polygon's free tier allows 5 calls/minute
+    pub large_request_bars: u64,  // Requests estimated above this need acknowledging
+    pub large_request_calls: u32, // Likewise for calls (pages) in one request
+}
+
+impl Default for ApiLimits {
+    fn default() -> Self {
+        Self { daily_calls: 5 * 60 * 24, large_request_bars: 20_000, large_request_calls: 1 }
+    }
+}
+
+/// What a history request will cost before it is made
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RequestEstimate {
+    pub provider: String,
+    pub symbol: String,
+    pub interval: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub trading_days: u32,
+    pub bars: u64,
+    pub api_calls: u32,
+    #[serde(default)]
+    pub extended_hours: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecordedRequest {
+    pub date: NaiveDate,
+    #[serde(flatten)]
+    pub estimate: RequestEstimate,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct UsageFile {
+    #[serde(default)]
+    limits: ApiLimits,
+    #[serde(default)]
+    date: Option<NaiveDate>,
+    #[serde(default)]
+    calls: BTreeMap<String, u32>, // Calls made on `date`, by provider
+    #[serde(default)]
+    largest_requests: Vec<RecordedRequest>, // Largest first
+}
+
+/// Today's usage as `get_api_usage` reports it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiUsage {
+    pub date: NaiveDate,
+    pub calls: BTreeMap<String, u32>,
+    pub remaining: BTreeMap<String, u32>,
+    pub limits: ApiLimits,
+    pub largest_requests: Vec<RecordedRequest>,
+}
+
+/// Minutes per bar at `interval` ("1day" | "1hour" | "1minute"); 0 for daily bars
+pub fn interval_minutes(interval: &str) -> u32 {
+    match interval {
+        "1hour" | "1h" => 60,
+        "1minute" | "1m" => 1,
+        _ => 0,
+    }
+}
+
+/// Bars the calendar expects over the range's trading days, over the aggregates page
+/// size. The range is trimmed to its first and last trading days; intraday bars count
+/// the regular session unless `extended_hours` asks for 4:00 to 20:00.
+pub fn estimate_history_request(
+    provider: &str,
+    symbol: &str,
+    interval: &str,
+    start: NaiveDate,
+    end: NaiveDate,
+    extended_hours: bool,
+    calendar: &MarketCalendar,
+) -> RequestEstimate {
+    let (start, end) = calendar.trading_range(start, end).unwrap_or((start, end));
+    let trading_days = if start <= end { calendar.get_trading_days(start, end).len() as u32 } else { 0 };
+    let bars = if trading_days > 0 { calendar.expected_bars(start, end, interval_minutes(interval), extended_hours) } else { 0 };
+    RequestEstimate {
+        provider: provider.to_string(),
+        symbol: symbol.to_string(),
+        interval: interval.to_string(),
+        start,
+        end,
+        trading_days,
+        bars,
+        api_calls: if trading_days > 0 { bars.div_ceil(AGGREGATES_PAGE_SIZE).max(1) as u32 } else { 0 },
+        extended_hours,
+    }
+}
+
+pub struct ApiBudget {
+    path: PathBuf,
+}
+
+impl ApiBudget {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn open(app_handle: &AppHandle) -> Result<Self, String> {
+        Ok(Self::new(FileCache::new(app_handle)?.cache_dir().join(USAGE_FILE)))
+    }
+
+    pub fn set_limits(&self, limits: ApiLimits) -> Result<ApiLimits, String> {
+        if limits.daily_calls == 0 {
+            return Err("The daily call limit must be at least 1".to_string());
+        }
+        let _guard = USAGE_LOCK.lock().map_err(|e| e.to_string())?;
+        let mut usage = self.load()?;
+        usage.limits = limits.clone();
+        self.write(&usage)?;
+        Ok(limits)
+    }
+
+    /// Calls `provider` has left today
+    pub fn remaining(&self, provider: &str, today: NaiveDate) -> Result<u32, String> {
+        Ok(self.usage(today)?.remaining.get(provider).copied().unwrap_or(0))
+    }
+
+    /// Refuse a request that would overrun today's quota, or that is over the large
+    /// request thresholds without `acknowledge_large_request`
+    pub fn check(&self, estimate: &RequestEstimate, acknowledge_large_request: bool, today: NaiveDate) -> Result<(), String> {
+        let usage = self.usage(today)?;
+        let remaining = usage.remaining.get(&estimate.provider).copied().unwrap_or(0);
+        if estimate.api_calls > remaining {
+            return Err(format!(
+                "Daily {} API quota reached: {} calls needed, {} of {} left today",
+                estimate.provider, estimate.api_calls, remaining, usage.limits.daily_calls
+            ));
+        }
+        let limits = &usage.limits;
+        if !acknowledge_large_request && (estimate.bars > limits.large_request_bars || estimate.api_calls > limits.large_request_calls) {
+            return Err(format!(
+                "Large request: {} {} bars for {} ({} trading days, {} API calls); pass acknowledge_large_request to proceed",
+                estimate.bars, estimate.interval, estimate.symbol, estimate.trading_days, estimate.api_calls
+            ));
+        }
+        Ok(())
+    }
+
+    /// Count `calls` actually made for `estimate` today
+    pub fn record(&self, estimate: &RequestEstimate, calls: u32, today: NaiveDate) -> Result<(), String> {
+        let _guard = USAGE_LOCK.lock().map_err(|e| e.to_string())?;
+        let mut usage = self.load_for(today)?;
+        *usage.calls.entry(estimate.provider.clone()).or_insert(0) += calls;
+
+        usage.largest_requests.push(RecordedRequest { date: today, estimate: estimate.clone() });
+        usage.largest_requests.retain(|r| (today - r.date).num_days() < LARGEST_REQUESTS_DAYS);
+        usage.largest_requests.sort_by_key(|r| std::cmp::Reverse(r.estimate.bars));
+        usage.largest_requests.truncate(LARGEST_REQUESTS_KEPT);
+        self.write(&usage)
+    }
+
+    pub fn usage(&self, today: NaiveDate) -> Result<ApiUsage, String> {
+        let usage = self.load_for(today)?;
+        let mut remaining: BTreeMap<String, u32> = usage
+            .calls
+            .iter()
+            .map(|(provider, calls)| (provider.clone(), usage.limits.daily_calls.saturating_sub(*calls)))
+            .collect();
+        remaining.entry(POLYGON.to_string()).or_insert(usage.limits.daily_calls);
+        Ok(ApiUsage {
+            date: today,
+            calls: usage.calls,
+            remaining,
+            limits: usage.limits,
+            largest_requests: usage.largest_requests,
+        })
+    }
+
+    /// The usage file with its counters reset if they are from an earlier day
+    fn load_for(&self, today: NaiveDate) -> Result<UsageFile, String> {
+        let mut usage = self.load()?;
+        if usage.date != Some(today) {
+            usage.date = Some(today);
+            usage.calls.clear();
+        }
+        Ok(usage)
+    }
+
+    fn load(&self) -> Result<UsageFile, String> {
+        if !self.path.exists() {
+            return Ok(UsageFile::default());
+        }
+        let text = fs::read_to_string(&self.path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| format!("API usage file is unreadable: {}", e))
+    }
+
+    fn write(&self, usage: &UsageFile) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(usage).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &self.path).map_err(|e| e.to_string())
+    }
+}
+
+/// The day quotas are counted against
+pub fn usage_day() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_estimates_and_daily_quota() {
+        let calendar = MarketCalendar::new();
+        // July 2024: 22 trading days (the 4th is a holiday)
+        let daily = estimate_history_request(POLYGON, "AAPL", "1day", date(2024, 7, 1), date(2024, 7, 31), false, &calendar);
+        assert_eq!((daily.trading_days, daily.bars, daily.api_calls), (22, 22, 1));
+        // Hourly bars from 9:00 through 15:00 in the regular session, 4:00 through 19:00 extended
+        let hourly = estimate_history_request(POLYGON, "AAPL", "1hour", date(2024, 7, 1), date(2024, 7, 31), false, &calendar);
+        assert_eq!(hourly.bars, 22 * 7);
+        let extended = estimate_history_request(POLYGON, "AAPL", "1hour", date(2024, 7, 1), date(2024, 7, 31), true, &calendar);
+        assert_eq!(extended.bars, 22 * 16);
+        // Weekends either side are trimmed; a weekend alone costs nothing
+        let trimmed = estimate_history_request(POLYGON, "AAPL", "1day", date(2024, 6, 29), date(2024, 7, 7), false, &calendar);
+        assert_eq!((trimmed.start, trimmed.end, trimmed.bars), (date(2024, 7, 1), date(2024, 7, 5), 4));
+        let weekend = estimate_history_request(POLYGON, "AAPL", "1hour", date(2024, 7, 6), date(2024, 7, 7), false, &calendar);
+        assert_eq!((weekend.bars, weekend.api_calls), (0, 0));
+        let minutes = estimate_history_request(POLYGON, "AAPL", "1minute", date(2024, 1, 1), date(2024, 12, 31), true, &calendar);
+        assert_eq!(minutes.api_calls as u64, minutes.bars.div_ceil(AGGREGATES_PAGE_SIZE));
+        assert!(minutes.api_calls > 1);
+
+        let dir = std::env::temp_dir().join(format!("api-budget-test-{}", uuid::Uuid::new_v4()));
+        let budget = ApiBudget::new(dir.join(USAGE_FILE));
+        let today = date(2024, 8, 1);
+        budget.set_limits(ApiLimits { daily_calls: 3, ..ApiLimits::default() }).unwrap();
+
+        assert!(budget.check(&daily, false, today).is_ok());
+        let held = budget.check(&minutes, false, today).unwrap_err();
+        assert!(held.contains("Daily polygon API quota"), "{}", held); // Quota is checked first
+        let large = RequestEstimate { api_calls: 1, ..minutes.clone() };
+        assert!(budget.check(&large, false, today).unwrap_err().contains("acknowledge_large_request"));
+        assert!(budget.check(&large, true, today).is_ok());
+
+        budget.record(&daily, 1, today).unwrap();
+        budget.record(&large, 2, today).unwrap();
+        let usage = budget.usage(today).unwrap();
+        assert_eq!(usage.calls[POLYGON], 3);
+        assert_eq!(usage.remaining[POLYGON], 0);
+        assert_eq!(usage.largest_requests[0].estimate.bars, large.bars);
+        assert!(budget.check(&daily, true, today).is_err());
+
+        // A new day resets the counters but keeps recent large requests
+        let tomorrow = today.succ_opt().unwrap();
+        assert_eq!(budget.remaining(POLYGON, tomorrow).unwrap(), 3);
+        assert_eq!(budget.usage(tomorrow).unwrap().largest_requests.len(), 2);
+        assert!(budget.set_limits(ApiLimits { daily_calls: 0, ..ApiLimits::default() }).is_err());
+    }
+}
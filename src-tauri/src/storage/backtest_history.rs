@@ -0,0 +1,135 @@
+// src-tauri/src/storage/backtest_history.rs
+// The last 50 backtest runs, oldest first, in backtest_history.json in the cache dir.
+// Runs are kept without their trade logs; a run's id loads the full result.
+
+use super::cache::FileCache;
+use crate::commands::backtest::BacktestSummary;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+const HISTORY_FILE: &str = "backtest_history.json";
+pub const MAX_BACKTEST_RUNS: usize = 50;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BacktestRun {
+    pub run_id: String, // "{run_timestamp}-{strategy}"
+    pub run_timestamp: i64,
+    #[serde(flatten)]
+    pub summary: BacktestSummary,
+}
+
+pub struct BacktestHistory {
+    path: PathBuf,
+}
+
+impl BacktestHistory {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn open(app_handle: &AppHandle) -> Result<Self, String> {
+        Ok(Self::new(FileCache::new(app_handle)?.cache_dir().join(HISTORY_FILE)))
+    }
+
+    /// Recorded runs, oldest first
+    pub fn load(&self) -> Result<Vec<BacktestRun>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let text = fs::read_to_string(&self.path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| format!("Backtest history is unreadable: {}", e))
+    }
+
+    pub fn get(&self, run_id: &str) -> Result<BacktestRun, String> {
+        self.load()?
+            .into_iter()
+            .find(|run| run.run_id == run_id)
+            .ok_or_else(|| format!("No backtest run {}", run_id))
+    }
+
+    /// Append `summary` as a run at `run_timestamp`, evicting the oldest runs beyond
+    /// MAX_BACKTEST_RUNS. Returns the new run and the evicted ones.
+    pub fn record(&self, summary: BacktestSummary, run_timestamp: i64) -> Result<(BacktestRun, Vec<BacktestRun>), String> {
+        let mut runs = self.load()?;
+        let base_id = format!("{}-{}", run_timestamp, summary.strategy);
+        let mut run_id = base_id.clone();
+        for n in 2.. {
+            if !runs.iter().any(|run| run.run_id == run_id) {
+                break;
+            }
+            run_id = format!("{}-{}", base_id, n);
+        }
+
+        let run = BacktestRun { run_id, run_timestamp, summary };
+        runs.push(run.clone());
+        let evicted: Vec<BacktestRun> = runs.drain(..runs.len().saturating_sub(MAX_BACKTEST_RUNS)).collect();
+        self.write(&runs)?;
+        Ok((run, evicted))
+    }
+
+    /// Remove a run; returns it
+    pub fn delete(&self, run_id: &str) -> Result<BacktestRun, String> {
+        let mut runs = self.load()?;
+        let index = runs
+            .iter()
+            .position(|run| run.run_id == run_id)
+            .ok_or_else(|| format!("No backtest run {}", run_id))?;
+        let run = runs.remove(index);
+        self.write(&runs)?;
+        Ok(run)
+    }
+
+    fn write(&self, runs: &[BacktestRun]) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(runs).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &self.path).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::backtest::get_sample_backtest_result;
+
+    #[tokio::test]
+    async fn test_history_keeps_the_last_fifty_runs() {
+        let dir = std::env::temp_dir().join(format!("backtest-history-test-{}", uuid::Uuid::new_v4()));
+        let history = BacktestHistory::new(dir.join(HISTORY_FILE));
+        assert!(history.load().unwrap().is_empty());
+
+        let mut sample = get_sample_backtest_result().await.0;
+        sample.equity_curve.truncate(5);
+        sample.benchmark = None;
+        let (first, evicted) = history.record(sample.clone(), 1_700_000_000).unwrap();
+        assert_eq!(first.run_id, "1700000000-PMCC");
+        assert!(evicted.is_empty());
+        // Same second, same strategy
+        let (second, _) = history.record(sample.clone(), 1_700_000_000).unwrap();
+        assert_eq!(second.run_id, "1700000000-PMCC-2");
+
+        let loaded = history.get(&first.run_id).unwrap();
+        assert_eq!(loaded.run_timestamp, 1_700_000_000);
+        assert_eq!(loaded.summary.equity_curve.len(), sample.equity_curve.len());
+        assert_eq!(serde_json::to_value(&loaded).unwrap()["cagr"], serde_json::json!(sample.cagr));
+
+        let mut evicted_ids = Vec::new();
+        for i in 1..=MAX_BACKTEST_RUNS as i64 - 1 {
+            let (_, evicted) = history.record(sample.clone(), 1_700_000_000 + i).unwrap();
+            evicted_ids.extend(evicted.into_iter().map(|run| run.run_id));
+        }
+        assert_eq!(evicted_ids, vec![first.run_id.clone()]);
+        let runs = history.load().unwrap();
+        assert_eq!(runs.len(), MAX_BACKTEST_RUNS);
+        assert_eq!(runs[0].run_id, second.run_id);
+
+        history.delete(&second.run_id).unwrap();
+        assert_eq!(history.load().unwrap().len(), MAX_BACKTEST_RUNS - 1);
+        assert!(history.delete(&second.run_id).is_err());
+        assert!(history.get(&first.run_id).is_err());
+    }
+}
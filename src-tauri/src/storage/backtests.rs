@@ -0,0 +1,302 @@
+// src-tauri/src/storage/backtests.rs
+// Completed backtest results with their trade logs, stored as backtests/{id}.json with
+// their reproducibility manifests as backtests/manifests/{id}.json, basket results over
+// several of them as backtests/baskets/{id}.json, and signal parameter optimizations as
+// backtests/optimizations/{id}.json
+
+use crate::commands::backtest::BacktestSummary;
+use super::bar_pins::BarPinStore;
+use crate::engine::basket::BasketBacktest;
+use crate::engine::reproducibility::{BacktestManifest, BarSource};
+use crate::engine::signal_optimizer::SignalOptimization;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+pub struct BacktestStore {
+    root: PathBuf,
+}
+
+impl BacktestStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    // Ids are generated here; anything else is not a stored result
+    fn result_path(&self, id: &str) -> Result<PathBuf, String> {
+        let id = Uuid::parse_str(id).map_err(|_| format!("No backtest {}", id))?;
+        Ok(self.root.join(format!("{}.json", id)))
+    }
+
+    fn manifest_path(&self, id: &str) -> Result<PathBuf, String> {
+        let id = Uuid::parse_str(id).map_err(|_| format!("No backtest {}", id))?;
+        Ok(self.root.join("manifests").join(format!("{}.json", id)))
+    }
+
+    fn basket_path(&self, id: &str) -> Result<PathBuf, String> {
+        let id = Uuid::parse_str(id).map_err(|_| format!("No basket backtest {}", id))?;
+        Ok(self.root.join("baskets").join(format!("{}.json", id)))
+    }
+
+    fn optimization_path(&self, id: &str) -> Result<PathBuf, String> {
+        let id = Uuid::parse_str(id).map_err(|_| format!("No optimization {}", id))?;
+        Ok(self.root.join("optimizations").join(format!("{}.json", id)))
+    }
+
+    fn write<T: Serialize>(path: &PathBuf, value: &T) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(value).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, path).map_err(|e| e.to_string())
+    }
+
+    /// Store `summary` under a new id, set on the summary and returned
+    pub fn save(&self, summary: &mut BacktestSummary) -> Result<String, String> {
+        let id = Uuid::new_v4().to_string();
+        summary.id = Some(id.clone());
+        Self::write(&self.result_path(&id)?, summary)?;
+        Ok(id)
+    }
+
+    pub fn load(&self, id: &str) -> Result<BacktestSummary, String> {
+        let path = self.result_path(id)?;
+        if !path.exists() {
+            return Err(format!("No backtest {}", id));
+        }
+        let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| format!("Backtest {} is unreadable: {}", id, e))
+    }
+
+    /// Remove a result with its manifest, releasing the bars it pinned
+    pub fn delete(&self, id: &str) -> Result<(), String> {
+        if let Some(manifest) = self.load_manifest(id)? {
+            let pins = self.pins();
+            for series in manifest.series.iter().filter(|s| s.source == BarSource::Pinned) {
+                pins.release(&series.content_hash, id)?;
+            }
+            fs::remove_file(self.manifest_path(id)?).map_err(|e| e.to_string())?;
+        }
+        let path = self.result_path(id)?;
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    pub fn pins(&self) -> BarPinStore {
+        BarPinStore::new(self.root.join("pins"))
+    }
+
+    /// Store the manifest of saved result `manifest.run_id`
+    pub fn save_manifest(&self, manifest: &BacktestManifest) -> Result<(), String> {
+        Self::write(&self.manifest_path(&manifest.run_id)?, manifest)
+    }
+
+    /// The result's manifest; None for results saved before manifests were kept
+    pub fn load_manifest(&self, id: &str) -> Result<Option<BacktestManifest>, String> {
+        let path = self.manifest_path(id)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map(Some).map_err(|e| format!("Manifest of backtest {} is unreadable: {}", id, e))
+    }
+
+    /// Store a basket result under a new id; its members' runs are saved separately
+    pub fn save_basket(&self, basket: &mut BasketBacktest) -> Result<String, String> {
+        let id = Uuid::new_v4().to_string();
+        basket.id = Some(id.clone());
+        Self::write(&self.basket_path(&id)?, basket)?;
+        Ok(id)
+    }
+
+    pub fn load_basket(&self, id: &str) -> Result<BasketBacktest, String> {
+        let path = self.basket_path(id)?;
+        if !path.exists() {
+            return Err(format!("No basket backtest {}", id));
+        }
+        let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| format!("Basket backtest {} is unreadable: {}", id, e))
+    }
+
+    /// Stored basket results, newest first
+    pub fn list_baskets(&self) -> Result<Vec<BasketBacktest>, String> {
+        let Ok(entries) = fs::read_dir(self.root.join("baskets")) else {
+            return Ok(Vec::new());
+        };
+        let mut baskets: Vec<BasketBacktest> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|p| fs::read_to_string(p).ok())
+            .filter_map(|text| serde_json::from_str(&text).ok())
+            .collect();
+        baskets.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+        Ok(baskets)
+    }
+
+    /// Remove a basket result and its members' runs; returns the basket
+    pub fn delete_basket(&self, id: &str) -> Result<BasketBacktest, String> {
+        let basket = self.load_basket(id)?;
+        for run_id in basket.members.iter().filter_map(|m| m.run_id.as_deref()) {
+            self.delete(run_id)?;
+        }
+        fs::remove_file(self.basket_path(id)?).map_err(|e| e.to_string())?;
+        Ok(basket)
+    }
+
+    /// Store an optimization result under a new id
+    pub fn save_optimization(&self, optimization: &mut SignalOptimization) -> Result<String, String> {
+        let id = Uuid::new_v4().to_string();
+        optimization.id = Some(id.clone());
+        Self::write(&self.optimization_path(&id)?, optimization)?;
+        Ok(id)
+    }
+
+    pub fn load_optimization(&self, id: &str) -> Result<SignalOptimization, String> {
+        let path = self.optimization_path(id)?;
+        if !path.exists() {
+            return Err(format!("No optimization {}", id));
+        }
+        let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| format!("Optimization {} is unreadable: {}", id, e))
+    }
+
+    /// Stored optimizations, newest first
+    pub fn list_optimizations(&self) -> Result<Vec<SignalOptimization>, String> {
+        let Ok(entries) = fs::read_dir(self.root.join("optimizations")) else {
+            return Ok(Vec::new());
+        };
+        let mut optimizations: Vec<SignalOptimization> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|p| fs::read_to_string(p).ok())
+            .filter_map(|text| serde_json::from_str(&text).ok())
+            .collect();
+        optimizations.sort_by_key(|o| std::cmp::Reverse(o.created_at));
+        Ok(optimizations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::backtest::{summarize_backtest, BacktestParams};
+    use crate::market_data::types::Candle;
+
+    #[test]
+    fn test_backtest_store_keeps_the_trade_log() {
+        let store = BacktestStore::new(std::env::temp_dir().join(format!("backtests-test-{}", Uuid::new_v4())));
+        let params = BacktestParams {
+            ticker: "SPY".into(),
+            start_date: chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+            end_date: chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap(),
+            strategy: "MeanReversion".into(),
+            initial_capital: 100_000.0,
+            seed: None,
+            warm_job_id: None,
+            transaction_costs: Default::default(),
+            cost_model: None,
+            pair: None,
+            signal_orders: Default::default(),
+        };
+        let mut closes: Vec<f64> = (0..25).map(|i| if i % 2 == 0 { 100.5 } else { 99.5 }).collect();
+        closes.extend([90.0, 95.0, 100.0, 112.0]);
+        let candles: Vec<Candle> = closes
+            .iter()
+            .enumerate()
+            .map(|(i, close)| Candle::new(1704153600 + i as i64 * 86400, *close, *close, *close, *close, 1_000_000))
+            .collect();
+        let mut summary = summarize_backtest(&params, &candles);
+        assert!(!summary.trade_log.is_empty());
+
+        let id = store.save(&mut summary).unwrap();
+        assert_eq!(summary.id.as_deref(), Some(id.as_str()));
+        let loaded = store.load(&id).unwrap();
+        assert_eq!(loaded.trade_log, summary.trade_log);
+        assert_eq!(loaded.fills.len(), summary.fills.len());
+
+        assert!(store.load(&Uuid::new_v4().to_string()).is_err());
+        assert!(store.load("../ui_state").is_err());
+    }
+
+    #[test]
+    fn test_basket_is_stored_with_its_member_runs() {
+        use crate::engine::basket::{aggregate_basket, BasketAllocation, BasketRankMetric, BasketRun};
+
+        let store = BacktestStore::new(std::env::temp_dir().join(format!("baskets-test-{}", Uuid::new_v4())));
+        assert!(store.list_baskets().unwrap().is_empty());
+        let candles: Vec<Candle> = [100.0, 101.0, 99.0, 103.0]
+            .iter()
+            .enumerate()
+            .map(|(i, close)| Candle::new(1704153600 + i as i64 * 86400, *close, *close, *close, *close, 1_000_000))
+            .collect();
+        let params = BacktestParams {
+            ticker: "SPY".into(),
+            start_date: chrono::NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+            end_date: chrono::NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+            strategy: "BuyHold".into(),
+            initial_capital: 100_000.0,
+            seed: None,
+            warm_job_id: None,
+            transaction_costs: Default::default(),
+            cost_model: None,
+            pair: None,
+            signal_orders: Default::default(),
+        };
+        let mut member = summarize_backtest(&params, &candles);
+        let run_id = store.save(&mut member).unwrap();
+        let runs = vec![("SPY".to_string(), Ok(BasketRun { summary: member, volatility: 0.2 }))];
+        let mut basket = aggregate_basket(runs, 100_000.0, BasketAllocation::EqualWeight, BasketRankMetric::Sharpe, 1_704_500_000);
+
+        let id = store.save_basket(&mut basket).unwrap();
+        let loaded = store.load_basket(&id).unwrap();
+        assert_eq!(loaded.members[0].run_id.as_deref(), Some(run_id.as_str()));
+        assert_eq!(loaded.equity_curve.len(), 4);
+        assert_eq!(store.list_baskets().unwrap().len(), 1);
+        assert!(store.load(&run_id).is_ok());
+
+        store.delete_basket(&id).unwrap();
+        assert!(store.load_basket(&id).is_err());
+        assert!(store.load(&run_id).is_err());
+        assert!(store.list_baskets().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_pinned_bars_are_shared_and_released_with_the_last_run() {
+        use crate::engine::reproducibility::BarSeriesRecord;
+
+        let store = BacktestStore::new(std::env::temp_dir().join(format!("pins-test-{}", Uuid::new_v4())));
+        let candles: Vec<Candle> = [100.0, 101.0, 99.0, 103.0]
+            .iter()
+            .enumerate()
+            .map(|(i, close)| Candle::new(1704153600 + i as i64 * 86400, *close, *close, *close, *close, 1_000_000))
+            .collect();
+        let params: BacktestParams = serde_json::from_value(serde_json::json!({
+            "ticker": "SPY", "start_date": "01/02/2024", "end_date": "01/05/2024",
+            "strategy": "BuyHold", "initial_capital": 100000.0, "seed": null
+        }))
+        .unwrap();
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let mut summary = summarize_backtest(&params, &candles);
+            let id = store.save(&mut summary).unwrap();
+            let hash = store.pins().pin("SPY", &candles, &id).unwrap();
+            store.save_manifest(&BacktestManifest::new(&summary, &params, vec![BarSeriesRecord::new("SPY", &candles, false)])).unwrap();
+            assert_eq!(store.load_manifest(&id).unwrap().unwrap().series[0].content_hash, hash);
+            ids.push((id, hash));
+        }
+        assert_eq!(ids[0].1, ids[1].1); // One copy of the same bars
+        assert_eq!(fs::read_dir(store.root.join("pins")).unwrap().count(), 1);
+
+        store.delete(&ids[0].0).unwrap();
+        assert_eq!(store.pins().load(&ids[1].1).unwrap().len(), 4);
+        assert!(store.load_manifest(&ids[0].0).unwrap().is_none());
+        store.delete(&ids[1].0).unwrap();
+        assert!(store.pins().load(&ids[1].1).is_err());
+    }
+}
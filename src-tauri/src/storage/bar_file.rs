@@ -0,0 +1,232 @@
+// src-tauri/src/storage/bar_file.rs
+// Compact columnar file for one bar series. A small header (magic, version, flags, bar
+// count, symbol and interval) is followed by six little-endian columns of equal length:
+// timestamps, open, high, low, close and volume. Readers memory-map the file and find a
+// time range by binary search over the timestamp column, so a query decodes only the
+// bars it returns. Values round-trip bit for bit.
+
+use crate::market_data::types::Candle;
+use memmap2::Mmap;
+use std::fs::{self, File};
+use std::ops::Range;
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"TBAR";
+const VERSION: u16 = 1;
+const FLAG_ADJUSTED: u16 = 1;
+const FIXED_HEADER: usize = 20; // Magic, version, flags, count and the two name lengths
+const COLUMNS: usize = 6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BarHeader {
+    pub symbol: String,
+    pub interval: String,
+    pub adjusted: bool, // Split-adjusted prices
+}
+
+impl BarHeader {
+    pub fn new(symbol: &str, interval: &str, adjusted: bool) -> Self {
+        Self { symbol: symbol.to_string(), interval: interval.to_string(), adjusted }
+    }
+
+    /// Header bytes, padded so the columns start 8-byte aligned
+    fn encode(&self, count: usize) -> Result<Vec<u8>, String> {
+        let name_len = |name: &str| u16::try_from(name.len()).map_err(|_| format!("Bar file name too long: {}", name));
+        let mut bytes = Vec::with_capacity(64);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(if self.adjusted { FLAG_ADJUSTED } else { 0 }).to_le_bytes());
+        bytes.extend_from_slice(&(count as u64).to_le_bytes());
+        bytes.extend_from_slice(&name_len(&self.symbol)?.to_le_bytes());
+        bytes.extend_from_slice(&name_len(&self.interval)?.to_le_bytes());
+        bytes.extend_from_slice(self.symbol.as_bytes());
+        bytes.extend_from_slice(self.interval.as_bytes());
+        bytes.resize(bytes.len().next_multiple_of(8), 0);
+        Ok(bytes)
+    }
+}
+
+/// Write `bars`, which must be in strictly ascending timestamp order, to `path`. The
+/// file is replaced by rename, so readers holding the old one keep a complete copy.
+pub fn write_bars(path: &Path, header: &BarHeader, bars: &[Candle]) -> Result<(), String> {
+    if let Some(pair) = bars.windows(2).find(|w| w[0].timestamp >= w[1].timestamp) {
+        return Err(format!("Bars out of order at {} then {}", pair[0].timestamp, pair[1].timestamp));
+    }
+    let mut bytes = header.encode(bars.len())?;
+    bytes.reserve(bars.len() * COLUMNS * 8);
+    for bar in bars {
+        bytes.extend_from_slice(&bar.timestamp.to_le_bytes());
+    }
+    for column in [|b: &Candle| b.open, |b: &Candle| b.high, |b: &Candle| b.low, |b: &Candle| b.close] {
+        for bar in bars {
+            bytes.extend_from_slice(&column(bar).to_le_bytes());
+        }
+    }
+    for bar in bars {
+        bytes.extend_from_slice(&bar.volume.to_le_bytes());
+    }
+
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, &bytes).map_err(|e| format!("Failed to write bar file: {}", e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to replace bar file: {}", e))
+}
+
+/// A memory-mapped bar file
+pub struct BarFile {
+    map: Mmap,
+    header: BarHeader,
+    len: usize,
+    columns_at: usize,
+}
+
+impl BarFile {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Failed to open bar file: {}", e))?;
+        // SAFETY: bar files are only ever replaced by rename, never written in place, so
+        // the mapped bytes cannot change under the reader
+        let map = unsafe { Mmap::map(&file) }.map_err(|e| format!("Failed to map bar file: {}", e))?;
+        let invalid = |what: &str| format!("Invalid bar file {}: {}", path.display(), what);
+
+        if map.len() < FIXED_HEADER || &map[..4] != MAGIC {
+            return Err(invalid("not a bar file"));
+        }
+        let version = u16::from_le_bytes([map[4], map[5]]);
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported version {}", version)));
+        }
+        let flags = u16::from_le_bytes([map[6], map[7]]);
+        let len = u64::from_le_bytes(map[8..16].try_into().unwrap_or_default()) as usize;
+        let symbol_len = u16::from_le_bytes([map[16], map[17]]) as usize;
+        let interval_len = u16::from_le_bytes([map[18], map[19]]) as usize;
+        let names_end = FIXED_HEADER + symbol_len + interval_len;
+        let columns_at = names_end.next_multiple_of(8);
+        if len.checked_mul(COLUMNS * 8).and_then(|n| n.checked_add(columns_at)) != Some(map.len()) {
+            return Err(invalid("length does not match its bar count"));
+        }
+        let name = |range: Range<usize>| String::from_utf8(map[range].to_vec()).map_err(|_| invalid("name is not UTF-8"));
+        let header = BarHeader {
+            symbol: name(FIXED_HEADER..FIXED_HEADER + symbol_len)?,
+            interval: name(FIXED_HEADER + symbol_len..names_end)?,
+            adjusted: flags & FLAG_ADJUSTED != 0,
+        };
+        Ok(Self { map, header, len, columns_at })
+    }
+
+    fn word(&self, column: usize, index: usize) -> [u8; 8] {
+        let at = self.columns_at + (column * self.len + index) * 8;
+        self.map[at..at + 8].try_into().unwrap_or_default()
+    }
+
+    pub fn timestamp(&self, index: usize) -> i64 {
+        i64::from_le_bytes(self.word(0, index))
+    }
+
+    /// Indices of the bars with `from <= timestamp <= to`
+    pub fn range(&self, from: i64, to: i64) -> Range<usize> {
+        let start = self.partition_point(|t| t < from);
+        let end = self.partition_point(|t| t <= to);
+        start..end.max(start)
+    }
+
+    fn partition_point(&self, before: impl Fn(i64) -> bool) -> usize {
+        let (mut low, mut high) = (0, self.len);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if before(self.timestamp(mid)) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    pub fn bar(&self, index: usize) -> Candle {
+        let price = |column| f64::from_le_bytes(self.word(column, index));
+        Candle {
+            timestamp: self.timestamp(index),
+            open: price(1),
+            high: price(2),
+            low: price(3),
+            close: price(4),
+            volume: u64::from_le_bytes(self.word(5, index)),
+            symbol: Some(self.header.symbol.clone()),
+            interval: Some(self.header.interval.clone()),
+        }
+    }
+
+    /// Bars with `from <= timestamp <= to`, decoding only those
+    pub fn query(&self, from: i64, to: i64) -> Vec<Candle> {
+        self.range(from, to).map(|i| self.bar(i)).collect()
+    }
+
+    pub fn bars(&self) -> Vec<Candle> {
+        (0..self.len).map(|i| self.bar(i)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn series(count: usize) -> Vec<Candle> {
+        (0..count)
+            .map(|i| {
+                let open = 100.0 + (i % 977) as f64 * 0.013 + 1.0 / (i + 3) as f64;
+                let mut bar = Candle::new(1_262_304_000 + i as i64 * 60, open, open + 0.07, open - 0.05, open + 0.01, (i * 37 % 100_000) as u64);
+                (bar.symbol, bar.interval) = (Some("SPY".to_string()), Some("1m".to_string()));
+                bar
+            })
+            .collect()
+    }
+
+    fn bits(bar: &Candle) -> (i64, [u64; 4], u64) {
+        (bar.timestamp, [bar.open.to_bits(), bar.high.to_bits(), bar.low.to_bits(), bar.close.to_bits()], bar.volume)
+    }
+
+    #[test]
+    fn test_round_trip_range_queries_and_parse_speed() {
+        let dir = std::env::temp_dir().join(format!("bar_file_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let (bin_path, json_path) = (dir.join("SPY_1m.bars"), dir.join("SPY_1m.json"));
+
+        // Awkward values come back bit for bit
+        let mut odd = series(4);
+        (odd[0].open, odd[1].high, odd[2].low, odd[3].close) = (0.1 + 0.2, -0.0, f64::MIN_POSITIVE / 3.0, f64::MAX);
+        odd[3].volume = u64::MAX;
+        write_bars(&bin_path, &BarHeader::new("SPY", "1m", true), &odd).unwrap();
+        let file = BarFile::open(&bin_path).unwrap();
+        assert_eq!(&file.header, &BarHeader::new("SPY", "1m", true));
+        assert_eq!(file.bars().iter().map(bits).collect::<Vec<_>>(), odd.iter().map(bits).collect::<Vec<_>>());
+        assert_eq!(file.bars(), odd);
+        drop(file);
+        assert!(write_bars(&bin_path, &BarHeader::new("SPY", "1m", true), &[odd[1].clone(), odd[0].clone()]).is_err());
+
+        // A million minute bars, in both formats
+        let bars = series(1_000_000);
+        write_bars(&bin_path, &BarHeader::new("SPY", "1m", false), &bars).unwrap();
+        fs::write(&json_path, serde_json::to_string_pretty(&bars).unwrap()).unwrap();
+
+        let started = Instant::now();
+        let parsed: Vec<Candle> = serde_json::from_str(&fs::read_to_string(&json_path).unwrap()).unwrap();
+        let json_time = started.elapsed();
+        let started = Instant::now();
+        let file = BarFile::open(&bin_path).unwrap();
+        let decoded = file.bars();
+        let binary_time = started.elapsed();
+        assert_eq!(decoded.len(), parsed.len());
+        assert!(decoded.iter().zip(&bars).all(|(a, b)| bits(a) == bits(b)));
+        assert!(binary_time * 10 <= json_time, "binary {:?} vs JSON {:?}", binary_time, json_time);
+
+        // A sub-range decodes only its own bars, inclusive at both ends
+        let (from, to) = (bars[500_000].timestamp, bars[500_389].timestamp);
+        assert_eq!(file.range(from, to), 500_000..500_390);
+        assert_eq!(file.query(from - 30, to + 30), bars[500_000..500_390].to_vec());
+        assert!(file.query(0, bars[0].timestamp - 1).is_empty());
+        assert!(file.query(to, from).is_empty());
+        assert_eq!(file.query(i64::MIN, i64::MAX).len(), 1_000_000);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
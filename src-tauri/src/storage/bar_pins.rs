@@ -0,0 +1,99 @@
+// src-tauri/src/storage/bar_pins.rs
+// Bar series pinned by saved backtests, stored as backtests/pins/{hash}.json. Each pin is
+// a copy of the exact bars a run used, keyed by content hash, so runs over the same data
+// share one copy and the history cache can be evicted or restated without touching them.
+// A pin lists the runs holding it and is removed with the last of them.
+
+use crate::engine::reproducibility::bars_hash;
+use crate::market_data::types::Candle;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// Pins are updated with a read-modify-write of their file
+static PIN_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinnedSeries {
+    pub content_hash: String,
+    pub symbol: String,
+    pub bars: Vec<Candle>,
+    pub pinned_by: BTreeSet<String>, // Saved backtest ids
+}
+
+pub struct BarPinStore {
+    root: PathBuf,
+}
+
+impl BarPinStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    // Hashes are generated here; anything else is not a pin
+    fn pin_path(&self, hash: &str) -> Result<PathBuf, String> {
+        if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("No pinned bars {}", hash));
+        }
+        Ok(self.root.join(format!("{}.json", hash)))
+    }
+
+    fn read(&self, hash: &str) -> Result<Option<PinnedSeries>, String> {
+        let path = self.pin_path(hash)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map(Some).map_err(|e| format!("Pinned bars {} are unreadable: {}", hash, e))
+    }
+
+    fn write(&self, pin: &PinnedSeries) -> Result<(), String> {
+        fs::create_dir_all(&self.root).map_err(|e| e.to_string())?;
+        let path = self.pin_path(&pin.content_hash)?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(pin).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &path).map_err(|e| e.to_string())
+    }
+
+    /// Pin `bars` for backtest `run_id`, copying them only if no run pinned the same
+    /// data before; returns their content hash
+    pub fn pin(&self, symbol: &str, bars: &[Candle], run_id: &str) -> Result<String, String> {
+        let _guard = PIN_LOCK.lock().map_err(|e| e.to_string())?;
+        let hash = bars_hash(bars);
+        let mut pin = self.read(&hash)?.unwrap_or_else(|| PinnedSeries {
+            content_hash: hash.clone(),
+            symbol: symbol.to_string(),
+            bars: bars.to_vec(),
+            pinned_by: BTreeSet::new(),
+        });
+        pin.pinned_by.insert(run_id.to_string());
+        self.write(&pin)?;
+        Ok(hash)
+    }
+
+    /// The pinned bars, checked against their hash
+    pub fn load(&self, hash: &str) -> Result<Vec<Candle>, String> {
+        let _guard = PIN_LOCK.lock().map_err(|e| e.to_string())?;
+        let pin = self.read(hash)?.ok_or_else(|| format!("No pinned bars {}", hash))?;
+        if bars_hash(&pin.bars) != hash {
+            return Err(format!("Pinned bars {} no longer match their hash", hash));
+        }
+        Ok(pin.bars)
+    }
+
+    /// Drop `run_id`'s hold on a pin, removing the pin once nothing holds it
+    pub fn release(&self, hash: &str, run_id: &str) -> Result<(), String> {
+        let _guard = PIN_LOCK.lock().map_err(|e| e.to_string())?;
+        let Some(mut pin) = self.read(hash)? else {
+            return Ok(());
+        };
+        pin.pinned_by.remove(run_id);
+        if pin.pinned_by.is_empty() {
+            fs::remove_file(self.pin_path(hash)?).map_err(|e| e.to_string())
+        } else {
+            self.write(&pin)
+        }
+    }
+}
@@ -0,0 +1,56 @@
+// src-tauri/src/storage/bulk_actions.rs
+// Bulk order operations, appended to bulk_actions/{YYYY}-{MM}.jsonl in the cache dir by
+// session month, one parent record per line with its children inline.
+
+use crate::engine::bulk::BulkAction;
+use chrono::{Datelike, NaiveDate};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+pub const BULK_ACTIONS_DIR: &str = "bulk_actions";
+
+// Appends from concurrent commands must not interleave
+static BULK_ACTIONS_LOCK: Mutex<()> = Mutex::new(());
+
+pub struct BulkActionStore {
+    root: PathBuf,
+}
+
+impl BulkActionStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn month_path(&self, year: i32, month: u32) -> PathBuf {
+        self.root.join(format!("{:04}-{:02}.jsonl", year, month))
+    }
+
+    pub fn append(&self, action: &BulkAction) -> Result<(), String> {
+        let _guard = BULK_ACTIONS_LOCK.lock().map_err(|e| e.to_string())?;
+        fs::create_dir_all(&self.root).map_err(|e| e.to_string())?;
+        let line = serde_json::to_string(action).map_err(|e| e.to_string())?;
+        let path = self.month_path(action.session_date.year(), action.session_date.month());
+        let mut file = OpenOptions::new().create(true).append(true).open(&path).map_err(|e| e.to_string())?;
+        writeln!(file, "{}", line).map_err(|e| e.to_string())
+    }
+
+    /// Stored bulk actions from sessions `from` through `to`, oldest first. Lines that do
+    /// not parse (e.g. cut short by a crash) are skipped.
+    pub fn load(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<BulkAction>, String> {
+        let _guard = BULK_ACTIONS_LOCK.lock().map_err(|e| e.to_string())?;
+        let mut actions = Vec::new();
+        let (mut year, mut month) = (from.year(), from.month());
+        while (year, month) <= (to.year(), to.month()) {
+            let path = self.month_path(year, month);
+            if path.exists() {
+                let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+                actions.extend(text.lines().filter_map(|line| serde_json::from_str::<BulkAction>(line).ok()));
+            }
+            (year, month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+        }
+        actions.retain(|a| a.session_date >= from && a.session_date <= to);
+        Ok(actions)
+    }
+}
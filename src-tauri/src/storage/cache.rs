@@ -1,13 +1,17 @@
 // src-tauri/src/storage/cache.rs
 // Simple file cache in app_config_dir for JSON data
 
+use super::bar_file::{self, BarFile, BarHeader};
+use super::journal_store::{JournalStore, StorageBackendKind, TradeQuery};
+use crate::engine::types::{DailySummary, Trade};
+use crate::market_data::types::Candle;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{self, OpenOptions};
 use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::{AppHandle, Manager};
-use chrono::Utc;
+use chrono::{NaiveDate, Utc};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheEntry<T> {
@@ -218,6 +222,10 @@ impl FileCache {
         Ok(removed_count)
     }
 
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
     pub fn get_keys(&self) -> Vec<String> {
         self.metadata.keys().cloned().collect()
     }
@@ -264,6 +272,26 @@ pub fn cache_key_for_news(symbol: &str, days: u32) -> String {
     format!("news_{}_{}", symbol, days)
 }
 
+pub fn cache_key_for_news_impact(symbol: &str, days: u32) -> String {
+    format!("news_impact_{}_{}", symbol, days)
+}
+
+pub fn cache_key_for_option_chain(symbol: &str, as_of: &str) -> String {
+    format!("av_options_{}_{}", symbol, as_of)
+}
+
+pub fn cache_key_for_earnings(symbol: &str, horizon: &str) -> String {
+    format!("av_earnings_{}_{}", symbol, horizon)
+}
+
+pub fn cache_key_for_dividends(symbol: &str) -> String {
+    format!("av_dividends_{}", symbol)
+}
+
+pub fn cache_key_for_treasury_yield(maturity: &str) -> String {
+    format!("av_treasury_{}", maturity)
+}
+
 // Broker persistence utilities
 impl FileCache {
     pub fn save_broker_state<T>(&mut self, broker_state: &T) -> Result<(), String>
@@ -409,6 +437,129 @@ impl FileCache {
     }
 }
 
+/// The daily summaries inside broker_state.json, skipping the rest of the state
+#[derive(Deserialize)]
+struct SavedDailySummaries {
+    #[serde(default)]
+    daily_summaries: Vec<DailySummary>,
+}
+
+// File backend of the journal store: every query reads its whole file
+impl JournalStore for FileCache {
+    fn backend(&self) -> StorageBackendKind {
+        StorageBackendKind::File
+    }
+
+    fn append_trade(&self, trade: &Trade) -> Result<(), String> {
+        self.append_to_trade_journal(trade)
+    }
+
+    fn load_trades(&self) -> Result<Vec<Trade>, String> {
+        self.load_trade_journal()
+    }
+
+    // Reads the journal a line at a time
+    fn stream_trades(&self, query: &TradeQuery, visit: &mut dyn FnMut(Trade) -> Result<bool, String>) -> Result<(), String> {
+        let journal_file = self.cache_dir.join("trade_journal.jsonl");
+        if !journal_file.exists() {
+            return Ok(());
+        }
+        let file = fs::File::open(&journal_file).map_err(|e| format!("Failed to open journal file: {}", e))?;
+        for (line_num, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| format!("Failed to read line {}: {}", line_num + 1, e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let trade: Trade = serde_json::from_str(&line).map_err(|e| format!("Failed to parse line {}: {}", line_num + 1, e))?;
+            if query.matches(&trade) && !visit(trade)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // Summaries are saved with the broker state
+    fn append_daily_summary(&self, _summary: &DailySummary) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn daily_summaries(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Vec<DailySummary>, String> {
+        let saved: Option<SavedDailySummaries> = self.load_broker_state()?;
+        let mut summaries: Vec<DailySummary> = saved
+            .map(|s| s.daily_summaries)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|s| from.is_none_or(|from| s.date >= from) && to.is_none_or(|to| s.date <= to))
+            .collect();
+        summaries.sort_by_key(|s| s.date);
+        Ok(summaries)
+    }
+
+    fn save_bars(&self, symbol: &str, interval: &str, bars: &[Candle]) -> Result<(), String> {
+        let existing = self.open_bars(symbol, interval)?.map(|file| file.bars()).unwrap_or_default();
+        let mut merged: BTreeMap<i64, Candle> = existing.into_iter().map(|c| (c.timestamp, c)).collect();
+        merged.extend(bars.iter().map(|c| (c.timestamp, c.clone())));
+        let merged: Vec<Candle> = merged.into_values().collect();
+        bar_file::write_bars(&self.bars_file(symbol, interval), &BarHeader::new(symbol, interval, ADJUSTED_BARS), &merged)
+    }
+
+    fn query_bars(&self, symbol: &str, interval: &str, from: i64, to: i64) -> Result<Vec<Candle>, String> {
+        Ok(self.open_bars(symbol, interval)?.map(|file| file.query(from, to)).unwrap_or_default())
+    }
+
+    fn journal_stats(&self) -> Result<JournalStats, String> {
+        self.get_journal_stats()
+    }
+
+    fn backup(&self, suffix: &str) -> Result<PathBuf, String> {
+        self.backup_journal(suffix)
+    }
+}
+
+/// History bars are fetched split-adjusted
+const ADJUSTED_BARS: bool = true;
+
+impl FileCache {
+    fn bars_file(&self, symbol: &str, interval: &str) -> PathBuf {
+        self.get_file_path(&format!("bars_{}_{}", symbol, interval)).with_extension("bars")
+    }
+
+    /// Where the bar cache kept a series as JSON before the binary format
+    fn legacy_bars_file(&self, symbol: &str, interval: &str) -> PathBuf {
+        self.get_file_path(&format!("bars_{}_{}", symbol, interval))
+    }
+
+    /// The series' bar file, converting a JSON series left by an earlier version first
+    fn open_bars(&self, symbol: &str, interval: &str) -> Result<Option<BarFile>, String> {
+        let path = self.bars_file(symbol, interval);
+        let legacy = self.legacy_bars_file(symbol, interval);
+        if legacy.exists() {
+            if !path.exists() {
+                let content = fs::read_to_string(&legacy).map_err(|e| format!("Failed to read bars: {}", e))?;
+                let bars: Vec<Candle> = serde_json::from_str(&content).map_err(|e| format!("Failed to parse bars: {}", e))?;
+                let sorted: BTreeMap<i64, Candle> = bars.into_iter().map(|c| (c.timestamp, c)).collect();
+                let sorted: Vec<Candle> = sorted.into_values().collect();
+                bar_file::write_bars(&path, &BarHeader::new(symbol, interval, ADJUSTED_BARS), &sorted)?;
+            }
+            fs::remove_file(&legacy).map_err(|e| format!("Failed to remove migrated JSON bars: {}", e))?;
+        }
+        if !path.exists() {
+            return Ok(None);
+        }
+        BarFile::open(&path).map(Some)
+    }
+
+    /// Write a cached series out as pretty-printed JSON beside its bar file, for
+    /// debugging or export; returns the file written
+    pub fn export_bars_json(&self, symbol: &str, interval: &str) -> Result<PathBuf, String> {
+        let file = self.open_bars(symbol, interval)?.ok_or_else(|| format!("No cached {} bars for {}", interval, symbol))?;
+        let path = self.bars_file(symbol, interval).with_extension("export.json");
+        let content = serde_json::to_string_pretty(&file.bars()).map_err(|e| format!("Failed to serialize bars: {}", e))?;
+        fs::write(&path, content).map_err(|e| format!("Failed to write bars: {}", e))?;
+        Ok(path)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JournalStats {
     pub total_entries: usize,
@@ -434,4 +585,28 @@ mod tests {
         let path = cache.get_file_path("AAPL/2023-01-01/2023-12-31");
         assert!(path.to_string_lossy().contains("AAPL_2023-01-01_2023-12-31"));
     }
+
+    #[test]
+    fn test_json_bars_migrate_to_the_binary_format_on_first_access() {
+        let cache_dir = std::env::temp_dir().join(format!("bar_cache_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&cache_dir).unwrap();
+        let cache = FileCache { cache_dir: cache_dir.clone(), metadata: HashMap::new(), metadata_file: cache_dir.join("metadata.json") };
+        let bar = |day: i64, close: f64| Candle::new(1_700_000_000 + day * 86400, close, close, close, close, 100);
+        let legacy: Vec<Candle> = vec![bar(2, 12.0), bar(0, 10.0), bar(1, 11.0)];
+        fs::write(cache.legacy_bars_file("AAPL", "1d"), serde_json::to_string(&legacy).unwrap()).unwrap();
+
+        let range = cache.query_bars("AAPL", "1d", 1_700_000_000, 1_700_000_000 + 86400).unwrap();
+        assert_eq!(range.iter().map(|c| c.close).collect::<Vec<_>>(), vec![10.0, 11.0]);
+        assert!(!cache.legacy_bars_file("AAPL", "1d").exists() && cache.bars_file("AAPL", "1d").exists());
+
+        // Later saves merge into the binary series; JSON stays available as an export
+        cache.save_bars("AAPL", "1d", &[bar(2, 12.5), bar(3, 13.0)]).unwrap();
+        let all = cache.query_bars("AAPL", "1d", 0, i64::MAX).unwrap();
+        assert_eq!(all.iter().map(|c| c.close).collect::<Vec<_>>(), vec![10.0, 11.0, 12.5, 13.0]);
+        let exported: Vec<Candle> = serde_json::from_str(&fs::read_to_string(cache.export_bars_json("AAPL", "1d").unwrap()).unwrap()).unwrap();
+        assert_eq!(exported, all);
+        assert!(cache.query_bars("AAPL", "1h", 0, i64::MAX).unwrap().is_empty());
+
+        fs::remove_dir_all(&cache_dir).unwrap();
+    }
 }
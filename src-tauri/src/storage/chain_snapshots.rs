@@ -0,0 +1,525 @@
+// src-tauri/src/storage/chain_snapshots.rs
+// Recorded end-of-day option chains for backtesting on real quotes instead of
+// Black-Scholes estimates. Stored as chains/{SYMBOL}/{YYYY-MM-DD}.json.gz with an index.
+
+use super::cache::FileCache;
+use crate::engine::calendar::MarketCalendar;
+use crate::engine::symbols::normalize_symbol;
+use crate::provider::alphavantage::OptionChain;
+use crate::provider::polygon as poly;
+use chrono::{NaiveDate, NaiveTime, Utc};
+use chrono_tz::America::New_York;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration};
+
+const INDEX_FILE: &str = "index.json";
+const RECORDER_CONFIG_KEY: &str = "chain_recorder_config";
+const SCHEDULER_POLL_SECS: u64 = 300;
+const BYTES_PER_GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// Snapshots are taken once quotes have settled after the 16:00 ET close
+fn record_after() -> NaiveTime {
+    NaiveTime::from_hms_opt(16, 30, 0).unwrap()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChainRecorderConfig {
+    pub enabled: bool,
+    pub underlyings: Vec<String>,
+    pub max_storage_gb: f64, // Oldest snapshots are pruned beyond this
+}
+
+impl Default for ChainRecorderConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            underlyings: Vec::new(),
+            max_storage_gb: 5.0,
+        }
+    }
+}
+
+impl ChainRecorderConfig {
+    fn max_bytes(&self) -> u64 {
+        (self.max_storage_gb.max(0.0) * BYTES_PER_GB) as u64
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SnapshotEntry {
+    pub symbol: String,
+    pub date: NaiveDate, // Session the quotes belong to
+    pub bytes: u64,      // Compressed size on disk
+    pub contracts: usize,
+    pub recorded_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChainIndex {
+    pub entries: Vec<SnapshotEntry>,
+}
+
+impl ChainIndex {
+    pub fn total_bytes(&self) -> u64 {
+        self.entries.iter().map(|e| e.bytes).sum()
+    }
+
+    fn find(&self, symbol: &str, date: NaiveDate) -> Option<&SnapshotEntry> {
+        self.entries.iter().find(|e| e.symbol == symbol && e.date == date)
+    }
+
+    fn remove(&mut self, symbol: &str, date: NaiveDate) {
+        self.entries.retain(|e| !(e.symbol == symbol && e.date == date));
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotSaved {
+    pub entry: SnapshotEntry,
+    pub pruned: Vec<SnapshotEntry>, // Evicted to stay within the storage budget
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ChainSource {
+    Recorded, // Real quotes from a stored snapshot
+    Modeled,  // No snapshot; the backtest prices options synthetically
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainDay {
+    pub date: NaiveDate,
+    pub source: ChainSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainCoverage {
+    pub symbol: String,
+    pub days: Vec<ChainDay>,
+    pub recorded_days: usize,
+    pub modeled_days: usize,
+}
+
+/// Snapshot files and their index. Not synchronized; `ChainRecorder` serializes access.
+pub struct ChainStore {
+    root: PathBuf,
+}
+
+impl ChainStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn open(app_handle: &AppHandle) -> Result<Self, String> {
+        let root = app_handle
+            .path()
+            .app_config_dir()
+            .map_err(|e| format!("Failed to get app config directory: {}", e))?
+            .join("chains");
+        Ok(Self::new(root))
+    }
+
+    fn snapshot_path(&self, symbol: &str, date: NaiveDate) -> PathBuf {
+        self.root.join(symbol).join(format!("{}.json.gz", date.format("%Y-%m-%d")))
+    }
+
+    /// The index, rebuilt from the snapshot files when it is missing or unreadable
+    pub fn index(&self) -> ChainIndex {
+        let parsed = fs::read_to_string(self.root.join(INDEX_FILE))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok());
+        if let Some(index) = parsed {
+            return index;
+        }
+
+        let index = self.rebuild_index();
+        if !index.entries.is_empty() {
+            if let Err(e) = self.write_index(&index) {
+                eprintln!("Failed to write rebuilt chain index: {}", e);
+            }
+        }
+        index
+    }
+
+    fn rebuild_index(&self) -> ChainIndex {
+        let mut entries = Vec::new();
+        let Ok(symbols) = fs::read_dir(&self.root) else {
+            return ChainIndex::default();
+        };
+
+        for symbol_dir in symbols.flatten().filter(|d| d.path().is_dir()) {
+            let symbol = symbol_dir.file_name().to_string_lossy().to_string();
+            let Ok(files) = fs::read_dir(symbol_dir.path()) else {
+                continue;
+            };
+            for file in files.flatten() {
+                let name = file.file_name().to_string_lossy().to_string();
+                let Some(date) = name.strip_suffix(".json.gz").and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()) else {
+                    continue;
+                };
+                let metadata = file.metadata().ok();
+                entries.push(SnapshotEntry {
+                    symbol: symbol.clone(),
+                    date,
+                    bytes: metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+                    contracts: 0, // Unknown until the snapshot is read
+                    recorded_at: metadata
+                        .and_then(|m| m.modified().ok())
+                        .map(|t| chrono::DateTime::<Utc>::from(t).timestamp())
+                        .unwrap_or(0),
+                });
+            }
+        }
+
+        entries.sort_by(|a, b| (a.date, &a.symbol).cmp(&(b.date, &b.symbol)));
+        ChainIndex { entries }
+    }
+
+    fn write_index(&self, index: &ChainIndex) -> Result<(), String> {
+        fs::create_dir_all(&self.root).map_err(|e| e.to_string())?;
+        let path = self.root.join(INDEX_FILE);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(index).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &path).map_err(|e| e.to_string())
+    }
+
+    /// Compress and store a chain, replacing any snapshot for the same day, then prune
+    /// the oldest snapshots until the store fits in `max_bytes`
+    pub fn save(&self, chain: &OptionChain, date: NaiveDate, max_bytes: u64) -> Result<SnapshotSaved, String> {
+        let symbol = chain.underlying_symbol.to_uppercase();
+        let path = self.snapshot_path(&symbol, date);
+        fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+
+        let json = serde_json::to_vec(chain).map_err(|e| e.to_string())?;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&json).map_err(|e| e.to_string())?;
+        let compressed = encoder.finish().map_err(|e| e.to_string())?;
+
+        let tmp = path.with_extension("gz.tmp");
+        fs::write(&tmp, &compressed).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &path).map_err(|e| e.to_string())?;
+
+        let entry = SnapshotEntry {
+            symbol: symbol.clone(),
+            date,
+            bytes: compressed.len() as u64,
+            contracts: chain.contracts.len(),
+            recorded_at: Utc::now().timestamp(),
+        };
+
+        let mut index = self.index();
+        index.remove(&symbol, date);
+        index.entries.push(entry.clone());
+        let pruned = self.prune(&mut index, max_bytes, &entry);
+        self.write_index(&index)?;
+
+        Ok(SnapshotSaved { entry, pruned })
+    }
+
+    fn prune(&self, index: &mut ChainIndex, max_bytes: u64, keep: &SnapshotEntry) -> Vec<SnapshotEntry> {
+        index.entries.sort_by(|a, b| (a.date, a.recorded_at).cmp(&(b.date, b.recorded_at)));
+
+        let mut pruned = Vec::new();
+        while index.total_bytes() > max_bytes {
+            let Some(position) = index.entries.iter().position(|e| !(e.symbol == keep.symbol && e.date == keep.date)) else {
+                break;
+            };
+            let entry = index.entries.remove(position);
+            if let Err(e) = fs::remove_file(self.snapshot_path(&entry.symbol, entry.date)) {
+                eprintln!("Failed to remove pruned chain snapshot {} {}: {}", entry.symbol, entry.date, e);
+            }
+            pruned.push(entry);
+        }
+        pruned
+    }
+
+    pub fn list(&self, symbol: &str) -> Vec<SnapshotEntry> {
+        let symbol = symbol.to_uppercase();
+        let mut entries: Vec<SnapshotEntry> = self.index().entries.into_iter().filter(|e| e.symbol == symbol).collect();
+        entries.sort_by_key(|e| e.date);
+        entries
+    }
+
+    /// A stored chain, or None when there is no usable snapshot. Unreadable files are
+    /// renamed to `.corrupt` and dropped from the index so later runs model that day.
+    pub fn load(&self, symbol: &str, date: NaiveDate) -> Result<Option<OptionChain>, String> {
+        let symbol = symbol.to_uppercase();
+        let path = self.snapshot_path(&symbol, date);
+        if !path.exists() {
+            let mut index = self.index();
+            if index.find(&symbol, date).is_some() {
+                index.remove(&symbol, date);
+                self.write_index(&index)?;
+            }
+            return Ok(None);
+        }
+
+        let decoded = fs::read(&path).map_err(|e| e.to_string()).and_then(|bytes| {
+            let mut json = String::new();
+            GzDecoder::new(bytes.as_slice()).read_to_string(&mut json).map_err(|e| e.to_string())?;
+            serde_json::from_str::<OptionChain>(&json).map_err(|e| e.to_string())
+        });
+
+        match decoded {
+            Ok(chain) => Ok(Some(chain)),
+            Err(e) => {
+                eprintln!("Chain snapshot {} {} is corrupt, ignoring it: {}", symbol, date, e);
+                let _ = fs::rename(&path, path.with_extension("gz.corrupt"));
+                let mut index = self.index();
+                index.remove(&symbol, date);
+                self.write_index(&index)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Which trading days in the range have a recorded chain
+    pub fn coverage(&self, symbol: &str, start: NaiveDate, end: NaiveDate, calendar: &MarketCalendar) -> ChainCoverage {
+        let symbol = symbol.to_uppercase();
+        let index = self.index();
+        let days: Vec<ChainDay> = calendar
+            .get_trading_days(start, end)
+            .into_iter()
+            .map(|date| ChainDay {
+                date,
+                source: if index.find(&symbol, date).is_some() { ChainSource::Recorded } else { ChainSource::Modeled },
+            })
+            .collect();
+        let recorded_days = days.iter().filter(|d| d.source == ChainSource::Recorded).count();
+
+        ChainCoverage {
+            symbol,
+            modeled_days: days.len() - recorded_days,
+            recorded_days,
+            days,
+        }
+    }
+}
+
+/// Records chains on demand and once per trading day after the close
+#[derive(Clone)]
+pub struct ChainRecorder {
+    store: Arc<Mutex<ChainStore>>,
+    config: Arc<Mutex<ChainRecorderConfig>>,
+    app_handle: AppHandle,
+}
+
+impl ChainRecorder {
+    pub fn new(app_handle: AppHandle) -> Result<Self, String> {
+        let config = FileCache::new(&app_handle)
+            .and_then(|mut cache| cache.get(RECORDER_CONFIG_KEY))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+
+        Ok(Self {
+            store: Arc::new(Mutex::new(ChainStore::open(&app_handle)?)),
+            config: Arc::new(Mutex::new(config)),
+            app_handle,
+        })
+    }
+
+    pub fn start_scheduler(&self) -> JoinHandle<()> {
+        let recorder = self.clone();
+        tauri::async_runtime::spawn(async move {
+            recorder.run_scheduler().await;
+        })
+    }
+
+    pub async fn config(&self) -> ChainRecorderConfig {
+        self.config.lock().await.clone()
+    }
+
+    pub async fn configure(&self, mut config: ChainRecorderConfig) -> Result<ChainRecorderConfig, String> {
+        if config.max_storage_gb <= 0.0 {
+            return Err("max_storage_gb must be positive".to_string());
+        }
+        config.underlyings = config
+            .underlyings
+            .iter()
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| normalize_symbol(s))
+            .collect::<Result<_, _>>()?;
+        config.underlyings.dedup();
+
+        FileCache::new(&self.app_handle).and_then(|mut cache| cache.set(RECORDER_CONFIG_KEY, config.clone(), None))?;
+        *self.config.lock().await = config.clone();
+        Ok(config)
+    }
+
+    /// Fetch and store today's chain for an underlying
+    pub async fn snapshot(&self, symbol: &str) -> Result<SnapshotSaved, String> {
+        let chain = poly::fetch_option_chain_snapshot(&self.app_handle, symbol).await?;
+        let session = Utc::now().with_timezone(&New_York).date_naive();
+        let max_bytes = self.config.lock().await.max_bytes();
+
+        let saved = self.store.lock().await.save(&chain, session, max_bytes)?;
+        let _ = self.app_handle.emit("chain_snapshot_saved", &saved);
+        Ok(saved)
+    }
+
+    pub async fn list(&self, symbol: &str) -> Vec<SnapshotEntry> {
+        self.store.lock().await.list(symbol)
+    }
+
+    pub async fn load(&self, symbol: &str, date: NaiveDate) -> Result<Option<OptionChain>, String> {
+        self.store.lock().await.load(symbol, date)
+    }
+
+    pub async fn coverage(&self, symbol: &str, start: NaiveDate, end: NaiveDate) -> ChainCoverage {
+        self.store.lock().await.coverage(symbol, start, end, &MarketCalendar::new())
+    }
+
+    async fn run_scheduler(&self) {
+        let calendar = MarketCalendar::new();
+        loop {
+            let config = self.config().await;
+            let now = Utc::now().with_timezone(&New_York);
+            let session = now.date_naive();
+
+            if config.enabled && calendar.is_trading_day(session) && now.time() >= record_after() {
+                let recorded = self.store.lock().await.index();
+                for symbol in &config.underlyings {
+                    if recorded.find(symbol, session).is_some() {
+                        continue;
+                    }
+                    if let Err(e) = self.snapshot(symbol).await {
+                        eprintln!("Chain snapshot for {} failed: {}", symbol, e);
+                        let _ = self.app_handle.emit("chain_snapshot_failed", &serde_json::json!({
+                            "symbol": symbol,
+                            "date": session,
+                            "error": e,
+                        }));
+                    }
+                }
+            }
+
+            sleep(Duration::from_secs(SCHEDULER_POLL_SECS)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::alphavantage::OptionContract;
+    use std::collections::HashMap;
+
+    fn temp_store() -> ChainStore {
+        ChainStore::new(std::env::temp_dir().join(format!("chains-test-{}", uuid::Uuid::new_v4())))
+    }
+
+    fn chain(symbol: &str, contracts: usize) -> OptionChain {
+        let contracts: HashMap<String, OptionContract> = (0..contracts)
+            .map(|i| {
+                let ticker = format!("O:{}250117C{:08}", symbol, 100_000 + i * 5000);
+                let contract = OptionContract {
+                    symbol: ticker.clone(),
+                    strike: 100.0 + i as f64 * 5.0,
+                    expiry: date("2025-01-17"),
+                    option_type: "call".to_string(),
+                    last_price: Some(2.5),
+                    mark: Some(2.45),
+                    bid: Some(2.4),
+                    ask: Some(2.5),
+                    volume: Some(100),
+                    open_interest: Some(1000),
+                    implied_volatility: Some(0.25),
+                    delta: Some(0.5),
+                    gamma: None,
+                    theta: None,
+                    vega: None,
+                };
+                (ticker, contract)
+            })
+            .collect();
+
+        OptionChain {
+            underlying_symbol: symbol.to_string(),
+            as_of: date("2024-01-02"),
+            expiries: vec![date("2025-01-17")],
+            strikes: contracts.values().map(|c| c.strike).collect(),
+            contracts,
+        }
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_roundtrip_and_index_rebuild() {
+        let store = temp_store();
+        let saved = store.save(&chain("spy", 40), date("2024-01-02"), u64::MAX).unwrap();
+        assert_eq!(saved.entry.symbol, "SPY");
+        assert_eq!(saved.entry.contracts, 40);
+        assert!(store.root.join("SPY/2024-01-02.json.gz").exists());
+
+        let loaded = store.load("SPY", date("2024-01-02")).unwrap().unwrap();
+        assert_eq!(loaded.contracts.len(), 40);
+        assert!(store.load("SPY", date("2024-01-03")).unwrap().is_none());
+
+        // A lost index is rebuilt from the files on disk
+        fs::remove_file(store.root.join(INDEX_FILE)).unwrap();
+        let entries = store.list("spy");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].date, date("2024-01-02"));
+        assert_eq!(entries[0].bytes, saved.entry.bytes);
+    }
+
+    #[test]
+    fn test_corrupt_snapshot_is_skipped() {
+        let store = temp_store();
+        store.save(&chain("QQQ", 10), date("2024-01-02"), u64::MAX).unwrap();
+        store.save(&chain("QQQ", 10), date("2024-01-03"), u64::MAX).unwrap();
+        fs::write(store.snapshot_path("QQQ", date("2024-01-03")), b"not gzip").unwrap();
+
+        assert!(store.load("QQQ", date("2024-01-03")).unwrap().is_none());
+        assert_eq!(store.list("QQQ").len(), 1);
+        assert!(store.root.join("QQQ/2024-01-03.json.gz.corrupt").exists());
+        assert!(store.load("QQQ", date("2024-01-02")).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_budget_prunes_oldest_first() {
+        let store = temp_store();
+        let first = store.save(&chain("SPY", 50), date("2024-01-02"), u64::MAX).unwrap().entry;
+        store.save(&chain("QQQ", 50), date("2024-01-03"), u64::MAX).unwrap();
+
+        // Room for roughly two snapshots
+        let budget = first.bytes * 2 + first.bytes / 2;
+        let saved = store.save(&chain("SPY", 50), date("2024-01-04"), budget).unwrap();
+        assert_eq!(saved.pruned.len(), 1);
+        assert_eq!(saved.pruned[0].date, date("2024-01-02"));
+        assert!(!store.snapshot_path("SPY", date("2024-01-02")).exists());
+        assert!(store.index().total_bytes() <= budget);
+
+        // The new snapshot is kept even if it alone exceeds the budget
+        let saved = store.save(&chain("SPY", 50), date("2024-01-05"), 1).unwrap();
+        assert_eq!(saved.pruned.len(), 2);
+        assert_eq!(store.index().entries.len(), 1);
+    }
+
+    #[test]
+    fn test_coverage_marks_recorded_and_modeled_days() {
+        let store = temp_store();
+        store.save(&chain("SPY", 5), date("2024-01-03"), u64::MAX).unwrap();
+
+        // Jan 1 is a holiday and Jan 6-7 a weekend
+        let coverage = store.coverage("SPY", date("2024-01-01"), date("2024-01-08"), &MarketCalendar::new());
+        assert_eq!(coverage.days.len(), 5);
+        assert_eq!(coverage.recorded_days, 1);
+        assert_eq!(coverage.modeled_days, 4);
+        let recorded: Vec<NaiveDate> = coverage.days.iter().filter(|d| d.source == ChainSource::Recorded).map(|d| d.date).collect();
+        assert_eq!(recorded, vec![date("2024-01-03")]);
+    }
+}
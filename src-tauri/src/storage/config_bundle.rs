@@ -0,0 +1,263 @@
+// src-tauri/src/storage/config_bundle.rs
+// Versioned configuration bundles for moving settings between machines.
+// Broker state, journals and cached market data are never part of a bundle.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+pub const SECTION_PREFERENCES: &str = "preferences";
+pub const SECTION_RISK_LIMITS: &str = "risk_limits";
+pub const SECTION_STRATEGY_LOOP: &str = "strategy_loop"; // Includes per-symbol rules, whose symbols are imported into the watchlist
+pub const SECTION_FEE_SCHEDULE: &str = "fee_schedule";   // BrokerConfig commissions and fill simulation
+pub const SECTION_CALENDAR: &str = "calendar";
+pub const SECTION_SECRETS: &str = "secrets";
+
+pub const ALL_SECTIONS: [&str; 6] = [
+    SECTION_PREFERENCES,
+    SECTION_RISK_LIMITS,
+    SECTION_STRATEGY_LOOP,
+    SECTION_FEE_SCHEDULE,
+    SECTION_CALENDAR,
+    SECTION_SECRETS,
+];
+
+const MASKED: &str = "********";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SectionManifest {
+    pub name: String,
+    pub checksum: String, // FNV-1a 64 of the section's compact JSON
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub app_version: String,
+    pub created_at: i64,
+    pub contains_secrets: bool,
+    pub sections: Vec<SectionManifest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConfigBundle {
+    pub manifest: BundleManifest,
+    pub sections: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConfigChange {
+    pub path: String, // e.g. "max_daily_loss" or "holidays[3].name"
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionDiff {
+    pub section: String,
+    pub changes: Vec<ConfigChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub bundle_created_at: i64,
+    pub sections: Vec<SectionDiff>,
+    pub applied: Vec<String>,
+    pub backup_path: Option<String>,
+}
+
+pub fn checksum(value: &Value) -> String {
+    let bytes = serde_json::to_vec(value).unwrap_or_default();
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+impl ConfigBundle {
+    /// Bundle the given sections; secrets are dropped unless `include_secrets`
+    pub fn new(mut sections: BTreeMap<String, Value>, include_secrets: bool) -> Self {
+        if !include_secrets {
+            sections.remove(SECTION_SECRETS);
+        }
+
+        let manifest = BundleManifest {
+            format_version: BUNDLE_FORMAT_VERSION,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: Utc::now().timestamp(),
+            contains_secrets: sections.contains_key(SECTION_SECRETS),
+            sections: sections
+                .iter()
+                .map(|(name, value)| SectionManifest { name: name.clone(), checksum: checksum(value) })
+                .collect(),
+        };
+
+        Self { manifest, sections }
+    }
+
+    /// Reject bundles from a newer format, with unknown sections, or whose
+    /// sections do not match the manifest checksums
+    pub fn validate(&self) -> Result<(), String> {
+        if self.manifest.format_version > BUNDLE_FORMAT_VERSION {
+            return Err(format!(
+                "Bundle format {} is newer than this app supports ({})",
+                self.manifest.format_version, BUNDLE_FORMAT_VERSION
+            ));
+        }
+
+        for (name, value) in &self.sections {
+            if !ALL_SECTIONS.contains(&name.as_str()) {
+                return Err(format!("Unknown configuration section: {}", name));
+            }
+            let entry = self.manifest.sections
+                .iter()
+                .find(|s| &s.name == name)
+                .ok_or_else(|| format!("Section {} is missing from the manifest", name))?;
+            if entry.checksum != checksum(value) {
+                return Err(format!("Checksum mismatch in section {}; the bundle was modified or corrupted", name));
+            }
+        }
+
+        if let Some(missing) = self.manifest.sections.iter().find(|s| !self.sections.contains_key(&s.name)) {
+            return Err(format!("Section {} is listed in the manifest but missing", missing.name));
+        }
+
+        Ok(())
+    }
+
+    /// Bundle sections to import: all of them, or the requested subset
+    pub fn selected_sections(&self, requested: Option<&[String]>) -> Result<Vec<String>, String> {
+        match requested {
+            None => Ok(self.sections.keys().cloned().collect()),
+            Some(names) => names
+                .iter()
+                .map(|name| {
+                    if self.sections.contains_key(name) {
+                        Ok(name.clone())
+                    } else {
+                        Err(format!("Section {} is not in the bundle", name))
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Field-level differences between the current and incoming section values.
+/// Secret values are masked.
+pub fn diff_section(section: &str, current: &Value, incoming: &Value) -> SectionDiff {
+    let mut changes = Vec::new();
+    diff_values("", current, incoming, &mut changes);
+
+    if section == SECTION_SECRETS {
+        for change in &mut changes {
+            change.before = change.before.take().map(|_| Value::String(MASKED.to_string()));
+            change.after = change.after.take().map(|_| Value::String(MASKED.to_string()));
+        }
+    }
+
+    SectionDiff { section: section.to_string(), changes }
+}
+
+fn diff_values(path: &str, before: &Value, after: &Value, changes: &mut Vec<ConfigChange>) {
+    let join = |key: &str| if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) };
+
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                match (a.get(key), b.get(key)) {
+                    (Some(x), Some(y)) => diff_values(&join(key), x, y, changes),
+                    (x, y) => changes.push(ConfigChange { path: join(key), before: x.cloned(), after: y.cloned() }),
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for i in 0..a.len().max(b.len()) {
+                let item_path = format!("{}[{}]", path, i);
+                match (a.get(i), b.get(i)) {
+                    (Some(x), Some(y)) => diff_values(&item_path, x, y, changes),
+                    (x, y) => changes.push(ConfigChange { path: item_path, before: x.cloned(), after: y.cloned() }),
+                }
+            }
+        }
+        _ if before != after => changes.push(ConfigChange {
+            path: path.to_string(),
+            before: (!before.is_null()).then(|| before.clone()),
+            after: (!after.is_null()).then(|| after.clone()),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sections() -> BTreeMap<String, Value> {
+        BTreeMap::from([
+            (SECTION_RISK_LIMITS.to_string(), json!({ "max_daily_loss": 1000.0, "max_daily_trades": 50 })),
+            (SECTION_CALENDAR.to_string(), json!({ "allow_premarket": false, "holidays": [{ "name": "New Year" }] })),
+            (SECTION_SECRETS.to_string(), json!({ "polygon": "abc123" })),
+        ])
+    }
+
+    #[test]
+    fn test_secrets_only_when_requested() {
+        let bundle = ConfigBundle::new(sections(), false);
+        assert!(!bundle.manifest.contains_secrets);
+        assert!(!bundle.sections.contains_key(SECTION_SECRETS));
+        assert_eq!(bundle.manifest.sections.len(), 2);
+
+        let with_secrets = ConfigBundle::new(sections(), true);
+        assert!(with_secrets.manifest.contains_secrets);
+        assert!(with_secrets.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validation_catches_tampering_and_newer_versions() {
+        let mut bundle = ConfigBundle::new(sections(), false);
+        let text = serde_json::to_string(&bundle).unwrap();
+        let roundtrip: ConfigBundle = serde_json::from_str(&text).unwrap();
+        assert!(roundtrip.validate().is_ok());
+
+        bundle.sections.get_mut(SECTION_RISK_LIMITS).unwrap()["max_daily_loss"] = json!(1.0);
+        assert!(bundle.validate().unwrap_err().contains("Checksum mismatch"));
+
+        let mut newer = ConfigBundle::new(sections(), false);
+        newer.manifest.format_version = BUNDLE_FORMAT_VERSION + 1;
+        assert!(newer.validate().is_err());
+
+        let mut unknown = ConfigBundle::new(sections(), false);
+        unknown.sections.insert("broker_state".to_string(), json!({}));
+        assert!(unknown.validate().unwrap_err().contains("Unknown"));
+
+        let bundle = ConfigBundle::new(sections(), false);
+        assert!(bundle.selected_sections(Some(&[SECTION_SECRETS.to_string()])).is_err());
+        assert_eq!(bundle.selected_sections(None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_diff_reports_field_paths_and_masks_secrets() {
+        let current = json!({ "allow_premarket": false, "holidays": [{ "name": "New Year" }], "old": 1 });
+        let incoming = json!({ "allow_premarket": true, "holidays": [{ "name": "New Year" }, { "name": "Pi Day" }] });
+        let diff = diff_section(SECTION_CALENDAR, &current, &incoming);
+        let paths: Vec<&str> = diff.changes.iter().map(|c| c.path.as_str()).collect();
+        assert_eq!(paths, vec!["allow_premarket", "holidays[1]", "old"]);
+        assert_eq!(diff.changes[2].after, None);
+
+        let secrets = diff_section(SECTION_SECRETS, &json!({ "polygon": "old" }), &json!({ "polygon": "new" }));
+        assert_eq!(secrets.changes[0].after, Some(json!(MASKED)));
+
+        assert!(diff_section(SECTION_RISK_LIMITS, &json!({ "a": 1 }), &json!({ "a": 1 })).changes.is_empty());
+    }
+}
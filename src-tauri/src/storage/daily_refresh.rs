@@ -0,0 +1,403 @@
+// src-tauri/src/storage/daily_refresh.rs
+// Keeps daily bars for watchlist and held symbols warm: shortly after each close the
+// session's bars are fetched into the bar cache and the volatility profiles recomputed.
+// Sessions missed while the app was closed are caught up on the next start.
+
+use super::api_budget::{self, ApiBudget};
+use super::cache::FileCache;
+use super::downloads::{DownloadManager, MIN_REQUEST_INTERVAL_SECS};
+use super::journal_store;
+use crate::engine::calendar::MarketCalendar;
+use crate::engine::metrics::TRADING_DAYS_PER_YEAR;
+use crate::engine::symbols::normalize_symbol;
+use crate::market_data::types::Candle;
+use crate::provider::polygon as poly;
+use chrono::{Duration as ChronoDuration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration};
+
+const CONFIG_KEY: &str = "daily_refresh_config";
+const STATE_KEY: &str = "daily_refresh_state";
+pub const PROFILES_KEY: &str = "volatility_profiles";
+const SCHEDULER_POLL_SECS: u64 = 300;
+const YIELD_POLL_SECS: u64 = 30; // While downloads run or the quota is down to the reserve
+const PROFILE_LOOKBACK_DAYS: i64 = 120; // Calendar days of cached bars a profile reads
+const ATR_PERIOD: usize = 14;
+const SHORT_VOL_PERIOD: usize = 20;
+const LONG_VOL_PERIOD: usize = 60;
+
+/// Symbols the refresh covers, read from the broker and strategy loop on each run
+pub type TrackedSymbols = Arc<dyn Fn(&AppHandle) -> Vec<String> + Send + Sync>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DailyRefreshConfig {
+    pub enabled: bool,
+    pub delay_minutes: u32,     // After the close, for the provider to finalize the day's bars
+    pub max_catch_up_days: u32, // Trading days backfilled at most after missed refreshes
+    pub reserved_calls: u32,    // Daily API calls scheduled refreshes leave for interactive requests
+}
+
+impl Default for DailyRefreshConfig {
+    fn default() -> Self {
+        Self { enabled: true, delay_minutes: 30, max_catch_up_days: 10, reserved_calls: 25 }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct RefreshState {
+    #[serde(default)]
+    last_session: Option<NaiveDate>, // Latest session a scheduled refresh covered
+}
+
+/// ATR and realized volatility from cached daily bars
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VolatilityProfile {
+    pub symbol: String,
+    pub as_of: NaiveDate, // Date of the last bar used
+    pub bars: usize,
+    pub atr: f64,     // 14-day average true range
+    pub atr_pct: f64, // ATR over the last close
+    pub realized_vol_20: f64,
+    pub realized_vol_60: Option<f64>, // None with fewer than 61 bars
+}
+
+impl VolatilityProfile {
+    /// None with fewer bars than the 20-day realized volatility needs
+    pub fn compute(symbol: &str, bars: &[Candle]) -> Option<Self> {
+        let last = bars.last()?;
+        if bars.len() <= SHORT_VOL_PERIOD.max(ATR_PERIOD) || last.close <= 0.0 {
+            return None;
+        }
+        let true_ranges: Vec<f64> = bars
+            .windows(2)
+            .map(|w| (w[1].high - w[1].low).max((w[1].high - w[0].close).abs()).max((w[1].low - w[0].close).abs()))
+            .collect();
+        let atr = true_ranges[true_ranges.len() - ATR_PERIOD..].iter().sum::<f64>() / ATR_PERIOD as f64;
+        let log_returns: Vec<f64> = bars
+            .windows(2)
+            .map(|w| if w[0].close > 0.0 && w[1].close > 0.0 { (w[1].close / w[0].close).ln() } else { 0.0 })
+            .collect();
+
+        Some(Self {
+            symbol: symbol.to_string(),
+            as_of: last.date(),
+            bars: bars.len(),
+            atr,
+            atr_pct: atr / last.close,
+            realized_vol_20: realized_vol(&log_returns[log_returns.len() - SHORT_VOL_PERIOD..]),
+            realized_vol_60: (log_returns.len() >= LONG_VOL_PERIOD).then(|| realized_vol(&log_returns[log_returns.len() - LONG_VOL_PERIOD..])),
+        })
+    }
+}
+
+/// Annualized sample standard deviation of daily log returns
+fn realized_vol(returns: &[f64]) -> f64 {
+    if returns.len() < 2 {
+        return 0.0;
+    }
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    variance.sqrt() * TRADING_DAYS_PER_YEAR.sqrt()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SymbolRefresh {
+    pub symbol: String,
+    pub ok: bool,
+    pub bars: usize, // Bars the provider returned for the refreshed sessions
+    pub last_bar: Option<NaiveDate>,
+    pub error: Option<String>,
+}
+
+/// Payload of the "daily_data_refreshed" event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyRefreshReport {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub manual: bool,
+    pub symbols: Vec<SymbolRefresh>,
+    pub profiles_updated: usize,
+    pub finished_at: i64,
+}
+
+/// Latest trading day whose close was at least `delay_minutes` before `now`
+pub fn latest_final_session(calendar: &MarketCalendar, now: i64, delay_minutes: u32) -> Option<NaiveDate> {
+    let today = chrono::DateTime::from_timestamp(now, 0)?.date_naive();
+    (0..10)
+        .filter_map(|days_back| today.checked_sub_signed(ChronoDuration::days(days_back)))
+        .filter(|date| calendar.is_trading_day(*date))
+        .find(|date| {
+            // Noon UTC is before the open, so this is the session's own close
+            let noon = date.and_hms_opt(12, 0, 0).map(|t| t.and_utc().timestamp()).unwrap_or(now);
+            calendar
+                .next_regular_close(noon)
+                .is_some_and(|close| close + delay_minutes as i64 * 60 <= now)
+        })
+}
+
+/// Sessions a scheduled refresh still has to fetch: those after `last_session` up to the
+/// latest final one, at most `max_catch_up_days` of them. A first run fetches only the
+/// latest session.
+pub fn refresh_window(
+    calendar: &MarketCalendar,
+    now: i64,
+    config: &DailyRefreshConfig,
+    last_session: Option<NaiveDate>,
+) -> Option<(NaiveDate, NaiveDate)> {
+    let latest = latest_final_session(calendar, now, config.delay_minutes)?;
+    let Some(last_session) = last_session else {
+        return Some((latest, latest));
+    };
+    if last_session >= latest {
+        return None;
+    }
+    let lookback = latest - ChronoDuration::days(config.max_catch_up_days.max(1) as i64 * 2 + 10);
+    let missed: Vec<NaiveDate> = calendar
+        .get_trading_days(lookback.max(last_session.succ_opt()?), latest)
+        .into_iter()
+        .filter(|date| *date > last_session)
+        .collect();
+    let from = missed[missed.len().saturating_sub(config.max_catch_up_days.max(1) as usize)..].first().copied()?;
+    Some((from, latest))
+}
+
+/// Runs the after-close refresh and manual refreshes, one at a time. Scheduled runs
+/// yield to download jobs and leave `reserved_calls` of the daily quota untouched.
+#[derive(Clone)]
+pub struct DailyRefresher {
+    config: Arc<Mutex<DailyRefreshConfig>>,
+    running: Arc<Mutex<()>>,
+    downloads: DownloadManager,
+    tracked: TrackedSymbols,
+    app_handle: AppHandle,
+}
+
+impl DailyRefresher {
+    pub fn new(app_handle: AppHandle, downloads: DownloadManager, tracked: TrackedSymbols) -> Self {
+        let config = FileCache::new(&app_handle)
+            .and_then(|mut cache| cache.get(CONFIG_KEY))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+
+        Self {
+            config: Arc::new(Mutex::new(config)),
+            running: Arc::new(Mutex::new(())),
+            downloads,
+            tracked,
+            app_handle,
+        }
+    }
+
+    /// Poll for a finished session, starting with any the app missed while closed
+    pub fn start_scheduler(&self) -> JoinHandle<()> {
+        let refresher = self.clone();
+        tauri::async_runtime::spawn(async move {
+            refresher.run_scheduler().await;
+        })
+    }
+
+    pub async fn config(&self) -> DailyRefreshConfig {
+        self.config.lock().await.clone()
+    }
+
+    pub async fn configure(&self, config: DailyRefreshConfig) -> Result<DailyRefreshConfig, String> {
+        if config.max_catch_up_days == 0 {
+            return Err("max_catch_up_days must be at least 1".to_string());
+        }
+        if config.delay_minutes > 8 * 60 {
+            return Err("delay_minutes must be at most 480".to_string());
+        }
+        FileCache::new(&self.app_handle).and_then(|mut cache| cache.set(CONFIG_KEY, config.clone(), None))?;
+        *self.config.lock().await = config.clone();
+        Ok(config)
+    }
+
+    /// Cached profiles by symbol
+    pub fn profiles(&self) -> Result<BTreeMap<String, VolatilityProfile>, String> {
+        Ok(FileCache::new(&self.app_handle)?.get(PROFILES_KEY)?.unwrap_or_default())
+    }
+
+    /// Refresh `symbols` (the tracked ones when None) for the latest final session now,
+    /// without waiting behind download jobs
+    pub async fn refresh_now(&self, symbols: Option<Vec<String>>) -> Result<DailyRefreshReport, String> {
+        let symbols = match symbols {
+            Some(symbols) => symbols.iter().map(|s| normalize_symbol(s)).collect::<Result<Vec<_>, _>>()?,
+            None => (self.tracked)(&self.app_handle),
+        };
+        if symbols.is_empty() {
+            return Err("No symbols to refresh; add symbols to the watchlist or pass a list".to_string());
+        }
+        let delay = self.config().await.delay_minutes;
+        let session = latest_final_session(&MarketCalendar::new(), Utc::now().timestamp(), delay)
+            .ok_or("No finished trading session to refresh")?;
+        Ok(self.refresh(symbols, session, session, true).await)
+    }
+
+    async fn run_scheduler(&self) {
+        let calendar = MarketCalendar::new();
+        loop {
+            let config = self.config().await;
+            let last_session = self.load_state().last_session;
+            if let Some((from, to)) = config.enabled.then(|| refresh_window(&calendar, Utc::now().timestamp(), &config, last_session)).flatten() {
+                let symbols = (self.tracked)(&self.app_handle);
+                if !symbols.is_empty() {
+                    self.refresh(symbols, from, to, false).await;
+                }
+                self.save_state(&RefreshState { last_session: Some(to) });
+            }
+            sleep(Duration::from_secs(SCHEDULER_POLL_SECS)).await;
+        }
+    }
+
+    async fn refresh(&self, mut symbols: Vec<String>, from: NaiveDate, to: NaiveDate, manual: bool) -> DailyRefreshReport {
+        let _running = self.running.lock().await;
+        symbols.sort();
+        symbols.dedup();
+
+        let mut results = Vec::new();
+        let mut profiles = self.profiles().unwrap_or_default();
+        let mut profiles_updated = 0;
+        for symbol in symbols {
+            let cached = poly::is_history_cached(&self.app_handle, &symbol, from, to, Some("1day"));
+            if !cached && !manual {
+                self.wait_for_turn().await;
+            }
+
+            let result = poly::fetch_history_budgeted(&self.app_handle, symbol.clone(), from, to, Some("1day".to_string()), true, false).await;
+            results.push(match result {
+                Ok(bars) => SymbolRefresh { symbol: symbol.clone(), ok: true, bars: bars.len(), last_bar: bars.last().map(Candle::date), error: None },
+                Err(e) => SymbolRefresh { symbol: symbol.clone(), ok: false, bars: 0, last_bar: None, error: Some(e) },
+            });
+
+            match self.recompute_profile(&symbol, to) {
+                Ok(Some(profile)) => {
+                    profiles.insert(symbol.clone(), profile);
+                    profiles_updated += 1;
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("Volatility profile for {} failed: {}", symbol, e),
+            }
+
+            if !cached {
+                sleep(Duration::from_secs(MIN_REQUEST_INTERVAL_SECS)).await;
+            }
+        }
+
+        if let Err(e) = FileCache::new(&self.app_handle).and_then(|mut cache| cache.set(PROFILES_KEY, profiles, None)) {
+            eprintln!("Failed to save volatility profiles: {}", e);
+        }
+        let report = DailyRefreshReport { from, to, manual, symbols: results, profiles_updated, finished_at: Utc::now().timestamp() };
+        let _ = self.app_handle.emit("daily_data_refreshed", &report);
+        report
+    }
+
+    /// Wait while download jobs are running or the quota is down to the reserve
+    async fn wait_for_turn(&self) {
+        loop {
+            let reserved = self.config().await.reserved_calls;
+            let quota_left = ApiBudget::open(&self.app_handle)
+                .and_then(|budget| budget.remaining(api_budget::POLYGON, api_budget::usage_day()))
+                .map_or(true, |remaining| remaining > reserved);
+            if quota_left && !self.downloads.is_busy().await {
+                return;
+            }
+            sleep(Duration::from_secs(YIELD_POLL_SECS)).await;
+        }
+    }
+
+    /// Profile from the bar cache's daily bars up to `session`
+    fn recompute_profile(&self, symbol: &str, session: NaiveDate) -> Result<Option<VolatilityProfile>, String> {
+        let cache = FileCache::new(&self.app_handle)?;
+        let backend = journal_store::configured_backend(&cache);
+        let store = journal_store::open_store(cache, backend);
+        let bounds = (
+            journal_store::session_start(session - ChronoDuration::days(PROFILE_LOOKBACK_DAYS)),
+            session.succ_opt().and_then(journal_store::session_start),
+        );
+        let (Some(start), Some(end)) = bounds else {
+            return Err(format!("Invalid session {}", session));
+        };
+        let bars = store.query_bars(symbol, "1d", start, end - 1)?;
+        Ok(VolatilityProfile::compute(symbol, &bars))
+    }
+
+    fn load_state(&self) -> RefreshState {
+        FileCache::new(&self.app_handle)
+            .and_then(|mut cache| cache.get(STATE_KEY))
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    fn save_state(&self, state: &RefreshState) {
+        if let Err(e) = FileCache::new(&self.app_handle).and_then(|mut cache| cache.set(STATE_KEY, state.clone(), None)) {
+            eprintln!("Failed to save daily refresh state: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn at(s: &str) -> i64 {
+        chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap().and_utc().timestamp()
+    }
+
+    #[test]
+    fn test_refresh_waits_for_the_close_and_bounds_catch_up() {
+        let calendar = MarketCalendar::new();
+        let config = DailyRefreshConfig::default();
+
+        // Tue Jan 9 2024: the close is 21:00 UTC, so the session is final at 21:30
+        assert_eq!(latest_final_session(&calendar, at("2024-01-09 21:20"), 30), Some(date("2024-01-08")));
+        assert_eq!(latest_final_session(&calendar, at("2024-01-09 21:30"), 30), Some(date("2024-01-09")));
+        // Sunday looks back to Friday; Jan 2 looks back past the New Year holiday
+        assert_eq!(latest_final_session(&calendar, at("2024-01-07 15:00"), 30), Some(date("2024-01-05")));
+        assert_eq!(latest_final_session(&calendar, at("2024-01-02 15:00"), 30), Some(date("2023-12-29")));
+
+        let now = at("2024-01-09 22:00");
+        assert_eq!(refresh_window(&calendar, now, &config, None), Some((date("2024-01-09"), date("2024-01-09"))));
+        assert_eq!(refresh_window(&calendar, now, &config, Some(date("2024-01-09"))), None);
+        // Missed Friday's and Monday's refreshes
+        assert_eq!(refresh_window(&calendar, now, &config, Some(date("2024-01-04"))), Some((date("2024-01-05"), date("2024-01-09"))));
+        // A month away catches up only the last ten sessions (Dec 25 and Jan 1 are holidays)
+        assert_eq!(refresh_window(&calendar, now, &config, Some(date("2023-12-01"))), Some((date("2023-12-26"), date("2024-01-09"))));
+        let short = DailyRefreshConfig { max_catch_up_days: 5, ..config };
+        assert_eq!(refresh_window(&calendar, now, &short, Some(date("2023-12-01"))), Some((date("2024-01-03"), date("2024-01-09"))));
+    }
+
+    #[test]
+    fn test_volatility_profile_from_daily_bars() {
+        let day = 86_400;
+        let bars: Vec<Candle> = (0..70)
+            .map(|i| {
+                // Alternating +1%/-1% closes with a 2-point daily range
+                let close = if i % 2 == 0 { 100.0 } else { 101.0 };
+                Candle::new(1_704_171_600 + i * day, close, close + 1.0, close - 1.0, close, 1_000)
+            })
+            .collect();
+        assert!(VolatilityProfile::compute("SPY", &bars[..20]).is_none());
+
+        let profile = VolatilityProfile::compute("SPY", &bars[..30]).unwrap();
+        assert_eq!(profile.bars, 30);
+        assert!((profile.atr - 2.0).abs() < 1e-9, "{}", profile.atr);
+        assert!(profile.realized_vol_20 > 0.15 && profile.realized_vol_20 < 0.17, "{}", profile.realized_vol_20);
+        assert!(profile.realized_vol_60.is_none());
+
+        let profile = VolatilityProfile::compute("SPY", &bars).unwrap();
+        assert_eq!(profile.as_of, bars[69].date());
+        assert!((profile.atr_pct - 2.0 / 101.0).abs() < 1e-9);
+        assert!(profile.realized_vol_60.is_some());
+    }
+}
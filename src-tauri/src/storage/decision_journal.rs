@@ -0,0 +1,71 @@
+// src-tauri/src/storage/decision_journal.rs
+// The strategy's decision journal: the last 5000 decisions that placed orders, oldest
+// first, in decision_journal.json in the cache dir. Outcomes are written onto their
+// decisions as the positions those decisions opened close.
+
+use super::cache::FileCache;
+use crate::engine::decision_outcomes::{DecisionOutcome, DecisionRecord};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+const JOURNAL_FILE: &str = "decision_journal.json";
+pub const MAX_DECISIONS: usize = 5000;
+
+// Records and outcomes are written with a read-modify-write of the file
+static JOURNAL_LOCK: Mutex<()> = Mutex::new(());
+
+pub struct DecisionJournal {
+    path: PathBuf,
+}
+
+impl DecisionJournal {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn open(app_handle: &AppHandle) -> Result<Self, String> {
+        Ok(Self::new(FileCache::new(app_handle)?.cache_dir().join(JOURNAL_FILE)))
+    }
+
+    /// Recorded decisions, oldest first
+    pub fn load(&self) -> Result<Vec<DecisionRecord>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let text = fs::read_to_string(&self.path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| format!("Decision journal is unreadable: {}", e))
+    }
+
+    /// Append `record`, dropping the oldest decisions beyond MAX_DECISIONS
+    pub fn record(&self, record: DecisionRecord) -> Result<(), String> {
+        let _guard = JOURNAL_LOCK.lock().map_err(|e| e.to_string())?;
+        let mut records = self.load()?;
+        records.push(record);
+        records.drain(..records.len().saturating_sub(MAX_DECISIONS));
+        self.write(&records)
+    }
+
+    /// Write each outcome onto its decision; outcomes already written are kept
+    pub fn write_outcomes(&self, outcomes: &[DecisionOutcome]) -> Result<(), String> {
+        if outcomes.is_empty() {
+            return Ok(());
+        }
+        let _guard = JOURNAL_LOCK.lock().map_err(|e| e.to_string())?;
+        let mut records = self.load()?;
+        for record in records.iter_mut().filter(|r| r.outcome.is_none()) {
+            record.outcome = outcomes.iter().find(|o| o.evaluation_id == record.evaluation_id).cloned();
+        }
+        self.write(&records)
+    }
+
+    fn write(&self, records: &[DecisionRecord]) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(records).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &self.path).map_err(|e| e.to_string())
+    }
+}
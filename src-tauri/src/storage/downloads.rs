@@ -0,0 +1,645 @@
+// src-tauri/src/storage/downloads.rs
+// Persisted, resumable bulk history downloads drained by a rate-limited background worker
+
+use super::api_budget::{self, ApiBudget};
+use super::cache::FileCache;
+use crate::engine::calendar::MarketCalendar;
+use crate::engine::symbols::normalize_symbol;
+use crate::market_data::dates::{self, parse as parse_date};
+use crate::market_data::types::Candle;
+use crate::provider::polygon as poly;
+use chrono::{Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+const JOBS_CACHE_KEY: &str = "download_jobs";
+pub const MIN_REQUEST_INTERVAL_SECS: u64 = 12; // Polygon free tier allows 5 requests/minute
+const IDLE_POLL_SECS: u64 = 2;
+const QUOTA_POLL_SECS: u64 = 60; // While the daily API quota is used up
+const MAX_ATTEMPTS: u32 = 5;
+const BASE_BACKOFF_SECS: i64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Paused,
+    Completed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum TaskStatus {
+    Pending,
+    Done,
+    Failed,  // Transient errors exhausted their retries
+    Skipped, // Permanent failure, e.g. an invalid symbol
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadTask {
+    pub symbol: String,
+    pub status: TaskStatus,
+    pub next_chunk_start: NaiveDate, // Resume point; earlier chunks are already fetched
+    pub attempts: u32,               // Consecutive failures on the current chunk
+    pub next_attempt_at: i64,
+    pub bars_fetched: u64,
+    pub gaps_found: u32,
+    pub api_calls: u32,
+    pub cached_chunks: u32,          // Chunks served from the bar cache without an API call
+    pub last_bar_date: Option<NaiveDate>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadJob {
+    pub id: String,
+    #[serde(rename = "start_on", alias = "start", deserialize_with = "dates::lenient")]
+    pub start: NaiveDate,
+    #[serde(rename = "end_on", alias = "end", deserialize_with = "dates::lenient")]
+    pub end: NaiveDate,
+    pub interval: String, // "1day" | "1hour"
+    pub status: JobStatus,
+    pub tasks: Vec<DownloadTask>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub completed_at: Option<i64>,
+    #[serde(default)]
+    pub estimated_api_calls: u32, // Chunks not cached when the job was queued
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolReport {
+    pub symbol: String,
+    pub status: TaskStatus,
+    pub bars_fetched: u64,
+    pub gaps_found: u32,
+    pub api_calls: u32,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadReport {
+    pub job_id: String,
+    pub status: JobStatus,
+    pub symbols: Vec<SymbolReport>,
+    pub total_bars: u64,
+    pub total_api_calls: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadProgress {
+    pub job_id: String,
+    pub symbol: String,
+    pub tasks_finished: usize,
+    pub tasks_total: usize,
+    pub bars_fetched: u64,
+    pub message: String,
+}
+
+/// Errors that retrying will not fix
+fn is_permanent_error(error: &str) -> bool {
+    let error = error.to_lowercase();
+    ["400", "404", "not found", "invalid", "not included in your plan"].iter().any(|marker| error.contains(marker))
+}
+
+/// Count runs of trading days missing between consecutive bar dates, including the
+/// run after the previous chunk's last bar. Weekends and holidays are never missing.
+fn count_gaps(previous: Option<NaiveDate>, dates: &[NaiveDate], calendar: &MarketCalendar) -> u32 {
+    let mut gaps = 0;
+    let mut last = previous;
+
+    for &date in dates {
+        if let Some(prev) = last {
+            let between = prev.succ_opt().zip(date.pred_opt()).filter(|(from, to)| from <= to);
+            if between.is_some_and(|(from, to)| calendar.trading_range(from, to).is_some()) {
+                gaps += 1;
+            }
+        }
+        if last.map_or(true, |prev| date > prev) {
+            last = Some(date);
+        }
+    }
+
+    gaps
+}
+
+impl DownloadTask {
+    fn new(symbol: String, start: NaiveDate) -> Self {
+        Self {
+            symbol,
+            status: TaskStatus::Pending,
+            next_chunk_start: start,
+            attempts: 0,
+            next_attempt_at: 0,
+            bars_fetched: 0,
+            gaps_found: 0,
+            api_calls: 0,
+            cached_chunks: 0,
+            last_bar_date: None,
+            error: None,
+        }
+    }
+
+    fn record_chunk(&mut self, chunk_end: NaiveDate, job_end: NaiveDate, dates: &[NaiveDate]) {
+        self.bars_fetched += dates.len() as u64;
+        self.gaps_found += count_gaps(self.last_bar_date, dates, &MarketCalendar::new());
+        self.last_bar_date = dates.iter().copied().max().max(self.last_bar_date);
+        self.next_chunk_start = chunk_end + chrono::Duration::days(1);
+        self.attempts = 0;
+        self.error = None;
+
+        if self.next_chunk_start > job_end {
+            if self.bars_fetched == 0 {
+                self.status = TaskStatus::Skipped;
+                self.error = Some("No bars returned for the range (invalid symbol?)".to_string());
+            } else {
+                self.status = TaskStatus::Done;
+            }
+        }
+    }
+
+    fn record_failure(&mut self, error: &str, now: i64) {
+        self.error = Some(error.to_string());
+
+        if is_permanent_error(error) {
+            self.status = TaskStatus::Skipped;
+            return;
+        }
+
+        self.attempts += 1;
+        if self.attempts >= MAX_ATTEMPTS {
+            self.status = TaskStatus::Failed;
+        } else {
+            self.next_attempt_at = now + BASE_BACKOFF_SECS * 2_i64.pow(self.attempts - 1);
+        }
+    }
+}
+
+impl DownloadJob {
+    pub fn new(symbols: Vec<String>, start: &str, end: &str, interval: &str) -> Result<Self, String> {
+        let start_date = parse_date(start)?;
+        let end_date = parse_date(end)?;
+        if start_date > end_date {
+            return Err("Start date must be on or before end date".to_string());
+        }
+        if interval != "1day" && interval != "1hour" {
+            return Err(format!("Unsupported interval {} (expected 1day or 1hour)", interval));
+        }
+
+        let mut symbols: Vec<String> = symbols
+            .into_iter()
+            .filter(|s| !s.trim().is_empty())
+            .map(|s| normalize_symbol(&s))
+            .collect::<Result<_, _>>()?;
+        symbols.sort();
+        symbols.dedup();
+        if symbols.is_empty() {
+            return Err("At least one symbol is required".to_string());
+        }
+
+        let now = Utc::now().timestamp();
+        Ok(Self {
+            id: Uuid::new_v4().to_string(),
+            start: start_date,
+            end: end_date,
+            interval: interval.to_string(),
+            status: JobStatus::Queued,
+            tasks: symbols.into_iter().map(|s| DownloadTask::new(s, start_date)).collect(),
+            created_at: now,
+            updated_at: now,
+            completed_at: None,
+            estimated_api_calls: 0,
+        })
+    }
+
+    /// Next fetch window for a task: up to one year, clipped to the job's end
+    fn chunk_for(&self, task: &DownloadTask) -> (NaiveDate, NaiveDate) {
+        self.chunk_from(task.next_chunk_start)
+    }
+
+    fn chunk_from(&self, start: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let year_end = start
+            .checked_add_months(Months::new(12))
+            .map(|d| d - chrono::Duration::days(1))
+            .unwrap_or(start);
+        (start, year_end.min(self.end))
+    }
+
+    /// Every fetch window of the job, in order
+    fn chunks(&self) -> Vec<(NaiveDate, NaiveDate)> {
+        let mut chunks = Vec::new();
+        let mut start = self.start;
+        let end = self.end;
+        while start <= end {
+            let chunk = self.chunk_from(start);
+            chunks.push(chunk);
+            start = chunk.1 + chrono::Duration::days(1);
+        }
+        chunks
+    }
+
+    fn next_runnable(&self, now: i64) -> Option<usize> {
+        self.tasks
+            .iter()
+            .position(|t| t.status == TaskStatus::Pending && t.next_attempt_at <= now)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.tasks.iter().all(|t| t.status != TaskStatus::Pending)
+    }
+
+    fn tasks_finished(&self) -> usize {
+        self.tasks.iter().filter(|t| t.status != TaskStatus::Pending).count()
+    }
+
+    pub fn report(&self) -> DownloadReport {
+        let symbols: Vec<SymbolReport> = self.tasks
+            .iter()
+            .map(|t| SymbolReport {
+                symbol: t.symbol.clone(),
+                status: t.status.clone(),
+                bars_fetched: t.bars_fetched,
+                gaps_found: t.gaps_found,
+                api_calls: t.api_calls,
+                error: t.error.clone(),
+            })
+            .collect();
+
+        DownloadReport {
+            job_id: self.id.clone(),
+            status: self.status.clone(),
+            total_bars: symbols.iter().map(|s| s.bars_fetched).sum(),
+            total_api_calls: symbols.iter().map(|s| s.api_calls).sum(),
+            symbols,
+        }
+    }
+
+    /// Whether this job proves `symbol` is cached for the whole range
+    pub fn covers(&self, symbol: &str, start: NaiveDate, end: NaiveDate, interval: &str) -> Result<(), String> {
+        if self.status != JobStatus::Completed {
+            return Err(format!("Download job {} is {:?}, not completed", self.id, self.status));
+        }
+        if self.interval != interval {
+            return Err(format!("Download job {} fetched {} bars, not {}", self.id, self.interval, interval));
+        }
+
+        let task = self.tasks
+            .iter()
+            .find(|t| t.symbol.eq_ignore_ascii_case(symbol))
+            .ok_or_else(|| format!("Download job {} does not include {}", self.id, symbol))?;
+        if task.status != TaskStatus::Done {
+            return Err(format!("{} was not downloaded: {}", symbol, task.error.clone().unwrap_or_default()));
+        }
+
+        if start < self.start || end > self.end {
+            return Err(format!("Download job {} covers {} - {}, not {} - {}", self.id, self.start, self.end, start, end));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct DownloadManager {
+    jobs: Arc<Mutex<HashMap<String, DownloadJob>>>,
+    app_handle: AppHandle,
+}
+
+impl DownloadManager {
+    /// Load persisted jobs; anything interrupted mid-run is queued again
+    pub fn new(app_handle: AppHandle) -> Self {
+        let mut jobs: HashMap<String, DownloadJob> = FileCache::new(&app_handle)
+            .and_then(|mut cache| cache.get(JOBS_CACHE_KEY))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+
+        for job in jobs.values_mut() {
+            if job.status == JobStatus::Running {
+                job.status = JobStatus::Queued;
+            }
+        }
+
+        Self {
+            jobs: Arc::new(Mutex::new(jobs)),
+            app_handle,
+        }
+    }
+
+    pub fn start_worker(&self) -> JoinHandle<()> {
+        let manager = self.clone();
+        tauri::async_runtime::spawn(async move {
+            manager.run_worker().await;
+        })
+    }
+
+    /// Queue a download. Its uncached chunks are counted against the daily API quota as
+    /// the worker reaches them; a job needing more calls than are left runs over
+    /// several days.
+    pub async fn queue(&self, symbols: Vec<String>, start: &str, end: &str, interval: &str) -> Result<DownloadJob, String> {
+        let mut job = DownloadJob::new(symbols, start, end, interval)?;
+        let chunks = job.chunks();
+        job.estimated_api_calls = job
+            .tasks
+            .iter()
+            .flat_map(|task| chunks.iter().map(move |chunk| (&task.symbol, chunk)))
+            .filter(|(symbol, (from, to))| {
+                !poly::is_history_cached(&self.app_handle, symbol, *from, *to, Some(interval))
+            })
+            .count() as u32;
+        self.jobs.lock().await.insert(job.id.clone(), job.clone());
+        self.persist().await;
+        Ok(job)
+    }
+
+    /// Whether any job is queued or running
+    pub async fn is_busy(&self) -> bool {
+        self.jobs.lock().await.values().any(|j| j.status == JobStatus::Queued || j.status == JobStatus::Running)
+    }
+
+    pub async fn list(&self) -> Vec<DownloadJob> {
+        let mut jobs: Vec<DownloadJob> = self.jobs.lock().await.values().cloned().collect();
+        jobs.sort_by_key(|j| j.created_at);
+        jobs
+    }
+
+    pub async fn pause(&self, id: &str) -> Result<DownloadJob, String> {
+        self.transition(id, |job| match job.status {
+            JobStatus::Queued | JobStatus::Running => Ok(JobStatus::Paused),
+            _ => Err(format!("Cannot pause a {:?} job", job.status)),
+        }).await
+    }
+
+    pub async fn resume(&self, id: &str) -> Result<DownloadJob, String> {
+        self.transition(id, |job| match job.status {
+            JobStatus::Paused => Ok(JobStatus::Queued),
+            _ => Err(format!("Cannot resume a {:?} job", job.status)),
+        }).await
+    }
+
+    pub async fn cancel(&self, id: &str) -> Result<DownloadJob, String> {
+        self.transition(id, |job| match job.status {
+            JobStatus::Completed | JobStatus::Cancelled => Err(format!("Cannot cancel a {:?} job", job.status)),
+            _ => Ok(JobStatus::Cancelled),
+        }).await
+    }
+
+    /// Daily bars for a backtest, read only from chunks a completed job cached
+    pub async fn load_warm_bars(&self, job_id: &str, symbol: &str, from: NaiveDate, to: NaiveDate) -> Result<Vec<Candle>, String> {
+        let job = {
+            let jobs = self.jobs.lock().await;
+            let job = jobs.get(job_id).ok_or_else(|| format!("Download job {} not found", job_id))?;
+            job.covers(symbol, from, to, "1day")?;
+            job.clone()
+        };
+
+        let mut bars = Vec::new();
+        for (chunk_start, chunk_end) in job.chunks() {
+            if chunk_end < from || chunk_start > to {
+                continue;
+            }
+            if !poly::is_history_cached(&self.app_handle, symbol, chunk_start, chunk_end, Some("1day")) {
+                return Err(format!("{} bars for {} - {} are no longer cached; re-run the download", symbol, chunk_start, chunk_end));
+            }
+            let chunk = poly::fetch_history(&self.app_handle, symbol.to_uppercase(), chunk_start, chunk_end, Some("1day".to_string())).await?;
+            bars.extend(chunk.into_iter().filter(|b| b.date() >= from && b.date() <= to));
+        }
+
+        Ok(bars)
+    }
+
+    /// Whether the daily Polygon quota has a call left; an unreadable usage file does
+    /// not stop downloads, `fetch_history` reports it instead
+    fn quota_left(&self) -> bool {
+        ApiBudget::open(&self.app_handle)
+            .and_then(|budget| budget.remaining(api_budget::POLYGON, api_budget::usage_day()))
+            .map_or(true, |remaining| remaining > 0)
+    }
+
+    async fn transition<F>(&self, id: &str, next: F) -> Result<DownloadJob, String>
+    where
+        F: FnOnce(&DownloadJob) -> Result<JobStatus, String>,
+    {
+        let job = {
+            let mut jobs = self.jobs.lock().await;
+            let job = jobs.get_mut(id).ok_or_else(|| format!("Download job {} not found", id))?;
+            job.status = next(job)?;
+            job.updated_at = Utc::now().timestamp();
+            job.clone()
+        };
+        self.persist().await;
+        Ok(job)
+    }
+
+    async fn persist(&self) {
+        let jobs = self.jobs.lock().await.clone();
+        let result = FileCache::new(&self.app_handle).and_then(|mut cache| cache.set(JOBS_CACHE_KEY, jobs, None));
+        if let Err(e) = result {
+            eprintln!("Failed to persist download jobs: {}", e);
+        }
+    }
+
+    async fn run_worker(&self) {
+        let mut waiting_for_quota = false;
+        loop {
+            let now = Utc::now().timestamp();
+
+            // Pick the oldest active job with a task ready to run
+            let work = {
+                let mut jobs = self.jobs.lock().await;
+                let mut active: Vec<&mut DownloadJob> = jobs
+                    .values_mut()
+                    .filter(|j| j.status == JobStatus::Queued || j.status == JobStatus::Running)
+                    .collect();
+                active.sort_by_key(|j| j.created_at);
+
+                active.into_iter().find_map(|job| {
+                    let index = job.next_runnable(now)?;
+                    job.status = JobStatus::Running;
+                    let (chunk_start, chunk_end) = job.chunk_for(&job.tasks[index]);
+                    Some((job.id.clone(), index, job.tasks[index].symbol.clone(), chunk_start, chunk_end, job.interval.clone()))
+                })
+            };
+
+            let Some((job_id, index, symbol, chunk_start, chunk_end, interval)) = work else {
+                sleep(Duration::from_secs(IDLE_POLL_SECS)).await;
+                continue;
+            };
+
+            let (start, end) = (chunk_start, chunk_end);
+            let cached = poly::is_history_cached(&self.app_handle, &symbol, start, end, Some(&interval));
+            if !cached && !self.quota_left() {
+                if !waiting_for_quota {
+                    let _ = self.app_handle.emit("download_quota_exhausted", &job_id);
+                    waiting_for_quota = true;
+                }
+                sleep(Duration::from_secs(QUOTA_POLL_SECS)).await;
+                continue;
+            }
+            waiting_for_quota = false;
+            // Chunks are a year at most, and the job was asked for as a whole
+            let result = poly::fetch_history_budgeted(&self.app_handle, symbol.clone(), start, end, Some(interval), true, false).await;
+
+            let finished_report = {
+                let mut jobs = self.jobs.lock().await;
+                let Some(job) = jobs.get_mut(&job_id) else {
+                    continue;
+                };
+                let job_end = job.end;
+                let task = &mut job.tasks[index];
+
+                if cached {
+                    task.cached_chunks += 1;
+                } else {
+                    task.api_calls += 1;
+                }
+
+                let message = match result {
+                    Ok(bars) => {
+                        let mut dates: Vec<NaiveDate> = bars.iter().map(Candle::date).collect();
+                        dates.dedup();
+                        task.record_chunk(chunk_end, job_end, &dates);
+                        format!("{}: {} bars for {} - {}", symbol, dates.len(), start, end)
+                    }
+                    Err(e) => {
+                        task.record_failure(&e, Utc::now().timestamp());
+                        format!("{}: {} - {} failed ({:?}): {}", symbol, start, end, task.status, e)
+                    }
+                };
+
+                job.updated_at = Utc::now().timestamp();
+                let _ = self.app_handle.emit("download_progress", &DownloadProgress {
+                    job_id: job.id.clone(),
+                    symbol: symbol.clone(),
+                    tasks_finished: job.tasks_finished(),
+                    tasks_total: job.tasks.len(),
+                    bars_fetched: job.tasks[index].bars_fetched,
+                    message,
+                });
+
+                if job.status == JobStatus::Running && job.is_finished() {
+                    job.status = JobStatus::Completed;
+                    job.completed_at = Some(job.updated_at);
+                    Some(job.report())
+                } else {
+                    None
+                }
+            };
+
+            if let Some(report) = finished_report {
+                let _ = self.app_handle.emit("download_complete", &report);
+            }
+            self.persist().await;
+
+            if !cached {
+                sleep(Duration::from_secs(MIN_REQUEST_INTERVAL_SECS)).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        parse_date(s).unwrap()
+    }
+
+    #[test]
+    fn test_job_validation_and_chunking() {
+        assert!(DownloadJob::new(vec!["AAPL".into()], "01/01/2024", "01/01/2023", "1day").is_err());
+        assert!(DownloadJob::new(vec![" ".into()], "01/01/2023", "01/01/2024", "1day").is_err());
+        assert!(DownloadJob::new(vec!["AAPL".into()], "01/01/2023", "01/01/2024", "5min").is_err());
+
+        let job = DownloadJob::new(vec!["msft".into(), "AAPL".into(), "MSFT".into()], "03/15/2021", "06/30/2023", "1day").unwrap();
+        let symbols: Vec<&str> = job.tasks.iter().map(|t| t.symbol.as_str()).collect();
+        assert_eq!(symbols, vec!["AAPL", "MSFT"]);
+
+        let (start, end) = job.chunk_for(&job.tasks[0]);
+        assert_eq!((start, end), (date("03/15/2021"), date("03/14/2022")));
+
+        let mut task = job.tasks[0].clone();
+        task.next_chunk_start = date("03/15/2023");
+        assert_eq!(job.chunk_for(&task).1, date("06/30/2023"));
+
+        let chunks = job.chunks();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[1], (date("03/15/2022"), date("03/14/2023")));
+        assert_eq!(chunks[2], (date("03/15/2023"), date("06/30/2023")));
+    }
+
+    #[test]
+    fn test_task_resumes_and_counts_gaps() {
+        let job_end = date("01/31/2024");
+        let mut task = DownloadTask::new("AAPL".into(), date("01/02/2024"));
+
+        task.record_chunk(date("01/15/2024"), job_end, &[date("01/02/2024"), date("01/03/2024"), date("01/12/2024")]);
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert_eq!(task.next_chunk_start, date("01/16/2024"));
+        assert_eq!(task.gaps_found, 1);
+
+        // Gap across the chunk boundary is detected too
+        task.record_chunk(job_end, job_end, &[date("01/22/2024"), date("01/23/2024")]);
+        assert_eq!(task.status, TaskStatus::Done);
+        assert_eq!(task.bars_fetched, 5);
+        assert_eq!(task.gaps_found, 2);
+
+        // A holiday weekend is not a gap; a missing Friday is, however short
+        let calendar = MarketCalendar::new();
+        assert_eq!(count_gaps(Some(date("01/12/2024")), &[date("01/16/2024")], &calendar), 0);
+        assert_eq!(count_gaps(Some(date("01/25/2024")), &[date("01/29/2024")], &calendar), 1);
+    }
+
+    #[test]
+    fn test_failures_retry_with_backoff_or_skip() {
+        let mut task = DownloadTask::new("AAPL".into(), date("01/02/2024"));
+
+        task.record_failure("Polygon error: 429 Too Many Requests", 1000);
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert_eq!(task.next_attempt_at, 1000 + BASE_BACKOFF_SECS);
+        task.record_failure("timed out", 2000);
+        assert_eq!(task.next_attempt_at, 2000 + BASE_BACKOFF_SECS * 2);
+
+        for _ in 2..MAX_ATTEMPTS {
+            task.record_failure("timed out", 3000);
+        }
+        assert_eq!(task.status, TaskStatus::Failed);
+
+        let mut invalid = DownloadTask::new("ZZZZZ".into(), date("01/02/2024"));
+        invalid.record_failure("Polygon error: 404 Not Found", 1000);
+        assert_eq!(invalid.status, TaskStatus::Skipped);
+
+        // A range that returns nothing at all is treated as an invalid symbol
+        let mut empty = DownloadTask::new("ZZZZZ".into(), date("01/02/2024"));
+        empty.record_chunk(date("01/31/2024"), date("01/31/2024"), &[]);
+        assert_eq!(empty.status, TaskStatus::Skipped);
+    }
+
+    #[test]
+    fn test_completed_job_proves_warm_data() {
+        let mut job = DownloadJob::new(vec!["AAPL".into(), "ZZZZZ".into()], "01/01/2020", "12/31/2023", "1day").unwrap();
+        assert!(job.covers("AAPL", date("01/01/2021"), date("12/31/2022"), "1day").is_err());
+
+        job.tasks[0].status = TaskStatus::Done;
+        job.tasks[1].status = TaskStatus::Skipped;
+        job.status = JobStatus::Completed;
+
+        assert!(job.covers("aapl", date("01/01/2021"), date("12/31/2022"), "1day").is_ok());
+        assert!(job.covers("AAPL", date("01/01/2019"), date("12/31/2022"), "1day").is_err());
+        assert!(job.covers("AAPL", date("01/01/2021"), date("12/31/2022"), "1hour").is_err());
+        assert!(job.covers("ZZZZZ", date("01/01/2021"), date("12/31/2022"), "1day").is_err());
+        assert!(job.covers("MSFT", date("01/01/2021"), date("12/31/2022"), "1day").is_err());
+
+        let report = job.report();
+        assert_eq!(report.symbols.len(), 2);
+        assert_eq!(report.symbols[1].status, TaskStatus::Skipped);
+    }
+}
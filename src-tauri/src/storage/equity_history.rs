@@ -0,0 +1,109 @@
+// src-tauri/src/storage/equity_history.rs
+// Sampled equity tiers in the cache dir under equity_history/: full.jsonl holds samples
+// as they are taken, 15m.json and daily.json the compacted buckets. daily.json is
+// written when the store is first opened, seeded from the recorded session summaries.
+
+use crate::engine::equity_history::{EquityBucket, EquityRetention, EquitySample, EquityTiers};
+use crate::engine::types::DailySummary;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+pub const EQUITY_HISTORY_DIR: &str = "equity_history";
+const FULL_FILE: &str = "full.jsonl";
+const FIFTEEN_MINUTE_FILE: &str = "15m.json";
+const DAILY_FILE: &str = "daily.json";
+
+// Compaction rewrites every tier; appends must not land in between
+static EQUITY_HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+pub struct EquityHistoryStore {
+    root: PathBuf,
+}
+
+impl EquityHistoryStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn read_buckets(&self, file: &str) -> Result<Vec<EquityBucket>, String> {
+        let path = self.root.join(file);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| format!("Equity history {} is unreadable: {}", file, e))
+    }
+
+    fn write_file(&self, file: &str, contents: String) -> Result<(), String> {
+        fs::create_dir_all(&self.root).map_err(|e| e.to_string())?;
+        let path = self.root.join(file);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, contents).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &path).map_err(|e| e.to_string())
+    }
+
+    fn read_tiers(&self) -> Result<EquityTiers, String> {
+        let path = self.root.join(FULL_FILE);
+        let full = if path.exists() {
+            // A line cut short by a crash is dropped
+            fs::read_to_string(&path).map_err(|e| e.to_string())?
+                .lines()
+                .filter_map(|line| serde_json::from_str::<EquitySample>(line).ok())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Ok(EquityTiers { full, fifteen_minute: self.read_buckets(FIFTEEN_MINUTE_FILE)?, daily: self.read_buckets(DAILY_FILE)? })
+    }
+
+    fn write_tiers(&self, tiers: &EquityTiers) -> Result<(), String> {
+        let mut full = String::new();
+        for sample in &tiers.full {
+            full.push_str(&serde_json::to_string(sample).map_err(|e| e.to_string())?);
+            full.push('\n');
+        }
+        self.write_file(FULL_FILE, full)?;
+        self.write_file(FIFTEEN_MINUTE_FILE, serde_json::to_string(&tiers.fifteen_minute).map_err(|e| e.to_string())?)?;
+        self.write_file(DAILY_FILE, serde_json::to_string(&tiers.daily).map_err(|e| e.to_string())?)
+    }
+
+    /// Seed the daily tier from `summaries` the first time the store is opened, so
+    /// sessions recorded before sampling began read back at daily resolution; returns
+    /// the sessions seeded
+    pub fn migrate(&self, summaries: &[DailySummary]) -> Result<usize, String> {
+        let _guard = EQUITY_HISTORY_LOCK.lock().map_err(|e| e.to_string())?;
+        if self.root.join(DAILY_FILE).exists() {
+            return Ok(0);
+        }
+        let mut daily: Vec<EquityBucket> = summaries.iter().map(EquityBucket::from_summary).collect();
+        daily.sort_by_key(|b| b.start);
+        daily.dedup_by_key(|b| b.start);
+        self.write_file(DAILY_FILE, serde_json::to_string(&daily).map_err(|e| e.to_string())?)?;
+        Ok(daily.len())
+    }
+
+    pub fn append(&self, sample: &EquitySample) -> Result<(), String> {
+        let _guard = EQUITY_HISTORY_LOCK.lock().map_err(|e| e.to_string())?;
+        fs::create_dir_all(&self.root).map_err(|e| e.to_string())?;
+        let line = serde_json::to_string(sample).map_err(|e| e.to_string())?;
+        let mut file = OpenOptions::new().create(true).append(true).open(self.root.join(FULL_FILE)).map_err(|e| e.to_string())?;
+        writeln!(file, "{}", line).map_err(|e| e.to_string())
+    }
+
+    /// Age samples and buckets down their tiers as of `now`
+    pub fn compact(&self, now: i64, retention: &EquityRetention) -> Result<(), String> {
+        let _guard = EQUITY_HISTORY_LOCK.lock().map_err(|e| e.to_string())?;
+        let mut tiers = self.read_tiers()?;
+        if tiers.compact(now, retention) {
+            self.write_tiers(&tiers)?;
+        }
+        Ok(())
+    }
+
+    pub fn load(&self) -> Result<EquityTiers, String> {
+        let _guard = EQUITY_HISTORY_LOCK.lock().map_err(|e| e.to_string())?;
+        self.read_tiers()
+    }
+}
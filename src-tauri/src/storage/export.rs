@@ -0,0 +1,207 @@
+// src-tauri/src/storage/export.rs
+// Journal exports written a chunk at a time. Rows are serialized into a chunk buffer
+// and written out every EXPORT_CHUNK_ROWS, so memory stays bounded by the chunk size
+// however long the journal is.
+
+use super::journal_store::TradeQuery;
+use crate::engine::types::Trade;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+pub const EXPORT_CHUNK_ROWS: usize = 1000;
+
+const CSV_HEADER: [&str; 12] = [
+    "id", "timestamp", "symbol", "side", "quantity", "price", "commission", "net_amount", "instrument_type", "order_id", "tag",
+    "evaluation_id",
+];
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Jsonl, // One trade per line, as in trade_journal.jsonl
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportSpec {
+    pub path: String,
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default, flatten)]
+    pub query: TradeQuery, // Symbol and session range; limit is not supported
+}
+
+impl ExportSpec {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.path.trim().is_empty() {
+            return Err("Export path cannot be empty".to_string());
+        }
+        if self.query.limit.is_some() {
+            return Err("Exports stream the journal in order and cannot take a limit".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Serializes trades into chunks and writes each full chunk to `out`
+pub struct ChunkedExporter<W: Write> {
+    out: W,
+    format: ExportFormat,
+    chunk_rows: usize,
+    chunk: Vec<u8>,
+    buffered: usize,
+    rows: u64,
+    bytes: u64,
+}
+
+impl<W: Write> ChunkedExporter<W> {
+    pub fn new(out: W, format: ExportFormat, chunk_rows: usize) -> Result<Self, String> {
+        let mut exporter = Self { out, format, chunk_rows: chunk_rows.max(1), chunk: Vec::new(), buffered: 0, rows: 0, bytes: 0 };
+        if format == ExportFormat::Csv {
+            exporter.chunk = csv_line(&CSV_HEADER)?;
+            exporter.flush_chunk()?;
+        }
+        Ok(exporter)
+    }
+
+    /// Rows serialized but not yet written out
+    #[cfg(test)]
+    pub fn buffered_rows(&self) -> usize {
+        self.buffered
+    }
+
+    /// Add `trade`; returns (rows, bytes) written so far when this filled a chunk
+    pub fn push(&mut self, trade: &Trade) -> Result<Option<(u64, u64)>, String> {
+        match self.format {
+            ExportFormat::Csv => {
+                let record = csv_record(trade);
+                self.chunk.extend(csv_line(&record)?);
+            }
+            ExportFormat::Jsonl => {
+                serde_json::to_writer(&mut self.chunk, trade).map_err(|e| e.to_string())?;
+                self.chunk.push(b'\n');
+            }
+        }
+        self.buffered += 1;
+        if self.buffered < self.chunk_rows {
+            return Ok(None);
+        }
+        self.flush_chunk()?;
+        Ok(Some((self.rows, self.bytes)))
+    }
+
+    /// Write the last partial chunk; returns the rows and bytes written
+    pub fn finish(mut self) -> Result<(u64, u64), String> {
+        self.flush_chunk()?;
+        self.out.flush().map_err(|e| e.to_string())?;
+        Ok((self.rows, self.bytes))
+    }
+
+    fn flush_chunk(&mut self) -> Result<(), String> {
+        self.out.write_all(&self.chunk).map_err(|e| format!("Failed to write export: {}", e))?;
+        self.bytes += self.chunk.len() as u64;
+        self.rows += self.buffered as u64;
+        self.chunk.clear();
+        self.buffered = 0;
+        Ok(())
+    }
+}
+
+fn csv_record(trade: &Trade) -> [String; 12] {
+    let timestamp = chrono::DateTime::from_timestamp(trade.timestamp, 0).map(|t| t.to_rfc3339()).unwrap_or_default();
+    [
+        trade.id.clone(),
+        timestamp,
+        trade.symbol.clone(),
+        format!("{:?}", trade.side),
+        trade.quantity.to_string(),
+        trade.price.to_string(),
+        trade.commission.to_string(),
+        trade.net_amount.to_string(),
+        format!("{:?}", trade.instrument_type),
+        trade.order_id.clone(),
+        trade.tag.clone().unwrap_or_default(),
+        trade.evaluation_id.clone().unwrap_or_default(),
+    ]
+}
+
+fn csv_line<S: AsRef<[u8]>>(fields: &[S]) -> Result<Vec<u8>, String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(fields).map_err(|e| e.to_string())?;
+    writer.into_inner().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::types::{InstrumentType, OrderSide};
+
+    /// Records the largest single write, so the test can see chunks stay bounded
+    struct CountingWriter {
+        bytes: u64,
+        lines: u64,
+        largest_write: usize,
+    }
+
+    impl Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.bytes += buf.len() as u64;
+            self.lines += buf.iter().filter(|b| **b == b'\n').count() as u64;
+            self.largest_write = self.largest_write.max(buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn trade(i: u64) -> Trade {
+        Trade {
+            id: format!("trade-{}", i),
+            symbol: "ABC".to_string(),
+            side: if i.is_multiple_of(2) { OrderSide::Buy } else { OrderSide::Sell },
+            quantity: 100,
+            price: 50.0 + (i % 100) as f64 / 100.0,
+            timestamp: 1_700_000_000 + i as i64,
+            order_id: format!("order-{}", i),
+            commission: 1.0,
+            net_amount: 5_000.0,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            leg_number: None,
+            assignment_id: None,
+            tag: Some("note, with a comma".to_string()),
+            evaluation_id: None,
+        }
+    }
+
+    #[test]
+    fn test_large_export_streams_in_bounded_chunks() {
+        const ROWS: u64 = 100_000;
+        let mut out = CountingWriter { bytes: 0, lines: 0, largest_write: 0 };
+        let mut exporter = ChunkedExporter::new(&mut out, ExportFormat::Csv, EXPORT_CHUNK_ROWS).unwrap();
+
+        let mut progress_reports = 0;
+        let mut max_buffered = 0;
+        for i in 0..ROWS {
+            if let Some((rows, _)) = exporter.push(&trade(i)).unwrap() {
+                progress_reports += 1;
+                assert_eq!(rows, i + 1);
+            }
+            max_buffered = max_buffered.max(exporter.buffered_rows());
+        }
+        let (rows, bytes) = exporter.finish().unwrap();
+
+        assert_eq!(rows, ROWS);
+        assert_eq!(progress_reports, ROWS / EXPORT_CHUNK_ROWS as u64);
+        assert!(max_buffered < EXPORT_CHUNK_ROWS);
+        assert_eq!((out.lines, out.bytes), (ROWS + 1, bytes)); // Header plus one line per trade
+        let longest_row = csv_line(&csv_record(&trade(ROWS - 1))).unwrap().len();
+        assert!(out.largest_write <= EXPORT_CHUNK_ROWS * (longest_row + 8), "{}", out.largest_write);
+
+        let spec = ExportSpec { path: "out.csv".to_string(), format: ExportFormat::Csv, query: TradeQuery { limit: Some(5), ..TradeQuery::default() } };
+        assert!(spec.validate().is_err());
+    }
+}
@@ -0,0 +1,291 @@
+// src-tauri/src/storage/journal_store.rs
+// Trade journal, equity history and bar storage behind one interface. The file backend
+// (trade_journal.jsonl, broker_state.json and per-symbol bar blobs) answers queries by
+// reading whole files; the optional SQLite backend keeps the same data indexed in
+// journal.sqlite3 beside them and answers symbol and date filters in SQL.
+
+use super::cache::{FileCache, JournalStats};
+use crate::engine::types::{DailySummary, Trade};
+use crate::market_data::types::Candle;
+use chrono::{NaiveDate, TimeZone};
+use chrono_tz::America::New_York;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[cfg(feature = "sqlite")]
+pub const SQLITE_FILE: &str = "journal.sqlite3";
+// Beside the journal rather than in the UI state store, which evicts under pressure
+const BACKEND_FILE: &str = "journal_backend.json";
+#[cfg(not(feature = "sqlite"))]
+const SQLITE_UNAVAILABLE: &str = "This build does not include the SQLite backend";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum StorageBackendKind {
+    #[default]
+    File,
+    Sqlite,
+}
+
+/// Trades in sessions `from` through `to` (New York dates), oldest first
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TradeQuery {
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default)]
+    pub from: Option<NaiveDate>,
+    #[serde(default)]
+    pub to: Option<NaiveDate>,
+    #[serde(default)]
+    pub limit: Option<usize>, // Keep the most recent matches
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MigratedCount {
+    pub source: usize,   // Distinct records in the file backend
+    pub imported: usize, // Rows in the database afterwards
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MigrationReport {
+    pub database: String,
+    pub trades: MigratedCount,
+    pub daily_summaries: MigratedCount,
+    pub bars: MigratedCount,
+}
+
+/// Where the broker keeps its journal and history. Implementations must agree on
+/// results; only how much they read to answer differs.
+pub trait JournalStore: Send + Sync + std::fmt::Debug {
+    fn backend(&self) -> StorageBackendKind;
+
+    fn append_trade(&self, trade: &Trade) -> Result<(), String>;
+
+    fn load_trades(&self) -> Result<Vec<Trade>, String>;
+
+    fn query_trades(&self, query: &TradeQuery) -> Result<Vec<Trade>, String> {
+        Ok(query.apply(self.load_trades()?))
+    }
+
+    /// Visit the trades `query` matches in journal order without loading them all;
+    /// `visit` returns false to stop. The query's limit is ignored.
+    fn stream_trades(&self, query: &TradeQuery, visit: &mut dyn FnMut(Trade) -> Result<bool, String>) -> Result<(), String> {
+        for trade in self.load_trades()?.into_iter().filter(|t| query.matches(t)) {
+            if !visit(trade)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn append_daily_summary(&self, summary: &DailySummary) -> Result<(), String>;
+
+    /// Recorded sessions from `from` through `to`, oldest first
+    fn daily_summaries(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Vec<DailySummary>, String>;
+
+    /// Merge `bars` into the stored series, replacing bars with the same timestamp
+    fn save_bars(&self, symbol: &str, interval: &str, bars: &[Candle]) -> Result<(), String>;
+
+    /// Stored bars with `from <= timestamp <= to`, oldest first
+    fn query_bars(&self, symbol: &str, interval: &str, from: i64, to: i64) -> Result<Vec<Candle>, String>;
+
+    fn journal_stats(&self) -> Result<JournalStats, String>;
+
+    /// Copy the journal beside itself with `suffix` in the name; returns the copy's path
+    fn backup(&self, suffix: &str) -> Result<PathBuf, String>;
+}
+
+impl TradeQuery {
+    /// First second of session `from` and first second after session `to`, as bounds
+    /// for `from_ts <= timestamp < to_ts`
+    pub fn timestamp_bounds(&self) -> (Option<i64>, Option<i64>) {
+        (self.from.and_then(session_start), self.to.and_then(|d| d.succ_opt()).and_then(session_start))
+    }
+
+    pub fn matches(&self, trade: &Trade) -> bool {
+        let (from_ts, to_ts) = self.timestamp_bounds();
+        self.symbol.as_ref().is_none_or(|s| &trade.symbol == s)
+            && from_ts.is_none_or(|from| trade.timestamp >= from)
+            && to_ts.is_none_or(|to| trade.timestamp < to)
+    }
+
+    /// Filter, order and limit `trades` as the query asks
+    pub fn apply(&self, trades: Vec<Trade>) -> Vec<Trade> {
+        let mut trades: Vec<Trade> = trades.into_iter().filter(|t| self.matches(t)).collect();
+        trades.sort_by(|a, b| (a.timestamp, &a.id).cmp(&(b.timestamp, &b.id)));
+        if let Some(limit) = self.limit {
+            trades.drain(..trades.len().saturating_sub(limit));
+        }
+        trades
+    }
+}
+
+/// Midnight New York at the start of `date`, in epoch seconds
+pub fn session_start(date: NaiveDate) -> Option<i64> {
+    New_York.from_local_datetime(&date.and_hms_opt(0, 0, 0)?).earliest().map(|t| t.timestamp())
+}
+
+pub fn sqlite_available() -> bool {
+    cfg!(feature = "sqlite")
+}
+
+/// Backend chosen in preferences; the file backend until one is chosen
+pub fn configured_backend(file: &FileCache) -> StorageBackendKind {
+    std::fs::read_to_string(file.cache_dir().join(BACKEND_FILE))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn set_configured_backend(file: &FileCache, backend: StorageBackendKind) -> Result<(), String> {
+    let text = serde_json::to_string(&backend).map_err(|e| e.to_string())?;
+    std::fs::write(file.cache_dir().join(BACKEND_FILE), text).map_err(|e| format!("Failed to save storage backend: {}", e))
+}
+
+/// Store for `backend`, falling back to the file journal when SQLite is not built in,
+/// cannot be opened, or has not been migrated into yet
+pub fn open_store(file: FileCache, backend: StorageBackendKind) -> Arc<dyn JournalStore> {
+    try_open_store(file.clone(), backend).unwrap_or_else(|e| {
+        eprintln!("SQLite journal unavailable, using the file journal: {}", e);
+        Arc::new(file)
+    })
+}
+
+/// Store for `backend`, or why it cannot be used
+pub fn try_open_store(file: FileCache, backend: StorageBackendKind) -> Result<Arc<dyn JournalStore>, String> {
+    match backend {
+        StorageBackendKind::File => Ok(Arc::new(file)),
+        StorageBackendKind::Sqlite => open_sqlite(&file),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn open_sqlite(file: &FileCache) -> Result<Arc<dyn JournalStore>, String> {
+    let store = super::sqlite::SqliteStore::open(&file.cache_dir().join(SQLITE_FILE))?;
+    if store.migrated_at()?.is_none() {
+        return Err("the file journal has not been migrated to it".to_string());
+    }
+    Ok(Arc::new(store))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn open_sqlite(_file: &FileCache) -> Result<Arc<dyn JournalStore>, String> {
+    Err(SQLITE_UNAVAILABLE.to_string())
+}
+
+/// Import the file backend's trades, `daily_summaries` (the broker's, which live in
+/// broker_state.json) and `bars` (symbol, interval, series) into journal.sqlite3, check
+/// every record arrived and mark the database migrated. Runs once.
+#[cfg(feature = "sqlite")]
+pub fn migrate_to_sqlite(
+    file: &FileCache,
+    daily_summaries: &[DailySummary],
+    bars: &[(String, String, Vec<Candle>)],
+) -> Result<MigrationReport, String> {
+    let path = file.cache_dir().join(SQLITE_FILE);
+    let store = super::sqlite::SqliteStore::open(&path)?;
+    if let Some(migrated_at) = store.migrated_at()? {
+        return Err(format!("The journal was already migrated to SQLite at {}", migrated_at));
+    }
+
+    let trades = file.load_trades()?;
+    store.insert_trades(&trades)?;
+    store.insert_daily_summaries(daily_summaries)?;
+    for (symbol, interval, series) in bars {
+        store.save_bars(symbol, interval, series)?;
+    }
+
+    let counts = store.row_counts()?;
+    let report = MigrationReport {
+        database: path.to_string_lossy().to_string(),
+        trades: MigratedCount { source: distinct(trades.iter().map(|t| t.id.clone())), imported: counts.0 },
+        daily_summaries: MigratedCount { source: distinct(daily_summaries.iter().map(|s| s.date)), imported: counts.1 },
+        bars: MigratedCount {
+            source: distinct(bars.iter().flat_map(|(s, i, series)| series.iter().map(move |c| (s, i, c.timestamp)))),
+            imported: counts.2,
+        },
+    };
+    for (what, count) in [("trades", &report.trades), ("daily summaries", &report.daily_summaries), ("bars", &report.bars)] {
+        if count.source != count.imported {
+            return Err(format!(
+                "Migration incomplete: {} of {} {} imported; the file backend is still in use",
+                count.imported, count.source, what
+            ));
+        }
+    }
+    store.mark_migrated(chrono::Utc::now().timestamp())?;
+    Ok(report)
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn migrate_to_sqlite(
+    _file: &FileCache,
+    _daily_summaries: &[DailySummary],
+    _bars: &[(String, String, Vec<Candle>)],
+) -> Result<MigrationReport, String> {
+    Err(SQLITE_UNAVAILABLE.to_string())
+}
+
+#[cfg(feature = "sqlite")]
+fn distinct<T: std::hash::Hash + Eq>(items: impl Iterator<Item = T>) -> usize {
+    items.collect::<HashSet<T>>().len()
+}
+
+/// Trades in `source` that `target` lacks, by id, oldest first: those recorded while
+/// the other backend was active
+pub fn missing_trades(source: &dyn JournalStore, target: &dyn JournalStore) -> Result<Vec<Trade>, String> {
+    let present: HashSet<String> = target.load_trades()?.into_iter().map(|t| t.id).collect();
+    Ok(source.load_trades()?.into_iter().filter(|t| !present.contains(&t.id)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::position_history::session_date;
+    use crate::engine::types::{InstrumentType, OrderSide};
+
+    fn trade(id: &str, symbol: &str, timestamp: i64) -> Trade {
+        Trade {
+            id: id.to_string(),
+            symbol: symbol.to_string(),
+            side: OrderSide::Buy,
+            quantity: 10,
+            price: 100.0,
+            timestamp,
+            order_id: format!("order-{}", id),
+            commission: 1.0,
+            net_amount: 1001.0,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            leg_number: None,
+            assignment_id: None,
+            tag: None,
+            evaluation_id: None,
+        }
+    }
+
+    #[test]
+    fn test_trade_query_uses_new_york_sessions() {
+        // 2024-03-04 23:30 New York is 04:30 UTC on the 5th
+        let late = 1709613000;
+        let trades = vec![
+            trade("a", "AAPL", late),
+            trade("b", "MSFT", late - 3600),
+            trade("c", "AAPL", late + 86400),
+            trade("d", "AAPL", late - 86400),
+        ];
+        let day = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let query = TradeQuery { symbol: Some("AAPL".into()), from: Some(day), to: Some(day), limit: None };
+        let ids: Vec<String> = query.apply(trades.clone()).into_iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec!["a"]);
+
+        let (from_ts, to_ts) = query.timestamp_bounds();
+        assert_eq!((from_ts, to_ts), (Some(1709528400), Some(1709614800))); // EST, UTC-5
+        assert!(trades.iter().all(|t| query.matches(t) == (t.symbol == "AAPL" && session_date(t.timestamp) == day)));
+
+        let latest_two = TradeQuery { symbol: Some("AAPL".into()), limit: Some(2), ..Default::default() };
+        let ids: Vec<String> = latest_two.apply(trades).into_iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec!["a", "c"]);
+    }
+}
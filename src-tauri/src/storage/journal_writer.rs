@@ -0,0 +1,318 @@
+// src-tauri/src/storage/journal_writer.rs
+// The storage worker: trade journal appends are queued and written by a background
+// thread so a slow disk or database never holds the broker lock. While the worker is
+// stopped appends are written straight through, and stopping drains the queue, so a
+// restart cannot drop an entry. Reads flush first and always see every append.
+
+use super::cache::JournalStats;
+use super::journal_store::{JournalStore, StorageBackendKind, TradeQuery};
+use crate::engine::types::{DailySummary, Trade};
+use crate::market_data::types::Candle;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread::JoinHandle;
+
+#[derive(Debug, Default)]
+struct WriteQueue {
+    pending: VecDeque<Trade>,
+    running: bool,
+    written: u64,
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JournalWriterStatus {
+    pub running: bool,
+    pub pending: usize,
+    pub written: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct JournalWriter {
+    store: RwLock<Arc<dyn JournalStore>>,
+    queue: Mutex<WriteQueue>,
+    wake: Condvar,
+    drain: Mutex<()>, // Held while writing, so batches land in the order they were queued
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl JournalWriter {
+    /// Writer in front of `store`, stopped until `start`
+    pub fn new(store: Arc<dyn JournalStore>) -> Arc<Self> {
+        Arc::new(Self {
+            store: RwLock::new(store),
+            queue: Mutex::new(WriteQueue::default()),
+            wake: Condvar::new(),
+            drain: Mutex::new(()),
+            worker: Mutex::new(None),
+        })
+    }
+
+    /// Start the background worker; appends queue from here on
+    pub fn start(self: &Arc<Self>) -> Result<(), String> {
+        let mut worker = self.worker.lock().map_err(|e| e.to_string())?;
+        if worker.is_some() {
+            return Ok(());
+        }
+        self.queue.lock().map_err(|e| e.to_string())?.running = true;
+        let writer = self.clone();
+        *worker = Some(std::thread::spawn(move || writer.run()));
+        Ok(())
+    }
+
+    /// Stop the worker and write out everything it had not reached
+    pub fn stop(&self) -> Result<usize, String> {
+        let mut worker = self.worker.lock().map_err(|e| e.to_string())?;
+        self.queue.lock().map_err(|e| e.to_string())?.running = false;
+        self.wake.notify_all();
+        if let Some(handle) = worker.take() {
+            handle.join().map_err(|_| "Journal writer panicked".to_string())?;
+        }
+        drop(worker);
+        self.flush()
+    }
+
+    fn run(&self) {
+        loop {
+            {
+                let Ok(mut queue) = self.queue.lock() else { return };
+                while queue.running && queue.pending.is_empty() {
+                    queue = match self.wake.wait(queue) {
+                        Ok(queue) => queue,
+                        Err(_) => return,
+                    };
+                }
+                if !queue.running {
+                    return;
+                }
+            }
+            if let Err(e) = self.flush() {
+                eprintln!("Journal writer failed: {}", e);
+                std::thread::sleep(std::time::Duration::from_secs(1));
+            }
+        }
+    }
+
+    /// Write every queued trade now; returns how many were written. Trades that fail
+    /// stay queued, in order, for the next attempt.
+    pub fn flush(&self) -> Result<usize, String> {
+        let _drain = self.drain.lock().map_err(|e| e.to_string())?;
+        let batch: Vec<Trade> = self.queue.lock().map_err(|e| e.to_string())?.pending.drain(..).collect();
+        let store = self.store();
+        let mut written = 0;
+        let mut failure = None;
+        for trade in &batch {
+            if let Err(e) = store.append_trade(trade) {
+                failure = Some(e);
+                break;
+            }
+            written += 1;
+        }
+
+        let mut queue = self.queue.lock().map_err(|e| e.to_string())?;
+        queue.written += written as u64;
+        match failure {
+            Some(e) => {
+                for trade in batch.into_iter().skip(written).rev() {
+                    queue.pending.push_front(trade);
+                }
+                queue.last_error = Some(e.clone());
+                Err(e)
+            }
+            None => Ok(written),
+        }
+    }
+
+    /// Write to `store` from now on, after flushing what is queued for the old one
+    pub fn replace_store(&self, store: Arc<dyn JournalStore>) -> Result<(), String> {
+        self.flush()?;
+        *self.store.write().map_err(|e| e.to_string())? = store;
+        Ok(())
+    }
+
+    pub fn status(&self) -> JournalWriterStatus {
+        let running = self.worker.lock().is_ok_and(|w| w.is_some());
+        match self.queue.lock() {
+            Ok(queue) => JournalWriterStatus {
+                running,
+                pending: queue.pending.len(),
+                written: queue.written,
+                last_error: queue.last_error.clone(),
+            },
+            Err(e) => JournalWriterStatus { running, pending: 0, written: 0, last_error: Some(e.to_string()) },
+        }
+    }
+
+    fn store(&self) -> Arc<dyn JournalStore> {
+        self.store.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+impl JournalStore for JournalWriter {
+    fn backend(&self) -> StorageBackendKind {
+        self.store().backend()
+    }
+
+    fn append_trade(&self, trade: &Trade) -> Result<(), String> {
+        let running = {
+            let mut queue = self.queue.lock().map_err(|e| e.to_string())?;
+            queue.pending.push_back(trade.clone());
+            queue.running
+        };
+        if running {
+            self.wake.notify_one();
+            Ok(())
+        } else {
+            self.flush().map(|_| ())
+        }
+    }
+
+    fn load_trades(&self) -> Result<Vec<Trade>, String> {
+        self.flush()?;
+        self.store().load_trades()
+    }
+
+    fn query_trades(&self, query: &TradeQuery) -> Result<Vec<Trade>, String> {
+        self.flush()?;
+        self.store().query_trades(query)
+    }
+
+    fn stream_trades(&self, query: &TradeQuery, visit: &mut dyn FnMut(Trade) -> Result<bool, String>) -> Result<(), String> {
+        self.flush()?;
+        self.store().stream_trades(query, visit)
+    }
+
+    fn append_daily_summary(&self, summary: &DailySummary) -> Result<(), String> {
+        self.store().append_daily_summary(summary)
+    }
+
+    fn daily_summaries(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Vec<DailySummary>, String> {
+        self.store().daily_summaries(from, to)
+    }
+
+    fn save_bars(&self, symbol: &str, interval: &str, bars: &[Candle]) -> Result<(), String> {
+        self.store().save_bars(symbol, interval, bars)
+    }
+
+    fn query_bars(&self, symbol: &str, interval: &str, from: i64, to: i64) -> Result<Vec<Candle>, String> {
+        self.store().query_bars(symbol, interval, from, to)
+    }
+
+    fn journal_stats(&self) -> Result<JournalStats, String> {
+        self.flush()?;
+        self.store().journal_stats()
+    }
+
+    fn backup(&self, suffix: &str) -> Result<PathBuf, String> {
+        self.flush()?;
+        self.store().backup(suffix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::types::{InstrumentType, OrderSide};
+    use std::time::Duration;
+
+    // Slow in-memory journal, so appends pile up behind the worker
+    #[derive(Debug, Default)]
+    struct SlowStore {
+        trades: Mutex<Vec<Trade>>,
+    }
+
+    impl JournalStore for SlowStore {
+        fn backend(&self) -> StorageBackendKind {
+            StorageBackendKind::File
+        }
+        fn append_trade(&self, trade: &Trade) -> Result<(), String> {
+            std::thread::sleep(Duration::from_micros(200));
+            self.trades.lock().unwrap().push(trade.clone());
+            Ok(())
+        }
+        fn load_trades(&self) -> Result<Vec<Trade>, String> {
+            Ok(self.trades.lock().unwrap().clone())
+        }
+        fn append_daily_summary(&self, _summary: &DailySummary) -> Result<(), String> {
+            Ok(())
+        }
+        fn daily_summaries(&self, _from: Option<NaiveDate>, _to: Option<NaiveDate>) -> Result<Vec<DailySummary>, String> {
+            Ok(Vec::new())
+        }
+        fn save_bars(&self, _symbol: &str, _interval: &str, _bars: &[Candle]) -> Result<(), String> {
+            Ok(())
+        }
+        fn query_bars(&self, _symbol: &str, _interval: &str, _from: i64, _to: i64) -> Result<Vec<Candle>, String> {
+            Ok(Vec::new())
+        }
+        fn journal_stats(&self) -> Result<JournalStats, String> {
+            Err("not recorded".to_string())
+        }
+        fn backup(&self, _suffix: &str) -> Result<PathBuf, String> {
+            Err("not recorded".to_string())
+        }
+    }
+
+    fn trade(writer: usize, i: usize) -> Trade {
+        Trade {
+            id: format!("{}-{}", writer, i),
+            symbol: "ABC".to_string(),
+            side: OrderSide::Buy,
+            quantity: 1,
+            price: 10.0,
+            timestamp: 1_700_000_000 + i as i64,
+            order_id: format!("order-{}-{}", writer, i),
+            commission: 0.0,
+            net_amount: 10.0,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            leg_number: None,
+            assignment_id: None,
+            tag: None,
+            evaluation_id: None,
+        }
+    }
+
+    #[test]
+    fn test_restarting_the_worker_under_load_keeps_every_entry() {
+        let store = Arc::new(SlowStore::default());
+        let writer = JournalWriter::new(store.clone());
+        writer.start().unwrap();
+
+        let appenders: Vec<_> = (0..4)
+            .map(|w| {
+                let writer = writer.clone();
+                std::thread::spawn(move || {
+                    for i in 0..250 {
+                        writer.append_trade(&trade(w, i)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for _ in 0..10 {
+            writer.stop().unwrap();
+            assert!(!writer.status().running);
+            writer.start().unwrap();
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        for appender in appenders {
+            appender.join().unwrap();
+        }
+        writer.stop().unwrap();
+
+        let status = writer.status();
+        assert_eq!((status.pending, status.written, status.last_error), (0, 1000, None));
+        let trades = writer.load_trades().unwrap();
+        assert_eq!(trades.len(), 1000);
+        // Each appender's trades land in the order it queued them
+        for w in 0..4 {
+            let ids: Vec<&str> = trades.iter().filter(|t| t.order_id.starts_with(&format!("order-{}-", w))).map(|t| t.id.as_str()).collect();
+            let expected: Vec<String> = (0..250).map(|i| format!("{}-{}", w, i)).collect();
+            assert_eq!(ids, expected);
+        }
+    }
+}
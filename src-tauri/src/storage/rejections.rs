@@ -0,0 +1,78 @@
+// src-tauri/src/storage/rejections.rs
+// Order rejections, appended to rejections/{YYYY}-{MM}.jsonl in the cache dir by
+// session month, one record per line. Risk overrides go to overrides-{YYYY}-{MM}.jsonl
+// alongside them.
+
+use crate::engine::rejections::{OrderRejection, RiskOverride};
+use chrono::{Datelike, NaiveDate};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+pub const REJECTIONS_DIR: &str = "rejections";
+const OVERRIDES_PREFIX: &str = "overrides-";
+
+// Appends from concurrent commands must not interleave
+static REJECTIONS_LOCK: Mutex<()> = Mutex::new(());
+
+pub struct RejectionStore {
+    root: PathBuf,
+}
+
+impl RejectionStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn month_path(&self, prefix: &str, year: i32, month: u32) -> PathBuf {
+        self.root.join(format!("{}{:04}-{:02}.jsonl", prefix, year, month))
+    }
+
+    fn append_line<T: Serialize>(&self, prefix: &str, date: NaiveDate, record: &T) -> Result<(), String> {
+        let _guard = REJECTIONS_LOCK.lock().map_err(|e| e.to_string())?;
+        fs::create_dir_all(&self.root).map_err(|e| e.to_string())?;
+        let line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+        let path = self.month_path(prefix, date.year(), date.month());
+        let mut file = OpenOptions::new().create(true).append(true).open(&path).map_err(|e| e.to_string())?;
+        writeln!(file, "{}", line).map_err(|e| e.to_string())
+    }
+
+    // Lines that do not parse (e.g. cut short by a crash) are skipped
+    fn load_lines<T: DeserializeOwned>(&self, prefix: &str, from: NaiveDate, to: NaiveDate) -> Result<Vec<T>, String> {
+        let _guard = REJECTIONS_LOCK.lock().map_err(|e| e.to_string())?;
+        let mut records = Vec::new();
+        let (mut year, mut month) = (from.year(), from.month());
+        while (year, month) <= (to.year(), to.month()) {
+            let path = self.month_path(prefix, year, month);
+            if path.exists() {
+                let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+                records.extend(text.lines().filter_map(|line| serde_json::from_str::<T>(line).ok()));
+            }
+            (year, month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+        }
+        Ok(records)
+    }
+
+    pub fn append(&self, rejection: &OrderRejection) -> Result<(), String> {
+        self.append_line("", rejection.session_date, rejection)
+    }
+
+    /// Stored rejections from sessions `from` through `to`, in the order they were logged
+    pub fn load(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<OrderRejection>, String> {
+        let rejections: Vec<OrderRejection> = self.load_lines("", from, to)?;
+        Ok(rejections.into_iter().filter(|r| r.session_date >= from && r.session_date <= to).collect())
+    }
+
+    pub fn append_override(&self, risk_override: &RiskOverride) -> Result<(), String> {
+        self.append_line(OVERRIDES_PREFIX, risk_override.session_date, risk_override)
+    }
+
+    /// Stored risk overrides from sessions `from` through `to`, in the order they were logged
+    pub fn load_overrides(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<RiskOverride>, String> {
+        let overrides: Vec<RiskOverride> = self.load_lines(OVERRIDES_PREFIX, from, to)?;
+        Ok(overrides.into_iter().filter(|o| o.session_date >= from && o.session_date <= to).collect())
+    }
+}
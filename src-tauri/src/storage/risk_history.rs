@@ -0,0 +1,72 @@
+// src-tauri/src/storage/risk_history.rs
+// Daily risk snapshots, stored as risk_history/{YYYY}-{MM}.json in the cache dir, each
+// holding that month's snapshots in date order
+
+use crate::engine::risk_history::RiskSnapshot;
+use chrono::{Datelike, NaiveDate};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+pub const RISK_HISTORY_DIR: &str = "risk_history";
+
+// Snapshots are written with a read-modify-write of the month's file
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+pub struct RiskHistoryStore {
+    root: PathBuf,
+}
+
+impl RiskHistoryStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn month_path(&self, year: i32, month: u32) -> PathBuf {
+        self.root.join(format!("{:04}-{:02}.json", year, month))
+    }
+
+    fn load_month(&self, year: i32, month: u32) -> Result<Vec<RiskSnapshot>, String> {
+        let path = self.month_path(year, month);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| format!("Risk history {:04}-{:02} is unreadable: {}", year, month, e))
+    }
+
+    /// Store snapshots, replacing any already stored for the same dates
+    pub fn record(&self, snapshots: &[RiskSnapshot]) -> Result<(), String> {
+        let _guard = HISTORY_LOCK.lock().map_err(|e| e.to_string())?;
+        let mut months: Vec<(i32, u32)> = snapshots.iter().map(|s| (s.date.year(), s.date.month())).collect();
+        months.sort();
+        months.dedup();
+        for (year, month) in months {
+            let mut stored = self.load_month(year, month)?;
+            for snapshot in snapshots.iter().filter(|s| (s.date.year(), s.date.month()) == (year, month)) {
+                stored.retain(|s| s.date != snapshot.date);
+                stored.push(snapshot.clone());
+            }
+            stored.sort_by_key(|s| s.date);
+
+            fs::create_dir_all(&self.root).map_err(|e| e.to_string())?;
+            let path = self.month_path(year, month);
+            let tmp = path.with_extension("json.tmp");
+            fs::write(&tmp, serde_json::to_string(&stored).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+            fs::rename(&tmp, &path).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Stored snapshots for `from` through `to`, oldest first
+    pub fn load(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<RiskSnapshot>, String> {
+        let _guard = HISTORY_LOCK.lock().map_err(|e| e.to_string())?;
+        let mut snapshots = Vec::new();
+        let (mut year, mut month) = (from.year(), from.month());
+        while (year, month) <= (to.year(), to.month()) {
+            snapshots.extend(self.load_month(year, month)?.into_iter().filter(|s| s.date >= from && s.date <= to));
+            (year, month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+        }
+        Ok(snapshots)
+    }
+}
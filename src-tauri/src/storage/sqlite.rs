@@ -0,0 +1,342 @@
+// src-tauri/src/storage/sqlite.rs
+// SQLite journal backend (the "sqlite" feature). Records are stored as the same JSON the
+// file backend writes, beside indexed columns for the filters commands push down. WAL
+// mode keeps a crash mid-write from corrupting earlier rows.
+
+use super::cache::JournalStats;
+use super::journal_store::{JournalStore, StorageBackendKind, TradeQuery};
+use crate::engine::types::{DailySummary, Trade};
+use crate::market_data::types::Candle;
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS trades (
+        id TEXT PRIMARY KEY,
+        symbol TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS trades_symbol_timestamp ON trades (symbol, timestamp);
+    CREATE INDEX IF NOT EXISTS trades_timestamp ON trades (timestamp);
+    CREATE TABLE IF NOT EXISTS daily_summaries (date TEXT PRIMARY KEY, data TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS bars (
+        symbol TEXT NOT NULL,
+        interval TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (symbol, interval, timestamp)
+    ) WITHOUT ROWID;
+";
+const MIGRATED_AT_KEY: &str = "migrated_at";
+
+#[derive(Debug)]
+pub struct SqliteStore {
+    path: PathBuf,
+    conn: Mutex<Connection>,
+}
+
+fn sql_err(e: rusqlite::Error) -> String {
+    format!("SQLite error: {}", e)
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| format!("Failed to serialize journal entry: {}", e))
+}
+
+fn from_json<T: for<'de> serde::Deserialize<'de>>(text: &str) -> Result<T, String> {
+    serde_json::from_str(text).map_err(|e| format!("Failed to parse journal entry: {}", e))
+}
+
+impl SqliteStore {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let conn = Connection::open(path).map_err(sql_err)?;
+        // journal_mode answers with the mode now in effect
+        let mode: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0)).map_err(sql_err)?;
+        if !mode.eq_ignore_ascii_case("wal") {
+            eprintln!("SQLite journal {:?} is in {} mode, not WAL", path, mode);
+        }
+        conn.execute_batch("PRAGMA synchronous = NORMAL;").map_err(sql_err)?;
+        conn.execute_batch(SCHEMA).map_err(sql_err)?;
+        Ok(Self { path: path.to_path_buf(), conn: Mutex::new(conn) })
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>, String> {
+        self.conn.lock().map_err(|_| "SQLite connection lock poisoned".to_string())
+    }
+
+    /// When the file journal was imported, if it has been
+    pub fn migrated_at(&self) -> Result<Option<i64>, String> {
+        let value: Option<String> = self
+            .lock()?
+            .query_row("SELECT value FROM meta WHERE key = ?1", [MIGRATED_AT_KEY], |row| row.get(0))
+            .optional()
+            .map_err(sql_err)?;
+        Ok(value.and_then(|v| v.parse().ok()))
+    }
+
+    pub fn mark_migrated(&self, timestamp: i64) -> Result<(), String> {
+        self.lock()?
+            .execute("INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)", params![MIGRATED_AT_KEY, timestamp.to_string()])
+            .map_err(sql_err)?;
+        Ok(())
+    }
+
+    /// Insert `trades` in one transaction, skipping ids already present
+    pub fn insert_trades(&self, trades: &[Trade]) -> Result<(), String> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction().map_err(sql_err)?;
+        {
+            let mut insert = tx
+                .prepare("INSERT OR IGNORE INTO trades (id, symbol, timestamp, data) VALUES (?1, ?2, ?3, ?4)")
+                .map_err(sql_err)?;
+            for trade in trades {
+                insert.execute(params![trade.id, trade.symbol, trade.timestamp, to_json(trade)?]).map_err(sql_err)?;
+            }
+        }
+        tx.commit().map_err(sql_err)
+    }
+
+    pub fn insert_daily_summaries(&self, summaries: &[DailySummary]) -> Result<(), String> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction().map_err(sql_err)?;
+        {
+            let mut insert =
+                tx.prepare("INSERT OR REPLACE INTO daily_summaries (date, data) VALUES (?1, ?2)").map_err(sql_err)?;
+            for summary in summaries {
+                insert.execute(params![summary.date.to_string(), to_json(summary)?]).map_err(sql_err)?;
+            }
+        }
+        tx.commit().map_err(sql_err)
+    }
+
+    /// Rows in (trades, daily_summaries, bars)
+    pub fn row_counts(&self) -> Result<(usize, usize, usize), String> {
+        let conn = self.lock()?;
+        let count = |table: &str| -> Result<usize, String> {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get::<_, i64>(0))
+                .map(|n| n as usize)
+                .map_err(sql_err)
+        };
+        Ok((count("trades")?, count("daily_summaries")?, count("bars")?))
+    }
+
+    fn select_json<T: for<'de> serde::Deserialize<'de>>(
+        &self,
+        sql: &str,
+        params: &[&dyn rusqlite::ToSql],
+    ) -> Result<Vec<T>, String> {
+        let conn = self.lock()?;
+        let mut statement = conn.prepare(sql).map_err(sql_err)?;
+        let rows = statement.query_map(params, |row| row.get::<_, String>(0)).map_err(sql_err)?;
+        rows.map(|row| row.map_err(sql_err).and_then(|text| from_json(&text))).collect()
+    }
+}
+
+impl JournalStore for SqliteStore {
+    fn backend(&self) -> StorageBackendKind {
+        StorageBackendKind::Sqlite
+    }
+
+    fn append_trade(&self, trade: &Trade) -> Result<(), String> {
+        self.insert_trades(std::slice::from_ref(trade))
+    }
+
+    fn load_trades(&self) -> Result<Vec<Trade>, String> {
+        self.select_json("SELECT data FROM trades ORDER BY timestamp, id", &[])
+    }
+
+    fn query_trades(&self, query: &TradeQuery) -> Result<Vec<Trade>, String> {
+        let (from_ts, to_ts) = query.timestamp_bounds();
+        let limit = query.limit.map(|n| n as i64).unwrap_or(-1); // Negative is unlimited
+        // Only the filters given, so the planner can use the (symbol, timestamp) index
+        let mut conditions = Vec::new();
+        let mut params: Vec<&dyn rusqlite::ToSql> = Vec::new();
+        if let Some(symbol) = &query.symbol {
+            conditions.push("symbol = ?");
+            params.push(symbol);
+        }
+        if let Some(from_ts) = &from_ts {
+            conditions.push("timestamp >= ?");
+            params.push(from_ts);
+        }
+        if let Some(to_ts) = &to_ts {
+            conditions.push("timestamp < ?");
+            params.push(to_ts);
+        }
+        params.push(&limit);
+        let filter = if conditions.is_empty() { String::new() } else { format!("WHERE {}", conditions.join(" AND ")) };
+        // Newest first so LIMIT keeps the most recent, then back to oldest first
+        let sql = format!("SELECT data FROM trades {} ORDER BY timestamp DESC, id DESC LIMIT ?", filter);
+        let mut trades: Vec<Trade> = self.select_json(&sql, &params)?;
+        trades.reverse();
+        Ok(trades)
+    }
+
+    fn append_daily_summary(&self, summary: &DailySummary) -> Result<(), String> {
+        self.insert_daily_summaries(std::slice::from_ref(summary))
+    }
+
+    fn daily_summaries(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Vec<DailySummary>, String> {
+        // ISO dates sort as text
+        let from = from.map(|d| d.to_string()).unwrap_or_default();
+        let to = to.map(|d| d.to_string()).unwrap_or_else(|| "9999".to_string());
+        self.select_json("SELECT data FROM daily_summaries WHERE date BETWEEN ?1 AND ?2 ORDER BY date", &[&from, &to])
+    }
+
+    fn save_bars(&self, symbol: &str, interval: &str, bars: &[Candle]) -> Result<(), String> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction().map_err(sql_err)?;
+        {
+            let mut insert = tx
+                .prepare("INSERT OR REPLACE INTO bars (symbol, interval, timestamp, data) VALUES (?1, ?2, ?3, ?4)")
+                .map_err(sql_err)?;
+            for bar in bars {
+                insert.execute(params![symbol, interval, bar.timestamp, to_json(bar)?]).map_err(sql_err)?;
+            }
+        }
+        tx.commit().map_err(sql_err)
+    }
+
+    fn query_bars(&self, symbol: &str, interval: &str, from: i64, to: i64) -> Result<Vec<Candle>, String> {
+        self.select_json(
+            "SELECT data FROM bars WHERE symbol = ?1 AND interval = ?2 AND timestamp BETWEEN ?3 AND ?4 ORDER BY timestamp",
+            &[&symbol, &interval, &from, &to],
+        )
+    }
+
+    fn journal_stats(&self) -> Result<JournalStats, String> {
+        let (total_entries, _, _) = self.row_counts()?;
+        let metadata = std::fs::metadata(&self.path).map_err(|e| format!("Failed to get journal metadata: {}", e))?;
+        let wal_bytes = std::fs::metadata(self.path.with_extension("sqlite3-wal")).map(|m| m.len()).unwrap_or(0);
+        let epoch = |t: std::io::Result<std::time::SystemTime>| {
+            t.ok().and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok()).map(|d| d.as_secs() as i64)
+        };
+        Ok(JournalStats {
+            total_entries,
+            file_size_bytes: metadata.len() + wal_bytes,
+            created_at: epoch(metadata.created()),
+            last_modified: epoch(metadata.modified()),
+        })
+    }
+
+    fn backup(&self, suffix: &str) -> Result<PathBuf, String> {
+        let stem = self.path.file_stem().and_then(|s| s.to_str()).unwrap_or("journal");
+        let backup = self.path.with_file_name(format!("{}_{}.sqlite3", stem, suffix));
+        if backup.exists() {
+            return Err(format!("Backup {:?} already exists", backup));
+        }
+        // A consistent copy even while the WAL holds unmerged pages
+        self.lock()?.execute("VACUUM INTO ?1", [backup.to_string_lossy()]).map_err(sql_err)?;
+        println!("Journal backed up to: {:?}", backup);
+        Ok(backup)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::types::{InstrumentType, OrderSide};
+    use std::time::Instant;
+
+    const SYMBOLS: [&str; 50] = [
+        "AAPL", "MSFT", "AMZN", "GOOG", "META", "NVDA", "TSLA", "BRK.B", "JPM", "V", "UNH", "XOM", "JNJ", "WMT", "MA",
+        "PG", "HD", "CVX", "MRK", "ABBV", "KO", "PEP", "AVGO", "COST", "LLY", "TMO", "MCD", "CSCO", "ACN", "ABT",
+        "DHR", "WFC", "NEE", "LIN", "TXN", "PM", "BMY", "RTX", "UPS", "ORCL", "AMD", "QCOM", "HON", "INTC", "IBM",
+        "SBUX", "CAT", "GS", "BA", "MMM",
+    ];
+
+    fn store() -> SqliteStore {
+        let dir = std::env::temp_dir().join(format!("sqlite-journal-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        SqliteStore::open(&dir.join("journal.sqlite3")).unwrap()
+    }
+
+    fn trade(i: usize) -> Trade {
+        Trade {
+            id: format!("trade-{:06}", i),
+            symbol: SYMBOLS[i % SYMBOLS.len()].to_string(),
+            side: if i.is_multiple_of(2) { OrderSide::Buy } else { OrderSide::Sell },
+            quantity: 10,
+            price: 100.0 + (i % 17) as f64,
+            timestamp: 1_600_000_000 + i as i64 * 900, // Every 15 minutes, about 2.8 years
+            order_id: format!("order-{}", i),
+            commission: 1.0,
+            net_amount: 1000.0,
+            instrument_type: InstrumentType::Stock,
+            option_details: None,
+            leg_number: None,
+            assignment_id: None,
+            tag: None,
+            evaluation_id: None,
+        }
+    }
+
+    #[test]
+    fn test_symbol_and_date_filter_over_100k_trades() {
+        let store = store();
+        let trades: Vec<Trade> = (0..100_000).map(trade).collect();
+        store.insert_trades(&trades).unwrap();
+        store.insert_trades(&trades[..10]).unwrap(); // Re-imports are ignored
+        assert_eq!(store.row_counts().unwrap().0, 100_000);
+
+        let query = TradeQuery {
+            symbol: Some("NVDA".to_string()),
+            from: NaiveDate::from_ymd_opt(2021, 3, 1),
+            to: NaiveDate::from_ymd_opt(2021, 3, 31),
+            limit: None,
+        };
+        let started = Instant::now();
+        let found = store.query_trades(&query).unwrap();
+        let elapsed = started.elapsed();
+        println!("SQLite symbol+date filter: {} of 100000 trades in {:?}", found.len(), elapsed);
+
+        let ids = |trades: &[Trade]| trades.iter().map(|t| t.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&found), ids(&query.apply(trades)));
+        assert!(!found.is_empty());
+        assert!(elapsed.as_millis() < 100, "indexed query took {:?}", elapsed);
+
+        let latest = store.query_trades(&TradeQuery { limit: Some(3), ..Default::default() }).unwrap();
+        assert_eq!(latest.iter().map(|t| t.id.as_str()).collect::<Vec<_>>(), vec!["trade-099997", "trade-099998", "trade-099999"]);
+    }
+
+    #[test]
+    fn test_summaries_bars_and_migration_marker() {
+        let store = store();
+        assert_eq!(store.migrated_at().unwrap(), None);
+        store.mark_migrated(1_700_000_000).unwrap();
+        assert_eq!(store.migrated_at().unwrap(), Some(1_700_000_000));
+
+        let summary = |day: u32, equity: f64| DailySummary {
+            date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+            starting_equity: equity,
+            ending_equity: equity,
+            cash: equity,
+            unrealized_pnl: 0.0,
+            marks: Default::default(),
+            open_auction_fills: 0,
+            close_auction_fills: 0,
+            realized_equity: None,
+            equity_range: None,
+            benchmark_day_pnl: None,
+        };
+        store.insert_daily_summaries(&[summary(3, 100.0), summary(2, 99.0), summary(4, 101.0)]).unwrap();
+        store.append_daily_summary(&summary(4, 102.0)).unwrap(); // Replaces the Jan 4 row
+        let middle = store.daily_summaries(NaiveDate::from_ymd_opt(2024, 1, 3), None).unwrap();
+        assert_eq!(middle.iter().map(|s| s.ending_equity).collect::<Vec<_>>(), vec![100.0, 102.0]);
+
+        let bars: Vec<Candle> = (0..10).map(|i| Candle::new(1_700_000_000 + i * 86400, 1.0, 2.0, 0.5, 1.5, 100)).collect();
+        store.save_bars("AAPL", "1d", &bars).unwrap();
+        store.save_bars("AAPL", "1d", &bars[5..]).unwrap();
+        let range = store.query_bars("AAPL", "1d", 1_700_000_000 + 2 * 86400, 1_700_000_000 + 4 * 86400).unwrap();
+        assert_eq!(range, bars[2..=4].to_vec());
+        assert!(store.query_bars("AAPL", "1h", 0, i64::MAX).unwrap().is_empty());
+        assert_eq!(store.row_counts().unwrap(), (0, 3, 10));
+
+        let backup = store.backup("test").unwrap();
+        assert_eq!(SqliteStore::open(&backup).unwrap().row_counts().unwrap(), (0, 3, 10));
+    }
+}
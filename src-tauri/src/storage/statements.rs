@@ -0,0 +1,100 @@
+// src-tauri/src/storage/statements.rs
+// Generated monthly statements, stored as statements/{YYYY}-{MM}.json
+
+use crate::engine::statements::Statement;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatementEntry {
+    pub year: i32,
+    pub month: u32,
+    pub bytes: u64,
+}
+
+pub struct StatementStore {
+    root: PathBuf,
+}
+
+impl StatementStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn open(app_handle: &AppHandle) -> Result<Self, String> {
+        let root = app_handle
+            .path()
+            .app_config_dir()
+            .map_err(|e| format!("Failed to get app config directory: {}", e))?
+            .join("statements");
+        Ok(Self::new(root))
+    }
+
+    fn statement_path(&self, year: i32, month: u32) -> PathBuf {
+        self.root.join(format!("{:04}-{:02}.json", year, month))
+    }
+
+    /// Write a statement, replacing any earlier one for the same month
+    pub fn save(&self, statement: &Statement) -> Result<(), String> {
+        fs::create_dir_all(&self.root).map_err(|e| e.to_string())?;
+        let path = self.statement_path(statement.year, statement.month);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(statement).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &path).map_err(|e| e.to_string())
+    }
+
+    pub fn load(&self, year: i32, month: u32) -> Result<Statement, String> {
+        let path = self.statement_path(year, month);
+        if !path.exists() {
+            return Err(format!("No statement for {:04}-{:02}; generate it first", year, month));
+        }
+        let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| format!("Statement {:04}-{:02} is unreadable: {}", year, month, e))
+    }
+
+    /// Stored statements, oldest month first
+    pub fn list(&self) -> Vec<StatementEntry> {
+        let Ok(files) = fs::read_dir(&self.root) else {
+            return Vec::new();
+        };
+
+        let mut entries: Vec<StatementEntry> = files
+            .flatten()
+            .filter_map(|file| {
+                let name = file.file_name().to_string_lossy().to_string();
+                let (year, month) = name.strip_suffix(".json")?.split_once('-')?;
+                Some(StatementEntry {
+                    year: year.parse().ok()?,
+                    month: month.parse().ok().filter(|m| (1..=12).contains(m))?,
+                    bytes: file.metadata().map(|m| m.len()).unwrap_or(0),
+                })
+            })
+            .collect();
+        entries.sort_by_key(|e| (e.year, e.month));
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::statements::build_statement;
+
+    #[test]
+    fn test_statement_store_roundtrip() {
+        let store = StatementStore::new(std::env::temp_dir().join(format!("statements-test-{}", uuid::Uuid::new_v4())));
+        assert!(store.list().is_empty());
+        assert!(store.load(2024, 2).is_err());
+
+        for (year, month) in [(2024, 2), (2023, 12)] {
+            store.save(&build_statement(year, month, &[], &[], &[], &[]).unwrap()).unwrap();
+        }
+        store.save(&build_statement(2024, 2, &[], &[], &[], &[]).unwrap()).unwrap();
+
+        let months: Vec<(i32, u32)> = store.list().iter().map(|e| (e.year, e.month)).collect();
+        assert_eq!(months, vec![(2023, 12), (2024, 2)]);
+        assert_eq!(store.load(2024, 2).unwrap(), build_statement(2024, 2, &[], &[], &[], &[]).unwrap());
+    }
+}
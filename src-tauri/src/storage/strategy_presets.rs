@@ -0,0 +1,244 @@
+// src-tauri/src/storage/strategy_presets.rs
+// Named strategy loop configurations, saved in the UI state store's "presets" namespace
+// beside the backtest preferences. Each preset records its schema version; older ones
+// are upgraded on read, and fields added to StrategyLoopConfig since fill from their
+// defaults. The built-in presets are generated here and cannot be overwritten.
+
+use super::ui_state::UiStateStore;
+use crate::engine::bars::Timeframe;
+use crate::engine::r#loop::{CombinationRule, MarketRegimeFilter, SignalConfig, StrategyLoopConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const PRESETS_NAMESPACE: &str = "presets";
+pub const PRESET_SCHEMA_VERSION: u32 = 1;
+
+pub const CONSERVATIVE_DRY_RUN: &str = "conservative-dry-run";
+pub const AGGRESSIVE_TREND_FOLLOWING: &str = "aggressive-trend-following";
+pub const MEAN_REVERSION: &str = "mean-reversion";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresetSource {
+    BuiltIn,
+    User,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StrategyPreset {
+    pub schema_version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub source: PresetSource,
+    pub created_at: i64,
+    pub config: StrategyLoopConfig,
+}
+
+impl StrategyPreset {
+    pub fn read_only(&self) -> bool {
+        self.source == PresetSource::BuiltIn
+    }
+}
+
+pub struct PresetStore {
+    store: UiStateStore,
+}
+
+impl PresetStore {
+    pub fn new(store: UiStateStore) -> Self {
+        Self { store }
+    }
+
+    /// Built-in presets followed by saved ones, by name
+    pub fn list(&self) -> Result<Vec<StrategyPreset>, String> {
+        let mut presets = built_in_presets();
+        for (name, value) in self.store.get_namespace(PRESETS_NAMESPACE)? {
+            match migrate_preset(&name, value) {
+                Ok(preset) => presets.push(preset),
+                Err(e) => eprintln!("Skipping strategy preset {}: {}", name, e),
+            }
+        }
+        presets.sort_by(|a, b| (!a.read_only(), &a.name).cmp(&(!b.read_only(), &b.name)));
+        Ok(presets)
+    }
+
+    pub fn get(&self, name: &str) -> Result<StrategyPreset, String> {
+        if let Some(preset) = built_in_presets().into_iter().find(|p| p.name == name) {
+            return Ok(preset);
+        }
+        let value = self
+            .store
+            .get(PRESETS_NAMESPACE, name)?
+            .ok_or_else(|| format!("No strategy preset named '{}'", name))?;
+        migrate_preset(name, value)
+    }
+
+    /// Save `config` as `name`, replacing an earlier preset of that name
+    pub fn save(&self, name: &str, description: &str, source: PresetSource, mut config: StrategyLoopConfig) -> Result<StrategyPreset, String> {
+        if is_built_in(name) {
+            return Err(format!("'{}' is a built-in preset; save under another name", name));
+        }
+        config.normalize_watchlist()?;
+        let preset = StrategyPreset {
+            schema_version: PRESET_SCHEMA_VERSION,
+            name: name.to_string(),
+            description: description.to_string(),
+            source,
+            created_at: chrono::Utc::now().timestamp(),
+            config,
+        };
+        let json = serde_json::to_string(&preset).map_err(|e| e.to_string())?;
+        let evicted = self.store.set(PRESETS_NAMESPACE, name, &json)?;
+        if !evicted.is_empty() {
+            eprintln!("Saving strategy preset {} evicted UI state: {}", name, evicted.join(", "));
+        }
+        Ok(preset)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        if is_built_in(name) {
+            return Err(format!("'{}' is a built-in preset and cannot be deleted", name));
+        }
+        if self.store.remove(PRESETS_NAMESPACE, name)? {
+            Ok(())
+        } else {
+            Err(format!("No strategy preset named '{}'", name))
+        }
+    }
+}
+
+fn is_built_in(name: &str) -> bool {
+    [CONSERVATIVE_DRY_RUN, AGGRESSIVE_TREND_FOLLOWING, MEAN_REVERSION].contains(&name)
+}
+
+/// A stored preset at the current schema version. Version 0 is a bare
+/// StrategyLoopConfig, as saved through set_ui_state before presets were versioned.
+pub fn migrate_preset(name: &str, value: serde_json::Value) -> Result<StrategyPreset, String> {
+    let version = value.get("schema_version").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    if version > PRESET_SCHEMA_VERSION {
+        return Err(format!(
+            "Preset schema {} is newer than this app supports ({})",
+            version, PRESET_SCHEMA_VERSION
+        ));
+    }
+    let parse_err = |e: serde_json::Error| format!("Preset {} is invalid: {}", name, e);
+    if version == 0 {
+        return Ok(StrategyPreset {
+            schema_version: PRESET_SCHEMA_VERSION,
+            name: name.to_string(),
+            description: String::new(),
+            source: PresetSource::User,
+            created_at: 0,
+            config: serde_json::from_value(value).map_err(parse_err)?,
+        });
+    }
+    let mut preset: StrategyPreset = serde_json::from_value(value).map_err(parse_err)?;
+    preset.name = name.to_string();
+    Ok(preset)
+}
+
+fn signal(name: &str, timeframe: Timeframe, lookback: usize, weight: f64) -> SignalConfig {
+    SignalConfig { name: name.to_string(), timeframe, lookback, weight }
+}
+
+fn built_in(name: &str, description: &str, config: StrategyLoopConfig) -> StrategyPreset {
+    StrategyPreset {
+        schema_version: PRESET_SCHEMA_VERSION,
+        name: name.to_string(),
+        description: description.to_string(),
+        source: PresetSource::BuiltIn,
+        created_at: 0,
+        config,
+    }
+}
+
+/// Starting points for new users. They carry no watchlist or pairs, and the
+/// conservative one only logs its decisions.
+pub fn built_in_presets() -> Vec<StrategyPreset> {
+    let base = StrategyLoopConfig::default();
+    vec![
+        built_in(
+            CONSERVATIVE_DRY_RUN,
+            "Every signal must agree, long cooldowns and a regime filter; decisions are logged, not placed",
+            StrategyLoopConfig {
+                dry_run: true,
+                cadence_minutes: 15,
+                max_concurrent_signals: 3,
+                cooldown_seconds: 3600,
+                default_rule: CombinationRule::AllMustAgree,
+                regime_filter: MarketRegimeFilter { enabled: true, require_trend: true, ..MarketRegimeFilter::default() },
+                ..base.clone()
+            },
+        ),
+        built_in(
+            AGGRESSIVE_TREND_FOLLOWING,
+            "Trend and crossover signals on short bars, a low vote threshold and short cooldowns",
+            StrategyLoopConfig {
+                dry_run: false,
+                cadence_minutes: 1,
+                max_concurrent_signals: 20,
+                cooldown_seconds: 60,
+                signals: vec![
+                    signal("SMA_Crossover", Timeframe::OneMinute, 20, 1.0),
+                    signal("MACD", Timeframe::FiveMinute, 35, 1.0),
+                    signal("Trend", Timeframe::OneDay, 20, 2.0),
+                    signal("Volume_Spike", Timeframe::FiveMinute, 20, 0.5),
+                ],
+                default_rule: CombinationRule::WeightedVote { threshold: 0.2 },
+                multi_timeframe_signals: HashMap::new(),
+                ..base.clone()
+            },
+        ),
+        built_in(
+            MEAN_REVERSION,
+            "RSI extremes on hourly and five-minute bars, in ranging markets only",
+            StrategyLoopConfig {
+                dry_run: false,
+                cadence_minutes: 5,
+                max_concurrent_signals: 10,
+                cooldown_seconds: 900,
+                signals: vec![signal("RSI", Timeframe::FiveMinute, 15, 1.0), signal("RSI", Timeframe::OneHour, 15, 1.5)],
+                default_rule: CombinationRule::WeightedVote { threshold: 0.5 },
+                multi_timeframe_signals: HashMap::new(),
+                regime_filter: MarketRegimeFilter { enabled: true, max_adr_pct: 4.0, ..MarketRegimeFilter::default() },
+                ..base
+            },
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_round_trip_and_migrate() {
+        let path = std::env::temp_dir().join(format!("presets-test-{}.json", uuid::Uuid::new_v4()));
+        let store = UiStateStore::new(path.clone());
+        let presets = PresetStore::new(store.clone());
+
+        let config = StrategyLoopConfig { cooldown_seconds: 42, ..StrategyLoopConfig::default() };
+        presets.save("tuned", "", PresetSource::User, config).unwrap();
+        assert_eq!(presets.get("tuned").unwrap().config.cooldown_seconds, 42);
+        assert!(presets.save(MEAN_REVERSION, "", PresetSource::User, StrategyLoopConfig::default()).is_err());
+        assert!(presets.delete(CONSERVATIVE_DRY_RUN).is_err());
+
+        // A bare config from before presets were versioned, missing newer fields
+        let mut legacy = serde_json::to_value(StrategyLoopConfig::default()).unwrap();
+        legacy.as_object_mut().unwrap().remove("watchdog");
+        store.set(PRESETS_NAMESPACE, "legacy", &legacy.to_string()).unwrap();
+        let migrated = presets.get("legacy").unwrap();
+        assert_eq!((migrated.schema_version, migrated.source), (PRESET_SCHEMA_VERSION, PresetSource::User));
+
+        let mut newer = serde_json::to_value(presets.get("tuned").unwrap()).unwrap();
+        newer["schema_version"] = serde_json::json!(PRESET_SCHEMA_VERSION + 1);
+        assert!(migrate_preset("newer", newer).unwrap_err().contains("newer"));
+
+        let names: Vec<String> = presets.list().unwrap().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec![AGGRESSIVE_TREND_FOLLOWING, CONSERVATIVE_DRY_RUN, MEAN_REVERSION, "legacy", "tuned"]);
+        presets.delete("tuned").unwrap();
+        assert!(presets.get("tuned").is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
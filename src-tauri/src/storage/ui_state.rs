@@ -0,0 +1,240 @@
+// src-tauri/src/storage/ui_state.rs
+// Namespaced key-value store for frontend state (layouts, column configs, last-viewed
+// symbols). Kept in its own file beside config.json rather than in the data cache, so
+// clearing cached market data leaves layouts alone.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const MAX_VALUE_BYTES: usize = 64 * 1024;
+pub const DEFAULT_BUDGET_BYTES: usize = 1024 * 1024;
+const MAX_NAME_LEN: usize = 64;
+
+// Commands can run concurrently; read-modify-write of the file is serialized
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UiStateEntry {
+    pub value: serde_json::Value,
+    pub bytes: usize, // Serialized size, counted against the budget
+    pub updated_at: i64,
+    pub seq: u64,     // Write order; the lowest is evicted first
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct UiStateFile {
+    #[serde(default)]
+    next_seq: u64,
+    #[serde(default)]
+    namespaces: BTreeMap<String, BTreeMap<String, UiStateEntry>>,
+}
+
+impl UiStateFile {
+    fn total_bytes(&self) -> usize {
+        self.namespaces.values().flat_map(|keys| keys.values()).map(|e| e.bytes).sum()
+    }
+
+    /// Remove the least recently written entry other than `keep`
+    fn evict_oldest(&mut self, keep: (&str, &str)) -> Option<String> {
+        let (namespace, key) = self
+            .namespaces
+            .iter()
+            .flat_map(|(ns, keys)| keys.iter().map(move |(k, e)| (ns, k, e.seq)))
+            .filter(|(ns, k, _)| (ns.as_str(), k.as_str()) != keep)
+            .min_by_key(|(_, _, seq)| *seq)
+            .map(|(ns, k, _)| (ns.clone(), k.clone()))?;
+        if let Some(keys) = self.namespaces.get_mut(&namespace) {
+            keys.remove(&key);
+            if keys.is_empty() {
+                self.namespaces.remove(&namespace);
+            }
+        }
+        Some(format!("{}/{}", namespace, key))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UiStateStore {
+    path: PathBuf,
+    budget_bytes: usize,
+}
+
+impl UiStateStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, budget_bytes: DEFAULT_BUDGET_BYTES }
+    }
+
+    #[cfg(test)]
+    pub fn with_budget(mut self, budget_bytes: usize) -> Self {
+        self.budget_bytes = budget_bytes;
+        self
+    }
+
+    /// Store `value_json` under `namespace`/`key`, evicting the oldest entries if the
+    /// store goes over budget. Returns the evicted "namespace/key" names.
+    pub fn set(&self, namespace: &str, key: &str, value_json: &str) -> Result<Vec<String>, String> {
+        validate_name("Namespace", namespace)?;
+        validate_name("Key", key)?;
+        let value: serde_json::Value = serde_json::from_str(value_json)
+            .map_err(|e| format!("Value for {}/{} is not valid JSON: {}", namespace, key, e))?;
+        let bytes = serde_json::to_string(&value).map_err(|e| e.to_string())?.len();
+        if bytes > MAX_VALUE_BYTES.min(self.budget_bytes) {
+            return Err(format!(
+                "Value for {}/{} is {} bytes; the limit is {}",
+                namespace,
+                key,
+                bytes,
+                MAX_VALUE_BYTES.min(self.budget_bytes)
+            ));
+        }
+
+        let _lock = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = self.load()?;
+        let seq = file.next_seq;
+        file.next_seq += 1;
+        file.namespaces.entry(namespace.to_string()).or_default().insert(
+            key.to_string(),
+            UiStateEntry { value, bytes, updated_at: chrono::Utc::now().timestamp(), seq },
+        );
+
+        let mut evicted = Vec::new();
+        while file.total_bytes() > self.budget_bytes {
+            match file.evict_oldest((namespace, key)) {
+                Some(name) => evicted.push(name),
+                None => break,
+            }
+        }
+        self.save(&file)?;
+        Ok(evicted)
+    }
+
+    pub fn get(&self, namespace: &str, key: &str) -> Result<Option<serde_json::Value>, String> {
+        let _lock = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(self.load()?.namespaces.get(namespace).and_then(|keys| keys.get(key)).map(|e| e.value.clone()))
+    }
+
+    pub fn get_namespace(&self, namespace: &str) -> Result<BTreeMap<String, serde_json::Value>, String> {
+        let _lock = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(self
+            .load()?
+            .namespaces
+            .remove(namespace)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(k, e)| (k, e.value))
+            .collect())
+    }
+
+    /// Remove `namespace`/`key`; returns whether it was there
+    pub fn remove(&self, namespace: &str, key: &str) -> Result<bool, String> {
+        let _lock = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = self.load()?;
+        let Some(keys) = file.namespaces.get_mut(namespace) else {
+            return Ok(false);
+        };
+        if keys.remove(key).is_none() {
+            return Ok(false);
+        }
+        if keys.is_empty() {
+            file.namespaces.remove(namespace);
+        }
+        self.save(&file)?;
+        Ok(true)
+    }
+
+    /// Remove every key in `namespace`; returns how many were removed
+    pub fn clear_namespace(&self, namespace: &str) -> Result<usize, String> {
+        let _lock = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = self.load()?;
+        let removed = file.namespaces.remove(namespace).map(|keys| keys.len()).unwrap_or(0);
+        if removed > 0 {
+            self.save(&file)?;
+        }
+        Ok(removed)
+    }
+
+    fn load(&self) -> Result<UiStateFile, String> {
+        if !self.path.exists() {
+            return Ok(UiStateFile::default());
+        }
+        let text = fs::read_to_string(&self.path).map_err(|e| format!("Failed to read UI state: {}", e))?;
+        serde_json::from_str(&text).map_err(|e| format!("UI state file is corrupt: {}", e))
+    }
+
+    /// Write to a temporary file and rename over the old one so a crash never leaves
+    /// a half-written store
+    fn save(&self, file: &UiStateFile) -> Result<(), String> {
+        write_atomic(&self.path, &serde_json::to_string(file).map_err(|e| e.to_string())?)
+    }
+}
+
+fn validate_name(kind: &str, name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("{} '{}' must be 1-{} letters, digits, '_', '-' or '.'", kind, name, MAX_NAME_LEN))
+    }
+}
+
+fn write_atomic(path: &Path, contents: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, contents).map_err(|e| format!("Failed to write UI state: {}", e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to write UI state: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store() -> UiStateStore {
+        UiStateStore::new(std::env::temp_dir().join(format!("ui-state-test-{}.json", uuid::Uuid::new_v4())))
+    }
+
+    #[test]
+    fn test_set_get_and_clear_namespace() {
+        let store = temp_store();
+        store.set("chart", "timeframe", r#""1D""#).unwrap();
+        store.set("chart", "layout", r#"{"panels":[{"id":"price","height":0.7}]}"#).unwrap();
+        store.set("watchlist", "last_viewed", r#"["AAPL","MSFT"]"#).unwrap();
+
+        assert_eq!(store.get("chart", "timeframe").unwrap(), Some(serde_json::json!("1D")));
+        assert_eq!(store.get("chart", "missing").unwrap(), None);
+        assert_eq!(store.get_namespace("chart").unwrap().len(), 2);
+
+        assert!(store.set("chart", "timeframe", "1D").unwrap_err().contains("not valid JSON"));
+        assert!(store.set("chart/../", "x", "1").is_err());
+        let too_big = format!("\"{}\"", "x".repeat(MAX_VALUE_BYTES));
+        assert!(store.set("chart", "big", &too_big).unwrap_err().contains("limit"));
+
+        assert_eq!(store.clear_namespace("chart").unwrap(), 2);
+        assert!(store.get_namespace("chart").unwrap().is_empty());
+        assert_eq!(store.get("watchlist", "last_viewed").unwrap(), Some(serde_json::json!(["AAPL", "MSFT"])));
+        let _ = fs::remove_file(&store.path);
+    }
+
+    #[test]
+    fn test_budget_evicts_oldest_writes() {
+        let store = temp_store().with_budget(40);
+        let value = format!("\"{}\"", "x".repeat(10)); // 12 bytes
+        store.set("a", "first", &value).unwrap();
+        store.set("b", "second", &value).unwrap();
+        store.set("a", "third", &value).unwrap();
+        // Rewriting refreshes an entry's age
+        store.set("a", "first", &value).unwrap();
+
+        assert_eq!(store.set("c", "fourth", &value).unwrap(), vec!["b/second".to_string()]);
+        assert!(store.get_namespace("b").unwrap().is_empty());
+        assert_eq!(store.get_namespace("a").unwrap().len(), 2);
+        assert!(store.get("c", "fourth").unwrap().is_some());
+        let _ = fs::remove_file(&store.path);
+    }
+}